@@ -0,0 +1,306 @@
+//! BitTorrent traffic detection
+//!
+//! BitTorrent peers and its DHT don't use fixed ports, so the payloads left unclassified by the other
+//! dissectors are recognized by their content:
+//! - peer wire handshake (BEP 3): the `BitTorrent protocol` string, prefixed by its length, followed by
+//!   the reserved bytes, the info-hash of the torrent and the ID of the peer
+//! - DHT (BEP 5): KRPC messages, a bencoded dictionary with the transaction ID (`t`) and the message
+//!   type (`y`): a query (`q`, with the method name and its arguments `a`), a response (`r`) or an error
+//!   (`e`). The `get_peers` and `announce_peer` queries carry the info-hash of the torrent looked up
+//!
+//! Only the fields telling the kind of traffic are kept: the pieces exchanged and the nodes or peers of
+//! the DHT responses are not decoded.
+
+use std::net::IpAddr;
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableBitTorrentPacket, ParsedPacket, SerializablePacket,
+};
+
+const HANDSHAKE_PROTOCOL: &[u8] = b"BitTorrent protocol";
+/// Protocol string length and name, reserved bytes
+const HANDSHAKE_INFO_HASH_OFFSET: usize = 1 + 19 + 8;
+const HASH_LENGTH: usize = 20;
+
+/// Nesting allowed in a bencoded value, DHT messages use at most a few levels
+const MAX_BENCODE_DEPTH: usize = 16;
+
+/// Bencoded value, borrowing the strings from the payload
+#[derive(Debug, PartialEq)]
+enum Bencode<'a> {
+    Integer(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Bencode<'a>>),
+    /// Entries in order of appearance
+    Dictionary(Vec<(&'a [u8], Bencode<'a>)>),
+}
+
+impl<'a> Bencode<'a> {
+    fn get(&self, key: &[u8]) -> Option<&Bencode<'a>> {
+        match self {
+            Bencode::Dictionary(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| *entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Bencode::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+/// Build a BitTorrent packet from a transport-layer packet, save it in a Parsed Packet, leaving the
+/// packets not recognized untouched
+pub fn handle_bittorrent_packet(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    let bittorrent_packet = match parse_handshake(packet).or_else(|| parse_dht_message(packet)) {
+        Some(bittorrent_packet) => bittorrent_packet,
+        None => return,
+    };
+
+    debug!(
+        "BitTorrent Packet: {}:{} > {}:{}; Message: {}, Info-hash: {:?}",
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        bittorrent_packet.message_type,
+        bittorrent_packet.info_hash,
+    );
+
+    parsed_packet.set_application_layer_packet(Some(SerializablePacket::BitTorrentPacket(
+        bittorrent_packet,
+    )));
+}
+
+fn parse_handshake(packet: &[u8]) -> Option<SerializableBitTorrentPacket> {
+    if packet.first() != Some(&(HANDSHAKE_PROTOCOL.len() as u8))
+        || packet.get(1..1 + HANDSHAKE_PROTOCOL.len()) != Some(HANDSHAKE_PROTOCOL)
+    {
+        return None;
+    }
+
+    // The handshake can be split across segments, or captured partially
+    let info_hash_end = HANDSHAKE_INFO_HASH_OFFSET + HASH_LENGTH;
+    Some(SerializableBitTorrentPacket {
+        message_type: "Handshake".to_owned(),
+        info_hash: packet
+            .get(HANDSHAKE_INFO_HASH_OFFSET..info_hash_end)
+            .map(to_hex),
+        peer_id: packet
+            .get(info_hash_end..info_hash_end + HASH_LENGTH)
+            .map(to_hex),
+        ..Default::default()
+    })
+}
+
+fn parse_dht_message(packet: &[u8]) -> Option<SerializableBitTorrentPacket> {
+    // A KRPC message is a single dictionary, filling the datagram
+    if packet.first() != Some(&b'd') {
+        return None;
+    }
+    let (message, rest) = parse_bencode(packet, 0)?;
+    if !rest.is_empty() {
+        return None;
+    }
+
+    let transaction_id = message.get(b"t")?.as_bytes()?;
+    let mut bittorrent_packet = SerializableBitTorrentPacket {
+        transaction_id: Some(to_hex(transaction_id)),
+        ..Default::default()
+    };
+
+    match message.get(b"y")?.as_bytes()? {
+        b"q" => {
+            bittorrent_packet.message_type = "DHT Query".to_owned();
+            bittorrent_packet.query =
+                Some(String::from_utf8_lossy(message.get(b"q")?.as_bytes()?).to_string());
+            bittorrent_packet.info_hash = message
+                .get(b"a")
+                .and_then(|arguments| arguments.get(b"info_hash"))
+                .and_then(Bencode::as_bytes)
+                .filter(|info_hash| info_hash.len() == HASH_LENGTH)
+                .map(to_hex);
+        }
+        b"r" => bittorrent_packet.message_type = "DHT Response".to_owned(),
+        b"e" => {
+            bittorrent_packet.message_type = "DHT Error".to_owned();
+            // List of the error code and message
+            if let Some(Bencode::List(error)) = message.get(b"e") {
+                bittorrent_packet.error_code = match error.first() {
+                    Some(Bencode::Integer(code)) => Some(*code),
+                    _ => None,
+                };
+            }
+        }
+        _ => return None,
+    }
+
+    Some(bittorrent_packet)
+}
+
+/// Parse the bencoded value at the start of the data, returning it with the data following it
+fn parse_bencode(data: &[u8], depth: usize) -> Option<(Bencode<'_>, &[u8])> {
+    if depth > MAX_BENCODE_DEPTH {
+        return None;
+    }
+
+    match *data.first()? {
+        // i<decimal>e
+        b'i' => {
+            let end = data.iter().position(|byte| *byte == b'e')?;
+            let integer = std::str::from_utf8(&data[1..end]).ok()?.parse().ok()?;
+            Some((Bencode::Integer(integer), &data[end + 1..]))
+        }
+        // l<values>e
+        b'l' => {
+            let mut values = vec![];
+            let mut rest = &data[1..];
+            while *rest.first()? != b'e' {
+                let (value, next) = parse_bencode(rest, depth + 1)?;
+                values.push(value);
+                rest = next;
+            }
+            Some((Bencode::List(values), &rest[1..]))
+        }
+        // d<key><value>...e, with string keys
+        b'd' => {
+            let mut entries = vec![];
+            let mut rest = &data[1..];
+            while *rest.first()? != b'e' {
+                let (key, next) = parse_bencode(rest, depth + 1)?;
+                let (value, next) = parse_bencode(next, depth + 1)?;
+                entries.push((key.as_bytes()?, value));
+                rest = next;
+            }
+            Some((Bencode::Dictionary(entries), &rest[1..]))
+        }
+        // <length>:<bytes>
+        b'0'..=b'9' => {
+            let separator = data.iter().position(|byte| *byte == b':')?;
+            let length: usize = std::str::from_utf8(&data[..separator]).ok()?.parse().ok()?;
+            let start = separator + 1;
+            let bytes = data.get(start..start.checked_add(length)?)?;
+            Some((Bencode::Bytes(bytes), &data[start + length..]))
+        }
+        _ => None,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::{handle_bittorrent_packet, parse_bencode, Bencode};
+
+    fn parse(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_bittorrent_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            51413,
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            6881,
+            payload,
+            &mut parsed_packet,
+        );
+
+        parsed_packet
+    }
+
+    #[test]
+    fn bittorrent_handshake() {
+        let mut packet = vec![19];
+        packet.extend_from_slice(b"BitTorrent protocol");
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0x10, 0, 0x05]);
+        packet.extend_from_slice(&[0xab; 20]);
+        packet.extend_from_slice(b"-qB4250-0123456789ab");
+
+        match parse(&packet).get_application_layer_packet().unwrap() {
+            SerializablePacket::BitTorrentPacket(bittorrent_packet) => {
+                assert_eq!(bittorrent_packet.message_type, "Handshake");
+                assert_eq!(bittorrent_packet.info_hash, Some("ab".repeat(20)));
+                assert!(bittorrent_packet
+                    .peer_id
+                    .as_ref()
+                    .unwrap()
+                    .starts_with("2d7142"));
+            }
+            _ => unreachable!(),
+        }
+
+        // Captured partially
+        match parse(&packet[..40]).get_application_layer_packet().unwrap() {
+            SerializablePacket::BitTorrentPacket(bittorrent_packet) => {
+                assert_eq!(bittorrent_packet.info_hash, None);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bittorrent_dht_messages() {
+        let mut query = b"d1:ad2:id20:".to_vec();
+        query.extend_from_slice(&[0x11; 20]);
+        query.extend_from_slice(b"9:info_hash20:");
+        query.extend_from_slice(&[0xcd; 20]);
+        query.extend_from_slice(b"e1:q9:get_peers1:t2:aa1:y1:qe");
+
+        match parse(&query).get_application_layer_packet().unwrap() {
+            SerializablePacket::BitTorrentPacket(bittorrent_packet) => {
+                assert_eq!(bittorrent_packet.message_type, "DHT Query");
+                assert_eq!(bittorrent_packet.query.as_deref(), Some("get_peers"));
+                assert_eq!(bittorrent_packet.info_hash, Some("cd".repeat(20)));
+                assert_eq!(bittorrent_packet.transaction_id.as_deref(), Some("6161"));
+            }
+            _ => unreachable!(),
+        }
+
+        let error = b"d1:eli201e23:A Generic Error Ocurrede1:t2:aa1:y1:ee";
+        match parse(error).get_application_layer_packet().unwrap() {
+            SerializablePacket::BitTorrentPacket(bittorrent_packet) => {
+                assert_eq!(bittorrent_packet.message_type, "DHT Error");
+                assert_eq!(bittorrent_packet.error_code, Some(201));
+            }
+            _ => unreachable!(),
+        }
+
+        // Not KRPC: missing message type, trailing data, truncated string
+        assert!(parse(b"d1:t2:aae").get_application_layer_packet().is_none());
+        assert!(parse(b"d1:t2:aa1:y1:rexx")
+            .get_application_layer_packet()
+            .is_none());
+        assert!(parse(b"d1:t20:aa1:y1:re")
+            .get_application_layer_packet()
+            .is_none());
+
+        assert_eq!(
+            parse_bencode(b"l4:spami-3ee", 0),
+            Some((
+                Bencode::List(vec![Bencode::Bytes(b"spam"), Bencode::Integer(-3)]),
+                &b""[..]
+            ))
+        );
+        // Nesting beyond the limit
+        assert!(parse_bencode(&[b'l'; 64], 0).is_none());
+    }
+}
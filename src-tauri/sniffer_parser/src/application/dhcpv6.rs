@@ -0,0 +1,360 @@
+//! DHCPv6 Packet parsing
+
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv6Addr};
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::{Dhcpv6IaAddress, Dhcpv6IdentityAssociation, SerializableDhcpv6Packet},
+    ParsedPacket, SerializablePacket,
+};
+
+/// DHCPv6 message types (RFC 8415)
+#[allow(non_snake_case)]
+mod Dhcpv6MessageTypes {
+    pub const SOLICIT: u8 = 1;
+    pub const ADVERTISE: u8 = 2;
+    pub const REQUEST: u8 = 3;
+    pub const CONFIRM: u8 = 4;
+    pub const RENEW: u8 = 5;
+    pub const REBIND: u8 = 6;
+    pub const REPLY: u8 = 7;
+    pub const RELEASE: u8 = 8;
+    pub const DECLINE: u8 = 9;
+    pub const RECONFIGURE: u8 = 10;
+    pub const INFORMATION_REQUEST: u8 = 11;
+    pub const RELAY_FORW: u8 = 12;
+    pub const RELAY_REPL: u8 = 13;
+}
+
+/// DHCPv6 option codes
+#[allow(non_snake_case)]
+mod Dhcpv6Options {
+    pub const CLIENTID: u16 = 1;
+    pub const SERVERID: u16 = 2;
+    pub const IA_NA: u16 = 3;
+    pub const IAADDR: u16 = 5;
+    pub const RELAY_MSG: u16 = 9;
+    pub const STATUS_CODE: u16 = 13;
+    pub const DNS_SERVERS: u16 = 23;
+    pub const DOMAIN_LIST: u16 = 24;
+}
+
+/// Length of the message type and transaction ID of client/server messages
+const MESSAGE_HEADER_LENGTH: usize = 4;
+/// Length of the message type, hop count, link and peer addresses of relay messages
+const RELAY_HEADER_LENGTH: usize = 34;
+/// Length of the IAID, T1 and T2 fields of an IA_NA option
+const IA_NA_HEADER_LENGTH: usize = 12;
+/// Length of the address, preferred and valid lifetime fields of an IAADDR option
+const IAADDR_HEADER_LENGTH: usize = 24;
+
+/// Build a DHCPv6 packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_dhcpv6_packet(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    if let Some(dhcpv6_packet) = parse_dhcpv6_message(packet) {
+        debug!(
+            "DHCPv6 Packet: {}:{} > {}:{}; Type: {}, Transaction ID: {:?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            dhcpv6_packet.message_type_name,
+            dhcpv6_packet.transaction_id,
+        );
+
+        parsed_packet
+            .set_application_layer_packet(Some(SerializablePacket::Dhcpv6Packet(dhcpv6_packet)));
+    } else {
+        debug!("Malformed DHCPv6 Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed DHCPv6 Packet".to_string(),
+        )));
+    }
+}
+
+fn parse_dhcpv6_message(packet: &[u8]) -> Option<SerializableDhcpv6Packet> {
+    let message_type = *packet.first()?;
+    let mut dhcpv6_packet = SerializableDhcpv6Packet {
+        message_type,
+        message_type_name: get_message_type_name(message_type).to_owned(),
+        ..Default::default()
+    };
+
+    let options = match message_type {
+        Dhcpv6MessageTypes::RELAY_FORW | Dhcpv6MessageTypes::RELAY_REPL => {
+            let header = packet.get(..RELAY_HEADER_LENGTH)?;
+            dhcpv6_packet.hop_count = Some(header[1]);
+            dhcpv6_packet.link_address = Some(read_ipv6(&header[2..18])?);
+            dhcpv6_packet.peer_address = Some(read_ipv6(&header[18..34])?);
+            &packet[RELAY_HEADER_LENGTH..]
+        }
+        _ => {
+            let header = packet.get(..MESSAGE_HEADER_LENGTH)?;
+            dhcpv6_packet.transaction_id =
+                Some(u32::from_be_bytes([0, header[1], header[2], header[3]]));
+            &packet[MESSAGE_HEADER_LENGTH..]
+        }
+    };
+
+    for (code, data) in split_options(options)? {
+        dhcpv6_packet.options.push(code);
+
+        match code {
+            Dhcpv6Options::CLIENTID => dhcpv6_packet.client_duid = Some(format_duid(data)),
+            Dhcpv6Options::SERVERID => dhcpv6_packet.server_duid = Some(format_duid(data)),
+            Dhcpv6Options::IA_NA => dhcpv6_packet.ia_na.push(parse_ia_na(data)?),
+            Dhcpv6Options::STATUS_CODE => dhcpv6_packet.status_code = Some(read_u16(data)?),
+            Dhcpv6Options::DNS_SERVERS => {
+                if data.len() % 16 != 0 {
+                    return None;
+                }
+                dhcpv6_packet.dns_servers = data.chunks(16).filter_map(read_ipv6).collect();
+            }
+            Dhcpv6Options::DOMAIN_LIST => {
+                dhcpv6_packet.domain_search_list = parse_domain_list(data)?
+            }
+            Dhcpv6Options::RELAY_MSG => {
+                dhcpv6_packet.relayed_message = Some(Box::new(parse_dhcpv6_message(data)?))
+            }
+            _ => (),
+        }
+    }
+
+    Some(dhcpv6_packet)
+}
+
+/// Split a sequence of options in code and data, `None` if an option exceeds the buffer
+fn split_options(mut options: &[u8]) -> Option<Vec<(u16, &[u8])>> {
+    let mut result = vec![];
+
+    while !options.is_empty() {
+        let code = read_u16(options)?;
+        let length = read_u16(options.get(2..)?)? as usize;
+        let data = options.get(4..4 + length)?;
+
+        result.push((code, data));
+        options = &options[4 + length..];
+    }
+
+    Some(result)
+}
+
+fn parse_ia_na(data: &[u8]) -> Option<Dhcpv6IdentityAssociation> {
+    let header = data.get(..IA_NA_HEADER_LENGTH)?;
+    let mut identity_association = Dhcpv6IdentityAssociation {
+        iaid: u32::from_be_bytes(header[0..4].try_into().ok()?),
+        t1: u32::from_be_bytes(header[4..8].try_into().ok()?),
+        t2: u32::from_be_bytes(header[8..12].try_into().ok()?),
+        addresses: vec![],
+        status_code: None,
+    };
+
+    for (code, data) in split_options(&data[IA_NA_HEADER_LENGTH..])? {
+        match code {
+            Dhcpv6Options::IAADDR => {
+                let header = data.get(..IAADDR_HEADER_LENGTH)?;
+                identity_association.addresses.push(Dhcpv6IaAddress {
+                    address: read_ipv6(&header[0..16])?,
+                    preferred_lifetime: u32::from_be_bytes(header[16..20].try_into().ok()?),
+                    valid_lifetime: u32::from_be_bytes(header[20..24].try_into().ok()?),
+                });
+            }
+            Dhcpv6Options::STATUS_CODE => identity_association.status_code = Some(read_u16(data)?),
+            _ => (),
+        }
+    }
+
+    Some(identity_association)
+}
+
+/// Domain names in uncompressed DNS wire format (RFC 1035, section 3.1)
+fn parse_domain_list(mut data: &[u8]) -> Option<Vec<String>> {
+    let mut domains = vec![];
+    let mut labels: Vec<String> = vec![];
+
+    while let Some((&length, rest)) = data.split_first() {
+        let length = length as usize;
+        if length == 0 {
+            domains.push(labels.join("."));
+            labels.clear();
+        } else {
+            labels.push(String::from_utf8_lossy(rest.get(..length)?).to_string());
+        }
+
+        data = &rest[length..];
+    }
+
+    // Unterminated (partial) name
+    if !labels.is_empty() {
+        domains.push(labels.join("."));
+    }
+
+    Some(domains)
+}
+
+/// DUID as colon-separated hex bytes, e.g. `00:01:00:01:...`
+fn format_duid(data: &[u8]) -> String {
+    data.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<String>>()
+        .join(":")
+}
+
+fn read_u16(data: &[u8]) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(..2)?.try_into().ok()?))
+}
+
+fn read_ipv6(data: &[u8]) -> Option<Ipv6Addr> {
+    let octets: [u8; 16] = data.get(..16)?.try_into().ok()?;
+    Some(Ipv6Addr::from(octets))
+}
+
+fn get_message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        Dhcpv6MessageTypes::SOLICIT => "SOLICIT",
+        Dhcpv6MessageTypes::ADVERTISE => "ADVERTISE",
+        Dhcpv6MessageTypes::REQUEST => "REQUEST",
+        Dhcpv6MessageTypes::CONFIRM => "CONFIRM",
+        Dhcpv6MessageTypes::RENEW => "RENEW",
+        Dhcpv6MessageTypes::REBIND => "REBIND",
+        Dhcpv6MessageTypes::REPLY => "REPLY",
+        Dhcpv6MessageTypes::RELEASE => "RELEASE",
+        Dhcpv6MessageTypes::DECLINE => "DECLINE",
+        Dhcpv6MessageTypes::RECONFIGURE => "RECONFIGURE",
+        Dhcpv6MessageTypes::INFORMATION_REQUEST => "INFORMATION-REQUEST",
+        Dhcpv6MessageTypes::RELAY_FORW => "RELAY-FORW",
+        Dhcpv6MessageTypes::RELAY_REPL => "RELAY-REPL",
+        _ => "UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv6Addr};
+
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::handle_dhcpv6_packet;
+
+    fn option(code: u16, data: &[u8]) -> Vec<u8> {
+        let mut option = code.to_be_bytes().to_vec();
+        option.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        option.extend_from_slice(data);
+        option
+    }
+
+    fn parse(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_dhcpv6_packet(
+            IpAddr::V6("fe80::1".parse().unwrap()),
+            547,
+            IpAddr::V6("fe80::2".parse().unwrap()),
+            546,
+            payload,
+            &mut parsed_packet,
+        );
+
+        parsed_packet
+    }
+
+    fn build_reply() -> Vec<u8> {
+        let address: Ipv6Addr = "2001:db8::100".parse().unwrap();
+        let mut ia_address = address.octets().to_vec();
+        ia_address.extend_from_slice(&3600u32.to_be_bytes());
+        ia_address.extend_from_slice(&7200u32.to_be_bytes());
+
+        let mut ia_na = vec![0, 0, 0, 1, 0, 0, 0x07, 0x08, 0, 0, 0x0b, 0x40];
+        ia_na.extend(option(5, &ia_address));
+
+        let dns_servers = [
+            "2001:db8::53".parse::<Ipv6Addr>().unwrap().octets(),
+            "2001:db8::54".parse::<Ipv6Addr>().unwrap().octets(),
+        ]
+        .concat();
+
+        // REPLY, transaction ID 0x123456
+        let mut reply = vec![7, 0x12, 0x34, 0x56];
+        reply.extend(option(1, &[0, 3, 0, 1, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55]));
+        reply.extend(option(2, &[0, 3, 0, 1, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb]));
+        reply.extend(option(3, &ia_na));
+        reply.extend(option(23, &dns_servers));
+        reply.extend(option(24, b"\x07example\x03com\x00"));
+        reply
+    }
+
+    #[test]
+    fn dhcpv6_reply() {
+        let parsed_packet = parse(&build_reply());
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::Dhcpv6Packet(dhcpv6_packet) => {
+                assert_eq!(dhcpv6_packet.message_type_name, "REPLY");
+                assert_eq!(dhcpv6_packet.transaction_id, Some(0x123456));
+                assert_eq!(
+                    dhcpv6_packet.client_duid.as_deref(),
+                    Some("00:03:00:01:00:11:22:33:44:55")
+                );
+                assert!(dhcpv6_packet.server_duid.is_some());
+                assert_eq!(dhcpv6_packet.options, vec![1, 2, 3, 23, 24]);
+
+                let ia_na = &dhcpv6_packet.ia_na[0];
+                assert_eq!((ia_na.iaid, ia_na.t1, ia_na.t2), (1, 1800, 2880));
+                assert_eq!(
+                    ia_na.addresses[0].address,
+                    "2001:db8::100".parse::<Ipv6Addr>().unwrap()
+                );
+                assert_eq!(ia_na.addresses[0].valid_lifetime, 7200);
+
+                assert_eq!(dhcpv6_packet.dns_servers.len(), 2);
+                assert_eq!(dhcpv6_packet.domain_search_list, vec!["example.com"]);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dhcpv6_relay_forward() {
+        let mut relay = vec![12, 1];
+        relay.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        relay.extend_from_slice(&"fe80::2".parse::<Ipv6Addr>().unwrap().octets());
+        // Relayed SOLICIT
+        relay.extend(option(9, &[1, 0, 0, 1]));
+
+        match parse(&relay).get_application_layer_packet().unwrap() {
+            SerializablePacket::Dhcpv6Packet(dhcpv6_packet) => {
+                assert_eq!(dhcpv6_packet.message_type_name, "RELAY-FORW");
+                assert_eq!(dhcpv6_packet.transaction_id, None);
+                assert_eq!(dhcpv6_packet.hop_count, Some(1));
+                assert_eq!(
+                    dhcpv6_packet
+                        .relayed_message
+                        .as_ref()
+                        .unwrap()
+                        .message_type_name,
+                    "SOLICIT"
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_dhcpv6() {
+        let mut reply = build_reply();
+        reply.truncate(reply.len() - 3);
+
+        assert!(matches!(
+            parse(&reply).get_application_layer_packet().unwrap(),
+            SerializablePacket::MalformedPacket(_)
+        ));
+    }
+}
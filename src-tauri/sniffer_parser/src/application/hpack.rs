@@ -0,0 +1,441 @@
+//! HPACK header decompression (RFC 7541), for the header blocks of HTTP/2
+//!
+//! Each direction of a connection has its own decoder: the dynamic table is filled by the header fields
+//! sent with incremental indexing, and evicted oldest first once over its maximum size. Parsing a
+//! connection from the middle lacks the fields indexed before the capture started, the header blocks
+//! referring to them can't be decoded.
+
+use std::collections::VecDeque;
+
+/// Initial size of the dynamic table, the default SETTINGS_HEADER_TABLE_SIZE
+const DEFAULT_TABLE_SIZE: usize = 4096;
+/// Overhead of each entry of the dynamic table, added to the length of its name and value
+const ENTRY_OVERHEAD: usize = 32;
+
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Length of the Huffman code of each symbol, the last one being EOS
+///
+/// The code is canonical: codes are assigned in order of length, then of symbol, so the lengths are
+/// enough to decode it
+const HUFFMAN_CODE_LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 30, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6, 5, 5,
+    5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10, 13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6, 15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6,
+    6, 5, 6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28, 20, 22, 20, 20, 22, 22, 22, 23, 22,
+    23, 23, 23, 23, 23, 24, 23, 24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24, 22,
+    21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23, 21, 21, 22, 21, 23, 22, 23, 23, 20,
+    22, 22, 22, 23, 22, 22, 23, 26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25, 19,
+    21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27, 20, 24, 20, 21, 22, 21, 21, 23, 22,
+    22, 25, 25, 24, 24, 26, 23, 26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26, 30,
+];
+const HUFFMAN_EOS: u16 = 256;
+const HUFFMAN_MAX_CODE_LENGTH: usize = 30;
+
+/// Number of Huffman codes of each length
+const HUFFMAN_LENGTH_COUNTS: [u32; HUFFMAN_MAX_CODE_LENGTH + 1] = count_code_lengths();
+/// Symbols in the order of their codes
+const HUFFMAN_SYMBOLS: [u16; 257] = sort_symbols();
+
+const fn count_code_lengths() -> [u32; HUFFMAN_MAX_CODE_LENGTH + 1] {
+    let mut counts = [0; HUFFMAN_MAX_CODE_LENGTH + 1];
+    let mut symbol = 0;
+    while symbol < HUFFMAN_CODE_LENGTHS.len() {
+        counts[HUFFMAN_CODE_LENGTHS[symbol] as usize] += 1;
+        symbol += 1;
+    }
+    counts
+}
+
+const fn sort_symbols() -> [u16; 257] {
+    let mut symbols = [0; 257];
+    let mut position = 0;
+    let mut length = 1;
+    while length <= HUFFMAN_MAX_CODE_LENGTH {
+        let mut symbol = 0;
+        while symbol < HUFFMAN_CODE_LENGTHS.len() {
+            if HUFFMAN_CODE_LENGTHS[symbol] as usize == length {
+                symbols[position] = symbol as u16;
+                position += 1;
+            }
+            symbol += 1;
+        }
+        length += 1;
+    }
+    symbols
+}
+
+/// Errors occurring during the decoding of a header block
+#[derive(Debug, PartialEq, Eq)]
+pub enum HpackError {
+    Truncated,
+    IntegerOverflow,
+    /// Index not in the static table nor in the dynamic one
+    InvalidIndex(usize),
+    InvalidHuffmanCode,
+}
+
+/// Decoder of the header blocks sent in a direction of a connection
+#[derive(Debug, Clone)]
+pub struct HpackDecoder {
+    /// Header fields indexed by the encoder, the most recent first
+    dynamic_table: VecDeque<(String, String)>,
+    table_size: usize,
+    max_table_size: usize,
+}
+
+impl Default for HpackDecoder {
+    fn default() -> Self {
+        HpackDecoder {
+            dynamic_table: VecDeque::new(),
+            table_size: 0,
+            max_table_size: DEFAULT_TABLE_SIZE,
+        }
+    }
+}
+
+impl HpackDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a whole header block into its header list, updating the dynamic table
+    pub fn decode(&mut self, mut block: &[u8]) -> Result<Vec<(String, String)>, HpackError> {
+        let mut headers = vec![];
+
+        while let Some(&first) = block.first() {
+            if first & 0x80 != 0 {
+                // Indexed header field
+                let index = decode_integer(&mut block, 7)?;
+                headers.push(self.get_entry(index)?);
+            } else if first & 0x40 != 0 {
+                // Literal header field with incremental indexing
+                let header = self.decode_literal(&mut block, 6)?;
+                self.insert(header.clone());
+                headers.push(header);
+            } else if first & 0x20 != 0 {
+                // Dynamic table size update
+                self.max_table_size = decode_integer(&mut block, 5)?;
+                self.evict(0);
+            } else {
+                // Literal header field without indexing, or never indexed
+                headers.push(self.decode_literal(&mut block, 4)?);
+            }
+        }
+
+        Ok(headers)
+    }
+
+    fn get_entry(&self, index: usize) -> Result<(String, String), HpackError> {
+        match index {
+            0 => Err(HpackError::InvalidIndex(index)),
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Ok((name.to_owned(), value.to_owned()))
+            }
+            _ => self
+                .dynamic_table
+                .get(index - STATIC_TABLE.len() - 1)
+                .cloned()
+                .ok_or(HpackError::InvalidIndex(index)),
+        }
+    }
+
+    /// Name (indexed or literal) and literal value of a header field
+    fn decode_literal(
+        &self,
+        block: &mut &[u8],
+        prefix_bits: u32,
+    ) -> Result<(String, String), HpackError> {
+        let name = match decode_integer(block, prefix_bits)? {
+            0 => decode_string(block)?,
+            index => self.get_entry(index)?.0,
+        };
+        let value = decode_string(block)?;
+
+        Ok((name, value))
+    }
+
+    fn insert(&mut self, header: (String, String)) {
+        let size = header.0.len() + header.1.len() + ENTRY_OVERHEAD;
+        self.evict(size);
+
+        // An entry larger than the table empties it, without being added
+        if size <= self.max_table_size {
+            self.table_size += size;
+            self.dynamic_table.push_front(header);
+        }
+    }
+
+    /// Evict the oldest entries until there is room for the new size
+    fn evict(&mut self, size: usize) {
+        while self.table_size + size > self.max_table_size {
+            match self.dynamic_table.pop_back() {
+                Some((name, value)) => self.table_size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+}
+
+/// Integer with an N-bit prefix, consumed from the block
+fn decode_integer(block: &mut &[u8], prefix_bits: u32) -> Result<usize, HpackError> {
+    let (&first, mut rest) = block.split_first().ok_or(HpackError::Truncated)?;
+    let max_prefix = (1 << prefix_bits) - 1;
+
+    let mut value = (first & max_prefix) as usize;
+    if value == max_prefix as usize {
+        let mut shift = 0;
+        loop {
+            let (&byte, next) = rest.split_first().ok_or(HpackError::Truncated)?;
+            rest = next;
+
+            // Larger values aren't used by any encoder, and could overflow
+            if shift > 28 {
+                return Err(HpackError::IntegerOverflow);
+            }
+            value += ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+
+    *block = rest;
+    Ok(value)
+}
+
+/// String literal, Huffman-encoded or not, consumed from the block
+fn decode_string(block: &mut &[u8]) -> Result<String, HpackError> {
+    let is_huffman = block.first().ok_or(HpackError::Truncated)? & 0x80 != 0;
+    let length = decode_integer(block, 7)?;
+    if block.len() < length {
+        return Err(HpackError::Truncated);
+    }
+    let (data, rest) = block.split_at(length);
+    *block = rest;
+
+    let data = if is_huffman {
+        decode_huffman(data)?
+    } else {
+        data.to_vec()
+    };
+
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+fn decode_huffman(data: &[u8]) -> Result<Vec<u8>, HpackError> {
+    let mut output = Vec::with_capacity(data.len() * 8 / 5);
+
+    // Bits of the current code, with the first code of its length and the index of its symbol
+    let mut code: u32 = 0;
+    let mut length = 0;
+    let mut first_code: u32 = 0;
+    let mut first_index = 0;
+
+    for byte in data {
+        for bit in (0..8).rev() {
+            code = (code << 1) | ((byte >> bit) & 1) as u32;
+            length += 1;
+
+            let count = HUFFMAN_LENGTH_COUNTS[length];
+            if code - first_code < count {
+                let symbol = HUFFMAN_SYMBOLS[first_index + (code - first_code) as usize];
+                if symbol == HUFFMAN_EOS {
+                    return Err(HpackError::InvalidHuffmanCode);
+                }
+                output.push(symbol as u8);
+
+                code = 0;
+                length = 0;
+                first_code = 0;
+                first_index = 0;
+            } else if length == HUFFMAN_MAX_CODE_LENGTH {
+                return Err(HpackError::InvalidHuffmanCode);
+            } else {
+                first_index += count as usize;
+                first_code = (first_code + count) << 1;
+            }
+        }
+    }
+
+    // Padded with the most significant bits of EOS, all ones, up to 7 bits
+    if length > 7 || code != (1 << length) - 1 {
+        return Err(HpackError::InvalidHuffmanCode);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HpackDecoder, HpackError};
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        let hex = hex.replace(' ', "");
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn headers(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// RFC 7541, C.3 and C.4: the same requests without and with Huffman coding
+    #[test]
+    fn request_examples_decoded() {
+        let requests = [
+            headers(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ]),
+            headers(&[
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+                ("cache-control", "no-cache"),
+            ]),
+            headers(&[
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/index.html"),
+                (":authority", "www.example.com"),
+                ("custom-key", "custom-value"),
+            ]),
+        ];
+
+        let examples = [
+            [
+                "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+                "8286 84be 5808 6e6f 2d63 6163 6865",
+                "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+            ],
+            [
+                "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+                "8286 84be 5886 a8eb 1064 9cbf",
+                "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+            ],
+        ];
+
+        for blocks in examples {
+            let mut decoder = HpackDecoder::new();
+            for (block, request) in blocks.iter().zip(&requests) {
+                assert_eq!(decoder.decode(&from_hex(block)).unwrap(), *request);
+            }
+            assert_eq!(decoder.dynamic_table.len(), 3);
+            assert_eq!(decoder.table_size, 164);
+        }
+    }
+
+    #[test]
+    fn invalid_blocks_rejected() {
+        // Dynamic table still empty
+        assert_eq!(
+            HpackDecoder::new().decode(&from_hex("be")),
+            Err(HpackError::InvalidIndex(62))
+        );
+        assert_eq!(
+            HpackDecoder::new().decode(&from_hex("80")),
+            Err(HpackError::InvalidIndex(0))
+        );
+        // Value longer than the block
+        assert_eq!(
+            HpackDecoder::new().decode(&from_hex("4105 6162")),
+            Err(HpackError::Truncated)
+        );
+        // Padding not made of ones
+        assert_eq!(
+            HpackDecoder::new().decode(&from_hex("4181 00")),
+            Err(HpackError::InvalidHuffmanCode)
+        );
+        assert_eq!(
+            HpackDecoder::new().decode(&from_hex("ff ff ff ff ff ff 01")),
+            Err(HpackError::IntegerOverflow)
+        );
+
+        // Entries evicted by a smaller table
+        let mut decoder = HpackDecoder::new();
+        decoder
+            .decode(&from_hex(
+                "400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+            ))
+            .unwrap();
+        assert_eq!(
+            decoder.decode(&from_hex("3f 11 be")),
+            Err(HpackError::InvalidIndex(62))
+        );
+    }
+}
@@ -71,7 +71,7 @@ pub fn handle_http_packet(
                             );
 
                             match parsed_payload {
-                                Ok(parsed_payload) => {
+                                Ok((parsed_payload, text_body)) => {
                                     debug!(
                                         "HTTP Request Packet: {:?} {:?} {:?}; Headers: {:?}; Payload: {:?}",
                                         request.method, request.path, request.version, request.headers, parsed_payload
@@ -79,7 +79,7 @@ pub fn handle_http_packet(
 
                                     parsed_packet.set_application_layer_packet(Some(
                                         SerializablePacket::HttpRequestPacket(
-                                            SerializableHttpRequestPacket::new(&request, parsed_payload),
+                                            SerializableHttpRequestPacket::new(&request, parsed_payload, text_body),
                                         ),
                                     ));
                                 },
@@ -116,7 +116,7 @@ pub fn handle_http_packet(
                             );
 
                             match parsed_payload {
-                                Ok(parsed_payload) => {
+                                Ok((parsed_payload, text_body)) => {
                                     debug!(
                                         "HTTP Response Packet: {:?} {:?} {:?}; Headers: {:?}; Payload: {:?}",
                                         response.version, response.code, response.reason, response.headers, parsed_payload
@@ -124,7 +124,7 @@ pub fn handle_http_packet(
 
                                     parsed_packet.set_application_layer_packet(Some(
                                         SerializablePacket::HttpResponsePacket(
-                                            SerializableHttpResponsePacket::new(&response, parsed_payload),
+                                            SerializableHttpResponsePacket::new(&response, parsed_payload, text_body),
                                         ),
                                     ));
                                 },
@@ -199,14 +199,15 @@ fn packet_is_ended(
     false
 }
 
+/// Content of the body, along with the bytes it was decoded from when it is text
 fn parse_http_payload(
     payload_with_headers: Vec<u8>,
     start: usize,
     headers: &mut [Header],
-) -> Result<HttpContentType> {
+) -> Result<(HttpContentType, Option<Vec<u8>>)> {
     let mut payload = payload_with_headers[start..].to_vec();
     if payload.is_empty() {
-        return Ok(HttpContentType::None);
+        return Ok((HttpContentType::None, None));
     }
 
     let transfer_encoding = get_header_value(HeaderNamesValues::TRANSFER_ENCODING, headers);
@@ -216,34 +217,37 @@ fn parse_http_payload(
 
     let mime = get_header_value(HeaderNamesValues::CONTENT_TYPE, headers);
     if mime.is_none() {
-        return Ok(HttpContentType::Unknown(payload));
+        return Ok((HttpContentType::Unknown(payload), None));
     }
     let mime = mime.unwrap().parse::<Mime>();
     if mime.is_err() {
-        return Ok(HttpContentType::Unknown(payload));
+        return Ok((HttpContentType::Unknown(payload), None));
     }
 
     let mime = mime.unwrap();
     let encoding = get_header_value(HeaderNamesValues::CONTENT_ENCODING, headers);
 
-    return match encoding {
-        Some(encoding) => {
-            let result = decode_payload(&mut payload, encoding);
-            return match result {
-                Ok(decoded_payload) => Ok(get_http_type(mime, decoded_payload.to_vec(), None)),
-                Err(algo) => match algo {
-                    HttpParsingError::DecodingPayloadFailed(algo, _) => {
-                        Ok(get_http_type(mime, payload.to_vec(), Some(&algo)))
-                    }
-                    HttpParsingError::UnknownDecodingAlgorithm(algo, _) => {
-                        Ok(get_http_type(mime, payload.to_vec(), Some(&algo)))
-                    }
-                    _ => Err(HttpParsingError::Other),
-                },
-            };
-        }
-        None => Ok(get_http_type(mime, payload.to_vec(), None)),
+    let (payload, failed_encoding) = match encoding {
+        Some(encoding) => match decode_payload(&mut payload, encoding) {
+            Ok(decoded_payload) => (decoded_payload, None),
+            Err(HttpParsingError::DecodingPayloadFailed(algo, _))
+            | Err(HttpParsingError::UnknownDecodingAlgorithm(algo, _)) => (payload, Some(algo)),
+            Err(_) => return Err(HttpParsingError::Other),
+        },
+        None => (payload, None),
     };
+
+    // Decoding the text with its charset can alter it, so the bytes it comes from are kept as well
+    let text_body = if failed_encoding.is_none() && mime.type_() == mime::TEXT {
+        Some(payload.clone())
+    } else {
+        None
+    };
+
+    Ok((
+        get_http_type(mime, payload, failed_encoding.as_deref()),
+        text_body,
+    ))
 }
 
 fn merge_chunks(payload: Vec<u8>) -> Result<Vec<u8>> {
@@ -368,7 +372,7 @@ fn decode_payload<'a>(payload: &mut Vec<u8>, encoding: &'a str) -> Result<Vec<u8
                     Err(_) => {
                         return Err(HttpParsingError::DecodingPayloadFailed(
                             ext.to_owned(),
-                            format!("Decoding failed for: {ext}"),
+                            format!("Decoding failed for: {}", ext),
                         ))
                     }
                     _ => (),
@@ -380,7 +384,7 @@ fn decode_payload<'a>(payload: &mut Vec<u8>, encoding: &'a str) -> Result<Vec<u8
                     Err(_) => {
                         return Err(HttpParsingError::DecodingPayloadFailed(
                             ext.to_owned(),
-                            format!("Decoding failed for: {ext}"),
+                            format!("Decoding failed for: {}", ext),
                         ))
                     }
                     _ => (),
@@ -392,7 +396,7 @@ fn decode_payload<'a>(payload: &mut Vec<u8>, encoding: &'a str) -> Result<Vec<u8
                     Err(_) => {
                         return Err(HttpParsingError::DecodingPayloadFailed(
                             ext.to_owned(),
-                            format!("Decoding failed for: {ext}"),
+                            format!("Decoding failed for: {}", ext),
                         ))
                     }
                     _ => (),
@@ -401,7 +405,7 @@ fn decode_payload<'a>(payload: &mut Vec<u8>, encoding: &'a str) -> Result<Vec<u8
             _ => {
                 return Err(HttpParsingError::UnknownDecodingAlgorithm(
                     ext.to_owned(),
-                    format!("Unknown algorithm: {ext}"),
+                    format!("Unknown algorithm: {}", ext),
                 ))
             }
         }
@@ -441,7 +445,7 @@ mod tests {
 
     use super::{
         decode_payload, get_http_type, handle_http_packet, merge_chunks, packet_is_ended,
-        HttpParsingError,
+        parse_http_payload, HttpParsingError,
     };
     use crate::{
         application::{HeaderNamesValues, WellKnownPorts},
@@ -912,4 +916,21 @@ mod tests {
             _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn text_body_kept_as_transferred() {
+        let payload = b"Content-Type: text/plain; charset=iso-8859-1\r\n\r\ncaf\xe9".to_vec();
+        let mut headers = [httparse::Header {
+            name: HeaderNamesValues::CONTENT_TYPE,
+            value: b"text/plain; charset=iso-8859-1",
+        }];
+
+        let (content, text_body) = parse_http_payload(payload, 48, &mut headers).unwrap();
+
+        match content {
+            HttpContentType::TextCorrectlyDecoded(text) => assert_eq!(text, "caf\u{e9}"),
+            _ => unreachable!(),
+        }
+        assert_eq!(text_body.unwrap(), b"caf\xe9");
+    }
 }
@@ -0,0 +1,481 @@
+//! HTTP/2 Packet parsing (RFC 9113)
+//!
+//! A connection is recognized by the preface of the client (`PRI * HTTP/2.0`), sent in cleartext (h2c)
+//! with prior knowledge or after an HTTP/1.1 upgrade, or as the first application data of a decrypted TLS
+//! connection. Its frames are then parsed in both directions: frames split across segments are buffered
+//! until complete, and the packet completing them carries them.
+//!
+//! Header blocks are decompressed with the HPACK state of their direction, once their last CONTINUATION
+//! frame is received: the frame ending the block carries the decoded header list. A decoding error leaves
+//! the state of the direction unknown, so its following header blocks aren't decoded.
+
+use std::{collections::HashMap, net::IpAddr};
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::{Http2Frame, SerializableHttp2Packet},
+    ParsedPacket, SerializablePacket,
+};
+use crate::ACTIVE_HTTP2_CONNECTIONS;
+
+use super::hpack::HpackDecoder;
+
+pub const CONNECTION_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const FRAME_HEADER_LENGTH: usize = 9;
+const PRIORITY_LENGTH: usize = 5;
+
+#[allow(non_snake_case)]
+mod FrameTypes {
+    pub const DATA: u8 = 0x0;
+    pub const HEADERS: u8 = 0x1;
+    pub const PRIORITY: u8 = 0x2;
+    pub const RST_STREAM: u8 = 0x3;
+    pub const SETTINGS: u8 = 0x4;
+    pub const PUSH_PROMISE: u8 = 0x5;
+    pub const PING: u8 = 0x6;
+    pub const GOAWAY: u8 = 0x7;
+    pub const WINDOW_UPDATE: u8 = 0x8;
+    pub const CONTINUATION: u8 = 0x9;
+}
+
+#[allow(non_snake_case)]
+mod Flags {
+    pub const END_STREAM: u8 = 0x1;
+    pub const ACK: u8 = 0x1;
+    pub const END_HEADERS: u8 = 0x4;
+    pub const PADDED: u8 = 0x8;
+    pub const PRIORITY: u8 = 0x20;
+}
+
+/// States of the directions of the HTTP/2 connections, by source and destination
+pub(crate) type Http2Connections = HashMap<((IpAddr, u16), (IpAddr, u16)), Http2Direction>;
+
+/// State of a direction of an HTTP/2 connection
+#[derive(Debug, Default)]
+pub(crate) struct Http2Direction {
+    /// Bytes of the frame not completely received yet
+    buffer: Vec<u8>,
+    decoder: HpackDecoder,
+    /// Fragments of the header block waiting for its CONTINUATION frames
+    header_block: Vec<u8>,
+    /// Whether a header block couldn't be decoded, losing the HPACK state
+    decoding_failed: bool,
+}
+
+/// Check whether the segment belongs to an HTTP/2 connection, already seen or starting with it
+pub fn is_http2_connection(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+) -> bool {
+    packet.starts_with(CONNECTION_PREFACE)
+        || ACTIVE_HTTP2_CONNECTIONS.with(|connections| {
+            let connections = connections.borrow();
+            connections.contains_key(&((source_ip, source_port), (dest_ip, dest_port)))
+                || connections.contains_key(&((dest_ip, dest_port), (source_ip, source_port)))
+        })
+}
+
+/// Build an HTTP/2 packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_http2_packet(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    ACTIVE_HTTP2_CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        let direction = connections
+            .entry(((source_ip, source_port), (dest_ip, dest_port)))
+            .or_default();
+        direction.buffer.extend_from_slice(packet);
+
+        let mut http2_packet = SerializableHttp2Packet::default();
+        if direction.buffer.starts_with(CONNECTION_PREFACE) {
+            direction.buffer.drain(..CONNECTION_PREFACE.len());
+            http2_packet.preface = true;
+        }
+
+        while direction.buffer.len() >= FRAME_HEADER_LENGTH {
+            let length = u32::from_be_bytes([
+                0,
+                direction.buffer[0],
+                direction.buffer[1],
+                direction.buffer[2],
+            ]) as usize;
+            if direction.buffer.len() < FRAME_HEADER_LENGTH + length {
+                break;
+            }
+
+            let frame = direction
+                .buffer
+                .drain(..FRAME_HEADER_LENGTH + length)
+                .collect::<Vec<u8>>();
+            http2_packet.frames.push(direction.parse_frame(&frame));
+        }
+
+        if !http2_packet.preface && http2_packet.frames.is_empty() {
+            return;
+        }
+
+        debug!(
+            "HTTP/2 Packet: {}:{} > {}:{}; Preface: {}, Frames: {:?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            http2_packet.preface,
+            http2_packet
+                .frames
+                .iter()
+                .map(|frame| &frame.frame_type)
+                .collect::<Vec<&String>>()
+        );
+
+        parsed_packet
+            .set_application_layer_packet(Some(SerializablePacket::Http2Packet(http2_packet)));
+    });
+}
+
+impl Http2Direction {
+    fn parse_frame(&mut self, frame: &[u8]) -> Http2Frame {
+        let (header, payload) = frame.split_at(FRAME_HEADER_LENGTH);
+        let frame_type = header[3];
+        let flags = header[4];
+
+        let mut parsed_frame = Http2Frame {
+            frame_type: get_frame_type_name(frame_type),
+            flags,
+            stream_id: read_stream_id(&header[5..]).unwrap_or_default(),
+            length: payload.len(),
+            ..Default::default()
+        };
+
+        let has_end_stream = frame_type == FrameTypes::DATA || frame_type == FrameTypes::HEADERS;
+        parsed_frame.end_stream = has_end_stream && flags & Flags::END_STREAM != 0;
+        let has_ack = frame_type == FrameTypes::SETTINGS || frame_type == FrameTypes::PING;
+        parsed_frame.ack = has_ack && flags & Flags::ACK != 0;
+
+        let content = match frame_type {
+            FrameTypes::DATA | FrameTypes::HEADERS | FrameTypes::PUSH_PROMISE => {
+                remove_padding(flags, payload)
+            }
+            _ => Some(payload),
+        };
+        let content = match content {
+            Some(content) => content,
+            None => {
+                parsed_frame.error = Some("Padding longer than the frame".to_owned());
+                return parsed_frame;
+            }
+        };
+
+        match frame_type {
+            FrameTypes::DATA => parsed_frame.data = Some(content.to_vec()),
+            FrameTypes::HEADERS => {
+                let fragment = if flags & Flags::PRIORITY != 0 {
+                    content.get(PRIORITY_LENGTH..)
+                } else {
+                    Some(content)
+                };
+                match fragment {
+                    Some(fragment) => self.add_header_fragment(&mut parsed_frame, fragment),
+                    None => parsed_frame.error = Some("Truncated priority".to_owned()),
+                }
+            }
+            FrameTypes::PUSH_PROMISE => match read_stream_id(content) {
+                Some(promised_stream_id) => {
+                    parsed_frame.promised_stream_id = Some(promised_stream_id);
+                    self.add_header_fragment(&mut parsed_frame, &content[4..]);
+                }
+                None => parsed_frame.error = Some("Truncated promised stream ID".to_owned()),
+            },
+            FrameTypes::CONTINUATION => self.add_header_fragment(&mut parsed_frame, content),
+            FrameTypes::RST_STREAM => {
+                parsed_frame.error_code = read_u32(content).map(get_error_code_name);
+            }
+            FrameTypes::SETTINGS => {
+                parsed_frame.settings = Some(
+                    content
+                        .chunks_exact(6)
+                        .map(|setting| {
+                            (
+                                get_setting_name(u16::from_be_bytes([setting[0], setting[1]])),
+                                u32::from_be_bytes([
+                                    setting[2], setting[3], setting[4], setting[5],
+                                ]),
+                            )
+                        })
+                        .collect(),
+                );
+            }
+            FrameTypes::PING => parsed_frame.data = Some(content.to_vec()),
+            FrameTypes::GOAWAY => {
+                parsed_frame.last_stream_id = read_stream_id(content);
+                parsed_frame.error_code =
+                    content.get(4..).and_then(read_u32).map(get_error_code_name);
+                parsed_frame.data = content.get(8..).map(<[u8]>::to_vec);
+            }
+            FrameTypes::WINDOW_UPDATE => parsed_frame.window_increment = read_stream_id(content),
+            _ => (),
+        }
+
+        parsed_frame
+    }
+
+    /// Gather the fragments of a header block, decoding it on its last frame
+    fn add_header_fragment(&mut self, parsed_frame: &mut Http2Frame, fragment: &[u8]) {
+        self.header_block.extend_from_slice(fragment);
+        if parsed_frame.flags & Flags::END_HEADERS == 0 {
+            return;
+        }
+
+        let header_block = std::mem::take(&mut self.header_block);
+        if self.decoding_failed {
+            parsed_frame.error = Some("HPACK state lost by a previous header block".to_owned());
+            return;
+        }
+
+        match self.decoder.decode(&header_block) {
+            Ok(headers) => parsed_frame.headers = Some(headers),
+            Err(e) => {
+                debug!("HTTP/2 header block not decoded: {:?}", e);
+                self.decoding_failed = true;
+                parsed_frame.error = Some(format!("Header block not decoded: {:?}", e));
+            }
+        }
+    }
+}
+
+/// Content of a frame without its padding, `None` if the padding doesn't fit
+fn remove_padding(flags: u8, payload: &[u8]) -> Option<&[u8]> {
+    if flags & Flags::PADDED == 0 {
+        return Some(payload);
+    }
+
+    let (&padding, content) = payload.split_first()?;
+    content.get(..content.len().checked_sub(padding as usize)?)
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+/// 31-bit stream ID (or window increment), without the reserved bit
+fn read_stream_id(data: &[u8]) -> Option<u32> {
+    read_u32(data).map(|value| value & 0x7fff_ffff)
+}
+
+fn get_frame_type_name(frame_type: u8) -> String {
+    match frame_type {
+        FrameTypes::DATA => "DATA".to_owned(),
+        FrameTypes::HEADERS => "HEADERS".to_owned(),
+        FrameTypes::PRIORITY => "PRIORITY".to_owned(),
+        FrameTypes::RST_STREAM => "RST_STREAM".to_owned(),
+        FrameTypes::SETTINGS => "SETTINGS".to_owned(),
+        FrameTypes::PUSH_PROMISE => "PUSH_PROMISE".to_owned(),
+        FrameTypes::PING => "PING".to_owned(),
+        FrameTypes::GOAWAY => "GOAWAY".to_owned(),
+        FrameTypes::WINDOW_UPDATE => "WINDOW_UPDATE".to_owned(),
+        FrameTypes::CONTINUATION => "CONTINUATION".to_owned(),
+        other => format!("UNKNOWN(0x{:02x})", other),
+    }
+}
+
+fn get_setting_name(setting: u16) -> String {
+    match setting {
+        0x1 => "HEADER_TABLE_SIZE".to_owned(),
+        0x2 => "ENABLE_PUSH".to_owned(),
+        0x3 => "MAX_CONCURRENT_STREAMS".to_owned(),
+        0x4 => "INITIAL_WINDOW_SIZE".to_owned(),
+        0x5 => "MAX_FRAME_SIZE".to_owned(),
+        0x6 => "MAX_HEADER_LIST_SIZE".to_owned(),
+        0x8 => "ENABLE_CONNECT_PROTOCOL".to_owned(),
+        other => format!("UNKNOWN(0x{:04x})", other),
+    }
+}
+
+fn get_error_code_name(error_code: u32) -> String {
+    match error_code {
+        0x0 => "NO_ERROR".to_owned(),
+        0x1 => "PROTOCOL_ERROR".to_owned(),
+        0x2 => "INTERNAL_ERROR".to_owned(),
+        0x3 => "FLOW_CONTROL_ERROR".to_owned(),
+        0x4 => "SETTINGS_TIMEOUT".to_owned(),
+        0x5 => "STREAM_CLOSED".to_owned(),
+        0x6 => "FRAME_SIZE_ERROR".to_owned(),
+        0x7 => "REFUSED_STREAM".to_owned(),
+        0x8 => "CANCEL".to_owned(),
+        0x9 => "COMPRESSION_ERROR".to_owned(),
+        0xa => "CONNECT_ERROR".to_owned(),
+        0xb => "ENHANCE_YOUR_CALM".to_owned(),
+        0xc => "INADEQUATE_SECURITY".to_owned(),
+        0xd => "HTTP_1_1_REQUIRED".to_owned(),
+        other => format!("UNKNOWN(0x{:x})", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::cleanup_sniffing_state;
+    use crate::serializable_packet::application::Http2Frame;
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::{handle_http2_packet, is_http2_connection, CONNECTION_PREFACE};
+
+    const CLIENT: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 40000);
+    const SERVER: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 80)), 80);
+
+    fn frame(frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[frame_type, flags]);
+        frame.extend_from_slice(&stream_id.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn parse(from_client: bool, payload: &[u8]) -> Option<Vec<Http2Frame>> {
+        let ((source_ip, source_port), (dest_ip, dest_port)) = if from_client {
+            (CLIENT, SERVER)
+        } else {
+            (SERVER, CLIENT)
+        };
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_http2_packet(
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            payload,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_application_layer_packet() {
+            Some(SerializablePacket::Http2Packet(http2_packet)) => {
+                Some(http2_packet.frames.clone())
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn http2_connection_parsed() {
+        cleanup_sniffing_state();
+        let (client_ip, client_port) = CLIENT;
+        let (server_ip, server_port) = SERVER;
+        assert!(!is_http2_connection(
+            server_ip,
+            server_port,
+            client_ip,
+            client_port,
+            b"HTTP/1.1 200 OK\r\n"
+        ));
+
+        // RFC 7541, C.4.1 and C.4.2
+        let first_request = [
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab,
+            0x90, 0xf4, 0xff,
+        ];
+        let second_request = [
+            0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf,
+        ];
+
+        let client_data = [
+            CONNECTION_PREFACE.to_vec(),
+            frame(0x4, 0, 0, &[0, 0x3, 0, 0, 0, 100]),
+            // HEADERS split in a CONTINUATION frame
+            frame(0x1, 0x1, 1, &first_request[..5]),
+            frame(0x9, 0x4, 1, &first_request[5..]),
+            frame(0x1, 0x1 | 0x4, 3, &second_request),
+        ]
+        .concat();
+        assert!(is_http2_connection(
+            client_ip,
+            client_port,
+            server_ip,
+            server_port,
+            &client_data
+        ));
+
+        // The first segment ends in the middle of the CONTINUATION frame
+        let frames = parse(
+            true,
+            &client_data[..CONNECTION_PREFACE.len() + 15 + 14 + 10],
+        )
+        .unwrap();
+        assert_eq!(
+            frames
+                .iter()
+                .map(|frame| frame.frame_type.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["SETTINGS", "HEADERS"]
+        );
+        assert_eq!(
+            frames[0].settings,
+            Some(vec![("MAX_CONCURRENT_STREAMS".to_owned(), 100)])
+        );
+        assert!(frames[1].headers.is_none());
+        assert!(frames[1].end_stream);
+
+        let frames = parse(
+            true,
+            &client_data[CONNECTION_PREFACE.len() + 15 + 14 + 10..],
+        )
+        .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            frames[0].headers.as_ref().unwrap()[3],
+            (":authority".to_owned(), "www.example.com".to_owned())
+        );
+        // Indexing the dynamic table filled by the first request
+        assert_eq!(frames[1].stream_id, 3);
+        assert_eq!(
+            frames[1].headers.as_ref().unwrap()[3..],
+            [
+                (":authority".to_owned(), "www.example.com".to_owned()),
+                ("cache-control".to_owned(), "no-cache".to_owned())
+            ]
+        );
+
+        // The server direction of the connection, with a padded DATA frame
+        assert!(is_http2_connection(
+            server_ip,
+            server_port,
+            client_ip,
+            client_port,
+            &[]
+        ));
+        let server_data = [
+            frame(0x4, 0x1, 0, &[]),
+            frame(0x1, 0x4, 1, &[0x88]),
+            frame(0x0, 0x1 | 0x8, 1, &[2, b'o', b'k', 0, 0]),
+            frame(0x3, 0, 3, &8u32.to_be_bytes()),
+            // Index not in the dynamic table
+            frame(0x1, 0x4, 5, &[0xbe]),
+            frame(0x1, 0x4, 7, &[0x88]),
+        ]
+        .concat();
+        let frames = parse(false, &server_data).unwrap();
+        assert!(frames[0].ack);
+        assert_eq!(
+            frames[1].headers,
+            Some(vec![(":status".to_owned(), "200".to_owned())])
+        );
+        assert_eq!(frames[2].data.as_deref(), Some(b"ok".as_slice()));
+        assert!(frames[2].end_stream);
+        assert_eq!(frames[3].error_code.as_deref(), Some("CANCEL"));
+        assert!(frames[4].error.is_some() && frames[4].headers.is_none());
+        assert!(frames[5].error.is_some() && frames[5].headers.is_none());
+
+        // Nothing complete
+        assert!(parse(false, &frame(0x0, 0, 1, b"partial")[..10]).is_none());
+        cleanup_sniffing_state();
+    }
+}
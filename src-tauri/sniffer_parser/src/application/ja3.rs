@@ -0,0 +1,213 @@
+//! JA3 and JA3S fingerprints of the TLS Client and Server Hello messages
+//!
+//! The fingerprint string joins, with commas, the decimal values of the Hello fields, each list joined by
+//! dashes:
+//! - JA3: `version,ciphers,extensions,elliptic curves,EC point formats`
+//! - JA3S: `version,cipher,extensions`
+//!
+//! GREASE values (RFC 8701) are left out, as they're random. The fingerprint is usually compared by the
+//! MD5 hash of its string.
+
+use tls_parser::{TlsClientHelloContents, TlsServerHelloContents};
+
+use crate::serializable_packet::application::Ja3Fingerprint;
+
+const SUPPORTED_GROUPS: u16 = 10;
+const EC_POINT_FORMATS: u16 = 11;
+
+/// Compute the JA3 fingerprint of a Client Hello message
+pub fn get_ja3(message: &TlsClientHelloContents) -> Ja3Fingerprint {
+    let extensions = get_extensions(message.ext.unwrap_or(b""));
+
+    let ciphers = message.ciphers.iter().map(|cipher| cipher.0);
+    let extension_types = extensions.iter().map(|(extension_type, _)| *extension_type);
+    let curves = extensions
+        .iter()
+        .find(|(extension_type, _)| *extension_type == SUPPORTED_GROUPS)
+        .map_or(vec![], |(_, data)| {
+            // Length of the list, then the groups
+            data.get(2..)
+                .unwrap_or(b"")
+                .chunks_exact(2)
+                .map(|group| u16::from_be_bytes([group[0], group[1]]))
+                .collect()
+        });
+    let point_formats = extensions
+        .iter()
+        .find(|(extension_type, _)| *extension_type == EC_POINT_FORMATS)
+        .map_or(vec![], |(_, data)| {
+            // Length of the list, then the formats
+            data.get(1..)
+                .unwrap_or(b"")
+                .iter()
+                .map(|format| *format as u16)
+                .collect()
+        });
+
+    Ja3Fingerprint::new(format!(
+        "{},{},{},{},{}",
+        message.version.0,
+        join_values(ciphers),
+        join_values(extension_types),
+        join_values(curves.into_iter()),
+        join_values(point_formats.into_iter())
+    ))
+}
+
+/// Compute the JA3S fingerprint of a Server Hello message
+pub fn get_ja3s(message: &TlsServerHelloContents) -> Ja3Fingerprint {
+    let extensions = get_extensions(message.ext.unwrap_or(b""));
+    let extension_types = extensions.iter().map(|(extension_type, _)| *extension_type);
+
+    Ja3Fingerprint::new(format!(
+        "{},{},{}",
+        message.version.0,
+        message.cipher.0,
+        join_values(extension_types)
+    ))
+}
+
+/// Types and data of the extensions, in order, up to the first truncated one
+fn get_extensions(mut data: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut extensions = vec![];
+
+    while data.len() >= 4 {
+        let extension_type = u16::from_be_bytes([data[0], data[1]]);
+        let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let extension_data = match data.get(4..4 + length) {
+            Some(extension_data) => extension_data,
+            None => break,
+        };
+
+        extensions.push((extension_type, extension_data));
+        data = &data[4 + length..];
+    }
+
+    extensions
+}
+
+/// GREASE values are `0x?a?a`, with the same byte twice
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn join_values(values: impl Iterator<Item = u16>) -> String {
+    values
+        .filter(|value| !is_grease(*value))
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// MD5 digest (RFC 1321) as lowercase hexadecimal
+pub(crate) fn md5_hex(data: &[u8]) -> String {
+    const SHIFTS: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    let constants = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32)
+        .collect::<Vec<u32>>();
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for block in message.chunks_exact(64) {
+        let words = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<u32>>();
+
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(constants[i])
+                .wrapping_add(words[g])
+                .rotate_left(SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        state[0] = state[0].wrapping_add(a);
+        state[1] = state[1].wrapping_add(b);
+        state[2] = state[2].wrapping_add(c);
+        state[3] = state[3].wrapping_add(d);
+    }
+
+    state
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tls_parser::{TlsCipherSuiteID, TlsClientHelloContents, TlsServerHelloContents};
+
+    use super::{get_ja3, get_ja3s, md5_hex};
+
+    #[test]
+    fn ja3_fingerprints_of_hellos() {
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            md5_hex(b"The quick brown fox jumps over the lazy dog"),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+
+        let extensions = [
+            // GREASE
+            0x1a, 0x1a, 0x00, 0x00, //
+            // Server name
+            0x00, 0x00, 0x00, 0x05, 0x00, 0x03, 0x00, 0x00, 0x00, //
+            // Supported groups: GREASE, x25519, secp256r1
+            0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x2a, 0x2a, 0x00, 0x1d, 0x00, 0x17, //
+            // EC point formats: uncompressed
+            0x00, 0x0b, 0x00, 0x02, 0x01, 0x00,
+        ];
+        let client_hello = TlsClientHelloContents::new(
+            0x0303,
+            0,
+            &[0; 28],
+            None,
+            vec![
+                TlsCipherSuiteID(0x0a0a),
+                TlsCipherSuiteID(0x1301),
+                TlsCipherSuiteID(0xc02b),
+            ],
+            vec![],
+            Some(&extensions),
+        );
+        let ja3 = get_ja3(&client_hello);
+        assert_eq!(ja3.raw, "771,4865-49195,0-10-11,29-23,0");
+        assert_eq!(ja3.hash, md5_hex(ja3.raw.as_bytes()));
+
+        let server_hello = TlsServerHelloContents::new(
+            0x0303,
+            0,
+            &[0; 28],
+            None,
+            0x1301,
+            0,
+            Some(&[0x00, 0x2b, 0x00, 0x02, 0x03, 0x04]),
+        );
+        let ja3s = get_ja3s(&server_hello);
+        assert_eq!(ja3s.raw, "771,4865,43");
+    }
+}
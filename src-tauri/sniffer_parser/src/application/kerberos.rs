@@ -0,0 +1,503 @@
+//! Kerberos Packet parsing
+//!
+//! Messages (RFC 4120) are ASN.1 DER encoded, sent as they are over UDP and prefixed by their 4 bytes
+//! length over TCP. Segments continuing a message split over TCP are not reassembled and left unparsed,
+//! while the fields found in the first segment of a split message are still reported.
+
+use std::net::IpAddr;
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableKerberosPacket, ParsedPacket, SerializablePacket,
+};
+
+/// Kerberos message types, also used as APPLICATION tag numbers of the messages
+#[allow(non_snake_case)]
+mod KerberosMessageTypes {
+    pub const AS_REQ: u8 = 10;
+    pub const AS_REP: u8 = 11;
+    pub const TGS_REQ: u8 = 12;
+    pub const TGS_REP: u8 = 13;
+    pub const AP_REQ: u8 = 14;
+    pub const AP_REP: u8 = 15;
+    pub const KRB_ERROR: u8 = 30;
+}
+
+/// ASN.1 DER tags
+#[allow(non_snake_case)]
+mod DerTags {
+    pub const INTEGER: u8 = 0x02;
+    pub const SEQUENCE: u8 = 0x30;
+    pub const GENERAL_STRING: u8 = 0x1b;
+    /// Constructed, application class
+    pub const APPLICATION: u8 = 0x60;
+    /// Constructed, context-specific class
+    pub const CONTEXT: u8 = 0xa0;
+}
+
+/// Length of the record mark preceding each message sent over TCP
+const TCP_RECORD_MARK_LENGTH: usize = 4;
+
+/// Build a Kerberos packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_kerberos_packet(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    let message = match get_kerberos_message(packet) {
+        Some(message) => message,
+        // Empty segments (e.g. pure TCP ACKs) and continuations of split messages
+        None => return,
+    };
+
+    if let Some(kerberos_packet) = parse_kerberos_message(message) {
+        debug!(
+            "Kerberos Packet: {}:{} > {}:{}; Type: {}, Realm: {:?}, Client: {:?}, Server: {:?}, Error: {:?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            kerberos_packet.message_type_name,
+            kerberos_packet.realm,
+            kerberos_packet.client_name,
+            kerberos_packet.server_name,
+            kerberos_packet.error_name,
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::KerberosPacket(
+            kerberos_packet,
+        )));
+    } else {
+        debug!("Malformed Kerberos Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed Kerberos Packet".to_string(),
+        )));
+    }
+}
+
+/// DER encoded message carried by the packet, skipping the TCP record mark
+fn get_kerberos_message(packet: &[u8]) -> Option<&[u8]> {
+    let is_message = |data: &[u8]| {
+        data.first()
+            .map_or(false, |tag| tag & 0xe0 == DerTags::APPLICATION)
+    };
+
+    if is_message(packet) {
+        Some(packet)
+    } else if packet.len() > TCP_RECORD_MARK_LENGTH && is_message(&packet[TCP_RECORD_MARK_LENGTH..])
+    {
+        Some(&packet[TCP_RECORD_MARK_LENGTH..])
+    } else {
+        None
+    }
+}
+
+fn parse_kerberos_message(message: &[u8]) -> Option<SerializableKerberosPacket> {
+    let (tag, body, _) = read_tlv(message)?;
+    let message_type = tag & 0x1f;
+
+    let mut kerberos_packet = SerializableKerberosPacket {
+        message_type,
+        message_type_name: get_message_type_name(message_type).to_owned(),
+        ..Default::default()
+    };
+
+    let fields = read_fields(read_expected(body, DerTags::SEQUENCE)?);
+
+    match message_type {
+        KerberosMessageTypes::AS_REQ | KerberosMessageTypes::TGS_REQ => {
+            if let Some(padata) = get_field(&fields, 3) {
+                kerberos_packet.padata_types = read_padata_types(padata);
+            }
+
+            let body = read_fields(read_expected(get_field(&fields, 4)?, DerTags::SEQUENCE)?);
+            kerberos_packet.client_name = get_field(&body, 1).and_then(read_principal_name);
+            kerberos_packet.realm = get_field(&body, 2).and_then(read_string);
+            kerberos_packet.server_name = get_field(&body, 3).and_then(read_principal_name);
+            kerberos_packet.encryption_types = get_field(&body, 8)
+                .and_then(|etypes| read_expected(etypes, DerTags::SEQUENCE))
+                .map(read_integers)
+                .unwrap_or_default();
+        }
+        KerberosMessageTypes::AS_REP | KerberosMessageTypes::TGS_REP => {
+            if let Some(padata) = get_field(&fields, 2) {
+                kerberos_packet.padata_types = read_padata_types(padata);
+            }
+
+            kerberos_packet.realm = get_field(&fields, 3).and_then(read_string);
+            kerberos_packet.client_name = get_field(&fields, 4).and_then(read_principal_name);
+
+            // Ticket ::= [APPLICATION 1] SEQUENCE { tkt-vno [0], realm [1], sname [2], enc-part [3] }
+            kerberos_packet.server_name = get_field(&fields, 5)
+                .and_then(read_tlv)
+                .and_then(|(_, ticket, _)| read_expected(ticket, DerTags::SEQUENCE))
+                .map(read_fields)
+                .and_then(|ticket| get_field(&ticket, 2).and_then(read_principal_name));
+        }
+        KerberosMessageTypes::KRB_ERROR => {
+            let error_code = get_field(&fields, 6).and_then(read_integer)?;
+            kerberos_packet.error_code = Some(error_code);
+            kerberos_packet.error_name = get_error_name(error_code).map(str::to_owned);
+            kerberos_packet.client_name = get_field(&fields, 8).and_then(read_principal_name);
+            kerberos_packet.realm = get_field(&fields, 9).and_then(read_string);
+            kerberos_packet.server_name = get_field(&fields, 10).and_then(read_principal_name);
+            kerberos_packet.error_text = get_field(&fields, 11).and_then(read_string);
+        }
+        KerberosMessageTypes::AP_REQ | KerberosMessageTypes::AP_REP => (),
+        _ => return None,
+    }
+
+    Some(kerberos_packet)
+}
+
+/// Read a DER Tag-Length-Value, returning tag, value and the following data
+///
+/// Values longer than the available data are truncated, to parse the first segment of split messages
+fn read_tlv(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    // Multi-byte tag numbers are not used by Kerberos
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+
+    let first_length = *data.get(1)?;
+    let (length, header_length) = if first_length & 0x80 == 0 {
+        (first_length as usize, 2)
+    } else {
+        let length_bytes = (first_length & 0x7f) as usize;
+        if length_bytes == 0 || length_bytes > 4 {
+            return None;
+        }
+
+        let length = data
+            .get(2..2 + length_bytes)?
+            .iter()
+            .fold(0usize, |length, byte| (length << 8) | *byte as usize);
+        (length, 2 + length_bytes)
+    };
+
+    let value_end = data.len().min(header_length + length);
+    Some((tag, &data[header_length..value_end], &data[value_end..]))
+}
+
+/// Value of the TLV with the expected tag
+fn read_expected(data: &[u8], expected_tag: u8) -> Option<&[u8]> {
+    match read_tlv(data)? {
+        (tag, value, _) if tag == expected_tag => Some(value),
+        _ => None,
+    }
+}
+
+/// Context-specific tagged fields of a SEQUENCE, by tag number
+fn read_fields(mut data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut fields = vec![];
+
+    while let Some((tag, value, rest)) = read_tlv(data) {
+        if tag & 0xe0 == DerTags::CONTEXT {
+            fields.push((tag & 0x1f, value));
+        }
+        data = rest;
+    }
+
+    fields
+}
+
+fn get_field<'a>(fields: &[(u8, &'a [u8])], number: u8) -> Option<&'a [u8]> {
+    fields
+        .iter()
+        .find(|(field_number, _)| *field_number == number)
+        .map(|(_, value)| *value)
+}
+
+fn read_integer(data: &[u8]) -> Option<i32> {
+    let value = read_expected(data, DerTags::INTEGER)?;
+    if value.is_empty() || value.len() > 4 {
+        return None;
+    }
+
+    // Sign extension of the two's complement value
+    let initial = if value[0] & 0x80 != 0 { -1 } else { 0 };
+    Some(
+        value
+            .iter()
+            .fold(initial, |integer, byte| (integer << 8) | *byte as i32),
+    )
+}
+
+fn read_integers(mut data: &[u8]) -> Vec<i32> {
+    let mut integers = vec![];
+
+    while !data.is_empty() {
+        match (read_integer(data), read_tlv(data)) {
+            (Some(integer), Some((_, _, rest))) => {
+                integers.push(integer);
+                data = rest;
+            }
+            _ => break,
+        }
+    }
+
+    integers
+}
+
+fn read_string(data: &[u8]) -> Option<String> {
+    read_expected(data, DerTags::GENERAL_STRING)
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+}
+
+/// PrincipalName ::= SEQUENCE { name-type [0] Int32, name-string [1] SEQUENCE OF KerberosString },
+/// with the components joined by `/` (e.g. `krbtgt/EXAMPLE.COM`)
+fn read_principal_name(data: &[u8]) -> Option<String> {
+    let fields = read_fields(read_expected(data, DerTags::SEQUENCE)?);
+    let mut components = read_expected(get_field(&fields, 1)?, DerTags::SEQUENCE)?;
+
+    let mut name = vec![];
+    while let Some((_, _, rest)) = read_tlv(components) {
+        name.push(read_string(components)?);
+        components = rest;
+    }
+
+    Some(name.join("/"))
+}
+
+/// Types of the PA-DATA ::= SEQUENCE { padata-type [1] Int32, padata-value [2] OCTET STRING }
+fn read_padata_types(data: &[u8]) -> Vec<i32> {
+    let mut padata = match read_expected(data, DerTags::SEQUENCE) {
+        Some(padata) => padata,
+        None => return vec![],
+    };
+
+    let mut types = vec![];
+    while let Some((_, value, rest)) = read_tlv(padata) {
+        if let Some(padata_type) = get_field(&read_fields(value), 1).and_then(read_integer) {
+            types.push(padata_type);
+        }
+        padata = rest;
+    }
+
+    types
+}
+
+fn get_message_type_name(message_type: u8) -> &'static str {
+    match message_type {
+        KerberosMessageTypes::AS_REQ => "AS-REQ",
+        KerberosMessageTypes::AS_REP => "AS-REP",
+        KerberosMessageTypes::TGS_REQ => "TGS-REQ",
+        KerberosMessageTypes::TGS_REP => "TGS-REP",
+        KerberosMessageTypes::AP_REQ => "AP-REQ",
+        KerberosMessageTypes::AP_REP => "AP-REP",
+        KerberosMessageTypes::KRB_ERROR => "KRB-ERROR",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Names of the most common error codes
+fn get_error_name(error_code: i32) -> Option<&'static str> {
+    let name = match error_code {
+        6 => "KDC_ERR_C_PRINCIPAL_UNKNOWN",
+        7 => "KDC_ERR_S_PRINCIPAL_UNKNOWN",
+        12 => "KDC_ERR_POLICY",
+        13 => "KDC_ERR_BADOPTION",
+        14 => "KDC_ERR_ETYPE_NOSUPP",
+        18 => "KDC_ERR_CLIENT_REVOKED",
+        23 => "KDC_ERR_KEY_EXPIRED",
+        24 => "KDC_ERR_PREAUTH_FAILED",
+        25 => "KDC_ERR_PREAUTH_REQUIRED",
+        31 => "KRB_AP_ERR_BAD_INTEGRITY",
+        32 => "KRB_AP_ERR_TKT_EXPIRED",
+        37 => "KRB_AP_ERR_SKEW",
+        41 => "KRB_AP_ERR_MODIFIED",
+        52 => "KRB_ERR_RESPONSE_TOO_BIG",
+        60 => "KRB_ERR_GENERIC",
+        68 => "KDC_ERR_WRONG_REALM",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::handle_kerberos_packet;
+
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut tlv = vec![tag];
+        if value.len() < 0x80 {
+            tlv.push(value.len() as u8);
+        } else {
+            tlv.push(0x82);
+            tlv.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        }
+        tlv.extend_from_slice(value);
+        tlv
+    }
+
+    fn field(number: u8, value: &[u8]) -> Vec<u8> {
+        tlv(0xa0 | number, value)
+    }
+
+    fn integer(value: u8) -> Vec<u8> {
+        tlv(0x02, &[value])
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        tlv(0x1b, value.as_bytes())
+    }
+
+    fn principal_name(name_type: u8, components: &[&str]) -> Vec<u8> {
+        let components: Vec<u8> = components.iter().flat_map(|c| string(c)).collect();
+        tlv(
+            0x30,
+            &[
+                field(0, &integer(name_type)),
+                field(1, &tlv(0x30, &components)),
+            ]
+            .concat(),
+        )
+    }
+
+    fn parse(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_kerberos_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            50000,
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            88,
+            payload,
+            &mut parsed_packet,
+        );
+
+        parsed_packet
+    }
+
+    fn build_as_req() -> Vec<u8> {
+        let padata = tlv(
+            0x30,
+            &tlv(
+                0x30,
+                &[field(1, &integer(2)), field(2, &tlv(0x04, &[0x30, 0x00]))].concat(),
+            ),
+        );
+
+        let request_body = tlv(
+            0x30,
+            &[
+                field(0, &tlv(0x03, &[0, 0x40, 0x81, 0, 0x10])),
+                field(1, &principal_name(1, &["alice"])),
+                field(2, &string("EXAMPLE.COM")),
+                field(3, &principal_name(2, &["krbtgt", "EXAMPLE.COM"])),
+                field(7, &integer(42)),
+                field(8, &tlv(0x30, &[integer(18), integer(17)].concat())),
+            ]
+            .concat(),
+        );
+
+        tlv(
+            0x6a,
+            &tlv(
+                0x30,
+                &[
+                    field(1, &integer(5)),
+                    field(2, &integer(10)),
+                    field(3, &padata),
+                    field(4, &request_body),
+                ]
+                .concat(),
+            ),
+        )
+    }
+
+    #[test]
+    fn kerberos_as_req() {
+        let message = build_as_req();
+
+        // Over TCP, with the record mark
+        let mut tcp_payload = (message.len() as u32).to_be_bytes().to_vec();
+        tcp_payload.extend_from_slice(&message);
+
+        for payload in [message.clone(), tcp_payload] {
+            let parsed_packet = parse(&payload);
+
+            match parsed_packet.get_application_layer_packet().unwrap() {
+                SerializablePacket::KerberosPacket(kerberos_packet) => {
+                    assert_eq!(kerberos_packet.message_type_name, "AS-REQ");
+                    assert_eq!(kerberos_packet.realm.as_deref(), Some("EXAMPLE.COM"));
+                    assert_eq!(kerberos_packet.client_name.as_deref(), Some("alice"));
+                    assert_eq!(
+                        kerberos_packet.server_name.as_deref(),
+                        Some("krbtgt/EXAMPLE.COM")
+                    );
+                    assert_eq!(kerberos_packet.encryption_types, vec![18, 17]);
+                    assert_eq!(kerberos_packet.padata_types, vec![2]);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn kerberos_error() {
+        let error = tlv(
+            0x7e,
+            &tlv(
+                0x30,
+                &[
+                    field(0, &integer(5)),
+                    field(1, &integer(30)),
+                    field(4, &tlv(0x18, b"20230101000000Z")),
+                    field(5, &integer(0)),
+                    field(6, &integer(25)),
+                    field(9, &string("EXAMPLE.COM")),
+                    field(10, &principal_name(2, &["krbtgt", "EXAMPLE.COM"])),
+                    field(11, &string("Additional pre-authentication required")),
+                ]
+                .concat(),
+            ),
+        );
+
+        match parse(&error).get_application_layer_packet().unwrap() {
+            SerializablePacket::KerberosPacket(kerberos_packet) => {
+                assert_eq!(kerberos_packet.message_type_name, "KRB-ERROR");
+                assert_eq!(kerberos_packet.error_code, Some(25));
+                assert_eq!(
+                    kerberos_packet.error_name.as_deref(),
+                    Some("KDC_ERR_PREAUTH_REQUIRED")
+                );
+                assert_eq!(kerberos_packet.realm.as_deref(), Some("EXAMPLE.COM"));
+                assert_eq!(kerberos_packet.client_name, None);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn kerberos_segments() {
+        // Pure ACK and continuation of a split message
+        assert!(parse(&[]).get_application_layer_packet().is_none());
+        assert!(parse(&[0x04, 0x11, 0x22, 0x33, 0x44, 0x55])
+            .get_application_layer_packet()
+            .is_none());
+
+        // First segment of a message split over TCP
+        let message = build_as_req();
+        let mut tcp_payload = (message.len() as u32).to_be_bytes().to_vec();
+        tcp_payload.extend_from_slice(&message[..message.len() - 12]);
+
+        match parse(&tcp_payload).get_application_layer_packet().unwrap() {
+            SerializablePacket::KerberosPacket(kerberos_packet) => {
+                assert_eq!(kerberos_packet.message_type_name, "AS-REQ");
+                assert_eq!(kerberos_packet.client_name.as_deref(), Some("alice"));
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,30 +1,87 @@
 //! Application layer Packet parsing
 
-use std::{cell::RefCell, collections::HashMap, net::IpAddr};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+};
 
 use crate::serializable_packet::ParsedPacket;
 
-use self::{dns::handle_dns_packet, http::handle_http_packet, tls::handle_tls_packet};
+use self::{
+    bittorrent::handle_bittorrent_packet,
+    dhcpv6::handle_dhcpv6_packet,
+    dns::handle_dns_packet,
+    http::handle_http_packet,
+    http2::{handle_http2_packet, is_http2_connection, Http2Connections},
+    kerberos::handle_kerberos_packet,
+    radius::handle_radius_packet,
+    sip::handle_sip_packet,
+    tls::handle_tls_packet,
+};
 
+pub mod bittorrent;
+pub mod credentials;
+pub mod dhcpv6;
 pub mod dns;
+pub mod hpack;
 pub mod http;
+pub mod http2;
+pub mod ja3;
+pub mod kerberos;
+pub mod radius;
+pub mod sip;
 pub mod tls;
 
 thread_local!(
     pub(crate) static ACTIVE_HTTP_PARSERS: RefCell<
         HashMap<((IpAddr, u16), (IpAddr, u16)), Vec<u8>>,
     > = RefCell::new(HashMap::new());
+    pub(crate) static ACTIVE_HTTP2_CONNECTIONS: RefCell<Http2Connections> =
+        RefCell::new(HashMap::new());
     pub(crate) static ACTIVE_TLS_PARSERS: RefCell<
         HashMap<((IpAddr, u16), (IpAddr, u16)), Vec<u8>>,
     > = RefCell::new(HashMap::new());
+    static ENABLED_DISSECTORS: RefCell<HashSet<String>> =
+        RefCell::new(Dissectors::ALL.iter().map(|d| d.to_string()).collect());
 );
 
+/// Application layer dissectors that can be enabled or disabled
+#[allow(non_snake_case)]
+pub mod Dissectors {
+    pub const HTTP: &str = "http";
+    pub const TLS: &str = "tls";
+    pub const DNS: &str = "dns";
+    pub const SIP: &str = "sip";
+    pub const DHCPV6: &str = "dhcpv6";
+    pub const KERBEROS: &str = "kerberos";
+    pub const RADIUS: &str = "radius";
+    pub const BITTORRENT: &str = "bittorrent";
+
+    pub const ALL: [&str; 8] = [HTTP, TLS, DNS, SIP, DHCPV6, KERBEROS, RADIUS, BITTORRENT];
+}
+
+/// Set the application layer dissectors used by the current thread, every other application protocol is left unparsed
+pub fn set_enabled_dissectors(enabled: &HashSet<String>) {
+    ENABLED_DISSECTORS.with(|dissectors| *dissectors.borrow_mut() = enabled.clone());
+}
+
+fn is_dissector_enabled(name: &str) -> bool {
+    ENABLED_DISSECTORS.with(|dissectors| dissectors.borrow().contains(name))
+}
+
 /// IANA Well Known TCP/UDP Ports
 #[allow(non_snake_case)]
 mod WellKnownPorts {
     pub const HTTP_PORT: u16 = 80;
     pub const TLS_PORT: u16 = 443;
     pub const DNS_PORT: u16 = 53;
+    pub const SIP_PORT: u16 = 5060;
+    pub const DHCPV6_CLIENT_PORT: u16 = 546;
+    pub const DHCPV6_SERVER_PORT: u16 = 547;
+    pub const KERBEROS_PORT: u16 = 88;
+    pub const RADIUS_AUTHENTICATION_PORT: u16 = 1812;
+    pub const RADIUS_ACCOUNTING_PORT: u16 = 1813;
 }
 
 // HTTP ----------------------------------------------------------------------------------------------------------------
@@ -64,7 +121,22 @@ pub fn handle_application_protocol(
     parsed_packet: &mut ParsedPacket,
 ) {
     match (source_port, dest_port) {
-        (WellKnownPorts::HTTP_PORT, _) | (_, WellKnownPorts::HTTP_PORT) => {
+        (WellKnownPorts::HTTP_PORT, _) | (_, WellKnownPorts::HTTP_PORT)
+            if is_dissector_enabled(Dissectors::HTTP)
+                && is_http2_connection(source_ip, source_port, dest_ip, dest_port, packet) =>
+        {
+            handle_http2_packet(
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                packet,
+                parsed_packet,
+            )
+        }
+        (WellKnownPorts::HTTP_PORT, _) | (_, WellKnownPorts::HTTP_PORT)
+            if is_dissector_enabled(Dissectors::HTTP) =>
+        {
             let http_type = match dest_port {
                 WellKnownPorts::HTTP_PORT => HttpPacketType::Request,
                 _ => HttpPacketType::Response,
@@ -81,7 +153,75 @@ pub fn handle_application_protocol(
                 parsed_packet,
             )
         }
-        (WellKnownPorts::TLS_PORT, _) | (_, WellKnownPorts::TLS_PORT) => handle_tls_packet(
+        (WellKnownPorts::TLS_PORT, _) | (_, WellKnownPorts::TLS_PORT)
+            if is_dissector_enabled(Dissectors::TLS) =>
+        {
+            handle_tls_packet(
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                packet,
+                parsed_packet,
+            )
+        }
+        (WellKnownPorts::DNS_PORT, _) | (_, WellKnownPorts::DNS_PORT)
+            if is_dissector_enabled(Dissectors::DNS) =>
+        {
+            handle_dns_packet(
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                packet,
+                parsed_packet,
+            )
+        }
+        (WellKnownPorts::SIP_PORT, _) | (_, WellKnownPorts::SIP_PORT)
+            if is_dissector_enabled(Dissectors::SIP) =>
+        {
+            handle_sip_packet(
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                packet,
+                parsed_packet,
+            )
+        }
+        (WellKnownPorts::DHCPV6_CLIENT_PORT | WellKnownPorts::DHCPV6_SERVER_PORT, _)
+        | (_, WellKnownPorts::DHCPV6_CLIENT_PORT | WellKnownPorts::DHCPV6_SERVER_PORT)
+            if is_dissector_enabled(Dissectors::DHCPV6) =>
+        {
+            handle_dhcpv6_packet(
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                packet,
+                parsed_packet,
+            )
+        }
+        (WellKnownPorts::KERBEROS_PORT, _) | (_, WellKnownPorts::KERBEROS_PORT)
+            if is_dissector_enabled(Dissectors::KERBEROS) =>
+        {
+            handle_kerberos_packet(
+                source_ip,
+                source_port,
+                dest_ip,
+                dest_port,
+                packet,
+                parsed_packet,
+            )
+        }
+        (
+            WellKnownPorts::RADIUS_AUTHENTICATION_PORT | WellKnownPorts::RADIUS_ACCOUNTING_PORT,
+            _,
+        )
+        | (
+            _,
+            WellKnownPorts::RADIUS_AUTHENTICATION_PORT | WellKnownPorts::RADIUS_ACCOUNTING_PORT,
+        ) if is_dissector_enabled(Dissectors::RADIUS) => handle_radius_packet(
             source_ip,
             source_port,
             dest_ip,
@@ -89,7 +229,8 @@ pub fn handle_application_protocol(
             packet,
             parsed_packet,
         ),
-        (WellKnownPorts::DNS_PORT, _) | (_, WellKnownPorts::DNS_PORT) => handle_dns_packet(
+        // BitTorrent uses no fixed port, recognized by the content
+        _ if is_dissector_enabled(Dissectors::BITTORRENT) => handle_bittorrent_packet(
             source_ip,
             source_port,
             dest_ip,
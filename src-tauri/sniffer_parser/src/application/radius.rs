@@ -0,0 +1,282 @@
+//! RADIUS Packet parsing
+//!
+//! Authentication (port 1812) and accounting (port 1813) packets (RFC 2865, RFC 2866) are a header
+//! followed by type-length-value attributes. Only the cleartext attributes are decoded: the hidden ones
+//! (e.g. User-Password) can't be read without the shared secret.
+
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr};
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::SerializableRadiusPacket, ParsedPacket, SerializablePacket,
+};
+
+/// RADIUS packet codes
+#[allow(non_snake_case)]
+mod RadiusCodes {
+    pub const ACCESS_REQUEST: u8 = 1;
+    pub const ACCESS_ACCEPT: u8 = 2;
+    pub const ACCESS_REJECT: u8 = 3;
+    pub const ACCOUNTING_REQUEST: u8 = 4;
+    pub const ACCOUNTING_RESPONSE: u8 = 5;
+    pub const ACCESS_CHALLENGE: u8 = 11;
+    pub const STATUS_SERVER: u8 = 12;
+    pub const STATUS_CLIENT: u8 = 13;
+    pub const DISCONNECT_REQUEST: u8 = 40;
+    pub const DISCONNECT_ACK: u8 = 41;
+    pub const DISCONNECT_NAK: u8 = 42;
+    pub const COA_REQUEST: u8 = 43;
+    pub const COA_ACK: u8 = 44;
+    pub const COA_NAK: u8 = 45;
+}
+
+/// RADIUS attribute types
+#[allow(non_snake_case)]
+mod RadiusAttributes {
+    pub const USER_NAME: u8 = 1;
+    pub const NAS_IP_ADDRESS: u8 = 4;
+    pub const FRAMED_IP_ADDRESS: u8 = 8;
+    pub const REPLY_MESSAGE: u8 = 18;
+    pub const CALLED_STATION_ID: u8 = 30;
+    pub const CALLING_STATION_ID: u8 = 31;
+    pub const NAS_IDENTIFIER: u8 = 32;
+    pub const ACCT_STATUS_TYPE: u8 = 40;
+    pub const ACCT_SESSION_ID: u8 = 44;
+}
+
+/// Length of the code, identifier, length and authenticator fields
+const HEADER_LENGTH: usize = 20;
+
+/// Build a RADIUS packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_radius_packet(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    if let Some(radius_packet) = parse_radius_packet(packet) {
+        debug!(
+            "RADIUS Packet: {}:{} > {}:{}; Code: {}, Identifier: {}, User: {:?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            radius_packet.code_name,
+            radius_packet.identifier,
+            radius_packet.user_name,
+        );
+
+        parsed_packet
+            .set_application_layer_packet(Some(SerializablePacket::RadiusPacket(radius_packet)));
+    } else {
+        debug!("Malformed RADIUS Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed RADIUS Packet".to_string(),
+        )));
+    }
+}
+
+fn parse_radius_packet(packet: &[u8]) -> Option<SerializableRadiusPacket> {
+    let header = packet.get(..HEADER_LENGTH)?;
+    let length = u16::from_be_bytes([header[2], header[3]]);
+    // Octets past the length field are padding, to be ignored
+    let attributes = packet.get(HEADER_LENGTH..length as usize)?;
+
+    let mut radius_packet = SerializableRadiusPacket {
+        code: header[0],
+        code_name: get_code_name(header[0]).to_owned(),
+        identifier: header[1],
+        length,
+        authenticator: header[4..HEADER_LENGTH]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+        ..Default::default()
+    };
+
+    for (attribute_type, value) in split_attributes(attributes)? {
+        radius_packet.attributes.push(attribute_type);
+
+        let text = || String::from_utf8_lossy(value).to_string();
+        match attribute_type {
+            RadiusAttributes::USER_NAME => radius_packet.user_name = Some(text()),
+            RadiusAttributes::NAS_IP_ADDRESS => {
+                radius_packet.nas_ip_address = Some(read_ipv4(value)?)
+            }
+            RadiusAttributes::FRAMED_IP_ADDRESS => {
+                radius_packet.framed_ip_address = Some(read_ipv4(value)?)
+            }
+            RadiusAttributes::REPLY_MESSAGE => radius_packet.reply_messages.push(text()),
+            RadiusAttributes::CALLED_STATION_ID => radius_packet.called_station_id = Some(text()),
+            RadiusAttributes::CALLING_STATION_ID => radius_packet.calling_station_id = Some(text()),
+            RadiusAttributes::NAS_IDENTIFIER => radius_packet.nas_identifier = Some(text()),
+            RadiusAttributes::ACCT_STATUS_TYPE => {
+                radius_packet.acct_status_type = Some(u32::from_be_bytes(value.try_into().ok()?))
+            }
+            RadiusAttributes::ACCT_SESSION_ID => radius_packet.acct_session_id = Some(text()),
+            _ => (),
+        }
+    }
+
+    Some(radius_packet)
+}
+
+/// Split a sequence of attributes in type and value, `None` if an attribute exceeds the buffer
+fn split_attributes(mut attributes: &[u8]) -> Option<Vec<(u8, &[u8])>> {
+    let mut result = vec![];
+
+    while !attributes.is_empty() {
+        let attribute_type = *attributes.first()?;
+        // Including the type and length fields
+        let length = *attributes.get(1)? as usize;
+        if length < 2 {
+            return None;
+        }
+
+        result.push((attribute_type, attributes.get(2..length)?));
+        attributes = &attributes[length..];
+    }
+
+    Some(result)
+}
+
+fn read_ipv4(data: &[u8]) -> Option<Ipv4Addr> {
+    let octets: [u8; 4] = data.try_into().ok()?;
+    Some(Ipv4Addr::from(octets))
+}
+
+fn get_code_name(code: u8) -> &'static str {
+    match code {
+        RadiusCodes::ACCESS_REQUEST => "Access-Request",
+        RadiusCodes::ACCESS_ACCEPT => "Access-Accept",
+        RadiusCodes::ACCESS_REJECT => "Access-Reject",
+        RadiusCodes::ACCOUNTING_REQUEST => "Accounting-Request",
+        RadiusCodes::ACCOUNTING_RESPONSE => "Accounting-Response",
+        RadiusCodes::ACCESS_CHALLENGE => "Access-Challenge",
+        RadiusCodes::STATUS_SERVER => "Status-Server",
+        RadiusCodes::STATUS_CLIENT => "Status-Client",
+        RadiusCodes::DISCONNECT_REQUEST => "Disconnect-Request",
+        RadiusCodes::DISCONNECT_ACK => "Disconnect-ACK",
+        RadiusCodes::DISCONNECT_NAK => "Disconnect-NAK",
+        RadiusCodes::COA_REQUEST => "CoA-Request",
+        RadiusCodes::COA_ACK => "CoA-ACK",
+        RadiusCodes::COA_NAK => "CoA-NAK",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::handle_radius_packet;
+
+    fn attribute(attribute_type: u8, value: &[u8]) -> Vec<u8> {
+        let mut attribute = vec![attribute_type, value.len() as u8 + 2];
+        attribute.extend_from_slice(value);
+        attribute
+    }
+
+    fn build_packet(code: u8, attributes: &[Vec<u8>]) -> Vec<u8> {
+        let attributes = attributes.concat();
+        let mut packet = vec![code, 7];
+        packet.extend_from_slice(&(20 + attributes.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0xab; 16]);
+        packet.extend(attributes);
+        packet
+    }
+
+    fn parse(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_radius_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            50000,
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            1812,
+            payload,
+            &mut parsed_packet,
+        );
+
+        parsed_packet
+    }
+
+    #[test]
+    fn radius_access_request() {
+        let mut packet = build_packet(
+            1,
+            &[
+                attribute(1, b"alice"),
+                // User-Password, hidden
+                attribute(2, &[0x42; 16]),
+                attribute(4, &[10, 0, 0, 2]),
+                attribute(31, b"02-00-00-00-00-01"),
+                attribute(8, &[192, 168, 1, 10]),
+            ],
+        );
+        // Padding
+        packet.extend_from_slice(&[0, 0]);
+
+        match parse(&packet).get_application_layer_packet().unwrap() {
+            SerializablePacket::RadiusPacket(radius_packet) => {
+                assert_eq!(radius_packet.code_name, "Access-Request");
+                assert_eq!(radius_packet.identifier, 7);
+                assert_eq!(radius_packet.length as usize, packet.len() - 2);
+                assert_eq!(radius_packet.authenticator, "ab".repeat(16));
+                assert_eq!(radius_packet.user_name.as_deref(), Some("alice"));
+                assert_eq!(
+                    radius_packet.nas_ip_address,
+                    Some(Ipv4Addr::new(10, 0, 0, 2))
+                );
+                assert_eq!(
+                    radius_packet.framed_ip_address,
+                    Some(Ipv4Addr::new(192, 168, 1, 10))
+                );
+                assert_eq!(
+                    radius_packet.calling_station_id.as_deref(),
+                    Some("02-00-00-00-00-01")
+                );
+                assert_eq!(radius_packet.attributes, vec![1, 2, 4, 31, 8]);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn radius_accounting_request() {
+        let packet = build_packet(
+            4,
+            &[
+                attribute(40, &1u32.to_be_bytes()),
+                attribute(44, b"session-1"),
+            ],
+        );
+
+        match parse(&packet).get_application_layer_packet().unwrap() {
+            SerializablePacket::RadiusPacket(radius_packet) => {
+                assert_eq!(radius_packet.code_name, "Accounting-Request");
+                assert_eq!(radius_packet.acct_status_type, Some(1));
+                assert_eq!(radius_packet.acct_session_id.as_deref(), Some("session-1"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_radius() {
+        let mut packet = build_packet(2, &[attribute(18, b"Welcome")]);
+        // Attribute exceeding the packet length
+        packet[21] = 20;
+
+        assert!(matches!(
+            parse(&packet).get_application_layer_packet().unwrap(),
+            SerializablePacket::MalformedPacket(_)
+        ));
+    }
+}
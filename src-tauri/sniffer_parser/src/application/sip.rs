@@ -0,0 +1,308 @@
+//! SIP Packet parsing
+
+use std::net::IpAddr;
+use std::str::from_utf8;
+
+use log::debug;
+
+use crate::serializable_packet::{
+    application::{SdpMediaDescription, SerializableSdpSession, SerializableSipPacket},
+    ParsedPacket, SerializablePacket,
+};
+
+/// SIP protocol version supported by the parser
+const SIP_VERSION: &str = "SIP/2.0";
+
+/// SIP String representation for Header names, both in full and compact form
+#[allow(non_snake_case)]
+mod SipHeaderNames {
+    pub const FROM: (&str, &str) = ("From", "f");
+    pub const TO: (&str, &str) = ("To", "t");
+    pub const CALL_ID: (&str, &str) = ("Call-ID", "i");
+    pub const VIA: (&str, &str) = ("Via", "v");
+    pub const CONTENT_TYPE: (&str, &str) = ("Content-Type", "c");
+    pub const CSEQ: &str = "CSeq";
+}
+
+/// Content type of SDP bodies
+const SDP_CONTENT_TYPE: &str = "application/sdp";
+
+/// Build a SIP packet from a transport-layer packet, save it in a Parsed Packet
+pub fn handle_sip_packet(
+    source_ip: IpAddr,
+    source_port: u16,
+    dest_ip: IpAddr,
+    dest_port: u16,
+    packet: &[u8],
+    parsed_packet: &mut ParsedPacket,
+) {
+    // Empty segments (e.g. pure TCP ACKs) and CRLF keep-alives carry no SIP message
+    if packet.iter().all(|byte| byte.is_ascii_whitespace()) {
+        return;
+    }
+
+    if let Some(sip_packet) = parse_sip_message(packet) {
+        debug!(
+            "SIP Packet: {}:{} > {}:{}; Method: {:?}, Status: {:?}, Call-ID: {:?}, CSeq: {:?}",
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            sip_packet.method,
+            sip_packet.status_code,
+            sip_packet.call_id,
+            sip_packet.cseq,
+        );
+
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::SipPacket(sip_packet)));
+    } else {
+        debug!("Malformed SIP Packet");
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed SIP Packet".to_string(),
+        )));
+    }
+}
+
+/// Parse a full SIP message (start line, headers and optional SDP body)
+fn parse_sip_message(packet: &[u8]) -> Option<SerializableSipPacket> {
+    let message = from_utf8(packet).ok()?;
+
+    let (head, body) = match message.find("\r\n\r\n") {
+        Some(index) => (&message[..index], &message[index + 4..]),
+        None => (message, ""),
+    };
+
+    let mut lines = head.lines();
+    let mut sip_packet = SerializableSipPacket::default();
+
+    // Start line: either "<METHOD> <Request-URI> SIP/2.0" or "SIP/2.0 <Code> <Reason>"
+    let start_line = lines.next()?.trim();
+    if let Some(status) = start_line.strip_prefix(SIP_VERSION) {
+        let mut status = status.trim_start().splitn(2, ' ');
+        sip_packet.status_code = Some(status.next()?.parse().ok()?);
+        sip_packet.reason = status.next().map(|reason| reason.to_owned());
+    } else {
+        let mut request = start_line.split_whitespace();
+        let method = request.next()?;
+        let request_uri = request.next()?;
+
+        if request.next()? != SIP_VERSION || !method.chars().all(|c| c.is_ascii_uppercase()) {
+            return None;
+        }
+
+        sip_packet.method = Some(method.to_owned());
+        sip_packet.request_uri = Some(request_uri.to_owned());
+    }
+
+    for line in lines {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim().to_owned()),
+            None => continue,
+        };
+
+        if header_matches(name, SipHeaderNames::FROM) {
+            sip_packet.from = Some(value.clone());
+        } else if header_matches(name, SipHeaderNames::TO) {
+            sip_packet.to = Some(value.clone());
+        } else if header_matches(name, SipHeaderNames::CALL_ID) {
+            sip_packet.call_id = Some(value.clone());
+        } else if header_matches(name, SipHeaderNames::VIA) {
+            sip_packet.via.push(value.clone());
+        } else if name.eq_ignore_ascii_case(SipHeaderNames::CSEQ) {
+            sip_packet.cseq = Some(value.clone());
+        }
+
+        sip_packet.headers.push((name.to_owned(), value));
+    }
+
+    let is_sdp = sip_packet.headers.iter().any(|(name, value)| {
+        header_matches(name, SipHeaderNames::CONTENT_TYPE)
+            && value.to_ascii_lowercase().starts_with(SDP_CONTENT_TYPE)
+    });
+
+    if is_sdp && !body.is_empty() {
+        sip_packet.sdp = Some(parse_sdp_body(body));
+    }
+
+    Some(sip_packet)
+}
+
+/// Check if a header name matches either the full or the compact form (case insensitive)
+fn header_matches(name: &str, (full, compact): (&str, &str)) -> bool {
+    name.eq_ignore_ascii_case(full) || name.eq_ignore_ascii_case(compact)
+}
+
+/// Extract the connection address and the negotiated media streams from an SDP body
+fn parse_sdp_body(body: &str) -> SerializableSdpSession {
+    let mut session = SerializableSdpSession::default();
+
+    for line in body.lines() {
+        let (kind, value) = match line.trim().split_once('=') {
+            Some(field) => field,
+            None => continue,
+        };
+
+        match kind {
+            // c=<nettype> <addrtype> <connection-address>
+            "c" => {
+                let address = value.split_whitespace().nth(2).map(|a| a.to_owned());
+                match session.media.last_mut() {
+                    Some(media) => media.connection_address = address,
+                    None => session.connection_address = address,
+                }
+            }
+            // m=<media> <port>[/<number of ports>] <proto> <fmt> ...
+            "m" => {
+                let mut fields = value.split_whitespace();
+                let media_type = fields.next();
+                let port = fields
+                    .next()
+                    .and_then(|port| port.split('/').next())
+                    .and_then(|port| port.parse::<u16>().ok());
+
+                if let (Some(media_type), Some(port)) = (media_type, port) {
+                    session.media.push(SdpMediaDescription {
+                        media_type: media_type.to_owned(),
+                        port,
+                        protocol: fields.next().unwrap_or_default().to_owned(),
+                        formats: fields.map(|f| f.to_owned()).collect(),
+                        connection_address: None,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    session
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::handle_sip_packet;
+
+    const INVITE: &str = "INVITE sip:bob@biloxi.example.com SIP/2.0\r\n\
+        Via: SIP/2.0/UDP pc33.atlanta.example.com;branch=z9hG4bK776asdhds\r\n\
+        Max-Forwards: 70\r\n\
+        To: Bob <sip:bob@biloxi.example.com>\r\n\
+        From: Alice <sip:alice@atlanta.example.com>;tag=1928301774\r\n\
+        Call-ID: a84b4c76e66710@pc33.atlanta.example.com\r\n\
+        CSeq: 314159 INVITE\r\n\
+        Content-Type: application/sdp\r\n\
+        \r\n\
+        v=0\r\n\
+        o=alice 2890844526 2890844526 IN IP4 pc33.atlanta.example.com\r\n\
+        s=-\r\n\
+        c=IN IP4 192.0.2.101\r\n\
+        t=0 0\r\n\
+        m=audio 49172 RTP/AVP 0 8\r\n\
+        m=video 51372/2 RTP/AVP 31\r\n\
+        c=IN IP4 192.0.2.102\r\n";
+
+    const OK_RESPONSE: &str = "SIP/2.0 200 OK\r\n\
+        v: SIP/2.0/UDP server10.biloxi.example.com;branch=z9hG4bKnashds8\r\n\
+        v: SIP/2.0/UDP pc33.atlanta.example.com;branch=z9hG4bK776asdhds\r\n\
+        t: Bob <sip:bob@biloxi.example.com>;tag=a6c85cf\r\n\
+        f: Alice <sip:alice@atlanta.example.com>;tag=1928301774\r\n\
+        i: a84b4c76e66710@pc33.atlanta.example.com\r\n\
+        CSeq: 314159 INVITE\r\n\
+        l: 0\r\n\
+        \r\n";
+
+    fn parse(payload: &[u8]) -> ParsedPacket {
+        let mut parsed_packet = ParsedPacket::new(0);
+
+        handle_sip_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            5060,
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            5060,
+            payload,
+            &mut parsed_packet,
+        );
+
+        parsed_packet
+    }
+
+    #[test]
+    fn sip_invite_with_sdp() {
+        let parsed_packet = parse(INVITE.as_bytes());
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::SipPacket(sip_packet) => {
+                assert_eq!(sip_packet.method.as_deref(), Some("INVITE"));
+                assert_eq!(
+                    sip_packet.request_uri.as_deref(),
+                    Some("sip:bob@biloxi.example.com")
+                );
+                assert_eq!(sip_packet.status_code, None);
+                assert_eq!(
+                    sip_packet.call_id.as_deref(),
+                    Some("a84b4c76e66710@pc33.atlanta.example.com")
+                );
+                assert_eq!(sip_packet.cseq.as_deref(), Some("314159 INVITE"));
+                assert_eq!(
+                    sip_packet.from.as_deref(),
+                    Some("Alice <sip:alice@atlanta.example.com>;tag=1928301774")
+                );
+                assert_eq!(
+                    sip_packet.to.as_deref(),
+                    Some("Bob <sip:bob@biloxi.example.com>")
+                );
+                assert_eq!(sip_packet.via.len(), 1);
+
+                let sdp = sip_packet.sdp.as_ref().unwrap();
+                assert_eq!(sdp.connection_address.as_deref(), Some("192.0.2.101"));
+                assert_eq!(sdp.media.len(), 2);
+
+                assert_eq!(sdp.media[0].media_type, "audio");
+                assert_eq!(sdp.media[0].port, 49172);
+                assert_eq!(sdp.media[0].protocol, "RTP/AVP");
+                assert_eq!(sdp.media[0].formats, vec!["0", "8"]);
+                assert_eq!(sdp.media[0].connection_address, None);
+
+                assert_eq!(sdp.media[1].media_type, "video");
+                assert_eq!(sdp.media[1].port, 51372);
+                assert_eq!(
+                    sdp.media[1].connection_address.as_deref(),
+                    Some("192.0.2.102")
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn sip_response_with_compact_headers() {
+        let parsed_packet = parse(OK_RESPONSE.as_bytes());
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::SipPacket(sip_packet) => {
+                assert_eq!(sip_packet.method, None);
+                assert_eq!(sip_packet.status_code, Some(200));
+                assert_eq!(sip_packet.reason.as_deref(), Some("OK"));
+                assert_eq!(
+                    sip_packet.call_id.as_deref(),
+                    Some("a84b4c76e66710@pc33.atlanta.example.com")
+                );
+                assert_eq!(sip_packet.via.len(), 2);
+                assert!(sip_packet.sdp.is_none());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_sip_packet() {
+        let parsed_packet = parse(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n");
+
+        match parsed_packet.get_application_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(_) => (),
+            _ => unreachable!(),
+        }
+    }
+}
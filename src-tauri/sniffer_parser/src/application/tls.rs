@@ -40,8 +40,8 @@ pub fn handle_tls_packet(
                 Ok((rem, record)) => {
                     for (i, msg) in record.msg.iter().enumerate() {
                         debug!(
-                            "[{i}]: TLS Record Packet: {}:{} > {}:{}; Version: {}, Record Type: {:?}, Len: {}, Payload: {:?}",
-                            source_ip, source_port, dest_ip, dest_port, record.hdr.version, record.hdr.record_type, record.hdr.len, msg
+                            "[{}]: TLS Record Packet: {}:{} > {}:{}; Version: {}, Record Type: {:?}, Len: {}, Payload: {:?}",
+                            i, source_ip, source_port, dest_ip, dest_port, record.hdr.version, record.hdr.record_type, record.hdr.len, msg
                         );
                     }
 
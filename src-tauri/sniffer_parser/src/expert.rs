@@ -0,0 +1,196 @@
+//! Expert info of a single parsed packet
+//!
+//! Conditions visible in the layers of the packet alone: malformed layers, wrong checksums, errors
+//! reported by the capture tool and the TCP flags worth noticing. Conditions spanning more packets
+//! (e.g. retransmissions) are left to the analyses of the collected packets.
+
+use pnet::packet::tcp::TcpFlags;
+
+use crate::serializable_packet::{
+    ExpertGroups, ExpertSeverities, ParsedPacket, SerializablePacket,
+};
+
+/// Annotate the packet with the expert info of its layers
+pub fn add_layer_expert_info(parsed_packet: &mut ParsedPacket) {
+    let mut annotations = vec![];
+
+    let layers = [
+        parsed_packet.get_link_layer_packet(),
+        parsed_packet.get_network_layer_packet(),
+        parsed_packet.get_tunnel_layer_packet(),
+        parsed_packet.get_transport_layer_packet(),
+        parsed_packet.get_application_layer_packet(),
+    ];
+
+    for layer in layers.into_iter().flatten() {
+        match layer {
+            SerializablePacket::MalformedPacket(message) => annotations.push((
+                ExpertSeverities::ERROR,
+                ExpertGroups::MALFORMED,
+                message.clone(),
+            )),
+            SerializablePacket::UnknownPacket(unknown_packet) => annotations.push((
+                ExpertSeverities::NOTE,
+                ExpertGroups::UNDECODED,
+                format!("Unknown EtherType {}", unknown_packet.ethertype),
+            )),
+            SerializablePacket::Ipv4Packet(ipv4_packet)
+                if ipv4_packet.checksum_valid == Some(false) =>
+            {
+                annotations.push((
+                    ExpertSeverities::ERROR,
+                    ExpertGroups::CHECKSUM,
+                    "Bad IPv4 header checksum".to_owned(),
+                ))
+            }
+            SerializablePacket::IcmpPacket(icmp_packet)
+                if icmp_packet.checksum_valid == Some(false) =>
+            {
+                annotations.push((
+                    ExpertSeverities::ERROR,
+                    ExpertGroups::CHECKSUM,
+                    "Bad ICMP checksum".to_owned(),
+                ))
+            }
+            SerializablePacket::UdpPacket(udp_packet)
+                if udp_packet.checksum_valid == Some(false) =>
+            {
+                annotations.push((
+                    ExpertSeverities::ERROR,
+                    ExpertGroups::CHECKSUM,
+                    "Bad UDP checksum".to_owned(),
+                ))
+            }
+            SerializablePacket::TcpPacket(tcp_packet) => {
+                if tcp_packet.checksum_valid == Some(false) {
+                    annotations.push((
+                        ExpertSeverities::ERROR,
+                        ExpertGroups::CHECKSUM,
+                        "Bad TCP checksum".to_owned(),
+                    ));
+                }
+
+                let flags = tcp_packet.flags;
+                if flags & TcpFlags::RST != 0 {
+                    annotations.push((
+                        ExpertSeverities::WARNING,
+                        ExpertGroups::SEQUENCE,
+                        "Connection reset (RST)".to_owned(),
+                    ));
+                } else if tcp_packet.window == 0 && flags & (TcpFlags::SYN | TcpFlags::FIN) == 0 {
+                    annotations.push((
+                        ExpertSeverities::WARNING,
+                        ExpertGroups::SEQUENCE,
+                        "Zero window".to_owned(),
+                    ));
+                }
+
+                if flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK == 0 {
+                    annotations.push((
+                        ExpertSeverities::CHAT,
+                        ExpertGroups::SEQUENCE,
+                        "Connection establish request (SYN)".to_owned(),
+                    ));
+                }
+                if flags & TcpFlags::FIN != 0 {
+                    annotations.push((
+                        ExpertSeverities::CHAT,
+                        ExpertGroups::SEQUENCE,
+                        "Connection finish (FIN)".to_owned(),
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for error in parsed_packet.get_link_errors() {
+        annotations.push((ExpertSeverities::WARNING, ExpertGroups::LINK, error.clone()));
+    }
+
+    for (severity, group, message) in annotations {
+        parsed_packet.add_expert_info(severity, group, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::ethernet::{EtherTypes, MutableEthernetPacket};
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::ipv4::MutableIpv4Packet;
+    use pnet::packet::tcp::{MutableTcpPacket, TcpFlags};
+
+    use crate::parse_ethernet_bytes;
+    use crate::serializable_packet::{ExpertGroups, ExpertSeverities};
+
+    use super::add_layer_expert_info;
+
+    #[test]
+    fn reset_with_bad_checksum() {
+        let mut frame = [0u8; 54];
+
+        let mut ethernet = MutableEthernetPacket::new(&mut frame).unwrap();
+        ethernet.set_ethertype(EtherTypes::Ipv4);
+
+        let mut ipv4 = MutableIpv4Packet::new(&mut frame[14..]).unwrap();
+        ipv4.set_version(4);
+        ipv4.set_header_length(5);
+        ipv4.set_total_length(40);
+        ipv4.set_ttl(64);
+        ipv4.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
+        // Wrong checksum
+        ipv4.set_checksum(0xbeef);
+
+        let mut tcp = MutableTcpPacket::new(&mut frame[34..]).unwrap();
+        tcp.set_source(4444);
+        tcp.set_destination(80);
+        tcp.set_data_offset(5);
+        tcp.set_flags(TcpFlags::RST | TcpFlags::ACK);
+
+        let mut parsed_packet = parse_ethernet_bytes(&frame, 0);
+        parsed_packet.set_link_errors(vec!["FCS error".to_owned()]);
+        add_layer_expert_info(&mut parsed_packet);
+
+        let expert_info = parsed_packet
+            .get_expert_info()
+            .iter()
+            .map(|info| {
+                (
+                    info.severity.as_str(),
+                    info.group.as_str(),
+                    info.message.as_str(),
+                )
+            })
+            .collect::<Vec<(&str, &str, &str)>>();
+
+        assert!(expert_info.contains(&(
+            ExpertSeverities::ERROR,
+            ExpertGroups::CHECKSUM,
+            "Bad IPv4 header checksum"
+        )));
+        assert!(expert_info.contains(&(
+            ExpertSeverities::WARNING,
+            ExpertGroups::SEQUENCE,
+            "Connection reset (RST)"
+        )));
+        assert!(expert_info.contains(&(
+            ExpertSeverities::WARNING,
+            ExpertGroups::LINK,
+            "FCS error"
+        )));
+        // The window of a reset doesn't matter
+        assert!(!expert_info.iter().any(|info| info.2 == "Zero window"));
+
+        let malformed = parse_ethernet_bytes(&[0; 6], 1);
+        let mut malformed = malformed;
+        add_layer_expert_info(&mut malformed);
+        assert_eq!(
+            malformed.get_expert_info()[0].severity,
+            ExpertSeverities::ERROR
+        );
+        assert_eq!(
+            malformed.get_expert_info()[0].group,
+            ExpertGroups::MALFORMED
+        );
+    }
+}
@@ -4,16 +4,24 @@
 //! and represents the parsed packet data at the different levels of the TCP/IP stack
 
 mod application;
+mod loopback;
 mod network;
 mod transport;
+mod wireless;
 
 pub use crate::application::*;
+pub use crate::loopback::*;
 pub use crate::network::*;
-use crate::serializable_packet::SerializableUnknownPacket;
+use crate::serializable_packet::{SerializableRawPacket, SerializableUnknownPacket};
 pub use crate::transport::*;
+pub use crate::wireless::*;
 
+pub mod expert;
 pub mod serializable_packet;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+
 use log::debug;
 use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
 use pnet::packet::Packet;
@@ -28,10 +36,43 @@ pub mod HeaderLength {
     pub const ETHERNET: usize = 14;
 }
 
+thread_local!(
+    static REGISTERED_ETHERTYPES: RefCell<HashMap<u16, String>> = RefCell::new(
+        CustomEtherTypes::DEFAULT
+            .iter()
+            .map(|(ethertype, name)| (*ethertype, name.to_string()))
+            .collect(),
+    );
+);
+
+/// EtherTypes without a native parser, registered by default to label their payload
+#[allow(non_snake_case)]
+pub mod CustomEtherTypes {
+    pub const DEFAULT: [(u16, &str); 6] = [
+        (0x8892, "PROFINET"),
+        (0x88a4, "EtherCAT"),
+        (0x88b8, "GOOSE"),
+        (0x88ba, "Sampled Values"),
+        (0x88cc, "LLDP"),
+        (0x88f7, "PTP"),
+    ];
+}
+
+/// Set the EtherTypes whose payload is labelled as raw by the current thread, by protocol name
+pub fn set_registered_ethertypes(ethertypes: &HashMap<u16, String>) {
+    REGISTERED_ETHERTYPES.with(|registered| *registered.borrow_mut() = ethertypes.clone());
+}
+
+fn get_registered_ethertype(ethertype: u16) -> Option<String> {
+    REGISTERED_ETHERTYPES.with(|registered| registered.borrow().get(&ethertype).cloned())
+}
+
 /// Delete active parsers
 pub fn cleanup_sniffing_state() {
     ACTIVE_HTTP_PARSERS.with(|parsers| parsers.borrow_mut().clear());
+    ACTIVE_HTTP2_CONNECTIONS.with(|connections| connections.borrow_mut().clear());
     ACTIVE_TLS_PARSERS.with(|parsers| parsers.borrow_mut().clear());
+    credentials::clear_credential_scanners();
 }
 
 /// Parse ethernet frame obtaining the packet link-layer and network-layer representations
@@ -51,32 +92,80 @@ pub fn parse_ethernet_frame(ethernet: &EthernetPacket, id: usize) -> ParsedPacke
             ethernet.get_destination(),
             &mut parsed_packet,
         ),
-        _ => {
-            debug!(
-                "Unknown packet: {} > {}; ethertype: {:?} length: {}",
-                ethernet.get_source(),
-                ethernet.get_destination(),
-                ethernet.get_ethertype(),
-                ethernet.packet().len()
-            );
-
-            parsed_packet.set_link_layer_packet(Some(SerializablePacket::UnknownPacket(
-                SerializableUnknownPacket::from(ethernet),
-            )));
-        }
+        ethertype => match get_registered_ethertype(ethertype.0) {
+            Some(protocol) => handle_raw_packet(ethernet, protocol, &mut parsed_packet),
+            None => {
+                debug!(
+                    "Unknown packet: {} > {}; ethertype: {:?} length: {}",
+                    ethernet.get_source(),
+                    ethernet.get_destination(),
+                    ethernet.get_ethertype(),
+                    ethernet.packet().len()
+                );
+
+                parsed_packet.set_link_layer_packet(Some(SerializablePacket::UnknownPacket(
+                    SerializableUnknownPacket::from(ethernet),
+                )));
+            }
+        },
     }
 
     parsed_packet
 }
 
+/// Labels the payload of a registered EtherType with its protocol, keeping the ethernet header
+fn handle_raw_packet(
+    ethernet: &EthernetPacket,
+    protocol: String,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let payload = ethernet.payload();
+
+    debug!(
+        "{} packet: {} > {}; length: {}",
+        protocol,
+        ethernet.get_source(),
+        ethernet.get_destination(),
+        payload.len()
+    );
+
+    parsed_packet.set_network_layer_packet(Some(SerializablePacket::RawPacket(
+        SerializableRawPacket {
+            protocol,
+            ethertype: ethernet.get_ethertype().0,
+            payload: payload.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            length: payload.len(),
+        },
+    )));
+}
+
+/// Parse the bytes of an ethernet frame, frames shorter than the header are saved as malformed
+pub fn parse_ethernet_bytes(packet: &[u8], id: usize) -> ParsedPacket {
+    if let Some(ethernet) = EthernetPacket::new(packet) {
+        return parse_ethernet_frame(&ethernet, id);
+    }
+
+    debug!("Malformed Ethernet Frame: {} bytes", packet.len());
+
+    let mut parsed_packet = ParsedPacket::new(id);
+    parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(format!(
+        "Malformed Ethernet Frame: {} bytes, shorter than the {} bytes header",
+        packet.len(),
+        HeaderLength::ETHERNET
+    ))));
+
+    parsed_packet
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parse_ethernet_frame;
     use crate::serializable_packet::SerializablePacket;
+    use crate::{parse_ethernet_bytes, parse_ethernet_frame, set_registered_ethertypes};
     use pnet::packet::ethernet::EtherType;
     use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
     use pnet::packet::Packet;
     use pnet::util::MacAddr;
+    use std::collections::HashMap;
 
     #[test]
     fn valid_ethernet_packet() {
@@ -127,6 +216,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn registered_ethertype_packet() {
+        let mut ethernet_buffer = [0u8; 18];
+        ethernet_buffer[14..].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let ethernet_packet = build_test_unknown_ethernet_packet(ethernet_buffer.as_mut_slice());
+
+        set_registered_ethertypes(&HashMap::from([(0x9999, "Custom".to_owned())]));
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 0);
+        set_registered_ethertypes(&HashMap::new());
+
+        assert!(matches!(
+            parsed_packet.get_link_layer_packet().unwrap(),
+            SerializablePacket::EthernetPacket(_)
+        ));
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::RawPacket(raw_packet) => {
+                assert_eq!(raw_packet.protocol, "Custom");
+                assert_eq!(raw_packet.ethertype, 0x9999);
+                assert_eq!(raw_packet.payload, "deadbeef");
+                assert_eq!(raw_packet.length, 4);
+            }
+            _ => unreachable!(),
+        }
+
+        // No longer registered
+        let parsed_packet = parse_ethernet_frame(&ethernet_packet, 1);
+        assert!(parsed_packet.get_network_layer_packet().is_none());
+    }
+
+    #[test]
+    fn truncated_ethernet_frame() {
+        for length in [0, 6] {
+            let parsed_packet = parse_ethernet_bytes(&vec![0xff; length], 0);
+
+            match parsed_packet.get_link_layer_packet().unwrap() {
+                SerializablePacket::MalformedPacket(reason) => assert_eq!(
+                    *reason,
+                    format!(
+                        "Malformed Ethernet Frame: {} bytes, shorter than the 14 bytes header",
+                        length
+                    )
+                ),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn jumbo_ethernet_frame() {
+        let mut ethernet_buffer = vec![0u8; 9000];
+        build_test_ethernet_packet(ethernet_buffer.as_mut_slice());
+
+        let parsed_packet = parse_ethernet_bytes(&ethernet_buffer, 0);
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::EthernetPacket(ethernet_packet) => {
+                assert_eq!(ethernet_packet.payload.len(), 9000 - 14)
+            }
+            _ => unreachable!(),
+        }
+    }
+
     ///////////////////// Utils
 
     fn build_test_ethernet_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
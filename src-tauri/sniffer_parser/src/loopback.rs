@@ -0,0 +1,180 @@
+//! Loopback frame parsing (BSD loopback encapsulation, DLT_NULL and DLT_LOOP)
+//!
+//! Instead of an Ethernet header, loopback frames start with the 4-byte address family of the carried
+//! packet: in the byte order of the capturing host for DLT_NULL, in network byte order for DLT_LOOP.
+//! The value of AF_INET6 depends on the operating system.
+
+use log::debug;
+
+use super::*;
+use crate::serializable_packet::SerializableLoopbackPacket;
+
+/// Address families of the carried packet
+#[allow(non_snake_case)]
+pub mod LoopbackFamilies {
+    pub const INET: u32 = 2;
+    /// Linux
+    pub const INET6_LINUX: u32 = 10;
+    /// Windows
+    pub const INET6_WINDOWS: u32 = 23;
+    /// NetBSD, OpenBSD
+    pub const INET6_BSD: u32 = 24;
+    /// FreeBSD, DragonFly BSD
+    pub const INET6_FREEBSD: u32 = 28;
+    /// macOS
+    pub const INET6_DARWIN: u32 = 30;
+}
+
+const LOOPBACK_HEADER_LENGTH: usize = 4;
+
+/// Parse a DLT_NULL frame, whose address family is in the byte order of the capturing host
+pub fn parse_null_frame(packet: &[u8], id: usize) -> ParsedPacket {
+    parse_loopback_frame(packet, id, false)
+}
+
+/// Parse a DLT_LOOP frame, whose address family is in network byte order
+pub fn parse_loop_frame(packet: &[u8], id: usize) -> ParsedPacket {
+    parse_loopback_frame(packet, id, true)
+}
+
+fn parse_loopback_frame(packet: &[u8], id: usize, big_endian: bool) -> ParsedPacket {
+    let mut parsed_packet = ParsedPacket::new(id);
+
+    if packet.len() < LOOPBACK_HEADER_LENGTH {
+        debug!("Malformed Loopback Frame");
+        parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+            "Malformed Loopback Frame".to_string(),
+        )));
+        return parsed_packet;
+    }
+
+    let header = [packet[0], packet[1], packet[2], packet[3]];
+    let family = if big_endian {
+        u32::from_be_bytes(header)
+    } else {
+        // The capturing host is unknown, address families always fit in the low-order bytes
+        match u32::from_le_bytes(header) {
+            family if family > 0xffff => u32::from_be_bytes(header),
+            family => family,
+        }
+    };
+
+    let payload = &packet[LOOPBACK_HEADER_LENGTH..];
+    let protocol = match family {
+        LoopbackFamilies::INET => "IPv4",
+        LoopbackFamilies::INET6_LINUX
+        | LoopbackFamilies::INET6_WINDOWS
+        | LoopbackFamilies::INET6_BSD
+        | LoopbackFamilies::INET6_FREEBSD
+        | LoopbackFamilies::INET6_DARWIN => "IPv6",
+        _ => "Unknown",
+    };
+
+    parsed_packet.set_link_layer_packet(Some(SerializablePacket::LoopbackPacket(
+        SerializableLoopbackPacket {
+            family,
+            protocol: protocol.to_owned(),
+            payload: payload.to_vec().into(),
+        },
+    )));
+
+    match protocol {
+        "IPv4" => handle_ipv4_packet(payload, &mut parsed_packet),
+        "IPv6" => handle_ipv6_packet(payload, &mut parsed_packet),
+        _ => debug!("Unknown loopback address family: {}", family),
+    }
+
+    parsed_packet
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::packet::ip::IpNextHeaderProtocols;
+    use pnet::packet::ipv4::MutableIpv4Packet;
+    use pnet::packet::ipv6::MutableIpv6Packet;
+
+    use crate::serializable_packet::SerializablePacket;
+
+    use super::{parse_loop_frame, parse_null_frame, LoopbackFamilies};
+
+    fn build_ipv4_packet() -> Vec<u8> {
+        let mut buffer = vec![0u8; 20];
+        let mut ipv4_packet = MutableIpv4Packet::new(&mut buffer).unwrap();
+        ipv4_packet.set_version(4);
+        ipv4_packet.set_header_length(5);
+        ipv4_packet.set_total_length(20);
+        ipv4_packet.set_ttl(64);
+        ipv4_packet.set_next_level_protocol(IpNextHeaderProtocols::Tcp);
+        ipv4_packet.set_source("127.0.0.1".parse().unwrap());
+        ipv4_packet.set_destination("127.0.0.1".parse().unwrap());
+
+        buffer
+    }
+
+    fn build_ipv6_packet() -> Vec<u8> {
+        let mut buffer = vec![0u8; 40];
+        let mut ipv6_packet = MutableIpv6Packet::new(&mut buffer).unwrap();
+        ipv6_packet.set_version(6);
+        ipv6_packet.set_hop_limit(64);
+        ipv6_packet.set_next_header(IpNextHeaderProtocols::Tcp);
+        ipv6_packet.set_source("::1".parse().unwrap());
+        ipv6_packet.set_destination("::1".parse().unwrap());
+
+        buffer
+    }
+
+    #[test]
+    fn loopback_ipv4_frame() {
+        let mut frame = LoopbackFamilies::INET.to_le_bytes().to_vec();
+        frame.extend(build_ipv4_packet());
+
+        let parsed_packet = parse_null_frame(&frame, 0);
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::LoopbackPacket(loopback_packet) => {
+                assert_eq!(loopback_packet.family, LoopbackFamilies::INET);
+                assert_eq!(loopback_packet.protocol, "IPv4");
+            }
+            _ => unreachable!(),
+        }
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(ipv4_packet) => {
+                assert_eq!(ipv4_packet.source.to_string(), "127.0.0.1")
+            }
+            _ => unreachable!(),
+        }
+
+        // Captured on a big-endian host
+        frame[..4].copy_from_slice(&LoopbackFamilies::INET.to_be_bytes());
+        let parsed_packet = parse_null_frame(&frame, 0);
+        assert!(matches!(
+            parsed_packet.get_network_layer_packet(),
+            Some(SerializablePacket::Ipv4Packet(_))
+        ));
+    }
+
+    #[test]
+    fn loopback_ipv6_frame() {
+        for family in [
+            LoopbackFamilies::INET6_BSD,
+            LoopbackFamilies::INET6_FREEBSD,
+            LoopbackFamilies::INET6_DARWIN,
+        ] {
+            let mut frame = family.to_be_bytes().to_vec();
+            frame.extend(build_ipv6_packet());
+
+            let parsed_packet = parse_loop_frame(&frame, 0);
+            match parsed_packet.get_network_layer_packet().unwrap() {
+                SerializablePacket::Ipv6Packet(ipv6_packet) => {
+                    assert_eq!(ipv6_packet.source.to_string(), "::1")
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let parsed_packet = parse_null_frame(&[2, 0], 0);
+        assert!(matches!(
+            parsed_packet.get_link_layer_packet(),
+            Some(SerializablePacket::MalformedPacket(_))
+        ));
+    }
+}
@@ -8,10 +8,20 @@ use std::net::IpAddr;
 
 use super::*;
 use crate::serializable_packet::network::{
-    SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet,
+    SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet, SerializableTunnelPacket,
 };
 use crate::transport::*;
 
+/// UDP Port used by Teredo tunnels
+pub const TEREDO_PORT: u16 = 3544;
+
+/// Types of IPv6-in-IPv4 tunnels
+#[allow(non_snake_case)]
+pub mod TunnelTypes {
+    pub const SIX_TO_FOUR: &str = "6to4";
+    pub const TEREDO: &str = "Teredo";
+}
+
 /// Build a IPv4 packet from a data-link packet, save it in a Parsed Packet
 pub fn handle_ipv4_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
     let header = Ipv4Packet::new(packet);
@@ -56,6 +66,88 @@ pub fn handle_ipv6_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
     }
 }
 
+/// Build the IPv6 packet carried by an IPv6-in-IPv4 tunnel, save it in a Parsed Packet
+///
+/// The outer IPv4 packet is kept as network layer, while the decapsulated IPv6 packet is saved as tunnel layer.
+/// The inner transport protocol is parsed only if requested (i.e. the transport layer is not already used by the tunnel)
+pub fn handle_tunneled_ipv6_packet(
+    tunnel_type: &str,
+    packet: &[u8],
+    parse_transport: bool,
+    parsed_packet: &mut ParsedPacket,
+) {
+    let header = Ipv6Packet::new(packet).filter(|header| {
+        header.get_version() == 6
+            && Ipv6Packet::minimum_packet_size() + header.get_payload_length() as usize
+                <= packet.len()
+    });
+
+    if let Some(header) = header {
+        debug!(
+            "{} tunneled IPv6 packet: {} > {}; next header: {}",
+            tunnel_type,
+            header.get_source(),
+            header.get_destination(),
+            header.get_next_header()
+        );
+
+        parsed_packet.set_tunnel_layer_packet(Some(SerializablePacket::TunnelPacket(
+            SerializableTunnelPacket {
+                tunnel_type: tunnel_type.to_owned(),
+                inner: SerializableIpv6Packet::from(&header),
+            },
+        )));
+
+        if parse_transport {
+            handle_transport_protocol(
+                IpAddr::V6(header.get_source()),
+                IpAddr::V6(header.get_destination()),
+                header.get_next_header(),
+                header.payload(),
+                parsed_packet,
+            );
+        }
+    } else {
+        debug!("Malformed {} Packet", tunnel_type);
+        parsed_packet.set_tunnel_layer_packet(Some(SerializablePacket::MalformedPacket(format!(
+            "Malformed {} Packet",
+            tunnel_type
+        ))));
+    }
+}
+
+/// Build the IPv6 packet carried by a Teredo UDP datagram, save it in a Parsed Packet
+pub fn handle_teredo_packet(packet: &[u8], parsed_packet: &mut ParsedPacket) {
+    match strip_teredo_indicators(packet) {
+        Some(ipv6_packet) => {
+            handle_tunneled_ipv6_packet(TunnelTypes::TEREDO, ipv6_packet, false, parsed_packet)
+        }
+        None => {
+            debug!("Malformed Teredo Packet");
+            parsed_packet.set_tunnel_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed Teredo Packet".to_string(),
+            )));
+        }
+    }
+}
+
+/// Skip the optional Teredo authentication and origin indicators preceding the IPv6 packet
+fn strip_teredo_indicators(mut packet: &[u8]) -> Option<&[u8]> {
+    // Authentication: 0x0001, ID length, AU length, client ID, authentication value, nonce (8), confirmation (1)
+    if packet.starts_with(&[0x00, 0x01]) {
+        let id_length = *packet.get(2)? as usize;
+        let au_length = *packet.get(3)? as usize;
+        packet = packet.get(4 + id_length + au_length + 9..)?;
+    }
+
+    // Origin: 0x0000, obfuscated port (2), obfuscated IPv4 address (4)
+    if packet.starts_with(&[0x00, 0x00]) {
+        packet = packet.get(8..)?;
+    }
+
+    Some(packet)
+}
+
 /// Build a ARP packet from a data-link packet, save it in a Parsed Packet
 pub fn handle_arp_packet(
     packet: &[u8],
@@ -92,15 +184,16 @@ pub mod tests {
     use pnet::packet::arp::{ArpHardwareTypes, ArpOperations, ArpPacket, MutableArpPacket};
     use pnet::packet::ethernet::{EtherTypes, EthernetPacket, MutableEthernetPacket};
     use pnet::packet::ip::IpNextHeaderProtocols;
-    use pnet::packet::ipv4::{Ipv4Packet, MutableIpv4Packet};
+    use pnet::packet::ipv4::{self, Ipv4Packet, MutableIpv4Packet};
     use pnet::packet::ipv6::{Ipv6Packet, MutableIpv6Packet};
     use pnet::packet::Packet;
     use pnet::util::MacAddr;
 
+    use crate::serializable_packet::network::SerializableIpv4Option;
     use crate::serializable_packet::{ParsedPacket, SerializablePacket};
     use crate::{handle_ipv4_packet, handle_ipv6_packet};
 
-    use super::handle_arp_packet;
+    use super::{handle_arp_packet, handle_teredo_packet, TunnelTypes};
 
     #[test]
     fn valid_arp_packet() {
@@ -213,6 +306,77 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn ip_packet_with_options() {
+        let mut ip_buffer = [0u8; 52];
+        ip_buffer[20..].copy_from_slice(&[
+            7, 11, 8, 10, 0, 0, 1, 0, 0, 0, 0, // Record Route, one address recorded
+            68, 12, 9, 0, 0, 0, 0, 1, 0, 0, 0, 0, // Timestamp, one timestamp recorded
+            148, 4, 0, 0, // Router Alert
+            1, 7, 20, 0, 0, // NOP, Record Route exceeding the options field
+        ]);
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_version(4);
+        ip_packet.set_header_length(13);
+        ip_packet.set_total_length(52);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ip_packet.packet(), &mut parsed_packet);
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(new_ip_packet) => assert_eq!(
+                new_ip_packet.parsed_options,
+                vec![
+                    SerializableIpv4Option::RecordRoute {
+                        pointer: 8,
+                        addresses: vec![Ipv4Addr::new(10, 0, 0, 1)]
+                    },
+                    SerializableIpv4Option::Timestamp {
+                        pointer: 9,
+                        overflow: 0,
+                        flag: 0,
+                        timestamps: vec![(None, 1)]
+                    },
+                    SerializableIpv4Option::RouterAlert(0),
+                    SerializableIpv4Option::NoOperation,
+                    SerializableIpv4Option::Malformed {
+                        kind: 7,
+                        data: vec![20, 0, 0]
+                    },
+                ]
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn ip_header_checksum() {
+        let mut ip_buffer = [0u8; 20];
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_version(4);
+        ip_packet.set_header_length(5);
+        ip_packet.set_total_length(20);
+        ip_packet.set_ttl(64);
+        ip_packet.set_source(Ipv4Addr::new(10, 10, 10, 10));
+        ip_packet.set_destination(Ipv4Addr::new(11, 11, 11, 11));
+        let checksum = ipv4::checksum(&ip_packet.to_immutable());
+        ip_packet.set_checksum(checksum);
+
+        let checksum_valid = |packet: &[u8]| {
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_ipv4_packet(packet, &mut parsed_packet);
+            match parsed_packet.get_network_layer_packet().unwrap() {
+                SerializablePacket::Ipv4Packet(new_ip_packet) => new_ip_packet.checksum_valid,
+                _ => unreachable!(),
+            }
+        };
+
+        assert_eq!(checksum_valid(ip_packet.packet()), Some(true));
+
+        ip_packet.set_ttl(63);
+        assert_eq!(checksum_valid(ip_packet.packet()), Some(false));
+    }
+
     #[test]
     fn malformed_ip_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -273,8 +437,123 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn valid_6to4_packet() {
+        let mut ipv6_buffer = [0u8; 48];
+        build_test_tunneled_ipv6_packet(&mut ipv6_buffer);
+
+        let mut ip_buffer = [0u8; 68];
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_version(4);
+        ip_packet.set_header_length(5);
+        ip_packet.set_total_length(68);
+        ip_packet.set_next_level_protocol(IpNextHeaderProtocols::Ipv6);
+        ip_packet.set_source(Ipv4Addr::new(192, 88, 99, 1));
+        ip_packet.set_destination(Ipv4Addr::new(10, 10, 10, 10));
+        ip_packet.set_payload(&ipv6_buffer);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ip_packet.packet(), &mut parsed_packet);
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(outer) => {
+                assert_eq!(outer.source, Ipv4Addr::new(192, 88, 99, 1))
+            }
+            _ => unreachable!(),
+        }
+
+        match parsed_packet.get_tunnel_layer_packet().unwrap() {
+            SerializablePacket::TunnelPacket(tunnel) => {
+                assert_eq!(tunnel.tunnel_type, TunnelTypes::SIX_TO_FOUR);
+                assert_eq!(
+                    tunnel.inner.source,
+                    Ipv6Addr::new(0x2002, 0xc058, 0x6301, 0, 0, 0, 0, 1)
+                );
+                assert_eq!(
+                    tunnel.inner.destination,
+                    Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::UdpPacket(udp) => assert_eq!(udp.destination, 9999),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_6to4_packet() {
+        let mut ip_buffer = [0u8; 30];
+        let mut ip_packet = MutableIpv4Packet::new(&mut ip_buffer).unwrap();
+        ip_packet.set_version(4);
+        ip_packet.set_header_length(5);
+        ip_packet.set_total_length(30);
+        ip_packet.set_next_level_protocol(IpNextHeaderProtocols::Ipv6);
+        ip_packet.set_payload(&[0x60, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_ipv4_packet(ip_packet.packet(), &mut parsed_packet);
+
+        match parsed_packet.get_tunnel_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => assert_eq!(str, "Malformed 6to4 Packet"),
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_transport_layer_packet().is_none());
+    }
+
+    #[test]
+    fn valid_teredo_packet_with_origin_indication() {
+        let mut teredo_buffer = [0u8; 56];
+        teredo_buffer[..8].copy_from_slice(&[0x00, 0x00, 0xf2, 0x27, 0x3f, 0xf5, 0x9c, 0xfe]);
+        build_test_tunneled_ipv6_packet(&mut teredo_buffer[8..]);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_teredo_packet(&teredo_buffer, &mut parsed_packet);
+
+        match parsed_packet.get_tunnel_layer_packet().unwrap() {
+            SerializablePacket::TunnelPacket(tunnel) => {
+                assert_eq!(tunnel.tunnel_type, TunnelTypes::TEREDO);
+                assert_eq!(
+                    tunnel.inner.destination,
+                    Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)
+                );
+            }
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_transport_layer_packet().is_none());
+    }
+
+    #[test]
+    fn malformed_teredo_packet() {
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_teredo_packet(&[0x00, 0x01, 0x10], &mut parsed_packet);
+
+        match parsed_packet.get_tunnel_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, "Malformed Teredo Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+
     ///////////////////// Utils
 
+    fn build_test_tunneled_ipv6_packet(ipv6_buffer: &mut [u8]) {
+        let mut ipv6_packet = MutableIpv6Packet::new(ipv6_buffer).unwrap();
+
+        ipv6_packet.set_version(6);
+        ipv6_packet.set_payload_length(8);
+        ipv6_packet.set_next_header(IpNextHeaderProtocols::Udp);
+        ipv6_packet.set_hop_limit(64);
+        ipv6_packet.set_source(Ipv6Addr::new(0x2002, 0xc058, 0x6301, 0, 0, 0, 0, 1));
+        ipv6_packet.set_destination(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+        // UDP header: source port 1234, destination port 9999, length 8
+        ipv6_packet.set_payload(&[0x04, 0xd2, 0x27, 0x0f, 0x00, 0x08, 0x00, 0x00]);
+    }
+
     fn build_test_arp_packet<'a>(ethernet_buffer: &'a mut [u8]) -> EthernetPacket<'a> {
         let mut ethernet_packet = MutableEthernetPacket::new(ethernet_buffer).unwrap();
 
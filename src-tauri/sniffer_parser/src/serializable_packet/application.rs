@@ -19,6 +19,8 @@ use tls_parser::{
 };
 use x509_parser::{parse_x509_certificate, prelude::X509Certificate};
 
+use crate::application::ja3::{get_ja3, get_ja3s, md5_hex};
+
 /// HTTP Body content
 #[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type", content = "content")]
@@ -42,10 +44,17 @@ pub struct SerializableHttpRequestPacket {
     pub version: u8,
     pub headers: Vec<(String, String)>,
     pub payload: HttpContentType,
+    /// Bytes of a text body as transferred, dechunked and decompressed but not decoded
+    #[serde(skip)]
+    pub text_body: Option<Vec<u8>>,
 }
 
 impl<'a, 'b> SerializableHttpRequestPacket {
-    pub fn new(packet: &Request<'a, 'b>, payload: HttpContentType) -> Self {
+    pub fn new(
+        packet: &Request<'a, 'b>,
+        payload: HttpContentType,
+        text_body: Option<Vec<u8>>,
+    ) -> Self {
         SerializableHttpRequestPacket {
             method: packet.method.unwrap().to_owned(),
             path: packet.path.unwrap().to_owned(),
@@ -63,6 +72,7 @@ impl<'a, 'b> SerializableHttpRequestPacket {
                 })
                 .collect(),
             payload,
+            text_body,
         }
     }
 }
@@ -75,10 +85,17 @@ pub struct SerializableHttpResponsePacket {
     pub reason: String,
     pub headers: Vec<(String, String)>,
     pub payload: HttpContentType,
+    /// Bytes of a text body as transferred, dechunked and decompressed but not decoded
+    #[serde(skip)]
+    pub text_body: Option<Vec<u8>>,
 }
 
 impl<'a, 'b> SerializableHttpResponsePacket {
-    pub fn new(packet: &Response<'a, 'b>, payload: HttpContentType) -> Self {
+    pub fn new(
+        packet: &Response<'a, 'b>,
+        payload: HttpContentType,
+        text_body: Option<Vec<u8>>,
+    ) -> Self {
         SerializableHttpResponsePacket {
             version: packet.version.unwrap(),
             code: packet.code.unwrap(),
@@ -96,10 +113,41 @@ impl<'a, 'b> SerializableHttpResponsePacket {
                 })
                 .collect(),
             payload,
+            text_body,
         }
     }
 }
 
+/// HTTP/2 Packet Representation, with the frames completed by the segment
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableHttp2Packet {
+    /// Whether the segment carries the connection preface of the client
+    pub preface: bool,
+    pub frames: Vec<Http2Frame>,
+}
+
+/// HTTP/2 Frame, with the fields of its type
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Http2Frame {
+    pub frame_type: String,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub length: usize,
+    pub end_stream: bool,
+    pub ack: bool,
+    /// Decoded header list, on the frame ending a header block
+    pub headers: Option<Vec<(String, String)>>,
+    /// Content of DATA frames, opaque data of PING and GOAWAY frames
+    pub data: Option<Vec<u8>>,
+    pub settings: Option<Vec<(String, u32)>>,
+    pub error_code: Option<String>,
+    pub promised_stream_id: Option<u32>,
+    pub last_stream_id: Option<u32>,
+    pub window_increment: Option<u32>,
+    /// Reason why the frame wasn't completely parsed
+    pub error: Option<String>,
+}
+
 /// TLS Malformed Packet Representation
 #[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type", content = "error")]
@@ -229,6 +277,7 @@ pub struct ClientHelloMessage {
     pub ciphers: Vec<String>,
     pub compressions: Vec<String>,
     pub extensions: Vec<String>,
+    pub ja3: Ja3Fingerprint,
 }
 
 impl ClientHelloMessage {
@@ -244,6 +293,25 @@ impl ClientHelloMessage {
                 Ok((_, exts)) => parse_custom_tls_extensions(exts),
                 Err(_) => vec!["Error parsing".to_owned()],
             },
+            ja3: get_ja3(message),
+        }
+    }
+}
+
+/// JA3 or JA3S fingerprint of a TLS Hello message
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Ja3Fingerprint {
+    /// Decimal values of the fingerprinted fields
+    pub raw: String,
+    /// MD5 hash of the raw string, lowercase hexadecimal
+    pub hash: String,
+}
+
+impl Ja3Fingerprint {
+    pub fn new(raw: String) -> Self {
+        Ja3Fingerprint {
+            hash: md5_hex(raw.as_bytes()),
+            raw,
         }
     }
 }
@@ -258,7 +326,8 @@ pub(crate) fn parse_custom_tls_extensions(exts: Vec<TlsExtension>) -> Vec<String
                 let mut sni = "SNI: ".to_owned();
                 for (i, (sni_type, data)) in data.into_iter().enumerate() {
                     sni.push_str(&format!(
-                        "{sni_type} = {}",
+                        "{} = {}",
+                        sni_type,
                         std::str::from_utf8(data).unwrap_or("-")
                     ));
 
@@ -270,7 +339,7 @@ pub(crate) fn parse_custom_tls_extensions(exts: Vec<TlsExtension>) -> Vec<String
                 new_extensions.push(sni);
             }
             TlsExtension::MaxFragmentLength(length) => {
-                new_extensions.push(format!("MaxFragmentLength: {length}"));
+                new_extensions.push(format!("MaxFragmentLength: {}", length));
             }
             TlsExtension::StatusRequest(req) => {
                 if let Some((cert_type, data)) = req {
@@ -441,6 +510,7 @@ pub struct ServerHelloMessage {
     pub cipher: String,
     pub compression: String,
     pub extensions: Vec<String>,
+    pub ja3s: Ja3Fingerprint,
 }
 
 impl ServerHelloMessage {
@@ -456,6 +526,7 @@ impl ServerHelloMessage {
                 Ok((_, exts)) => parse_custom_tls_extensions(exts),
                 Err(_) => vec!["Error parsing".to_owned()],
             },
+            ja3s: get_ja3s(message),
         }
     }
 }
@@ -1200,3 +1271,146 @@ pub struct Txt {
 pub struct Unknown {
     pub data: Vec<u8>,
 }
+
+/// SIP Packet Representation
+///
+/// Requests carry `method` and `request_uri`, responses carry `status_code` and `reason`
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableSipPacket {
+    pub method: Option<String>,
+    pub request_uri: Option<String>,
+    pub status_code: Option<u16>,
+    pub reason: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub call_id: Option<String>,
+    pub cseq: Option<String>,
+    pub via: Vec<String>,
+    pub headers: Vec<(String, String)>,
+    pub sdp: Option<SerializableSdpSession>,
+}
+
+/// SDP Session Description carried in a SIP body
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableSdpSession {
+    pub connection_address: Option<String>,
+    pub media: Vec<SdpMediaDescription>,
+}
+
+/// SDP Media Description, with the negotiated (RTP) port
+#[derive(Serialize, Debug, Clone)]
+pub struct SdpMediaDescription {
+    pub media_type: String,
+    pub port: u16,
+    pub protocol: String,
+    pub formats: Vec<String>,
+    pub connection_address: Option<String>,
+}
+
+/// DHCPv6 Packet Representation
+///
+/// Client/server messages carry `transaction_id`, relay messages carry `hop_count`, `link_address`,
+/// `peer_address` and the `relayed_message`
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableDhcpv6Packet {
+    pub message_type: u8,
+    pub message_type_name: String,
+    pub transaction_id: Option<u32>,
+    pub hop_count: Option<u8>,
+    pub link_address: Option<Ipv6Addr>,
+    pub peer_address: Option<Ipv6Addr>,
+    pub client_duid: Option<String>,
+    pub server_duid: Option<String>,
+    pub ia_na: Vec<Dhcpv6IdentityAssociation>,
+    pub status_code: Option<u16>,
+    pub dns_servers: Vec<Ipv6Addr>,
+    pub domain_search_list: Vec<String>,
+    pub relayed_message: Option<Box<SerializableDhcpv6Packet>>,
+    /// Codes of all the options, in order of appearance
+    pub options: Vec<u16>,
+}
+
+/// DHCPv6 Identity Association for Non-temporary Addresses (IA_NA)
+#[derive(Serialize, Debug, Clone)]
+pub struct Dhcpv6IdentityAssociation {
+    pub iaid: u32,
+    pub t1: u32,
+    pub t2: u32,
+    pub addresses: Vec<Dhcpv6IaAddress>,
+    pub status_code: Option<u16>,
+}
+
+/// DHCPv6 address assigned in an IA_NA, with its lifetimes in seconds
+#[derive(Serialize, Debug, Clone)]
+pub struct Dhcpv6IaAddress {
+    pub address: Ipv6Addr,
+    pub preferred_lifetime: u32,
+    pub valid_lifetime: u32,
+}
+
+/// Kerberos Packet Representation
+///
+/// Principal names are reported with their components joined by `/`, `error_code` is set for KRB-ERROR messages
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableKerberosPacket {
+    pub message_type: u8,
+    pub message_type_name: String,
+    pub realm: Option<String>,
+    pub client_name: Option<String>,
+    pub server_name: Option<String>,
+    /// Encryption types requested by the client, in order of preference
+    pub encryption_types: Vec<i32>,
+    /// Types of the pre-authentication data
+    pub padata_types: Vec<i32>,
+    pub error_code: Option<i32>,
+    pub error_name: Option<String>,
+    pub error_text: Option<String>,
+}
+
+/// RADIUS Packet Representation
+///
+/// Only the cleartext attributes are decoded, `attributes` lists the types of all of them in order of appearance
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableRadiusPacket {
+    pub code: u8,
+    pub code_name: String,
+    pub identifier: u8,
+    pub length: u16,
+    pub authenticator: String,
+    pub user_name: Option<String>,
+    pub nas_ip_address: Option<Ipv4Addr>,
+    pub nas_identifier: Option<String>,
+    pub framed_ip_address: Option<Ipv4Addr>,
+    pub called_station_id: Option<String>,
+    pub calling_station_id: Option<String>,
+    pub acct_status_type: Option<u32>,
+    pub acct_session_id: Option<String>,
+    pub reply_messages: Vec<String>,
+    pub attributes: Vec<u8>,
+}
+
+/// BitTorrent Packet Representation
+///
+/// `message_type` is either `Handshake`, `DHT Query`, `DHT Response` or `DHT Error`. Hashes and IDs are
+/// in hexadecimal
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SerializableBitTorrentPacket {
+    pub message_type: String,
+    /// Info-hash of the torrent, for handshakes and DHT `get_peers` and `announce_peer` queries
+    pub info_hash: Option<String>,
+    /// ID of the peer sending the handshake
+    pub peer_id: Option<String>,
+    /// Transaction ID of the DHT message
+    pub transaction_id: Option<String>,
+    /// Method of the DHT query
+    pub query: Option<String>,
+    pub error_code: Option<i64>,
+}
+
+/// Credential sent in cleartext, detected in audit mode
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SerializableCredential {
+    pub protocol: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
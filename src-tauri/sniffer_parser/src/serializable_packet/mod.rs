@@ -3,6 +3,7 @@
 //! A parsed packet contains the packet representation at each level of the TCP/IP stack (except physical):
 //! - link_layer_packet
 //! - network_layer_packet
+//! - tunnel_layer_packet (IPv6 packet decapsulated from an IPv6-in-IPv4 tunnel, if any)
 //! - transport_layer_packet
 //! - application_layer_packet
 //!
@@ -13,27 +14,102 @@ pub mod transport;
 #[cfg(feature = "utils")]
 pub mod util;
 
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
 use pnet::packet::Packet;
 use pnet::{packet::ethernet::EthernetPacket, util::MacAddr};
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 
 use self::application::{
-    SerializableDnsPacket, SerializableHttpRequestPacket, SerializableHttpResponsePacket,
-    SerializableTlsPacket,
+    SerializableBitTorrentPacket, SerializableCredential, SerializableDhcpv6Packet,
+    SerializableDnsPacket, SerializableHttp2Packet, SerializableHttpRequestPacket,
+    SerializableHttpResponsePacket, SerializableKerberosPacket, SerializableRadiusPacket,
+    SerializableSipPacket, SerializableTlsPacket,
+};
+use self::network::{
+    SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet, SerializableTunnelPacket,
 };
-use self::network::{SerializableArpPacket, SerializableIpv4Packet, SerializableIpv6Packet};
 use self::transport::{
     SerializableEchoReplyPacket, SerializableEchoRequestPacket, SerializableIcmpPacket,
     SerializableIcmpv6Packet, SerializableTcpPacket, SerializableUdpPacket,
 };
 
+/// Option of an IPv4 or TCP header, in the Type-Length-Value encoding
+pub(crate) enum RawOption<'a> {
+    EndOfOptionList,
+    NoOperation,
+    Option(u8, &'a [u8]),
+    /// Option whose length is invalid or exceeds the options field, with the remaining bytes
+    Malformed(u8, &'a [u8]),
+}
+
+/// Splits an options field in its options, up to the end of the option list
+pub(crate) fn split_options(mut options: &[u8]) -> Vec<RawOption<'_>> {
+    let mut raw_options = vec![];
+
+    while let Some(&kind) = options.first() {
+        match kind {
+            0 => {
+                raw_options.push(RawOption::EndOfOptionList);
+                break;
+            }
+            1 => {
+                raw_options.push(RawOption::NoOperation);
+                options = &options[1..];
+            }
+            _ => match options.get(1).map(|length| *length as usize) {
+                Some(length) if length >= 2 && length <= options.len() => {
+                    raw_options.push(RawOption::Option(kind, &options[2..length]));
+                    options = &options[length..];
+                }
+                _ => {
+                    raw_options.push(RawOption::Malformed(kind, &options[1..]));
+                    break;
+                }
+            },
+        }
+    }
+
+    raw_options
+}
+
 /// Data structure containing representations of the packet at each TCP/IP layer
 #[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ParsedPacket {
     id: usize,
+    timestamp: i64,
+    /// Interface the packet was captured on, none when loaded from a file
+    interface_name: Option<String>,
+    direction: Option<String>,
+    service: Option<String>,
+    /// Hardware vendors of the source and destination MAC addresses
+    source_vendor: Option<String>,
+    dest_vendor: Option<String>,
+    /// Whether the packet is marked to be found again
+    bookmarked: bool,
+    /// Kind of destination: unicast, multicast or broadcast
+    cast_type: Option<String>,
+    /// Hash of the flow, the same for both directions
+    flow_hash: Option<String>,
+    /// Index of the color of the flow in the conversation palette
+    conversation_color: Option<usize>,
+    /// Seconds since the first packet of its TCP or UDP flow
+    flow_time_relative: Option<f64>,
+    /// Shannon entropy of the transport layer payload, in bits per byte
+    payload_entropy: Option<f64>,
+    /// Link layer errors reported by the capture tool (pcapng files)
+    link_errors: Vec<String>,
+    /// Notes, warnings and errors of the parsing and of the analyses of the packet
+    expert_info: Vec<ExpertInfo>,
+    /// Cleartext credential carried by the packet, never sent along with it
+    #[serde(skip)]
+    credential: Option<SerializableCredential>,
     link_layer_packet: Option<SerializablePacket>,
     network_layer_packet: Option<SerializablePacket>,
+    tunnel_layer_packet: Option<SerializablePacket>,
     transport_layer_packet: Option<SerializablePacket>,
     application_layer_packet: Option<SerializablePacket>,
 }
@@ -42,8 +118,24 @@ impl ParsedPacket {
     pub fn new(id: usize) -> Self {
         ParsedPacket {
             id,
+            timestamp: 0,
+            interface_name: None,
+            direction: None,
+            service: None,
+            source_vendor: None,
+            dest_vendor: None,
+            bookmarked: false,
+            cast_type: None,
+            flow_hash: None,
+            conversation_color: None,
+            flow_time_relative: None,
+            payload_entropy: None,
+            link_errors: vec![],
+            expert_info: vec![],
+            credential: None,
             link_layer_packet: None,
             network_layer_packet: None,
+            tunnel_layer_packet: None,
             transport_layer_packet: None,
             application_layer_packet: None,
         }
@@ -54,6 +146,151 @@ impl ParsedPacket {
         self.id
     }
 
+    /// Get packet capture timestamp, in microseconds since the Unix epoch
+    pub fn get_timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Set packet capture timestamp, in microseconds since the Unix epoch
+    pub fn set_timestamp(&mut self, timestamp: i64) {
+        self.timestamp = timestamp;
+    }
+
+    /// Get name of the interface the packet was captured on
+    pub fn get_interface_name(&self) -> Option<&String> {
+        self.interface_name.as_ref()
+    }
+
+    /// Set name of the interface the packet was captured on
+    pub fn set_interface_name(&mut self, interface_name: Option<String>) {
+        self.interface_name = interface_name;
+    }
+
+    /// Get packet direction relative to a reference IP address, if requested
+    pub fn get_direction(&self) -> Option<&String> {
+        self.direction.as_ref()
+    }
+
+    /// Set packet direction relative to a reference IP address
+    pub fn set_direction(&mut self, direction: Option<String>) {
+        self.direction = direction;
+    }
+
+    /// Get name of the service associated to the transport ports, if requested
+    pub fn get_service(&self) -> Option<&String> {
+        self.service.as_ref()
+    }
+
+    /// Set name of the service associated to the transport ports
+    pub fn set_service(&mut self, service: Option<String>) {
+        self.service = service;
+    }
+
+    /// Get hardware vendors of the source and destination MAC addresses, if requested
+    pub fn get_vendors(&self) -> (Option<&String>, Option<&String>) {
+        (self.source_vendor.as_ref(), self.dest_vendor.as_ref())
+    }
+
+    /// Set hardware vendors of the source and destination MAC addresses
+    pub fn set_vendors(&mut self, source_vendor: Option<String>, dest_vendor: Option<String>) {
+        self.source_vendor = source_vendor;
+        self.dest_vendor = dest_vendor;
+    }
+
+    /// Get whether the packet is bookmarked
+    pub fn is_bookmarked(&self) -> bool {
+        self.bookmarked
+    }
+
+    /// Set whether the packet is bookmarked
+    pub fn set_bookmarked(&mut self, bookmarked: bool) {
+        self.bookmarked = bookmarked;
+    }
+
+    /// Get kind of destination of the packet
+    pub fn get_cast_type(&self) -> Option<&String> {
+        self.cast_type.as_ref()
+    }
+
+    /// Set kind of destination of the packet
+    pub fn set_cast_type(&mut self, cast_type: Option<String>) {
+        self.cast_type = cast_type;
+    }
+
+    /// Get hash of the flow of the packet
+    pub fn get_flow_hash(&self) -> Option<&String> {
+        self.flow_hash.as_ref()
+    }
+
+    /// Set hash of the flow of the packet
+    pub fn set_flow_hash(&mut self, flow_hash: Option<String>) {
+        self.flow_hash = flow_hash;
+    }
+
+    /// Get index of the color of the flow in the conversation palette, if requested
+    pub fn get_conversation_color(&self) -> Option<usize> {
+        self.conversation_color
+    }
+
+    /// Set index of the color of the flow in the conversation palette
+    pub fn set_conversation_color(&mut self, conversation_color: Option<usize>) {
+        self.conversation_color = conversation_color;
+    }
+
+    /// Get seconds since the first packet of the flow, if requested
+    pub fn get_flow_time_relative(&self) -> Option<f64> {
+        self.flow_time_relative
+    }
+
+    /// Set seconds since the first packet of the flow
+    pub fn set_flow_time_relative(&mut self, flow_time_relative: Option<f64>) {
+        self.flow_time_relative = flow_time_relative;
+    }
+
+    /// Get entropy of the transport layer payload, in bits per byte
+    pub fn get_payload_entropy(&self) -> Option<f64> {
+        self.payload_entropy
+    }
+
+    /// Set entropy of the transport layer payload, in bits per byte
+    pub fn set_payload_entropy(&mut self, payload_entropy: Option<f64>) {
+        self.payload_entropy = payload_entropy;
+    }
+
+    /// Get link layer errors reported by the capture tool
+    pub fn get_link_errors(&self) -> &Vec<String> {
+        &self.link_errors
+    }
+
+    /// Set link layer errors reported by the capture tool
+    pub fn set_link_errors(&mut self, link_errors: Vec<String>) {
+        self.link_errors = link_errors;
+    }
+
+    /// Get expert info annotations of the packet
+    pub fn get_expert_info(&self) -> &Vec<ExpertInfo> {
+        &self.expert_info
+    }
+
+    /// Add an expert info annotation to the packet
+    pub fn add_expert_info(&mut self, severity: &str, group: &str, message: String) {
+        self.expert_info.push(ExpertInfo {
+            severity: severity.to_owned(),
+            group: group.to_owned(),
+            message,
+        });
+    }
+
+    /// Get cleartext credential detected in audit mode
+    pub fn get_credential(&self) -> Option<&SerializableCredential> {
+        self.credential.as_ref()
+    }
+
+    /// Set cleartext credential detected in audit mode
+    pub fn set_credential(&mut self, credential: Option<SerializableCredential>) {
+        self.credential = credential;
+    }
+
     /// Get link layer packet representation
     pub fn get_link_layer_packet(&self) -> Option<&SerializablePacket> {
         self.link_layer_packet.as_ref()
@@ -64,6 +301,11 @@ impl ParsedPacket {
         self.network_layer_packet.as_ref()
     }
 
+    /// Get tunneled (decapsulated) network layer packet representation
+    pub fn get_tunnel_layer_packet(&self) -> Option<&SerializablePacket> {
+        self.tunnel_layer_packet.as_ref()
+    }
+
     /// Get transport layer packet representation
     pub fn get_transport_layer_packet(&self) -> Option<&SerializablePacket> {
         self.transport_layer_packet.as_ref()
@@ -84,6 +326,11 @@ impl ParsedPacket {
         self.network_layer_packet = network_layer_packet;
     }
 
+    /// Set tunneled (decapsulated) network layer packet representation
+    pub fn set_tunnel_layer_packet(&mut self, tunnel_layer_packet: Option<SerializablePacket>) {
+        self.tunnel_layer_packet = tunnel_layer_packet;
+    }
+
     /// Set transport layer packet representation
     pub fn set_transport_layer_packet(
         &mut self,
@@ -101,14 +348,51 @@ impl ParsedPacket {
     }
 }
 
+/// Severities of the expert info, from the least to the most severe
+#[allow(non_snake_case)]
+pub mod ExpertSeverities {
+    pub const CHAT: &str = "chat";
+    pub const NOTE: &str = "note";
+    pub const WARNING: &str = "warning";
+    pub const ERROR: &str = "error";
+
+    pub const ALL: [&str; 4] = [CHAT, NOTE, WARNING, ERROR];
+
+    /// Position of the severity in `ALL`, `None` if unknown
+    pub fn get_level(severity: &str) -> Option<usize> {
+        ALL.iter().position(|s| *s == severity)
+    }
+}
+
+/// Groups of the expert info, the kind of the reported condition
+#[allow(non_snake_case)]
+pub mod ExpertGroups {
+    pub const MALFORMED: &str = "malformed";
+    pub const CHECKSUM: &str = "checksum";
+    pub const SEQUENCE: &str = "sequence";
+    pub const LINK: &str = "link";
+    pub const UNDECODED: &str = "undecoded";
+}
+
+/// Condition of a packet worth the attention of the user (e.g. malformed layer, retransmission)
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExpertInfo {
+    pub severity: String,
+    pub group: String,
+    pub message: String,
+}
+
 /// All possible packet serialization options
 #[derive(Serialize, Debug, Clone)]
 #[serde(tag = "type", content = "packet")]
 pub enum SerializablePacket {
     EthernetPacket(SerializableEthernetPacket),
+    Dot11Packet(SerializableDot11Packet),
+    LoopbackPacket(SerializableLoopbackPacket),
     ArpPacket(SerializableArpPacket),
     Ipv4Packet(SerializableIpv4Packet),
     Ipv6Packet(SerializableIpv6Packet),
+    TunnelPacket(SerializableTunnelPacket),
     EchoReplyPacket(SerializableEchoReplyPacket),
     EchoRequestPacket(SerializableEchoRequestPacket),
     IcmpPacket(SerializableIcmpPacket),
@@ -117,11 +401,71 @@ pub enum SerializablePacket {
     UdpPacket(SerializableUdpPacket),
     HttpRequestPacket(SerializableHttpRequestPacket),
     HttpResponsePacket(SerializableHttpResponsePacket),
+    Http2Packet(SerializableHttp2Packet),
     TlsPacket(SerializableTlsPacket),
     DnsPacket(SerializableDnsPacket),
+    SipPacket(SerializableSipPacket),
+    Dhcpv6Packet(SerializableDhcpv6Packet),
+    KerberosPacket(SerializableKerberosPacket),
+    RadiusPacket(SerializableRadiusPacket),
+    BitTorrentPacket(SerializableBitTorrentPacket),
 
     MalformedPacket(String),
     UnknownPacket(SerializableUnknownPacket),
+    RawPacket(SerializableRawPacket),
+}
+
+/// Bytes of a packet, copied when parsed and then possibly shared with the frame they were captured in
+///
+/// Serialized as a `Vec<u8>` would be
+#[derive(Clone)]
+pub struct PacketBytes {
+    frame: Arc<dyn AsRef<[u8]> + Send + Sync>,
+    range: Range<usize>,
+}
+
+impl PacketBytes {
+    /// `range` of the bytes of `frame`, which must be in bounds
+    pub fn shared(frame: Arc<dyn AsRef<[u8]> + Send + Sync>, range: Range<usize>) -> Self {
+        assert!(range.start <= range.end && range.end <= (*frame).as_ref().len());
+        PacketBytes { frame, range }
+    }
+}
+
+impl From<Vec<u8>> for PacketBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        let range = 0..bytes.len();
+        PacketBytes {
+            frame: Arc::new(bytes),
+            range,
+        }
+    }
+}
+
+impl Deref for PacketBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &(*self.frame).as_ref()[self.range.clone()]
+    }
+}
+
+impl PartialEq<Vec<u8>> for PacketBytes {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        **self == other[..]
+    }
+}
+
+impl fmt::Debug for PacketBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Serialize for PacketBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
 }
 
 /// Ethernet Packet Representation
@@ -130,7 +474,7 @@ pub struct SerializableEthernetPacket {
     pub destination: MacAddr,
     pub source: MacAddr,
     pub ethertype: String,
-    pub payload: Vec<u8>,
+    pub payload: PacketBytes,
 }
 
 impl<'a> From<&EthernetPacket<'a>> for SerializableEthernetPacket {
@@ -139,11 +483,70 @@ impl<'a> From<&EthernetPacket<'a>> for SerializableEthernetPacket {
             destination: packet.get_destination(),
             source: packet.get_source(),
             ethertype: packet.get_ethertype().to_string(),
-            payload: packet.payload().to_vec(),
+            payload: packet.payload().to_vec().into(),
         }
     }
 }
 
+/// Loopback (DLT_NULL, DLT_LOOP) Packet Representation
+#[derive(Serialize, Debug, Clone)]
+pub struct SerializableLoopbackPacket {
+    /// Address family of the carried packet
+    pub family: u32,
+    pub protocol: String,
+    pub payload: PacketBytes,
+}
+
+/// Radiotap Header Representation
+#[derive(Serialize, Debug, Clone)]
+pub struct SerializableRadiotapHeader {
+    pub version: u8,
+    pub length: u16,
+    pub present: u32,
+    pub flags: Option<u8>,
+    /// Data rate in Mbps
+    pub rate: Option<f32>,
+    /// Channel frequency in MHz
+    pub channel_frequency: Option<u16>,
+    pub channel_flags: Option<u16>,
+    pub signal_dbm: Option<i8>,
+    pub noise_dbm: Option<i8>,
+}
+
+/// IEEE 802.11 Packet Representation
+#[derive(Serialize, Debug, Clone)]
+pub struct SerializableDot11Packet {
+    pub radiotap: Option<SerializableRadiotapHeader>,
+    pub frame_type: u8,
+    pub subtype: u8,
+    pub type_name: String,
+    pub to_ds: bool,
+    pub from_ds: bool,
+    pub protected: bool,
+    pub duration: u16,
+    /// Addresses in the order they appear in the header
+    pub addresses: Vec<MacAddr>,
+    pub destination: Option<MacAddr>,
+    pub source: Option<MacAddr>,
+    pub bssid: Option<MacAddr>,
+    pub sequence_number: Option<u16>,
+    /// Network name advertised by beacons and probes
+    pub ssid: Option<String>,
+    /// Length of the MAC header, preceding the body of the frame
+    pub header_length: usize,
+    pub length: usize,
+}
+
+/// Payload of a registered EtherType, labelled with the name of its protocol but left unparsed
+#[derive(Serialize, Debug, Clone)]
+pub struct SerializableRawPacket {
+    pub protocol: String,
+    pub ethertype: u16,
+    /// Payload as lowercase hex digits
+    pub payload: String,
+    pub length: usize,
+}
+
 /// Unknown Packet Representation
 #[derive(Serialize, Debug, Clone)]
 pub struct SerializableUnknownPacket {
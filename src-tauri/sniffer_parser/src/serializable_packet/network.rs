@@ -3,12 +3,14 @@
 use std::net::{Ipv4Addr, Ipv6Addr};
 
 use pnet::packet::arp::{ArpOperations, ArpPacket};
-use pnet::packet::ipv4::Ipv4Packet;
+use pnet::packet::ipv4::{self, Ipv4Packet};
 use pnet::packet::ipv6::Ipv6Packet;
 use pnet::packet::Packet;
 use pnet::util::MacAddr;
 use serde::Serialize;
 
+use super::{split_options, RawOption};
+
 /// ARP Packet Representation
 #[derive(Serialize, Debug, Clone)]
 pub struct SerializableArpPacket {
@@ -79,6 +81,142 @@ impl<'a> From<&Ipv6Packet<'a>> for SerializableIpv6Packet {
     }
 }
 
+/// IPv4 Option Representation
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", content = "value")]
+pub enum SerializableIpv4Option {
+    EndOfOptionList,
+    NoOperation,
+    /// Addresses recorded so far, among the ones the option has room for
+    RecordRoute {
+        pointer: u8,
+        addresses: Vec<Ipv4Addr>,
+    },
+    LooseSourceRoute {
+        pointer: u8,
+        addresses: Vec<Ipv4Addr>,
+    },
+    StrictSourceRoute {
+        pointer: u8,
+        addresses: Vec<Ipv4Addr>,
+    },
+    /// Timestamps recorded so far, with the address of the recording host when requested by the flag
+    Timestamp {
+        pointer: u8,
+        overflow: u8,
+        flag: u8,
+        timestamps: Vec<(Option<Ipv4Addr>, u32)>,
+    },
+    RouterAlert(u16),
+    Unknown {
+        kind: u8,
+        data: Vec<u8>,
+    },
+    /// Option with an invalid length, or truncated
+    Malformed {
+        kind: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// Parses the options field of an IPv4 header
+pub fn parse_ipv4_options(options: &[u8]) -> Vec<SerializableIpv4Option> {
+    split_options(options)
+        .into_iter()
+        .map(|option| match option {
+            RawOption::EndOfOptionList => SerializableIpv4Option::EndOfOptionList,
+            RawOption::NoOperation => SerializableIpv4Option::NoOperation,
+            RawOption::Option(kind, data) => {
+                parse_ipv4_option(kind, data).unwrap_or(SerializableIpv4Option::Malformed {
+                    kind,
+                    data: data.to_vec(),
+                })
+            }
+            RawOption::Malformed(kind, data) => SerializableIpv4Option::Malformed {
+                kind,
+                data: data.to_vec(),
+            },
+        })
+        .collect()
+}
+
+/// Parses a single IPv4 option, `None` if its content is not valid
+fn parse_ipv4_option(kind: u8, data: &[u8]) -> Option<SerializableIpv4Option> {
+    let read_address = |bytes: &[u8]| Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]);
+
+    match kind {
+        // Record Route, Loose Source Route, Strict Source Route
+        7 | 131 | 137 => {
+            let (&pointer, addresses) = data.split_first()?;
+            if pointer < 4 || addresses.len() % 4 != 0 {
+                return None;
+            }
+
+            let recorded = ((pointer as usize - 4) / 4).min(addresses.len() / 4);
+            let addresses = addresses
+                .chunks(4)
+                .take(recorded)
+                .map(read_address)
+                .collect();
+
+            Some(match kind {
+                7 => SerializableIpv4Option::RecordRoute { pointer, addresses },
+                131 => SerializableIpv4Option::LooseSourceRoute { pointer, addresses },
+                _ => SerializableIpv4Option::StrictSourceRoute { pointer, addresses },
+            })
+        }
+        // Timestamp
+        68 => {
+            let (&pointer, data) = data.split_first()?;
+            let (&overflow_flag, entries) = data.split_first()?;
+            let (overflow, flag) = (overflow_flag >> 4, overflow_flag & 0x0f);
+            let entry_length = match flag {
+                0 => 4,
+                1 | 3 => 8,
+                _ => return None,
+            };
+            if pointer < 5 || entries.len() % entry_length != 0 {
+                return None;
+            }
+
+            let recorded =
+                ((pointer as usize - 5) / entry_length).min(entries.len() / entry_length);
+            let timestamps = entries
+                .chunks(entry_length)
+                .take(recorded)
+                .map(|entry| match entry_length {
+                    4 => (
+                        None,
+                        u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                    ),
+                    _ => (
+                        Some(read_address(entry)),
+                        u32::from_be_bytes([entry[4], entry[5], entry[6], entry[7]]),
+                    ),
+                })
+                .collect();
+
+            Some(SerializableIpv4Option::Timestamp {
+                pointer,
+                overflow,
+                flag,
+                timestamps,
+            })
+        }
+        // Router Alert
+        148 => match data {
+            &[high, low] => Some(SerializableIpv4Option::RouterAlert(u16::from_be_bytes([
+                high, low,
+            ]))),
+            _ => None,
+        },
+        _ => Some(SerializableIpv4Option::Unknown {
+            kind,
+            data: data.to_vec(),
+        }),
+    }
+}
+
 /// IPv4 Packet Representation
 #[derive(Serialize, Debug, Clone)]
 pub struct SerializableIpv4Packet {
@@ -93,8 +231,11 @@ pub struct SerializableIpv4Packet {
     pub ttl: u8,
     pub next_level_protocol: String,
     pub checksum: u16,
+    /// Whether the header checksum matches the one recomputed over the captured header
+    pub checksum_valid: Option<bool>,
     pub source: Ipv4Addr,
     pub destination: Ipv4Addr,
+    pub parsed_options: Vec<SerializableIpv4Option>,
     pub length: usize,
 }
 
@@ -116,9 +257,18 @@ impl<'a> From<&Ipv4Packet<'a>> for SerializableIpv4Packet {
                 packet.get_next_level_protocol().0
             ),
             checksum: packet.get_checksum(),
+            checksum_valid: Some(ipv4::checksum(packet) == packet.get_checksum()),
             source: packet.get_source(),
             destination: packet.get_destination(),
+            parsed_options: parse_ipv4_options(packet.get_options_raw()),
             length: packet.payload().len(),
         }
     }
 }
+
+/// IPv6-in-IPv4 Tunnel Packet Representation (6to4 or Teredo), carrying the decapsulated IPv6 packet
+#[derive(Serialize, Debug, Clone)]
+pub struct SerializableTunnelPacket {
+    pub tunnel_type: String,
+    pub inner: SerializableIpv6Packet,
+}
@@ -2,12 +2,17 @@
 
 use pnet::packet::icmp::echo_reply::EchoReplyPacket;
 use pnet::packet::icmp::echo_request::EchoRequestPacket;
-use pnet::packet::icmp::{IcmpPacket, IcmpType, IcmpTypes};
+use pnet::packet::icmp::{self, IcmpPacket, IcmpType, IcmpTypes};
 use pnet::packet::icmpv6::{Icmpv6Packet, Icmpv6Type, Icmpv6Types};
+use pnet::packet::ip::IpNextHeaderProtocols;
+use pnet::packet::ipv4::Ipv4Packet;
 use pnet::packet::tcp::TcpPacket;
 use pnet::packet::udp::UdpPacket;
 use pnet::packet::Packet;
 use serde::Serialize;
+use std::net::Ipv4Addr;
+
+use super::{split_options, RawOption};
 
 /// TCP Packet Representation
 #[derive(Serialize, Debug, Clone)]
@@ -21,8 +26,11 @@ pub struct SerializableTcpPacket {
     pub flags: u16,
     pub window: u16,
     pub checksum: u16,
+    /// Whether the checksum matches the one recomputed over the pseudo-header and the captured segment
+    pub checksum_valid: Option<bool>,
     pub urgent_ptr: u16,
     pub options: Vec<u8>,
+    pub parsed_options: Vec<SerializableTcpOption>,
     pub length: usize,
 }
 
@@ -38,13 +46,86 @@ impl<'a> From<&TcpPacket<'a>> for SerializableTcpPacket {
             flags: packet.get_flags(),
             window: packet.get_window(),
             checksum: packet.get_checksum(),
+            checksum_valid: None,
             urgent_ptr: packet.get_urgent_ptr(),
             options: packet.get_options_raw().to_vec(),
+            parsed_options: parse_tcp_options(packet.get_options_raw()),
             length: packet.payload().len(),
         }
     }
 }
 
+/// TCP Option Representation
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", content = "value")]
+pub enum SerializableTcpOption {
+    EndOfOptionList,
+    NoOperation,
+    MaximumSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    /// Left and right edges of the selectively acknowledged blocks
+    Sack(Vec<(u32, u32)>),
+    Timestamps {
+        value: u32,
+        echo_reply: u32,
+    },
+    Unknown {
+        kind: u8,
+        data: Vec<u8>,
+    },
+    /// Option with an invalid length, or truncated
+    Malformed {
+        kind: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// Parses the options field of a TCP header
+pub fn parse_tcp_options(options: &[u8]) -> Vec<SerializableTcpOption> {
+    split_options(options)
+        .into_iter()
+        .map(|option| match option {
+            RawOption::EndOfOptionList => SerializableTcpOption::EndOfOptionList,
+            RawOption::NoOperation => SerializableTcpOption::NoOperation,
+            RawOption::Option(2, &[high, low]) => {
+                SerializableTcpOption::MaximumSegmentSize(u16::from_be_bytes([high, low]))
+            }
+            RawOption::Option(3, &[shift]) => SerializableTcpOption::WindowScale(shift),
+            RawOption::Option(4, &[]) => SerializableTcpOption::SackPermitted,
+            RawOption::Option(5, data) if !data.is_empty() && data.len() % 8 == 0 => {
+                SerializableTcpOption::Sack(
+                    data.chunks(8)
+                        .map(|block| (read_u32(&block[..4]), read_u32(&block[4..])))
+                        .collect(),
+                )
+            }
+            RawOption::Option(8, data) if data.len() == 8 => SerializableTcpOption::Timestamps {
+                value: read_u32(&data[..4]),
+                echo_reply: read_u32(&data[4..]),
+            },
+            RawOption::Option(kind @ 2..=5, data) | RawOption::Option(kind @ 8, data) => {
+                SerializableTcpOption::Malformed {
+                    kind,
+                    data: data.to_vec(),
+                }
+            }
+            RawOption::Option(kind, data) => SerializableTcpOption::Unknown {
+                kind,
+                data: data.to_vec(),
+            },
+            RawOption::Malformed(kind, data) => SerializableTcpOption::Malformed {
+                kind,
+                data: data.to_vec(),
+            },
+        })
+        .collect()
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
 /// UDP Packet Representation
 #[derive(Serialize, Debug, Clone)]
 pub struct SerializableUdpPacket {
@@ -52,6 +133,9 @@ pub struct SerializableUdpPacket {
     pub destination: u16,
     pub length: u16,
     pub checksum: u16,
+    /// Whether the checksum matches the one recomputed over the pseudo-header and the captured datagram,
+    /// `None` when the sender didn't compute it (zero checksum)
+    pub checksum_valid: Option<bool>,
 }
 
 impl<'a> From<&UdpPacket<'a>> for SerializableUdpPacket {
@@ -61,6 +145,7 @@ impl<'a> From<&UdpPacket<'a>> for SerializableUdpPacket {
             destination: packet.get_destination(),
             length: packet.get_length(),
             checksum: packet.get_checksum(),
+            checksum_valid: None,
         }
     }
 }
@@ -71,16 +156,32 @@ pub struct SerializableIcmpv6Packet {
     pub icmpv6_type: String,
     pub icmpv6_code: u8,
     pub checksum: u16,
+    /// Identifier and sequence number of echo requests and replies
+    pub identifier: Option<u16>,
+    pub sequence_number: Option<u16>,
     pub length: usize,
 }
 
 impl<'a> From<&Icmpv6Packet<'a>> for SerializableIcmpv6Packet {
     fn from(packet: &Icmpv6Packet<'a>) -> Self {
+        let payload = packet.payload();
+        let word = |offset: usize| {
+            payload
+                .get(offset..offset + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        };
+        let (identifier, sequence_number) = match packet.get_icmpv6_type() {
+            Icmpv6Types::EchoRequest | Icmpv6Types::EchoReply => (word(0), word(2)),
+            _ => (None, None),
+        };
+
         SerializableIcmpv6Packet {
             icmpv6_type: icmpv6_type_to_string(packet.get_icmpv6_type()),
             icmpv6_code: packet.get_icmpv6_code().0,
             checksum: packet.get_checksum(),
-            length: packet.payload().len(),
+            identifier,
+            sequence_number,
+            length: payload.len(),
         }
     }
 }
@@ -109,20 +210,96 @@ pub struct SerializableIcmpPacket {
     pub icmp_type: String,
     pub icmp_code: u8,
     pub checksum: u16,
+    pub checksum_valid: Option<bool>,
+    /// Headers of the packet that caused the error, carried by error messages
+    pub original: Option<SerializableIcmpOriginalPacket>,
     pub length: usize,
 }
 
 impl<'a> From<&IcmpPacket<'a>> for SerializableIcmpPacket {
     fn from(packet: &IcmpPacket<'a>) -> Self {
+        let original = match packet.get_icmp_type() {
+            IcmpTypes::DestinationUnreachable
+            | IcmpTypes::SourceQuench
+            | IcmpTypes::RedirectMessage
+            | IcmpTypes::TimeExceeded
+            | IcmpTypes::ParameterProblem => get_icmp_original_packet(packet.payload()),
+            _ => None,
+        };
+
         SerializableIcmpPacket {
             icmp_type: icmp_type_to_string(packet.get_icmp_type()),
             icmp_code: packet.get_icmp_code().0,
             checksum: packet.get_checksum(),
+            checksum_valid: is_icmp_checksum_valid(packet.packet()),
+            original,
             length: packet.payload().len(),
         }
     }
 }
 
+/// IPv4 header and first 8 bytes of the transport header of the packet an ICMP error refers to
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SerializableIcmpOriginalPacket {
+    pub source: Ipv4Addr,
+    pub destination: Ipv4Addr,
+    pub identification: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    /// Ports of UDP and TCP packets
+    pub source_port: Option<u16>,
+    pub destination_port: Option<u16>,
+    /// Identifier and sequence number of ICMP echo requests
+    pub echo_identifier: Option<u16>,
+    pub echo_sequence_number: Option<u16>,
+}
+
+/// Parses the original packet following the 4 unused bytes of an ICMP error message, `None` if truncated
+/// before the end of its IPv4 header
+fn get_icmp_original_packet(payload: &[u8]) -> Option<SerializableIcmpOriginalPacket> {
+    let data = payload.get(4..)?;
+    let ipv4_packet = Ipv4Packet::new(data)?;
+    let header_length = ipv4_packet.get_header_length() as usize * 4;
+    if ipv4_packet.get_version() != 4 || header_length < 20 || data.len() < header_length {
+        return None;
+    }
+
+    let transport = &data[header_length..];
+    let word = |offset: usize| {
+        transport
+            .get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+
+    let protocol = ipv4_packet.get_next_level_protocol();
+    let (ports, echo) = match protocol {
+        IpNextHeaderProtocols::Udp | IpNextHeaderProtocols::Tcp => {
+            ((word(0), word(2)), (None, None))
+        }
+        IpNextHeaderProtocols::Icmp if transport.first() == Some(&IcmpTypes::EchoRequest.0) => {
+            ((None, None), (word(4), word(6)))
+        }
+        _ => ((None, None), (None, None)),
+    };
+
+    Some(SerializableIcmpOriginalPacket {
+        source: ipv4_packet.get_source(),
+        destination: ipv4_packet.get_destination(),
+        identification: ipv4_packet.get_identification(),
+        ttl: ipv4_packet.get_ttl(),
+        protocol: protocol.0,
+        source_port: ports.0,
+        destination_port: ports.1,
+        echo_identifier: echo.0,
+        echo_sequence_number: echo.1,
+    })
+}
+
+/// Whether the ICMP checksum matches the one recomputed over the captured message
+fn is_icmp_checksum_valid(data: &[u8]) -> Option<bool> {
+    IcmpPacket::new(data).map(|packet| icmp::checksum(&packet) == packet.get_checksum())
+}
+
 /// Get ICMPv4 Message Type
 pub fn icmp_type_to_string(icmp_type: IcmpType) -> String {
     return match icmp_type {
@@ -152,6 +329,7 @@ pub struct SerializableEchoReplyPacket {
     pub icmp_type: u8,
     pub icmp_code: u8,
     pub checksum: u16,
+    pub checksum_valid: Option<bool>,
     pub identifier: u16,
     pub sequence_number: u16,
     pub length: usize,
@@ -163,7 +341,8 @@ impl<'a> From<&EchoReplyPacket<'a>> for SerializableEchoReplyPacket {
             icmp_type: packet.get_icmp_type().0,
             icmp_code: packet.get_icmp_code().0,
             checksum: packet.get_checksum(),
-            identifier: packet.get_checksum(),
+            checksum_valid: is_icmp_checksum_valid(packet.packet()),
+            identifier: packet.get_identifier(),
             sequence_number: packet.get_sequence_number(),
             length: packet.payload().len(),
         }
@@ -176,6 +355,7 @@ pub struct SerializableEchoRequestPacket {
     pub icmp_type: u8,
     pub icmp_code: u8,
     pub checksum: u16,
+    pub checksum_valid: Option<bool>,
     pub identifier: u16,
     pub sequence_number: u16,
     pub length: usize,
@@ -187,6 +367,7 @@ impl<'a> From<&EchoRequestPacket<'a>> for SerializableEchoRequestPacket {
             icmp_type: packet.get_icmp_type().0,
             icmp_code: packet.get_icmp_code().0,
             checksum: packet.get_checksum(),
+            checksum_valid: is_icmp_checksum_valid(packet.packet()),
             identifier: packet.get_identifier(),
             sequence_number: packet.get_sequence_number(),
             length: packet.payload().len(),
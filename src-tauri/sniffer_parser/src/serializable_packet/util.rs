@@ -1,7 +1,21 @@
 //! Utility functions to retrieve specific fields of packets
 
+use std::net::IpAddr;
+
+use pnet::util::MacAddr;
+
+use super::application::{CustomHandshakeMessage, CustomTlsMessage};
+use super::transport::SerializableTcpOption;
 use super::{ParsedPacket, SerializablePacket};
 
+/// Kind of destination of a packet
+#[allow(non_snake_case)]
+pub mod CastTypes {
+    pub const UNICAST: &str = "unicast";
+    pub const MULTICAST: &str = "multicast";
+    pub const BROADCAST: &str = "broadcast";
+}
+
 /// Get Source MAC address (Link layer sender)
 pub fn get_source_mac(packet: &ParsedPacket) -> Option<String> {
     if let Some(SerializablePacket::EthernetPacket(ethernet_packet)) =
@@ -10,6 +24,10 @@ pub fn get_source_mac(packet: &ParsedPacket) -> Option<String> {
         return Some(ethernet_packet.source.to_string());
     }
 
+    if let Some(SerializablePacket::Dot11Packet(dot11_packet)) = packet.get_link_layer_packet() {
+        return dot11_packet.source.map(|source| source.to_string());
+    }
+
     return None;
 }
 
@@ -21,6 +39,12 @@ pub fn get_dest_mac(packet: &ParsedPacket) -> Option<String> {
         return Some(ethernet_packet.destination.to_string());
     }
 
+    if let Some(SerializablePacket::Dot11Packet(dot11_packet)) = packet.get_link_layer_packet() {
+        return dot11_packet
+            .destination
+            .map(|destination| destination.to_string());
+    }
+
     return None;
 }
 
@@ -56,6 +80,36 @@ pub fn get_dest_ip(packet: &ParsedPacket) -> Option<String> {
     };
 }
 
+/// Get Source IP address of the tunneled IPv6 packet
+pub fn get_inner_source_ip(packet: &ParsedPacket) -> Option<String> {
+    if let Some(SerializablePacket::TunnelPacket(tunnel_packet)) = packet.get_tunnel_layer_packet()
+    {
+        return Some(tunnel_packet.inner.source.to_string());
+    }
+
+    return None;
+}
+
+/// Get Destination IP address of the tunneled IPv6 packet
+pub fn get_inner_dest_ip(packet: &ParsedPacket) -> Option<String> {
+    if let Some(SerializablePacket::TunnelPacket(tunnel_packet)) = packet.get_tunnel_layer_packet()
+    {
+        return Some(tunnel_packet.inner.destination.to_string());
+    }
+
+    return None;
+}
+
+/// Get the type of IPv6-in-IPv4 tunnel (6to4 or Teredo)
+pub fn get_tunnel_type(packet: &ParsedPacket) -> Option<String> {
+    if let Some(SerializablePacket::TunnelPacket(tunnel_packet)) = packet.get_tunnel_layer_packet()
+    {
+        return Some(tunnel_packet.tunnel_type.clone());
+    }
+
+    return None;
+}
+
 /// Get Source Port (Transport layer sender)
 pub fn get_source_port(packet: &ParsedPacket) -> Option<String> {
     return match packet.get_transport_layer_packet() {
@@ -82,6 +136,159 @@ pub fn get_dest_port(packet: &ParsedPacket) -> Option<String> {
     };
 }
 
+/// Get TCP Maximum Segment Size option (Transport layer)
+pub fn get_tcp_mss(packet: &ParsedPacket) -> Option<u16> {
+    if let Some(SerializablePacket::TcpPacket(tcp_packet)) = packet.get_transport_layer_packet() {
+        return tcp_packet
+            .parsed_options
+            .iter()
+            .find_map(|option| match option {
+                SerializableTcpOption::MaximumSegmentSize(mss) => Some(*mss),
+                _ => None,
+            });
+    }
+
+    return None;
+}
+
+/// Check if packet has an IPv4 header with a wrong checksum (Network layer)
+pub fn has_bad_ip_checksum(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Ipv4Packet(ipv4_packet)) = packet.get_network_layer_packet() {
+        return ipv4_packet.checksum_valid == Some(false);
+    }
+
+    return false;
+}
+
+/// Check if packet has a TCP segment with a wrong checksum (Transport layer)
+pub fn has_bad_tcp_checksum(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::TcpPacket(tcp_packet)) = packet.get_transport_layer_packet() {
+        return tcp_packet.checksum_valid == Some(false);
+    }
+
+    return false;
+}
+
+/// Get TCP Window Scale option shift count (Transport layer)
+pub fn get_tcp_window_scale(packet: &ParsedPacket) -> Option<u8> {
+    if let Some(SerializablePacket::TcpPacket(tcp_packet)) = packet.get_transport_layer_packet() {
+        return tcp_packet
+            .parsed_options
+            .iter()
+            .find_map(|option| match option {
+                SerializableTcpOption::WindowScale(shift) => Some(*shift),
+                _ => None,
+            });
+    }
+
+    return None;
+}
+
+/// Get destination kind (unicast, multicast, broadcast) from the destination MAC and IP addresses,
+/// the broadest one winning
+pub fn get_cast_type(packet: &ParsedPacket) -> Option<&'static str> {
+    let mac = get_dest_mac(packet).and_then(|mac| mac.parse::<MacAddr>().ok());
+    let ip = get_dest_ip(packet).and_then(|ip| ip.parse::<IpAddr>().ok());
+
+    if mac.is_none() && ip.is_none() {
+        return None;
+    }
+
+    let is_broadcast = |ip: &IpAddr| match ip {
+        IpAddr::V4(ip) => ip.is_broadcast(),
+        IpAddr::V6(_) => false,
+    };
+
+    if mac.map_or(false, |mac| mac.is_broadcast()) || ip.as_ref().map_or(false, is_broadcast) {
+        return Some(CastTypes::BROADCAST);
+    }
+
+    if mac.map_or(false, |mac| mac.is_multicast()) || ip.map_or(false, |ip| ip.is_multicast()) {
+        return Some(CastTypes::MULTICAST);
+    }
+
+    return Some(CastTypes::UNICAST);
+}
+
+/// Compute a hash of the flow of the packet, the same for both directions: the transport protocol and the
+/// endpoints (IP address and port), sorted, hashed with 64-bit FNV-1a
+///
+/// The hash is stable across runs, `None` for packets without an IP layer
+pub fn compute_flow_hash(packet: &ParsedPacket) -> Option<String> {
+    let protocol = match packet.get_network_layer_packet() {
+        Some(SerializablePacket::Ipv4Packet(_)) | Some(SerializablePacket::Ipv6Packet(_)) => {
+            if contains_tcp(packet) {
+                "tcp"
+            } else if contains_udp(packet) {
+                "udp"
+            } else if contains_icmp(packet) {
+                "icmp"
+            } else if contains_icmp6(packet) {
+                "icmpv6"
+            } else {
+                "ip"
+            }
+        }
+        _ => return None,
+    };
+
+    let port = |port: Option<String>| port.unwrap_or_default();
+    let source = (get_source_ip(packet)?, port(get_source_port(packet)));
+    let destination = (get_dest_ip(packet)?, port(get_dest_port(packet)));
+    let (first, second) = if source <= destination {
+        (source, destination)
+    } else {
+        (destination, source)
+    };
+
+    let tuple = format!(
+        "{}|{}|{}|{}|{}",
+        protocol, first.0, first.1, second.0, second.1
+    );
+
+    let hash = tuple.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    return Some(format!("{:016x}", hash));
+}
+
+/// Compute the Shannon entropy of a payload, in bits per byte (from 0, constant bytes, to 8, uniformly random
+/// bytes), `None` for empty payloads
+///
+/// Encrypted and compressed payloads get close to 8, while plaintext usually stays below 5
+pub fn compute_entropy(payload: &[u8]) -> Option<f64> {
+    if payload.is_empty() {
+        return None;
+    }
+
+    let mut occurrences = [0usize; 256];
+    for byte in payload {
+        occurrences[*byte as usize] += 1;
+    }
+
+    let length = payload.len() as f64;
+    let entropy = occurrences
+        .iter()
+        .filter(|occurrences| **occurrences > 0)
+        .map(|occurrences| {
+            let probability = *occurrences as f64 / length;
+            -probability * probability.log2()
+        })
+        .sum::<f64>();
+
+    return Some(entropy.max(0.0));
+}
+
+/// Check if packet is carried by a loopback frame (Link layer)
+pub fn contains_loopback(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::LoopbackPacket(_)) = packet.get_link_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
 /// Check if packet type is unknown
 pub fn contains_unknokn(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::UnknownPacket(_)) = packet.get_link_layer_packet() {
@@ -101,6 +308,10 @@ pub fn contains_malformed(packet: &ParsedPacket) -> bool {
         return true;
     }
 
+    if let Some(SerializablePacket::MalformedPacket(_)) = packet.get_tunnel_layer_packet() {
+        return true;
+    }
+
     if let Some(SerializablePacket::MalformedPacket(_)) = packet.get_transport_layer_packet() {
         return true;
     }
@@ -160,6 +371,15 @@ pub fn contains_icmp6(packet: &ParsedPacket) -> bool {
     return false;
 }
 
+/// Check if packet contains an IEEE 802.11 frame (Link layer)
+pub fn contains_dot11(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Dot11Packet(_)) = packet.get_link_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
 /// Check if packet contains ARP
 pub fn contains_arp(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::ArpPacket(_)) = packet.get_network_layer_packet() {
@@ -178,6 +398,15 @@ pub fn contains_ipv6(packet: &ParsedPacket) -> bool {
     return false;
 }
 
+/// Check if packet contains an IPv6-in-IPv4 tunnel
+pub fn contains_tunnel(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::TunnelPacket(_)) = packet.get_tunnel_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
 /// Check if packet contains IPv4 protocol (Network layer)
 pub fn contains_ipv4(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::Ipv4Packet(_)) = packet.get_network_layer_packet() {
@@ -196,6 +425,34 @@ pub fn contains_tls(packet: &ParsedPacket) -> bool {
     return false;
 }
 
+/// Get the MD5 hash of the JA3 fingerprint of the TLS Client Hello in the packet
+pub fn get_ja3_hash(packet: &ParsedPacket) -> Option<&str> {
+    get_handshake_messages(packet).find_map(|message| match message {
+        CustomHandshakeMessage::ClientHello(client_hello) => Some(client_hello.ja3.hash.as_str()),
+        _ => None,
+    })
+}
+
+/// Get the MD5 hash of the JA3S fingerprint of the TLS Server Hello in the packet
+pub fn get_ja3s_hash(packet: &ParsedPacket) -> Option<&str> {
+    get_handshake_messages(packet).find_map(|message| match message {
+        CustomHandshakeMessage::ServerHello(server_hello) => Some(server_hello.ja3s.hash.as_str()),
+        _ => None,
+    })
+}
+
+fn get_handshake_messages(packet: &ParsedPacket) -> impl Iterator<Item = &CustomHandshakeMessage> {
+    let messages = match packet.get_application_layer_packet() {
+        Some(SerializablePacket::TlsPacket(tls_packet)) => tls_packet.messages.as_slice(),
+        _ => &[],
+    };
+
+    messages.iter().filter_map(|message| match message {
+        CustomTlsMessage::Handshake(handshake) => Some(handshake),
+        _ => None,
+    })
+}
+
 /// Check if packet contains DNS protocol (Application layer)
 pub fn contains_dns(packet: &ParsedPacket) -> bool {
     if let Some(SerializablePacket::DnsPacket(_)) = packet.get_application_layer_packet() {
@@ -215,3 +472,57 @@ pub fn contains_http(packet: &ParsedPacket) -> bool {
 
     return false;
 }
+
+/// Check if packet contains HTTP/2 protocol (Application layer)
+pub fn contains_http2(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Http2Packet(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains SIP protocol (Application layer)
+pub fn contains_sip(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::SipPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains DHCPv6 protocol (Application layer)
+pub fn contains_dhcpv6(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::Dhcpv6Packet(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains Kerberos protocol (Application layer)
+pub fn contains_kerberos(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::KerberosPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains RADIUS protocol (Application layer)
+pub fn contains_radius(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::RadiusPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
+
+/// Check if packet contains BitTorrent protocol (Application layer)
+pub fn contains_bittorrent(packet: &ParsedPacket) -> bool {
+    if let Some(SerializablePacket::BitTorrentPacket(_)) = packet.get_application_layer_packet() {
+        return true;
+    }
+
+    return false;
+}
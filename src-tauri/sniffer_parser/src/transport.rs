@@ -3,16 +3,18 @@
 use pnet::packet::icmp::{echo_reply, echo_request, IcmpPacket, IcmpTypes};
 use pnet::packet::icmpv6::Icmpv6Packet;
 use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
-use pnet::packet::tcp::TcpPacket;
-use pnet::packet::udp::UdpPacket;
+use pnet::packet::tcp::{self, TcpPacket};
+use pnet::packet::udp::{self, UdpPacket};
 
 use std::net::IpAddr;
 
+use crate::application::credentials::scan_credentials;
 use crate::application::handle_application_protocol;
 use crate::serializable_packet::transport::{
     SerializableEchoReplyPacket, SerializableEchoRequestPacket, SerializableIcmpPacket,
     SerializableIcmpv6Packet, SerializableTcpPacket, SerializableUdpPacket,
 };
+use crate::serializable_packet::util::compute_entropy;
 
 const ACK_BIT_SHIFT: usize = 4;
 const FIN_BIT_SHIFT: usize = 0;
@@ -38,19 +40,24 @@ pub fn handle_udp_packet(
             udp.get_length()
         );
 
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::UdpPacket(
-            SerializableUdpPacket::from(&udp),
-        )));
+        let mut udp_packet = SerializableUdpPacket::from(&udp);
+        udp_packet.checksum_valid = is_udp_checksum_valid(&udp, source, destination);
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::UdpPacket(udp_packet)));
+        parsed_packet.set_payload_entropy(compute_entropy(udp.payload()));
 
-        handle_application_protocol(
-            source,
-            udp.get_source(),
-            destination,
-            udp.get_destination(),
-            false,
-            udp.payload(),
-            parsed_packet,
-        );
+        if udp.get_source() == TEREDO_PORT || udp.get_destination() == TEREDO_PORT {
+            handle_teredo_packet(udp.payload(), parsed_packet);
+        } else {
+            handle_application_protocol(
+                source,
+                udp.get_source(),
+                destination,
+                udp.get_destination(),
+                false,
+                udp.payload(),
+                parsed_packet,
+            );
+        }
     } else {
         debug!("Malformed UDP Packet");
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
@@ -77,9 +84,10 @@ pub fn handle_tcp_packet(
             packet.len()
         );
 
-        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(
-            SerializableTcpPacket::from(&tcp),
-        )));
+        let mut tcp_packet = SerializableTcpPacket::from(&tcp);
+        tcp_packet.checksum_valid = is_tcp_checksum_valid(&tcp, source, destination);
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+        parsed_packet.set_payload_entropy(compute_entropy(tcp.payload()));
 
         let flags = tcp.get_flags();
         let is_fin = (flags & (1 << ACK_BIT_SHIFT)) != 0 && (flags & (1 << FIN_BIT_SHIFT)) != 0;
@@ -93,6 +101,16 @@ pub fn handle_tcp_packet(
             tcp.payload(),
             parsed_packet,
         );
+
+        scan_credentials(
+            source,
+            tcp.get_source(),
+            destination,
+            tcp.get_destination(),
+            is_fin,
+            tcp.payload(),
+            parsed_packet,
+        );
     } else {
         debug!("Malformed TCP Packet");
         parsed_packet.set_transport_layer_packet(Some(SerializablePacket::MalformedPacket(
@@ -101,6 +119,41 @@ pub fn handle_tcp_packet(
     }
 }
 
+/// Whether the TCP checksum matches the one recomputed with the IP pseudo-header
+fn is_tcp_checksum_valid(tcp: &TcpPacket, source: IpAddr, destination: IpAddr) -> Option<bool> {
+    let checksum = match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            tcp::ipv4_checksum(tcp, &source, &destination)
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            tcp::ipv6_checksum(tcp, &source, &destination)
+        }
+        _ => return None,
+    };
+
+    Some(checksum == tcp.get_checksum())
+}
+
+/// Whether the UDP checksum matches the one recomputed with the IP pseudo-header, `None` if not computed
+/// by the sender or if the datagram was truncated
+fn is_udp_checksum_valid(udp: &UdpPacket, source: IpAddr, destination: IpAddr) -> Option<bool> {
+    if udp.get_checksum() == 0 || (udp.get_length() as usize) > udp.packet().len() {
+        return None;
+    }
+
+    let checksum = match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            udp::ipv4_checksum(udp, &source, &destination)
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            udp::ipv6_checksum(udp, &source, &destination)
+        }
+        _ => return None,
+    };
+
+    Some(checksum == udp.get_checksum())
+}
+
 /// Build a Transport-layer packet from a network-layer packet, save it in a Parsed Packet
 pub fn handle_transport_protocol(
     source: IpAddr,
@@ -118,6 +171,9 @@ pub fn handle_transport_protocol(
         IpNextHeaderProtocols::Icmpv6 => {
             handle_icmpv6_packet(source, destination, packet, parsed_packet)
         }
+        IpNextHeaderProtocols::Ipv6 if source.is_ipv4() => {
+            handle_tunneled_ipv6_packet(TunnelTypes::SIX_TO_FOUR, packet, true, parsed_packet)
+        }
         _ => {
             debug!(
                 "Unknown {} packet: {} > {}; protocol: {:?} length: {}",
@@ -227,6 +283,7 @@ pub fn handle_icmpv6_packet(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use std::net::IpAddr;
     use std::net::Ipv4Addr;
 
@@ -243,6 +300,7 @@ mod tests {
 
     use crate::serializable_packet::transport::icmp_type_to_string;
     use crate::serializable_packet::transport::icmpv6_type_to_string;
+    use crate::serializable_packet::transport::SerializableTcpOption;
 
     use super::*;
 
@@ -287,6 +345,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn udp_packet_with_disabled_dissector() {
+        let mut udp_buffer = [0u8; 42];
+        let mut udp_packet = MutableUdpPacket::new(udp_buffer.as_mut_slice()).unwrap();
+        udp_packet.set_source(4444);
+        udp_packet.set_destination(53);
+        udp_packet.set_length(42);
+
+        let parse = || {
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_udp_packet(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                udp_packet.packet(),
+                &mut parsed_packet,
+            );
+            parsed_packet
+        };
+
+        assert!(parse().get_application_layer_packet().is_some());
+
+        set_enabled_dissectors(&HashSet::from([Dissectors::HTTP.to_string()]));
+        let parsed_packet = parse();
+        set_enabled_dissectors(&Dissectors::ALL.iter().map(|d| d.to_string()).collect());
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::UdpPacket(new_udp_packet) => {
+                assert_eq!(new_udp_packet.destination, 53)
+            }
+            _ => unreachable!(),
+        }
+        assert!(parsed_packet.get_application_layer_packet().is_none());
+    }
+
     #[test]
     fn valid_tcp_packet() {
         let mut tcp_buffer = [0u8; 42];
@@ -322,6 +414,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tcp_packet_with_options() {
+        let mut tcp_buffer = [0u8; 44];
+        tcp_buffer[20..].copy_from_slice(&[
+            2, 4, 0x05, 0xb4, // MSS 1460
+            1, 3, 3, 7, // NOP, Window Scale 7
+            4, 2, // SACK permitted
+            8, 10, 0, 0, 0, 1, 0, 0, 0, 2, // Timestamps
+            5, 7, 0, 0, // Truncated SACK
+        ]);
+        let mut tcp_packet = MutableTcpPacket::new(&mut tcp_buffer).unwrap();
+        tcp_packet.set_data_offset(11);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_tcp_packet(
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            tcp_packet.packet(),
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::TcpPacket(new_tcp_packet) => assert_eq!(
+                new_tcp_packet.parsed_options,
+                vec![
+                    SerializableTcpOption::MaximumSegmentSize(1460),
+                    SerializableTcpOption::NoOperation,
+                    SerializableTcpOption::WindowScale(7),
+                    SerializableTcpOption::SackPermitted,
+                    SerializableTcpOption::Timestamps {
+                        value: 1,
+                        echo_reply: 2
+                    },
+                    SerializableTcpOption::Malformed {
+                        kind: 5,
+                        data: vec![7, 0, 0]
+                    },
+                ]
+            ),
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_tcp_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -341,6 +476,8 @@ mod tests {
     #[test]
     fn valid_icmp_echo_reply_packet() {
         let mut icmp_buffer = [0u8; 42];
+        // Checksum, identifier and sequence number all different
+        icmp_buffer[2..8].copy_from_slice(&[0x11, 0x11, 0x22, 0x22, 0x00, 0x07]);
 
         let echo_reply_packet = echo_reply::EchoReplyPacket::new(&mut icmp_buffer).unwrap();
         let mut parsed_packet = ParsedPacket::new(0);
@@ -458,6 +595,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn time_exceeded_icmp_packet() {
+        // Type, code, checksum, unused, then the original IPv4 header and UDP header
+        let mut icmp_buffer = vec![11, 0, 0, 0, 0, 0, 0, 0];
+        icmp_buffer.extend_from_slice(&[
+            0x45, 0, 0, 60, 0x12, 0x34, 0, 0, 1, 17, 0, 0, 10, 10, 10, 10, 8, 8, 8, 8,
+        ]);
+        icmp_buffer.extend_from_slice(&[0x9c, 0x40, 0x82, 0x9a, 0, 40, 0, 0]);
+
+        let mut parsed_packet = ParsedPacket::new(0);
+        handle_icmp_packet(
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            &icmp_buffer,
+            &mut parsed_packet,
+        );
+
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::IcmpPacket(new_icmp_packet) => {
+                let original = new_icmp_packet.original.as_ref().unwrap();
+                assert_eq!(original.source, Ipv4Addr::new(10, 10, 10, 10));
+                assert_eq!(original.destination, Ipv4Addr::new(8, 8, 8, 8));
+                assert_eq!(original.identification, 0x1234);
+                assert_eq!(original.ttl, 1);
+                assert_eq!(original.source_port, Some(40000));
+                assert_eq!(original.destination_port, Some(33434));
+                assert_eq!(original.echo_identifier, None);
+            }
+            _ => unreachable!(),
+        }
+
+        // Truncated original header
+        let mut parsed_packet = ParsedPacket::new(1);
+        handle_icmp_packet(
+            IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+            &icmp_buffer[..20],
+            &mut parsed_packet,
+        );
+        match parsed_packet.get_transport_layer_packet().unwrap() {
+            SerializablePacket::IcmpPacket(new_icmp_packet) => {
+                assert!(new_icmp_packet.original.is_none())
+            }
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn malformed_icmp_packet() {
         let mut parsed_packet = ParsedPacket::new(0);
@@ -522,6 +706,95 @@ mod tests {
 
     ///////////////////// Utils
 
+    #[test]
+    fn tcp_and_udp_checksums() {
+        let (source, destination) = (Ipv4Addr::new(10, 10, 10, 10), Ipv4Addr::new(11, 11, 11, 11));
+
+        let mut tcp_buffer = [0u8; 24];
+        let mut tcp_packet = MutableTcpPacket::new(tcp_buffer.as_mut_slice()).unwrap();
+        tcp_packet.set_source(4444);
+        tcp_packet.set_destination(4445);
+        tcp_packet.set_data_offset(5);
+        tcp_packet.set_payload(b"ping");
+        let checksum = tcp::ipv4_checksum(&tcp_packet.to_immutable(), &source, &destination);
+        tcp_packet.set_checksum(checksum);
+
+        let tcp_checksum_valid = |packet: &[u8]| {
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_tcp_packet(
+                source.into(),
+                destination.into(),
+                packet,
+                &mut parsed_packet,
+            );
+            match parsed_packet.get_transport_layer_packet().unwrap() {
+                SerializablePacket::TcpPacket(new_tcp_packet) => new_tcp_packet.checksum_valid,
+                _ => unreachable!(),
+            }
+        };
+
+        assert_eq!(tcp_checksum_valid(tcp_packet.packet()), Some(true));
+        tcp_packet.set_payload(b"pong");
+        assert_eq!(tcp_checksum_valid(tcp_packet.packet()), Some(false));
+
+        let mut udp_buffer = [0u8; 12];
+        let mut udp_packet = MutableUdpPacket::new(udp_buffer.as_mut_slice()).unwrap();
+        udp_packet.set_source(4444);
+        udp_packet.set_destination(4445);
+        udp_packet.set_length(12);
+        udp_packet.set_payload(b"ping");
+
+        let udp_checksum_valid = |packet: &[u8]| {
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_udp_packet(
+                source.into(),
+                destination.into(),
+                packet,
+                &mut parsed_packet,
+            );
+            match parsed_packet.get_transport_layer_packet().unwrap() {
+                SerializablePacket::UdpPacket(new_udp_packet) => new_udp_packet.checksum_valid,
+                _ => unreachable!(),
+            }
+        };
+
+        // Not computed by the sender
+        assert_eq!(udp_checksum_valid(udp_packet.packet()), None);
+
+        let checksum = udp::ipv4_checksum(&udp_packet.to_immutable(), &source, &destination);
+        udp_packet.set_checksum(checksum);
+        assert_eq!(udp_checksum_valid(udp_packet.packet()), Some(true));
+        udp_packet.set_checksum(checksum.wrapping_add(1));
+        assert_eq!(udp_checksum_valid(udp_packet.packet()), Some(false));
+    }
+
+    #[test]
+    fn payload_entropy() {
+        let entropy = |payload: &[u8]| {
+            let mut tcp_buffer = vec![0u8; 20 + payload.len()];
+            let mut tcp_packet = MutableTcpPacket::new(tcp_buffer.as_mut_slice()).unwrap();
+            tcp_packet.set_source(4444);
+            tcp_packet.set_destination(443);
+            tcp_packet.set_data_offset(5);
+            tcp_packet.set_payload(payload);
+
+            let mut parsed_packet = ParsedPacket::new(0);
+            handle_tcp_packet(
+                IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+                IpAddr::V4(Ipv4Addr::new(11, 11, 11, 11)),
+                tcp_packet.packet(),
+                &mut parsed_packet,
+            );
+            parsed_packet.get_payload_entropy()
+        };
+
+        let uniform = (0..=255).collect::<Vec<u8>>();
+        assert_eq!(entropy(&uniform), Some(8.0));
+        assert_eq!(entropy(&[0x41; 64]), Some(0.0));
+        assert_eq!(entropy(b"abab"), Some(1.0));
+        assert_eq!(entropy(&[]), None);
+    }
+
     fn build_test_udp_packet<'a>(udp_buffer: &'a mut [u8]) -> UdpPacket<'a> {
         let mut udp_packet = MutableUdpPacket::new(udp_buffer).unwrap();
 
@@ -0,0 +1,453 @@
+//! IEEE 802.11 frame parsing, optionally preceded by a Radiotap header (monitor mode)
+
+use log::debug;
+use pnet::packet::ethernet::EtherTypes;
+use pnet::util::MacAddr;
+
+use super::*;
+use crate::serializable_packet::{SerializableDot11Packet, SerializableRadiotapHeader};
+
+/// 802.11 frame types
+#[allow(non_snake_case)]
+pub mod Dot11FrameTypes {
+    pub const MANAGEMENT: u8 = 0;
+    pub const CONTROL: u8 = 1;
+    pub const DATA: u8 = 2;
+}
+
+/// Radiotap present flags of the decoded fields
+#[allow(non_snake_case)]
+mod RadiotapFields {
+    pub const TSFT: u32 = 0;
+    pub const FLAGS: u32 = 1;
+    pub const RATE: u32 = 2;
+    pub const CHANNEL: u32 = 3;
+    pub const FHSS: u32 = 4;
+    pub const ANTENNA_SIGNAL: u32 = 5;
+    pub const ANTENNA_NOISE: u32 = 6;
+    pub const EXTENDED: u32 = 31;
+}
+
+/// Radiotap flag: the frame includes the FCS at the end
+const RADIOTAP_FLAG_FCS: u8 = 0x10;
+/// Frame control flags
+const FLAG_TO_DS: u8 = 0x01;
+const FLAG_FROM_DS: u8 = 0x02;
+const FLAG_PROTECTED: u8 = 0x40;
+const FLAG_ORDER: u8 = 0x80;
+/// Management information element carrying the SSID
+const SSID_ELEMENT: u8 = 0;
+/// LLC/SNAP header preceding the EtherType in data frames
+const LLC_SNAP_HEADER: [u8; 6] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00];
+
+/// Parse a Radiotap header followed by an 802.11 frame, save it in a Parsed Packet
+pub fn parse_radiotap_frame(packet: &[u8], id: usize) -> ParsedPacket {
+    let mut parsed_packet = ParsedPacket::new(id);
+
+    match parse_radiotap_header(packet) {
+        Some(radiotap) => {
+            let mut frame = &packet[radiotap.length as usize..];
+            if radiotap.flags.unwrap_or_default() & RADIOTAP_FLAG_FCS != 0 {
+                frame = &frame[..frame.len().saturating_sub(4)];
+            }
+
+            handle_dot11_frame(frame, Some(radiotap), &mut parsed_packet);
+        }
+        None => {
+            debug!("Malformed Radiotap Packet");
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed Radiotap Packet".to_string(),
+            )));
+        }
+    }
+
+    parsed_packet
+}
+
+/// Parse an 802.11 frame without Radiotap header, save it in a Parsed Packet
+pub fn parse_dot11_frame(packet: &[u8], id: usize) -> ParsedPacket {
+    let mut parsed_packet = ParsedPacket::new(id);
+    handle_dot11_frame(packet, None, &mut parsed_packet);
+
+    parsed_packet
+}
+
+fn parse_radiotap_header(packet: &[u8]) -> Option<SerializableRadiotapHeader> {
+    let version = *packet.first()?;
+    let length = u16::from_le_bytes(packet.get(2..4)?.try_into().ok()?);
+    if version != 0 || (length as usize) < 8 || packet.len() < length as usize {
+        return None;
+    }
+
+    let header = &packet[..length as usize];
+    let present = u32::from_le_bytes(header[4..8].try_into().ok()?);
+
+    // Skip the extended present bitmaps
+    let mut offset = 8;
+    let mut bitmap = present;
+    while bitmap & (1 << RadiotapFields::EXTENDED) != 0 {
+        bitmap = u32::from_le_bytes(header.get(offset..offset + 4)?.try_into().ok()?);
+        offset += 4;
+    }
+
+    let mut radiotap = SerializableRadiotapHeader {
+        version,
+        length,
+        present,
+        flags: None,
+        rate: None,
+        channel_frequency: None,
+        channel_flags: None,
+        signal_dbm: None,
+        noise_dbm: None,
+    };
+
+    // Fields are in bit order, each aligned to its natural size
+    let field = |offset: &mut usize, size: usize, alignment: usize| {
+        *offset = (*offset + alignment - 1) / alignment * alignment;
+        let value = header.get(*offset..*offset + size);
+        *offset += size;
+        value
+    };
+
+    for bit in RadiotapFields::TSFT..=RadiotapFields::ANTENNA_NOISE {
+        if present & (1 << bit) == 0 {
+            continue;
+        }
+
+        match bit {
+            RadiotapFields::TSFT => {
+                field(&mut offset, 8, 8)?;
+            }
+            RadiotapFields::FLAGS => radiotap.flags = Some(field(&mut offset, 1, 1)?[0]),
+            RadiotapFields::RATE => {
+                radiotap.rate = Some(field(&mut offset, 1, 1)?[0] as f32 / 2.0);
+            }
+            RadiotapFields::CHANNEL => {
+                let channel = field(&mut offset, 4, 2)?;
+                radiotap.channel_frequency = Some(u16::from_le_bytes([channel[0], channel[1]]));
+                radiotap.channel_flags = Some(u16::from_le_bytes([channel[2], channel[3]]));
+            }
+            RadiotapFields::FHSS => {
+                field(&mut offset, 2, 1)?;
+            }
+            RadiotapFields::ANTENNA_SIGNAL => {
+                radiotap.signal_dbm = Some(field(&mut offset, 1, 1)?[0] as i8);
+            }
+            RadiotapFields::ANTENNA_NOISE => {
+                radiotap.noise_dbm = Some(field(&mut offset, 1, 1)?[0] as i8);
+            }
+            _ => (),
+        }
+    }
+
+    Some(radiotap)
+}
+
+fn handle_dot11_frame(
+    packet: &[u8],
+    radiotap: Option<SerializableRadiotapHeader>,
+    parsed_packet: &mut ParsedPacket,
+) {
+    match parse_dot11_header(packet, radiotap) {
+        Some((dot11_packet, body)) => {
+            debug!(
+                "802.11 packet: {:?} > {:?}; type: {}, subtype: {}, SSID: {:?}",
+                dot11_packet.source,
+                dot11_packet.destination,
+                dot11_packet.frame_type,
+                dot11_packet.subtype,
+                dot11_packet.ssid
+            );
+
+            let is_plain_data = dot11_packet.frame_type == Dot11FrameTypes::DATA
+                && !dot11_packet.protected
+                && body.starts_with(&LLC_SNAP_HEADER)
+                && body.len() >= 8;
+            let (source, destination) = (dot11_packet.source, dot11_packet.destination);
+
+            parsed_packet
+                .set_link_layer_packet(Some(SerializablePacket::Dot11Packet(dot11_packet)));
+
+            // Data frames carry an LLC/SNAP encapsulated network layer packet
+            if is_plain_data {
+                let ethertype = u16::from_be_bytes([body[6], body[7]]);
+                let payload = &body[8..];
+
+                match ethertype {
+                    ethertype if ethertype == EtherTypes::Ipv4.0 => {
+                        handle_ipv4_packet(payload, parsed_packet)
+                    }
+                    ethertype if ethertype == EtherTypes::Ipv6.0 => {
+                        handle_ipv6_packet(payload, parsed_packet)
+                    }
+                    ethertype if ethertype == EtherTypes::Arp.0 => handle_arp_packet(
+                        payload,
+                        source.unwrap_or(MacAddr::zero()),
+                        destination.unwrap_or(MacAddr::zero()),
+                        parsed_packet,
+                    ),
+                    _ => (),
+                }
+            }
+        }
+        None => {
+            debug!("Malformed 802.11 Packet");
+            parsed_packet.set_link_layer_packet(Some(SerializablePacket::MalformedPacket(
+                "Malformed 802.11 Packet".to_string(),
+            )));
+        }
+    }
+}
+
+/// Parse the 802.11 MAC header, returning the frame representation and its body
+fn parse_dot11_header(
+    packet: &[u8],
+    radiotap: Option<SerializableRadiotapHeader>,
+) -> Option<(SerializableDot11Packet, &[u8])> {
+    let frame_control = packet.get(0..2)?;
+    let frame_type = (frame_control[0] >> 2) & 0x03;
+    let subtype = frame_control[0] >> 4;
+    let flags = frame_control[1];
+    let duration = u16::from_le_bytes(packet.get(2..4)?.try_into().ok()?);
+
+    let address = |index: usize| -> Option<MacAddr> {
+        let bytes = packet.get(4 + index * 6..10 + index * 6)?;
+        Some(MacAddr::new(
+            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5],
+        ))
+    };
+
+    let mut addresses = vec![address(0)?];
+    let mut offset = 10;
+    let mut sequence_number = None;
+
+    if frame_type == Dot11FrameTypes::CONTROL {
+        // Only RTS, PS-Poll, Block Ack (Request) and CF-End carry the transmitter address
+        if matches!(subtype, 8..=11 | 14 | 15) {
+            addresses.push(address(1)?);
+            offset = 16;
+        }
+    } else {
+        addresses.push(address(1)?);
+        addresses.push(address(2)?);
+        let sequence_control = u16::from_le_bytes(packet.get(22..24)?.try_into().ok()?);
+        sequence_number = Some(sequence_control >> 4);
+        offset = 24;
+
+        if frame_type == Dot11FrameTypes::DATA
+            && flags & FLAG_TO_DS != 0
+            && flags & FLAG_FROM_DS != 0
+        {
+            addresses.push(address(3)?);
+            offset = 30;
+        }
+
+        // QoS data frames
+        if frame_type == Dot11FrameTypes::DATA && subtype & 0x08 != 0 {
+            offset += 2;
+            if flags & FLAG_ORDER != 0 {
+                offset += 4;
+            }
+        }
+    }
+
+    let body = packet.get(offset..)?;
+
+    let (destination, source, bssid) = match (frame_type, flags & (FLAG_TO_DS | FLAG_FROM_DS)) {
+        (Dot11FrameTypes::CONTROL, _) => (Some(addresses[0]), addresses.get(1).copied(), None),
+        (_, 0) => (Some(addresses[0]), Some(addresses[1]), Some(addresses[2])),
+        (_, FLAG_TO_DS) => (Some(addresses[2]), Some(addresses[1]), Some(addresses[0])),
+        (_, FLAG_FROM_DS) => (Some(addresses[0]), Some(addresses[2]), Some(addresses[1])),
+        _ => (Some(addresses[2]), addresses.get(3).copied(), None),
+    };
+
+    let ssid = match (frame_type, subtype) {
+        // Beacon and Probe Response: timestamp, beacon interval and capabilities precede the elements
+        (Dot11FrameTypes::MANAGEMENT, 8) | (Dot11FrameTypes::MANAGEMENT, 5) => {
+            find_ssid(body.get(12..)?)
+        }
+        // Probe Request
+        (Dot11FrameTypes::MANAGEMENT, 4) => find_ssid(body),
+        _ => None,
+    };
+
+    Some((
+        SerializableDot11Packet {
+            radiotap,
+            frame_type,
+            subtype,
+            type_name: get_frame_type_name(frame_type, subtype).to_owned(),
+            to_ds: flags & FLAG_TO_DS != 0,
+            from_ds: flags & FLAG_FROM_DS != 0,
+            protected: flags & FLAG_PROTECTED != 0,
+            duration,
+            addresses,
+            destination,
+            source,
+            bssid,
+            sequence_number,
+            ssid,
+            header_length: offset,
+            length: packet.len(),
+        },
+        body,
+    ))
+}
+
+/// Search the SSID among the management information elements
+fn find_ssid(mut elements: &[u8]) -> Option<String> {
+    while elements.len() >= 2 {
+        let (id, length) = (elements[0], elements[1] as usize);
+        let value = elements.get(2..2 + length)?;
+
+        if id == SSID_ELEMENT {
+            return Some(String::from_utf8_lossy(value).to_string());
+        }
+
+        elements = &elements[2 + length..];
+    }
+
+    None
+}
+
+fn get_frame_type_name(frame_type: u8, subtype: u8) -> &'static str {
+    match (frame_type, subtype) {
+        (Dot11FrameTypes::MANAGEMENT, 0) => "Association Request",
+        (Dot11FrameTypes::MANAGEMENT, 1) => "Association Response",
+        (Dot11FrameTypes::MANAGEMENT, 2) => "Reassociation Request",
+        (Dot11FrameTypes::MANAGEMENT, 3) => "Reassociation Response",
+        (Dot11FrameTypes::MANAGEMENT, 4) => "Probe Request",
+        (Dot11FrameTypes::MANAGEMENT, 5) => "Probe Response",
+        (Dot11FrameTypes::MANAGEMENT, 8) => "Beacon",
+        (Dot11FrameTypes::MANAGEMENT, 10) => "Disassociation",
+        (Dot11FrameTypes::MANAGEMENT, 11) => "Authentication",
+        (Dot11FrameTypes::MANAGEMENT, 12) => "Deauthentication",
+        (Dot11FrameTypes::MANAGEMENT, 13) => "Action",
+        (Dot11FrameTypes::CONTROL, 8) => "Block Ack Request",
+        (Dot11FrameTypes::CONTROL, 9) => "Block Ack",
+        (Dot11FrameTypes::CONTROL, 10) => "PS-Poll",
+        (Dot11FrameTypes::CONTROL, 11) => "RTS",
+        (Dot11FrameTypes::CONTROL, 12) => "CTS",
+        (Dot11FrameTypes::CONTROL, 13) => "ACK",
+        (Dot11FrameTypes::DATA, 0) => "Data",
+        (Dot11FrameTypes::DATA, 4) => "Null",
+        (Dot11FrameTypes::DATA, 8) => "QoS Data",
+        (Dot11FrameTypes::DATA, 12) => "QoS Null",
+        (Dot11FrameTypes::MANAGEMENT, _) => "Management",
+        (Dot11FrameTypes::CONTROL, _) => "Control",
+        (Dot11FrameTypes::DATA, _) => "Data",
+        _ => "Extension",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet::util::MacAddr;
+
+    use crate::serializable_packet::SerializablePacket;
+
+    use super::{parse_dot11_frame, parse_radiotap_frame};
+
+    const BSSID: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    const STATION: [u8; 6] = [0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb];
+
+    /// Radiotap header with flags (FCS included), rate, channel and antenna signal
+    fn build_radiotap_header() -> Vec<u8> {
+        vec![
+            0x00, 0x00, 0x0f, 0x00, // version, pad, length (15)
+            0x2e, 0x00, 0x00, 0x00, // present: flags, rate, channel, antenna signal
+            0x10, // flags: FCS at end
+            0x0c, // rate: 6 Mbps
+            0x6c, 0x09, 0xa0, 0x00, // channel: 2412 MHz, flags 0x00a0
+            0xc4, // signal: -60 dBm
+        ]
+    }
+
+    fn build_beacon_frame() -> Vec<u8> {
+        let mut frame = vec![0x80, 0x00, 0x00, 0x00];
+        frame.extend_from_slice(&[0xff; 6]);
+        frame.extend_from_slice(&BSSID);
+        frame.extend_from_slice(&BSSID);
+        frame.extend_from_slice(&[0x10, 0x00]);
+        // Timestamp, beacon interval, capabilities
+        frame.extend_from_slice(&[0u8; 12]);
+        // SSID element
+        frame.extend_from_slice(&[0x00, 0x08]);
+        frame.extend_from_slice(b"wirefish");
+        frame
+    }
+
+    #[test]
+    fn radiotap_beacon_frame() {
+        let mut packet = build_radiotap_header();
+        let beacon = build_beacon_frame();
+        packet.extend_from_slice(&beacon);
+        packet.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+
+        let parsed_packet = parse_radiotap_frame(&packet, 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::Dot11Packet(dot11) => {
+                let radiotap = dot11.radiotap.as_ref().unwrap();
+                assert_eq!(radiotap.rate, Some(6.0));
+                assert_eq!(radiotap.channel_frequency, Some(2412));
+                assert_eq!(radiotap.signal_dbm, Some(-60));
+
+                assert_eq!(dot11.type_name, "Beacon");
+                assert_eq!(dot11.destination, Some(MacAddr::broadcast()));
+                assert_eq!(dot11.bssid, Some(MacAddr::from(BSSID)));
+                assert_eq!(dot11.sequence_number, Some(1));
+                assert_eq!(dot11.ssid.as_deref(), Some("wirefish"));
+                assert_eq!(dot11.length, beacon.len());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn data_frame_with_ipv4_payload() {
+        // Data frame to the distribution system: BSSID, source, destination
+        let mut frame = vec![0x08, 0x01, 0x00, 0x00];
+        frame.extend_from_slice(&BSSID);
+        frame.extend_from_slice(&STATION);
+        frame.extend_from_slice(&[0xff; 6]);
+        frame.extend_from_slice(&[0x00, 0x00]);
+        frame.extend_from_slice(&[0xaa, 0xaa, 0x03, 0x00, 0x00, 0x00, 0x08, 0x00]);
+        frame.extend_from_slice(&[
+            0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x40, 0x11, 0x00, 0x00, 10, 0, 0, 1,
+            10, 0, 0, 255,
+        ]);
+
+        let parsed_packet = parse_dot11_frame(&frame, 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::Dot11Packet(dot11) => {
+                assert_eq!(dot11.type_name, "Data");
+                assert!(dot11.to_ds);
+                assert_eq!(dot11.source, Some(MacAddr::from(STATION)));
+                assert_eq!(dot11.destination, Some(MacAddr::broadcast()));
+                assert_eq!(dot11.bssid, Some(MacAddr::from(BSSID)));
+                assert_eq!(dot11.header_length, 24);
+            }
+            _ => unreachable!(),
+        }
+
+        match parsed_packet.get_network_layer_packet().unwrap() {
+            SerializablePacket::Ipv4Packet(ipv4) => assert_eq!(ipv4.source.octets(), [10, 0, 0, 1]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn malformed_radiotap_frame() {
+        let parsed_packet = parse_radiotap_frame(&[0x00, 0x00, 0x20, 0x00], 0);
+
+        match parsed_packet.get_link_layer_packet().unwrap() {
+            SerializablePacket::MalformedPacket(str) => {
+                assert_eq!(str, "Malformed Radiotap Packet")
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Active and idle periods of the capture, to locate the traffic in a long capture
+//!
+//! Packets are scanned in timestamp order: a silence longer than the gap threshold closes the current
+//! active period, and the next packet opens a new one. Periods are in wall-clock time, so pauses of the
+//! sniffing process are idle like any other silence.
+
+use log::info;
+use serde::Serialize;
+
+use crate::filtering::PacketsCollection;
+use crate::{SniffingError, SniffingState};
+
+/// Contiguous interval of traffic
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ActivityPeriod {
+    /// Timestamps of the first and last packet, in microseconds since the Unix epoch
+    pub start: i64,
+    pub end: i64,
+    pub packets: usize,
+    pub bytes: usize,
+}
+
+/// Returns the active periods of the capture, separated by silences longer than `gap_secs` seconds
+#[tauri::command]
+pub fn get_activity_periods(
+    state: tauri::State<SniffingState>,
+    gap_secs: f64,
+) -> Result<Vec<ActivityPeriod>, SniffingError> {
+    if !(gap_secs > 0.0 && gap_secs.is_finite()) {
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "Invalid activity gap: {}",
+            gap_secs
+        )));
+    }
+
+    let packets_collection = state.packets.lock().unwrap();
+    let periods =
+        get_activity_periods_internal(&packets_collection, (gap_secs * 1_000_000.0) as i64);
+    info!("Activity periods: {}", periods.len());
+
+    Ok(periods)
+}
+
+fn get_activity_periods_internal(
+    packets_collection: &PacketsCollection,
+    gap: i64,
+) -> Vec<ActivityPeriod> {
+    // Packets loaded from files aren't necessarily in timestamp order
+    let mut packets = packets_collection
+        .packets
+        .iter()
+        .map(|packet| {
+            let bytes = packets_collection
+                .raw_packets
+                .get(&packet.get_id())
+                .map_or(0, |raw_packet| raw_packet.data.len());
+            (packet.get_timestamp(), bytes)
+        })
+        .collect::<Vec<(i64, usize)>>();
+    packets.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut periods: Vec<ActivityPeriod> = vec![];
+    for (timestamp, bytes) in packets {
+        match periods.last_mut() {
+            Some(period) if timestamp - period.end <= gap => {
+                period.end = timestamp;
+                period.packets += 1;
+                period.bytes += bytes;
+            }
+            _ => periods.push(ActivityPeriod {
+                start: timestamp,
+                end: timestamp,
+                packets: 1,
+                bytes,
+            }),
+        }
+    }
+
+    periods
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::filtering::PacketsCollection;
+
+    use super::{get_activity_periods_internal, ActivityPeriod};
+
+    #[test]
+    fn periods_separated_by_gaps() {
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection.packets = [0, 500_000, 1_200_000, 10_000_000, 9_800_000, 30_000_000]
+            .into_iter()
+            .enumerate()
+            .map(|(id, timestamp)| {
+                let mut parsed_packet = ParsedPacket::new(id);
+                parsed_packet.set_timestamp(timestamp);
+                Arc::new(parsed_packet)
+            })
+            .collect::<Vec<Arc<ParsedPacket>>>();
+
+        let periods = get_activity_periods_internal(&packets_collection, 1_000_000);
+        assert_eq!(
+            periods,
+            vec![
+                ActivityPeriod {
+                    start: 0,
+                    end: 1_200_000,
+                    packets: 3,
+                    bytes: 0
+                },
+                ActivityPeriod {
+                    start: 9_800_000,
+                    end: 10_000_000,
+                    packets: 2,
+                    bytes: 0
+                },
+                ActivityPeriod {
+                    start: 30_000_000,
+                    end: 30_000_000,
+                    packets: 1,
+                    bytes: 0
+                },
+            ]
+        );
+
+        // A single period when the gap is longer than any silence
+        assert_eq!(
+            get_activity_periods_internal(&packets_collection, 20_000_000).len(),
+            1
+        );
+
+        packets_collection.packets.clear();
+        assert!(get_activity_periods_internal(&packets_collection, 1_000_000).is_empty());
+    }
+}
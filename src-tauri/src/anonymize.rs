@@ -0,0 +1,228 @@
+//! Pseudonymization of the network and link layer addresses written in exports and reports
+//!
+//! IP addresses are mapped preserving their prefixes: two addresses sharing the first n bits are
+//! mapped to two pseudonyms sharing the first n bits as well, so that the subnet structure of the
+//! capture survives. Each bit of the pseudonym is the original bit flipped according to a keyed
+//! hash of the preceding bits (as in Crypto-PAn).
+//!
+//! MAC addresses are replaced with locally administered addresses derived from a keyed hash,
+//! keeping the broadcast address and the multicast bit.
+//!
+//! The mapping only depends on the key, a new one being generated for every anonymizer
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pnet::util::MacAddr;
+use sha2::{Digest, Sha256};
+
+/// Distinguishes the keys generated within the same instant
+static GENERATED_KEYS: AtomicU64 = AtomicU64::new(0);
+
+/// Consistent mapping of real addresses to pseudonyms
+pub struct Anonymizer {
+    key: Vec<u8>,
+    ipv4_addresses: HashMap<Ipv4Addr, Ipv4Addr>,
+    ipv6_addresses: HashMap<Ipv6Addr, Ipv6Addr>,
+    mac_addresses: HashMap<MacAddr, MacAddr>,
+}
+
+impl Anonymizer {
+    /// Creates an anonymizer with a newly generated key
+    pub fn new() -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+                .to_le_bytes(),
+        );
+        hasher.update(std::process::id().to_le_bytes());
+        hasher.update(GENERATED_KEYS.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+
+        Self::with_key(&hasher.finalize())
+    }
+
+    /// Creates an anonymizer whose mapping is determined by the given key
+    pub fn with_key(key: &[u8]) -> Self {
+        Anonymizer {
+            key: key.to_vec(),
+            ipv4_addresses: HashMap::new(),
+            ipv6_addresses: HashMap::new(),
+            mac_addresses: HashMap::new(),
+        }
+    }
+
+    pub fn anonymize_ip(&mut self, ip: IpAddr) -> IpAddr {
+        match ip {
+            IpAddr::V4(ip) => {
+                if let Some(pseudonym) = self.ipv4_addresses.get(&ip) {
+                    return IpAddr::V4(*pseudonym);
+                }
+
+                let pseudonym =
+                    Ipv4Addr::from(self.preserve_prefix(u32::from(ip) as u128, 32) as u32);
+                self.ipv4_addresses.insert(ip, pseudonym);
+                IpAddr::V4(pseudonym)
+            }
+            IpAddr::V6(ip) => {
+                if let Some(pseudonym) = self.ipv6_addresses.get(&ip) {
+                    return IpAddr::V6(*pseudonym);
+                }
+
+                let pseudonym = Ipv6Addr::from(self.preserve_prefix(u128::from(ip), 128));
+                self.ipv6_addresses.insert(ip, pseudonym);
+                IpAddr::V6(pseudonym)
+            }
+        }
+    }
+
+    pub fn anonymize_mac(&mut self, mac: MacAddr) -> MacAddr {
+        if mac.is_broadcast() {
+            return mac;
+        }
+
+        if let Some(pseudonym) = self.mac_addresses.get(&mac) {
+            return *pseudonym;
+        }
+
+        let digest = self.hash(b"m", &mac.octets());
+        // Locally administered, same unicast/multicast bit as the original
+        let first = (digest[0] & 0xfc) | 0x02 | (mac.0 & 0x01);
+        let pseudonym = MacAddr::new(first, digest[1], digest[2], digest[3], digest[4], digest[5]);
+
+        self.mac_addresses.insert(mac, pseudonym);
+        pseudonym
+    }
+
+    /// Anonymizes an IP or MAC address as returned by the `get_*` accessors, leaving any other value as is
+    pub fn anonymize_address(&mut self, address: &str) -> String {
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            return self.anonymize_ip(ip).to_string();
+        }
+
+        if let Ok(mac) = address.parse::<MacAddr>() {
+            return self.anonymize_mac(mac).to_string();
+        }
+
+        address.to_owned()
+    }
+
+    /// Flips each of the `width` bits of the address according to the preceding ones
+    fn preserve_prefix(&self, address: u128, width: u32) -> u128 {
+        let mut pseudonym = 0;
+
+        for bit in 0..width {
+            let prefix = if bit == 0 {
+                0
+            } else {
+                address >> (width - bit)
+            };
+            let flip = self.hash(&[width as u8, bit as u8], &prefix.to_be_bytes())[0] & 1;
+            let original = (address >> (width - bit - 1)) & 1;
+
+            pseudonym = (pseudonym << 1) | (original ^ flip as u128);
+        }
+
+        pseudonym
+    }
+
+    fn hash(&self, domain: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key);
+        hasher.update(domain);
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::IpAddr;
+
+    use pnet::util::MacAddr;
+
+    use super::Anonymizer;
+
+    fn common_prefix(first: IpAddr, second: IpAddr) -> u32 {
+        match (first, second) {
+            (IpAddr::V4(first), IpAddr::V4(second)) => {
+                (u32::from(first) ^ u32::from(second)).leading_zeros()
+            }
+            (IpAddr::V6(first), IpAddr::V6(second)) => {
+                (u128::from(first) ^ u128::from(second)).leading_zeros()
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn prefix_preserving_ip_anonymization() {
+        let mut anonymizer = Anonymizer::with_key(b"wirefish");
+        let addresses: Vec<IpAddr> = ["192.168.1.10", "192.168.1.20", "192.168.7.1", "10.0.0.1"]
+            .iter()
+            .map(|ip| ip.parse().unwrap())
+            .collect();
+        let pseudonyms: Vec<IpAddr> = addresses
+            .iter()
+            .map(|ip| anonymizer.anonymize_ip(*ip))
+            .collect();
+
+        for i in 0..addresses.len() {
+            assert_ne!(addresses[i], pseudonyms[i]);
+            for j in 0..addresses.len() {
+                assert_eq!(
+                    common_prefix(addresses[i], addresses[j]),
+                    common_prefix(pseudonyms[i], pseudonyms[j])
+                );
+            }
+        }
+
+        let ipv6: IpAddr = "2001:db8::1".parse().unwrap();
+        let other_ipv6: IpAddr = "2001:db8::ff".parse().unwrap();
+        assert_eq!(
+            common_prefix(ipv6, other_ipv6),
+            common_prefix(
+                anonymizer.anonymize_ip(ipv6),
+                anonymizer.anonymize_ip(other_ipv6)
+            )
+        );
+    }
+
+    #[test]
+    fn consistent_mapping() {
+        let mut anonymizer = Anonymizer::with_key(b"wirefish");
+        let mut same_key = Anonymizer::with_key(b"wirefish");
+        let mut other_key = Anonymizer::with_key(b"other");
+
+        let pseudonym = anonymizer.anonymize_address("10.10.10.10");
+        assert_eq!(anonymizer.anonymize_address("10.10.10.10"), pseudonym);
+        assert_eq!(same_key.anonymize_address("10.10.10.10"), pseudonym);
+        assert_ne!(other_key.anonymize_address("10.10.10.10"), pseudonym);
+
+        assert_eq!(anonymizer.anonymize_address("-"), "-");
+    }
+
+    #[test]
+    fn mac_anonymization() {
+        let mut anonymizer = Anonymizer::with_key(b"wirefish");
+
+        let unicast = anonymizer.anonymize_mac(MacAddr::new(0x00, 0x11, 0x22, 0x33, 0x44, 0x55));
+        assert_eq!(unicast.0 & 0x03, 0x02);
+
+        let multicast = anonymizer.anonymize_mac(MacAddr::new(0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb));
+        assert_eq!(multicast.0 & 0x03, 0x03);
+
+        assert_eq!(
+            anonymizer.anonymize_mac(MacAddr::broadcast()),
+            MacAddr::broadcast()
+        );
+        assert_eq!(
+            anonymizer.anonymize_address("00:11:22:33:44:55"),
+            unicast.to_string()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Decoupling of the capture from the parsing of the frames
+//!
+//! The sniffing thread only receives the frames and pushes them in a bounded queue, consumed by a parser
+//! thread that parses and stores them. A single parser per interface keeps the reassembly state of each
+//! connection (HTTP, TLS) in the same thread, and takes the packet IDs while holding the collection so
+//! that the parsers of the interfaces sniffed concurrently insert their packets in ID order.
+//!
+//! When the queue is full the frame is either dropped right away (`drop` policy) or the capture waits
+//! briefly for the parser before dropping it (`block` policy). Dropped frames are counted in the heartbeat.
+
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use log::{info, warn};
+
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod BackpressurePolicies {
+    pub const DROP: &str = "drop";
+    pub const BLOCK: &str = "block";
+}
+
+/// Default number of frames waiting to be parsed
+pub const DEFAULT_QUEUE_CAPACITY: usize = 4096;
+/// Longest wait for room in the queue with the `block` policy
+const BLOCK_TIMEOUT: Duration = Duration::from_millis(10);
+const BLOCK_RETRY_INTERVAL: Duration = Duration::from_micros(100);
+
+/// Queue settings used by the following sniffing processes
+#[derive(Debug, Clone)]
+pub struct BackpressureConfig {
+    pub policy: &'static str,
+    pub queue_capacity: usize,
+}
+
+impl BackpressureConfig {
+    pub fn new() -> Self {
+        BackpressureConfig {
+            policy: BackpressurePolicies::DROP,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+        }
+    }
+}
+
+/// Frame received by the sniffing thread, waiting to be parsed
+pub struct CapturedFrame {
+    pub data: Vec<u8>,
+    pub timestamp: DateTime<Local>,
+}
+
+/// Producer side of the queue between the sniffing and the parser thread
+pub struct FrameQueue {
+    sender: SyncSender<CapturedFrame>,
+    block: bool,
+    /// Frames dropped since the queue was full
+    pub overflows: usize,
+}
+
+impl FrameQueue {
+    pub fn new(config: &BackpressureConfig) -> (Self, Receiver<CapturedFrame>) {
+        let (sender, receiver) = sync_channel(config.queue_capacity);
+
+        let queue = FrameQueue {
+            sender,
+            block: config.policy == BackpressurePolicies::BLOCK,
+            overflows: 0,
+        };
+
+        (queue, receiver)
+    }
+
+    /// Enqueue the frame for parsing, returning whether it was accepted
+    pub fn push(&mut self, frame: CapturedFrame) -> bool {
+        let deadline = Instant::now() + BLOCK_TIMEOUT;
+        let mut frame = frame;
+
+        loop {
+            match self.sender.try_send(frame) {
+                Ok(()) => return true,
+                Err(TrySendError::Full(rejected)) if self.block && Instant::now() < deadline => {
+                    frame = rejected;
+                    thread::sleep(BLOCK_RETRY_INTERVAL);
+                }
+                Err(TrySendError::Full(_)) => {
+                    self.overflows += 1;
+                    if self.overflows.is_power_of_two() {
+                        warn!("Parser queue full, {} frames dropped", self.overflows);
+                    }
+                    return false;
+                }
+                // The parser thread is gone, nothing to do with the frame
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+    }
+}
+
+/// Sets how the sniffing process handles the bursts exceeding the parsing speed, from the next start
+///
+/// `policy` is either `drop` or `block`, `queue_capacity` the number of frames buffered before applying it
+#[tauri::command]
+pub fn set_backpressure(
+    state: tauri::State<SniffingState>,
+    policy: String,
+    queue_capacity: Option<usize>,
+) -> Result<(), SniffingError> {
+    let config = get_backpressure_config(&policy, queue_capacity)?;
+
+    info!(
+        "Backpressure policy: {}, queue capacity: {}",
+        config.policy, config.queue_capacity
+    );
+    *state.backpressure.lock().unwrap() = config;
+
+    Ok(())
+}
+
+/// Validates the queue settings, the capacity defaulting to `DEFAULT_QUEUE_CAPACITY`
+pub fn get_backpressure_config(
+    policy: &str,
+    queue_capacity: Option<usize>,
+) -> Result<BackpressureConfig, SniffingError> {
+    let policy = match policy {
+        BackpressurePolicies::DROP => BackpressurePolicies::DROP,
+        BackpressurePolicies::BLOCK => BackpressurePolicies::BLOCK,
+        _ => {
+            warn!("Unknown backpressure policy: {}", policy);
+            return Err(SniffingError::InvalidConfiguration(format!(
+                "Unknown backpressure policy: {}",
+                policy
+            )));
+        }
+    };
+
+    let queue_capacity = queue_capacity.unwrap_or(DEFAULT_QUEUE_CAPACITY);
+    if queue_capacity == 0 {
+        return Err(SniffingError::InvalidConfiguration(
+            "The queue capacity must be positive".to_owned(),
+        ));
+    }
+
+    Ok(BackpressureConfig {
+        policy,
+        queue_capacity,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use chrono::Local;
+
+    use super::{BackpressureConfig, BackpressurePolicies, CapturedFrame, FrameQueue};
+
+    fn frame(byte: u8) -> CapturedFrame {
+        CapturedFrame {
+            data: vec![byte],
+            timestamp: Local::now(),
+        }
+    }
+
+    #[test]
+    fn full_queue_drops_frames() {
+        let (mut queue, receiver) = FrameQueue::new(&BackpressureConfig {
+            policy: BackpressurePolicies::DROP,
+            queue_capacity: 2,
+        });
+
+        assert!(queue.push(frame(0)));
+        assert!(queue.push(frame(1)));
+        assert!(!queue.push(frame(2)));
+        assert_eq!(queue.overflows, 1);
+
+        let received: Vec<u8> = receiver.try_iter().map(|f| f.data[0]).collect();
+        assert_eq!(received, vec![0, 1]);
+    }
+
+    #[test]
+    fn full_queue_waits_for_parser() {
+        let (mut queue, receiver) = FrameQueue::new(&BackpressureConfig {
+            policy: BackpressurePolicies::BLOCK,
+            queue_capacity: 1,
+        });
+
+        assert!(queue.push(frame(0)));
+
+        let parser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1));
+            receiver.iter().map(|f| f.data[0]).collect::<Vec<u8>>()
+        });
+
+        assert!(queue.push(frame(1)));
+        assert_eq!(queue.overflows, 0);
+
+        drop(queue);
+        assert_eq!(parser.join().unwrap(), vec![0, 1]);
+    }
+}
@@ -0,0 +1,314 @@
+//! Comparison of the collected traffic with a recorded baseline, to detect new or unexpected flows
+//!
+//! The baseline is either a saved session (a pcap or pcapng file, optionally gzip-compressed) or a text
+//! file of the expected flows and hosts, one per line (`#` starts a comment):
+//! - `tcp 10.0.0.1 10.0.0.2 443`: flow of a transport protocol (`tcp`, `udp`) between two IP addresses,
+//!   on a service port (`*` for any port)
+//! - `ip 10.0.0.1 10.0.0.2`: traffic without a transport layer between two IP addresses
+//! - `10.0.0.1`: host whose traffic is all expected
+//!
+//! Flows are matched regardless of their direction and of the ephemeral port of the client: the
+//! service port of a flow is the lower of its two ports. The first packet of each flow missing from the
+//! baseline is reported with a `new_flow` event and listed by `get_new_flows`. Without a baseline no
+//! flow is reported.
+
+use std::collections::HashSet;
+use std::fs;
+use std::net::IpAddr;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    contains_tcp, contains_udp, get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::capture_file::read_capture_file;
+use crate::{SniffingError, SniffingState};
+
+const CAPTURE_EXTENSIONS: [&str; 6] = [
+    ".pcap",
+    ".pcapng",
+    ".cap",
+    ".pcap.gz",
+    ".pcapng.gz",
+    ".cap.gz",
+];
+const ANY_PORT: &str = "*";
+
+/// Protocol, IP addresses (sorted) and service port of a flow
+type FlowKey = (String, IpAddr, IpAddr, Option<u16>);
+
+/// Flow missing from the baseline, described by its first packet
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NewFlow {
+    pub packet_id: usize,
+    pub timestamp: i64,
+    pub protocol: String,
+    pub source_ip: String,
+    pub source_port: Option<u16>,
+    pub dest_ip: String,
+    pub dest_port: Option<u16>,
+}
+
+/// Flows and hosts expected in the collected traffic, with the new flows found so far
+#[derive(Debug, Default)]
+pub struct Baseline {
+    /// `None` without a loaded baseline
+    flows: Option<HashSet<FlowKey>>,
+    hosts: HashSet<IpAddr>,
+    new_flows: Vec<NewFlow>,
+    reported: HashSet<FlowKey>,
+    /// New flow of the last checked packet, if any
+    last_new_flow: Option<NewFlow>,
+}
+
+impl Baseline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the expected flows and hosts, forgetting the new flows found so far
+    pub fn set(&mut self, flows: HashSet<FlowKey>, hosts: HashSet<IpAddr>) {
+        self.flows = Some(flows);
+        self.hosts = hosts;
+        self.clear();
+    }
+
+    /// Forgets the new flows found so far, keeping the baseline
+    pub fn clear(&mut self) {
+        self.new_flows.clear();
+        self.reported.clear();
+        self.last_new_flow = None;
+    }
+
+    pub fn update(&mut self, packet: &ParsedPacket) {
+        self.last_new_flow = None;
+
+        let flows = match &self.flows {
+            Some(flows) => flows,
+            None => return,
+        };
+        let key = match get_flow_key(packet) {
+            Some(key) => key,
+            None => return,
+        };
+
+        if self.hosts.contains(&key.1) || self.hosts.contains(&key.2) {
+            return;
+        }
+        // Any port of the flow is expected
+        if flows.contains(&key) || flows.contains(&(key.0.clone(), key.1, key.2, None)) {
+            return;
+        }
+        if !self.reported.insert(key) {
+            return;
+        }
+
+        let parse_port = |port: Option<String>| port.and_then(|port| port.parse::<u16>().ok());
+        let new_flow = NewFlow {
+            packet_id: packet.get_id(),
+            timestamp: packet.get_timestamp(),
+            protocol: get_protocol(packet).to_owned(),
+            source_ip: get_source_ip(packet).unwrap_or_default(),
+            source_port: parse_port(get_source_port(packet)),
+            dest_ip: get_dest_ip(packet).unwrap_or_default(),
+            dest_port: parse_port(get_dest_port(packet)),
+        };
+
+        info!(
+            "[{}] New flow: {} {} > {}",
+            new_flow.packet_id, new_flow.protocol, new_flow.source_ip, new_flow.dest_ip
+        );
+        self.new_flows.push(new_flow.clone());
+        self.last_new_flow = Some(new_flow);
+    }
+
+    /// New flow of the last checked packet, to be notified
+    pub fn take_new_flow(&mut self) -> Option<NewFlow> {
+        self.last_new_flow.take()
+    }
+
+    pub fn get_new_flows(&self) -> Vec<NewFlow> {
+        self.new_flows.clone()
+    }
+}
+
+fn get_protocol(packet: &ParsedPacket) -> &'static str {
+    if contains_tcp(packet) {
+        "tcp"
+    } else if contains_udp(packet) {
+        "udp"
+    } else {
+        "ip"
+    }
+}
+
+fn get_flow_key(packet: &ParsedPacket) -> Option<FlowKey> {
+    let source = get_source_ip(packet)?.parse::<IpAddr>().ok()?;
+    let destination = get_dest_ip(packet)?.parse::<IpAddr>().ok()?;
+
+    let protocol = get_protocol(packet);
+    let port = if protocol == "ip" {
+        None
+    } else {
+        let source_port = get_source_port(packet)?.parse::<u16>().ok()?;
+        let dest_port = get_dest_port(packet)?.parse::<u16>().ok()?;
+        Some(source_port.min(dest_port))
+    };
+
+    Some((
+        protocol.to_owned(),
+        source.min(destination),
+        source.max(destination),
+        port,
+    ))
+}
+
+/// Expected flows and hosts of a baseline text file
+fn parse_baseline(content: &str) -> Result<(HashSet<FlowKey>, HashSet<IpAddr>), String> {
+    let mut flows = HashSet::new();
+    let mut hosts = HashSet::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = || format!("Invalid baseline line {}: {}", number + 1, line);
+        let parse_ip = |ip: &str| ip.parse::<IpAddr>().map_err(|_| invalid());
+
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        match fields.as_slice() {
+            [host] => {
+                hosts.insert(parse_ip(host)?);
+            }
+            ["ip", first, second] => {
+                let (first, second) = (parse_ip(first)?, parse_ip(second)?);
+                flows.insert(("ip".to_owned(), first.min(second), first.max(second), None));
+            }
+            [protocol @ ("tcp" | "udp"), first, second, port] => {
+                let (first, second) = (parse_ip(first)?, parse_ip(second)?);
+                let port = match *port {
+                    ANY_PORT => None,
+                    port => Some(port.parse::<u16>().map_err(|_| invalid())?),
+                };
+                flows.insert((
+                    protocol.to_string(),
+                    first.min(second),
+                    first.max(second),
+                    port,
+                ));
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok((flows, hosts))
+}
+
+/// Loads the flows expected in the collected traffic from a saved session or a baseline text file,
+/// returning the number of expected flows and hosts
+///
+/// Each call replaces the previous baseline, the new flows are checked from the next collected packet
+#[tauri::command(async)]
+pub fn load_baseline(
+    state: tauri::State<SniffingState>,
+    path: String,
+) -> Result<usize, SniffingError> {
+    let (flows, hosts) = if CAPTURE_EXTENSIONS
+        .iter()
+        .any(|extension| path.ends_with(extension))
+    {
+        let flows = read_capture_file(&path)?
+            .iter()
+            .filter_map(get_flow_key)
+            .collect::<HashSet<FlowKey>>();
+        (flows, HashSet::new())
+    } else {
+        let content = fs::read_to_string(&path).map_err(|e| {
+            SniffingError::BaselineAccessFailed(format!("Unable to read {}: {}", path, e))
+        })?;
+        parse_baseline(&content).map_err(|e| {
+            warn!("{}", e);
+            SniffingError::BaselineAccessFailed(e)
+        })?
+    };
+
+    let loaded = flows.len() + hosts.len();
+    state.packets.lock().unwrap().baseline.set(flows, hosts);
+
+    info!(
+        "Loaded a baseline of {} flows and hosts from {}",
+        loaded, path
+    );
+
+    Ok(loaded)
+}
+
+/// Returns the flows of the collected packets missing from the baseline, in order of appearance
+#[tauri::command]
+pub fn get_new_flows(state: tauri::State<SniffingState>) -> Vec<NewFlow> {
+    state.packets.lock().unwrap().baseline.get_new_flows()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::util::MacAddr;
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{parse_baseline, Baseline};
+
+    #[test]
+    fn flows_missing_from_baseline() {
+        let baseline = "# Office\n\
+            tcp 10.0.0.2 10.0.0.1 443\n\
+            udp 10.0.0.1 10.0.0.53 *  # DNS\n\
+            192.168.1.10\n";
+        let (flows, hosts) = parse_baseline(baseline).unwrap();
+        assert_eq!((flows.len(), hosts.len()), (2, 1));
+        assert!(parse_baseline("tcp 10.0.0.1 10.0.0.2").is_err());
+        assert!(parse_baseline("sctp 10.0.0.1 10.0.0.2 80").is_err());
+
+        let mut baseline = Baseline::new();
+        let client = Ipv4Addr::new(10, 0, 0, 1);
+        let packet = |destination, source_port, dest_port| {
+            build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                client,
+                destination,
+                source_port,
+                dest_port,
+            )
+        };
+
+        // Without a baseline nothing is new
+        baseline.update(&packet(Ipv4Addr::new(8, 8, 8, 8), 50000, 443));
+        assert!(baseline.take_new_flow().is_none());
+
+        baseline.set(flows, hosts);
+        // Expected, whatever the client port and the direction
+        baseline.update(&packet(Ipv4Addr::new(10, 0, 0, 2), 50001, 443));
+        assert!(baseline.take_new_flow().is_none());
+        baseline.update(&packet(Ipv4Addr::new(192, 168, 1, 10), 50002, 22));
+        assert!(baseline.take_new_flow().is_none());
+
+        // New service of a known server, reported once
+        baseline.update(&packet(Ipv4Addr::new(10, 0, 0, 2), 50003, 22));
+        let new_flow = baseline.take_new_flow().unwrap();
+        assert_eq!(new_flow.dest_ip, "10.0.0.2");
+        assert_eq!(
+            (new_flow.source_port, new_flow.dest_port),
+            (Some(50003), Some(22))
+        );
+        baseline.update(&packet(Ipv4Addr::new(10, 0, 0, 2), 50004, 22));
+        assert!(baseline.take_new_flow().is_none());
+
+        assert_eq!(baseline.get_new_flows(), vec![new_flow]);
+    }
+}
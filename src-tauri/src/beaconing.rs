@@ -0,0 +1,294 @@
+//! Heuristic detection of beaconing, the periodic connections of a host to the same destination (e.g. the
+//! check-ins of a malware to its command and control server)
+//!
+//! Connections are the TCP SYN segments (retransmissions excluded) and, for UDP, the first packet of a
+//! burst, sent after at least `UDP_IDLE_GAP` of silence. The connections of a source to the same
+//! destination address, port and protocol are regular when the standard deviation of their intervals
+//! (jitter) is at most `tolerance` times the mean interval (period).
+//!
+//! Legitimate software (update checks, keep-alives, NTP) beacons too: candidates are only hints to be
+//! investigated
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use log::info;
+use pnet::packet::tcp::TcpFlags;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::{SniffingError, SniffingState};
+
+/// Regularity threshold used when none is provided
+const DEFAULT_TOLERANCE: f64 = 0.1;
+/// Minimum number of connections to consider the timing of a destination
+const MIN_CONNECTIONS: usize = 5;
+/// Silence after which a UDP packet starts a new burst, in microseconds
+const UDP_IDLE_GAP: i64 = 1_000_000;
+
+/// Destination contacted by a source at regular intervals
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BeaconCandidate {
+    pub source: String,
+    pub destination: String,
+    pub destination_port: String,
+    pub protocol: String,
+    pub connections: usize,
+    /// Mean and standard deviation of the intervals between the connections, in microseconds
+    pub period: f64,
+    pub jitter: f64,
+    /// Jitter relative to the period, the lower the more regular
+    pub regularity: f64,
+    /// Timestamps of the first and last connection, in microseconds since the Unix epoch
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+/// Returns the destinations contacted at regular intervals, most regular first
+///
+/// `tolerance` is the highest jitter to period ratio of a candidate, 0.1 if not provided
+#[tauri::command]
+pub fn detect_beaconing(
+    state: tauri::State<SniffingState>,
+    tolerance: Option<f64>,
+) -> Result<Vec<BeaconCandidate>, SniffingError> {
+    let tolerance = tolerance.unwrap_or(DEFAULT_TOLERANCE);
+    if !(tolerance > 0.0 && tolerance.is_finite()) {
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "Invalid beaconing tolerance: {}",
+            tolerance
+        )));
+    }
+
+    let packets_collection = state.packets.lock().unwrap();
+    let mut packets = packets_collection.tcp_packets.clone();
+    packets.extend(packets_collection.udp_packets.iter().cloned());
+    drop(packets_collection);
+    packets.sort_by_key(|packet| packet.get_timestamp());
+
+    let candidates = detect_beaconing_internal(&packets, tolerance);
+    info!("Beaconing candidates: {}", candidates.len());
+
+    Ok(candidates)
+}
+
+type Destination = (String, String, String, &'static str);
+
+/// Connections of a source to a destination
+struct ConnectionTimes {
+    timestamps: Vec<i64>,
+    last_packet: i64,
+    /// Source port of the last SYN, to skip its retransmissions
+    last_source_port: Option<String>,
+}
+
+/// Packets are expected in timestamp order
+fn detect_beaconing_internal(
+    packets: &[Arc<ParsedPacket>],
+    tolerance: f64,
+) -> Vec<BeaconCandidate> {
+    let mut destinations: BTreeMap<Destination, ConnectionTimes> = BTreeMap::new();
+
+    for packet in packets {
+        let (protocol, is_syn) = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => (
+                "TCP",
+                tcp_packet.flags & TcpFlags::SYN != 0 && tcp_packet.flags & TcpFlags::ACK == 0,
+            ),
+            Some(SerializablePacket::UdpPacket(_)) => ("UDP", false),
+            _ => continue,
+        };
+
+        let key = match (
+            get_source_ip(packet),
+            get_dest_ip(packet),
+            get_dest_port(packet),
+        ) {
+            (Some(source), Some(destination), Some(port)) => (source, destination, port, protocol),
+            _ => continue,
+        };
+        let source_port = get_source_port(packet);
+        let timestamp = packet.get_timestamp();
+
+        // Only connection attempts of TCP are tracked, so the pair is created by the first one
+        if protocol == "TCP" && !is_syn {
+            continue;
+        }
+
+        let times = destinations.entry(key).or_insert_with(|| ConnectionTimes {
+            timestamps: vec![],
+            last_packet: i64::MIN,
+            last_source_port: None,
+        });
+
+        let is_connection = if protocol == "TCP" {
+            times.last_source_port != source_port
+        } else {
+            times.timestamps.is_empty() || timestamp - times.last_packet >= UDP_IDLE_GAP
+        };
+
+        if is_connection {
+            times.timestamps.push(timestamp);
+        }
+        times.last_packet = timestamp;
+        times.last_source_port = source_port;
+    }
+
+    let mut candidates = destinations
+        .into_iter()
+        .filter(|(_, times)| times.timestamps.len() >= MIN_CONNECTIONS)
+        .filter_map(
+            |((source, destination, destination_port, protocol), times)| {
+                let intervals = times
+                    .timestamps
+                    .windows(2)
+                    .map(|pair| (pair[1] - pair[0]) as f64)
+                    .collect::<Vec<f64>>();
+
+                let period = intervals.iter().sum::<f64>() / intervals.len() as f64;
+                let variance = intervals
+                    .iter()
+                    .map(|interval| (interval - period).powi(2))
+                    .sum::<f64>()
+                    / intervals.len() as f64;
+                let jitter = variance.sqrt();
+
+                // Bursts of connections in the same instant aren't periodic
+                if period <= 0.0 || jitter / period > tolerance {
+                    return None;
+                }
+
+                Some(BeaconCandidate {
+                    source,
+                    destination,
+                    destination_port,
+                    protocol: protocol.to_owned(),
+                    connections: times.timestamps.len(),
+                    period,
+                    jitter,
+                    regularity: jitter / period,
+                    first_seen: times.timestamps[0],
+                    last_seen: times.timestamps[times.timestamps.len() - 1],
+                })
+            },
+        )
+        .collect::<Vec<BeaconCandidate>>();
+
+    candidates.sort_by(|a, b| {
+        a.regularity
+            .partial_cmp(&b.regularity)
+            .unwrap_or(Ordering::Equal)
+    });
+    candidates
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::detect_beaconing_internal;
+
+    fn build_syn(
+        id: usize,
+        timestamp: i64,
+        dest_ip: Ipv4Addr,
+        source_port: u16,
+    ) -> Arc<ParsedPacket> {
+        let template = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            Ipv4Addr::new(10, 0, 0, 5),
+            dest_ip,
+            source_port,
+            443,
+        );
+
+        let mut tcp_packet = match template.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.flags = TcpFlags::SYN;
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_timestamp(timestamp);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn periodic_connections_are_beacons() {
+        let beacon = Ipv4Addr::new(203, 0, 113, 7);
+        let browsing = Ipv4Addr::new(198, 51, 100, 1);
+
+        let mut packets = vec![];
+        // Every 60 seconds, give or take half a second
+        for (i, offset) in [0, 500_000, -300_000, 200_000, 0, -400_000]
+            .into_iter()
+            .enumerate()
+        {
+            let timestamp = i as i64 * 60_000_000 + offset;
+            packets.push(build_syn(
+                packets.len(),
+                timestamp,
+                beacon,
+                50000 + i as u16,
+            ));
+            // Retransmission
+            if i == 2 {
+                packets.push(build_syn(
+                    packets.len(),
+                    timestamp + 1_000_000,
+                    beacon,
+                    50002,
+                ));
+            }
+        }
+        for (i, timestamp) in [1, 3, 40, 41, 150, 300].into_iter().enumerate() {
+            packets.push(build_syn(
+                packets.len(),
+                timestamp * 1_000_000,
+                browsing,
+                51000 + i as u16,
+            ));
+        }
+        packets.sort_by_key(|packet| packet.get_timestamp());
+
+        let candidates = detect_beaconing_internal(&packets, 0.1);
+        assert_eq!(candidates.len(), 1);
+
+        let candidate = &candidates[0];
+        assert_eq!(
+            (candidate.source.as_str(), candidate.destination.as_str()),
+            ("10.0.0.5", "203.0.113.7")
+        );
+        assert_eq!(
+            (
+                candidate.destination_port.as_str(),
+                candidate.protocol.as_str()
+            ),
+            ("443", "TCP")
+        );
+        assert_eq!(candidate.connections, 6);
+        assert!((candidate.period - 59_920_000.0).abs() < 1.0);
+        assert!(candidate.regularity < 0.02);
+        assert_eq!(
+            (candidate.first_seen, candidate.last_seen),
+            (0, 299_600_000)
+        );
+
+        // Stricter than the observed jitter
+        assert!(detect_beaconing_internal(&packets, 0.001).is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+//! Bookmarks of the collected packets, to mark them and jump between the marks
+//!
+//! Bookmarks are packet IDs, cleared along with the collected packets. The pcap format has no room for
+//! them, so the ones of an exported capture are saved beside it, in `<capture file>.bookmarks`: one line
+//! for each bookmarked packet, with its position in the capture file. Loading the capture file restores
+//! them.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, ErrorKind};
+
+use log::{info, warn};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::{SniffingError, SniffingState};
+
+const BOOKMARKS_EXTENSION: &str = ".bookmarks";
+
+/// IDs of the bookmarked packets
+pub type Bookmarks = BTreeSet<usize>;
+
+/// Bookmarks the packet, or removes its bookmark, returning whether it's bookmarked
+#[tauri::command]
+pub fn toggle_bookmark(
+    state: tauri::State<SniffingState>,
+    id: usize,
+) -> Result<bool, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+    // IDs are assigned in increasing order
+    if packets_collection
+        .packets
+        .binary_search_by_key(&id, |packet| packet.get_id())
+        .is_err()
+    {
+        return Err(SniffingError::GetPacketsIndexNotValid(format!(
+            "No collected packet with ID {}",
+            id
+        )));
+    }
+    drop(packets_collection);
+
+    let mut bookmarks = state.bookmarks.lock().unwrap();
+    Ok(toggle_bookmark_internal(&mut bookmarks, id))
+}
+
+/// Returns the IDs of the bookmarked packets, in order
+#[tauri::command]
+pub fn list_bookmarks(state: tauri::State<SniffingState>) -> Vec<usize> {
+    state.bookmarks.lock().unwrap().iter().copied().collect()
+}
+
+/// Returns the first bookmark after the packet `after_id`, the first bookmark if not provided
+#[tauri::command]
+pub fn next_bookmark(state: tauri::State<SniffingState>, after_id: Option<usize>) -> Option<usize> {
+    get_next_bookmark(&state.bookmarks.lock().unwrap(), after_id)
+}
+
+fn toggle_bookmark_internal(bookmarks: &mut Bookmarks, id: usize) -> bool {
+    if bookmarks.remove(&id) {
+        false
+    } else {
+        bookmarks.insert(id)
+    }
+}
+
+fn get_next_bookmark(bookmarks: &Bookmarks, after_id: Option<usize>) -> Option<usize> {
+    match after_id {
+        Some(after_id) => bookmarks.range(after_id + 1..).next().copied(),
+        None => bookmarks.iter().next().copied(),
+    }
+}
+
+/// Flag each packet bookmarked
+pub fn tag_bookmarks(packets: &mut Vec<ParsedPacket>, bookmarks: &Bookmarks) {
+    for packet in packets {
+        packet.set_bookmarked(bookmarks.contains(&packet.get_id()));
+    }
+}
+
+fn get_bookmarks_path(capture_path: &str) -> String {
+    format!("{}{}", capture_path, BOOKMARKS_EXTENSION)
+}
+
+/// Saves the bookmarks among the packets written in the capture file, by position, removing the file of
+/// a previous export when there are none
+pub fn write_bookmarks_file(
+    capture_path: &str,
+    written_packets: &[usize],
+    bookmarks: &Bookmarks,
+) -> io::Result<usize> {
+    let path = get_bookmarks_path(capture_path);
+    let positions = written_packets
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| bookmarks.contains(id))
+        .map(|(position, _)| position.to_string())
+        .collect::<Vec<String>>();
+
+    if positions.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(0),
+        };
+    }
+
+    fs::write(&path, positions.join("\n") + "\n")?;
+    info!("Saved {} bookmarks in {}", positions.len(), path);
+
+    Ok(positions.len())
+}
+
+/// Positions of the bookmarked packets in the capture file, empty when it has no bookmarks file
+pub fn read_bookmarks_file(capture_path: &str) -> BTreeSet<usize> {
+    let path = get_bookmarks_path(capture_path);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return BTreeSet::new(),
+        Err(e) => {
+            warn!("Unable to read {}: {}", path, e);
+            return BTreeSet::new();
+        }
+    };
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| match line.parse::<usize>() {
+            Ok(position) => Some(position),
+            Err(_) => {
+                warn!("Skipped invalid bookmark in {}: {}", path, line);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeSet;
+    use std::env::temp_dir;
+    use std::fs;
+
+    use super::{
+        get_bookmarks_path, get_next_bookmark, read_bookmarks_file, toggle_bookmark_internal,
+        write_bookmarks_file, Bookmarks,
+    };
+
+    #[test]
+    fn bookmarks_navigation_and_files() {
+        let mut bookmarks = Bookmarks::new();
+        assert!(toggle_bookmark_internal(&mut bookmarks, 7));
+        assert!(toggle_bookmark_internal(&mut bookmarks, 3));
+        assert!(toggle_bookmark_internal(&mut bookmarks, 12));
+        assert!(!toggle_bookmark_internal(&mut bookmarks, 12));
+
+        assert_eq!(get_next_bookmark(&bookmarks, None), Some(3));
+        assert_eq!(get_next_bookmark(&bookmarks, Some(3)), Some(7));
+        assert_eq!(get_next_bookmark(&bookmarks, Some(5)), Some(7));
+        assert_eq!(get_next_bookmark(&bookmarks, Some(7)), None);
+
+        let capture_path = temp_dir()
+            .join("wirefish_bookmarks_test.pcap")
+            .to_string_lossy()
+            .to_string();
+
+        // Packet 3 not exported
+        assert_eq!(
+            write_bookmarks_file(&capture_path, &[1, 2, 7, 9], &bookmarks).unwrap(),
+            1
+        );
+        assert_eq!(read_bookmarks_file(&capture_path), BTreeSet::from([2]));
+
+        // No bookmarks left, the previous file is removed
+        assert_eq!(
+            write_bookmarks_file(&capture_path, &[1, 2], &bookmarks).unwrap(),
+            0
+        );
+        assert!(fs::metadata(get_bookmarks_path(&capture_path)).is_err());
+        assert!(read_bookmarks_file(&capture_path).is_empty());
+    }
+}
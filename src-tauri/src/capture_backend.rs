@@ -0,0 +1,419 @@
+//! Backends reading the frames of the interface for the sniffing process
+//!
+//! - `pnet`: the datalink channel of pnet, available on every platform (default)
+//! - `tpacket_v3`: a memory-mapped AF_PACKET ring, on Linux only, see [`crate::tpacket`]. Frames are read
+//!   in blocks with far fewer system calls and copies, cutting the drops on high-rate links
+//! - `pcap`: a libpcap handle, the only one applying a BPF filter in the kernel, so it is selected whenever
+//!   the sniffing process is started with a filter
+//!
+//! All of them feed the same frame queue and parser, the backend applies from the next sniffing process.
+//! The filter is kept when a paused sniffing process is resumed, and cleared once stopped
+//!
+//! The buffers and the read timeout of the channel can be configured too, but not while capturing: the read
+//! buffer of pnet bounds the captured length of the frames, the ring of `tpacket_v3` only uses the timeout.
+//! So can the promiscuous mode, applied by every backend each time a channel is opened (resumes included)
+
+use std::io::{self, ErrorKind};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone};
+use log::{error, info, warn};
+use pcap::{Active, Capture, Linktype};
+use pnet::datalink::Channel::Ethernet;
+use pnet::datalink::{self, ChannelType, Config, DataLinkReceiver, NetworkInterface};
+
+#[cfg(target_os = "linux")]
+use crate::tpacket::TpacketRing;
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod CaptureBackends {
+    pub const PNET: &str = "pnet";
+    pub const TPACKET_V3: &str = "tpacket_v3";
+    pub const PCAP: &str = "pcap";
+
+    #[cfg(target_os = "linux")]
+    pub const ALL: [&str; 3] = [PNET, TPACKET_V3, PCAP];
+    #[cfg(not(target_os = "linux"))]
+    pub const ALL: [&str; 2] = [PNET, PCAP];
+}
+
+/// Largest frame that can be captured
+const MAX_FRAME_LENGTH: usize = 65535;
+
+/// Buffers, read timeout and promiscuous mode of the channels opened by the following sniffing processes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureConfig {
+    pub read_buffer_size: usize,
+    pub write_buffer_size: usize,
+    /// Periodically wakes up the sniffing thread, to handle stop requests and heartbeats while idle
+    pub read_timeout: Duration,
+    /// Whether the frames not addressed to the host are received too
+    pub promiscuous: bool,
+}
+
+impl CaptureConfig {
+    pub fn new() -> Self {
+        CaptureConfig {
+            read_buffer_size: 16384,
+            write_buffer_size: 16384,
+            read_timeout: Duration::from_millis(200),
+            promiscuous: true,
+        }
+    }
+
+    /// Configuration of the datalink channel
+    pub fn get_channel_config(&self) -> Config {
+        Config {
+            write_buffer_size: self.write_buffer_size,
+            read_buffer_size: self.read_buffer_size,
+            read_timeout: Some(self.read_timeout),
+            write_timeout: None,
+            channel_type: ChannelType::Layer2,
+            bpf_fd_attempts: 1000,
+            linux_fanout: None,
+            promiscuous: self.promiscuous,
+        }
+    }
+}
+
+/// Sets the buffer sizes (powers of two, in bytes) and the read timeout of the channels of the following sniffing
+/// processes, refused while capturing
+#[tauri::command]
+pub fn set_capture_config(
+    state: tauri::State<SniffingState>,
+    read_buffer_size: usize,
+    write_buffer_size: usize,
+    read_timeout_ms: u64,
+) -> Result<(), SniffingError> {
+    set_capture_config_internal(&state, read_buffer_size, write_buffer_size, read_timeout_ms)
+}
+
+fn set_capture_config_internal(
+    state: &SniffingState,
+    read_buffer_size: usize,
+    write_buffer_size: usize,
+    read_timeout_ms: u64,
+) -> Result<(), SniffingError> {
+    if !read_buffer_size.is_power_of_two() || !write_buffer_size.is_power_of_two() {
+        warn!(
+            "Invalid capture buffer sizes: {} (read), {} (write)",
+            read_buffer_size, write_buffer_size
+        );
+        return Err(SniffingError::FailedChannelCreation(format!(
+            "The buffer sizes must be powers of two, not {} (read) and {} (write)",
+            read_buffer_size, write_buffer_size
+        )));
+    }
+
+    if read_timeout_ms == 0 {
+        return Err(SniffingError::InvalidConfiguration(
+            "The read timeout must be positive".to_owned(),
+        ));
+    }
+
+    if is_capturing(state) {
+        return Err(SniffingError::InvalidConfiguration(
+            "The capture configuration can't be changed while capturing".to_owned(),
+        ));
+    }
+
+    let mut capture_config = state.capture_config.lock().unwrap();
+    capture_config.read_buffer_size = read_buffer_size;
+    capture_config.write_buffer_size = write_buffer_size;
+    capture_config.read_timeout = Duration::from_millis(read_timeout_ms);
+    info!("Capture configuration: {:?}", capture_config);
+
+    Ok(())
+}
+
+/// Enables or disables the promiscuous mode of the following sniffing processes, refused while capturing
+///
+/// Without it only the frames addressed to the host (and broadcast or multicast) are captured
+#[tauri::command]
+pub fn set_promiscuous(
+    state: tauri::State<SniffingState>,
+    enabled: bool,
+) -> Result<(), SniffingError> {
+    set_promiscuous_internal(&state, enabled)
+}
+
+fn set_promiscuous_internal(state: &SniffingState, enabled: bool) -> Result<(), SniffingError> {
+    if is_capturing(state) {
+        return Err(SniffingError::InvalidConfiguration(
+            "The promiscuous mode can't be changed while capturing".to_owned(),
+        ));
+    }
+
+    info!("Promiscuous mode: {}", enabled);
+    state.capture_config.lock().unwrap().promiscuous = enabled;
+
+    Ok(())
+}
+
+/// Whether a sniffing process is capturing, started and neither paused nor stopped
+pub(crate) fn is_capturing(state: &SniffingState) -> bool {
+    matches!(
+        state.packets.lock().unwrap().capture_intervals.last(),
+        Some((_, None))
+    )
+}
+
+/// Resolves the filter of a sniffing process: the given one, or the one of the paused process when resumed
+///
+/// An empty filter captures every frame, even when resumed
+pub(crate) fn get_capture_filter(
+    state: &SniffingState,
+    is_resume: bool,
+    filter: Option<String>,
+) -> Option<String> {
+    match filter {
+        Some(filter) if filter.trim().is_empty() => None,
+        Some(filter) => Some(filter),
+        None if is_resume => state.capture_filter.lock().unwrap().clone(),
+        None => None,
+    }
+}
+
+/// Checks that a BPF filter compiles for the frames of the given link-layer header type
+pub(crate) fn validate_bpf_filter(filter: &str, link_type: u32) -> Result<(), SniffingError> {
+    Capture::dead(Linktype(link_type as i32))
+        .and_then(|capture| capture.compile(filter, true))
+        .map(|_| ())
+        .map_err(|e| {
+            warn!("Invalid BPF filter {}: {}", filter, e);
+            SniffingError::InvalidBpfFilter(format!("Invalid BPF filter {}: {}", filter, e))
+        })
+}
+
+/// Selects the backend of the following sniffing processes, among the ones available on the platform
+#[tauri::command]
+pub fn set_capture_backend(
+    state: tauri::State<SniffingState>,
+    backend: String,
+) -> Result<(), SniffingError> {
+    let backend = backend.to_lowercase();
+    if !CaptureBackends::ALL.contains(&backend.as_str()) {
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "Unknown or unsupported capture backend: {} (available: {})",
+            backend,
+            CaptureBackends::ALL.join(", ")
+        )));
+    }
+
+    info!("Capture backend: {}", backend);
+    *state.capture_backend.lock().unwrap() = backend;
+
+    Ok(())
+}
+
+/// Source of the frames of a sniffing process
+pub enum CaptureChannel {
+    Pnet(Box<dyn DataLinkReceiver>),
+    #[cfg(target_os = "linux")]
+    Tpacket(TpacketRing),
+    Pcap(Capture<Active>),
+}
+
+impl CaptureChannel {
+    /// Maximum number of bytes read of each frame: the read buffer of pnet, or the whole frame otherwise
+    pub fn get_snaplen(&self, config: &Config) -> usize {
+        match self {
+            CaptureChannel::Pnet(_) => config.read_buffer_size,
+            #[cfg(target_os = "linux")]
+            CaptureChannel::Tpacket(_) => MAX_FRAME_LENGTH,
+            CaptureChannel::Pcap(_) => MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Next frame, with its capture time when recorded by the backend
+    ///
+    /// Fails with `TimedOut` when no frame is received within the read timeout
+    pub fn next(&mut self) -> io::Result<(&[u8], Option<DateTime<Local>>)> {
+        match self {
+            CaptureChannel::Pnet(receiver) => receiver.next().map(|frame| (frame, None)),
+            #[cfg(target_os = "linux")]
+            CaptureChannel::Tpacket(ring) => ring
+                .next()
+                .map(|(frame, timestamp)| (frame, Some(Local.timestamp_nanos(timestamp * 1000)))),
+            CaptureChannel::Pcap(capture) => match capture.next() {
+                Ok(packet) => {
+                    // The fields of timeval are narrower on some platforms
+                    #[allow(clippy::unnecessary_cast)]
+                    let timestamp = packet.header.ts.tv_sec as i64 * 1_000_000
+                        + packet.header.ts.tv_usec as i64;
+                    Ok((packet.data, Some(Local.timestamp_nanos(timestamp * 1000))))
+                }
+                Err(pcap::Error::TimeoutExpired) => Err(ErrorKind::TimedOut.into()),
+                Err(e) => Err(io::Error::new(ErrorKind::Other, e.to_string())),
+            },
+        }
+    }
+}
+
+/// Opens the channel of the backend on the interface, the filter is only applied by the `pcap` backend
+pub fn open_capture_channel(
+    interface: &NetworkInterface,
+    backend: &str,
+    config: Config,
+    filter: Option<&str>,
+) -> Result<CaptureChannel, SniffingError> {
+    match backend {
+        CaptureBackends::PCAP => {
+            open_pcap_capture(interface, &config, filter).map(CaptureChannel::Pcap)
+        }
+        #[cfg(target_os = "linux")]
+        CaptureBackends::TPACKET_V3 => TpacketRing::open(
+            interface.index,
+            config.promiscuous,
+            config.read_timeout.unwrap_or_default(),
+        )
+        .map(CaptureChannel::Tpacket)
+        .map_err(get_channel_error),
+        _ => match datalink::channel(interface, config) {
+            Ok(Ethernet(_, rx)) => Ok(CaptureChannel::Pnet(rx)),
+            Ok(_) => Err(SniffingError::UnhandledChannelType(
+                "Unhandled channel type".to_owned(),
+            )),
+            Err(e) => Err(get_channel_error(e)),
+        },
+    }
+}
+
+fn open_pcap_capture(
+    interface: &NetworkInterface,
+    config: &Config,
+    filter: Option<&str>,
+) -> Result<Capture<Active>, SniffingError> {
+    let read_timeout = config.read_timeout.unwrap_or_default().as_millis();
+    let mut capture = Capture::from_device(interface.name.as_str())
+        .and_then(|capture| {
+            capture
+                .promisc(config.promiscuous)
+                .snaplen(MAX_FRAME_LENGTH as i32)
+                .timeout(read_timeout.min(i32::MAX as u128) as i32)
+                .open()
+        })
+        .map_err(|e| {
+            // libpcap only reports the missing privileges in its message
+            let kind = if e.to_string().to_lowercase().contains("permission") {
+                ErrorKind::PermissionDenied
+            } else {
+                ErrorKind::Other
+            };
+            get_channel_error(io::Error::new(kind, e.to_string()))
+        })?;
+
+    if let Some(filter) = filter {
+        capture.filter(filter, true).map_err(|e| {
+            SniffingError::InvalidBpfFilter(format!("Invalid BPF filter {}: {}", filter, e))
+        })?;
+        info!("[{}] BPF filter applied: {}", interface.name, filter);
+    }
+
+    Ok(capture)
+}
+
+fn get_channel_error(e: io::Error) -> SniffingError {
+    if e.kind() == ErrorKind::PermissionDenied {
+        error!("Channel creation not permitted: {}", e);
+        SniffingError::InsufficientPrivileges("Insufficient privileges to capture".to_owned())
+    } else {
+        error!("Unexpected channel creation failure: {}", e);
+        SniffingError::FailedChannelCreation("Unexpected channel creation failure".to_owned())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::time::Duration;
+
+    use crate::{SniffingError, SniffingState};
+
+    use super::{
+        get_capture_filter, set_capture_config_internal, set_promiscuous_internal, CaptureConfig,
+    };
+
+    #[test]
+    fn capture_config_validated() {
+        let state = SniffingState::new();
+        assert!(matches!(
+            set_capture_config_internal(&state, 65536, 3000, 100),
+            Err(SniffingError::FailedChannelCreation(_))
+        ));
+        assert!(matches!(
+            set_capture_config_internal(&state, 0, 4096, 100),
+            Err(SniffingError::FailedChannelCreation(_))
+        ));
+        assert!(set_capture_config_internal(&state, 65536, 4096, 0).is_err());
+        assert_eq!(*state.capture_config.lock().unwrap(), CaptureConfig::new());
+
+        set_capture_config_internal(&state, 65536, 4096, 50).unwrap();
+        let capture_config = *state.capture_config.lock().unwrap();
+        assert_eq!(capture_config.read_buffer_size, 65536);
+        assert_eq!(
+            capture_config.get_channel_config().read_timeout,
+            Some(Duration::from_millis(50))
+        );
+
+        // Refused while capturing, accepted once paused
+        state
+            .packets
+            .lock()
+            .unwrap()
+            .capture_intervals
+            .push((100, None));
+        assert!(matches!(
+            set_capture_config_internal(&state, 16384, 16384, 200),
+            Err(SniffingError::InvalidConfiguration(_))
+        ));
+        state.packets.lock().unwrap().capture_intervals[0].1 = Some(200);
+        set_capture_config_internal(&state, 16384, 16384, 200).unwrap();
+        assert_eq!(*state.capture_config.lock().unwrap(), CaptureConfig::new());
+    }
+
+    #[test]
+    fn promiscuous_mode_kept_until_capturing() {
+        let state = SniffingState::new();
+        set_promiscuous_internal(&state, false).unwrap();
+        // Kept along with the other settings
+        set_capture_config_internal(&state, 65536, 16384, 200).unwrap();
+        assert!(
+            !state
+                .capture_config
+                .lock()
+                .unwrap()
+                .get_channel_config()
+                .promiscuous
+        );
+
+        state
+            .packets
+            .lock()
+            .unwrap()
+            .capture_intervals
+            .push((100, None));
+        assert!(matches!(
+            set_promiscuous_internal(&state, true),
+            Err(SniffingError::InvalidConfiguration(_))
+        ));
+        assert!(!state.capture_config.lock().unwrap().promiscuous);
+    }
+
+    #[test]
+    fn filter_kept_across_resumes() {
+        let state = SniffingState::new();
+        let filter = Some("tcp port 443".to_owned());
+        assert_eq!(get_capture_filter(&state, false, filter.clone()), filter);
+
+        *state.capture_filter.lock().unwrap() = filter.clone();
+        assert_eq!(get_capture_filter(&state, true, None), filter);
+        assert_eq!(
+            get_capture_filter(&state, true, Some("udp".to_owned())).as_deref(),
+            Some("udp")
+        );
+        // Cleared on demand, or by a new sniffing process
+        assert_eq!(get_capture_filter(&state, true, Some(" ".to_owned())), None);
+        assert_eq!(get_capture_filter(&state, false, None), None);
+    }
+}
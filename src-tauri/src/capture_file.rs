@@ -0,0 +1,1349 @@
+//! Import of packets from capture files in pcap format, or in pcapng format through [`crate::pcapng`] and
+//! in NetMon 2.x format through [`crate::netmon`], detected by their first bytes
+//!
+//! The pcap global header starts with a magic number that identifies both the byte order used by the
+//! machine that wrote the file and the resolution of the packets timestamps:
+//! - `0xa1b2c3d4`: microseconds resolution
+//! - `0xa1b23c4d`: nanoseconds resolution
+//!
+//! When read with the opposite byte order (`0xd4c3b2a1`, `0x4d3cb2a1`) all the header fields must be byte-swapped
+//!
+//! Exported files are always written in little-endian byte order with microseconds resolution, optionally
+//! with the packets sorted by timestamp
+//!
+//! Gzip-compressed files, detected by their magic number or `.gz` extension, are decompressed while reading
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{Local, TimeZone};
+use flate2::read::GzDecoder;
+use log::{debug, info, warn};
+use pnet::datalink::NetworkInterface;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{compute_flow_hash, get_cast_type};
+use sniffer_parser::serializable_packet::ParsedPacket;
+use sniffer_parser::{
+    cleanup_sniffing_state, parse_dot11_frame, parse_ethernet_bytes, parse_loop_frame,
+    parse_null_frame, parse_radiotap_frame, set_enabled_dissectors, set_registered_ethertypes,
+    HeaderLength, LoopbackFamilies,
+};
+use tauri::{Window, Wry};
+
+use crate::bookmarks::{read_bookmarks_file, write_bookmarks_file};
+use crate::capture_backend::is_capturing;
+use crate::filtering::{apply_all_strong_filters, PacketsCollection, RawPacket};
+use crate::netmon::{NetmonReader, NETMON_SIGNATURE, NETMON_V1_SIGNATURE};
+use crate::pcapng::{
+    get_flags_direction, get_flags_errors, PcapngReader, PcapngRecord, PCAPNG_MAGIC_NUMBER,
+};
+use crate::report::data::{PacketExchange, SourceDestination};
+use crate::sessions::{read_description_file, write_description_file};
+use crate::{store_packet, SniffingError, SniffingState};
+
+/// Magic numbers of the pcap global header, as read in little-endian byte order
+#[allow(non_snake_case)]
+mod PcapMagicNumbers {
+    pub const MICROSECONDS: u32 = 0xa1b2c3d4;
+    pub const MICROSECONDS_SWAPPED: u32 = 0xd4c3b2a1;
+    pub const NANOSECONDS: u32 = 0xa1b23c4d;
+    pub const NANOSECONDS_SWAPPED: u32 = 0x4d3cb2a1;
+}
+
+/// Link-layer header types (LINKTYPE_*) supported by the importer
+#[allow(non_snake_case)]
+pub mod LinkTypes {
+    pub const NULL: u32 = 0;
+    pub const ETHERNET: u32 = 1;
+    pub const IEEE802_11: u32 = 105;
+    pub const LOOP: u32 = 108;
+    pub const IEEE802_11_RADIOTAP: u32 = 127;
+}
+
+/// Hardware types (ARPHRD_*) reported by Linux for wireless interfaces in monitor mode
+const ARPHRD_IEEE80211: u32 = 801;
+const ARPHRD_IEEE80211_RADIOTAP: u32 = 803;
+
+/// Parses a frame according to its link-layer header type, `None` if too short or unsupported
+pub(crate) fn parse_frame(link_type: u32, data: &[u8], id: usize) -> Option<ParsedPacket> {
+    let mut parsed_packet = match link_type {
+        LinkTypes::ETHERNET => Some(parse_ethernet_bytes(data, id)),
+        LinkTypes::IEEE802_11 => Some(parse_dot11_frame(data, id)),
+        LinkTypes::IEEE802_11_RADIOTAP => Some(parse_radiotap_frame(data, id)),
+        LinkTypes::NULL => Some(parse_null_frame(data, id)),
+        LinkTypes::LOOP => Some(parse_loop_frame(data, id)),
+        _ => None,
+    }?;
+
+    parsed_packet.set_cast_type(get_cast_type(&parsed_packet).map(str::to_owned));
+    parsed_packet.set_flow_hash(compute_flow_hash(&parsed_packet));
+    Some(parsed_packet)
+}
+
+/// Detects the link-layer header type of the frames captured on an interface, Ethernet if unknown
+///
+/// Loopback interfaces use the BSD loopback encapsulation, except on Linux where they carry Ethernet headers
+pub(crate) fn get_interface_link_type(interface: &NetworkInterface) -> u32 {
+    if interface.is_loopback() && !cfg!(target_os = "linux") {
+        return LinkTypes::NULL;
+    }
+
+    let hardware_type = std::fs::read_to_string(format!("/sys/class/net/{}/type", interface.name))
+        .ok()
+        .and_then(|hardware_type| hardware_type.trim().parse::<u32>().ok());
+
+    match hardware_type {
+        Some(ARPHRD_IEEE80211) => LinkTypes::IEEE802_11,
+        Some(ARPHRD_IEEE80211_RADIOTAP) => LinkTypes::IEEE802_11_RADIOTAP,
+        _ => LinkTypes::ETHERNET,
+    }
+}
+
+/// Whether the loopback header of the captured frames is replaced by a zeroed Ethernet header (BPF channels)
+pub(crate) const LOOPBACK_HEADER_REPLACED: bool = cfg!(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd"
+));
+
+/// Rebuilds the DLT_NULL header of a frame captured on a loopback interface through BPF, where it is
+/// replaced by a zeroed Ethernet header, from the version of the carried IP packet
+pub(crate) fn restore_loopback_header(frame: &[u8]) -> Vec<u8> {
+    let packet = frame.get(HeaderLength::ETHERNET..).unwrap_or(&[]);
+
+    let family = match packet.first().map(|byte| byte >> 4) {
+        Some(6) if cfg!(any(target_os = "macos", target_os = "ios")) => {
+            LoopbackFamilies::INET6_DARWIN
+        }
+        Some(6) if cfg!(target_os = "freebsd") => LoopbackFamilies::INET6_FREEBSD,
+        Some(6) => LoopbackFamilies::INET6_BSD,
+        _ => LoopbackFamilies::INET,
+    };
+
+    // In the byte order of the capturing host
+    let mut restored = family.to_ne_bytes().to_vec();
+    restored.extend_from_slice(packet);
+    restored
+}
+
+const GLOBAL_HEADER_LENGTH: usize = 24;
+const RECORD_HEADER_LENGTH: usize = 16;
+
+/// Upper bound to the length of a single record, to reject corrupted files before allocating
+pub(crate) const MAX_RECORD_LENGTH: u32 = 16 * 1024 * 1024;
+
+const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+
+/// Packet read from a capture file
+#[derive(Debug)]
+pub struct PcapRecord {
+    /// Capture timestamp, in microseconds since the Unix epoch
+    pub timestamp: i64,
+    /// Length of the packet on the wire, possibly greater than the captured data
+    pub original_length: u32,
+    pub data: Vec<u8>,
+}
+
+/// Sequential reader of the packets stored in a pcap stream
+pub struct PcapReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    nanoseconds: bool,
+    pub version: (u16, u16),
+    pub snapshot_length: u32,
+    pub link_type: u32,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the global header, detecting byte order and timestamps resolution
+    pub fn new(mut reader: R) -> Result<Self, SniffingError> {
+        let mut header = [0u8; GLOBAL_HEADER_LENGTH];
+        reader.read_exact(&mut header).map_err(|e| {
+            SniffingError::InvalidCaptureFile(format!("Unable to read pcap global header: {}", e))
+        })?;
+
+        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let (big_endian, nanoseconds) = match magic {
+            PcapMagicNumbers::MICROSECONDS => (false, false),
+            PcapMagicNumbers::MICROSECONDS_SWAPPED => (true, false),
+            PcapMagicNumbers::NANOSECONDS => (false, true),
+            PcapMagicNumbers::NANOSECONDS_SWAPPED => (true, true),
+            _ => {
+                return Err(SniffingError::InvalidCaptureFile(format!(
+                    "Unknown pcap magic number: {:#010x}",
+                    magic
+                )))
+            }
+        };
+
+        let mut pcap_reader = PcapReader {
+            reader,
+            big_endian,
+            nanoseconds,
+            version: (0, 0),
+            snapshot_length: 0,
+            link_type: 0,
+        };
+
+        pcap_reader.version = (
+            pcap_reader.read_u16(&header[4..6]),
+            pcap_reader.read_u16(&header[6..8]),
+        );
+        pcap_reader.snapshot_length = pcap_reader.read_u32(&header[16..20]);
+        pcap_reader.link_type = pcap_reader.read_u32(&header[20..24]);
+
+        Ok(pcap_reader)
+    }
+
+    /// Read the next packet, `None` at the end of the stream
+    pub fn next_record(&mut self) -> Result<Option<PcapRecord>, SniffingError> {
+        let (timestamp, captured_length, original_length) = match self.read_record_header()? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        let mut data = vec![0u8; captured_length as usize];
+        self.reader.read_exact(&mut data).map_err(|e| {
+            SniffingError::InvalidCaptureFile(format!("Truncated pcap record: {}", e))
+        })?;
+
+        Ok(Some(PcapRecord {
+            timestamp,
+            original_length,
+            data,
+        }))
+    }
+
+    /// Read a record header, returning timestamp, captured and original length
+    fn read_record_header(&mut self) -> Result<Option<(i64, u32, u32)>, SniffingError> {
+        let mut header = [0u8; RECORD_HEADER_LENGTH];
+        let mut read = 0;
+        while read < RECORD_HEADER_LENGTH {
+            match self.reader.read(&mut header[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(SniffingError::InvalidCaptureFile(format!(
+                        "Truncated pcap record header: {} of {} bytes",
+                        read, RECORD_HEADER_LENGTH
+                    )))
+                }
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => {
+                    return Err(SniffingError::InvalidCaptureFile(format!(
+                        "Unable to read pcap record header: {}",
+                        e
+                    )))
+                }
+            }
+        }
+
+        let seconds = self.read_u32(&header[0..4]) as i64;
+        let fraction = self.read_u32(&header[4..8]) as i64;
+        let captured_length = self.read_u32(&header[8..12]);
+        let original_length = self.read_u32(&header[12..16]);
+
+        if captured_length > MAX_RECORD_LENGTH {
+            return Err(SniffingError::InvalidCaptureFile(format!(
+                "Invalid pcap record length: {}",
+                captured_length
+            )));
+        }
+
+        let fraction = if self.nanoseconds {
+            fraction / 1000
+        } else {
+            fraction
+        };
+
+        Ok(Some((
+            seconds * 1_000_000 + fraction,
+            captured_length,
+            original_length,
+        )))
+    }
+
+    fn read_u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+impl<R: SkipRead> PcapReader<R> {
+    /// Skip the next packet without reading its data, returning its timestamp and captured length
+    pub fn skip_record(&mut self) -> Result<Option<(i64, u32)>, SniffingError> {
+        let (timestamp, captured_length, _) = match self.read_record_header()? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        self.reader.skip(captured_length as u64).map_err(|e| {
+            SniffingError::InvalidCaptureFile(format!("Truncated pcap record: {}", e))
+        })?;
+
+        Ok(Some((timestamp, captured_length)))
+    }
+}
+
+/// Stream of a capture file whose record data can be skipped without being read
+pub trait SkipRead: Read {
+    /// Skip the next `length` bytes, failing if the stream ends before
+    fn skip(&mut self, length: u64) -> io::Result<()>;
+}
+
+/// Uncompressed files are seeked through, reading only the last byte to tell a truncated record
+impl SkipRead for BufReader<File> {
+    fn skip(&mut self, length: u64) -> io::Result<()> {
+        if length == 0 {
+            return Ok(());
+        }
+
+        // As `SeekFrom::Current`, but keeping the buffer when the end of the record is already read
+        self.seek_relative(length as i64 - 1)?;
+        self.read_exact(&mut [0u8; 1])
+    }
+}
+
+/// Decompressed streams are read through, discarding the data
+impl<'a> SkipRead for BufReader<Box<dyn Read + 'a>> {
+    fn skip(&mut self, length: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut self.by_ref().take(length), &mut io::sink())?;
+        if skipped < length {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("{} of {} bytes", skipped, length),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Sequential writer of packets in a pcap stream
+pub struct PcapWriter<W: Write> {
+    writer: W,
+    pub link_type: u32,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Write the global header for packets of the given link-layer header type
+    pub fn new(mut writer: W, link_type: u32) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(GLOBAL_HEADER_LENGTH);
+        header.extend_from_slice(&PcapMagicNumbers::MICROSECONDS.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        // Timezone offset and timestamps accuracy, always zero
+        header.extend_from_slice(&[0u8; 8]);
+        header.extend_from_slice(&MAX_RECORD_LENGTH.to_le_bytes());
+        header.extend_from_slice(&link_type.to_le_bytes());
+        writer.write_all(&header)?;
+
+        Ok(PcapWriter { writer, link_type })
+    }
+
+    /// Write a packet captured at `timestamp` (microseconds since the Unix epoch)
+    pub fn write_record(&mut self, timestamp: i64, data: &[u8]) -> io::Result<()> {
+        let mut header = Vec::with_capacity(RECORD_HEADER_LENGTH);
+        header.extend_from_slice(&(timestamp.div_euclid(1_000_000) as u32).to_le_bytes());
+        header.extend_from_slice(&(timestamp.rem_euclid(1_000_000) as u32).to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(data)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Stream of a gzip-compressed capture, reporting decompression failures as such
+struct GzipStream<R: BufRead>(GzDecoder<R>);
+
+impl<R: BufRead> Read for GzipStream<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(|e| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Corrupt or truncated gzip stream: {}", e),
+            )
+        })
+    }
+}
+
+/// Decompress the stream when it starts with the gzip magic number, or when `gzip` is set
+pub(crate) fn get_capture_stream<'a, R: BufRead + 'a>(
+    mut reader: R,
+    gzip: bool,
+) -> Result<Box<dyn Read + 'a>, SniffingError> {
+    if gzip || is_gzip_stream(&mut reader)? {
+        Ok(Box::new(BufReader::new(GzipStream(GzDecoder::new(reader)))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Whether the stream starts with the gzip magic number
+pub(crate) fn is_gzip_stream<R: BufRead>(reader: &mut R) -> Result<bool, SniffingError> {
+    let magic = reader.fill_buf().map_err(|e| {
+        SniffingError::InvalidCaptureFile(format!("Unable to read capture file: {}", e))
+    })?;
+
+    Ok(magic.starts_with(&GZIP_MAGIC_NUMBER))
+}
+
+/// Sequential reader of the packets stored in a capture stream, in any format
+pub(crate) enum CaptureReader<R: Read> {
+    Pcap(PcapReader<R>),
+    Pcapng(PcapngReader<R>),
+    Netmon(NetmonReader),
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read the next packet along with the link type of its interface, `None` at the end of the stream
+    fn next_record(&mut self) -> Result<Option<PcapngRecord>, SniffingError> {
+        match self {
+            CaptureReader::Pcap(pcap_reader) => {
+                Ok(pcap_reader.next_record()?.map(|record| PcapngRecord {
+                    link_type: pcap_reader.link_type,
+                    flags: None,
+                    record,
+                }))
+            }
+            CaptureReader::Pcapng(pcapng_reader) => pcapng_reader.next_record(),
+            CaptureReader::Netmon(netmon_reader) => {
+                Ok(netmon_reader.next_record()?.map(|record| PcapngRecord {
+                    link_type: netmon_reader.link_type,
+                    flags: None,
+                    record,
+                }))
+            }
+        }
+    }
+
+    /// Hostnames recorded in the stream, only by pcapng Name Resolution Blocks
+    fn take_names(&mut self) -> Vec<(IpAddr, String)> {
+        match self {
+            CaptureReader::Pcap(_) | CaptureReader::Netmon(_) => vec![],
+            CaptureReader::Pcapng(pcapng_reader) => pcapng_reader.take_names(),
+        }
+    }
+
+    /// Link type of all the packets, unknown in pcapng streams until their interfaces are described
+    pub(crate) fn get_link_type(&self) -> Option<u32> {
+        match self {
+            CaptureReader::Pcap(pcap_reader) => Some(pcap_reader.link_type),
+            CaptureReader::Pcapng(_) => None,
+            CaptureReader::Netmon(netmon_reader) => Some(netmon_reader.link_type),
+        }
+    }
+}
+
+impl<R: SkipRead> CaptureReader<R> {
+    /// Skip the next packet, returning the link type of its interface, its timestamp and captured length
+    ///
+    /// The data is skipped only in pcap streams, pcapng blocks and NetMon files being read as a whole anyway
+    pub(crate) fn skip_record(&mut self) -> Result<Option<(u32, i64, u32)>, SniffingError> {
+        match self {
+            CaptureReader::Pcap(pcap_reader) => {
+                Ok(pcap_reader
+                    .skip_record()?
+                    .map(|(timestamp, captured_length)| {
+                        (pcap_reader.link_type, timestamp, captured_length)
+                    }))
+            }
+            _ => Ok(self.next_record()?.map(|record| {
+                (
+                    record.link_type,
+                    record.record.timestamp,
+                    record.record.data.len() as u32,
+                )
+            })),
+        }
+    }
+}
+
+/// Reader of a pcap, pcapng or NetMon stream, already decompressed
+pub(crate) fn get_capture_reader<R: BufRead>(
+    mut stream: R,
+) -> Result<CaptureReader<R>, SniffingError> {
+    let magic = stream.fill_buf().map_err(|e| {
+        SniffingError::InvalidCaptureFile(format!("Unable to read capture file: {}", e))
+    })?;
+
+    if magic.starts_with(&PCAPNG_MAGIC_NUMBER) {
+        // Interfaces, each one with its link type, are described along the file
+        return Ok(CaptureReader::Pcapng(PcapngReader::new(stream)?));
+    }
+
+    if magic.starts_with(&NETMON_SIGNATURE) || magic.starts_with(&NETMON_V1_SIGNATURE) {
+        return Ok(CaptureReader::Netmon(NetmonReader::new(stream)?));
+    }
+
+    Ok(CaptureReader::Pcap(PcapReader::new(stream)?))
+}
+
+/// Opens a pcap, pcapng or NetMon file, optionally gzip-compressed
+fn open_capture_file(
+    path: &str,
+) -> Result<CaptureReader<BufReader<Box<dyn Read + 'static>>>, SniffingError> {
+    let capture_reader = get_capture_reader(open_capture_stream(path)?)?;
+
+    if let CaptureReader::Pcap(pcap_reader) = &capture_reader {
+        if !is_supported_link_type(pcap_reader.link_type) {
+            return Err(SniffingError::InvalidCaptureFile(format!(
+                "Unsupported link type: {}",
+                pcap_reader.link_type
+            )));
+        }
+    }
+
+    Ok(capture_reader)
+}
+
+/// Opens the stream of a capture file, decompressed if gzip-compressed
+fn open_capture_stream(path: &str) -> Result<BufReader<Box<dyn Read + 'static>>, SniffingError> {
+    let file = File::open(path).map_err(|e| {
+        SniffingError::CaptureFileAccessFailed(format!("Unable to open {}: {}", path, e))
+    })?;
+
+    Ok(BufReader::new(get_capture_stream(
+        BufReader::new(file),
+        path.ends_with(".gz"),
+    )?))
+}
+
+/// Parses the packets stored in a capture file, without collecting them
+///
+/// Application layer dissectors keep their default state, the packets of unsupported link types are skipped
+pub(crate) fn read_capture_file(path: &str) -> Result<Vec<ParsedPacket>, SniffingError> {
+    let mut capture_reader = open_capture_file(path)?;
+    let mut packets = vec![];
+
+    cleanup_sniffing_state();
+    while let Some(PcapngRecord {
+        link_type, record, ..
+    }) = capture_reader.next_record()?
+    {
+        if let Some(mut packet) = parse_frame(link_type, &record.data, packets.len()) {
+            packet.set_timestamp(record.timestamp);
+            packets.push(packet);
+        }
+    }
+    cleanup_sniffing_state();
+
+    Ok(packets)
+}
+
+/// Records read between two `packet_received` events while loading a capture file
+const LOAD_PROGRESS_RECORDS: usize = 1000;
+
+/// Replaces the collected packets with the ones stored in a pcap, pcapng or NetMon file, returning the number of
+/// loaded packets
+///
+/// The direction and link layer errors recorded by the pcapng `epb_flags` option are kept with the packets,
+/// while the hostnames recorded by pcapng Name Resolution Blocks replace the cached ones of the same
+/// addresses, so that they are shown without lookups. The collection is released and `packet_received`
+/// emitted every [`LOAD_PROGRESS_RECORDS`] records, so that the interface updates during long loads
+///
+/// Refused while sniffing, since the loaded packets would be mixed with the captured ones
+#[tauri::command]
+pub fn load_pcap(
+    state: tauri::State<SniffingState>,
+    window: Window<Wry>,
+    path: String,
+) -> Result<usize, SniffingError> {
+    if is_capturing(&state) {
+        return Err(SniffingError::LoadCaptureFileWhileSniffing(
+            "Stop or pause the sniffing process before loading a capture file".to_owned(),
+        ));
+    }
+
+    let capture_reader = open_capture_file(&path)?;
+    load_capture(&state, &path, capture_reader, &|| {
+        let _result = window.emit("packet_received", ());
+    })
+}
+
+/// Replaces the collected packets with the Ethernet frames stored in a pcap file, optionally
+/// gzip-compressed, returning the number of imported packets
+///
+/// The frames are parsed and collected as the captured ones, with IDs restarting from 0 as the import replaces
+/// the collection. Files of other link types are rejected as a whole, instead of skipping all their packets, and
+/// `packet_received` is emitted as while loading a capture file
+///
+/// Refused while sniffing, as is the loading of a capture file
+#[tauri::command]
+pub fn import_pcap(
+    state: tauri::State<SniffingState>,
+    window: Window<Wry>,
+    path: String,
+) -> Result<usize, SniffingError> {
+    import_pcap_internal(&state, &path, &|| {
+        let _result = window.emit("packet_received", ());
+    })
+}
+
+pub(crate) fn import_pcap_internal(
+    state: &SniffingState,
+    path: &str,
+    on_progress: &dyn Fn(),
+) -> Result<usize, SniffingError> {
+    if is_capturing(state) {
+        return Err(SniffingError::LoadCaptureFileWhileSniffing(
+            "Stop or pause the sniffing process before importing a capture file".to_owned(),
+        ));
+    }
+
+    let pcap_reader = PcapReader::new(open_capture_stream(path)?)?;
+    if pcap_reader.link_type != LinkTypes::ETHERNET {
+        warn!(
+            "Refused import of {} with link type {}",
+            path, pcap_reader.link_type
+        );
+        return Err(SniffingError::UnsupportedLinkType(format!(
+            "Only Ethernet captures (link type {}) can be imported, {} has link type {}",
+            LinkTypes::ETHERNET,
+            path,
+            pcap_reader.link_type
+        )));
+    }
+
+    load_capture(state, path, CaptureReader::Pcap(pcap_reader), on_progress)
+}
+
+/// Collects the packets of the capture in place of the ones collected so far, calling `on_progress` every
+/// [`LOAD_PROGRESS_RECORDS`] records with the collection released
+fn load_capture(
+    state: &SniffingState,
+    path: &str,
+    mut capture_reader: CaptureReader<BufReader<Box<dyn Read + 'static>>>,
+    on_progress: &dyn Fn(),
+) -> Result<usize, SniffingError> {
+    {
+        let mut sniffing_info = state.info.lock().unwrap();
+        state.packets.lock().unwrap().clear();
+        state.exchanged_packets.lock().unwrap().clear();
+        state.bookmarks.lock().unwrap().clear();
+        sniffing_info.counter = 0;
+    }
+
+    let bookmarked_positions = read_bookmarks_file(path);
+    *state.description.lock().unwrap() = read_description_file(path);
+
+    cleanup_sniffing_state();
+    set_enabled_dissectors(&state.dissectors.lock().unwrap());
+    set_registered_ethertypes(&state.ethertypes.lock().unwrap());
+
+    let mut position = 0;
+    let mut finished = false;
+    while !finished {
+        let mut sniffing_info = state.info.lock().unwrap();
+        let mut packets_collection = state.packets.lock().unwrap();
+        let mut exchanged_packets = state.exchanged_packets.lock().unwrap();
+        let mut bookmarks = state.bookmarks.lock().unwrap();
+
+        for _ in 0..LOAD_PROGRESS_RECORDS {
+            let capture_record = match capture_reader.next_record()? {
+                Some(capture_record) => capture_record,
+                None => {
+                    finished = true;
+                    break;
+                }
+            };
+
+            let bookmarked = bookmarked_positions.contains(&position);
+            position += 1;
+
+            let id = store_record(
+                capture_record,
+                &mut sniffing_info.counter,
+                &mut packets_collection,
+                &mut exchanged_packets,
+            );
+            if let (Some(id), true) = (id, bookmarked) {
+                bookmarks.insert(id);
+            }
+        }
+
+        drop((
+            sniffing_info,
+            packets_collection,
+            exchanged_packets,
+            bookmarks,
+        ));
+        on_progress();
+    }
+
+    cleanup_sniffing_state();
+
+    store_names(state, path, capture_reader.take_names());
+
+    let loaded_packets = state.packets.lock().unwrap().packets.len();
+    info!("Loaded {} packets from {}", loaded_packets, path);
+
+    Ok(loaded_packets)
+}
+
+/// Capture files of a directory loaded as a single capture
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CaptureDirectoryLoad {
+    /// Capture files loaded, in filename order
+    pub files: Vec<String>,
+    /// Files that aren't captures, or couldn't be opened
+    pub skipped: Vec<String>,
+    pub packets: usize,
+}
+
+/// Replaces the collected packets with the ones stored in all the capture files of a directory (e.g. rotated
+/// by a capture tool), as a single capture
+///
+/// The packets of the files are merged in order of timestamp, the ones with the same timestamp in filename
+/// order, so that their IDs follow the capture time. The other files are skipped, as is the rest of a file
+/// once unreadable (e.g. the truncated last record of a file being written)
+///
+/// Refused while sniffing, as is the loading of a single file
+#[tauri::command]
+pub fn load_pcap_dir(
+    state: tauri::State<SniffingState>,
+    dir: String,
+) -> Result<CaptureDirectoryLoad, SniffingError> {
+    load_pcap_dir_internal(&state, &dir)
+}
+
+pub(crate) fn load_pcap_dir_internal(
+    state: &SniffingState,
+    dir: &str,
+) -> Result<CaptureDirectoryLoad, SniffingError> {
+    if is_capturing(state) {
+        return Err(SniffingError::LoadCaptureFileWhileSniffing(
+            "Stop or pause the sniffing process before loading a capture directory".to_owned(),
+        ));
+    }
+
+    let entries = fs::read_dir(dir).map_err(|e| {
+        SniffingError::CaptureFileAccessFailed(format!("Unable to read {}: {}", dir, e))
+    })?;
+    let mut paths = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+
+    let mut load = CaptureDirectoryLoad::default();
+    let mut capture_readers = vec![];
+    for path in paths {
+        let path = path.to_string_lossy().to_string();
+        match open_capture_file(&path) {
+            Ok(capture_reader) => {
+                capture_readers.push(capture_reader);
+                load.files.push(path);
+            }
+            Err(e) => {
+                debug!("Skipped {}: {:?}", path, e);
+                load.skipped.push(path);
+            }
+        }
+    }
+
+    if capture_readers.is_empty() {
+        warn!("No capture files in {}", dir);
+        return Err(SniffingError::CaptureFileAccessFailed(format!(
+            "No capture files in {}",
+            dir
+        )));
+    }
+
+    let mut sniffing_info = state.info.lock().unwrap();
+    let mut packets_collection = state.packets.lock().unwrap();
+    let mut exchanged_packets = state.exchanged_packets.lock().unwrap();
+
+    packets_collection.clear();
+    exchanged_packets.clear();
+    sniffing_info.counter = 0;
+    state.bookmarks.lock().unwrap().clear();
+
+    cleanup_sniffing_state();
+    set_enabled_dissectors(&state.dissectors.lock().unwrap());
+    set_registered_ethertypes(&state.ethertypes.lock().unwrap());
+
+    let mut next_records = capture_readers
+        .iter_mut()
+        .zip(&load.files)
+        .map(|(capture_reader, path)| read_next_record(capture_reader, path))
+        .collect::<Vec<Option<PcapngRecord>>>();
+
+    // The earliest of the next records of the files, the first file on ties
+    while let Some((_, index)) = next_records
+        .iter()
+        .enumerate()
+        .filter_map(|(index, record)| Some((record.as_ref()?.record.timestamp, index)))
+        .min()
+    {
+        let capture_record = next_records[index].take().unwrap();
+        next_records[index] = read_next_record(&mut capture_readers[index], &load.files[index]);
+
+        store_record(
+            capture_record,
+            &mut sniffing_info.counter,
+            &mut packets_collection,
+            &mut exchanged_packets,
+        );
+    }
+
+    cleanup_sniffing_state();
+
+    for (capture_reader, path) in capture_readers.iter_mut().zip(&load.files) {
+        store_names(state, path, capture_reader.take_names());
+    }
+
+    load.packets = packets_collection.packets.len();
+    info!(
+        "Loaded {} packets from {} files of {} ({} skipped)",
+        load.packets,
+        load.files.len(),
+        dir,
+        load.skipped.len()
+    );
+
+    Ok(load)
+}
+
+/// Next record of a file of a directory, `None` at its end or once unreadable
+fn read_next_record<R: Read>(
+    capture_reader: &mut CaptureReader<R>,
+    path: &str,
+) -> Option<PcapngRecord> {
+    capture_reader.next_record().unwrap_or_else(|e| {
+        warn!("Rest of {} skipped: {:?}", path, e);
+        None
+    })
+}
+
+/// Parses and collects a packet read from a capture file, returning its ID unless its link type is unsupported
+fn store_record(
+    PcapngRecord {
+        link_type,
+        flags,
+        record,
+    }: PcapngRecord,
+    counter: &mut usize,
+    packets_collection: &mut PacketsCollection,
+    exchanged_packets: &mut HashMap<SourceDestination, PacketExchange>,
+) -> Option<usize> {
+    let mut new_packet = match parse_frame(link_type, &record.data, *counter) {
+        Some(new_packet) => new_packet,
+        None => {
+            warn!("Skipped packet with unsupported link type {}", link_type);
+            return None;
+        }
+    };
+
+    // Recorded by the capture tool, more reliable than the one derived from the addresses
+    if let Some(flags) = flags {
+        new_packet.set_direction(get_flags_direction(flags).map(str::to_owned));
+        new_packet.set_link_errors(get_flags_errors(flags));
+    }
+
+    if record.original_length as usize > record.data.len() {
+        debug!(
+            "Packet {} truncated to {} of {} bytes",
+            counter,
+            record.data.len(),
+            record.original_length
+        );
+    }
+
+    let id = *counter;
+    new_packet.set_timestamp(record.timestamp);
+    *counter += 1;
+
+    store_packet(
+        packets_collection,
+        exchanged_packets,
+        new_packet,
+        link_type,
+        &record.data,
+        Local.timestamp_nanos(record.timestamp * 1000),
+    );
+
+    Some(id)
+}
+
+/// Caches the hostnames recorded in a capture file, replacing the ones of the same addresses
+fn store_names(state: &SniffingState, path: &str, names: Vec<(IpAddr, String)>) {
+    if names.is_empty() {
+        return;
+    }
+
+    info!("Loaded {} hostnames from {}", names.len(), path);
+    let mut hostnames = state.hostnames.lock().unwrap();
+    for (address, name) in names {
+        hostnames.insert(address, Some(name));
+    }
+}
+
+/// Writes the packets matching the filters (all of them when not given) in a pcap file, returning the
+/// number of written packets
+///
+/// The records are streamed to the file from the raw data kept in the collection, without copying it, once
+/// the collection is released so that the packets keep being collected while writing. With `sort_by_timestamp` the packets are written in order of capture time rather than in order of
+/// arrival (e.g. when captured by several sniffing processes), packets with the same timestamp keep their
+/// arrival order. Packets with a link type other than the one of the first packet are skipped
+#[tauri::command]
+pub fn export_pcap<'a>(
+    state: tauri::State<SniffingState>,
+    path: String,
+    filters_value: Option<Vec<(&'a str, &'a str)>>,
+    sort_by_timestamp: Option<bool>,
+) -> Result<usize, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+
+    let mut packets = match filters_value {
+        Some(filters_value) if !filters_value.is_empty() => {
+            let end = packets_collection.packets.len();
+            apply_all_strong_filters(end, &filters_value, &mut packets_collection)?
+        }
+        _ => packets_collection.packets.clone(),
+    };
+    let raw_packets = packets_collection.get_raw_packets(&packets);
+    drop(packets_collection);
+
+    if sort_by_timestamp.unwrap_or(false) {
+        // Stable, so same-timestamp packets keep their arrival order
+        packets.sort_by_key(|packet| packet.get_timestamp());
+    }
+
+    let export_failed = |e: io::Error| {
+        SniffingError::CaptureExportFailed(format!("Unable to write {}: {}", path, e))
+    };
+
+    let file = File::create(&path).map_err(export_failed)?;
+    let written_packets =
+        write_pcap(BufWriter::new(file), &packets, &raw_packets).map_err(export_failed)?;
+
+    write_bookmarks_file(&path, &written_packets, &state.bookmarks.lock().unwrap())
+        .map_err(export_failed)?;
+    write_description_file(&path, state.description.lock().unwrap().as_deref())
+        .map_err(export_failed)?;
+
+    info!("Exported {} packets in {}", written_packets.len(), path);
+
+    Ok(written_packets.len())
+}
+
+/// Writes the raw data of the packets in order, returning the IDs of the written packets
+pub(crate) fn write_pcap<W: Write>(
+    writer: W,
+    packets: &[Arc<ParsedPacket>],
+    raw_packets: &HashMap<usize, RawPacket>,
+) -> io::Result<Vec<usize>> {
+    let records = packets
+        .iter()
+        .filter_map(|packet| Some((packet, raw_packets.get(&packet.get_id())?)))
+        .collect::<Vec<_>>();
+
+    let link_type = records
+        .first()
+        .map_or(LinkTypes::ETHERNET, |(_, raw_packet)| raw_packet.link_type);
+    let mut pcap_writer = PcapWriter::new(writer, link_type)?;
+
+    let mut written_packets = vec![];
+    for (packet, raw_packet) in records {
+        if raw_packet.link_type != link_type {
+            warn!(
+                "Skipped packet {} with link type {} in a capture with link type {}",
+                packet.get_id(),
+                raw_packet.link_type,
+                link_type
+            );
+            continue;
+        }
+
+        pcap_writer.write_record(packet.get_timestamp(), &raw_packet.data)?;
+        written_packets.push(packet.get_id());
+    }
+
+    pcap_writer.into_inner().flush()?;
+
+    Ok(written_packets)
+}
+
+fn is_supported_link_type(link_type: u32) -> bool {
+    matches!(
+        link_type,
+        LinkTypes::ETHERNET
+            | LinkTypes::IEEE802_11
+            | LinkTypes::IEEE802_11_RADIOTAP
+            | LinkTypes::NULL
+            | LinkTypes::LOOP
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::io::Write;
+    use std::path::MAIN_SEPARATOR;
+    use std::sync::Arc;
+
+    use chrono::Local;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use sniffer_parser::serializable_packet::util::{contains_loopback, get_source_ip};
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::filtering::PacketsCollection;
+    use crate::{store_packet, SniffingError, SniffingState};
+
+    use super::{
+        get_capture_stream, import_pcap_internal, load_pcap_dir_internal, parse_frame,
+        restore_loopback_header, write_pcap, LinkTypes, PcapReader, PcapWriter,
+    };
+
+    const LITTLE_ENDIAN_MICROSECONDS: [u8; 44] = [
+        // Global header
+        0xd4, 0xc3, 0xb2, 0xa1, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0xff, 0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        // Record header: 1600000000 s, 123456 us, 4 captured bytes, 60 original bytes
+        0x00, 0x10, 0x5e, 0x5f, 0x40, 0xe2, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00,
+        0x00, // Data
+        0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    const BIG_ENDIAN_MICROSECONDS: [u8; 44] = [
+        // Global header
+        0xa1, 0xb2, 0xc3, 0xd4, 0x00, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+        // Record header: 1600000000 s, 123456 us, 4 captured bytes, 60 original bytes
+        0x5f, 0x5e, 0x10, 0x00, 0x00, 0x01, 0xe2, 0x40, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+        0x3c, // Data
+        0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    const LITTLE_ENDIAN_NANOSECONDS: [u8; 44] = [
+        // Global header
+        0x4d, 0x3c, 0xb2, 0xa1, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0xff, 0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        // Record header: 1600000000 s, 123456789 ns, 4 captured bytes, 60 original bytes
+        0x00, 0x10, 0x5e, 0x5f, 0x15, 0xcd, 0x5b, 0x07, 0x04, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00,
+        0x00, // Data
+        0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    const BIG_ENDIAN_NANOSECONDS: [u8; 44] = [
+        // Global header
+        0xa1, 0xb2, 0x3c, 0x4d, 0x00, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+        // Record header: 1600000000 s, 123456789 ns, 4 captured bytes, 60 original bytes
+        0x5f, 0x5e, 0x10, 0x00, 0x07, 0x5b, 0xcd, 0x15, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+        0x3c, // Data
+        0xde, 0xad, 0xbe, 0xef,
+    ];
+
+    fn assert_single_record(capture: &[u8], expected_timestamp: i64) {
+        let mut pcap_reader = PcapReader::new(capture).unwrap();
+
+        assert_eq!(pcap_reader.version, (2, 4));
+        assert_eq!(pcap_reader.snapshot_length, 65535);
+        assert_eq!(pcap_reader.link_type, 1);
+
+        let record = pcap_reader.next_record().unwrap().unwrap();
+        assert_eq!(record.timestamp, expected_timestamp);
+        assert_eq!(record.original_length, 60);
+        assert_eq!(record.data, vec![0xde, 0xad, 0xbe, 0xef]);
+
+        assert!(pcap_reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn little_endian_pcap() {
+        assert_single_record(&LITTLE_ENDIAN_MICROSECONDS, 1_600_000_000_123_456);
+    }
+
+    #[test]
+    fn big_endian_pcap() {
+        assert_single_record(&BIG_ENDIAN_MICROSECONDS, 1_600_000_000_123_456);
+    }
+
+    #[test]
+    fn nanoseconds_pcap() {
+        assert_single_record(&LITTLE_ENDIAN_NANOSECONDS, 1_600_000_000_123_456);
+        assert_single_record(&BIG_ENDIAN_NANOSECONDS, 1_600_000_000_123_456);
+    }
+
+    #[test]
+    fn written_pcap_is_read_back() {
+        let mut pcap_writer = PcapWriter::new(vec![], LinkTypes::IEEE802_11_RADIOTAP).unwrap();
+        pcap_writer
+            .write_record(1_600_000_000_123_456, &[0xde, 0xad])
+            .unwrap();
+        pcap_writer
+            .write_record(1_600_000_001_000_000, &[0xbe, 0xef, 0x00])
+            .unwrap();
+        let capture = pcap_writer.into_inner();
+
+        let mut pcap_reader = PcapReader::new(capture.as_slice()).unwrap();
+        assert_eq!(pcap_reader.version, (2, 4));
+        assert_eq!(pcap_reader.link_type, LinkTypes::IEEE802_11_RADIOTAP);
+
+        let record = pcap_reader.next_record().unwrap().unwrap();
+        assert_eq!(record.timestamp, 1_600_000_000_123_456);
+        assert_eq!(record.data, vec![0xde, 0xad]);
+
+        let record = pcap_reader.next_record().unwrap().unwrap();
+        assert_eq!(record.timestamp, 1_600_000_001_000_000);
+        assert_eq!(record.original_length, 3);
+
+        assert!(pcap_reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn pcap_sorted_by_timestamp() {
+        let mut packets_collection = PacketsCollection::new();
+        for (id, timestamp) in [(0, 300), (1, 100), (2, 300), (3, 200)] {
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &[id as u8; 14]);
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+        // Captured on a wireless interface
+        let mut parsed_packet = ParsedPacket::new(4);
+        parsed_packet.set_timestamp(400);
+        packets_collection.add_raw_packet(4, LinkTypes::IEEE802_11, &[4; 24]);
+        packets_collection.insert(Arc::new(parsed_packet));
+
+        let mut packets = packets_collection.packets.clone();
+        packets.sort_by_key(|packet| packet.get_timestamp());
+
+        let mut capture = vec![];
+        let written_packets =
+            write_pcap(&mut capture, &packets, &packets_collection.raw_packets).unwrap();
+        assert_eq!(written_packets, vec![1, 3, 0, 2]);
+
+        let mut pcap_reader = PcapReader::new(capture.as_slice()).unwrap();
+        assert_eq!(pcap_reader.link_type, LinkTypes::ETHERNET);
+
+        let mut records = vec![];
+        while let Some(record) = pcap_reader.next_record().unwrap() {
+            records.push((record.timestamp, record.data[0]));
+        }
+        // Packets 0 and 2 share the timestamp and keep their arrival order
+        assert_eq!(records, vec![(100, 1), (200, 3), (300, 0), (300, 2)]);
+    }
+
+    #[test]
+    fn directory_merged_by_timestamp() {
+        let dir = temp_dir().join("wirefish_capture_dir_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+
+        for (name, records) in [
+            ("b.pcap", [(200, 2), (300, 4)]),
+            ("a.pcap", [(100, 1), (300, 3)]),
+        ] {
+            let mut pcap_writer = PcapWriter::new(vec![], LinkTypes::ETHERNET).unwrap();
+            for (timestamp, byte) in records {
+                pcap_writer.write_record(timestamp, &[byte; 60]).unwrap();
+            }
+            fs::write(dir.join(name), pcap_writer.into_inner()).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "Rotated every hour").unwrap();
+
+        let state = SniffingState::new();
+        let load = load_pcap_dir_internal(&state, dir.to_str().unwrap()).unwrap();
+        assert_eq!(
+            load.files
+                .iter()
+                .map(|file| file.rsplit(MAIN_SEPARATOR).next().unwrap())
+                .collect::<Vec<&str>>(),
+            vec!["a.pcap", "b.pcap"]
+        );
+        assert_eq!(load.skipped.len(), 1);
+        assert_eq!(load.packets, 4);
+
+        // IDs follow the timestamps, ties in filename order
+        let packets_collection = state.packets.lock().unwrap();
+        let packets = packets_collection
+            .packets
+            .iter()
+            .map(|packet| {
+                (
+                    packet.get_id(),
+                    packet.get_timestamp(),
+                    packets_collection.raw_packets[&packet.get_id()].data[0],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            packets,
+            vec![(0, 100, 1), (1, 200, 2), (2, 300, 3), (3, 300, 4)]
+        );
+        drop(packets_collection);
+
+        fs::remove_file(dir.join("a.pcap")).unwrap();
+        fs::remove_file(dir.join("b.pcap")).unwrap();
+        assert!(load_pcap_dir_internal(&state, dir.to_str().unwrap()).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pcap_imported_if_ethernet() {
+        let path = temp_dir().join("wirefish_import_test.pcap");
+        let path = path.to_str().unwrap();
+        let state = SniffingState::new();
+        state.info.lock().unwrap().counter = 7;
+
+        let mut pcap_writer = PcapWriter::new(vec![], LinkTypes::ETHERNET).unwrap();
+        pcap_writer.write_record(100, &[1; 60]).unwrap();
+        pcap_writer.write_record(200, &[2; 60]).unwrap();
+        fs::write(path, pcap_writer.into_inner()).unwrap();
+
+        let progress = std::cell::Cell::new(0);
+        let imported = import_pcap_internal(&state, path, &|| progress.set(progress.get() + 1));
+        assert_eq!(imported.unwrap(), 2);
+        assert_eq!(progress.get(), 1);
+        assert_eq!(state.info.lock().unwrap().counter, 2);
+        assert_eq!(
+            state
+                .packets
+                .lock()
+                .unwrap()
+                .packets
+                .iter()
+                .map(|packet| packet.get_id())
+                .collect::<Vec<usize>>(),
+            vec![0, 1]
+        );
+
+        let pcap_writer = PcapWriter::new(vec![], LinkTypes::IEEE802_11_RADIOTAP).unwrap();
+        fs::write(path, pcap_writer.into_inner()).unwrap();
+        assert!(matches!(
+            import_pcap_internal(&state, path, &|| ()),
+            Err(SniffingError::UnsupportedLinkType(_))
+        ));
+
+        state
+            .packets
+            .lock()
+            .unwrap()
+            .capture_intervals
+            .push((0, None));
+        assert!(matches!(
+            import_pcap_internal(&state, path, &|| ()),
+            Err(SniffingError::LoadCaptureFileWhileSniffing(_))
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn invalid_pcap() {
+        assert!(PcapReader::new([0u8; 24].as_slice()).is_err());
+        assert!(PcapReader::new(&LITTLE_ENDIAN_MICROSECONDS[..10]).is_err());
+
+        let mut pcap_reader = PcapReader::new(&LITTLE_ENDIAN_MICROSECONDS[..42]).unwrap();
+        assert!(pcap_reader.next_record().is_err());
+
+        let mut pcap_reader = PcapReader::new(&LITTLE_ENDIAN_MICROSECONDS[..30]).unwrap();
+        assert!(pcap_reader.next_record().is_err());
+    }
+
+    #[test]
+    fn gzip_pcap() {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&LITTLE_ENDIAN_MICROSECONDS).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let stream = get_capture_stream(compressed.as_slice(), false).unwrap();
+        let mut pcap_reader = PcapReader::new(stream).unwrap();
+        assert_eq!(pcap_reader.link_type, 1);
+        assert!(pcap_reader.next_record().unwrap().is_some());
+        assert!(pcap_reader.next_record().unwrap().is_none());
+
+        // Uncompressed files are read as they are
+        let stream = get_capture_stream(LITTLE_ENDIAN_MICROSECONDS.as_slice(), false).unwrap();
+        assert!(PcapReader::new(stream).is_ok());
+
+        // Truncated before the trailer
+        let truncated = &compressed[..compressed.len() - 4];
+        let mut pcap_reader =
+            PcapReader::new(get_capture_stream(truncated, false).unwrap()).unwrap();
+        assert!(pcap_reader.next_record().unwrap().is_some());
+        assert!(pcap_reader.next_record().is_err());
+
+        // Not compressed, despite the extension
+        let stream = get_capture_stream(LITTLE_ENDIAN_MICROSECONDS.as_slice(), true).unwrap();
+        assert!(PcapReader::new(stream).is_err());
+    }
+
+    #[test]
+    fn truncated_and_jumbo_frames_accounting() {
+        let mut packets_collection = PacketsCollection::new();
+        let mut exchanged_packets = HashMap::new();
+
+        // Truncated frame, and jumbo frame with an unknown EtherType
+        let mut jumbo_frame = vec![0xaa; 9000];
+        jumbo_frame[12..14].copy_from_slice(&[0x88, 0xb5]);
+
+        for (id, frame) in [vec![0xaa; 6], jumbo_frame].iter().enumerate() {
+            let parsed_packet = parse_frame(LinkTypes::ETHERNET, frame, id).unwrap();
+            store_packet(
+                &mut packets_collection,
+                &mut exchanged_packets,
+                parsed_packet,
+                LinkTypes::ETHERNET,
+                frame,
+                Local::now(),
+            );
+        }
+
+        assert_eq!(packets_collection.malformed_packets.len(), 1);
+        assert_eq!(packets_collection.captured_bytes, 9006);
+        assert_eq!(
+            exchanged_packets
+                .values()
+                .map(|exchange| exchange.transmitted_bytes)
+                .sum::<usize>(),
+            9006
+        );
+    }
+
+    #[test]
+    fn restored_loopback_frame() {
+        // Zeroed Ethernet header followed by an IPv4 header, as delivered by BPF channels
+        let mut frame = vec![0; 14];
+        frame.extend_from_slice(&[
+            0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x40, 0x00, 0x40, 0x06, 0x00, 0x00, 0x7f, 0x00,
+            0x00, 0x01, 0x7f, 0x00, 0x00, 0x01,
+        ]);
+
+        let restored = restore_loopback_header(&frame);
+        assert_eq!(restored.len(), 24);
+        assert_eq!(u32::from_ne_bytes(restored[..4].try_into().unwrap()), 2);
+
+        let parsed_packet = parse_frame(LinkTypes::NULL, &restored, 0).unwrap();
+        assert!(contains_loopback(&parsed_packet));
+        assert_eq!(get_source_ip(&parsed_packet), Some("127.0.0.1".to_owned()));
+    }
+}
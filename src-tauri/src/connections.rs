@@ -0,0 +1,846 @@
+//! Tracking of the TCP connections state, derived from the flags of the observed segments
+//!
+//! States, as seen from the capture point:
+//! - SYN_SENT: the client sent a SYN
+//! - SYN_RECEIVED: the server answered with a SYN/ACK
+//! - ESTABLISHED: the client acknowledged the SYN/ACK, or the connection was picked up mid-stream
+//! - FIN_WAIT: one endpoint sent a FIN
+//! - CLOSED: both endpoints sent a FIN and the last one was acknowledged
+//! - RESET: one endpoint sent a RST
+//!
+//! Connections are moved out of the active ones once CLOSED or RESET, and only the most recent
+//! closed connections are kept, to bound memory on long captures
+//!
+//! Resets are told apart by the state of the connection they terminate: `refused` during the handshake,
+//! `after_fin` once an endpoint closed its side (a common way to skip TIME_WAIT) and `abrupt` in the middle
+//! of a transfer, the mark of a crashed application or of an interfering middlebox.
+//!
+//! Failed handshakes are the connections opened by a SYN that never reached ESTABLISHED: `no_response`
+//! while still waiting for the SYN/ACK (or for its ACK), `refused` by a RST of the server answering the SYN
+//! and `reset_during_handshake` by any other RST before the handshake completed.
+//!
+//! Keep-alives are the ACK segments carrying at most one byte, sent with the sequence number preceding
+//! the next one expected from the endpoint (SEG.SEQ = SND.NXT - 1). They don't count as data, so an
+//! active connection exchanging only keep-alives is reported as idle, but still alive
+
+use std::collections::{HashMap, VecDeque};
+
+use pnet::packet::tcp::TcpFlags;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::transport::SerializableTcpPacket;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod ConnectionStates {
+    pub const SYN_SENT: &str = "SYN_SENT";
+    pub const SYN_RECEIVED: &str = "SYN_RECEIVED";
+    pub const ESTABLISHED: &str = "ESTABLISHED";
+    pub const FIN_WAIT: &str = "FIN_WAIT";
+    pub const CLOSED: &str = "CLOSED";
+    pub const RESET: &str = "RESET";
+}
+
+#[allow(non_snake_case)]
+pub mod ResetKinds {
+    pub const REFUSED: &str = "refused";
+    pub const AFTER_FIN: &str = "after_fin";
+    pub const ABRUPT: &str = "abrupt";
+}
+
+#[allow(non_snake_case)]
+pub mod HandshakeFailures {
+    pub const NO_RESPONSE: &str = "no_response";
+    pub const REFUSED: &str = "refused";
+    pub const RESET_DURING_HANDSHAKE: &str = "reset_during_handshake";
+}
+
+/// Maximum number of closed connections kept after being moved out of the active ones
+const MAX_CLOSED_CONNECTIONS: usize = 4096;
+
+/// Change of state of a connection, caused by a packet
+#[derive(Serialize, Debug, Clone)]
+pub struct ConnectionTransition {
+    pub state: String,
+    pub packet_id: usize,
+    pub timestamp: i64,
+}
+
+/// TCP connection, identified by the endpoint that sent the first observed packet (the client)
+#[derive(Serialize, Debug, Clone)]
+pub struct TcpConnection {
+    pub id: usize,
+    pub client_ip: String,
+    pub client_port: String,
+    pub server_ip: String,
+    pub server_port: String,
+    pub state: String,
+    pub transitions: Vec<ConnectionTransition>,
+    pub packets: usize,
+    /// Whether the three-way handshake was observed
+    pub handshake_observed: bool,
+    /// Identifiers of the keep-alive segments
+    pub keep_alive_packets: Vec<usize>,
+    /// Timestamp of the last segment carrying data, if any
+    pub last_data_timestamp: Option<i64>,
+    pub last_timestamp: i64,
+    /// Payload bytes sent by each endpoint, keep-alives excluded
+    pub client_bytes: usize,
+    pub server_bytes: usize,
+    /// Whether the client sent the RST terminating the connection, if reset
+    #[serde(skip)]
+    reset_by_client: Option<bool>,
+    /// Endpoints that sent a FIN, client first
+    #[serde(skip)]
+    fin_sent: (bool, bool),
+    /// Sequence number expected next from each endpoint, client first
+    #[serde(skip)]
+    next_sequence: (Option<u32>, Option<u32>),
+}
+
+/// TCP RST, with the connection it terminated
+#[derive(Serialize, Debug, Clone)]
+pub struct ConnectionReset {
+    pub connection_id: usize,
+    pub client_ip: String,
+    pub client_port: String,
+    pub server_ip: String,
+    pub server_port: String,
+    pub packet_id: usize,
+    pub timestamp: i64,
+    pub from_client: bool,
+    /// State of the connection before the RST
+    pub previous_state: String,
+    pub kind: String,
+    /// Payload bytes sent by each endpoint before the RST
+    pub client_bytes: usize,
+    pub server_bytes: usize,
+}
+
+/// Connection attempt that never reached ESTABLISHED
+#[derive(Serialize, Debug, Clone)]
+pub struct FailedHandshake {
+    pub connection_id: usize,
+    pub client_ip: String,
+    pub client_port: String,
+    pub server_ip: String,
+    pub server_port: String,
+    pub reason: String,
+    /// Timestamp of the first SYN
+    pub timestamp: i64,
+    /// Time from the first SYN to the last segment of the attempt (retransmissions or RST), in microseconds
+    pub waited: i64,
+    pub packets: usize,
+}
+
+type Endpoint = (String, String);
+type ConnectionKey = (Endpoint, Endpoint);
+
+/// State of the active TCP connections and the most recently closed ones
+#[derive(Debug, Default)]
+pub struct ConnectionTracker {
+    active: HashMap<ConnectionKey, TcpConnection>,
+    closed: VecDeque<TcpConnection>,
+    /// Number of closed connections kept for each key, to ignore their late segments
+    closed_keys: HashMap<ConnectionKey, usize>,
+    next_id: usize,
+    /// Timestamp of the last TCP segment seen
+    last_timestamp: i64,
+}
+
+/// Returns all the tracked TCP connections, active and recently closed, in order of appearance
+#[tauri::command]
+pub fn get_connections(
+    state: tauri::State<SniffingState>,
+) -> Result<Vec<TcpConnection>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+
+    Ok(packets_collection.connections.get_connections())
+}
+
+/// Returns the active TCP connections that carried no data for more than `idle_secs` seconds, measured
+/// up to the last captured segment. Keep-alives don't count as data
+#[tauri::command]
+pub fn get_idle_connections(
+    state: tauri::State<SniffingState>,
+    idle_secs: u64,
+) -> Result<Vec<TcpConnection>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+
+    Ok(packets_collection
+        .connections
+        .get_idle_connections(idle_secs))
+}
+
+/// Returns the RSTs terminating the tracked TCP connections, in order of appearance of the connections
+#[tauri::command]
+pub fn get_resets(
+    state: tauri::State<SniffingState>,
+) -> Result<Vec<ConnectionReset>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+
+    Ok(packets_collection.connections.get_resets())
+}
+
+/// Returns the TCP connections whose handshake failed, in order of appearance
+#[tauri::command]
+pub fn get_failed_handshakes(
+    state: tauri::State<SniffingState>,
+) -> Result<Vec<FailedHandshake>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+
+    Ok(packets_collection.connections.get_failed_handshakes())
+}
+
+impl ConnectionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_connections(&self) -> Vec<TcpConnection> {
+        let mut connections: Vec<TcpConnection> = self
+            .closed
+            .iter()
+            .chain(self.active.values())
+            .cloned()
+            .collect();
+        connections.sort_by_key(|connection| connection.id);
+
+        connections
+    }
+
+    /// Active connections without data since more than `idle_secs` seconds, in order of appearance
+    pub fn get_idle_connections(&self, idle_secs: u64) -> Vec<TcpConnection> {
+        let idle_micros = (idle_secs as i64).saturating_mul(1_000_000);
+
+        let mut connections: Vec<TcpConnection> = self
+            .active
+            .values()
+            .filter(|connection| {
+                let last_data = connection
+                    .last_data_timestamp
+                    .unwrap_or(connection.transitions[0].timestamp);
+                self.last_timestamp - last_data > idle_micros
+            })
+            .cloned()
+            .collect();
+        connections.sort_by_key(|connection| connection.id);
+
+        connections
+    }
+
+    /// Resets of the connections, active ones excluded since a RST closes them
+    pub fn get_resets(&self) -> Vec<ConnectionReset> {
+        let mut resets: Vec<ConnectionReset> = self
+            .closed
+            .iter()
+            .filter_map(|connection| {
+                let from_client = connection.reset_by_client?;
+                let (previous, reset) = match connection.transitions.as_slice() {
+                    [.., previous, reset] => (previous, reset),
+                    _ => return None,
+                };
+
+                let kind = match previous.state.as_str() {
+                    ConnectionStates::SYN_SENT | ConnectionStates::SYN_RECEIVED => {
+                        ResetKinds::REFUSED
+                    }
+                    ConnectionStates::FIN_WAIT => ResetKinds::AFTER_FIN,
+                    _ => ResetKinds::ABRUPT,
+                };
+
+                Some(ConnectionReset {
+                    connection_id: connection.id,
+                    client_ip: connection.client_ip.clone(),
+                    client_port: connection.client_port.clone(),
+                    server_ip: connection.server_ip.clone(),
+                    server_port: connection.server_port.clone(),
+                    packet_id: reset.packet_id,
+                    timestamp: reset.timestamp,
+                    from_client,
+                    previous_state: previous.state.clone(),
+                    kind: kind.to_owned(),
+                    client_bytes: connection.client_bytes,
+                    server_bytes: connection.server_bytes,
+                })
+            })
+            .collect();
+        resets.sort_by_key(|reset| reset.connection_id);
+
+        resets
+    }
+
+    /// Connections opened by a SYN, active or closed, that never reached ESTABLISHED
+    pub fn get_failed_handshakes(&self) -> Vec<FailedHandshake> {
+        self.get_connections()
+            .into_iter()
+            .filter(|connection| connection.handshake_observed)
+            .filter_map(|connection| {
+                let reason = match connection.state.as_str() {
+                    ConnectionStates::SYN_SENT | ConnectionStates::SYN_RECEIVED => {
+                        HandshakeFailures::NO_RESPONSE
+                    }
+                    ConnectionStates::RESET => {
+                        let previous = match connection.transitions.as_slice() {
+                            [.., previous, _] => previous,
+                            _ => return None,
+                        };
+                        match previous.state.as_str() {
+                            ConnectionStates::SYN_SENT
+                                if connection.reset_by_client == Some(false) =>
+                            {
+                                HandshakeFailures::REFUSED
+                            }
+                            ConnectionStates::SYN_SENT | ConnectionStates::SYN_RECEIVED => {
+                                HandshakeFailures::RESET_DURING_HANDSHAKE
+                            }
+                            _ => return None,
+                        }
+                    }
+                    _ => return None,
+                };
+
+                let timestamp = connection.transitions[0].timestamp;
+                Some(FailedHandshake {
+                    connection_id: connection.id,
+                    reason: reason.to_owned(),
+                    timestamp,
+                    waited: connection.last_timestamp - timestamp,
+                    packets: connection.packets,
+                    client_ip: connection.client_ip,
+                    client_port: connection.client_port,
+                    server_ip: connection.server_ip,
+                    server_port: connection.server_port,
+                })
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Updates the state of the connection the packet belongs to, if it's a TCP segment
+    pub fn update(&mut self, packet: &ParsedPacket) {
+        let tcp_packet = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => return,
+        };
+        let flags = tcp_packet.flags;
+
+        let (source, destination) = match (
+            get_source_ip(packet).zip(get_source_port(packet)),
+            get_dest_ip(packet).zip(get_dest_port(packet)),
+        ) {
+            (Some(source), Some(destination)) => (source, destination),
+            _ => return,
+        };
+
+        let key = if source <= destination {
+            (source.clone(), destination.clone())
+        } else {
+            (destination.clone(), source.clone())
+        };
+
+        let is_syn = flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK == 0;
+
+        if !self.active.contains_key(&key) {
+            // Late segments of a closed connection, e.g. retransmitted FINs
+            if !is_syn && self.closed_keys.contains_key(&key) {
+                return;
+            }
+
+            let (state, handshake_observed) = if is_syn {
+                (ConnectionStates::SYN_SENT, true)
+            } else {
+                (ConnectionStates::ESTABLISHED, false)
+            };
+
+            let connection = TcpConnection {
+                id: self.next_id,
+                client_ip: source.0.clone(),
+                client_port: source.1.clone(),
+                server_ip: destination.0.clone(),
+                server_port: destination.1.clone(),
+                state: state.to_owned(),
+                transitions: vec![ConnectionTransition {
+                    state: state.to_owned(),
+                    packet_id: packet.get_id(),
+                    timestamp: packet.get_timestamp(),
+                }],
+                packets: 0,
+                handshake_observed,
+                keep_alive_packets: vec![],
+                last_data_timestamp: None,
+                last_timestamp: packet.get_timestamp(),
+                client_bytes: 0,
+                server_bytes: 0,
+                reset_by_client: None,
+                fin_sent: (false, false),
+                next_sequence: (None, None),
+            };
+            self.next_id += 1;
+            self.active.insert(key.clone(), connection);
+        }
+
+        self.last_timestamp = self.last_timestamp.max(packet.get_timestamp());
+
+        let connection = self.active.get_mut(&key).unwrap();
+        connection.packets += 1;
+        connection.last_timestamp = packet.get_timestamp();
+
+        let from_client = source.0 == connection.client_ip && source.1 == connection.client_port;
+
+        if update_sequence(connection, tcp_packet, from_client) {
+            connection.keep_alive_packets.push(packet.get_id());
+        } else if tcp_packet.length > 0 {
+            connection.last_data_timestamp = Some(packet.get_timestamp());
+            let sent_bytes = if from_client {
+                &mut connection.client_bytes
+            } else {
+                &mut connection.server_bytes
+            };
+            // The payload of a RST is a diagnostic, not data exchanged before it
+            if flags & TcpFlags::RST == 0 {
+                *sent_bytes += tcp_packet.length;
+            }
+        }
+        let next_state = get_next_state(connection, flags, from_client);
+
+        if let Some(next_state) = next_state {
+            if next_state != connection.state {
+                if next_state == ConnectionStates::RESET {
+                    connection.reset_by_client = Some(from_client);
+                }
+                connection.state = next_state.to_owned();
+                connection.transitions.push(ConnectionTransition {
+                    state: next_state.to_owned(),
+                    packet_id: packet.get_id(),
+                    timestamp: packet.get_timestamp(),
+                });
+            }
+        }
+
+        if connection.state == ConnectionStates::CLOSED
+            || connection.state == ConnectionStates::RESET
+        {
+            let connection = self.active.remove(&key).unwrap();
+            self.close(key, connection);
+        }
+    }
+
+    fn close(&mut self, key: ConnectionKey, connection: TcpConnection) {
+        *self.closed_keys.entry(key).or_insert(0) += 1;
+        self.closed.push_back(connection);
+
+        while self.closed.len() > MAX_CLOSED_CONNECTIONS {
+            let evicted = self.closed.pop_front().unwrap();
+            let evicted_key = {
+                let client = (evicted.client_ip, evicted.client_port);
+                let server = (evicted.server_ip, evicted.server_port);
+                if client <= server {
+                    (client, server)
+                } else {
+                    (server, client)
+                }
+            };
+
+            if let Some(count) = self.closed_keys.get_mut(&evicted_key) {
+                *count -= 1;
+                if *count == 0 {
+                    self.closed_keys.remove(&evicted_key);
+                }
+            }
+        }
+    }
+}
+
+/// Advances the sequence number expected from the sender of the segment, returning whether the segment
+/// is a keep-alive
+fn update_sequence(
+    connection: &mut TcpConnection,
+    tcp_packet: &SerializableTcpPacket,
+    from_client: bool,
+) -> bool {
+    let next_sequence = if from_client {
+        &mut connection.next_sequence.0
+    } else {
+        &mut connection.next_sequence.1
+    };
+
+    let flags = tcp_packet.flags;
+    let is_plain_ack =
+        flags & TcpFlags::ACK != 0 && flags & (TcpFlags::SYN | TcpFlags::FIN | TcpFlags::RST) == 0;
+
+    if let Some(expected) = *next_sequence {
+        if is_plain_ack && tcp_packet.length <= 1 && tcp_packet.sequence == expected.wrapping_sub(1)
+        {
+            return true;
+        }
+    }
+
+    // SYN and FIN take one sequence number each
+    let control = (flags & (TcpFlags::SYN | TcpFlags::FIN) != 0) as u32;
+    let end = tcp_packet
+        .sequence
+        .wrapping_add(tcp_packet.length as u32)
+        .wrapping_add(control);
+
+    // Retransmissions and out of order segments don't move it back
+    match *next_sequence {
+        Some(expected) if (end.wrapping_sub(expected) as i32) <= 0 => (),
+        _ => *next_sequence = Some(end),
+    }
+
+    false
+}
+
+/// State reached by a connection after a segment, `None` if unchanged
+fn get_next_state(
+    connection: &mut TcpConnection,
+    flags: u16,
+    from_client: bool,
+) -> Option<&'static str> {
+    if flags & TcpFlags::RST != 0 {
+        return Some(ConnectionStates::RESET);
+    }
+
+    if flags & TcpFlags::FIN != 0 {
+        if from_client {
+            connection.fin_sent.0 = true;
+        } else {
+            connection.fin_sent.1 = true;
+        }
+        return Some(ConnectionStates::FIN_WAIT);
+    }
+
+    let is_ack = flags & TcpFlags::ACK != 0;
+    match connection.state.as_str() {
+        ConnectionStates::SYN_SENT if !from_client && flags & TcpFlags::SYN != 0 && is_ack => {
+            Some(ConnectionStates::SYN_RECEIVED)
+        }
+        ConnectionStates::SYN_RECEIVED if from_client && is_ack => {
+            Some(ConnectionStates::ESTABLISHED)
+        }
+        // The ACK of the last FIN, once both endpoints sent one
+        ConnectionStates::FIN_WAIT if connection.fin_sent == (true, true) && is_ack => {
+            Some(ConnectionStates::CLOSED)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{
+        ConnectionStates, ConnectionTracker, HandshakeFailures, ResetKinds, MAX_CLOSED_CONNECTIONS,
+    };
+
+    fn build_tcp_packet(id: usize, to_server: bool, client_port: u16, flags: u16) -> ParsedPacket {
+        let (client, server) = (Ipv4Addr::new(10, 10, 10, 10), Ipv4Addr::new(11, 11, 11, 11));
+        let template = if to_server {
+            build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                client,
+                server,
+                client_port,
+                80,
+            )
+        } else {
+            build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                server,
+                client,
+                80,
+                client_port,
+            )
+        };
+
+        let mut tcp_packet = match template.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.flags = flags;
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+
+        parsed_packet
+    }
+
+    fn states(tracker: &ConnectionTracker) -> Vec<(String, Vec<String>)> {
+        tracker
+            .get_connections()
+            .into_iter()
+            .map(|connection| {
+                (
+                    connection.state,
+                    connection
+                        .transitions
+                        .into_iter()
+                        .map(|transition| transition.state)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn connection_lifecycle() {
+        let mut tracker = ConnectionTracker::new();
+        let packets = [
+            (true, TcpFlags::SYN),
+            (false, TcpFlags::SYN | TcpFlags::ACK),
+            (true, TcpFlags::ACK),
+            (true, TcpFlags::ACK | TcpFlags::PSH),
+            (true, TcpFlags::FIN | TcpFlags::ACK),
+            (false, TcpFlags::FIN | TcpFlags::ACK),
+            (true, TcpFlags::ACK),
+            // Retransmitted FIN, after the connection was closed
+            (false, TcpFlags::FIN | TcpFlags::ACK),
+        ];
+
+        for (id, (to_server, flags)) in packets.into_iter().enumerate() {
+            tracker.update(&build_tcp_packet(id, to_server, 4444, flags));
+        }
+
+        assert_eq!(
+            states(&tracker),
+            vec![(
+                ConnectionStates::CLOSED.to_owned(),
+                vec![
+                    ConnectionStates::SYN_SENT.to_owned(),
+                    ConnectionStates::SYN_RECEIVED.to_owned(),
+                    ConnectionStates::ESTABLISHED.to_owned(),
+                    ConnectionStates::FIN_WAIT.to_owned(),
+                    ConnectionStates::CLOSED.to_owned(),
+                ]
+            )]
+        );
+
+        let connection = &tracker.get_connections()[0];
+        assert!(connection.handshake_observed);
+        assert_eq!(connection.packets, 7);
+        assert_eq!(connection.transitions[4].packet_id, 6);
+    }
+
+    #[test]
+    fn half_open_and_reset_connections() {
+        let mut tracker = ConnectionTracker::new();
+
+        // Unanswered SYN
+        tracker.update(&build_tcp_packet(0, true, 4444, TcpFlags::SYN));
+        // Reset in the middle of the handshake
+        tracker.update(&build_tcp_packet(1, true, 5555, TcpFlags::SYN));
+        tracker.update(&build_tcp_packet(
+            2,
+            false,
+            5555,
+            TcpFlags::RST | TcpFlags::ACK,
+        ));
+        // Picked up mid-stream
+        tracker.update(&build_tcp_packet(3, false, 6666, TcpFlags::ACK));
+
+        let states = states(&tracker);
+        assert_eq!(states[0].0, ConnectionStates::SYN_SENT);
+        assert_eq!(
+            states[1].1,
+            vec![ConnectionStates::SYN_SENT, ConnectionStates::RESET]
+        );
+        assert_eq!(states[2].0, ConnectionStates::ESTABLISHED);
+        assert!(!tracker.get_connections()[2].handshake_observed);
+    }
+
+    fn build_data_packet(
+        id: usize,
+        to_server: bool,
+        client_port: u16,
+        sequence: u32,
+        length: usize,
+        timestamp: i64,
+    ) -> ParsedPacket {
+        let mut parsed_packet = build_tcp_packet(id, to_server, client_port, TcpFlags::ACK);
+        let mut tcp_packet = match parsed_packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.sequence = sequence;
+        tcp_packet.length = length;
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+        parsed_packet.set_timestamp(timestamp);
+
+        parsed_packet
+    }
+
+    #[test]
+    fn keep_alives_and_idle_connections() {
+        let mut tracker = ConnectionTracker::new();
+        let second = 1_000_000;
+
+        // Quiet connection, kept alive by the client
+        tracker.update(&build_data_packet(0, true, 4444, 1000, 100, 0));
+        tracker.update(&build_data_packet(1, false, 4444, 5000, 0, second));
+        tracker.update(&build_data_packet(2, true, 4444, 1099, 0, 60 * second));
+        tracker.update(&build_data_packet(3, true, 4444, 1099, 1, 120 * second));
+        // Retransmission of the data, not a keep-alive
+        tracker.update(&build_data_packet(4, true, 4444, 1000, 100, 130 * second));
+
+        // Busy connection
+        tracker.update(&build_data_packet(5, true, 5555, 0, 10, 0));
+        tracker.update(&build_data_packet(6, true, 5555, 10, 10, 150 * second));
+
+        let connections = tracker.get_connections();
+        assert_eq!(connections[0].keep_alive_packets, vec![2, 3]);
+        assert_eq!(connections[0].last_timestamp, 130 * second);
+        assert!(connections[1].keep_alive_packets.is_empty());
+
+        let idle = tracker.get_idle_connections(10);
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].client_port, "4444");
+        assert_eq!(idle[0].last_data_timestamp, Some(130 * second));
+        assert!(tracker.get_idle_connections(30).is_empty());
+
+        // Closed connections aren't idle
+        tracker.update(&build_tcp_packet(7, true, 4444, TcpFlags::RST));
+        tracker.update(&build_data_packet(8, true, 5555, 20, 1, 200 * second));
+        assert_eq!(tracker.get_idle_connections(10).len(), 0);
+    }
+
+    #[test]
+    fn resets_classified() {
+        let mut tracker = ConnectionTracker::new();
+
+        // Refused by the server
+        tracker.update(&build_tcp_packet(0, true, 4444, TcpFlags::SYN));
+        tracker.update(&build_tcp_packet(
+            1,
+            false,
+            4444,
+            TcpFlags::RST | TcpFlags::ACK,
+        ));
+        // Aborted by the server in the middle of a transfer
+        tracker.update(&build_data_packet(2, true, 5555, 0, 100, 0));
+        tracker.update(&build_data_packet(3, false, 5555, 0, 1400, 0));
+        tracker.update(&build_tcp_packet(4, false, 5555, TcpFlags::RST));
+        // Reset by the client after closing its side
+        tracker.update(&build_data_packet(5, true, 6666, 0, 10, 0));
+        tracker.update(&build_tcp_packet(
+            6,
+            true,
+            6666,
+            TcpFlags::FIN | TcpFlags::ACK,
+        ));
+        tracker.update(&build_tcp_packet(7, true, 6666, TcpFlags::RST));
+        // Still active
+        tracker.update(&build_data_packet(8, true, 7777, 0, 10, 0));
+
+        let resets = tracker.get_resets();
+        assert_eq!(
+            resets
+                .iter()
+                .map(|reset| (reset.packet_id, reset.from_client, reset.kind.as_str()))
+                .collect::<Vec<(usize, bool, &str)>>(),
+            vec![
+                (1, false, ResetKinds::REFUSED),
+                (4, false, ResetKinds::ABRUPT),
+                (7, true, ResetKinds::AFTER_FIN),
+            ]
+        );
+        assert_eq!(resets[0].previous_state, ConnectionStates::SYN_SENT);
+        assert_eq!(
+            (resets[1].client_bytes, resets[1].server_bytes),
+            (100, 1400)
+        );
+        assert_eq!(resets[2].client_port, "6666");
+    }
+
+    #[test]
+    fn failed_handshakes_found() {
+        let mut tracker = ConnectionTracker::new();
+        let second = 1_000_000;
+        let mut packet = |id, to_server, client_port, flags, timestamp| {
+            let mut parsed_packet = build_tcp_packet(id, to_server, client_port, flags);
+            parsed_packet.set_timestamp(timestamp);
+            tracker.update(&parsed_packet);
+        };
+
+        // Unanswered SYN, retransmitted
+        packet(0, true, 4444, TcpFlags::SYN, 0);
+        packet(1, true, 4444, TcpFlags::SYN, second);
+        packet(2, true, 4444, TcpFlags::SYN, 3 * second);
+        // Refused by the server
+        packet(3, true, 5555, TcpFlags::SYN, 4 * second);
+        packet(
+            4,
+            false,
+            5555,
+            TcpFlags::RST | TcpFlags::ACK,
+            4 * second + 200,
+        );
+        // Reset by the client after the SYN/ACK
+        packet(5, true, 6666, TcpFlags::SYN, 5 * second);
+        packet(
+            6,
+            false,
+            6666,
+            TcpFlags::SYN | TcpFlags::ACK,
+            5 * second + 100,
+        );
+        packet(7, true, 6666, TcpFlags::RST, 5 * second + 300);
+        // Established, then reset
+        packet(8, true, 7777, TcpFlags::SYN, 6 * second);
+        packet(9, false, 7777, TcpFlags::SYN | TcpFlags::ACK, 6 * second);
+        packet(10, true, 7777, TcpFlags::ACK, 6 * second);
+        packet(11, false, 7777, TcpFlags::RST, 7 * second);
+        // Picked up mid-stream
+        packet(12, true, 8888, TcpFlags::RST, 8 * second);
+
+        let failed = tracker.get_failed_handshakes();
+        assert_eq!(
+            failed
+                .iter()
+                .map(|handshake| (
+                    handshake.client_port.as_str(),
+                    handshake.reason.as_str(),
+                    handshake.waited
+                ))
+                .collect::<Vec<(&str, &str, i64)>>(),
+            vec![
+                ("4444", HandshakeFailures::NO_RESPONSE, 3 * second),
+                ("5555", HandshakeFailures::REFUSED, 200),
+                ("6666", HandshakeFailures::RESET_DURING_HANDSHAKE, 300),
+            ]
+        );
+        assert_eq!(failed[0].packets, 3);
+        assert_eq!(failed[1].timestamp, 4 * second);
+    }
+
+    #[test]
+    fn closed_connections_aged_out() {
+        let mut tracker = ConnectionTracker::new();
+
+        for port in 0..(MAX_CLOSED_CONNECTIONS + 10) {
+            tracker.update(&build_tcp_packet(port, true, port as u16, TcpFlags::RST));
+        }
+
+        let connections = tracker.get_connections();
+        assert_eq!(connections.len(), MAX_CLOSED_CONNECTIONS);
+        assert_eq!(connections[0].id, 10);
+        assert_eq!(tracker.closed_keys.len(), MAX_CLOSED_CONNECTIONS);
+    }
+}
@@ -0,0 +1,73 @@
+//! Automatic colors of the conversations, so that interleaved flows are told apart in the packet list
+//!
+//! Each flow gets the color of the palette indexed by its hash, so the color of a conversation is the same
+//! in both directions, across requests and across runs. Packets without a flow hash (e.g. ARP) have none.
+
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+/// Background colors, distinguishable from each other and readable with dark text
+pub const CONVERSATION_PALETTE: [&str; 16] = [
+    "#fde2e4", "#e2ece9", "#dfe7fd", "#fff1c1", "#e8dff5", "#d4f0f0", "#fce1e4", "#e2f0cb",
+    "#ffdfba", "#cde4f7", "#f1e3d3", "#d9f2d0", "#f6d5f7", "#fbe7c6", "#d7e3fc", "#e4f1ee",
+];
+
+/// Returns the colors of the palette, by index
+#[tauri::command]
+pub fn get_conversation_palette() -> Vec<&'static str> {
+    CONVERSATION_PALETTE.to_vec()
+}
+
+/// Index in the palette of the color of the flow with hash `flow_hash`
+fn get_color_index(flow_hash: &str) -> Option<usize> {
+    u64::from_str_radix(flow_hash, 16)
+        .ok()
+        .map(|hash| (hash % CONVERSATION_PALETTE.len() as u64) as usize)
+}
+
+/// Tag each packet with the color of its conversation
+pub fn tag_conversation_colors(packets: &mut Vec<ParsedPacket>) {
+    for packet in packets {
+        let color = packet
+            .get_flow_hash()
+            .and_then(|flow_hash| get_color_index(flow_hash));
+        packet.set_conversation_color(color);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::util::compute_flow_hash;
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use super::{get_color_index, tag_conversation_colors};
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    #[test]
+    fn stable_colors_of_conversations() {
+        let mac = MacAddr::new(10, 10, 10, 10, 10, 10);
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let mut packets = vec![
+            build_test_parsed_packet(mac, mac, client, server, 50000, 443),
+            build_test_parsed_packet(mac, mac, server, client, 443, 50000),
+            ParsedPacket::new(2),
+        ];
+        for packet in &mut packets {
+            let flow_hash = compute_flow_hash(packet);
+            packet.set_flow_hash(flow_hash);
+        }
+
+        tag_conversation_colors(&mut packets);
+        let color = packets[0].get_conversation_color();
+        assert_eq!(color, get_color_index(packets[0].get_flow_hash().unwrap()));
+        // Both directions of the flow
+        assert!(color.is_some());
+        assert_eq!(packets[1].get_conversation_color(), color);
+        assert_eq!(packets[2].get_conversation_color(), None);
+
+        assert_eq!(get_color_index("0000000000000011"), Some(1));
+        assert_eq!(get_color_index("not a hash"), None);
+    }
+}
@@ -0,0 +1,666 @@
+//! Grouping of the collected packets in conversations
+//!
+//! A conversation gathers the packets exchanged in both directions between two endpoints, identified
+//! by the 5-tuple (transport protocol, IP addresses and ports). Packets without a transport layer
+//! are grouped by their IP addresses only.
+//!
+//! The timeline of a conversation counts its packets and bytes in buckets of active capture time:
+//! the time during which the sniffing processes were paused is skipped, so that a conversation
+//! spanning a pause shows no gap. The entropy timeline of a conversation gives, in the same buckets, the
+//! mean and highest entropy of the payloads.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    contains_tcp, contains_udp, get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::capture_file::PcapWriter;
+use crate::filtering::{apply_all_strong_filters, PacketsCollection};
+use crate::{SniffingError, SniffingState};
+
+/// Endpoint of a conversation, as IP address and port ("-" when not available)
+pub type ConversationEndpoint = (String, String);
+
+/// Packets exchanged between two endpoints
+#[derive(Debug)]
+pub struct Conversation {
+    pub protocol: String,
+    pub endpoints: (ConversationEndpoint, ConversationEndpoint),
+    pub packets: Vec<Arc<ParsedPacket>>,
+    pub bytes: usize,
+}
+
+/// Writes one pcap file in `out_dir` for each conversation among the packets matching the filters,
+/// returning the paths of the written files
+///
+/// When `max_conversations` is provided, only the conversations with the most exchanged bytes are exported
+#[tauri::command]
+pub fn export_conversations<'a>(
+    state: tauri::State<SniffingState>,
+    out_dir: String,
+    filters_value: Vec<(&'a str, &'a str)>,
+    max_conversations: Option<usize>,
+) -> Result<Vec<String>, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+
+    let packets = if filters_value.is_empty() {
+        packets_collection.packets.clone()
+    } else {
+        let end = packets_collection.packets.len();
+        apply_all_strong_filters(end, &filters_value, &mut packets_collection)?
+    };
+
+    let mut conversations = get_conversations(&packets, &packets_collection);
+    conversations.sort_by_key(|conversation| Reverse(conversation.bytes));
+    if let Some(max_conversations) = max_conversations {
+        conversations.truncate(max_conversations);
+    }
+
+    let out_dir = Path::new(&out_dir);
+    fs::create_dir_all(out_dir).map_err(|e| {
+        SniffingError::CaptureExportFailed(format!("Unable to create output directory: {}", e))
+    })?;
+
+    let mut written_files = vec![];
+    for conversation in &conversations {
+        let path = out_dir.join(get_filename(conversation));
+        write_conversation(&path, conversation, &packets_collection).map_err(|e| {
+            SniffingError::CaptureExportFailed(format!("Unable to write {:?}: {}", path, e))
+        })?;
+
+        written_files.push(path.to_string_lossy().to_string());
+    }
+
+    info!(
+        "Exported {} conversations in {:?}",
+        written_files.len(),
+        out_dir
+    );
+
+    Ok(written_files)
+}
+
+/// Groups packets by conversation, in order of first appearance
+pub fn get_conversations(
+    packets: &[Arc<ParsedPacket>],
+    packets_collection: &PacketsCollection,
+) -> Vec<Conversation> {
+    let mut conversations: Vec<Conversation> = vec![];
+    let mut indexes = HashMap::new();
+
+    for packet in packets {
+        let (protocol, endpoints) = match get_conversation_key(packet) {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let index = *indexes
+            .entry((protocol.clone(), endpoints.clone()))
+            .or_insert_with(|| {
+                conversations.push(Conversation {
+                    protocol,
+                    endpoints,
+                    packets: vec![],
+                    bytes: 0,
+                });
+                conversations.len() - 1
+            });
+
+        let conversation = &mut conversations[index];
+        conversation.bytes += packets_collection
+            .raw_packets
+            .get(&packet.get_id())
+            .map_or(0, |raw_packet| raw_packet.data.len());
+        conversation.packets.push(Arc::clone(packet));
+    }
+
+    conversations
+}
+
+/// Packets and bytes of a conversation in a slot of active capture time
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ConversationBucket {
+    /// Active capture time since the first packet of the conversation, in milliseconds
+    pub offset: u64,
+    pub packets: usize,
+    pub bytes: usize,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ConversationTimeline {
+    pub bucket_ms: u64,
+    /// Timestamp of the first packet of the conversation, in microseconds since the Unix epoch
+    pub first_timestamp: Option<i64>,
+    /// Consecutive buckets, from the first to the last packet of the conversation
+    pub buckets: Vec<ConversationBucket>,
+}
+
+/// Entropy of the payloads of a conversation in a slot of active capture time
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct EntropyBucket {
+    /// Active capture time since the first packet of the conversation, in milliseconds
+    pub offset: u64,
+    /// Packets with a payload
+    pub packets: usize,
+    /// Mean and highest entropy of the payloads, in bits per byte
+    pub mean_entropy: Option<f64>,
+    pub max_entropy: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct EntropyTimeline {
+    pub bucket_ms: u64,
+    /// Timestamp of the first packet of the conversation, in microseconds since the Unix epoch
+    pub first_timestamp: Option<i64>,
+    /// Consecutive buckets, from the first to the last packet of the conversation
+    pub buckets: Vec<EntropyBucket>,
+}
+
+/// Returns the packets and bytes exchanged over time in a single conversation, in buckets of `bucket_ms`
+/// milliseconds
+///
+/// `protocol` is either `tcp`, `udp` or `ip`, the ports are omitted for `ip` conversations
+#[tauri::command]
+pub fn get_conversation_timeline(
+    state: tauri::State<SniffingState>,
+    protocol: String,
+    source_ip: String,
+    source_port: Option<u16>,
+    dest_ip: String,
+    dest_port: Option<u16>,
+    bucket_ms: u64,
+) -> Result<ConversationTimeline, SniffingError> {
+    let key = parse_conversation_key(
+        &protocol,
+        &source_ip,
+        source_port,
+        &dest_ip,
+        dest_port,
+        bucket_ms,
+    )?;
+
+    let packets_collection = state.packets.lock().unwrap();
+    let timeline = get_conversation_timeline_internal(&key, bucket_ms, &packets_collection);
+
+    info!(
+        "Timeline of the {} conversation {} - {}: {} buckets",
+        protocol,
+        source_ip,
+        dest_ip,
+        timeline.buckets.len()
+    );
+
+    Ok(timeline)
+}
+
+/// Returns the entropy of the payloads exchanged over time in a single conversation, in buckets of
+/// `bucket_ms` milliseconds
+///
+/// A jump from low to high entropy marks the start of an encrypted transfer (e.g. after a cleartext
+/// handshake), a high entropy all along on a port of a cleartext protocol may hint at tunneled data
+#[tauri::command]
+pub fn get_connection_entropy_timeline(
+    state: tauri::State<SniffingState>,
+    protocol: String,
+    source_ip: String,
+    source_port: Option<u16>,
+    dest_ip: String,
+    dest_port: Option<u16>,
+    bucket_ms: u64,
+) -> Result<EntropyTimeline, SniffingError> {
+    let key = parse_conversation_key(
+        &protocol,
+        &source_ip,
+        source_port,
+        &dest_ip,
+        dest_port,
+        bucket_ms,
+    )?;
+
+    let packets_collection = state.packets.lock().unwrap();
+    let timeline = get_entropy_timeline_internal(&key, bucket_ms, &packets_collection);
+
+    info!(
+        "Entropy timeline of the {} conversation {} - {}: {} buckets",
+        protocol,
+        source_ip,
+        dest_ip,
+        timeline.buckets.len()
+    );
+
+    Ok(timeline)
+}
+
+/// Key of the conversation between the two endpoints, checking the bucket size of its timeline
+fn parse_conversation_key(
+    protocol: &str,
+    source_ip: &str,
+    source_port: Option<u16>,
+    dest_ip: &str,
+    dest_port: Option<u16>,
+    bucket_ms: u64,
+) -> Result<(String, (ConversationEndpoint, ConversationEndpoint)), SniffingError> {
+    if bucket_ms == 0 {
+        return Err(SniffingError::InvalidConfiguration(
+            "The bucket size must be positive".to_owned(),
+        ));
+    }
+
+    let endpoint = |ip: &str, port: Option<u16>| match ip.parse::<IpAddr>() {
+        Ok(ip) => Ok((
+            ip.to_string(),
+            port.map_or(String::from("-"), |port| port.to_string()),
+        )),
+        Err(e) => {
+            warn!("Invalid conversation IP address {}: {}", ip, e);
+            Err(SniffingError::InvalidIpAddress(format!(
+                "Invalid conversation IP address {}: {}",
+                ip, e
+            )))
+        }
+    };
+
+    let source = endpoint(source_ip, source_port)?;
+    let destination = endpoint(dest_ip, dest_port)?;
+    let endpoints = if source <= destination {
+        (source, destination)
+    } else {
+        (destination, source)
+    };
+
+    Ok((protocol.to_lowercase(), endpoints))
+}
+
+fn get_conversation_timeline_internal(
+    key: &(String, (ConversationEndpoint, ConversationEndpoint)),
+    bucket_ms: u64,
+    packets_collection: &PacketsCollection,
+) -> ConversationTimeline {
+    let (first_timestamp, packets) = get_bucketed_packets(key, bucket_ms, packets_collection);
+    let mut timeline = ConversationTimeline {
+        bucket_ms,
+        first_timestamp,
+        ..Default::default()
+    };
+
+    for (index, packet) in packets {
+        while timeline.buckets.len() <= index {
+            timeline.buckets.push(ConversationBucket {
+                offset: timeline.buckets.len() as u64 * bucket_ms,
+                ..Default::default()
+            });
+        }
+
+        let bucket = &mut timeline.buckets[index];
+        bucket.packets += 1;
+        bucket.bytes += packets_collection
+            .raw_packets
+            .get(&packet.get_id())
+            .map_or(0, |raw_packet| raw_packet.data.len());
+    }
+
+    timeline
+}
+
+fn get_entropy_timeline_internal(
+    key: &(String, (ConversationEndpoint, ConversationEndpoint)),
+    bucket_ms: u64,
+    packets_collection: &PacketsCollection,
+) -> EntropyTimeline {
+    let (first_timestamp, packets) = get_bucketed_packets(key, bucket_ms, packets_collection);
+    let mut timeline = EntropyTimeline {
+        bucket_ms,
+        first_timestamp,
+        ..Default::default()
+    };
+
+    // Sum of the entropies of each bucket
+    let mut entropy_sums = vec![];
+    for (index, packet) in packets {
+        while timeline.buckets.len() <= index {
+            timeline.buckets.push(EntropyBucket {
+                offset: timeline.buckets.len() as u64 * bucket_ms,
+                ..Default::default()
+            });
+            entropy_sums.push(0.0);
+        }
+
+        let entropy = match packet.get_payload_entropy() {
+            Some(entropy) => entropy,
+            None => continue,
+        };
+
+        let bucket = &mut timeline.buckets[index];
+        bucket.packets += 1;
+        bucket.max_entropy = Some(bucket.max_entropy.map_or(entropy, |max| max.max(entropy)));
+        entropy_sums[index] += entropy;
+    }
+
+    for (bucket, entropy_sum) in timeline.buckets.iter_mut().zip(entropy_sums) {
+        if bucket.packets > 0 {
+            bucket.mean_entropy = Some(entropy_sum / bucket.packets as f64);
+        }
+    }
+
+    timeline
+}
+
+/// Packets of the conversation along with the index of their bucket of active capture time, and the
+/// timestamp of the first one
+fn get_bucketed_packets<'a>(
+    key: &(String, (ConversationEndpoint, ConversationEndpoint)),
+    bucket_ms: u64,
+    packets_collection: &'a PacketsCollection,
+) -> (Option<i64>, Vec<(usize, &'a ParsedPacket)>) {
+    let bucket_length = bucket_ms.saturating_mul(1000);
+    let mut first = None;
+    let mut packets = vec![];
+
+    for packet in &packets_collection.packets {
+        if get_conversation_key(packet).as_ref() != Some(key) {
+            continue;
+        }
+
+        let active_time = get_active_time(
+            packet.get_timestamp(),
+            &packets_collection.capture_intervals,
+        );
+        let (_, first_active_time) = *first.get_or_insert((packet.get_timestamp(), active_time));
+
+        let index = ((active_time - first_active_time).max(0) as u64 / bucket_length) as usize;
+        packets.push((index, &**packet));
+    }
+
+    (first.map(|(timestamp, _)| timestamp), packets)
+}
+
+/// Time spent capturing up to the timestamp, the wall-clock time when the capture intervals are not known
+/// (e.g. packets loaded from a file)
+pub(crate) fn get_active_time(timestamp: i64, capture_intervals: &[(i64, Option<i64>)]) -> i64 {
+    if capture_intervals.is_empty() {
+        return timestamp;
+    }
+
+    let mut active_time = 0;
+    for (start, end) in capture_intervals {
+        if timestamp < *start {
+            break;
+        }
+
+        let end = end.unwrap_or(i64::MAX).min(timestamp);
+        active_time += end - start;
+    }
+
+    active_time
+}
+
+/// Transport protocol and endpoints of a packet, sorted so that both directions share the same key
+fn get_conversation_key(
+    packet: &ParsedPacket,
+) -> Option<(String, (ConversationEndpoint, ConversationEndpoint))> {
+    let protocol = if contains_tcp(packet) {
+        "tcp"
+    } else if contains_udp(packet) {
+        "udp"
+    } else {
+        "ip"
+    };
+
+    let port = |port: Option<String>| port.unwrap_or(String::from("-"));
+    let source = (get_source_ip(packet)?, port(get_source_port(packet)));
+    let destination = (get_dest_ip(packet)?, port(get_dest_port(packet)));
+
+    let endpoints = if source <= destination {
+        (source, destination)
+    } else {
+        (destination, source)
+    };
+
+    Some((protocol.to_owned(), endpoints))
+}
+
+/// Name the file after the protocol and the endpoints, e.g. `tcp_10.0.0.1_4444_10.0.0.2_80.pcap`
+fn get_filename(conversation: &Conversation) -> String {
+    let ((first_ip, first_port), (second_ip, second_port)) = &conversation.endpoints;
+
+    let name = [
+        conversation.protocol.as_str(),
+        first_ip,
+        first_port,
+        second_ip,
+        second_port,
+    ]
+    .iter()
+    .filter(|part| **part != "-")
+    .map(|part| {
+        part.chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' => c,
+                _ => '-',
+            })
+            .collect::<String>()
+    })
+    .collect::<Vec<String>>()
+    .join("_");
+
+    format!("{}.pcap", name)
+}
+
+fn write_conversation(
+    path: &Path,
+    conversation: &Conversation,
+    packets_collection: &PacketsCollection,
+) -> std::io::Result<()> {
+    let mut pcap_writer: Option<PcapWriter<BufWriter<File>>> = None;
+
+    for packet in &conversation.packets {
+        let raw_packet = match packets_collection.raw_packets.get(&packet.get_id()) {
+            Some(raw_packet) => raw_packet,
+            None => continue,
+        };
+
+        let pcap_writer = match &mut pcap_writer {
+            Some(pcap_writer) => pcap_writer,
+            None => pcap_writer.insert(PcapWriter::new(
+                BufWriter::new(File::create(path)?),
+                raw_packet.link_type,
+            )?),
+        };
+
+        if raw_packet.link_type != pcap_writer.link_type {
+            warn!(
+                "Skipped packet {} with link type {} in a conversation with link type {}",
+                packet.get_id(),
+                raw_packet.link_type,
+                pcap_writer.link_type
+            );
+            continue;
+        }
+
+        pcap_writer.write_record(packet.get_timestamp(), &raw_packet.data)?;
+    }
+
+    match pcap_writer {
+        Some(pcap_writer) => pcap_writer.into_inner().flush(),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::{
+        get_active_time, get_conversation_key, get_conversation_timeline_internal,
+        get_conversations, get_entropy_timeline_internal, get_filename,
+    };
+
+    #[test]
+    fn packets_grouped_by_conversation() {
+        let (first, second, third) = (
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+            Ipv4Addr::new(10, 0, 0, 3),
+        );
+        let mut packets_collection = PacketsCollection::new();
+        let packets = [
+            (first, second, 4444, 80, 100),
+            (second, first, 80, 4444, 1500),
+            (first, third, 4444, 80, 60),
+            (first, second, 5555, 80, 60),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(
+            |(id, (source, destination, source_port, dest_port, length))| {
+                packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &vec![0; length]);
+                let template = build_test_parsed_packet(
+                    MacAddr::zero(),
+                    MacAddr::zero(),
+                    source,
+                    destination,
+                    source_port,
+                    dest_port,
+                );
+
+                let mut parsed_packet = ParsedPacket::new(id);
+                parsed_packet
+                    .set_network_layer_packet(template.get_network_layer_packet().cloned());
+                parsed_packet
+                    .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+                Arc::new(parsed_packet)
+            },
+        )
+        .collect::<Vec<_>>();
+
+        let conversations = get_conversations(&packets, &packets_collection);
+
+        assert_eq!(conversations.len(), 3);
+        assert_eq!(conversations[0].packets.len(), 2);
+        assert_eq!(conversations[0].bytes, 1600);
+        assert_eq!(
+            get_filename(&conversations[0]),
+            "tcp_10.0.0.1_4444_10.0.0.2_80.pcap"
+        );
+        assert_eq!(conversations[1].bytes, 60);
+        assert_eq!(
+            get_filename(&conversations[2]),
+            "tcp_10.0.0.1_5555_10.0.0.2_80.pcap"
+        );
+    }
+
+    #[test]
+    fn timeline_skips_pauses() {
+        let intervals = vec![(1_000_000, Some(2_000_000)), (10_000_000, None)];
+        assert_eq!(get_active_time(1_500_000, &intervals), 500_000);
+        assert_eq!(get_active_time(5_000_000, &intervals), 1_000_000);
+        assert_eq!(get_active_time(10_250_000, &intervals), 1_250_000);
+
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection.capture_intervals = intervals;
+
+        for (id, (timestamp, source_port)) in [
+            (1_100_000, 4444),
+            (1_900_000, 4444),
+            (1_950_000, 5555),
+            (10_300_000, 4444),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &[0; 100]);
+            let packet = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2),
+                source_port,
+                80,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_network_layer_packet(packet.get_network_layer_packet().cloned());
+            parsed_packet.set_transport_layer_packet(packet.get_transport_layer_packet().cloned());
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let key = get_conversation_key(&packets_collection.packets[0]).unwrap();
+        let timeline = get_conversation_timeline_internal(&key, 500, &packets_collection);
+
+        assert_eq!(timeline.first_timestamp, Some(1_100_000));
+        assert_eq!(
+            timeline
+                .buckets
+                .iter()
+                .map(|bucket| (bucket.offset, bucket.packets, bucket.bytes))
+                .collect::<Vec<_>>(),
+            vec![(0, 1, 100), (500, 1, 100), (1000, 1, 100)]
+        );
+    }
+
+    #[test]
+    fn entropy_timeline_of_conversation() {
+        let mut packets_collection = PacketsCollection::new();
+
+        // Cleartext handshake, then encrypted transfer, with a bare ACK in between
+        for (id, (timestamp, entropy)) in [
+            (1_000_000, Some(4.0)),
+            (1_200_000, Some(5.0)),
+            (1_300_000, None),
+            (2_100_000, Some(7.5)),
+            (2_400_000, Some(8.0)),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let packet = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2),
+                4444,
+                443,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_network_layer_packet(packet.get_network_layer_packet().cloned());
+            parsed_packet.set_transport_layer_packet(packet.get_transport_layer_packet().cloned());
+            parsed_packet.set_payload_entropy(entropy);
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let key = get_conversation_key(&packets_collection.packets[0]).unwrap();
+        let timeline = get_entropy_timeline_internal(&key, 500, &packets_collection);
+
+        assert_eq!(timeline.first_timestamp, Some(1_000_000));
+        assert_eq!(
+            timeline
+                .buckets
+                .iter()
+                .map(|bucket| (bucket.offset, bucket.packets, bucket.mean_entropy))
+                .collect::<Vec<_>>(),
+            vec![(0, 2, Some(4.5)), (500, 0, None), (1000, 2, Some(7.75))]
+        );
+        assert_eq!(timeline.buckets[0].max_entropy, Some(5.0));
+    }
+}
@@ -0,0 +1,227 @@
+//! Response times of the DNS queries
+//!
+//! Queries and responses are paired by transaction ID, transport protocol and endpoints, scanning the
+//! packets in timestamp order:
+//! - a query repeated before its response (e.g. retransmitted by the client) is counted as a
+//!   retransmission, and the response time is measured from the first one
+//! - responses without a previous query (e.g. captured after the query was sent) and the duplicated ones
+//!   are ignored
+//! - queries without any response are reported separately, including the ones still pending at the end
+//!   of the capture
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    contains_tcp, get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::neighbors::get_answer_value;
+use crate::SniffingState;
+
+/// DNS query, with its response if captured
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DnsQueryTiming {
+    pub id: u16,
+    pub protocol: String,
+    pub client: String,
+    pub server: String,
+    /// Name and type of the first question
+    pub name: String,
+    pub query_type: String,
+    /// Timestamp of the first query, in microseconds since the Unix epoch
+    pub timestamp: i64,
+    pub retransmissions: usize,
+    /// Time between the first query and the response, in microseconds
+    pub response_time: Option<i64>,
+    pub response_code: Option<String>,
+    /// Record type and value of each answer, e.g. `A 93.184.216.34`
+    pub answers: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct DnsTimings {
+    /// Queries with a response, in query order
+    pub answered: Vec<DnsQueryTiming>,
+    /// Queries without any response, in query order
+    pub unanswered: Vec<DnsQueryTiming>,
+}
+
+/// Returns the DNS queries with their response time and answers, and the ones without response
+#[tauri::command]
+pub fn get_dns_timings(state: tauri::State<SniffingState>) -> DnsTimings {
+    let mut packets = state.packets.lock().unwrap().dns_packets.clone();
+    packets.sort_by_key(|packet| packet.get_timestamp());
+
+    get_dns_timings_internal(&packets)
+}
+
+/// Transaction ID, transport protocol, client and server endpoints
+type Transaction = (u16, &'static str, (String, String), (String, String));
+
+fn get_dns_timings_internal(packets: &[Arc<ParsedPacket>]) -> DnsTimings {
+    let mut queries: Vec<DnsQueryTiming> = vec![];
+    // Queries still waiting for a response, by transaction
+    let mut pending: HashMap<Transaction, usize> = HashMap::new();
+
+    for packet in packets {
+        let dns_packet = match packet.get_application_layer_packet() {
+            Some(SerializablePacket::DnsPacket(dns_packet)) => dns_packet,
+            _ => continue,
+        };
+
+        let protocol = if contains_tcp(packet) { "tcp" } else { "udp" };
+        let sender = (
+            get_source_ip(packet).unwrap_or_default(),
+            get_source_port(packet).unwrap_or_default(),
+        );
+        let receiver = (
+            get_dest_ip(packet).unwrap_or_default(),
+            get_dest_port(packet).unwrap_or_default(),
+        );
+
+        if dns_packet.header.query {
+            let transaction = (dns_packet.header.id, protocol, sender, receiver);
+            if let Some(index) = pending.get(&transaction) {
+                queries[*index].retransmissions += 1;
+                continue;
+            }
+
+            let question = dns_packet.questions.first();
+            queries.push(DnsQueryTiming {
+                id: dns_packet.header.id,
+                protocol: protocol.to_owned(),
+                client: transaction.2 .0.clone(),
+                server: transaction.3 .0.clone(),
+                name: question.map_or(String::new(), |q| q.query_name.clone()),
+                query_type: question.map_or(String::new(), |q| q.query_type.clone()),
+                timestamp: packet.get_timestamp(),
+                retransmissions: 0,
+                response_time: None,
+                response_code: None,
+                answers: vec![],
+            });
+            pending.insert(transaction, queries.len() - 1);
+        } else {
+            // Responses are sent by the server to the client
+            let transaction = (dns_packet.header.id, protocol, receiver, sender);
+            if let Some(index) = pending.remove(&transaction) {
+                let query = &mut queries[index];
+                query.response_time = Some(packet.get_timestamp() - query.timestamp);
+                query.response_code = Some(dns_packet.header.response_code.clone());
+                query.answers = dns_packet
+                    .answers
+                    .iter()
+                    .filter_map(|answer| get_answer_value(&answer.data))
+                    .map(|(record_type, value)| format!("{} {}", record_type, value))
+                    .collect();
+            }
+        }
+    }
+
+    let (answered, unanswered) = queries
+        .into_iter()
+        .partition(|query| query.response_time.is_some());
+
+    DnsTimings {
+        answered,
+        unanswered,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use dns_parser::{Builder, Packet, QueryClass, QueryType};
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::application::{
+        CustomResourceData, CustomResourceRecord, SerializableDnsPacket, A,
+    };
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::get_dns_timings_internal;
+
+    const CLIENT: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const SERVER: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 53);
+
+    fn build_dns(
+        id: usize,
+        timestamp: i64,
+        transaction_id: u16,
+        response: Option<Ipv4Addr>,
+    ) -> Arc<ParsedPacket> {
+        let mut builder = Builder::new_query(transaction_id, true);
+        builder.add_question("example.com", false, QueryType::A, QueryClass::IN);
+        let query = builder.build().unwrap();
+        let mut dns_packet = SerializableDnsPacket::from(&Packet::parse(&query).unwrap());
+
+        let template = match response {
+            Some(address) => {
+                dns_packet.header.query = false;
+                dns_packet.answers.push(CustomResourceRecord {
+                    name: "example.com".to_owned(),
+                    multicast_unique: false,
+                    class: "IN".to_owned(),
+                    ttl: 300,
+                    data: CustomResourceData::A(A { address }),
+                });
+                build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), SERVER, CLIENT, 53, 5353)
+            }
+            None => {
+                build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), CLIENT, SERVER, 5353, 53)
+            }
+        };
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_timestamp(timestamp);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::DnsPacket(dns_packet)));
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn queries_paired_with_responses() {
+        let address = Ipv4Addr::new(93, 184, 216, 34);
+        let packets = vec![
+            build_dns(0, 0, 1, None),
+            build_dns(1, 1000, 2, None),
+            // Retransmission
+            build_dns(2, 5000, 1, None),
+            build_dns(3, 12_000, 1, Some(address)),
+            // Duplicate
+            build_dns(4, 13_000, 1, Some(address)),
+            // Query not captured
+            build_dns(5, 14_000, 9, Some(address)),
+        ];
+
+        let timings = get_dns_timings_internal(&packets);
+        assert_eq!(timings.answered.len(), 1);
+
+        let answered = &timings.answered[0];
+        assert_eq!((answered.id, answered.protocol.as_str()), (1, "tcp"));
+        assert_eq!(
+            (answered.client.as_str(), answered.server.as_str()),
+            ("10.0.0.1", "10.0.0.53")
+        );
+        assert_eq!(answered.name, "example.com");
+        assert_eq!(answered.query_type, "A");
+        assert_eq!(answered.retransmissions, 1);
+        assert_eq!(answered.response_time, Some(12_000));
+        assert_eq!(answered.response_code.as_deref(), Some("NoError"));
+        assert_eq!(answered.answers, vec!["A 93.184.216.34"]);
+
+        assert_eq!(timings.unanswered.len(), 1);
+        assert_eq!(
+            (timings.unanswered[0].id, timings.unanswered[0].timestamp),
+            (2, 1000)
+        );
+        assert_eq!(timings.unanswered[0].response_time, None);
+    }
+}
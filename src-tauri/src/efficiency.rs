@@ -0,0 +1,285 @@
+//! Efficiency of the data transfer of a TCP connection, in each direction
+//!
+//! A data segment is a retransmission when it carries sequence numbers already sent in the same
+//! direction: the bytes beyond the highest sequence number seen so far are goodput, the others are
+//! overhead. Segments lost before the capture point and retransmitted afterwards are seen only once,
+//! so their retransmission is not detected.
+//!
+//! The direction is relative to the endpoint given as source: `forward` for the segments it sent,
+//! `reverse` for the ones it received. Loss is often asymmetric, so each direction is measured separately.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use log::{info, warn};
+use pnet::packet::tcp::TcpFlags;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::latency::sequence_at_or_before;
+use crate::{SniffingError, SniffingState};
+
+/// Payload bytes sent in one direction of a connection
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct DirectionEfficiency {
+    pub segments: usize,
+    pub retransmitted_segments: usize,
+    /// Payload bytes, retransmissions included
+    pub bytes: usize,
+    /// Payload bytes sent for the first time
+    pub goodput: usize,
+    pub retransmitted_bytes: usize,
+    /// Percentage of the payload bytes that are retransmitted
+    pub retransmission_rate: f64,
+    /// Ratio of the goodput to the payload bytes, 1 without any retransmission
+    pub efficiency: f64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ConnectionEfficiency {
+    pub forward: DirectionEfficiency,
+    pub reverse: DirectionEfficiency,
+}
+
+type Endpoint = (IpAddr, u16);
+
+/// Returns the goodput and retransmissions of the TCP connection between the two endpoints, per direction
+#[tauri::command]
+pub fn get_connection_efficiency(
+    state: tauri::State<SniffingState>,
+    source_ip: String,
+    source_port: u16,
+    dest_ip: String,
+    dest_port: u16,
+) -> Result<ConnectionEfficiency, SniffingError> {
+    let parse_ip = |ip: &str| {
+        ip.parse::<IpAddr>().map_err(|e| {
+            warn!("Invalid connection IP address {}: {}", ip, e);
+            SniffingError::InvalidIpAddress(format!("Invalid connection IP address {}: {}", ip, e))
+        })
+    };
+
+    let source = (parse_ip(&source_ip)?, source_port);
+    let destination = (parse_ip(&dest_ip)?, dest_port);
+
+    let packets_collection = state.packets.lock().unwrap();
+    let efficiency =
+        get_connection_efficiency_internal(source, destination, &packets_collection.tcp_packets);
+
+    info!(
+        "TCP connection {:?} - {:?}: {} and {} retransmitted bytes",
+        source,
+        destination,
+        efficiency.forward.retransmitted_bytes,
+        efficiency.reverse.retransmitted_bytes
+    );
+
+    Ok(efficiency)
+}
+
+/// Bytes sent in one direction, with the sequence number following the highest one sent
+#[derive(Default)]
+struct DirectionState {
+    efficiency: DirectionEfficiency,
+    next_sequence: Option<u32>,
+}
+
+impl DirectionState {
+    fn update(&mut self, sequence: u32, length: usize, flags: u16) {
+        // The SYN takes a sequence number, without carrying payload
+        let sequence = if flags & TcpFlags::SYN != 0 {
+            sequence.wrapping_add(1)
+        } else {
+            sequence
+        };
+        let end = sequence.wrapping_add(length as u32);
+
+        let next_sequence = match self.next_sequence {
+            Some(next_sequence) => next_sequence,
+            None => {
+                self.next_sequence = Some(end);
+                sequence
+            }
+        };
+
+        if length == 0 {
+            if sequence_at_or_before(next_sequence, end) {
+                self.next_sequence = Some(end);
+            }
+            return;
+        }
+
+        let efficiency = &mut self.efficiency;
+        efficiency.segments += 1;
+        efficiency.bytes += length;
+
+        // Already sent up to `next_sequence`, e.g. a full or partial retransmission
+        let retransmitted = if sequence_at_or_before(end, next_sequence) {
+            length
+        } else if sequence_at_or_before(next_sequence, sequence) {
+            0
+        } else {
+            next_sequence.wrapping_sub(sequence) as usize
+        };
+
+        if retransmitted > 0 {
+            efficiency.retransmitted_segments += 1;
+            efficiency.retransmitted_bytes += retransmitted;
+        }
+        efficiency.goodput += length - retransmitted;
+
+        if sequence_at_or_before(next_sequence, end) {
+            self.next_sequence = Some(end);
+        }
+    }
+
+    fn into_efficiency(self) -> DirectionEfficiency {
+        let mut efficiency = self.efficiency;
+        if efficiency.bytes > 0 {
+            efficiency.retransmission_rate =
+                efficiency.retransmitted_bytes as f64 * 100.0 / efficiency.bytes as f64;
+            efficiency.efficiency = efficiency.goodput as f64 / efficiency.bytes as f64;
+        }
+        efficiency
+    }
+}
+
+fn get_connection_efficiency_internal(
+    source: Endpoint,
+    destination: Endpoint,
+    packets: &[Arc<ParsedPacket>],
+) -> ConnectionEfficiency {
+    let (mut forward, mut reverse) = (DirectionState::default(), DirectionState::default());
+
+    for packet in packets {
+        let tcp_packet = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => continue,
+        };
+
+        let endpoints = match get_endpoints(packet) {
+            Some(endpoints) => endpoints,
+            None => continue,
+        };
+
+        let direction = if endpoints == (source, destination) {
+            &mut forward
+        } else if endpoints == (destination, source) {
+            &mut reverse
+        } else {
+            continue;
+        };
+
+        direction.update(tcp_packet.sequence, tcp_packet.length, tcp_packet.flags);
+    }
+
+    ConnectionEfficiency {
+        forward: forward.into_efficiency(),
+        reverse: reverse.into_efficiency(),
+    }
+}
+
+pub(crate) fn get_endpoints(packet: &ParsedPacket) -> Option<(Endpoint, Endpoint)> {
+    let source_ip = get_source_ip(packet)?.parse::<IpAddr>().ok()?;
+    let source_port = get_source_port(packet)?.parse::<u16>().ok()?;
+    let dest_ip = get_dest_ip(packet)?.parse::<IpAddr>().ok()?;
+    let dest_port = get_dest_port(packet)?.parse::<u16>().ok()?;
+
+    Some(((source_ip, source_port), (dest_ip, dest_port)))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{get_connection_efficiency_internal, DirectionEfficiency};
+
+    fn build_tcp_packet(
+        id: usize,
+        to_server: bool,
+        flags: u16,
+        sequence: u32,
+        length: usize,
+    ) -> Arc<ParsedPacket> {
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 80));
+        let template = if to_server {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), client, server, 4444, 80)
+        } else {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), server, client, 80, 4444)
+        };
+
+        let mut tcp_packet = match template.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.flags = flags;
+        tcp_packet.sequence = sequence;
+        tcp_packet.length = length;
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn retransmitted_bytes_per_direction() {
+        let data = TcpFlags::ACK | TcpFlags::PSH;
+        // Close to the wrap around of the sequence numbers
+        let isn = u32::MAX - 150;
+        let packets = vec![
+            build_tcp_packet(0, true, TcpFlags::SYN, isn, 0),
+            build_tcp_packet(1, false, TcpFlags::SYN | TcpFlags::ACK, 9000, 0),
+            build_tcp_packet(2, true, data, isn + 1, 100),
+            build_tcp_packet(3, true, data, isn.wrapping_add(101), 100),
+            // Full retransmission
+            build_tcp_packet(4, true, data, isn.wrapping_add(101), 100),
+            // Partial retransmission, 50 bytes already sent
+            build_tcp_packet(5, true, data, isn.wrapping_add(151), 100),
+            build_tcp_packet(6, false, data, 9001, 300),
+            build_tcp_packet(7, false, TcpFlags::ACK, 9301, 0),
+        ];
+
+        let efficiency = get_connection_efficiency_internal(
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 4444),
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 80)), 80),
+            &packets,
+        );
+
+        assert_eq!(
+            efficiency.forward,
+            DirectionEfficiency {
+                segments: 4,
+                retransmitted_segments: 2,
+                bytes: 400,
+                goodput: 250,
+                retransmitted_bytes: 150,
+                retransmission_rate: 37.5,
+                efficiency: 0.625,
+            }
+        );
+        assert_eq!(
+            efficiency.reverse,
+            DirectionEfficiency {
+                segments: 1,
+                retransmitted_segments: 0,
+                bytes: 300,
+                goodput: 300,
+                retransmitted_bytes: 0,
+                retransmission_rate: 0.0,
+                efficiency: 1.0,
+            }
+        );
+    }
+}
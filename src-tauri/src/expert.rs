@@ -0,0 +1,270 @@
+//! Expert info of the collected packets, the notes, warnings and errors worth the attention of the user
+//!
+//! Each packet is annotated when stored: `sniffer_parser` reports the conditions of its layers (e.g.
+//! malformed layers, wrong checksums, resets), while the analyzer below follows the sequence numbers of
+//! each TCP direction to report:
+//! - retransmissions, segments carrying only already sent bytes
+//! - previous segments not captured, segments starting past the next expected sequence number
+//! - keep-alives, segments of a single byte (or none) preceding the next expected sequence number
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use pnet::packet::tcp::TcpFlags;
+use serde::Serialize;
+use sniffer_parser::expert::add_layer_expert_info;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{
+    ExpertGroups, ExpertSeverities, ParsedPacket, SerializablePacket,
+};
+
+use crate::filtering::apply_all_strong_filters;
+use crate::latency::sequence_at_or_before;
+use crate::{SniffingError, SniffingState};
+
+type Direction = (String, String, String, String);
+
+/// Annotates the packets with their expert info, following the sequence numbers of each TCP direction
+#[derive(Debug, Default)]
+pub struct ExpertAnalyzer {
+    /// Sequence number following the highest one sent in each direction
+    next_sequences: HashMap<Direction, u32>,
+}
+
+impl ExpertAnalyzer {
+    pub fn new() -> Self {
+        ExpertAnalyzer::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.next_sequences.clear();
+    }
+
+    /// Annotates a packet about to be stored, in capture order
+    pub fn analyze(&mut self, packet: &mut ParsedPacket) {
+        add_layer_expert_info(packet);
+
+        let tcp_packet = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => return,
+        };
+        let direction = match (
+            get_source_ip(packet),
+            get_source_port(packet),
+            get_dest_ip(packet),
+            get_dest_port(packet),
+        ) {
+            (Some(source_ip), Some(source_port), Some(dest_ip), Some(dest_port)) => {
+                (source_ip, source_port, dest_ip, dest_port)
+            }
+            _ => return,
+        };
+
+        let flags = tcp_packet.flags;
+        let is_syn = flags & TcpFlags::SYN != 0;
+        let is_fin = flags & TcpFlags::FIN != 0;
+        if flags & TcpFlags::RST != 0 {
+            return;
+        }
+
+        // SYN and FIN take a sequence number each
+        let sequence = tcp_packet.sequence;
+        let length = tcp_packet.length as u32 + is_syn as u32 + is_fin as u32;
+        let end = sequence.wrapping_add(length);
+
+        let next_sequence = match self.next_sequences.get_mut(&direction) {
+            Some(next_sequence) => next_sequence,
+            None => {
+                self.next_sequences.insert(direction, end);
+                return;
+            }
+        };
+
+        let annotation = if length == 0 {
+            None
+        } else if length <= 1 && !is_syn && !is_fin && sequence == next_sequence.wrapping_sub(1) {
+            Some((ExpertSeverities::NOTE, "Keep-alive"))
+        } else if sequence_at_or_before(end, *next_sequence) {
+            Some((ExpertSeverities::NOTE, "Suspected retransmission"))
+        } else if !sequence_at_or_before(sequence, *next_sequence) {
+            Some((ExpertSeverities::WARNING, "Previous segment not captured"))
+        } else {
+            None
+        };
+
+        if sequence_at_or_before(*next_sequence, end) {
+            *next_sequence = end;
+        }
+
+        if let Some((severity, message)) = annotation {
+            packet.add_expert_info(severity, ExpertGroups::SEQUENCE, message.to_owned());
+        }
+    }
+}
+
+/// Packets annotated with the same expert info
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExpertEntry {
+    pub severity: String,
+    pub group: String,
+    pub message: String,
+    pub packets: usize,
+    pub first_packet_id: usize,
+}
+
+/// Expert info of the collected packets
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExpertSummary {
+    /// Number of annotations of each severity
+    pub severities: BTreeMap<String, usize>,
+    /// Annotations, most severe and then most frequent first
+    pub entries: Vec<ExpertEntry>,
+}
+
+/// Returns the expert info of the packets matching the filters, counted by severity and message
+#[tauri::command]
+pub fn get_expert_info<'a>(
+    state: tauri::State<SniffingState>,
+    filters_value: Vec<(&'a str, &'a str)>,
+) -> Result<ExpertSummary, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+
+    let packets = if filters_value.is_empty() {
+        packets_collection.packets.clone()
+    } else {
+        let end = packets_collection.packets.len();
+        apply_all_strong_filters(end, &filters_value, &mut packets_collection)?
+    };
+
+    Ok(get_expert_info_internal(&packets))
+}
+
+fn get_expert_info_internal(packets: &[Arc<ParsedPacket>]) -> ExpertSummary {
+    let mut severities = BTreeMap::new();
+    let mut entries: HashMap<(&str, &str, &str), ExpertEntry> = HashMap::new();
+
+    for packet in packets {
+        for info in packet.get_expert_info() {
+            *severities.entry(info.severity.clone()).or_insert(0) += 1;
+
+            entries
+                .entry((&info.severity, &info.group, &info.message))
+                .and_modify(|entry| entry.packets += 1)
+                .or_insert_with(|| ExpertEntry {
+                    severity: info.severity.clone(),
+                    group: info.group.clone(),
+                    message: info.message.clone(),
+                    packets: 1,
+                    first_packet_id: packet.get_id(),
+                });
+        }
+    }
+
+    let mut entries = entries.into_values().collect::<Vec<ExpertEntry>>();
+    entries.sort_by(|a, b| {
+        ExpertSeverities::get_level(&b.severity)
+            .cmp(&ExpertSeverities::get_level(&a.severity))
+            .then(b.packets.cmp(&a.packets))
+            .then(a.first_packet_id.cmp(&b.first_packet_id))
+    });
+
+    ExpertSummary {
+        severities,
+        entries,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::{ExpertSeverities, ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{get_expert_info_internal, ExpertAnalyzer};
+
+    fn build_tcp_packet(id: usize, flags: u16, sequence: u32, length: usize) -> ParsedPacket {
+        let template = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+            4444,
+            80,
+        );
+
+        let mut tcp_packet = match template.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.flags = flags;
+        tcp_packet.sequence = sequence;
+        tcp_packet.length = length;
+        tcp_packet.window = 1024;
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+        parsed_packet
+    }
+
+    #[test]
+    fn sequence_analysis() {
+        let data = TcpFlags::ACK | TcpFlags::PSH;
+        let mut analyzer = ExpertAnalyzer::new();
+
+        let packets = [
+            build_tcp_packet(0, TcpFlags::SYN, 99, 0),
+            build_tcp_packet(1, data, 100, 100),
+            // Retransmission
+            build_tcp_packet(2, data, 100, 100),
+            // 100 bytes lost before the capture point
+            build_tcp_packet(3, data, 300, 100),
+            build_tcp_packet(4, TcpFlags::ACK, 399, 1),
+            build_tcp_packet(5, TcpFlags::RST, 400, 0),
+        ]
+        .into_iter()
+        .map(|mut packet| {
+            analyzer.analyze(&mut packet);
+            Arc::new(packet)
+        })
+        .collect::<Vec<Arc<ParsedPacket>>>();
+
+        let messages = packets
+            .iter()
+            .map(|packet| {
+                packet
+                    .get_expert_info()
+                    .iter()
+                    .map(|info| info.message.as_str())
+                    .collect::<Vec<&str>>()
+            })
+            .collect::<Vec<Vec<&str>>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                vec!["Connection establish request (SYN)"],
+                vec![],
+                vec!["Suspected retransmission"],
+                vec!["Previous segment not captured"],
+                vec!["Keep-alive"],
+                vec!["Connection reset (RST)"],
+            ]
+        );
+
+        let summary = get_expert_info_internal(&packets);
+        assert_eq!(summary.severities[ExpertSeverities::WARNING], 2);
+        assert_eq!(summary.severities[ExpertSeverities::NOTE], 2);
+        assert_eq!(summary.severities[ExpertSeverities::CHAT], 1);
+        assert_eq!(summary.entries[0].severity, ExpertSeverities::WARNING);
+        assert_eq!(summary.entries[0].first_packet_id, 3);
+        assert_eq!(summary.entries[4].severity, ExpertSeverities::CHAT);
+    }
+}
@@ -0,0 +1,128 @@
+//! Handling of the Frame Check Sequence (FCS) kept by some NICs and drivers at the end of the captured
+//! Ethernet frames
+//!
+//! - `absent`: frames are taken as captured (default)
+//! - `present`: the last 4 bytes of every frame are the FCS
+//! - `auto`: the last 4 bytes are the FCS when they match the CRC-32 of the rest of the frame
+//!
+//! The FCS is stripped before the frame is parsed and stored, so that it counts neither in the
+//! transmitted bytes nor in the payloads (e.g. the trailer of a UDP datagram). The mode applies to the
+//! frames captured from then on, with an Ethernet link layer only.
+
+use log::info;
+
+use crate::capture_file::LinkTypes;
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod FcsModes {
+    pub const ABSENT: &str = "absent";
+    pub const PRESENT: &str = "present";
+    pub const AUTO: &str = "auto";
+
+    pub const ALL: [&str; 3] = [ABSENT, PRESENT, AUTO];
+}
+
+const FCS_LENGTH: usize = 4;
+/// Destination and source MAC addresses and EtherType
+const ETHERNET_HEADER_LENGTH: usize = 14;
+
+/// Tells whether the captured frames end with the FCS, or whether to detect it frame by frame
+#[tauri::command]
+pub fn set_fcs_mode(state: tauri::State<SniffingState>, mode: String) -> Result<(), SniffingError> {
+    let mode = mode.to_lowercase();
+    if !FcsModes::ALL.contains(&mode.as_str()) {
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "Unknown FCS mode: {} (available: {})",
+            mode,
+            FcsModes::ALL.join(", ")
+        )));
+    }
+
+    info!("FCS mode: {}", mode);
+    *state.fcs_mode.lock().unwrap() = mode;
+
+    Ok(())
+}
+
+/// Frame without its FCS, according to the mode
+pub(crate) fn strip_fcs<'a>(mode: &str, link_type: u32, frame: &'a [u8]) -> &'a [u8] {
+    if link_type != LinkTypes::ETHERNET || frame.len() < ETHERNET_HEADER_LENGTH + FCS_LENGTH {
+        return frame;
+    }
+
+    let (data, trailer) = frame.split_at(frame.len() - FCS_LENGTH);
+    let has_fcs = match mode {
+        FcsModes::PRESENT => true,
+        FcsModes::AUTO => is_valid_fcs(data, trailer),
+        _ => false,
+    };
+
+    if has_fcs {
+        data
+    } else {
+        frame
+    }
+}
+
+/// The FCS is the CRC-32 of the frame, sent least significant byte first
+fn is_valid_fcs(data: &[u8], trailer: &[u8]) -> bool {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+
+    crc.sum().to_le_bytes() == trailer
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::capture_file::LinkTypes;
+
+    use super::{strip_fcs, FcsModes};
+
+    #[test]
+    fn fcs_stripped_by_mode() {
+        // Broadcast ARP request, padded to the minimum Ethernet size, with its FCS
+        let mut frame = vec![0xff; 6];
+        frame.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x08, 0x06]);
+        frame.extend_from_slice(&[0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01]);
+        frame.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 192, 168, 1, 10]);
+        frame.extend_from_slice(&[0; 6]);
+        frame.extend_from_slice(&[192, 168, 1, 1]);
+        frame.resize(60, 0);
+
+        let mut crc = flate2::Crc::new();
+        crc.update(&frame);
+        let mut frame_with_fcs = frame.clone();
+        frame_with_fcs.extend_from_slice(&crc.sum().to_le_bytes());
+
+        let strip =
+            |mode: &str, link_type: u32, frame: &[u8]| strip_fcs(mode, link_type, frame).len();
+
+        assert_eq!(
+            strip(FcsModes::ABSENT, LinkTypes::ETHERNET, &frame_with_fcs),
+            64
+        );
+        assert_eq!(
+            strip(FcsModes::PRESENT, LinkTypes::ETHERNET, &frame_with_fcs),
+            60
+        );
+        assert_eq!(
+            strip(FcsModes::AUTO, LinkTypes::ETHERNET, &frame_with_fcs),
+            60
+        );
+
+        // Trailer not matching the CRC, padding kept
+        assert_eq!(strip(FcsModes::AUTO, LinkTypes::ETHERNET, &frame), 60);
+        assert_eq!(strip(FcsModes::PRESENT, LinkTypes::ETHERNET, &frame), 56);
+
+        // Other link layers and frames too short are left untouched
+        assert_eq!(
+            strip(FcsModes::PRESENT, LinkTypes::NULL, &frame_with_fcs),
+            64
+        );
+        assert_eq!(
+            strip(FcsModes::PRESENT, LinkTypes::ETHERNET, &frame[..16]),
+            16
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Textual form of the packet filters, to validate them while they're typed
+//!
+//! An expression is a list of terms separated by `;`, each one either the name of a type filter (e.g.
+//! `tcp`) or an attribute filter with its value after `=` (e.g. `dst_port=80,443`, `entropy=>7.5`).
+//! The terms are the same filters taken by `get_packets`, with the same meaning: the packets match any of
+//! the type filters and all the attribute filters.
+//!
+//! Names and values are checked by the same parsers applying the filters, so an expression is valid
+//! exactly when `get_packets` accepts its filters. Errors carry the position of the term or of the value
+//! at fault, in characters from the start of the expression.
+
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::filtering::{apply_layer_type_filter, apply_specific_filter, PacketsCollection};
+use crate::SniffingError;
+
+const TERM_SEPARATOR: char = ';';
+const VALUE_SEPARATOR: char = '=';
+
+/// Error of a filter expression, serialized as the error itself (`type` and `description`) along with
+/// its `position`
+#[derive(Serialize, Debug)]
+pub struct FilterSyntaxError {
+    #[serde(flatten)]
+    pub error: SniffingError,
+    /// Characters preceding the failure in the expression
+    pub position: usize,
+}
+
+/// Filters of an expression, as taken by `get_packets`
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedFilters<'a> {
+    pub filters_type: Vec<&'a str>,
+    pub filters_value: Vec<(&'a str, &'a str)>,
+}
+
+/// Checks whether the expression is a valid filter, without applying it
+#[tauri::command]
+pub fn validate_filter(expression: String) -> Result<(), FilterSyntaxError> {
+    let filters = parse_filter_expression(&expression)?;
+
+    info!(
+        "Valid filter: {} type and {} attribute filters",
+        filters.filters_type.len(),
+        filters.filters_value.len()
+    );
+    Ok(())
+}
+
+/// Split the expression in its filters, checking each one of them
+pub(crate) fn parse_filter_expression(
+    expression: &str,
+) -> Result<ParsedFilters<'_>, FilterSyntaxError> {
+    let mut filters = ParsedFilters::default();
+    if expression.trim().is_empty() {
+        return Ok(filters);
+    }
+
+    let mut offset = 0;
+    for term in expression.split(TERM_SEPARATOR) {
+        let term_start = offset + leading_whitespace(term);
+        offset += term.len() + TERM_SEPARATOR.len_utf8();
+
+        let fail = |error: SniffingError, start: usize| {
+            warn!("Invalid filter expression {}: {:?}", expression, error);
+            FilterSyntaxError {
+                error,
+                position: expression[..start].chars().count(),
+            }
+        };
+
+        let term = term.trim();
+        if term.is_empty() {
+            return Err(fail(
+                SniffingError::InvalidFilterValue("Empty filter term".to_owned()),
+                term_start,
+            ));
+        }
+
+        match term.split_once(VALUE_SEPARATOR) {
+            Some((name, value)) => {
+                let name = name.trim();
+                let value_start = term_start + term.len() - value.len() + leading_whitespace(value);
+                let value = value.trim();
+
+                if value.is_empty() {
+                    return Err(fail(
+                        SniffingError::InvalidFilterValue(format!(
+                            "Missing value of filter {}",
+                            name
+                        )),
+                        value_start,
+                    ));
+                }
+
+                check_value_filter(name, value).map_err(|error| match error {
+                    SniffingError::UnknownFilterType(_) => fail(error, term_start),
+                    _ => fail(error, value_start),
+                })?;
+                filters.filters_value.push((name, value));
+            }
+            None => {
+                apply_layer_type_filter(term, &Arc::new(ParsedPacket::new(0)))
+                    .map_err(|error| fail(error, term_start))?;
+                filters.filters_type.push(term);
+            }
+        }
+    }
+
+    Ok(filters)
+}
+
+/// Run the attribute filter on an empty collection, which parses its value
+fn check_value_filter(name: &str, value: &str) -> Result<(), SniffingError> {
+    apply_specific_filter(
+        name,
+        value,
+        0,
+        true,
+        &mut PacketsCollection::new(),
+        &mut vec![],
+    )
+}
+
+fn leading_whitespace(text: &str) -> usize {
+    text.len() - text.trim_start().len()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::filtering::FilterNamesValues;
+    use crate::SniffingError;
+
+    use super::{parse_filter_expression, ParsedFilters};
+
+    #[test]
+    fn filter_expressions_validated() {
+        assert_eq!(
+            parse_filter_expression(" tcp; udp ;dst_port = 80,443; entropy=>7.5").unwrap(),
+            ParsedFilters {
+                filters_type: vec![FilterNamesValues::TCP, FilterNamesValues::UDP],
+                filters_value: vec![
+                    (FilterNamesValues::DST_PORT, "80,443"),
+                    (FilterNamesValues::ENTROPY, ">7.5"),
+                ],
+            }
+        );
+        assert_eq!(
+            parse_filter_expression("  ").unwrap(),
+            ParsedFilters::default()
+        );
+
+        let position = |expression| match parse_filter_expression(expression) {
+            Err(error) => (error.error, error.position),
+            Ok(_) => panic!("{} accepted", expression),
+        };
+
+        assert!(matches!(
+            position("tcp; tpc"),
+            (SniffingError::UnknownFilterType(_), 5)
+        ));
+        assert!(matches!(
+            position("tcp;;udp"),
+            (SniffingError::InvalidFilterValue(_), 4)
+        ));
+        assert!(matches!(
+            position("udp; src_port=  "),
+            (SniffingError::InvalidFilterValue(_), 14)
+        ));
+        assert!(matches!(
+            position("dst_port=80-x"),
+            (SniffingError::InvalidFilterValue(_), 9)
+        ));
+        // Positions in characters
+        assert!(matches!(
+            position("vendor=é; entropy= >9"),
+            (SniffingError::InvalidFilterValue(_), 19)
+        ));
+        assert!(matches!(
+            position("udp; colour=red"),
+            (SniffingError::UnknownFilterType(_), 5)
+        ));
+    }
+}
@@ -4,6 +4,7 @@
 //! - By Protocol
 //!     - UNKNOWN
 //!     - ETHERNET
+//!     - DOT11 (IEEE 802.11)
 //!     - IPV4
 //!     - IPV6
 //!     - ARP
@@ -14,6 +15,13 @@
 //!     - TLS
 //!     - DNS
 //!     - HTTP
+//!     - HTTP2
+//!     - SIP
+//!     - DHCPV6
+//!     - KERBEROS
+//!     - RADIUS
+//!     - BITTORRENT
+//!     - TUNNEL (6to4, Teredo)
 //! - By Attributes
 //!     - SOURCE MAC
 //!     - DESTINATION MAC
@@ -21,26 +29,84 @@
 //!     - DESTINATION IP
 //!     - SOURCE PORT
 //!     - DESTINATION PORT
+//!     - PORT (either source or destination)
+//!     - INNER SOURCE IP (tunneled IPv6 sender)
+//!     - INNER DESTINATION IP (tunneled IPv6 receiver)
+//!     - TCP MSS OPTION
+//!     - TCP WINDOW SCALE OPTION
+//!     - SERVICE (name of the service of the transport ports, e.g. `HTTPS`)
+//!     - VENDOR (hardware vendor of either the source or destination MAC address, by name or OUI)
+//!     - CAST (kind of destination MAC or IP address: unicast, multicast, broadcast)
+//!     - FLOW (hash of the flow, the same for both directions)
+//!     - FLOW TIME RELATIVE (seconds since the first packet of the TCP or UDP flow)
+//!     - ENTROPY (Shannon entropy of the transport layer payload, in bits per byte)
+//!     - EXPERT (lowest severity of the expert info of the packet: chat, note, warning, error)
+//!     - JA3 (MD5 hash of the JA3 fingerprint of the TLS Client Hello)
+//!     - JA3S (MD5 hash of the JA3S fingerprint of the TLS Server Hello)
+//!     - INTERFACE (name of the interface the packet was captured on)
 //! - By Type
 //!     - MALFORMED
-
+//!     - IP CHECKSUM BAD (IPv4 header checksum mismatch)
+//!     - TCP CHECKSUM BAD
+//!
+//! Port and TCP option filters accept sets of values and inclusive ranges, e.g. `80,443,8000-8100`
+//!
+//! Entropy and flow time filters accept a comparison or an inclusive range, e.g. `> 7.5`, `<=4`, `7-8`
+//!
+//! Service and cast filters accept comma separated names, case insensitive, e.g. `https,dns`, `unicast`
+//!
+//! Expert filters match the packets with at least one expert info of the given severity or higher, e.g.
+//! `warning` for the warnings and the errors
+//!
+//! JA3 and JA3S filters accept comma separated hashes, case insensitive
+//!
+//! Interface filters accept comma separated interface names, e.g. `eth0,wlan0`
+//!
+//! Vendor filters accept comma separated parts of vendor names or OUI prefixes, case insensitive, e.g.
+//! `espressif,00:50:56`
+//!
+//! Returned packets are tagged with the name of their service, the vendors of their MAC addresses, the
+//! color of their conversation, the time since the start of their flow and whether they're bookmarked,
+//! and can be optionally tagged with their direction (in, out, other) relative to a reference IP address
+
+use crate::baseline::Baseline;
+use crate::bookmarks::tag_bookmarks;
+use crate::capture_file::write_pcap;
+use crate::connections::ConnectionTracker;
+use crate::conversation_colors::tag_conversation_colors;
+use crate::expert::ExpertAnalyzer;
+use crate::flow_sampling::FlowSampler;
+use crate::flow_time::{get_flow_time_relative, tag_flow_times};
+use crate::neighbors::AddressTracker;
+use crate::packet_storage::{share_link_payload, FrameData, FrameStore};
+use crate::services::{tag_services, ServiceNames};
+use crate::vendors::{parse_oui, tag_vendors, VendorNames};
 use crate::{SniffingError, SniffingState};
 use log::{debug, info, warn};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use sniffer_parser::serializable_packet::util::{
-    contains_arp, contains_dns, contains_ethernet, contains_http, contains_icmp, contains_icmp6,
-    contains_ipv4, contains_ipv6, contains_malformed, contains_tcp, contains_tls, contains_udp,
-    contains_unknokn,
+    contains_arp, contains_bittorrent, contains_dhcpv6, contains_dns, contains_dot11,
+    contains_ethernet, contains_http, contains_http2, contains_icmp, contains_icmp6, contains_ipv4,
+    contains_ipv6, contains_kerberos, contains_malformed, contains_radius, contains_sip,
+    contains_tcp, contains_tls, contains_tunnel, contains_udp, contains_unknokn, CastTypes,
 };
 use sniffer_parser::serializable_packet::util::{
-    get_dest_ip, get_dest_mac, get_dest_port, get_source_ip, get_source_mac, get_source_port,
+    get_cast_type, get_dest_ip, get_dest_mac, get_dest_port, get_inner_dest_ip,
+    get_inner_source_ip, get_ja3_hash, get_ja3s_hash, get_source_ip, get_source_mac,
+    get_source_port, get_tcp_mss, get_tcp_window_scale, has_bad_ip_checksum, has_bad_tcp_checksum,
 };
-use sniffer_parser::serializable_packet::ParsedPacket;
+use sniffer_parser::serializable_packet::{ExpertSeverities, ParsedPacket};
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv6Addr};
+use std::ops::RangeInclusive;
 use std::slice::Iter;
-use std::{collections::BTreeMap, sync::Arc};
+use std::sync::Arc;
 
 #[allow(non_snake_case)]
-mod FilterNamesValues {
+pub(crate) mod FilterNamesValues {
     pub const ETHERNET: &str = "ethernet";
+    pub const DOT11: &str = "dot11";
     pub const MALFORMED: &str = "malformed";
     pub const UNKNOWN: &str = "unknown";
     pub const TCP: &str = "tcp";
@@ -48,11 +114,20 @@ mod FilterNamesValues {
     pub const ICMPV6: &str = "icmpv6";
     pub const ICMP: &str = "icmp";
     pub const HTTP: &str = "http";
+    pub const HTTP2: &str = "http2";
     pub const TLS: &str = "tls";
     pub const IPV4: &str = "ipv4";
     pub const IPV6: &str = "ipv6";
     pub const ARP: &str = "arp";
     pub const DNS: &str = "dns";
+    pub const SIP: &str = "sip";
+    pub const DHCPV6: &str = "dhcpv6";
+    pub const KERBEROS: &str = "kerberos";
+    pub const RADIUS: &str = "radius";
+    pub const BITTORRENT: &str = "bittorrent";
+    pub const IP_CHECKSUM_BAD: &str = "ip.checksum.bad";
+    pub const TCP_CHECKSUM_BAD: &str = "tcp.checksum.bad";
+    pub const TUNNEL: &str = "tunnel";
 
     pub const SRC_IP: &str = "src_ip";
     pub const DST_IP: &str = "dst_ip";
@@ -60,6 +135,158 @@ mod FilterNamesValues {
     pub const DST_MAC: &str = "dst_mac";
     pub const SRC_PORT: &str = "src_port";
     pub const DST_PORT: &str = "dst_port";
+    pub const PORT: &str = "port";
+    pub const INNER_SRC_IP: &str = "inner_src_ip";
+    pub const INNER_DST_IP: &str = "inner_dst_ip";
+    pub const TCP_MSS: &str = "tcp.options.mss";
+    pub const TCP_WINDOW_SCALE: &str = "tcp.options.wscale";
+    pub const SERVICE: &str = "service";
+    pub const VENDOR: &str = "vendor";
+    pub const CAST: &str = "cast";
+    pub const FLOW: &str = "flow";
+    pub const FLOW_TIME_RELATIVE: &str = "flow.time_relative";
+    pub const ENTROPY: &str = "entropy";
+    pub const EXPERT: &str = "expert";
+    pub const JA3: &str = "ja3";
+    pub const JA3S: &str = "ja3s";
+    pub const INTERFACE: &str = "interface";
+}
+
+/// Direction of a packet relative to a reference IP address
+#[allow(non_snake_case)]
+pub mod PacketDirections {
+    pub const IN: &str = "in";
+    pub const OUT: &str = "out";
+    pub const OTHER: &str = "other";
+}
+
+/// Packet as captured, before parsing
+#[derive(Debug, Clone)]
+pub struct RawPacket {
+    /// Link-layer header type (LINKTYPE_*) of the data
+    pub link_type: u32,
+    /// Shared with the link layer payload of the parsed packet
+    pub data: Arc<FrameData>,
+}
+
+/// Collected packets along with their raw data at some point, hashed without holding the collection
+pub struct ContentSnapshot {
+    key: (usize, usize),
+    packets: Vec<Arc<ParsedPacket>>,
+    raw_packets: HashMap<usize, RawPacket>,
+}
+
+impl ContentSnapshot {
+    /// Hex-encoded SHA-256 hash of the pcap file that `export_pcap` writes of the whole capture, without
+    /// filters nor sorting, so that `sha256sum` of the exported file gives the same hash
+    ///
+    /// That is the pcap global header (microseconds resolution, little endian, link type of the first packet)
+    /// followed by a record per collected packet in collection order, the packets without raw data or with
+    /// another link type left out
+    pub fn get_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        write_pcap(&mut hasher, &self.packets, &self.raw_packets)
+            .expect("Writing to a hasher can't fail");
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Index names, as the filters using them
+pub(crate) const INDEXES: [&str; 6] = [
+    FilterNamesValues::SRC_IP,
+    FilterNamesValues::DST_IP,
+    FilterNamesValues::SRC_MAC,
+    FilterNamesValues::DST_MAC,
+    FilterNamesValues::SRC_PORT,
+    FilterNamesValues::DST_PORT,
+];
+
+/// Recency of the keys of an index, to bound its number of entries
+///
+/// Once a key is evicted the index no longer lists all the packets, so the filters using it fall back to
+/// scanning the collected packets
+#[derive(Debug, Default)]
+pub struct IndexUsage {
+    limit: Option<usize>,
+    clock: u64,
+    touched: HashMap<String, u64>,
+    /// Keys by last touch, least recent first
+    order: BTreeMap<u64, String>,
+    evicted: bool,
+}
+
+impl IndexUsage {
+    /// Marks the key as the most recently touched one, returning the keys to evict
+    ///
+    /// Nothing is tracked without a limit, the recency of the keys being taken from the index once one is set
+    fn touch(&mut self, key: &str) -> Vec<String> {
+        if self.limit.is_none() {
+            return vec![];
+        }
+
+        self.clock += 1;
+        if let Some(previous) = self.touched.insert(key.to_owned(), self.clock) {
+            self.order.remove(&previous);
+        }
+        self.order.insert(self.clock, key.to_owned());
+
+        self.evict()
+    }
+
+    fn set_limit(
+        &mut self,
+        limit: Option<usize>,
+        index: &BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+    ) -> Vec<String> {
+        if limit.is_none() {
+            self.touched.clear();
+            self.order.clear();
+        } else if self.limit.is_none() {
+            // A key was last touched by the last packet it lists
+            let mut keys = index
+                .iter()
+                .filter_map(|(key, packets)| Some((packets.last()?.get_id(), key)))
+                .collect::<Vec<(usize, &String)>>();
+            keys.sort_unstable();
+
+            for (_, key) in keys {
+                self.clock += 1;
+                self.touched.insert(key.clone(), self.clock);
+                self.order.insert(self.clock, key.clone());
+            }
+        }
+
+        self.limit = limit;
+        self.evict()
+    }
+
+    fn evict(&mut self) -> Vec<String> {
+        let mut evicted = vec![];
+
+        if let Some(limit) = self.limit {
+            while self.touched.len() > limit {
+                let oldest = *self.order.keys().next().unwrap();
+                let key = self.order.remove(&oldest).unwrap();
+                self.touched.remove(&key);
+                evicted.push(key);
+            }
+        }
+
+        self.evicted |= !evicted.is_empty();
+        evicted
+    }
+
+    fn clear(&mut self) {
+        *self = IndexUsage {
+            limit: self.limit,
+            ..Default::default()
+        };
+    }
 }
 
 /// List of all the collected packets and additional data structures to speed up the filtering process
@@ -74,8 +301,13 @@ pub struct PacketsCollection {
     pub dest_port_index: BTreeMap<String, Vec<Arc<ParsedPacket>>>,
     pub source_mac_index: BTreeMap<String, Vec<Arc<ParsedPacket>>>,
     pub dest_mac_index: BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+    /// Recency of the keys of each index, by index name
+    index_usage: HashMap<&'static str, IndexUsage>,
+    /// Packets of each flow, by flow hash
+    pub flow_index: HashMap<String, Vec<Arc<ParsedPacket>>>,
 
     pub ethernet_packets: Vec<Arc<ParsedPacket>>,
+    pub dot11_packets: Vec<Arc<ParsedPacket>>,
     pub malformed_packets: Vec<Arc<ParsedPacket>>,
     pub unknown_packets: Vec<Arc<ParsedPacket>>,
     pub tcp_packets: Vec<Arc<ParsedPacket>>,
@@ -83,11 +315,46 @@ pub struct PacketsCollection {
     pub icmp_packets: Vec<Arc<ParsedPacket>>,
     pub icmpv6_packets: Vec<Arc<ParsedPacket>>,
     pub http_packets: Vec<Arc<ParsedPacket>>,
+    pub http2_packets: Vec<Arc<ParsedPacket>>,
     pub tls_packets: Vec<Arc<ParsedPacket>>,
     pub ipv4_packets: Vec<Arc<ParsedPacket>>,
     pub ipv6_packets: Vec<Arc<ParsedPacket>>,
     pub dns_packets: Vec<Arc<ParsedPacket>>,
     pub arp_packets: Vec<Arc<ParsedPacket>>,
+    pub sip_packets: Vec<Arc<ParsedPacket>>,
+    pub dhcpv6_packets: Vec<Arc<ParsedPacket>>,
+    pub kerberos_packets: Vec<Arc<ParsedPacket>>,
+    pub radius_packets: Vec<Arc<ParsedPacket>>,
+    pub bittorrent_packets: Vec<Arc<ParsedPacket>>,
+    pub bad_ip_checksum_packets: Vec<Arc<ParsedPacket>>,
+    pub bad_tcp_checksum_packets: Vec<Arc<ParsedPacket>>,
+    pub tunnel_packets: Vec<Arc<ParsedPacket>>,
+
+    /// Raw data of the collected packets by packet ID, needed to export them
+    pub raw_packets: HashMap<usize, RawPacket>,
+    /// State of the TCP connections seen so far
+    pub connections: ConnectionTracker,
+    /// IP to MAC address associations seen so far
+    pub addresses: AddressTracker,
+    /// Flows expected in the collected packets, kept across captures along with the new flows found
+    pub baseline: Baseline,
+    /// Expert info of the TCP sequence numbers, annotating the packets as they are stored
+    pub expert: ExpertAnalyzer,
+    /// Flows seen by the flow sampling, to collect only their first packets
+    pub flow_sampler: FlowSampler,
+    /// Port to service mapping, kept across captures
+    pub services: ServiceNames,
+    /// OUI to vendor mapping, kept across captures
+    pub vendors: VendorNames,
+    /// Total amount of captured bytes
+    pub captured_bytes: usize,
+    /// Start and end of each sniffing process, in microseconds since the Unix epoch, the last one
+    /// open while running
+    pub capture_intervals: Vec<(i64, Option<i64>)>,
+    /// Storage of the raw data, kept across captures
+    frame_store: FrameStore,
+    /// Content hash of the collection, along with the numbers of packets and raw packets it was computed for
+    content_hash: Option<((usize, usize), String)>,
 }
 
 impl PacketsCollection {
@@ -101,25 +368,388 @@ impl PacketsCollection {
             dest_port_index: BTreeMap::new(),
             source_mac_index: BTreeMap::new(),
             dest_mac_index: BTreeMap::new(),
+            index_usage: INDEXES
+                .iter()
+                .map(|index| (*index, IndexUsage::default()))
+                .collect(),
+            flow_index: HashMap::new(),
 
             unknown_packets: vec![],
             ethernet_packets: vec![],
+            dot11_packets: vec![],
             malformed_packets: vec![],
             tcp_packets: vec![],
             udp_packets: vec![],
             icmp_packets: vec![],
             icmpv6_packets: vec![],
             http_packets: vec![],
+            http2_packets: vec![],
             tls_packets: vec![],
             ipv4_packets: vec![],
             ipv6_packets: vec![],
             dns_packets: vec![],
             arp_packets: vec![],
+            sip_packets: vec![],
+            dhcpv6_packets: vec![],
+            kerberos_packets: vec![],
+            radius_packets: vec![],
+            bittorrent_packets: vec![],
+            bad_ip_checksum_packets: vec![],
+            bad_tcp_checksum_packets: vec![],
+            tunnel_packets: vec![],
+
+            raw_packets: HashMap::new(),
+            connections: ConnectionTracker::new(),
+            addresses: AddressTracker::new(),
+            baseline: Baseline::new(),
+            expert: ExpertAnalyzer::new(),
+            flow_sampler: FlowSampler::new(),
+            services: ServiceNames::new(),
+            vendors: VendorNames::new(),
+            captured_bytes: 0,
+            capture_intervals: vec![],
+            frame_store: FrameStore::default(),
+            content_hash: None,
         }
     }
 
+    /// Account for the raw data of a new captured packet
+    pub fn add_raw_packet(&mut self, id: usize, link_type: u32, data: &[u8]) {
+        self.captured_bytes += data.len();
+        self.raw_packets.insert(
+            id,
+            RawPacket {
+                link_type,
+                data: Arc::new(self.frame_store.store(data)),
+            },
+        );
+    }
+
+    /// Makes the link layer payload of the packet a view of its raw data, added before
+    pub fn share_raw_packet(&self, packet: &mut ParsedPacket) {
+        if let Some(raw_packet) = self.raw_packets.get(&packet.get_id()) {
+            share_link_payload(packet, &raw_packet.data);
+        }
+    }
+
+    /// Stores the raw data of the following packets in `frame_store`, moving the one of the collected packets
+    pub fn set_frame_store(&mut self, frame_store: FrameStore) {
+        self.frame_store = frame_store;
+        for raw_packet in self.raw_packets.values_mut() {
+            raw_packet.data = Arc::new(self.frame_store.store(&raw_packet.data));
+        }
+
+        // The collected packets still point to the frames before the move
+        let packets = self
+            .packets
+            .iter()
+            .map(|packet| {
+                let mut packet = ParsedPacket::clone(packet);
+                self.share_raw_packet(&mut packet);
+                Arc::new(packet)
+            })
+            .collect();
+        self.reindex(packets);
+        info!(
+            "Moved the raw data of {} packets to the {} storage",
+            self.raw_packets.len(),
+            self.frame_store.get_storage()
+        );
+    }
+
+    /// Raw data of the packets, to write them once the collection is released
+    pub fn get_raw_packets(&self, packets: &[Arc<ParsedPacket>]) -> HashMap<usize, RawPacket> {
+        packets
+            .iter()
+            .filter_map(|packet| {
+                let raw_packet = self.raw_packets.get(&packet.get_id())?;
+                Some((packet.get_id(), raw_packet.clone()))
+            })
+            .collect()
+    }
+
+    /// Content hash of the collection, see [`ContentSnapshot::get_hash`], computed while holding it
+    #[cfg(test)]
+    pub fn get_content_hash(&self) -> String {
+        self.get_cached_content_hash()
+            .unwrap_or_else(|| self.get_content_snapshot().get_hash())
+    }
+
+    /// Content hash computed by `cache_content_hash` for the collected packets, if they didn't change since
+    pub fn get_cached_content_hash(&self) -> Option<String> {
+        match &self.content_hash {
+            Some((key, hash)) if *key == self.get_content_key() => Some(hash.clone()),
+            _ => None,
+        }
+    }
+
+    /// Collected packets with their raw data, to hash them once the collection is released
+    pub fn get_content_snapshot(&self) -> ContentSnapshot {
+        ContentSnapshot {
+            key: self.get_content_key(),
+            packets: self.packets.clone(),
+            raw_packets: self.get_raw_packets(&self.packets),
+        }
+    }
+
+    /// Keeps the hash of a snapshot until packets are collected or cleared
+    pub fn cache_content_hash(&mut self, snapshot: &ContentSnapshot, hash: String) {
+        self.content_hash = Some((snapshot.key, hash));
+    }
+
+    /// Packets are only appended until the collection is cleared, so their number identifies its content
+    fn get_content_key(&self) -> (usize, usize) {
+        (self.packets.len(), self.raw_packets.len())
+    }
+
+    /// Insert a new packet, updating all the indexes and protocol lists
+    pub fn insert(&mut self, parsed_packet: Arc<ParsedPacket>) {
+        // Index by Flow hash
+        if let Some(flow_hash) = parsed_packet.get_flow_hash() {
+            self.flow_index
+                .entry(flow_hash.clone())
+                .or_default()
+                .push(parsed_packet.clone());
+        }
+
+        // Index by Source IP
+        if let Some(ip_address) = get_source_ip(&parsed_packet) {
+            self.index_packet(FilterNamesValues::SRC_IP, ip_address, &parsed_packet);
+        }
+
+        // Index by Dest IP
+        if let Some(ip_address) = get_dest_ip(&parsed_packet) {
+            self.index_packet(FilterNamesValues::DST_IP, ip_address, &parsed_packet);
+        }
+
+        // Index by Source MAC
+        if let Some(mac_address) = get_source_mac(&parsed_packet) {
+            self.index_packet(FilterNamesValues::SRC_MAC, mac_address, &parsed_packet);
+        }
+
+        // Index by Dest MAC
+        if let Some(mac_address) = get_dest_mac(&parsed_packet) {
+            self.index_packet(FilterNamesValues::DST_MAC, mac_address, &parsed_packet);
+        }
+
+        // Index by Source Port
+        if let Some(port) = get_source_port(&parsed_packet) {
+            self.index_packet(FilterNamesValues::SRC_PORT, port, &parsed_packet);
+        }
+
+        // Index by Dest Port
+        if let Some(port) = get_dest_port(&parsed_packet) {
+            self.index_packet(FilterNamesValues::DST_PORT, port, &parsed_packet);
+        }
+
+        if contains_ethernet(&parsed_packet) {
+            self.ethernet_packets.push(parsed_packet.clone());
+        }
+
+        if contains_dot11(&parsed_packet) {
+            self.dot11_packets.push(parsed_packet.clone());
+        }
+
+        if contains_malformed(&parsed_packet) {
+            self.malformed_packets.push(parsed_packet.clone());
+        }
+
+        if contains_unknokn(&parsed_packet) {
+            self.unknown_packets.push(parsed_packet.clone());
+        }
+
+        if contains_tcp(&parsed_packet) {
+            self.tcp_packets.push(parsed_packet.clone());
+        }
+
+        if contains_udp(&parsed_packet) {
+            self.udp_packets.push(parsed_packet.clone());
+        }
+
+        if contains_icmp(&parsed_packet) {
+            self.icmp_packets.push(parsed_packet.clone());
+        }
+
+        if contains_icmp6(&parsed_packet) {
+            self.icmpv6_packets.push(parsed_packet.clone());
+        }
+
+        if contains_http(&parsed_packet) {
+            self.http_packets.push(parsed_packet.clone());
+        }
+
+        if contains_http2(&parsed_packet) {
+            self.http2_packets.push(parsed_packet.clone());
+        }
+
+        if contains_tls(&parsed_packet) {
+            self.tls_packets.push(parsed_packet.clone());
+        }
+
+        if contains_ipv4(&parsed_packet) {
+            self.ipv4_packets.push(parsed_packet.clone());
+        }
+
+        if contains_ipv6(&parsed_packet) {
+            self.ipv6_packets.push(parsed_packet.clone());
+        }
+
+        if contains_arp(&parsed_packet) {
+            self.arp_packets.push(parsed_packet.clone());
+        }
+
+        if contains_dns(&parsed_packet) {
+            self.dns_packets.push(parsed_packet.clone());
+        }
+
+        if contains_sip(&parsed_packet) {
+            self.sip_packets.push(parsed_packet.clone());
+        }
+
+        if contains_dhcpv6(&parsed_packet) {
+            self.dhcpv6_packets.push(parsed_packet.clone());
+        }
+
+        if contains_kerberos(&parsed_packet) {
+            self.kerberos_packets.push(parsed_packet.clone());
+        }
+
+        if contains_radius(&parsed_packet) {
+            self.radius_packets.push(parsed_packet.clone());
+        }
+
+        if contains_bittorrent(&parsed_packet) {
+            self.bittorrent_packets.push(parsed_packet.clone());
+        }
+
+        if has_bad_ip_checksum(&parsed_packet) {
+            self.bad_ip_checksum_packets.push(parsed_packet.clone());
+        }
+
+        if has_bad_tcp_checksum(&parsed_packet) {
+            self.bad_tcp_checksum_packets.push(parsed_packet.clone());
+        }
+
+        if contains_tunnel(&parsed_packet) {
+            self.tunnel_packets.push(parsed_packet.clone());
+        }
+
+        self.connections.update(&parsed_packet);
+        self.addresses.update(&parsed_packet);
+        self.baseline.update(&parsed_packet);
+
+        self.packets.push(parsed_packet);
+    }
+
+    fn get_index_mut(&mut self, index_name: &str) -> &mut BTreeMap<String, Vec<Arc<ParsedPacket>>> {
+        match index_name {
+            FilterNamesValues::SRC_IP => &mut self.source_ip_index,
+            FilterNamesValues::DST_IP => &mut self.dest_ip_index,
+            FilterNamesValues::SRC_MAC => &mut self.source_mac_index,
+            FilterNamesValues::DST_MAC => &mut self.dest_mac_index,
+            FilterNamesValues::SRC_PORT => &mut self.source_port_index,
+            _ => &mut self.dest_port_index,
+        }
+    }
+
+    fn index_packet(&mut self, index_name: &'static str, key: String, packet: &Arc<ParsedPacket>) {
+        let evicted = self.index_usage.get_mut(index_name).unwrap().touch(&key);
+
+        let index = self.get_index_mut(index_name);
+        index
+            .entry(key)
+            .and_modify(|packets| packets.push(Arc::clone(packet)))
+            .or_insert(vec![Arc::clone(packet)]);
+
+        for key in evicted {
+            index.remove(&key);
+        }
+    }
+
+    /// Bounds the number of keys of an index, evicting the least recently touched ones
+    pub fn set_index_limit(
+        &mut self,
+        index_name: &str,
+        limit: Option<usize>,
+    ) -> Result<(), SniffingError> {
+        let (index_name, mut usage) = match self.index_usage.remove_entry(index_name) {
+            Some(entry) => entry,
+            None => {
+                return Err(SniffingError::UnknownFilterType(format!(
+                    "Unknown index: {}",
+                    index_name
+                )))
+            }
+        };
+
+        let evicted = usage.set_limit(limit, self.get_index_mut(index_name));
+        self.index_usage.insert(index_name, usage);
+
+        let index = self.get_index_mut(index_name);
+        for key in evicted {
+            index.remove(&key);
+        }
+
+        Ok(())
+    }
+
+    /// Limits of the number of keys of each index, by index name
+    pub fn get_index_limits(&self) -> BTreeMap<String, Option<usize>> {
+        self.index_usage
+            .iter()
+            .map(|(name, usage)| (name.to_string(), usage.limit))
+            .collect()
+    }
+
+    /// Whether the indexes used by the filter list all the collected packets
+    pub fn is_index_complete(&self, filter_name: &str) -> bool {
+        let indexes = if filter_name == FilterNamesValues::PORT {
+            vec![FilterNamesValues::SRC_PORT, FilterNamesValues::DST_PORT]
+        } else {
+            vec![filter_name]
+        };
+
+        !indexes
+            .iter()
+            .any(|index| matches!(self.index_usage.get(index), Some(usage) if usage.evicted))
+    }
+
     /// Empty the data structures
     pub fn clear(&mut self) {
+        self.clear_indexes();
+
+        self.raw_packets.clear();
+        self.frame_store.clear();
+        self.connections.clear();
+        self.addresses.clear();
+        self.baseline.clear();
+        self.expert.clear();
+        self.flow_sampler.clear();
+        self.captured_bytes = 0;
+        self.capture_intervals.clear();
+        self.content_hash = None;
+    }
+
+    /// Replace the collected packets with new representations of the same raw packets (e.g. parsed again),
+    /// rebuilding the indexes, protocol lists and trackers
+    ///
+    /// The expert info of the packets isn't analyzed again. Conflicts and new flows found again aren't
+    /// notified twice
+    pub fn reindex(&mut self, packets: Vec<Arc<ParsedPacket>>) {
+        self.clear_indexes();
+        self.connections.clear();
+        self.addresses.clear();
+        self.baseline.clear();
+
+        for packet in packets {
+            self.insert(packet);
+        }
+
+        while self.addresses.take_conflict().is_some() {}
+        while self.baseline.take_new_flow().is_some() {}
+    }
+
+    fn clear_indexes(&mut self) {
         self.packets.clear();
 
         self.source_ip_index.clear();
@@ -128,8 +758,11 @@ impl PacketsCollection {
         self.dest_port_index.clear();
         self.source_mac_index.clear();
         self.dest_mac_index.clear();
+        self.index_usage.values_mut().for_each(IndexUsage::clear);
+        self.flow_index.clear();
 
         self.ethernet_packets.clear();
+        self.dot11_packets.clear();
         self.malformed_packets.clear();
         self.unknown_packets.clear();
         self.tcp_packets.clear();
@@ -137,11 +770,20 @@ impl PacketsCollection {
         self.icmp_packets.clear();
         self.icmpv6_packets.clear();
         self.http_packets.clear();
+        self.http2_packets.clear();
         self.tls_packets.clear();
         self.ipv4_packets.clear();
         self.ipv6_packets.clear();
         self.dns_packets.clear();
         self.arp_packets.clear();
+        self.sip_packets.clear();
+        self.dhcpv6_packets.clear();
+        self.kerberos_packets.clear();
+        self.radius_packets.clear();
+        self.bittorrent_packets.clear();
+        self.bad_ip_checksum_packets.clear();
+        self.bad_tcp_checksum_packets.clear();
+        self.tunnel_packets.clear();
     }
 }
 
@@ -159,10 +801,23 @@ pub fn get_packets<'a>(
     end: usize,
     filters_type: Vec<&'a str>,
     filters_value: Vec<(&'a str, &'a str)>,
+    reference_ip: Option<String>,
     state: tauri::State<SniffingState>,
 ) -> Result<Vec<ParsedPacket>, SniffingError> {
+    let reference_ip = match reference_ip.map(|ip| ip.parse::<IpAddr>()) {
+        Some(Ok(ip)) => Some(ip),
+        Some(Err(e)) => {
+            warn!("Invalid reference IP address: {}", e);
+            return Err(SniffingError::InvalidIpAddress(format!(
+                "Invalid reference IP address: {}",
+                e
+            )));
+        }
+        None => None,
+    };
+
     let mut packets_collection = state.packets.lock().unwrap();
-    let result = get_packets_internal(
+    let mut result = get_packets_internal(
         start,
         end,
         &filters_type,
@@ -170,6 +825,22 @@ pub fn get_packets<'a>(
         &mut *packets_collection,
     );
 
+    if let Ok(packets) = &mut result {
+        tag_services(packets, &packets_collection.services);
+        tag_vendors(packets, &packets_collection.vendors);
+        tag_bookmarks(packets, &state.bookmarks.lock().unwrap());
+        tag_conversation_colors(packets);
+        tag_flow_times(packets, &packets_collection.flow_index);
+    }
+
+    if let (Ok(packets), Some(reference_ip)) = (&mut result, reference_ip) {
+        // Packets loaded from pcapng files keep the direction recorded by the capture tool
+        let untagged = packets
+            .iter_mut()
+            .filter(|packet| packet.get_direction().is_none());
+        tag_directions(untagged, reference_ip);
+    }
+
     match &result {
         Ok(packets) => {
             info!(
@@ -188,6 +859,167 @@ pub fn get_packets<'a>(
     result
 }
 
+/// Bounds the number of distinct keys of an index (`src_ip`, `dst_ip`, `src_mac`, `dst_mac`, `src_port`, `dst_port`),
+/// `None` to remove the limit
+///
+/// When the limit is exceeded the least recently touched keys are dropped from the index, and filtering on it
+/// falls back to scanning the collected packets
+#[tauri::command]
+pub fn set_index_limit(
+    state: tauri::State<SniffingState>,
+    index: String,
+    limit: Option<usize>,
+) -> Result<(), SniffingError> {
+    info!("Index limit of {}: {:?}", index, limit);
+    state.packets.lock().unwrap().set_index_limit(&index, limit)
+}
+
+/// Packets collected after a cursor, with the cursor to use for the next request
+#[derive(Serialize, Debug)]
+pub struct PacketsDelta {
+    pub packets: Vec<ParsedPacket>,
+    /// ID of the last returned packet, the same cursor of the request when nothing new was collected
+    pub cursor: Option<usize>,
+}
+
+/// Returns the packets with an ID greater than `cursor` (all of them if not provided)
+#[tauri::command]
+pub fn get_packets_since(
+    cursor: Option<usize>,
+    state: tauri::State<SniffingState>,
+) -> Result<PacketsDelta, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+    let mut delta = get_packets_since_internal(cursor, &packets_collection);
+    tag_services(&mut delta.packets, &packets_collection.services);
+    tag_vendors(&mut delta.packets, &packets_collection.vendors);
+    tag_bookmarks(&mut delta.packets, &state.bookmarks.lock().unwrap());
+    tag_conversation_colors(&mut delta.packets);
+    tag_flow_times(&mut delta.packets, &packets_collection.flow_index);
+
+    debug!(
+        "Received getPacketsSince request (cursor: {:?}); Len: {}",
+        cursor,
+        delta.packets.len()
+    );
+
+    Ok(delta)
+}
+
+fn get_packets_since_internal(
+    cursor: Option<usize>,
+    packets_collection: &PacketsCollection,
+) -> PacketsDelta {
+    // Packets are collected with increasing IDs
+    let start = match cursor {
+        Some(cursor) => packets_collection
+            .packets
+            .partition_point(|packet| packet.get_id() <= cursor),
+        None => 0,
+    };
+
+    let packets: Vec<ParsedPacket> = packets_collection.packets[start..]
+        .iter()
+        .map(|packet| ParsedPacket::clone(packet))
+        .collect();
+
+    PacketsDelta {
+        cursor: packets.last().map(|packet| packet.get_id()).or(cursor),
+        packets,
+    }
+}
+
+/// Evenly spaced subset of the (filtered) packets
+#[derive(Serialize, Debug)]
+pub struct PacketsSample {
+    pub packets: Vec<ParsedPacket>,
+    /// Distance between two sampled packets: every `stride`-th packet is returned
+    pub stride: usize,
+    /// Number of packets the sample is taken from
+    pub total: usize,
+}
+
+/// Returns about `n` packets, taking every k-th one of those matching the filters, for a quick overview
+/// of the capture without transferring all of it
+#[tauri::command]
+pub fn sample_packets<'a>(
+    n: usize,
+    filters_type: Vec<&'a str>,
+    filters_value: Vec<(&'a str, &'a str)>,
+    state: tauri::State<SniffingState>,
+) -> Result<PacketsSample, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+    let mut sample =
+        sample_packets_internal(n, &filters_type, &filters_value, &mut packets_collection)?;
+    tag_services(&mut sample.packets, &packets_collection.services);
+    tag_vendors(&mut sample.packets, &packets_collection.vendors);
+    tag_bookmarks(&mut sample.packets, &state.bookmarks.lock().unwrap());
+    tag_conversation_colors(&mut sample.packets);
+    tag_flow_times(&mut sample.packets, &packets_collection.flow_index);
+
+    info!(
+        "Received samplePackets request ({}); Len: {}, Stride: {}, Type Filters: {:?} Strong Filters: {:?}",
+        n, sample.packets.len(), sample.stride, filters_type, filters_value
+    );
+
+    Ok(sample)
+}
+
+fn sample_packets_internal<'a>(
+    n: usize,
+    filters_type: &Vec<&'a str>,
+    filters_value: &Vec<(&'a str, &'a str)>,
+    packets_collection: &mut PacketsCollection,
+) -> Result<PacketsSample, SniffingError> {
+    let filtered_packets;
+    let candidates = if filters_type.is_empty() && filters_value.is_empty() {
+        &packets_collection.packets
+    } else {
+        filtered_packets =
+            get_filtered_packets(usize::MAX, filters_type, filters_value, packets_collection)?;
+        &filtered_packets
+    };
+
+    let total = candidates.len();
+    if n == 0 || total == 0 {
+        return Ok(PacketsSample {
+            packets: vec![],
+            stride: 1,
+            total,
+        });
+    }
+
+    let stride = (total + n - 1) / n;
+    let packets = candidates
+        .iter()
+        .step_by(stride)
+        .map(|packet| ParsedPacket::clone(packet))
+        .collect();
+
+    Ok(PacketsSample {
+        packets,
+        stride,
+        total,
+    })
+}
+
+/// Tag each packet as incoming to, outgoing from or unrelated to the reference IP address
+fn tag_directions<'a>(packets: impl Iterator<Item = &'a mut ParsedPacket>, reference_ip: IpAddr) {
+    let is_reference =
+        |ip: Option<String>| ip.and_then(|ip| ip.parse::<IpAddr>().ok()) == Some(reference_ip);
+
+    for packet in packets {
+        let direction = if is_reference(get_dest_ip(packet)) {
+            PacketDirections::IN
+        } else if is_reference(get_source_ip(packet)) {
+            PacketDirections::OUT
+        } else {
+            PacketDirections::OTHER
+        };
+
+        packet.set_direction(Some(direction.to_string()));
+    }
+}
+
 fn get_packets_internal<'a>(
     start: usize,
     end: usize,
@@ -195,67 +1027,81 @@ fn get_packets_internal<'a>(
     filters_value: &Vec<(&'a str, &'a str)>,
     packets_collection: &mut PacketsCollection,
 ) -> Result<Vec<ParsedPacket>, SniffingError> {
-    if !filters_type.is_empty() || !filters_value.is_empty() {
+    if filters_type.is_empty() && filters_value.is_empty() {
+        return Ok(bound_and_map(start, end, &packets_collection.packets));
+    }
+
+    // If just 1 Type filter is enabled, bound its index directly
+    if filters_value.is_empty() && filters_type.len() == 1 {
+        let single_filter =
+            get_bounded_type_filter_index_iter(start, end, filters_type[0], packets_collection)?;
+
+        return Ok(single_filter.map(|x| ParsedPacket::clone(&*x)).collect());
+    }
+
+    let filtered_packets =
+        get_filtered_packets(end, filters_type, filters_value, packets_collection)?;
+    Ok(bound_and_map(start, end, &filtered_packets))
+}
+
+/// Returns the packets matching the filters, up to the `end`-th one
+fn get_filtered_packets<'a>(
+    end: usize,
+    filters_type: &Vec<&'a str>,
+    filters_value: &Vec<(&'a str, &'a str)>,
+    packets_collection: &mut PacketsCollection,
+) -> Result<Vec<Arc<ParsedPacket>>, SniffingError> {
+    // If Strong filters are enabled
+    if !filters_value.is_empty() {
         // Apply all Strong Filters
         let mut filtered_packets =
             apply_all_strong_filters(end, &filters_value, packets_collection)?;
 
-        // If Strong filters are enabled
-        if !filters_value.is_empty() {
-            // If Type filters are enabled
-            if !filters_type.is_empty() {
-                // Merge filter results just iterating
-                filtered_packets.retain(|packet| {
-                    let mut contain = false;
+        // If Type filters are enabled
+        if !filters_type.is_empty() {
+            // Merge filter results just iterating
+            filtered_packets.retain(|packet| {
+                let mut contain = false;
 
-                    for filter in filters_type {
-                        contain = apply_layer_type_filter(filter, &packet).unwrap();
+                for filter in filters_type {
+                    contain = apply_layer_type_filter(filter, &packet).unwrap();
 
-                        if contain {
-                            break;
-                        }
+                    if contain {
+                        break;
                     }
+                }
 
-                    contain
-                });
-            }
+                contain
+            });
+        }
 
-            return Ok(bound_and_map(start, end, &filtered_packets));
-        } else {
-            // If Type filters are disabled
-            if filters_type.is_empty() {
-                return Ok(Vec::new());
-            }
+        return Ok(filtered_packets);
+    }
 
-            // If just 1 Type filter is enabled
-            if filters_type.len() == 1 {
-                let single_filter = get_bounded_type_filter_index_iter(
-                    start,
-                    end,
-                    filters_type[0],
-                    &packets_collection,
-                )?;
-
-                return Ok(single_filter.map(|x| ParsedPacket::clone(&*x)).collect());
-            } else {
-                // If more than 1 Type filters are enabled
-                let mut filters_array = vec![];
-
-                for f in filters_type {
-                    let mut iter =
-                        get_bounded_type_filter_index_iter(0, end, f, &packets_collection)?;
-
-                    let value = iter.next();
-                    filters_array.push((iter, value));
-                }
+    // If Type filters are disabled
+    if filters_type.is_empty() {
+        return Ok(Vec::new());
+    }
 
-                let merged = merge_filter_type_arrays(&mut filters_array);
-                return Ok(bound_and_map(start, end, &merged));
-            }
-        }
-    } else {
-        return Ok(bound_and_map(start, end, &packets_collection.packets));
+    // If just 1 Type filter is enabled
+    if filters_type.len() == 1 {
+        let single_filter =
+            get_bounded_type_filter_index_iter(0, end, filters_type[0], packets_collection)?;
+
+        return Ok(single_filter.cloned().collect());
+    }
+
+    // If more than 1 Type filters are enabled
+    let mut filters_array = vec![];
+
+    for f in filters_type {
+        let mut iter = get_bounded_type_filter_index_iter(0, end, f, packets_collection)?;
+
+        let value = iter.next();
+        filters_array.push((iter, value));
     }
+
+    Ok(merge_filter_type_arrays(&mut filters_array))
 }
 
 fn bound_and_map(start: usize, end: usize, packets: &Vec<Arc<ParsedPacket>>) -> Vec<ParsedPacket> {
@@ -281,6 +1127,9 @@ fn get_bounded_type_filter_index_iter<'a>(
         FilterNamesValues::ETHERNET => {
             Ok(get_slice(&packets_collection.ethernet_packets, start, end).iter())
         }
+        FilterNamesValues::DOT11 => {
+            Ok(get_slice(&packets_collection.dot11_packets, start, end).iter())
+        }
         FilterNamesValues::IPV4 => {
             Ok(get_slice(&packets_collection.ipv4_packets, start, end).iter())
         }
@@ -299,8 +1148,33 @@ fn get_bounded_type_filter_index_iter<'a>(
         FilterNamesValues::HTTP => {
             Ok(get_slice(&packets_collection.http_packets, start, end).iter())
         }
+        FilterNamesValues::HTTP2 => {
+            Ok(get_slice(&packets_collection.http2_packets, start, end).iter())
+        }
         FilterNamesValues::TLS => Ok(get_slice(&packets_collection.tls_packets, start, end).iter()),
         FilterNamesValues::DNS => Ok(get_slice(&packets_collection.dns_packets, start, end).iter()),
+        FilterNamesValues::SIP => Ok(get_slice(&packets_collection.sip_packets, start, end).iter()),
+        FilterNamesValues::DHCPV6 => {
+            Ok(get_slice(&packets_collection.dhcpv6_packets, start, end).iter())
+        }
+        FilterNamesValues::KERBEROS => {
+            Ok(get_slice(&packets_collection.kerberos_packets, start, end).iter())
+        }
+        FilterNamesValues::RADIUS => {
+            Ok(get_slice(&packets_collection.radius_packets, start, end).iter())
+        }
+        FilterNamesValues::BITTORRENT => {
+            Ok(get_slice(&packets_collection.bittorrent_packets, start, end).iter())
+        }
+        FilterNamesValues::IP_CHECKSUM_BAD => {
+            Ok(get_slice(&packets_collection.bad_ip_checksum_packets, start, end).iter())
+        }
+        FilterNamesValues::TCP_CHECKSUM_BAD => {
+            Ok(get_slice(&packets_collection.bad_tcp_checksum_packets, start, end).iter())
+        }
+        FilterNamesValues::TUNNEL => {
+            Ok(get_slice(&packets_collection.tunnel_packets, start, end).iter())
+        }
         _ => {
             warn!("Unknown filter type: {}", index_name);
             Err(SniffingError::UnknownFilterType(format!(
@@ -311,7 +1185,7 @@ fn get_bounded_type_filter_index_iter<'a>(
     }
 }
 
-fn apply_all_strong_filters<'a>(
+pub(crate) fn apply_all_strong_filters<'a>(
     end: usize,
     filters_value: &Vec<(&'a str, &'a str)>,
     packets_collection: &mut PacketsCollection,
@@ -320,6 +1194,14 @@ fn apply_all_strong_filters<'a>(
 
     let mut filtered_packets = vec![];
 
+    // Scan all the packets when the index of the first filter lost some keys
+    if let Some((name, _)) = filters_value.first() {
+        if !packets_collection.is_index_complete(name) {
+            filtered_packets = packets_collection.packets.clone();
+            use_index = false;
+        }
+    }
+
     for (name, value) in filters_value {
         apply_specific_filter(
             name,
@@ -382,6 +1264,7 @@ pub fn apply_layer_type_filter(
         FilterNamesValues::UNKNOWN => Ok(contains_unknokn(packet)),
         FilterNamesValues::MALFORMED => Ok(contains_malformed(packet)),
         FilterNamesValues::ETHERNET => Ok(contains_ethernet(packet)),
+        FilterNamesValues::DOT11 => Ok(contains_dot11(packet)),
         FilterNamesValues::IPV4 => Ok(contains_ipv4(packet)),
         FilterNamesValues::IPV6 => Ok(contains_ipv6(packet)),
         FilterNamesValues::ARP => Ok(contains_arp(packet)),
@@ -390,8 +1273,17 @@ pub fn apply_layer_type_filter(
         FilterNamesValues::ICMP => Ok(contains_icmp(packet)),
         FilterNamesValues::ICMPV6 => Ok(contains_icmp6(packet)),
         FilterNamesValues::HTTP => Ok(contains_http(packet)),
+        FilterNamesValues::HTTP2 => Ok(contains_http2(packet)),
         FilterNamesValues::TLS => Ok(contains_tls(packet)),
         FilterNamesValues::DNS => Ok(contains_dns(packet)),
+        FilterNamesValues::SIP => Ok(contains_sip(packet)),
+        FilterNamesValues::DHCPV6 => Ok(contains_dhcpv6(packet)),
+        FilterNamesValues::KERBEROS => Ok(contains_kerberos(packet)),
+        FilterNamesValues::RADIUS => Ok(contains_radius(packet)),
+        FilterNamesValues::BITTORRENT => Ok(contains_bittorrent(packet)),
+        FilterNamesValues::IP_CHECKSUM_BAD => Ok(has_bad_ip_checksum(packet)),
+        FilterNamesValues::TCP_CHECKSUM_BAD => Ok(has_bad_tcp_checksum(packet)),
+        FilterNamesValues::TUNNEL => Ok(contains_tunnel(packet)),
 
         _ => {
             warn!("Unknown filter type: {}", name);
@@ -453,9 +1345,43 @@ pub fn apply_specific_filter<'a>(
             );
             Ok(())
         }
-        FilterNamesValues::SRC_PORT => {
-            filter_by_src_port(
-                &packets_collection.source_port_index,
+        FilterNamesValues::SRC_PORT => filter_by_src_port(
+            &packets_collection.source_port_index,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::DST_PORT => filter_by_dst_port(
+            &packets_collection.dest_port_index,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::PORT => filter_by_port(
+            &packets_collection.source_port_index,
+            &packets_collection.dest_port_index,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::INNER_SRC_IP => {
+            filter_by_inner_ip(
+                &packets_collection.tunnel_packets,
+                get_inner_source_ip,
+                end,
+                value,
+                is_index_used,
+                filtered_packets,
+            );
+            Ok(())
+        }
+        FilterNamesValues::INNER_DST_IP => {
+            filter_by_inner_ip(
+                &packets_collection.tunnel_packets,
+                get_inner_dest_ip,
                 end,
                 value,
                 is_index_used,
@@ -463,9 +1389,48 @@ pub fn apply_specific_filter<'a>(
             );
             Ok(())
         }
-        FilterNamesValues::DST_PORT => {
-            filter_by_dst_port(
-                &packets_collection.dest_port_index,
+        FilterNamesValues::TCP_MSS => filter_by_tcp_option(
+            &packets_collection.tcp_packets,
+            get_tcp_mss,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::TCP_WINDOW_SCALE => filter_by_tcp_option(
+            &packets_collection.tcp_packets,
+            |packet| get_tcp_window_scale(packet).map(u16::from),
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::SERVICE => filter_by_service(
+            &packets_collection.packets,
+            &packets_collection.services,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::EXPERT => filter_by_expert_severity(
+            &packets_collection.packets,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::VENDOR => filter_by_vendor(
+            &packets_collection.packets,
+            &packets_collection.vendors,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::FLOW => {
+            filter_by_flow(
+                &packets_collection.flow_index,
                 end,
                 value,
                 is_index_used,
@@ -473,6 +1438,51 @@ pub fn apply_specific_filter<'a>(
             );
             Ok(())
         }
+        FilterNamesValues::CAST => filter_by_cast_type(
+            &packets_collection.packets,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::INTERFACE => filter_by_interface(
+            &packets_collection.packets,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::ENTROPY => filter_by_entropy(
+            &packets_collection.packets,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::FLOW_TIME_RELATIVE => filter_by_flow_time(
+            &packets_collection.packets,
+            &packets_collection.flow_index,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::JA3 => filter_by_ja3(
+            &packets_collection.tls_packets,
+            get_ja3_hash,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
+        FilterNamesValues::JA3S => filter_by_ja3(
+            &packets_collection.tls_packets,
+            get_ja3s_hash,
+            end,
+            value,
+            is_index_used,
+            filtered_packets,
+        ),
         _ => {
             warn!("Unknown filter type: {}", name);
             Err(SniffingError::UnknownFilterType(format!(
@@ -557,6 +1567,57 @@ pub fn filter_by_dst_ip<'a>(
     }
 }
 
+/// Keep the packets matching `predicate`, among all the ones of the index when used, or among the ones
+/// filtered so far otherwise, up to `end`
+fn filter_candidates(
+    index: &[Arc<ParsedPacket>],
+    end: usize,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+    predicate: impl Fn(&ParsedPacket) -> bool,
+) {
+    if filtered_packets.is_empty() && !is_index_used {
+        return;
+    }
+
+    let candidates = if is_index_used {
+        index
+    } else {
+        &*filtered_packets
+    };
+
+    let mut counter = 0;
+    *filtered_packets = candidates
+        .iter()
+        .filter(|p| predicate(p))
+        .map(Arc::clone)
+        .take_while(|_| {
+            counter += 1;
+            is_index_used || counter <= end
+        })
+        .collect();
+}
+
+/// Filter collected packets by an IP address of the tunneled IPv6 packet
+pub fn filter_by_inner_ip<'a>(
+    tunnel_packets: &'a [Arc<ParsedPacket>],
+    get_inner_ip: fn(&ParsedPacket) -> Option<String>,
+    end: usize,
+    ip_address: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) {
+    // Normalize the address representation (e.g. "2001:0db8::0001" -> "2001:db8::1")
+    let ip_address = match ip_address.parse::<Ipv6Addr>() {
+        Ok(ip_address) => ip_address.to_string(),
+        Err(_) => ip_address.to_owned(),
+    };
+
+    filter_candidates(tunnel_packets, end, is_index_used, filtered_packets, |p| {
+        get_inner_ip(p).as_ref() == Some(&ip_address)
+    });
+}
+
 /// Filter collected packets by source MAC address
 pub fn filter_by_src_mac<'a>(
     index: &'a BTreeMap<String, Vec<Arc<ParsedPacket>>>,
@@ -638,44 +1699,259 @@ pub fn filter_by_src_port<'a>(
     src_port: &'a str,
     is_index_used: bool,
     filtered_packets: &mut Vec<Arc<ParsedPacket>>,
-) {
+) -> Result<(), SniffingError> {
+    filter_by_ports(
+        &[(index, get_source_port)],
+        end,
+        src_port,
+        is_index_used,
+        filtered_packets,
+    )
+}
+
+/// Filter collected packets by destination Port
+pub fn filter_by_dst_port<'a>(
+    index: &'a BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+    end: usize,
+    dst_port: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    filter_by_ports(
+        &[(index, get_dest_port)],
+        end,
+        dst_port,
+        is_index_used,
+        filtered_packets,
+    )
+}
+
+/// Filter collected packets by source or destination Port
+pub fn filter_by_port<'a>(
+    source_index: &'a BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+    dest_index: &'a BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+    end: usize,
+    port: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    filter_by_ports(
+        &[(source_index, get_source_port), (dest_index, get_dest_port)],
+        end,
+        port,
+        is_index_used,
+        filtered_packets,
+    )
+}
+
+type PortIndex<'a> = (
+    &'a BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+    fn(&ParsedPacket) -> Option<String>,
+);
+
+/// Filter collected packets by a set of ports and port ranges (e.g. `80,443,8000-8100`)
+///
+/// A packet matches if the port of any of the given directions is in the set
+fn filter_by_ports(
+    directions: &[PortIndex],
+    end: usize,
+    ports: &str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let ranges = parse_ranges("port", ports)?;
+    let contains = |port: Option<String>| {
+        port.and_then(|port| port.parse::<u16>().ok())
+            .map_or(false, |port| {
+                ranges.iter().any(|range| range.contains(&port))
+            })
+    };
+
     if filtered_packets.is_empty() && !is_index_used {
-        return;
+        return Ok(());
     }
 
     if !is_index_used {
         let mut counter = 0;
         *filtered_packets = filtered_packets
             .iter()
-            .filter_map(|p| {
-                let source_port = get_source_port(p);
-                if source_port.is_some() && source_port.unwrap() == src_port {
-                    return Some(Arc::clone(p));
-                }
-
-                return None;
-            })
+            .filter(|p| directions.iter().any(|(_, get_port)| contains(get_port(p))))
+            .map(Arc::clone)
             .take_while(|_| {
                 counter += 1;
                 counter <= end
             })
             .collect();
     } else {
-        match index.get(&src_port.to_owned()) {
-            Some(values) => filtered_packets.extend_from_slice(values),
-            _ => (),
+        // Select the index entries of the matching ports, without scanning the packets
+        let mut selected = vec![];
+        for (index, _) in directions {
+            if let [range] = ranges.as_slice() {
+                if range.start() == range.end() {
+                    selected.extend(index.get(&range.start().to_string()));
+                    continue;
+                }
+            }
+
+            selected.extend(
+                index
+                    .iter()
+                    .filter(|(port, _)| contains(Some(port.to_string())))
+                    .map(|(_, values)| values),
+            );
+        }
+
+        if let [values] = selected.as_slice() {
+            filtered_packets.extend_from_slice(values);
+        } else {
+            let mut merged = selected.into_iter().flatten().cloned().collect::<Vec<_>>();
+            merged.sort_by_key(|p| p.get_id());
+            merged.dedup_by_key(|p| p.get_id());
+            filtered_packets.extend(merged);
         }
     }
+
+    Ok(())
 }
 
-/// Filter collected packets by destination Port
-pub fn filter_by_dst_port<'a>(
-    index: &'a BTreeMap<String, Vec<Arc<ParsedPacket>>>,
+/// Filter collected packets by the value of a TCP option (e.g. `1460` or `1200-1460`)
+pub fn filter_by_tcp_option<'a>(
+    tcp_packets: &'a [Arc<ParsedPacket>],
+    get_option: fn(&ParsedPacket) -> Option<u16>,
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let ranges = parse_ranges("TCP option", value)?;
+
+    filter_candidates(tcp_packets, end, is_index_used, filtered_packets, |p| {
+        get_option(p).map_or(false, |option| ranges.iter().any(|r| r.contains(&option)))
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by a set of service names (e.g. `https,dns`)
+pub fn filter_by_service<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    services: &ServiceNames,
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let names = value
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<String>>();
+
+    if names.is_empty() {
+        warn!("Invalid service filter: {}", value);
+        return Err(SniffingError::InvalidFilterValue(format!(
+            "Invalid service filter: {}",
+            value
+        )));
+    }
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        services
+            .get_packet_service(p)
+            .map_or(false, |service| names.contains(&service.to_lowercase()))
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by the lowest severity of their expert info (e.g. `warning`)
+pub fn filter_by_expert_severity<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let level = match ExpertSeverities::get_level(&value.trim().to_lowercase()) {
+        Some(level) => level,
+        None => {
+            warn!("Invalid expert severity filter: {}", value);
+            return Err(SniffingError::InvalidFilterValue(format!(
+                "Invalid expert severity filter: {}",
+                value
+            )));
+        }
+    };
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        p.get_expert_info()
+            .iter()
+            .any(|info| ExpertSeverities::get_level(&info.severity) >= Some(level))
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by a set of vendor names or OUI prefixes (e.g. `espressif,00:50:56`)
+pub fn filter_by_vendor<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    vendors: &VendorNames,
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let names = value
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<String>>();
+
+    if names.is_empty() {
+        warn!("Invalid vendor filter: {}", value);
+        return Err(SniffingError::InvalidFilterValue(format!(
+            "Invalid vendor filter: {}",
+            value
+        )));
+    }
+
+    // A value is an OUI when it is exactly a 24 bit prefix, e.g. `00:50:56` or `005056`
+    let ouis = names
+        .iter()
+        .filter(|name| name.len() == 6 || name.len() == 8)
+        .filter_map(|name| parse_oui(name))
+        .collect::<Vec<[u8; 3]>>();
+
+    let matches_mac = |mac: Option<String>| {
+        let mac = match mac {
+            Some(mac) => mac,
+            None => return false,
+        };
+
+        parse_oui(&mac).map_or(false, |oui| ouis.contains(&oui))
+            || vendors.get(&mac).map_or(false, |vendor| {
+                let vendor = vendor.to_lowercase();
+                names.iter().any(|name| vendor.contains(name.as_str()))
+            })
+    };
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        matches_mac(get_source_mac(p)) || matches_mac(get_dest_mac(p))
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by flow hash
+pub fn filter_by_flow<'a>(
+    index: &'a HashMap<String, Vec<Arc<ParsedPacket>>>,
     end: usize,
-    dst_port: &'a str,
+    flow_hash: &'a str,
     is_index_used: bool,
     filtered_packets: &mut Vec<Arc<ParsedPacket>>,
 ) {
+    let flow_hash = flow_hash.trim().to_lowercase();
+
     if filtered_packets.is_empty() && !is_index_used {
         return;
     }
@@ -684,30 +1960,234 @@ pub fn filter_by_dst_port<'a>(
         let mut counter = 0;
         *filtered_packets = filtered_packets
             .iter()
-            .filter_map(|p| {
-                let dest_port = get_dest_port(p);
-                if dest_port.is_some() && dest_port.unwrap() == dst_port {
-                    return Some(Arc::clone(p));
-                }
-
-                return None;
-            })
+            .filter(|p| p.get_flow_hash() == Some(&flow_hash))
+            .map(Arc::clone)
             .take_while(|_| {
                 counter += 1;
                 counter <= end
             })
             .collect();
-    } else {
-        match index.get(&dst_port.to_owned()) {
-            Some(values) => filtered_packets.extend_from_slice(values),
-            _ => (),
-        }
+    } else if let Some(values) = index.get(&flow_hash) {
+        filtered_packets.extend_from_slice(values);
+    }
+}
+
+/// Filter collected packets by a set of destination kinds (e.g. `multicast,broadcast`)
+pub fn filter_by_cast_type<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let cast_types = value
+        .split(',')
+        .map(|cast_type| cast_type.trim().to_lowercase())
+        .filter(|cast_type| !cast_type.is_empty())
+        .collect::<Vec<String>>();
+
+    let is_valid = |cast_type: &String| {
+        [
+            CastTypes::UNICAST,
+            CastTypes::MULTICAST,
+            CastTypes::BROADCAST,
+        ]
+        .contains(&cast_type.as_str())
+    };
+
+    if cast_types.is_empty() || !cast_types.iter().all(is_valid) {
+        warn!("Invalid cast filter: {}", value);
+        return Err(SniffingError::InvalidFilterValue(format!(
+            "Invalid cast filter: {}",
+            value
+        )));
+    }
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        get_cast_type(p).map_or(false, |cast_type| cast_types.iter().any(|c| c == cast_type))
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by a set of names of the interfaces they were captured on (e.g. `eth0,wlan0`)
+pub fn filter_by_interface<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let interface_names = value
+        .split(',')
+        .map(str::trim)
+        .filter(|interface_name| !interface_name.is_empty())
+        .collect::<Vec<&str>>();
+
+    if interface_names.is_empty() {
+        warn!("Invalid interface filter: {}", value);
+        return Err(SniffingError::InvalidFilterValue(format!(
+            "Invalid interface filter: {}",
+            value
+        )));
+    }
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        p.get_interface_name()
+            .map_or(false, |name| interface_names.contains(&name.as_str()))
+    });
+
+    Ok(())
+}
+
+/// Filter collected TLS packets by the hash of their JA3 or JA3S fingerprint
+pub fn filter_by_ja3<'a>(
+    tls_packets: &'a [Arc<ParsedPacket>],
+    get_hash: fn(&ParsedPacket) -> Option<&str>,
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let hashes = value
+        .split(',')
+        .map(|hash| hash.trim().to_lowercase())
+        .filter(|hash| !hash.is_empty())
+        .collect::<Vec<String>>();
+
+    let is_valid = |hash: &String| hash.len() == 32 && hash.chars().all(|c| c.is_ascii_hexdigit());
+
+    if hashes.is_empty() || !hashes.iter().all(is_valid) {
+        warn!("Invalid JA3 filter: {}", value);
+        return Err(SniffingError::InvalidFilterValue(format!(
+            "Invalid JA3 filter: {}",
+            value
+        )));
     }
+
+    filter_candidates(tls_packets, end, is_index_used, filtered_packets, |p| {
+        get_hash(p).map_or(false, |hash| hashes.iter().any(|h| h == hash))
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by the entropy of their payload (e.g. `> 7.5` or `7-8`)
+pub fn filter_by_entropy<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let matches = parse_number_condition(value, "entropy", 8.0)?;
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        p.get_payload_entropy().map_or(false, &matches)
+    });
+
+    Ok(())
+}
+
+/// Filter collected packets by the seconds since the first packet of their flow (e.g. `< 1` or `0.5-2`)
+pub fn filter_by_flow_time<'a>(
+    packets: &'a [Arc<ParsedPacket>],
+    flow_index: &HashMap<String, Vec<Arc<ParsedPacket>>>,
+    end: usize,
+    value: &'a str,
+    is_index_used: bool,
+    filtered_packets: &mut Vec<Arc<ParsedPacket>>,
+) -> Result<(), SniffingError> {
+    let matches = parse_number_condition(value, "flow time", f64::MAX)?;
+
+    filter_candidates(packets, end, is_index_used, filtered_packets, |p| {
+        get_flow_time_relative(p, flow_index).map_or(false, &matches)
+    });
+
+    Ok(())
+}
+
+/// Parse a comparison (`>`, `>=`, `<`, `<=`, `=`) with a value from 0 to `max`, or an inclusive range of
+/// values
+fn parse_number_condition(
+    value: &str,
+    name: &str,
+    max: f64,
+) -> Result<Box<dyn Fn(f64) -> bool>, SniffingError> {
+    let invalid = || {
+        warn!("Invalid {} filter: {}", name, value);
+        SniffingError::InvalidFilterValue(format!("Invalid {} filter: {}", name, value))
+    };
+
+    let parse = |number: &str| {
+        number
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|number| (0.0..=max).contains(number))
+            .ok_or_else(invalid)
+    };
+
+    let value = value.trim();
+    let condition: Box<dyn Fn(f64) -> bool> = if let Some(threshold) = value.strip_prefix(">=") {
+        let threshold = parse(threshold)?;
+        Box::new(move |number| number >= threshold)
+    } else if let Some(threshold) = value.strip_prefix("<=") {
+        let threshold = parse(threshold)?;
+        Box::new(move |number| number <= threshold)
+    } else if let Some(threshold) = value.strip_prefix('>') {
+        let threshold = parse(threshold)?;
+        Box::new(move |number| number > threshold)
+    } else if let Some(threshold) = value.strip_prefix('<') {
+        let threshold = parse(threshold)?;
+        Box::new(move |number| number < threshold)
+    } else if let Some((start, end)) = value.split_once('-') {
+        let range = parse(start)?..=parse(end)?;
+        if range.is_empty() {
+            return Err(invalid());
+        }
+        Box::new(move |number| range.contains(&number))
+    } else {
+        let threshold = parse(value.strip_prefix('=').unwrap_or(value))?;
+        Box::new(move |number| number == threshold)
+    };
+
+    Ok(condition)
+}
+
+/// Parse a comma separated list of values and inclusive ranges of values
+fn parse_ranges(filter: &str, values: &str) -> Result<Vec<RangeInclusive<u16>>, SniffingError> {
+    let invalid = || {
+        warn!("Invalid {} filter: {}", filter, values);
+        SniffingError::InvalidFilterValue(format!("Invalid {} filter: {}", filter, values))
+    };
+
+    values
+        .split(',')
+        .map(|value| {
+            let value = value.trim();
+            match value.split_once('-') {
+                Some((start, end)) => {
+                    let start = start.trim().parse::<u16>().map_err(|_| invalid())?;
+                    let end = end.trim().parse::<u16>().map_err(|_| invalid())?;
+                    if start > end {
+                        return Err(invalid());
+                    }
+                    Ok(start..=end)
+                }
+                None => {
+                    let value = value.parse::<u16>().map_err(|_| invalid())?;
+                    Ok(value..=value)
+                }
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
 pub mod tests {
-    use std::net::Ipv6Addr;
+    use std::collections::HashMap;
+    use std::net::{IpAddr, Ipv6Addr};
     use std::{net::Ipv4Addr, sync::Arc};
 
     use pnet::util::MacAddr;
@@ -715,17 +2195,21 @@ pub mod tests {
     use sniffer_parser::serializable_packet::transport::SerializableUdpPacket;
     use sniffer_parser::serializable_packet::{
         network::SerializableIpv4Packet,
-        transport::SerializableTcpPacket,
+        transport::{SerializableTcpOption, SerializableTcpPacket},
         util::{
-            get_dest_ip, get_dest_mac, get_dest_port, get_source_ip, get_source_mac,
-            get_source_port,
+            compute_flow_hash, get_cast_type, get_dest_ip, get_dest_mac, get_dest_port,
+            get_source_ip, get_source_mac, get_source_port, CastTypes,
         },
         ParsedPacket, SerializableEthernetPacket, SerializablePacket,
     };
 
+    use crate::services::tag_services;
     use crate::SniffingError;
 
-    use super::{get_packets_internal, FilterNamesValues, PacketsCollection};
+    use super::{
+        get_packets_internal, get_packets_since_internal, sample_packets_internal, tag_directions,
+        FilterNamesValues, PacketDirections, PacketsCollection,
+    };
 
     const SOURCE_IP: &str = "10.10.10.10";
     const DEST_IP: &str = "11.11.11.11";
@@ -1018,9 +2502,726 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn directions_relative_to_reference_ip() {
+        let mut packets = [
+            build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(11, 11, 11, 11, 11, 11),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                SOURCE_PORT,
+                DEST_PORT,
+            ),
+            build_test_parsed_packet(
+                MacAddr::new(11, 11, 11, 11, 11, 11),
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                Ipv4Addr::new(10, 10, 10, 10),
+                DEST_PORT,
+                SOURCE_PORT,
+            ),
+            build_second_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(11, 11, 11, 11, 11, 11),
+                Ipv6Addr::new(10, 10, 10, 10, 0, 0, 0, 0),
+                Ipv6Addr::new(11, 11, 11, 11, 0, 0, 0, 0),
+                SOURCE_PORT,
+                DEST_PORT,
+            ),
+        ];
+
+        tag_directions(
+            packets.iter_mut(),
+            IpAddr::V4(Ipv4Addr::new(10, 10, 10, 10)),
+        );
+
+        let directions: Vec<&str> = packets
+            .iter()
+            .map(|p| p.get_direction().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            directions,
+            vec![
+                PacketDirections::OUT,
+                PacketDirections::IN,
+                PacketDirections::OTHER
+            ]
+        );
+
+        tag_directions(packets.iter_mut(), "b:b:b:b::".parse().unwrap());
+        assert_eq!(
+            packets[2].get_direction().map(|d| d.as_str()),
+            Some(PacketDirections::IN)
+        );
+    }
+
+    #[test]
+    fn service_filter_and_tags() {
+        let mut packets_collection = build_test_packets_collection(vec![
+            build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(11, 11, 11, 11, 11, 11),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                50000,
+                443,
+            ),
+            build_second_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(11, 11, 11, 11, 11, 11),
+                Ipv6Addr::new(10, 10, 10, 10, 0, 0, 0, 0),
+                Ipv6Addr::new(11, 11, 11, 11, 0, 0, 0, 0),
+                53,
+                50001,
+            ),
+        ]);
+
+        let filter = |packets_collection: &mut PacketsCollection, value| {
+            get_packets_internal(
+                0,
+                10,
+                &vec![],
+                &vec![(FilterNamesValues::SERVICE, value)],
+                packets_collection,
+            )
+            .map(|packets| packets.iter().map(|p| p.get_id()).collect::<Vec<usize>>())
+        };
+
+        assert_eq!(filter(&mut packets_collection, "https").unwrap(), vec![0]);
+        assert_eq!(
+            filter(&mut packets_collection, "HTTPS, dns").unwrap(),
+            vec![0, 1]
+        );
+        assert!(filter(&mut packets_collection, " , ").is_err());
+
+        packets_collection
+            .services
+            .set_custom_names(HashMap::from([(50001, "CUSTOM".to_owned())]));
+        // The lowest known port names the service
+        assert_eq!(
+            filter(&mut packets_collection, "custom").unwrap(),
+            Vec::<usize>::new()
+        );
+
+        let mut packets =
+            get_packets_internal(0, 10, &vec![], &vec![], &mut packets_collection).unwrap();
+        tag_services(&mut packets, &packets_collection.services);
+        assert_eq!(
+            packets
+                .iter()
+                .map(|p| p.get_service().map(|s| s.as_str()))
+                .collect::<Vec<_>>(),
+            vec![Some("HTTPS"), Some("DNS")]
+        );
+    }
+
+    #[test]
+    fn cast_filter() {
+        let mut packets_collection = build_test_packets_collection(vec![
+            build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::broadcast(),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(255, 255, 255, 255),
+                68,
+                67,
+            ),
+            build_second_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(0x33, 0x33, 0, 0, 0, 0xfb),
+                Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1),
+                Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb),
+                5353,
+                5353,
+            ),
+        ]);
+
+        let filter = |packets_collection: &mut PacketsCollection, value| {
+            get_packets_internal(
+                0,
+                10,
+                &vec![],
+                &vec![(FilterNamesValues::CAST, value)],
+                packets_collection,
+            )
+            .map(|packets| packets.iter().map(|p| p.get_id()).collect::<Vec<usize>>())
+        };
+
+        assert_eq!(
+            filter(&mut packets_collection, "broadcast").unwrap(),
+            vec![0]
+        );
+        assert_eq!(
+            filter(&mut packets_collection, "Multicast,broadcast").unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            filter(&mut packets_collection, "unicast").unwrap(),
+            Vec::<usize>::new()
+        );
+        assert!(filter(&mut packets_collection, "anycast").is_err());
+
+        let unicast = build_test_parsed_packet(
+            MacAddr::new(10, 10, 10, 10, 10, 10),
+            MacAddr::new(12, 12, 12, 12, 12, 12),
+            Ipv4Addr::new(10, 10, 10, 10),
+            Ipv4Addr::new(11, 11, 11, 11),
+            50000,
+            443,
+        );
+        assert_eq!(get_cast_type(&unicast), Some(CastTypes::UNICAST));
+    }
+
+    #[test]
+    fn interface_filter() {
+        let mut parsed_packets = vec![];
+        for (id, interface_name) in [Some("eth0"), Some("wlan0"), None].into_iter().enumerate() {
+            let mut parsed_packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                50000 + id as u16,
+                443,
+            );
+            parsed_packet.set_interface_name(interface_name.map(str::to_owned));
+            parsed_packets.push(parsed_packet);
+        }
+        let mut packets_collection = build_test_packets_collection(parsed_packets);
+
+        let filter = |packets_collection: &mut PacketsCollection, value| {
+            get_packets_internal(
+                0,
+                10,
+                &vec![],
+                &vec![(FilterNamesValues::INTERFACE, value)],
+                packets_collection,
+            )
+            .map(|packets| {
+                packets
+                    .iter()
+                    .map(|p| get_source_port(p).unwrap())
+                    .collect::<Vec<String>>()
+            })
+        };
+
+        assert_eq!(
+            filter(&mut packets_collection, "wlan0").unwrap(),
+            vec!["50001"]
+        );
+        assert_eq!(
+            filter(&mut packets_collection, "eth0, wlan0").unwrap(),
+            vec!["50000", "50001"]
+        );
+        assert!(filter(&mut packets_collection, "eth1").unwrap().is_empty());
+        assert!(filter(&mut packets_collection, " , ").is_err());
+    }
+
+    #[test]
+    fn entropy_filter() {
+        let mut parsed_packets = vec![];
+        for (id, entropy) in [Some(7.9), Some(4.2), None].into_iter().enumerate() {
+            let mut parsed_packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                50000 + id as u16,
+                443,
+            );
+            parsed_packet.set_payload_entropy(entropy);
+            parsed_packets.push(parsed_packet);
+        }
+        let mut packets_collection = build_test_packets_collection(parsed_packets);
+
+        let filter = |packets_collection: &mut PacketsCollection, value| {
+            get_packets_internal(
+                0,
+                10,
+                &vec![],
+                &vec![(FilterNamesValues::ENTROPY, value)],
+                packets_collection,
+            )
+            .map(|packets| {
+                packets
+                    .iter()
+                    .map(|p| get_source_port(p).unwrap())
+                    .collect::<Vec<String>>()
+            })
+        };
+
+        assert_eq!(
+            filter(&mut packets_collection, "> 7.5").unwrap(),
+            vec!["50000"]
+        );
+        assert_eq!(
+            filter(&mut packets_collection, "<=4.2").unwrap(),
+            vec!["50001"]
+        );
+        assert_eq!(
+            filter(&mut packets_collection, "4-8").unwrap(),
+            vec!["50000", "50001"]
+        );
+        assert!(filter(&mut packets_collection, ">9").is_err());
+        assert!(filter(&mut packets_collection, "high").is_err());
+    }
+
+    #[test]
+    fn flow_time_filter() {
+        use sniffer_parser::serializable_packet::util::compute_flow_hash;
+
+        let mut packets_collection = PacketsCollection::new();
+        for (id, timestamp) in [1_000_000, 1_400_000, 3_000_000].into_iter().enumerate() {
+            let mut parsed_packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                50000,
+                443 + id as u16 / 2,
+            );
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_flow_hash(compute_flow_hash(&parsed_packet));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let filter = |packets_collection: &mut PacketsCollection, value| {
+            get_packets_internal(
+                0,
+                10,
+                &vec![],
+                &vec![(FilterNamesValues::FLOW_TIME_RELATIVE, value)],
+                packets_collection,
+            )
+            .map(|packets| {
+                packets
+                    .iter()
+                    .map(|p| p.get_timestamp())
+                    .collect::<Vec<i64>>()
+            })
+        };
+
+        // The third packet starts a flow of its own
+        assert_eq!(
+            filter(&mut packets_collection, "< 0.1").unwrap(),
+            vec![1_000_000, 3_000_000]
+        );
+        assert_eq!(
+            filter(&mut packets_collection, "0.1-1").unwrap(),
+            vec![1_400_000]
+        );
+        assert!(filter(&mut packets_collection, "-1").is_err());
+        assert!(filter(&mut packets_collection, "early").is_err());
+    }
+
+    #[test]
+    fn ja3_filter() {
+        use sniffer_parser::serializable_packet::application::{
+            ClientHelloMessage, CustomHandshakeMessage, CustomTlsMessage, Ja3Fingerprint,
+            SerializableTlsPacket,
+        };
+
+        let mut packets_collection = PacketsCollection::new();
+        for (id, ja3) in ["771,4865,0,29,0", "771,4866,0,29,0"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut parsed_packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                50000 + id as u16,
+                443,
+            );
+            let client_hello = ClientHelloMessage {
+                version: "Tls12".to_owned(),
+                rand_time: 0,
+                rand_data: vec![],
+                session_id: None,
+                ciphers: vec![],
+                compressions: vec![],
+                extensions: vec![],
+                ja3: Ja3Fingerprint::new(ja3.to_owned()),
+            };
+            let mut tls_packet = SerializableTlsPacket::default();
+            tls_packet.set_messages(vec![CustomTlsMessage::Handshake(
+                CustomHandshakeMessage::ClientHello(client_hello),
+            )]);
+            parsed_packet
+                .set_application_layer_packet(Some(SerializablePacket::TlsPacket(tls_packet)));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+        let hash = Ja3Fingerprint::new("771,4865,0,29,0".to_owned()).hash;
+        // Case insensitive
+        let uppercase_hash = hash.to_uppercase();
+
+        let filter = |packets_collection: &mut PacketsCollection, name, value| {
+            get_packets_internal(0, 10, &vec![], &vec![(name, value)], packets_collection).map(
+                |packets| {
+                    packets
+                        .iter()
+                        .map(|p| get_source_port(p).unwrap())
+                        .collect::<Vec<String>>()
+                },
+            )
+        };
+
+        assert_eq!(
+            filter(
+                &mut packets_collection,
+                FilterNamesValues::JA3,
+                &uppercase_hash
+            )
+            .unwrap(),
+            vec!["50000"]
+        );
+        assert!(
+            filter(&mut packets_collection, FilterNamesValues::JA3S, &hash)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(filter(&mut packets_collection, FilterNamesValues::JA3, "771,4865").is_err());
+    }
+
+    #[test]
+    fn flow_filter() {
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let mut packets_collection = PacketsCollection::new();
+
+        for (id, (source, destination, source_port, dest_port)) in [
+            (client, server, 4444, 80),
+            (server, client, 80, 4444),
+            (client, server, 5555, 80),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                source,
+                destination,
+                source_port,
+                dest_port,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+            parsed_packet.set_flow_hash(compute_flow_hash(&parsed_packet));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let flow_hash = packets_collection.packets[0]
+            .get_flow_hash()
+            .unwrap()
+            .clone();
+        assert_eq!(flow_hash.len(), 16);
+        assert_ne!(
+            packets_collection.packets[2].get_flow_hash(),
+            Some(&flow_hash)
+        );
+
+        let mut filter = |filters_value| {
+            get_packets_internal(0, 10, &vec![], &filters_value, &mut packets_collection)
+                .unwrap()
+                .iter()
+                .map(|p| p.get_id())
+                .collect::<Vec<usize>>()
+        };
+
+        assert_eq!(
+            filter(vec![(FilterNamesValues::FLOW, flow_hash.as_str())]),
+            vec![0, 1]
+        );
+        assert_eq!(
+            filter(vec![
+                (FilterNamesValues::SRC_PORT, "80"),
+                (FilterNamesValues::FLOW, flow_hash.as_str())
+            ]),
+            vec![1]
+        );
+        assert_eq!(
+            filter(vec![(FilterNamesValues::FLOW, "0000000000000000")]),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn port_sets_and_ranges_filter() {
+        let packets_collection = || {
+            build_test_packets_collection(vec![
+                build_test_parsed_packet(
+                    MacAddr::new(10, 10, 10, 10, 10, 10),
+                    MacAddr::new(11, 11, 11, 11, 11, 11),
+                    Ipv4Addr::new(10, 10, 10, 10),
+                    Ipv4Addr::new(11, 11, 11, 11),
+                    SOURCE_PORT,
+                    DEST_PORT,
+                ),
+                build_second_test_parsed_packet(
+                    MacAddr::new(11, 11, 11, 11, 11, 11),
+                    MacAddr::new(10, 10, 10, 10, 10, 10),
+                    Ipv6Addr::new(11, 11, 11, 11, 0, 0, 0, 0),
+                    Ipv6Addr::new(10, 10, 10, 10, 0, 0, 0, 0),
+                    DEST_PORT,
+                    5555,
+                ),
+            ])
+        };
+
+        let ids = |filters_value: Vec<(&str, &str)>| {
+            get_packets_internal(0, 100, &vec![], &filters_value, &mut packets_collection())
+                .map(|packets| packets.iter().map(|p| p.get_id()).collect::<Vec<usize>>())
+        };
+
+        assert_eq!(
+            ids(vec![(FilterNamesValues::PORT, "443")]).unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            ids(vec![(FilterNamesValues::SRC_PORT, "400-500, 5555")]).unwrap(),
+            vec![1]
+        );
+        assert_eq!(
+            ids(vec![(FilterNamesValues::DST_PORT, "80,5000-6000")]).unwrap(),
+            vec![1]
+        );
+        assert_eq!(
+            ids(vec![
+                (FilterNamesValues::PORT, "4444,5555"),
+                (FilterNamesValues::DST_PORT, "443")
+            ])
+            .unwrap(),
+            vec![0]
+        );
+        assert!(ids(vec![(FilterNamesValues::DST_PORT, "8000-8100")])
+            .unwrap()
+            .is_empty());
+
+        match ids(vec![(FilterNamesValues::PORT, "80-")]) {
+            Err(SniffingError::InvalidFilterValue(_)) => (),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn tcp_options_filter() {
+        let mut packets_collection = PacketsCollection::new();
+
+        for (id, mss) in [(0, 1460), (1, 536), (2, 0)] {
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                SOURCE_PORT,
+                DEST_PORT,
+            );
+            let mut tcp_packet = match template.get_transport_layer_packet() {
+                Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+                _ => unreachable!(),
+            };
+            if mss > 0 {
+                tcp_packet.parsed_options = vec![
+                    SerializableTcpOption::MaximumSegmentSize(mss),
+                    SerializableTcpOption::WindowScale(7),
+                ];
+            }
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let mut ids = |filters_value: Vec<(&str, &str)>| {
+            get_packets_internal(0, 100, &vec![], &filters_value, &mut packets_collection)
+                .map(|packets| packets.iter().map(|p| p.get_id()).collect::<Vec<usize>>())
+        };
+
+        assert_eq!(
+            ids(vec![(FilterNamesValues::TCP_MSS, "1400-1500")]).unwrap(),
+            vec![0]
+        );
+        assert_eq!(
+            ids(vec![(FilterNamesValues::TCP_MSS, "536,1460")]).unwrap(),
+            vec![0, 1]
+        );
+        assert_eq!(
+            ids(vec![
+                (FilterNamesValues::SRC_IP, SOURCE_IP),
+                (FilterNamesValues::TCP_WINDOW_SCALE, "7")
+            ])
+            .unwrap(),
+            vec![0, 1]
+        );
+
+        match ids(vec![(FilterNamesValues::TCP_MSS, "large")]) {
+            Err(SniffingError::InvalidFilterValue(str)) => {
+                assert_eq!(str, "Invalid TCP option filter: large")
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn packets_since_cursor() {
+        let mut packets_collection = PacketsCollection::new();
+
+        let delta = get_packets_since_internal(None, &packets_collection);
+        assert!(delta.packets.is_empty());
+        assert_eq!(delta.cursor, None);
+
+        for id in 0..5 {
+            packets_collection.insert(Arc::new(ParsedPacket::new(id)));
+        }
+
+        let delta = get_packets_since_internal(None, &packets_collection);
+        assert_eq!(delta.packets.len(), 5);
+        assert_eq!(delta.cursor, Some(4));
+
+        let delta = get_packets_since_internal(Some(2), &packets_collection);
+        let ids: Vec<usize> = delta.packets.iter().map(|p| p.get_id()).collect();
+        assert_eq!(ids, vec![3, 4]);
+        assert_eq!(delta.cursor, Some(4));
+
+        let delta = get_packets_since_internal(Some(4), &packets_collection);
+        assert!(delta.packets.is_empty());
+        assert_eq!(delta.cursor, Some(4));
+    }
+
+    #[test]
+    fn sample_with_stride() {
+        let mut packets_collection = PacketsCollection::new();
+        for id in 0..10 {
+            packets_collection.insert(Arc::new(ParsedPacket::new(id)));
+        }
+
+        let sample = sample_packets_internal(3, &vec![], &vec![], &mut packets_collection).unwrap();
+        let ids: Vec<usize> = sample.packets.iter().map(|p| p.get_id()).collect();
+        assert_eq!(ids, vec![0, 4, 8]);
+        assert_eq!((sample.stride, sample.total), (4, 10));
+
+        let sample =
+            sample_packets_internal(20, &vec![], &vec![], &mut packets_collection).unwrap();
+        assert_eq!((sample.packets.len(), sample.stride), (10, 1));
+
+        // Sampled among the filtered packets only
+        let mut packets_collection = build_test_packets_collection(
+            (0..6)
+                .map(|_| {
+                    build_test_parsed_packet(
+                        MacAddr::zero(),
+                        MacAddr::broadcast(),
+                        SOURCE_IP.parse().unwrap(),
+                        DEST_IP.parse().unwrap(),
+                        SOURCE_PORT,
+                        DEST_PORT,
+                    )
+                })
+                .collect(),
+        );
+
+        let filters_type = vec![FilterNamesValues::TCP];
+        let sample =
+            sample_packets_internal(2, &filters_type, &vec![], &mut packets_collection).unwrap();
+        assert_eq!(
+            (sample.packets.len(), sample.stride, sample.total),
+            (2, 3, 6)
+        );
+
+        let sample =
+            sample_packets_internal(0, &filters_type, &vec![], &mut packets_collection).unwrap();
+        assert!(sample.packets.is_empty());
+        assert_eq!(sample.total, 6);
+    }
+
+    #[test]
+    fn index_limit_evicts_least_recent_keys() {
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection
+            .set_index_limit(FilterNamesValues::SRC_PORT, Some(2))
+            .unwrap();
+
+        for (id, source_port) in [1000, 2000, 1000, 3000].into_iter().enumerate() {
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                source_port,
+                DEST_PORT,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let keys: Vec<&String> = packets_collection.source_port_index.keys().collect();
+        assert_eq!(keys, vec!["1000", "3000"]);
+        assert!(!packets_collection.is_index_complete(FilterNamesValues::SRC_PORT));
+        assert!(!packets_collection.is_index_complete(FilterNamesValues::PORT));
+        assert!(packets_collection.is_index_complete(FilterNamesValues::DST_PORT));
+
+        // Evicted keys are still found by scanning the packets
+        let filters_value = vec![(FilterNamesValues::SRC_PORT, "2000")];
+        let packets =
+            get_packets_internal(0, 100, &vec![], &filters_value, &mut packets_collection).unwrap();
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].get_id(), 1);
+
+        assert!(matches!(
+            packets_collection.set_index_limit("tcp", Some(1)),
+            Err(SniffingError::UnknownFilterType(_))
+        ));
+
+        packets_collection
+            .set_index_limit(FilterNamesValues::DST_PORT, Some(0))
+            .unwrap();
+        assert!(packets_collection.dest_port_index.is_empty());
+    }
+
+    #[test]
+    fn index_limit_set_after_packets() {
+        let mut packets_collection = PacketsCollection::new();
+
+        for (id, source_port) in [1000, 2000, 3000, 1000].into_iter().enumerate() {
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                Ipv4Addr::new(10, 10, 10, 10),
+                Ipv4Addr::new(11, 11, 11, 11),
+                source_port,
+                DEST_PORT,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        // The recency of the keys comes from the packets they list
+        packets_collection
+            .set_index_limit(FilterNamesValues::SRC_PORT, Some(2))
+            .unwrap();
+        let keys: Vec<&String> = packets_collection.source_port_index.keys().collect();
+        assert_eq!(keys, vec!["1000", "3000"]);
+        assert!(!packets_collection.is_index_complete(FilterNamesValues::SRC_PORT));
+    }
+
     // Utils
 
-    fn build_test_packets_collection(parsed_packets: Vec<ParsedPacket>) -> PacketsCollection {
+    pub fn build_test_packets_collection(parsed_packets: Vec<ParsedPacket>) -> PacketsCollection {
         let mut packet_collection = PacketsCollection::new();
 
         for parsed_packet in parsed_packets {
@@ -1081,7 +3282,7 @@ pub mod tests {
         packet_collection
     }
 
-    fn build_test_parsed_packet(
+    pub fn build_test_parsed_packet(
         source_mac: MacAddr,
         dest_mac: MacAddr,
         source_ip: Ipv4Addr,
@@ -1096,7 +3297,7 @@ pub mod tests {
                 destination: dest_mac,
                 source: source_mac,
                 ethertype: "Ipv4".to_owned(),
-                payload: Vec::new(),
+                payload: Vec::new().into(),
             },
         )));
 
@@ -1113,8 +3314,10 @@ pub mod tests {
                 ttl: 1,
                 next_level_protocol: "Tcp (6)".to_owned(),
                 checksum: 1,
+                checksum_valid: None,
                 source: source_ip,
                 destination: dest_ip,
+                parsed_options: Vec::new(),
                 length: 1,
             },
         )));
@@ -1130,8 +3333,10 @@ pub mod tests {
                 flags: 1,
                 window: 1,
                 checksum: 1,
+                checksum_valid: None,
                 urgent_ptr: 1,
                 options: Vec::new(),
+                parsed_options: Vec::new(),
                 length: 1,
             },
         )));
@@ -1139,7 +3344,7 @@ pub mod tests {
         parsed_packet
     }
 
-    fn build_second_test_parsed_packet(
+    pub fn build_second_test_parsed_packet(
         source_mac: MacAddr,
         dest_mac: MacAddr,
         source_ip: Ipv6Addr,
@@ -1154,7 +3359,7 @@ pub mod tests {
                 destination: dest_mac,
                 source: source_mac,
                 ethertype: "Ipv4".to_owned(),
-                payload: Vec::new(),
+                payload: Vec::new().into(),
             },
         )));
 
@@ -1177,6 +3382,7 @@ pub mod tests {
                 source: source_port,
                 destination: dest_port,
                 checksum: 1,
+                checksum_valid: None,
                 length: 1,
             },
         )));
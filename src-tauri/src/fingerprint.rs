@@ -0,0 +1,153 @@
+//! Guess of the operating system family and hop distance of the hosts, from the IP TTL
+//!
+//! Operating systems start the TTL (hop limit in IPv6) of their packets from a well known value, decremented
+//! by each router along the path. The initial TTL is taken as the smallest well known value not lower than the
+//! highest TTL observed from the host, and the hop distance as the difference.
+//!
+//! This is only a heuristic: the initial TTL can be configured, NATs and tunnels fool it and hosts more than
+//! 64 hops away would be misclassified.
+
+use std::collections::BTreeMap;
+
+use log::info;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::filtering::PacketsCollection;
+use crate::{SniffingError, SniffingState};
+
+/// Well known initial TTLs, with the operating systems using them
+const INITIAL_TTLS: [(u8, &str); 4] = [
+    (32, "Windows 9x / embedded"),
+    (64, "Linux / macOS / Unix"),
+    (128, "Windows"),
+    (255, "Network device / Solaris"),
+];
+
+/// Likely operating system family of a host
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HostOsGuess {
+    pub ip: String,
+    /// Highest and lowest TTL among the packets sent by the host
+    pub max_ttl: u8,
+    pub min_ttl: u8,
+    pub packets: usize,
+    pub initial_ttl: u8,
+    /// Routers between the host and the capture point
+    pub hops: u8,
+    pub os: String,
+}
+
+/// Returns a guess of the operating system of each source IP address, derived from the TTL of its packets
+#[tauri::command]
+pub fn get_host_os_guesses(
+    state: tauri::State<SniffingState>,
+) -> Result<Vec<HostOsGuess>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+    let guesses = get_host_os_guesses_internal(&packets_collection);
+
+    info!("Guessed the operating system of {} hosts", guesses.len());
+
+    Ok(guesses)
+}
+
+fn get_host_os_guesses_internal(packets_collection: &PacketsCollection) -> Vec<HostOsGuess> {
+    let mut ttls: BTreeMap<String, (u8, u8, usize)> = BTreeMap::new();
+
+    for packet in &packets_collection.packets {
+        let (ip, ttl) = match get_source_ttl(packet) {
+            Some(source_ttl) => source_ttl,
+            None => continue,
+        };
+
+        let (max_ttl, min_ttl, packets) = ttls.entry(ip).or_insert((ttl, ttl, 0));
+        *max_ttl = (*max_ttl).max(ttl);
+        *min_ttl = (*min_ttl).min(ttl);
+        *packets += 1;
+    }
+
+    ttls.into_iter()
+        .map(|(ip, (max_ttl, min_ttl, packets))| {
+            let (initial_ttl, os) = INITIAL_TTLS
+                .iter()
+                .find(|(initial_ttl, _)| *initial_ttl >= max_ttl)
+                .unwrap_or(&INITIAL_TTLS[INITIAL_TTLS.len() - 1]);
+
+            HostOsGuess {
+                ip,
+                max_ttl,
+                min_ttl,
+                packets,
+                initial_ttl: *initial_ttl,
+                hops: initial_ttl - max_ttl,
+                os: os.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn get_source_ttl(packet: &ParsedPacket) -> Option<(String, u8)> {
+    match packet.get_network_layer_packet()? {
+        SerializablePacket::Ipv4Packet(ipv4) => Some((ipv4.source.to_string(), ipv4.ttl)),
+        SerializablePacket::Ipv6Packet(ipv6) => Some((ipv6.source.to_string(), ipv6.hop_limit)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::SerializablePacket;
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::get_host_os_guesses_internal;
+
+    #[test]
+    fn initial_ttl_and_hops() {
+        let mut packets_collection = PacketsCollection::new();
+
+        for (source, ttl) in [
+            (Ipv4Addr::new(10, 0, 0, 1), 64),
+            (Ipv4Addr::new(10, 0, 0, 2), 118),
+            (Ipv4Addr::new(10, 0, 0, 2), 1),
+            (Ipv4Addr::new(10, 0, 0, 3), 250),
+        ] {
+            let mut packet = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                source,
+                Ipv4Addr::new(10, 0, 0, 254),
+                4444,
+                80,
+            );
+
+            let mut network_layer = packet.get_network_layer_packet().cloned();
+            if let Some(SerializablePacket::Ipv4Packet(ipv4)) = &mut network_layer {
+                ipv4.ttl = ttl;
+            }
+            packet.set_network_layer_packet(network_layer);
+
+            packets_collection.insert(Arc::new(packet));
+        }
+
+        let guesses: Vec<(String, u8, u8, usize)> =
+            get_host_os_guesses_internal(&packets_collection)
+                .into_iter()
+                .map(|guess| (guess.ip, guess.initial_ttl, guess.hops, guess.packets))
+                .collect();
+
+        assert_eq!(
+            guesses,
+            vec![
+                ("10.0.0.1".to_owned(), 64, 0, 1),
+                ("10.0.0.2".to_owned(), 128, 10, 2),
+                ("10.0.0.3".to_owned(), 255, 5, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Flow sampling, collecting a few packets of each flow for a flow-level overview of the capture
+//!
+//! - `off`: every packet is collected (default)
+//! - `first`: only the first packet of each flow
+//! - `first_last`: the first packet of each flow, along with the TCP segments closing it (FIN or RST)
+//!
+//! Flows are identified by their hash: the transport protocol with the IP addresses and ports of both
+//! directions. Packets without an IP layer don't belong to a flow, and are always collected.
+//!
+//! The packets left out are parsed and counted anyway: they keep their packet ID, and they add up to the
+//! packets and bytes exchanged by the endpoints in the report. They aren't retrievable or exported, and
+//! don't count in the statistics of the collected packets. The mode applies to the packets captured from
+//! then on, the flows already sampled being remembered until the collected packets are cleared.
+
+use std::collections::HashSet;
+
+use log::info;
+use pnet::packet::tcp::TcpFlags;
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod FlowSamplingModes {
+    pub const OFF: &str = "off";
+    pub const FIRST: &str = "first";
+    pub const FIRST_LAST: &str = "first_last";
+
+    pub const ALL: [&str; 3] = [OFF, FIRST, FIRST_LAST];
+}
+
+/// Flows seen so far, by flow hash
+#[derive(Debug, Default)]
+pub struct FlowSampler {
+    flows: HashSet<String>,
+}
+
+impl FlowSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the packet is collected according to the mode, recording its flow
+    pub fn is_sampled(&mut self, mode: &str, packet: &ParsedPacket) -> bool {
+        let flow_hash = match packet.get_flow_hash() {
+            Some(flow_hash) => flow_hash,
+            None => return true,
+        };
+
+        let is_first = self.flows.insert(flow_hash.clone());
+        match mode {
+            FlowSamplingModes::FIRST => is_first,
+            FlowSamplingModes::FIRST_LAST => is_first || is_closing_segment(packet),
+            _ => true,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.flows.clear();
+    }
+}
+
+fn is_closing_segment(packet: &ParsedPacket) -> bool {
+    match packet.get_transport_layer_packet() {
+        Some(SerializablePacket::TcpPacket(tcp_packet)) => {
+            tcp_packet.flags & (TcpFlags::FIN | TcpFlags::RST) != 0
+        }
+        _ => false,
+    }
+}
+
+/// Collects only the first packet of each flow (`first`), optionally with its closing segments
+/// (`first_last`), or every packet (`off`)
+#[tauri::command]
+pub fn set_flow_sampling(
+    state: tauri::State<SniffingState>,
+    mode: String,
+) -> Result<(), SniffingError> {
+    let mode = mode.to_lowercase();
+    if !FlowSamplingModes::ALL.contains(&mode.as_str()) {
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "Unknown flow sampling mode: {} (available: {})",
+            mode,
+            FlowSamplingModes::ALL.join(", ")
+        )));
+    }
+
+    info!("Flow sampling: {}", mode);
+    *state.flow_sampling.lock().unwrap() = mode;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::util::compute_flow_hash;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{FlowSampler, FlowSamplingModes};
+
+    fn build_segment(to_server: bool, client_port: u16, flags: u16) -> ParsedPacket {
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 80));
+        let mut packet = if to_server {
+            build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                client,
+                server,
+                client_port,
+                80,
+            )
+        } else {
+            build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                server,
+                client,
+                80,
+                client_port,
+            )
+        };
+
+        if let Some(SerializablePacket::TcpPacket(tcp_packet)) = packet.get_transport_layer_packet()
+        {
+            let mut tcp_packet = tcp_packet.clone();
+            tcp_packet.flags = flags;
+            packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+        }
+        packet.set_flow_hash(compute_flow_hash(&packet));
+        packet
+    }
+
+    #[test]
+    fn first_packets_of_flows_sampled() {
+        let packets = [
+            build_segment(true, 4444, TcpFlags::SYN),
+            build_segment(false, 4444, TcpFlags::SYN | TcpFlags::ACK),
+            build_segment(true, 5555, TcpFlags::SYN),
+            build_segment(true, 4444, TcpFlags::ACK | TcpFlags::PSH),
+            build_segment(false, 4444, TcpFlags::FIN | TcpFlags::ACK),
+            build_segment(true, 5555, TcpFlags::RST),
+        ];
+
+        let sample = |mode| {
+            let mut sampler = FlowSampler::new();
+            packets
+                .iter()
+                .map(|packet| sampler.is_sampled(mode, packet))
+                .collect::<Vec<bool>>()
+        };
+
+        assert_eq!(sample(FlowSamplingModes::OFF), vec![true; 6]);
+        assert_eq!(
+            sample(FlowSamplingModes::FIRST),
+            vec![true, false, true, false, false, false]
+        );
+        assert_eq!(
+            sample(FlowSamplingModes::FIRST_LAST),
+            vec![true, false, true, false, true, true]
+        );
+
+        // Packets outside of a flow
+        let mut sampler = FlowSampler::new();
+        assert!(sampler.is_sampled(FlowSamplingModes::FIRST, &ParsedPacket::new(0)));
+        assert!(sampler.is_sampled(FlowSamplingModes::FIRST, &ParsedPacket::new(1)));
+    }
+}
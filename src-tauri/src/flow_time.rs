@@ -0,0 +1,101 @@
+//! Time of the packets relative to the start of their flow, to reason about a conversation in isolation
+//!
+//! The start of a TCP or UDP flow is the timestamp of its first collected packet, in either direction,
+//! as kept by the flow index of the collection. Packets of other protocols have no relative time.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use sniffer_parser::serializable_packet::util::{contains_tcp, contains_udp};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+/// Seconds elapsed between the first packet of the flow and the packet
+pub fn get_flow_time_relative(
+    packet: &ParsedPacket,
+    flow_index: &HashMap<String, Vec<Arc<ParsedPacket>>>,
+) -> Option<f64> {
+    if !contains_tcp(packet) && !contains_udp(packet) {
+        return None;
+    }
+
+    let first_packet = flow_index.get(packet.get_flow_hash()?)?.first()?;
+    let elapsed = (packet.get_timestamp() - first_packet.get_timestamp()).max(0);
+
+    Some(elapsed as f64 / 1_000_000.0)
+}
+
+/// Tag each packet with the time since the start of its flow
+pub fn tag_flow_times(
+    packets: &mut Vec<ParsedPacket>,
+    flow_index: &HashMap<String, Vec<Arc<ParsedPacket>>>,
+) {
+    for packet in packets {
+        let flow_time_relative = get_flow_time_relative(packet, flow_index);
+        packet.set_flow_time_relative(flow_time_relative);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::util::compute_flow_hash;
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::tag_flow_times;
+
+    #[test]
+    fn time_relative_to_flow_start() {
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 80));
+        let segments = [
+            (client, server, 4444, 80, 1_000_000),
+            (client, server, 5555, 80, 1_200_000),
+            (server, client, 80, 4444, 1_250_000),
+            (client, server, 4444, 80, 3_500_000),
+        ];
+
+        let mut packets_collection = PacketsCollection::new();
+        for (id, (source, destination, source_port, dest_port, timestamp)) in
+            segments.into_iter().enumerate()
+        {
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                source,
+                destination,
+                source_port,
+                dest_port,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+            parsed_packet.set_flow_hash(compute_flow_hash(&parsed_packet));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let mut packets = packets_collection
+            .packets
+            .iter()
+            .map(|packet| ParsedPacket::clone(packet))
+            .collect::<Vec<ParsedPacket>>();
+        // Without transport layer
+        packets[1].set_transport_layer_packet(None);
+        tag_flow_times(&mut packets, &packets_collection.flow_index);
+
+        assert_eq!(
+            packets
+                .iter()
+                .map(|packet| packet.get_flow_time_relative())
+                .collect::<Vec<Option<f64>>>(),
+            vec![Some(0.0), None, Some(0.25), Some(2.5)]
+        );
+    }
+}
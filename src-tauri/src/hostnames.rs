@@ -0,0 +1,296 @@
+//! Reverse DNS resolution of the collected IP addresses
+//!
+//! PTR queries are sent directly to the first nameserver of the system configuration by a bounded
+//! pool of threads, so that a slow resolver delays only the requesting call and never the capture.
+//! Both hostnames and missing PTR records are cached, while failed or timed out lookups are retried
+//! on the next request.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use dns_parser::{Builder, Packet, QueryClass, QueryType, RData, ResponseCode};
+use log::{debug, warn};
+
+use crate::own_traffic::{register_own_socket, OwnProtocols};
+use crate::{SniffingError, SniffingState};
+
+/// Maximum number of concurrent lookups
+const RESOLVER_THREADS: usize = 8;
+/// Overall time limit of a `resolve_hostnames` call
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(2);
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+static QUERY_ID: AtomicU16 = AtomicU16::new(0);
+
+/// Resolved hostnames by IP address, `None` when the address has no PTR record
+pub type HostnameCache = HashMap<IpAddr, Option<String>>;
+
+/// Returns the hostname of each IP address, `None` when not available
+///
+/// Lookups still pending after the timeout are reported as `None` and not cached
+#[tauri::command(async)]
+pub fn resolve_hostnames(
+    state: tauri::State<SniffingState>,
+    ips: Vec<String>,
+) -> Result<HashMap<String, Option<String>>, SniffingError> {
+    let ips = ips
+        .into_iter()
+        .map(|ip| {
+            ip.parse::<IpAddr>()
+                .map(|address| (ip.clone(), address))
+                .map_err(|_| SniffingError::InvalidIpAddress(format!("Invalid IP address: {}", ip)))
+        })
+        .collect::<Result<Vec<(String, IpAddr)>, SniffingError>>()?;
+
+    let pending: Vec<IpAddr> = {
+        let cache = state.hostnames.lock().unwrap();
+        ips.iter()
+            .map(|(_, address)| *address)
+            .filter(|address| !cache.contains_key(address))
+            .collect::<HashSet<IpAddr>>()
+            .into_iter()
+            .collect()
+    };
+
+    if !pending.is_empty() {
+        match get_nameserver() {
+            Some(nameserver) => {
+                let resolved = resolve_all(pending, nameserver, RESOLVE_TIMEOUT);
+                state.hostnames.lock().unwrap().extend(resolved);
+            }
+            None => warn!("No nameserver configured, hostnames not resolved"),
+        }
+    }
+
+    let cache = state.hostnames.lock().unwrap();
+    Ok(ips
+        .into_iter()
+        .map(|(ip, address)| (ip, cache.get(&address).cloned().flatten()))
+        .collect())
+}
+
+/// Looks up the addresses in parallel, returning the completed lookups
+fn resolve_all(
+    addresses: Vec<IpAddr>,
+    nameserver: SocketAddr,
+    timeout: Duration,
+) -> Vec<(IpAddr, Option<String>)> {
+    let deadline = Instant::now() + timeout;
+    let total = addresses.len();
+    let queue = Arc::new(Mutex::new(addresses));
+    let (sender, receiver) = channel();
+
+    for _ in 0..RESOLVER_THREADS.min(total) {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+
+        thread::spawn(move || loop {
+            let address = match queue.lock().unwrap().pop() {
+                Some(address) => address,
+                None => break,
+            };
+
+            let result = reverse_lookup(address, nameserver, deadline);
+            if sender.send((address, result)).is_err() {
+                break;
+            }
+        });
+    }
+
+    let mut resolved = vec![];
+    for _ in 0..total {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok((address, Ok(hostname))) => resolved.push((address, hostname)),
+            Ok((address, Err(e))) => debug!("Reverse lookup of {} failed: {}", address, e),
+            Err(_) => break,
+        }
+    }
+
+    resolved
+}
+
+fn reverse_lookup(
+    address: IpAddr,
+    nameserver: SocketAddr,
+    deadline: Instant,
+) -> std::io::Result<Option<String>> {
+    let id = QUERY_ID.fetch_add(1, Ordering::Relaxed);
+    let mut builder = Builder::new_query(id, true);
+    builder.add_question(
+        &get_reverse_name(address),
+        false,
+        QueryType::PTR,
+        QueryClass::IN,
+    );
+    let query = builder
+        .build()
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "Truncated query"))?;
+
+    let local: SocketAddr = if nameserver.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(nameserver)?;
+    // Registered until returning, so that the query and the response can be excluded from the capture
+    let _own_socket = register_own_socket(OwnProtocols::UDP, socket.local_addr()?, nameserver);
+    socket.send(&query)?;
+
+    let mut buffer = [0u8; 1500];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::new(ErrorKind::TimedOut, "Reverse lookup timed out"));
+        }
+
+        socket.set_read_timeout(Some(remaining))?;
+        let length = socket.recv(&mut buffer)?;
+
+        // Ignore unrelated datagrams
+        if let Some(hostname) = parse_ptr_response(&buffer[..length], id) {
+            return hostname;
+        }
+    }
+}
+
+/// Hostname carried by the response to the query with the given ID, `None` if the data is not such response
+fn parse_ptr_response(data: &[u8], id: u16) -> Option<std::io::Result<Option<String>>> {
+    let packet = Packet::parse(data).ok()?;
+    if packet.header.query || packet.header.id != id {
+        return None;
+    }
+
+    let result = match packet.header.response_code {
+        ResponseCode::NoError => Ok(packet.answers.iter().find_map(|answer| match &answer.data {
+            RData::PTR(ptr) => Some(ptr.0.to_string()),
+            _ => None,
+        })),
+        ResponseCode::NameError => Ok(None),
+        code => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Nameserver error: {:?}", code),
+        )),
+    };
+
+    Some(result)
+}
+
+/// Name to query for the PTR record of the address, e.g. `4.3.2.1.in-addr.arpa` for `1.2.3.4`
+fn get_reverse_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let octets = address.octets();
+            format!(
+                "{}.{}.{}.{}.in-addr.arpa",
+                octets[3], octets[2], octets[1], octets[0]
+            )
+        }
+        IpAddr::V6(address) => {
+            let mut name = String::new();
+            for octet in address.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", octet & 0x0f, octet >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// First nameserver of the system resolver configuration
+fn get_nameserver() -> Option<SocketAddr> {
+    let configuration = fs::read_to_string(RESOLV_CONF).ok()?;
+
+    configuration.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some("nameserver") {
+            return None;
+        }
+
+        // Scoped IPv6 addresses (fe80::1%eth0) are not supported
+        let address = fields.next()?.parse::<IpAddr>().ok()?;
+        Some(SocketAddr::new(address, 53))
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::IpAddr;
+
+    use dns_parser::{Builder, QueryClass, QueryType};
+
+    use super::{get_reverse_name, parse_ptr_response};
+
+    #[test]
+    fn reverse_names() {
+        assert_eq!(
+            get_reverse_name("192.168.1.10".parse::<IpAddr>().unwrap()),
+            "10.1.168.192.in-addr.arpa"
+        );
+        assert_eq!(
+            get_reverse_name("2001:db8::1".parse::<IpAddr>().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    fn build_response(id: u16, response_code: u8, hostname: Option<&str>) -> Vec<u8> {
+        let mut builder = Builder::new_query(id, true);
+        builder.add_question(
+            "10.1.168.192.in-addr.arpa",
+            false,
+            QueryType::PTR,
+            QueryClass::IN,
+        );
+        let mut response = builder.build().unwrap();
+
+        // QR flag and response code
+        response[2] |= 0x80;
+        response[3] = (response[3] & 0xf0) | response_code;
+
+        if let Some(hostname) = hostname {
+            response[7] = 1;
+
+            let mut rdata = vec![];
+            for label in hostname.split('.') {
+                rdata.push(label.len() as u8);
+                rdata.extend_from_slice(label.as_bytes());
+            }
+            rdata.push(0);
+
+            // Pointer to the question name, PTR, IN, TTL
+            response.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x0c, 0x00, 0x01, 0, 0, 0x0e, 0x10]);
+            response.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            response.extend_from_slice(&rdata);
+        }
+
+        response
+    }
+
+    #[test]
+    fn ptr_responses() {
+        let response = build_response(7, 0, Some("router.lan"));
+        assert_eq!(
+            parse_ptr_response(&response, 7).unwrap().unwrap(),
+            Some("router.lan".to_owned())
+        );
+
+        // Response to another query
+        assert!(parse_ptr_response(&response, 8).is_none());
+
+        // NXDOMAIN, cached as missing
+        let response = build_response(7, 3, None);
+        assert_eq!(parse_ptr_response(&response, 7).unwrap().unwrap(), None);
+
+        // SERVFAIL, to be retried
+        let response = build_response(7, 2, None);
+        assert!(parse_ptr_response(&response, 7).unwrap().is_err());
+    }
+}
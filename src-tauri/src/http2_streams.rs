@@ -0,0 +1,302 @@
+//! Streams of the HTTP/2 connections, rebuilt from their parsed frames
+//!
+//! Each stream gathers the header lists and the DATA payloads sent by the client (request) and by
+//! the server (response) on the same stream ID, along with the packets carrying its frames. Header
+//! lists following the first one of a side (trailers, interim responses) are appended to it.
+//! A stream promised by the server (PUSH_PROMISE) takes the promised header list as request.
+//!
+//! The client is the endpoint sending the connection preface; when the preface isn't captured,
+//! the endpoint given as source is assumed to be the client.
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::application::SerializableHttp2Packet;
+use sniffer_parser::serializable_packet::SerializablePacket;
+
+use crate::efficiency::get_endpoints;
+use crate::filtering::PacketsCollection;
+use crate::{SniffingError, SniffingState};
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Http2Stream {
+    pub stream_id: u32,
+    pub request_headers: Vec<(String, String)>,
+    pub response_headers: Vec<(String, String)>,
+    pub request_body: Vec<u8>,
+    pub response_body: Vec<u8>,
+    /// IDs of the packets carrying frames of the stream
+    pub packet_ids: Vec<usize>,
+    /// Whether each side closed its half of the stream (END_STREAM)
+    pub client_ended: bool,
+    pub server_ended: bool,
+    /// Error code of the RST_STREAM frame closing the stream, if any
+    pub reset: Option<String>,
+}
+
+type Endpoint = (IpAddr, u16);
+
+/// Returns the streams of the (cleartext) HTTP/2 connection between the two endpoints
+#[tauri::command]
+pub fn get_http2_streams(
+    state: tauri::State<SniffingState>,
+    source_ip: String,
+    source_port: u16,
+    dest_ip: String,
+    dest_port: u16,
+) -> Result<Vec<Http2Stream>, SniffingError> {
+    let parse_ip = |ip: &str| {
+        ip.parse::<IpAddr>().map_err(|e| {
+            warn!("Invalid connection IP address {}: {}", ip, e);
+            SniffingError::InvalidIpAddress(format!("Invalid connection IP address {}: {}", ip, e))
+        })
+    };
+
+    let source = (parse_ip(&source_ip)?, source_port);
+    let destination = (parse_ip(&dest_ip)?, dest_port);
+
+    let packets_collection = state.packets.lock().unwrap();
+    let streams = get_http2_streams_internal(source, destination, &packets_collection);
+
+    info!(
+        "HTTP/2 connection {:?} - {:?}: {} streams",
+        source,
+        destination,
+        streams.len()
+    );
+
+    Ok(streams)
+}
+
+fn get_http2_streams_internal(
+    source: Endpoint,
+    destination: Endpoint,
+    packets_collection: &PacketsCollection,
+) -> Vec<Http2Stream> {
+    let mut packets = vec![];
+    for packet in &packets_collection.http2_packets {
+        let http2_packet = match packet.get_application_layer_packet() {
+            Some(SerializablePacket::Http2Packet(http2_packet)) => http2_packet,
+            _ => continue,
+        };
+
+        match get_endpoints(packet) {
+            Some(endpoints) if endpoints == (source, destination) => {
+                packets.push((packet.get_id(), true, http2_packet))
+            }
+            Some(endpoints) if endpoints == (destination, source) => {
+                packets.push((packet.get_id(), false, http2_packet))
+            }
+            _ => continue,
+        }
+    }
+
+    // The preface tells the client apart, whatever the order of the endpoints
+    if packets
+        .iter()
+        .any(|(_, from_source, http2_packet)| !from_source && http2_packet.preface)
+    {
+        for (_, from_source, _) in packets.iter_mut() {
+            *from_source = !*from_source;
+        }
+    }
+
+    build_http2_streams(packets)
+}
+
+/// Rebuilds the streams from the HTTP/2 packets of a connection, each with its ID and whether it was sent by the client
+pub(crate) fn build_http2_streams<'a>(
+    packets: impl IntoIterator<Item = (usize, bool, &'a SerializableHttp2Packet)>,
+) -> Vec<Http2Stream> {
+    let mut streams: BTreeMap<u32, Http2Stream> = BTreeMap::new();
+
+    for (packet_id, from_client, http2_packet) in packets {
+        for frame in &http2_packet.frames {
+            // Stream 0 is the connection itself
+            if frame.stream_id == 0 {
+                continue;
+            }
+
+            let stream = streams
+                .entry(frame.stream_id)
+                .or_insert_with(|| Http2Stream {
+                    stream_id: frame.stream_id,
+                    ..Default::default()
+                });
+            if stream.packet_ids.last() != Some(&packet_id) {
+                stream.packet_ids.push(packet_id);
+            }
+
+            match frame.frame_type.as_str() {
+                "HEADERS" | "CONTINUATION" => {
+                    if let Some(headers) = &frame.headers {
+                        if from_client {
+                            stream.request_headers.extend(headers.iter().cloned());
+                        } else {
+                            stream.response_headers.extend(headers.iter().cloned());
+                        }
+                    }
+                }
+                "DATA" => {
+                    if let Some(data) = &frame.data {
+                        if from_client {
+                            stream.request_body.extend_from_slice(data);
+                        } else {
+                            stream.response_body.extend_from_slice(data);
+                        }
+                    }
+                }
+                "RST_STREAM" => stream.reset = frame.error_code.clone(),
+                _ => (),
+            }
+
+            if frame.end_stream {
+                if from_client {
+                    stream.client_ended = true;
+                } else {
+                    stream.server_ended = true;
+                }
+            }
+
+            if let (Some(promised_stream_id), Some(headers)) =
+                (frame.promised_stream_id, &frame.headers)
+            {
+                let promised = streams
+                    .entry(promised_stream_id)
+                    .or_insert_with(|| Http2Stream {
+                        stream_id: promised_stream_id,
+                        ..Default::default()
+                    });
+                promised.request_headers.extend(headers.iter().cloned());
+                promised.packet_ids.push(packet_id);
+            }
+        }
+    }
+
+    streams.into_values().collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::application::{Http2Frame, SerializableHttp2Packet};
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::get_http2_streams_internal;
+
+    fn header(name: &str, value: &str) -> (String, String) {
+        (name.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn streams_rebuilt() {
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 80));
+        let headers = |stream_id, end_stream, headers: Vec<(String, String)>| Http2Frame {
+            frame_type: "HEADERS".to_owned(),
+            stream_id,
+            end_stream,
+            headers: Some(headers),
+            ..Default::default()
+        };
+        let data = |stream_id, end_stream, data: &[u8]| Http2Frame {
+            frame_type: "DATA".to_owned(),
+            stream_id,
+            end_stream,
+            data: Some(data.to_vec()),
+            ..Default::default()
+        };
+
+        let packets = [
+            (
+                true,
+                true,
+                vec![
+                    Http2Frame {
+                        frame_type: "SETTINGS".to_owned(),
+                        settings: Some(vec![]),
+                        ..Default::default()
+                    },
+                    headers(
+                        1,
+                        true,
+                        vec![header(":method", "GET"), header(":path", "/")],
+                    ),
+                    headers(3, false, vec![header(":method", "POST")]),
+                ],
+            ),
+            (
+                false,
+                false,
+                vec![headers(1, false, vec![header(":status", "200")])],
+            ),
+            (true, false, vec![data(3, true, b"form")]),
+            (
+                false,
+                false,
+                vec![
+                    data(1, false, b"hello "),
+                    data(1, true, b"world"),
+                    Http2Frame {
+                        frame_type: "RST_STREAM".to_owned(),
+                        stream_id: 3,
+                        error_code: Some("REFUSED_STREAM".to_owned()),
+                        ..Default::default()
+                    },
+                ],
+            ),
+        ];
+
+        let mut packets_collection = PacketsCollection::new();
+        for (id, (from_client, preface, frames)) in packets.into_iter().enumerate() {
+            let template = if from_client {
+                build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), client, server, 4444, 80)
+            } else {
+                build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), server, client, 80, 4444)
+            };
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+            parsed_packet.set_application_layer_packet(Some(SerializablePacket::Http2Packet(
+                SerializableHttp2Packet { preface, frames },
+            )));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        // Given in reverse, the preface still tells the client apart
+        let streams = get_http2_streams_internal(
+            (IpAddr::V4(server), 80),
+            (IpAddr::V4(client), 4444),
+            &packets_collection,
+        );
+        assert_eq!(streams.len(), 2);
+
+        assert_eq!(streams[0].stream_id, 1);
+        assert_eq!(streams[0].request_headers[1], header(":path", "/"));
+        assert_eq!(streams[0].response_headers, vec![header(":status", "200")]);
+        assert_eq!(streams[0].response_body, b"hello world");
+        assert_eq!(streams[0].packet_ids, vec![0, 1, 3]);
+        assert!(streams[0].client_ended && streams[0].server_ended);
+        assert_eq!(streams[0].reset, None);
+
+        assert_eq!(streams[1].request_body, b"form");
+        assert!(streams[1].client_ended && !streams[1].server_ended);
+        assert_eq!(streams[1].reset.as_deref(), Some("REFUSED_STREAM"));
+
+        assert!(get_http2_streams_internal(
+            (IpAddr::V4(client), 4445),
+            (IpAddr::V4(server), 80),
+            &packets_collection,
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+//! Lookup of the network interfaces by a user provided selector
+//!
+//! Interfaces can be selected by any of:
+//! - name (e.g. `eth0.100`, `\Device\NPF_{...}`) or description, ignoring case when no exact match exists
+//! - MAC address (e.g. `aa:bb:cc:dd:ee:ff`)
+//! - index, as assigned by the operating system
+//! - GUID of the Npcap device (Windows), with or without braces
+//!
+//! Listed interfaces can be narrowed down to the ones able to capture, by their flags and addresses
+//!
+//! The speed of the link is read from sysfs on Linux, other platforms don't report it
+
+use log::warn;
+use pnet::datalink::NetworkInterface;
+use pnet::util::MacAddr;
+
+use crate::SniffingError;
+
+/// Name of the interface shown to the user and used to identify its sniffing process
+pub fn get_interface_display_name(interface: &NetworkInterface) -> String {
+    if cfg!(target_os = "windows") {
+        interface.description.clone()
+    } else {
+        interface.name.clone()
+    }
+}
+
+/// Capabilities required to the listed interfaces, none by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InterfaceFilter {
+    pub up_only: bool,
+    pub exclude_loopback: bool,
+    /// At least an IP address assigned
+    pub require_address: bool,
+}
+
+impl InterfaceFilter {
+    pub fn matches(&self, interface: &NetworkInterface) -> bool {
+        (!self.up_only || interface.is_up())
+            && (!self.exclude_loopback || !interface.is_loopback())
+            && (!self.require_address || !interface.ips.is_empty())
+    }
+}
+
+/// Finds the interface matching the selector, trying the most specific criteria first
+pub fn find_interface(
+    interfaces: Vec<NetworkInterface>,
+    selector: &str,
+) -> Result<NetworkInterface, SniffingError> {
+    let selector = selector.trim();
+    let guid = selector.trim_start_matches('{').trim_end_matches('}');
+
+    let criteria: [&dyn Fn(&NetworkInterface) -> bool; 5] = [
+        &|iface| {
+            iface.name == selector
+                || (!iface.description.is_empty() && iface.description == selector)
+        },
+        &|iface| {
+            iface.name.eq_ignore_ascii_case(selector)
+                || (!iface.description.is_empty()
+                    && iface.description.eq_ignore_ascii_case(selector))
+        },
+        &|iface| {
+            let mac = selector.parse::<MacAddr>().ok();
+            mac.is_some() && iface.mac == mac
+        },
+        &|iface| selector.parse::<u32>().ok() == Some(iface.index),
+        &|iface| {
+            !guid.is_empty()
+                && iface
+                    .name
+                    .to_ascii_lowercase()
+                    .ends_with(&format!("{{{}}}", guid.to_ascii_lowercase()))
+        },
+    ];
+
+    for matches in criteria {
+        if let Some(interface) = interfaces.iter().find(|iface| matches(iface)) {
+            return Ok(interface.clone());
+        }
+    }
+
+    let available = interfaces
+        .iter()
+        .map(get_interface_display_name)
+        .collect::<Vec<String>>();
+
+    warn!("Interface not found: {}", selector);
+    Err(SniffingError::InterfaceNotFound(format!(
+        "Interface not found: {}. Available interfaces: {}",
+        selector,
+        available.join(", ")
+    )))
+}
+
+/// Speed of the link of the interface in bits per second, as reported by the kernel (in Mb/s), `None` when
+/// the link is down or the driver doesn't report it
+#[cfg(target_os = "linux")]
+pub fn get_link_speed(interface: &NetworkInterface) -> Option<u64> {
+    let speed = std::fs::read_to_string(format!("/sys/class/net/{}/speed", interface.name)).ok()?;
+
+    speed
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|speed| *speed > 0)
+        .map(|speed| speed * 1_000_000)
+}
+
+/// Speed of the link of the interface in bits per second, not reported on this platform
+#[cfg(not(target_os = "linux"))]
+pub fn get_link_speed(_interface: &NetworkInterface) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+pub mod tests {
+    use pnet::datalink::NetworkInterface;
+    use pnet::util::MacAddr;
+
+    use crate::SniffingError;
+
+    use super::{find_interface, InterfaceFilter};
+
+    fn build_interface(
+        name: &str,
+        description: &str,
+        index: u32,
+        mac: MacAddr,
+    ) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            index,
+            mac: Some(mac),
+            ips: vec![],
+            flags: 0,
+        }
+    }
+
+    #[test]
+    fn interface_selectors() {
+        let interfaces = vec![
+            build_interface("eth0", "", 2, MacAddr::new(0, 1, 2, 3, 4, 5)),
+            build_interface("eth0.100", "", 5, MacAddr::new(0, 1, 2, 3, 4, 5)),
+            build_interface(
+                "\\Device\\NPF_{0D1A9D42-8A40-4E3B-A8C4-8C1F6E5A2B11}",
+                "Intel(R) Ethernet Connection",
+                7,
+                MacAddr::new(0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff),
+            ),
+        ];
+
+        let find = |selector| find_interface(interfaces.clone(), selector).map(|i| i.index);
+
+        assert_eq!(find("eth0.100").unwrap(), 5);
+        assert_eq!(find(" ETH0 ").unwrap(), 2);
+        assert_eq!(find("intel(r) ethernet connection").unwrap(), 7);
+        assert_eq!(find("AA:BB:CC:DD:EE:FF").unwrap(), 7);
+        assert_eq!(find("5").unwrap(), 5);
+        assert_eq!(find("{0d1a9d42-8a40-4e3b-a8c4-8c1f6e5a2b11}").unwrap(), 7);
+
+        match find("wlan0") {
+            Err(SniffingError::InterfaceNotFound(message)) => {
+                assert!(message.contains("eth0, eth0.100"))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Interface flags as defined by the platform
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn interfaces_filtered_by_capability() {
+        let mut loopback = build_interface("lo", "", 1, MacAddr::zero());
+        loopback.flags = (libc::IFF_UP | libc::IFF_LOOPBACK) as u32;
+        loopback.ips = vec!["127.0.0.1/8".parse().unwrap()];
+        let mut ethernet = build_interface("eth0", "", 2, MacAddr::new(0, 1, 2, 3, 4, 5));
+        ethernet.flags = libc::IFF_UP as u32;
+        ethernet.ips = vec!["192.168.1.10/24".parse().unwrap()];
+        let mut bridge = build_interface("br0", "", 3, MacAddr::new(0, 1, 2, 3, 4, 6));
+        bridge.flags = libc::IFF_UP as u32;
+        let down = build_interface("eth1", "", 4, MacAddr::new(0, 1, 2, 3, 4, 7));
+        let interfaces = [loopback, ethernet, bridge, down];
+
+        let list = |filter: InterfaceFilter| {
+            interfaces
+                .iter()
+                .filter(|interface| filter.matches(interface))
+                .map(|interface| interface.name.as_str())
+                .collect::<Vec<&str>>()
+        };
+
+        assert_eq!(
+            list(InterfaceFilter::default()),
+            vec!["lo", "eth0", "br0", "eth1"]
+        );
+        assert_eq!(
+            list(InterfaceFilter {
+                up_only: true,
+                ..Default::default()
+            }),
+            vec!["lo", "eth0", "br0"]
+        );
+        assert_eq!(
+            list(InterfaceFilter {
+                up_only: true,
+                exclude_loopback: true,
+                require_address: true,
+            }),
+            vec!["eth0"]
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! Round-trip times measured on the collected TCP connections
+//!
+//! Two kinds of samples are taken at the capture point:
+//! - handshake: time between the SYN and the matching SYN/ACK, mostly depending on network latency
+//!   since the handshake is answered by the kernel of the server
+//! - data: time between a data segment and the first ACK covering it, also including the time
+//!   spent by the receiver before acknowledging (e.g. processing time, delayed ACKs)
+//!
+//! Following Karn's algorithm, retransmitted segments (SYNs included) don't produce samples,
+//! since it is not possible to tell which transmission is being acknowledged
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pnet::packet::tcp::TcpFlags;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::filtering::apply_all_strong_filters;
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod RttSampleKinds {
+    pub const HANDSHAKE: &str = "handshake";
+    pub const DATA: &str = "data";
+}
+
+/// Round-trip time of a single segment
+#[derive(Serialize, Debug, Clone)]
+pub struct RttSample {
+    pub kind: String,
+    /// ID of the packet acknowledging the segment
+    pub packet_id: usize,
+    /// Round-trip time, in microseconds
+    pub rtt: i64,
+}
+
+/// Summary of a set of RTT samples, in microseconds
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RttStatistics {
+    pub samples: usize,
+    pub min: i64,
+    pub avg: f64,
+    pub max: i64,
+    pub stddev: f64,
+}
+
+/// RTT samples of a TCP connection, identified by the endpoint that sent the first packet
+#[derive(Serialize, Debug, Clone)]
+pub struct ConnectionRtt {
+    pub client_ip: String,
+    pub client_port: String,
+    pub server_ip: String,
+    pub server_port: String,
+    pub samples: Vec<RttSample>,
+    pub handshake: Option<RttStatistics>,
+    pub data: Option<RttStatistics>,
+}
+
+/// RTT samples of all the connections, with the statistics of the whole capture
+#[derive(Serialize, Debug, Clone)]
+pub struct RttReport {
+    pub connections: Vec<ConnectionRtt>,
+    pub handshake: Option<RttStatistics>,
+    pub data: Option<RttStatistics>,
+}
+
+/// Returns the RTT samples of the TCP connections among the packets matching the filters
+#[tauri::command]
+pub fn get_rtt_samples<'a>(
+    state: tauri::State<SniffingState>,
+    filters_value: Vec<(&'a str, &'a str)>,
+) -> Result<RttReport, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+
+    let packets = if filters_value.is_empty() {
+        packets_collection.tcp_packets.clone()
+    } else {
+        let end = packets_collection.packets.len();
+        apply_all_strong_filters(end, &filters_value, &mut packets_collection)?
+    };
+
+    Ok(get_rtt_samples_internal(&packets))
+}
+
+type Endpoint = (String, String);
+
+/// Data segment waiting to be acknowledged
+struct OutstandingSegment {
+    sequence: u32,
+    next_sequence: u32,
+    timestamp: i64,
+    retransmitted: bool,
+}
+
+struct ConnectionState {
+    connection: ConnectionRtt,
+    client: Endpoint,
+    /// Sequence number and timestamp of the SYN sent by the client, if not retransmitted
+    syn: Option<(u32, i64)>,
+    syn_retransmitted: bool,
+    /// Outstanding segments sent by the client and by the server
+    outstanding: (Vec<OutstandingSegment>, Vec<OutstandingSegment>),
+}
+
+fn get_rtt_samples_internal(packets: &[Arc<ParsedPacket>]) -> RttReport {
+    let mut connections: Vec<ConnectionState> = vec![];
+    let mut connection_indexes: HashMap<(Endpoint, Endpoint), usize> = HashMap::new();
+
+    for packet in packets {
+        let tcp_packet = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => continue,
+        };
+
+        let (source, destination) = match (
+            get_source_ip(packet).zip(get_source_port(packet)),
+            get_dest_ip(packet).zip(get_dest_port(packet)),
+        ) {
+            (Some(source), Some(destination)) => (source, destination),
+            _ => continue,
+        };
+
+        let key = if source <= destination {
+            (source.clone(), destination.clone())
+        } else {
+            (destination.clone(), source.clone())
+        };
+
+        let index = *connection_indexes.entry(key).or_insert_with(|| {
+            connections.push(ConnectionState {
+                connection: ConnectionRtt {
+                    client_ip: source.0.clone(),
+                    client_port: source.1.clone(),
+                    server_ip: destination.0.clone(),
+                    server_port: destination.1.clone(),
+                    samples: vec![],
+                    handshake: None,
+                    data: None,
+                },
+                client: source.clone(),
+                syn: None,
+                syn_retransmitted: false,
+                outstanding: (vec![], vec![]),
+            });
+            connections.len() - 1
+        });
+
+        let state = &mut connections[index];
+        let from_client = source == state.client;
+        let timestamp = packet.get_timestamp();
+        let flags = tcp_packet.flags;
+
+        if flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK == 0 {
+            if from_client {
+                match state.syn {
+                    Some((sequence, _)) if sequence == tcp_packet.sequence => {
+                        state.syn_retransmitted = true
+                    }
+                    _ if state.syn_retransmitted => (),
+                    _ => state.syn = Some((tcp_packet.sequence, timestamp)),
+                }
+            }
+            continue;
+        }
+
+        if flags & TcpFlags::SYN != 0 && flags & TcpFlags::ACK != 0 {
+            if let (false, Some((sequence, syn_timestamp))) = (from_client, state.syn.take()) {
+                if !state.syn_retransmitted
+                    && tcp_packet.acknowledgement == sequence.wrapping_add(1)
+                {
+                    state.connection.samples.push(RttSample {
+                        kind: RttSampleKinds::HANDSHAKE.to_owned(),
+                        packet_id: packet.get_id(),
+                        rtt: timestamp - syn_timestamp,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let (sent, received) = if from_client {
+            (&mut state.outstanding.0, &mut state.outstanding.1)
+        } else {
+            (&mut state.outstanding.1, &mut state.outstanding.0)
+        };
+
+        // Acknowledge the segments sent by the other endpoint
+        if flags & TcpFlags::ACK != 0 {
+            let acknowledgement = tcp_packet.acknowledgement;
+            let acknowledged = received
+                .iter()
+                .filter(|segment| sequence_at_or_before(segment.next_sequence, acknowledgement))
+                .max_by_key(|segment| segment.next_sequence.wrapping_sub(acknowledgement) as i32);
+
+            if let Some(segment) = acknowledged {
+                if !segment.retransmitted {
+                    state.connection.samples.push(RttSample {
+                        kind: RttSampleKinds::DATA.to_owned(),
+                        packet_id: packet.get_id(),
+                        rtt: timestamp - segment.timestamp,
+                    });
+                }
+            }
+
+            received
+                .retain(|segment| !sequence_at_or_before(segment.next_sequence, acknowledgement));
+        }
+
+        if tcp_packet.length > 0 {
+            match sent
+                .iter_mut()
+                .find(|segment| segment.sequence == tcp_packet.sequence)
+            {
+                Some(segment) => segment.retransmitted = true,
+                None => sent.push(OutstandingSegment {
+                    sequence: tcp_packet.sequence,
+                    next_sequence: tcp_packet.sequence.wrapping_add(tcp_packet.length as u32),
+                    timestamp,
+                    retransmitted: false,
+                }),
+            }
+        }
+    }
+
+    let mut connections: Vec<ConnectionRtt> = connections
+        .into_iter()
+        .map(|state| state.connection)
+        .filter(|connection| !connection.samples.is_empty())
+        .collect();
+
+    for connection in connections.iter_mut() {
+        connection.handshake = get_statistics(&connection.samples, RttSampleKinds::HANDSHAKE);
+        connection.data = get_statistics(&connection.samples, RttSampleKinds::DATA);
+    }
+
+    let samples: Vec<RttSample> = connections
+        .iter()
+        .flat_map(|connection| connection.samples.iter().cloned())
+        .collect();
+
+    RttReport {
+        handshake: get_statistics(&samples, RttSampleKinds::HANDSHAKE),
+        data: get_statistics(&samples, RttSampleKinds::DATA),
+        connections,
+    }
+}
+
+/// Compares sequence numbers taking wrap around into account
+pub(crate) fn sequence_at_or_before(sequence: u32, other: u32) -> bool {
+    other.wrapping_sub(sequence) as i32 >= 0
+}
+
+fn get_statistics(samples: &[RttSample], kind: &str) -> Option<RttStatistics> {
+    let rtts: Vec<i64> = samples
+        .iter()
+        .filter(|sample| sample.kind == kind)
+        .map(|sample| sample.rtt)
+        .collect();
+
+    get_rtt_statistics(&rtts)
+}
+
+/// Statistics of the round-trip times, `None` without any
+pub(crate) fn get_rtt_statistics(rtts: &[i64]) -> Option<RttStatistics> {
+    if rtts.is_empty() {
+        return None;
+    }
+
+    let avg = rtts.iter().sum::<i64>() as f64 / rtts.len() as f64;
+    let variance = rtts
+        .iter()
+        .map(|rtt| (*rtt as f64 - avg).powi(2))
+        .sum::<f64>()
+        / rtts.len() as f64;
+
+    Some(RttStatistics {
+        samples: rtts.len(),
+        min: *rtts.iter().min().unwrap(),
+        avg,
+        max: *rtts.iter().max().unwrap(),
+        stddev: variance.sqrt(),
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{get_rtt_samples_internal, RttStatistics};
+
+    fn build_tcp_packet(
+        id: usize,
+        timestamp: i64,
+        to_server: bool,
+        flags: u16,
+        sequence: u32,
+        acknowledgement: u32,
+        length: usize,
+    ) -> Arc<ParsedPacket> {
+        let (client, server) = (Ipv4Addr::new(10, 10, 10, 10), Ipv4Addr::new(11, 11, 11, 11));
+        let template = if to_server {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), client, server, 4444, 80)
+        } else {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), server, client, 80, 4444)
+        };
+
+        let mut tcp_packet = match template.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.flags = flags;
+        tcp_packet.sequence = sequence;
+        tcp_packet.acknowledgement = acknowledgement;
+        tcp_packet.length = length;
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_timestamp(timestamp);
+        parsed_packet.set_link_layer_packet(template.get_link_layer_packet().cloned());
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn handshake_and_data_rtt() {
+        let packets = vec![
+            build_tcp_packet(0, 0, true, TcpFlags::SYN, 100, 0, 0),
+            build_tcp_packet(1, 20, false, TcpFlags::SYN | TcpFlags::ACK, 500, 101, 0),
+            build_tcp_packet(2, 25, true, TcpFlags::ACK, 101, 501, 0),
+            build_tcp_packet(3, 30, true, TcpFlags::ACK | TcpFlags::PSH, 101, 501, 10),
+            build_tcp_packet(4, 40, true, TcpFlags::ACK | TcpFlags::PSH, 111, 501, 10),
+            // Cumulative ACK of both segments, sampled on the last one
+            build_tcp_packet(5, 70, false, TcpFlags::ACK, 501, 121, 0),
+            build_tcp_packet(6, 80, true, TcpFlags::ACK | TcpFlags::PSH, 121, 501, 10),
+            build_tcp_packet(7, 100, false, TcpFlags::ACK, 501, 131, 0),
+        ];
+
+        let report = get_rtt_samples_internal(&packets);
+        assert_eq!(report.connections.len(), 1);
+
+        let connection = &report.connections[0];
+        assert_eq!(connection.client_ip, "10.10.10.10");
+        assert_eq!(connection.server_port, "80");
+        assert_eq!(
+            connection.handshake,
+            Some(RttStatistics {
+                samples: 1,
+                min: 20,
+                avg: 20.0,
+                max: 20,
+                stddev: 0.0
+            })
+        );
+        assert_eq!(
+            connection.data,
+            Some(RttStatistics {
+                samples: 2,
+                min: 20,
+                avg: 25.0,
+                max: 30,
+                stddev: 5.0
+            })
+        );
+        assert_eq!(report.data, connection.data);
+    }
+
+    #[test]
+    fn retransmissions_are_not_sampled() {
+        let packets = vec![
+            build_tcp_packet(0, 0, true, TcpFlags::SYN, 100, 0, 0),
+            build_tcp_packet(1, 1_000_000, true, TcpFlags::SYN, 100, 0, 0),
+            build_tcp_packet(
+                2,
+                1_000_020,
+                false,
+                TcpFlags::SYN | TcpFlags::ACK,
+                500,
+                101,
+                0,
+            ),
+            build_tcp_packet(
+                3,
+                1_000_030,
+                true,
+                TcpFlags::ACK | TcpFlags::PSH,
+                101,
+                501,
+                10,
+            ),
+            build_tcp_packet(
+                4,
+                1_000_300,
+                true,
+                TcpFlags::ACK | TcpFlags::PSH,
+                101,
+                501,
+                10,
+            ),
+            build_tcp_packet(5, 1_000_320, false, TcpFlags::ACK, 501, 111, 0),
+        ];
+
+        let report = get_rtt_samples_internal(&packets);
+
+        assert!(report.connections.is_empty());
+        assert_eq!(report.handshake, None);
+        assert_eq!(report.data, None);
+    }
+}
@@ -0,0 +1,215 @@
+//! Utilization of the captured link over time, as a percentage of its capacity
+//!
+//! The bytes of the collected frames are counted in buckets of active capture time, as the conversation
+//! timelines do, and divided by what the link can carry in a bucket. The speed of the link is the one
+//! given by the user or, when not given, the one reported for the selected interface.
+//!
+//! Only the captured bytes are counted: the preamble and inter-frame gap of Ethernet are not, and
+//! frames truncated by the snap length count for their captured part, so the utilization is a lower bound.
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::conversations::get_active_time;
+use crate::filtering::PacketsCollection;
+use crate::interfaces::get_link_speed;
+use crate::{SniffingError, SniffingState};
+
+/// Traffic of the link in a slot of active capture time
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct UtilizationBucket {
+    /// Active capture time since the first packet, in milliseconds
+    pub offset: u64,
+    pub bytes: usize,
+    pub bits_per_second: f64,
+    /// Percentage of the capacity of the link
+    pub utilization: f64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct LinkUtilization {
+    pub bucket_ms: u64,
+    pub link_bps: u64,
+    /// Timestamp of the first packet, in microseconds since the Unix epoch
+    pub first_timestamp: Option<i64>,
+    /// Consecutive buckets, from the first to the last packet
+    pub buckets: Vec<UtilizationBucket>,
+    pub peak_utilization: Option<f64>,
+}
+
+/// Returns the utilization of the link over time, in buckets of `bucket_ms` milliseconds
+///
+/// `link_bps` is the speed of the link in bits per second, required when the platform doesn't report
+/// the one of the selected interface (or when the packets were loaded from a file)
+#[tauri::command]
+pub fn get_link_utilization(
+    state: tauri::State<SniffingState>,
+    bucket_ms: u64,
+    link_bps: Option<u64>,
+) -> Result<LinkUtilization, SniffingError> {
+    if bucket_ms == 0 {
+        return Err(SniffingError::InvalidConfiguration(
+            "The bucket size must be positive".to_owned(),
+        ));
+    }
+
+    let link_bps = match link_bps {
+        Some(link_bps) => link_bps,
+        None => {
+            let sniffing_info = state.info.lock().unwrap();
+            sniffing_info
+                .interface
+                .as_ref()
+                .and_then(get_link_speed)
+                .ok_or_else(|| {
+                    warn!("Link speed unknown");
+                    SniffingError::InvalidConfiguration(
+                        "The link speed of the interface is unknown, it must be given".to_owned(),
+                    )
+                })?
+        }
+    };
+
+    if link_bps == 0 {
+        return Err(SniffingError::InvalidConfiguration(
+            "The link speed must be positive".to_owned(),
+        ));
+    }
+
+    let packets_collection = state.packets.lock().unwrap();
+    let utilization = get_link_utilization_internal(bucket_ms, link_bps, &packets_collection);
+
+    info!(
+        "Link utilization at {} b/s: {} buckets, peak {:?}%",
+        link_bps,
+        utilization.buckets.len(),
+        utilization.peak_utilization
+    );
+
+    Ok(utilization)
+}
+
+fn get_link_utilization_internal(
+    bucket_ms: u64,
+    link_bps: u64,
+    packets_collection: &PacketsCollection,
+) -> LinkUtilization {
+    let bucket_length = bucket_ms.saturating_mul(1000);
+    let mut utilization = LinkUtilization {
+        bucket_ms,
+        link_bps,
+        ..Default::default()
+    };
+
+    let mut first_active_time = None;
+    for packet in &packets_collection.packets {
+        let active_time = get_active_time(
+            packet.get_timestamp(),
+            &packets_collection.capture_intervals,
+        );
+        let first_active_time = *first_active_time.get_or_insert_with(|| {
+            utilization.first_timestamp = Some(packet.get_timestamp());
+            active_time
+        });
+
+        let index = ((active_time - first_active_time).max(0) as u64 / bucket_length) as usize;
+        while utilization.buckets.len() <= index {
+            utilization.buckets.push(UtilizationBucket {
+                offset: utilization.buckets.len() as u64 * bucket_ms,
+                ..Default::default()
+            });
+        }
+
+        utilization.buckets[index].bytes += packets_collection
+            .raw_packets
+            .get(&packet.get_id())
+            .map_or(0, |raw_packet| raw_packet.data.len());
+    }
+
+    let bucket_secs = bucket_ms as f64 / 1000.0;
+    for bucket in utilization.buckets.iter_mut() {
+        bucket.bits_per_second = (bucket.bytes * 8) as f64 / bucket_secs;
+        bucket.utilization = bucket.bits_per_second * 100.0 / link_bps as f64;
+    }
+
+    utilization.peak_utilization = utilization
+        .buckets
+        .iter()
+        .map(|bucket| bucket.utilization)
+        .reduce(f64::max);
+
+    utilization
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::get_link_utilization_internal;
+
+    #[test]
+    fn utilization_over_time() {
+        let mut packets_collection = PacketsCollection::new();
+        // Paused for 8 seconds after the first second
+        packets_collection.capture_intervals =
+            vec![(1_000_000, Some(2_000_000)), (10_000_000, None)];
+
+        for (id, (timestamp, length)) in [
+            (1_000_000, 1000),
+            (1_400_000, 1500),
+            (10_100_000, 500),
+            (11_900_000, 250),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &vec![0; length]);
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2),
+                4444,
+                80,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        // 100 kb/s, 50000 bits per bucket
+        let utilization = get_link_utilization_internal(500, 100_000, &packets_collection);
+
+        assert_eq!(utilization.first_timestamp, Some(1_000_000));
+        assert_eq!(
+            utilization
+                .buckets
+                .iter()
+                .map(|bucket| (bucket.offset, bucket.bytes, bucket.utilization))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, 2500, 40.0),
+                (500, 0, 0.0),
+                (1000, 500, 8.0),
+                (1500, 0, 0.0),
+                (2000, 0, 0.0),
+                (2500, 250, 4.0)
+            ]
+        );
+        assert_eq!(utilization.buckets[2].bits_per_second, 8000.0);
+        assert_eq!(utilization.peak_utilization, Some(40.0));
+
+        let empty = get_link_utilization_internal(500, 100_000, &PacketsCollection::new());
+        assert!(empty.buckets.is_empty() && empty.peak_utilization.is_none());
+    }
+}
@@ -1,32 +1,213 @@
 //! Packet sniffing application built with Tauri
 //!
 //! Functionalities
-//! - List all available network interfaces
-//! - Select a network interface
+//! - List all available network interfaces, optionally only the ones up, not loopback or with an address
+//! - Select a network interface by name, description, MAC address, index or GUID
+//! - Select several network interfaces, sniffed concurrently into the same collection
 //! - Start the sniffing process
-//! - Stop the sniffing process
+//! - Stop the sniffing process, immediately or after draining the frames buffered by the interface
 //! - Pause the sniffing process
 //! - Resume the sniffing process
+//! - Append a new sniffing process to the already collected packets
+//! - Emit the captured bytes and packets at a regular interval, for the bitrate chart
+//! - Capture only the frames matching a BPF filter, dropped by the kernel otherwise
 //! - Generate a .csv report of the collected data
+//! - Anonymize the addresses written in reports
+//! - Write the report timestamps in ISO-8601 with sub-second precision, optionally in UTC
+//! - Get only the packets collected since the last request
+//! - Summarize the collected data
+//! - Enable or disable application layer dissectors
+//! - Label the payload of registered EtherTypes (e.g. industrial protocols), not parsed natively
+//! - Notify the sniffing process status periodically, even while idle
+//! - Confirm the effective configuration of each started sniffing process
+//! - Load packets from a pcap, pcapng or NetMon 2.x file, optionally gzip-compressed, keeping the direction
+//!   and hostnames recorded by pcapng
+//! - Load the capture files of a directory (e.g. rotated by a capture tool) as a single capture, merged by timestamp
+//! - Import a pcap file of Ethernet frames, refusing the other link types
+//! - Extract the files transferred over HTTP and FTP
+//! - Measure the round-trip times of TCP connections
+//! - Measure the round-trip times and losses of pings, pairing ICMP and ICMPv6 echoes
+//! - Export each conversation in a separate pcap file
+//! - Export the (filtered) packets in a pcap file, optionally sorted by capture timestamp
+//! - Track the state of TCP connections, flagging keep-alives and listing the idle ones
+//! - Bound the number of keys of the filtering indexes
+//! - Resolve the hostnames of IP addresses
+//! - Describe the capture session with a free-text comment, saved along with the exported pcap files
+//! - Check the privileges required to capture, optionally escalating through sudo at startup
+//! - Name the services of the transport ports, with custom names for non-standard ports
+//! - Name the hardware vendors of the MAC addresses, from a bundled or loaded OUI table, and filter by vendor
+//! - Report the credentials sent in cleartext, in the opt-in audit mode
+//! - Parse the captured frames in a separate thread, dropping or briefly waiting on bursts
+//! - List the capture sessions saved in a directory, with their metadata
+//! - Sample evenly spaced packets of the (filtered) capture for a quick overview
+//! - Follow the UDP stream between two endpoints
+//! - Guess the operating system and hop distance of the hosts from the IP TTL
+//! - Chart the throughput of a conversation over the active capture time
+//! - Capture the traffic of loopback interfaces (BSD loopback encapsulation)
+//! - Export the ARP table and the DNS resolution history in .csv files
+//! - List the distinct values of a field, most frequent first, to build filters
+//! - Save and load named capture profiles (interface, dissectors, heartbeat, backpressure, index limits)
+//! - Rebuild the hops of UDP and ICMP traceroutes, with the round-trip time of each probe
+//! - Schedule captures in a time window, once or every day
+//! - Capture a given number of packets in one shot, returning them (or the ones captured before a timeout)
+//! - Compute the size distribution of the (filtered) packets, overall and by protocol
+//! - Detect IP address conflicts, an IP address seen with more than one MAC address
+//! - Flag the destinations contacted at regular intervals (beaconing), as a heuristic hint
+//! - Measure the goodput and retransmissions of a TCP connection, in each direction
+//! - List the supported protocols, with the coverage of their parsing and their fields
+//! - Annotate the packets with expert info (e.g. malformed, bad checksum, reset, retransmission) and
+//!   summarize it by severity and message
+//! - Compare the collected traffic with a baseline (saved session or expected flows), flagging the new flows
+//! - Exclude the traffic of the app itself (e.g. the reverse DNS lookups) from the collected packets
+//! - List the active periods of the capture, separated by silences longer than a threshold
+//! - Measure the response times of the DNS queries, listing the ones without response
+//! - Bookmark packets and jump between the bookmarks, saved along with the exported pcap files
+//! - Capture through a memory-mapped AF_PACKET ring (TPACKET_V3) on Linux, for high-rate links
+//! - Size the buffers and the read timeout of the capture channel, for high-rate links
+//! - Capture in promiscuous mode or only the traffic addressed to the host
+//! - Store the raw bytes of the packets in memory-mapped files on Linux, for captures larger than the RAM
+//! - Parse again the collected packets with other dissectors, reporting the reclassified ones
+//! - Color the packets by conversation, with a stable color for each flow
+//! - Fingerprint the TLS clients and servers (JA3, JA3S) and filter the packets by fingerprint
+//! - Graph the sequence numbers, acknowledgements and windows of a TCP connection over time
+//! - Strip the Ethernet FCS kept in the captured frames by some NICs, always or when detected
+//! - Push the parsed packets (whole or summarized) to the frontend in batches, as an opt-in to polling
+//! - Time the TCP and UDP packets relative to the start of their flow, and filter by it
+//! - Validate a textual filter expression without applying it, locating the failure
+//! - Search a substring in the payloads of the packets, in parallel on large captures
+//! - Sample the flows, collecting only their first packet (and optionally their closing segments)
+//! - Chart the payload entropy of a conversation over time, to spot the start of encrypted transfers
+//! - Decrypt the TLS connections with the secrets of an NSS key log file (`SSLKEYLOGFILE`), as an opt-in
+//! - Parse the HTTP/2 frames over cleartext (h2c) and decrypted TLS, rebuilding the streams of a connection
+//! - List the TCP resets with the connection they terminated, telling the abrupt ones from the refused or closing ones
+//! - Chart the utilization of the link over time, as a percentage of its (reported or given) speed
+//! - List the TCP connection attempts that never got established, unanswered, refused or reset
 //!
 //! Errors
 //! These are the errors that can occur during the sniffing process, grouped by the action that can cause them:
+//! the errors of the actions involving an interface also carry the action and the interface name.
 //!
 //! - Select interface
-//!     - Inexistent
+//!     - Inexistent (reporting the available ones)
+//! - Select interfaces
+//!     - Inexistent (reporting the available ones)
+//!     - None given
 //! - Start sniffing
 //!     - Without prior selection of the interface
+//!     - Insufficient privileges
 //!     - (?) Unhandled channel type
 //!     - (?) Failed channel creation
 //!     - Empty interface
+//!     - Invalid BPF filter
 //! - Re-Start sniffing
 //!     - Same interface
 //!     - Another interface never selected
 //!     - Another interface selected previously
 //! - Stop Sniffing
 //!     - Sniffing process wasn't started
+//! - Toggle bookmark
+//!     - Packet not collected
+//! - Capture packets in one shot
+//!     - Empty number of packets or timeout
+//!     - Inexistent interface
+//!     - Same errors of start sniffing
 //! - Generate report
+//!     - Unknown timestamp format
 //!     - Generation failed (Permission denied)
+//! - Set enabled dissectors, Parse again packets
+//!     - Unknown dissector
+//! - Register EtherType
+//!     - Length value (below 0x0600) or natively parsed EtherType
+//! - Load pcap file
+//!     - While sniffing
+//!     - File not accessible
+//!     - Invalid or unsupported file format
+//! - Load pcap directory
+//!     - Directory not accessible or without capture files
+//! - Import pcap file
+//!     - While sniffing
+//!     - File not accessible
+//!     - Invalid pcap file or link type other than Ethernet
+//! - Export pcap file
+//!     - Invalid filter value
+//!     - Writing failed (Permission denied)
+//! - Extract objects
+//!     - Writing failed (Permission denied)
+//! - Get RTT samples
+//!     - Invalid filter value
+//! - Get ping statistics
+//!     - Invalid filter value
+//! - Get packet size statistics
+//!     - Invalid filter value
+//! - Get expert info
+//!     - Invalid filter value
+//! - Detect beaconing
+//!     - Invalid tolerance
+//! - Get activity periods
+//!     - Invalid gap
+//! - Load OUI file
+//!     - File not accessible or without OUI assignments
+//! - Set TLS key log
+//!     - File not accessible or without secrets
+//! - Load baseline
+//!     - File not accessible, invalid line or invalid capture file
+//! - Export conversations
+//!     - Invalid filter value
+//!     - Writing failed (Permission denied)
+//! - Set index limit
+//!     - Unknown index
+//! - Resolve hostnames
+//!     - Invalid IP address
+//! - Set backpressure
+//!     - Unknown policy or empty queue
+//! - Set capture backend
+//!     - Unknown backend or not supported by the platform
+//! - Set capture config
+//!     - Buffer size not a power of two, empty read timeout
+//!     - Capture running
+//! - Set promiscuous
+//!     - Capture running
+//! - Set FCS mode
+//!     - Unknown mode
+//! - Set packet push
+//!     - Unknown mode
+//! - Set flow sampling
+//!     - Unknown mode
+//! - Set packet storage
+//!     - Unknown storage or not supported by the platform
+//!     - Directory not found
+//! - List sessions
+//!     - Directory not accessible
+//! - Sample packets
+//!     - Invalid filter value
+//! - Validate filter
+//!     - Unknown filter type or invalid filter value, with its position
+//! - Get UDP stream
+//!     - Invalid IP address
+//! - Get connection efficiency, TCP sequence graph
+//!     - Invalid IP address
+//! - Get decrypted TLS
+//!     - No key log loaded
+//!     - Invalid IP address
+//! - Get HTTP/2 streams
+//!     - Invalid IP address
+//! - Get link utilization
+//!     - Empty bucket size
+//!     - Unknown or empty link speed
+//! - Get conversation timeline, entropy timeline
+//!     - Invalid IP address
+//!     - Empty bucket size
+//! - Export ARP table / DNS history
+//!     - Writing failed (Permission denied)
+//! - Get distinct values
+//!     - Unknown field
+//! - Schedule capture
+//!     - Invalid time or empty window
+//!     - Inexistent interface
+//! - Save / Load profile
+//!     - Invalid profile name
+//!     - Profile not accessible or invalid
+//!     - Inexistent interface, unknown dissector or invalid configuration (load)
 
 #![cfg_attr(
     all(not(debug_assertions), target_os = "windows"),
@@ -38,54 +219,159 @@ extern crate pnet;
 extern crate sniffer_parser;
 extern crate sudo;
 
+mod activity;
+mod anonymize;
+mod backpressure;
+mod baseline;
+mod beaconing;
+mod bookmarks;
+mod capture_backend;
+mod capture_file;
+mod connections;
+mod conversation_colors;
+mod conversations;
+mod dns_timings;
+mod efficiency;
+mod expert;
+mod fcs;
+mod filter_expression;
 mod filtering;
+mod fingerprint;
+mod flow_sampling;
+mod flow_time;
+mod hostnames;
+mod http2_streams;
+mod interfaces;
+mod latency;
+mod link_utilization;
+mod neighbors;
+mod netmon;
+mod objects;
+mod oneshot;
+mod own_traffic;
+mod packet_push;
+mod packet_storage;
+mod payload_search;
+mod pcapng;
+mod permissions;
+mod ping;
+mod profiles;
+mod protocols;
+mod reparse;
 mod report;
+mod schedule;
+mod sequence_graph;
+mod services;
+mod sessions;
+mod statistics;
+mod streams;
+mod tls_crypto;
+mod tls_decryption;
+#[cfg(target_os = "linux")]
+mod tpacket;
+mod traceroute;
+mod vendors;
 
 use dotenv;
-use log::{error, info};
+use log::{info, warn};
 use serde::Serialize;
-use sniffer_parser::serializable_packet::util::{
-    contains_arp, contains_dns, contains_ethernet, contains_http, contains_icmp, contains_icmp6,
-    contains_ipv4, contains_ipv6, contains_malformed, contains_tcp, contains_tls, contains_udp,
-    contains_unknokn, get_dest_ip, get_dest_mac, get_dest_port, get_source_ip, get_source_mac,
-    get_source_port,
-};
-use sniffer_parser::HeaderLength;
-use tauri_plugin_log::fern::colors::{ColoredLevelConfig, Color};
+use tauri_plugin_log::fern::colors::{Color, ColoredLevelConfig};
 use tauri_plugin_log::{LogTarget, LoggerBuilder};
 
-use pnet::datalink::Channel::Ethernet;
-use pnet::datalink::{self, ChannelType, Config, NetworkInterface};
-use pnet::packet::ethernet::EthernetPacket;
-
-use chrono::Local;
-use filtering::{get_packets, PacketsCollection};
+use pnet::datalink::{self, NetworkInterface};
+use pnet::packet::ethernet::EtherTypes;
+
+use activity::get_activity_periods;
+use anonymize::Anonymizer;
+use backpressure::{set_backpressure, BackpressureConfig, CapturedFrame, FrameQueue};
+use baseline::{get_new_flows, load_baseline};
+use beaconing::detect_beaconing;
+use bookmarks::{list_bookmarks, next_bookmark, toggle_bookmark, Bookmarks};
+use capture_backend::{
+    get_capture_filter, open_capture_channel, set_capture_backend, set_capture_config,
+    set_promiscuous, validate_bpf_filter, CaptureBackends, CaptureConfig,
+};
+use capture_file::{
+    export_pcap, get_interface_link_type, import_pcap, load_pcap, load_pcap_dir, parse_frame,
+    restore_loopback_header, LinkTypes, LOOPBACK_HEADER_REPLACED,
+};
+use chrono::{DateTime, Local};
+use connections::{get_connections, get_failed_handshakes, get_idle_connections, get_resets};
+use conversation_colors::get_conversation_palette;
+use conversations::{
+    export_conversations, get_connection_entropy_timeline, get_conversation_timeline,
+};
+use dns_timings::get_dns_timings;
+use efficiency::get_connection_efficiency;
+use expert::get_expert_info;
+use fcs::{set_fcs_mode, strip_fcs, FcsModes};
+use filter_expression::validate_filter;
+use filtering::{
+    get_packets, get_packets_since, sample_packets, set_index_limit, PacketsCollection,
+};
+use fingerprint::get_host_os_guesses;
+use flow_sampling::{set_flow_sampling, FlowSamplingModes};
+use hostnames::{resolve_hostnames, HostnameCache};
+use http2_streams::get_http2_streams;
+use interfaces::{find_interface, get_interface_display_name, InterfaceFilter};
+use latency::get_rtt_samples;
+use link_utilization::get_link_utilization;
+use neighbors::{export_arp_table, export_dns_history, get_conflicts};
+use objects::extract_objects;
+use oneshot::capture_n_packets;
+use own_traffic::{is_own_traffic, set_exclude_own_traffic};
+use packet_push::{
+    get_pushed_packet, set_packet_push, PacketBatch, PacketPushConfig, PacketPushModes,
+};
+use packet_storage::set_packet_storage;
+use payload_search::search_payload;
+use permissions::{check_capture_permissions, escalate_if_configured, get_capture_permissions};
+use ping::get_ping_stats;
+use profiles::{list_profiles, load_profile, save_profile};
+use protocols::get_supported_protocols;
+use reparse::reparse;
 use report::{
-    data::{PacketExchange, SourceDestination},
+    data::{PacketExchange, SourceDestination, TimestampFormat, TimestampFormats},
     write_report,
 };
-use std::collections::HashMap;
+use schedule::{cancel_schedule, schedule_capture};
+use sequence_graph::get_tcp_sequence_graph;
+use services::set_service_names;
+use sessions::list_sessions;
+use statistics::{get_capture_summary, get_distinct_values, get_packet_size_stats};
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use streams::get_udp_stream;
 use tauri::{Window, Wry};
+use tls_decryption::{get_decrypted_tls, set_tls_keylog, TlsKeyLog};
+use traceroute::get_traceroute_paths;
+use vendors::load_oui_file;
 
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use sniffer_parser::{
-    cleanup_sniffing_state, parse_ethernet_frame, serializable_packet::SerializablePacket,
+    cleanup_sniffing_state, serializable_packet::ParsedPacket,
+    set_enabled_dissectors as set_thread_enabled_dissectors,
+    set_registered_ethertypes as set_thread_registered_ethertypes, CustomEtherTypes, Dissectors,
+};
+use sniffer_parser::{
+    credentials::set_audit_mode as set_thread_audit_mode,
+    serializable_packet::util::{get_dest_ip, get_dest_port, get_source_ip, get_source_port},
 };
 
 use crate::report::get_sender_receiver;
 
-const CONFIG: Config = Config {
-    write_buffer_size: 16384,
-    read_buffer_size: 16384,
-    read_timeout: None,
-    write_timeout: None,
-    channel_type: ChannelType::Layer2,
-    bpf_fd_attempts: 1000,
-    linux_fanout: None,
-    promiscuous: true,
-};
+/// Default interval between two `capture_heartbeat` events
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default interval between two `bitrate_update` events
+const DEFAULT_BITRATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest time spent reading the frames already buffered by the interface, when stopping with `drain`
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
 
 /// Errors that can occur during the sniffing process
 #[derive(Serialize, Debug)]
@@ -97,9 +383,56 @@ pub enum SniffingError {
     UnhandledChannelType(String),
     FailedChannelCreation(String),
     StopSniffingWithoutPriorStart(String),
+    LoadCaptureFileWhileSniffing(String),
     ReportGenerationFailed(String),
     ReadingChannelFailed(String),
     UnknownFilterType(String),
+    InvalidFilterValue(String),
+    InvalidIpAddress(String),
+    UnknownDissector(String),
+    InvalidCaptureFile(String),
+    UnsupportedLinkType(String),
+    CaptureFileAccessFailed(String),
+    ObjectExtractionFailed(String),
+    CaptureExportFailed(String),
+    InsufficientPrivileges(String),
+    InvalidConfiguration(String),
+    ProfileAccessFailed(String),
+    OuiFileAccessFailed(String),
+    BaselineAccessFailed(String),
+    KeyLogAccessFailed(String),
+    InvalidBpfFilter(String),
+}
+
+/// Actions reported in the context of the errors
+#[allow(non_snake_case)]
+pub mod Actions {
+    pub const SELECT_INTERFACE: &str = "select_interface";
+    pub const START_SNIFFING: &str = "start_sniffing";
+    pub const STOP_SNIFFING: &str = "stop_sniffing";
+    pub const CAPTURE_PACKETS: &str = "capture_packets";
+    pub const SCHEDULE_CAPTURE: &str = "schedule_capture";
+}
+
+/// Error of an action involving an interface, so that the UI can route it to the right context
+///
+/// Serialized as the error itself (`type` and `description`) along with the `action` and the `interface`
+#[derive(Serialize, Debug)]
+pub struct ContextualError {
+    #[serde(flatten)]
+    pub error: SniffingError,
+    pub action: &'static str,
+    pub interface: Option<String>,
+}
+
+impl SniffingError {
+    pub fn with_context(self, action: &'static str, interface: Option<String>) -> ContextualError {
+        ContextualError {
+            error: self,
+            action,
+            interface,
+        }
+    }
 }
 
 /// Sniffing channel and data collected by the sniffing process
@@ -107,10 +440,45 @@ pub enum SniffingError {
 /// This `struct` is instanciated only once at application startup
 /// And its later shared with all actions handled by the application
 pub struct SniffingState {
-    sniffers: Arc<Mutex<HashMap<String, (Sender<()>, Receiver<SniffingError>)>>>,
+    /// Stop requests (whether to drain the buffered frames) and errors of the sniffing processes
+    sniffers: Arc<Mutex<HashMap<String, (Sender<bool>, Receiver<SniffingError>)>>>,
     exchanged_packets: Arc<Mutex<HashMap<SourceDestination, PacketExchange>>>,
     info: Arc<Mutex<SniffingInfo>>,
     packets: Arc<Mutex<PacketsCollection>>,
+    dissectors: Arc<Mutex<HashSet<String>>>,
+    heartbeat_interval: Arc<Mutex<Duration>>,
+    /// Pseudonyms of the addresses written in the report being generated
+    report_anonymizer: Arc<Mutex<Anonymizer>>,
+    /// Results of the reverse DNS lookups
+    hostnames: Arc<Mutex<HostnameCache>>,
+    /// Free-text description of the capture session
+    description: Arc<Mutex<Option<String>>>,
+    /// IDs of the packets marked by the user
+    bookmarks: Arc<Mutex<Bookmarks>>,
+    /// Whether cleartext credentials are reported with `credential_detected` events
+    audit_mode: Arc<Mutex<bool>>,
+    /// Whether the packets exchanged by the sockets of the app are dropped
+    exclude_own_traffic: Arc<Mutex<bool>>,
+    /// Handling of the frames received faster than they are parsed
+    backpressure: Arc<Mutex<BackpressureConfig>>,
+    /// Backend reading the frames of the following sniffing processes
+    capture_backend: Arc<Mutex<String>>,
+    /// Buffers, read timeout and promiscuous mode of the channels of the following sniffing processes
+    capture_config: Arc<Mutex<CaptureConfig>>,
+    /// BPF filter of the sniffing process, kept while paused
+    capture_filter: Arc<Mutex<Option<String>>>,
+    /// Whether the captured Ethernet frames end with the FCS
+    fcs_mode: Arc<Mutex<String>>,
+    /// Which packets of each flow are collected
+    flow_sampling: Arc<Mutex<String>>,
+    /// What is pushed of the parsed packets with `packet` events, and how often
+    packet_push: Arc<Mutex<PacketPushConfig>>,
+    /// Secrets decrypting the TLS connections, none unless loaded
+    tls_keylog: Arc<Mutex<Option<TlsKeyLog>>>,
+    /// Protocol names of the EtherTypes labelled as raw packets
+    ethertypes: Arc<Mutex<HashMap<u16, String>>>,
+    /// Cancellation of the armed capture schedule, if any
+    schedule: Arc<Mutex<Option<Sender<()>>>>,
 }
 
 impl SniffingState {
@@ -120,14 +488,100 @@ impl SniffingState {
             exchanged_packets: Arc::new(Mutex::new(HashMap::new())),
             info: Arc::new(Mutex::new(SniffingInfo::new())),
             packets: Arc::new(Mutex::new(PacketsCollection::new())),
+            dissectors: Arc::new(Mutex::new(
+                Dissectors::ALL.iter().map(|d| d.to_string()).collect(),
+            )),
+            heartbeat_interval: Arc::new(Mutex::new(DEFAULT_HEARTBEAT_INTERVAL)),
+            report_anonymizer: Arc::new(Mutex::new(Anonymizer::new())),
+            hostnames: Arc::new(Mutex::new(HashMap::new())),
+            description: Arc::new(Mutex::new(None)),
+            bookmarks: Arc::new(Mutex::new(Bookmarks::new())),
+            audit_mode: Arc::new(Mutex::new(false)),
+            exclude_own_traffic: Arc::new(Mutex::new(false)),
+            backpressure: Arc::new(Mutex::new(BackpressureConfig::new())),
+            capture_backend: Arc::new(Mutex::new(CaptureBackends::PNET.to_owned())),
+            capture_config: Arc::new(Mutex::new(CaptureConfig::new())),
+            capture_filter: Arc::new(Mutex::new(None)),
+            fcs_mode: Arc::new(Mutex::new(FcsModes::ABSENT.to_owned())),
+            flow_sampling: Arc::new(Mutex::new(FlowSamplingModes::OFF.to_owned())),
+            packet_push: Arc::new(Mutex::new(PacketPushConfig::new())),
+            tls_keylog: Arc::new(Mutex::new(None)),
+            ethertypes: Arc::new(Mutex::new(
+                CustomEtherTypes::DEFAULT
+                    .iter()
+                    .map(|(ethertype, name)| (*ethertype, name.to_string()))
+                    .collect(),
+            )),
+            schedule: Arc::new(Mutex::new(None)),
         }
     }
 }
 
+/// Periodic status of a running sniffing process, emitted even when no packets arrive
+#[derive(Serialize, Clone, Debug)]
+struct CaptureHeartbeat {
+    interface_name: String,
+    /// Packets in the collection
+    packets: usize,
+    /// Packets captured by the current sniffing process
+    captured_packets: usize,
+    /// Frames discarded since too short to be parsed
+    dropped_packets: usize,
+    /// Frames discarded since received while the parser queue was full
+    overflow_packets: usize,
+    /// Time since the sniffing process started (or resumed), in milliseconds
+    active_time: u128,
+}
+
+/// Traffic captured by a running sniffing process since the previous `bitrate_update` event
+#[derive(Serialize, Clone, Debug)]
+struct BitrateUpdate {
+    /// Microseconds since the Unix epoch
+    timestamp: i64,
+    /// Whole captured frames, including the ones left out by the flow sampling
+    bytes: usize,
+    packets: usize,
+}
+
+/// Configuration of a sniffing process once started, as resolved from the settings at that moment
+#[derive(Serialize, Clone, Debug)]
+struct CaptureStarted {
+    interface_name: String,
+    is_resume: bool,
+    append: bool,
+    backend: String,
+    link_type: u32,
+    promiscuous: bool,
+    /// Maximum number of bytes captured of each frame
+    snaplen: usize,
+    /// Capture filter, none when every frame is captured
+    filter: Option<String>,
+    /// Enabled application layer dissectors, sorted by name
+    dissectors: Vec<String>,
+    fcs_mode: String,
+    flow_sampling: String,
+    packet_push: &'static str,
+    backpressure: &'static str,
+}
+
+/// Cleartext credential seen by a sniffing process in audit mode
+#[derive(Serialize, Clone, Debug)]
+struct CredentialDetected {
+    packet_id: usize,
+    protocol: String,
+    /// Endpoints (`ip:port`) of the client and the server
+    client: String,
+    server: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
 /// Informations about the selected network interface
 struct SniffingInfo {
     interface_name: Option<String>,
     interface: Option<NetworkInterface>,
+    /// Interfaces sniffed along with the selected one, see `select_interfaces`
+    additional_interfaces: Vec<NetworkInterface>,
     counter: usize,
 }
 
@@ -136,23 +590,32 @@ impl SniffingInfo {
         SniffingInfo {
             interface_name: None,
             interface: None,
+            additional_interfaces: vec![],
             counter: 0,
         }
     }
 }
 
 /// Returns the list of all available network interfaces
+///
+/// The interfaces listed can be restricted to the ones up (`up_only`), not loopback (`exclude_loopback`)
+/// or with an IP address (`require_address`), all of them being listed by default
 #[tauri::command]
-fn get_interfaces_list() -> Vec<String> {
+fn get_interfaces_list(
+    up_only: Option<bool>,
+    exclude_loopback: Option<bool>,
+    require_address: Option<bool>,
+) -> Vec<String> {
+    let filter = InterfaceFilter {
+        up_only: up_only.unwrap_or(false),
+        exclude_loopback: exclude_loopback.unwrap_or(false),
+        require_address: require_address.unwrap_or(false),
+    };
+
     let interfaces = datalink::interfaces()
         .into_iter()
-        .map(|i| {
-            if cfg!(target_os = "windows") {
-                i.description
-            } else {
-                i.name
-            }
-        })
+        .filter(|i| filter.matches(i))
+        .map(|i| get_interface_display_name(&i))
         .collect::<Vec<String>>();
     info!("Interfaces retrieved: {:#?}", interfaces);
 
@@ -160,34 +623,24 @@ fn get_interfaces_list() -> Vec<String> {
 }
 
 /// Selection of a network interface among all the available ones
+///
+/// `interface_name` can be the name, description, MAC address, index or Npcap GUID of the interface,
+/// see `interfaces::find_interface`
 #[tauri::command]
 fn select_interface(
     state: tauri::State<SniffingState>,
     interface_name: String,
-) -> Result<(), SniffingError> {
-    let interface_names_match = |iface: &NetworkInterface| {
-        if cfg!(target_os = "windows") {
-            iface.description == interface_name
-        } else {
-            iface.name == interface_name
-        }
-    };
-
-    // Find the network interface with the provided name
-    let interfaces = datalink::interfaces();
-    let interface = interfaces
-        .into_iter()
-        .filter(interface_names_match)
-        .next()
-        .ok_or(SniffingError::InterfaceNotFound(
-            "The provided interface is inexistent".to_owned(),
-        ))?;
+) -> Result<(), ContextualError> {
+    let interface = find_interface(datalink::interfaces(), &interface_name)
+        .map_err(|e| e.with_context(Actions::SELECT_INTERFACE, Some(interface_name.clone())))?;
+    let interface_name = get_interface_display_name(&interface);
 
     info!("Interface selected: {}", interface_name);
 
     let mut sniffing_info = state.info.lock().unwrap();
     sniffing_info.interface = Some(interface);
     sniffing_info.interface_name = Some(interface_name);
+    sniffing_info.additional_interfaces.clear();
 
     info!(
         "[{}] Channel created",
@@ -197,297 +650,835 @@ fn select_interface(
     Ok(())
 }
 
-/// Instantiates a new thread that will execute the sniffing process
+/// Selection of several network interfaces, sniffed concurrently by the following sniffing processes
+///
+/// Each name is resolved as by `select_interface`. The packets of all the interfaces are merged in the
+/// same collection, tagged with the name of their interface, while the errors of the sniffing processes
+/// refer to the first interface
+#[tauri::command]
+fn select_interfaces(
+    state: tauri::State<SniffingState>,
+    names: Vec<String>,
+) -> Result<(), ContextualError> {
+    let available_interfaces = datalink::interfaces();
+    let mut interfaces: Vec<NetworkInterface> = vec![];
+    for name in &names {
+        let interface = find_interface(available_interfaces.clone(), name)
+            .map_err(|e| e.with_context(Actions::SELECT_INTERFACE, Some(name.clone())))?;
+        if !interfaces.contains(&interface) {
+            interfaces.push(interface);
+        }
+    }
+
+    if interfaces.is_empty() {
+        return Err(SniffingError::InvalidConfiguration(
+            "At least one interface must be selected".to_owned(),
+        )
+        .with_context(Actions::SELECT_INTERFACE, None));
+    }
+
+    let interface_names = interfaces
+        .iter()
+        .map(get_interface_display_name)
+        .collect::<Vec<String>>();
+    info!("Interfaces selected: {}", interface_names.join(", "));
+
+    let mut sniffing_info = state.info.lock().unwrap();
+    sniffing_info.interface_name = interface_names.into_iter().next();
+    sniffing_info.interface = Some(interfaces.remove(0));
+    sniffing_info.additional_interfaces = interfaces;
+
+    Ok(())
+}
+
+/// Instantiates a new thread that will execute the sniffing process, one for each selected interface
+///
+/// The collected packets are cleared before starting, unless:
+/// - `is_resume` is set: the paused sniffing process of the same interface is continued
+/// - `append` is set: a brand new sniffing process (possibly on another interface) is started, keeping
+///   the already collected packets and assigning IDs to the new ones after the last collected packet
+///
+/// Resume and append can be combined, in which case the collection is simply kept as is
+///
+/// With a BPF `filter` (e.g. `tcp port 443`) the frames are captured by the `pcap` backend, which drops the
+/// non-matching ones in the kernel. A resumed process keeps the filter of the paused one when none is given
+///
+/// Once the channel is open, a `capture_started` event carries the configuration actually in use, while a
+/// `bitrate_update` event carries the traffic captured every `bitrate_interval` milliseconds (1 second by
+/// default) until the sniffing process is stopped or paused
 #[tauri::command]
 fn start_sniffing(
     is_resume: bool,
+    append: bool,
+    bitrate_interval: Option<u64>,
+    filter: Option<String>,
     state: tauri::State<SniffingState>,
     window: Window<Wry>,
+) -> Result<(), ContextualError> {
+    start_sniffing_internal(is_resume, append, bitrate_interval, filter, &state, window).map_err(
+        |e| {
+            let interface_name = state.info.lock().unwrap().interface_name.clone();
+            e.with_context(Actions::START_SNIFFING, interface_name)
+        },
+    )
+}
+
+pub(crate) fn start_sniffing_internal(
+    is_resume: bool,
+    append: bool,
+    bitrate_interval: Option<u64>,
+    filter: Option<String>,
+    state: &SniffingState,
+    window: Window<Wry>,
 ) -> Result<(), SniffingError> {
-    let sniffing_state = state.info.lock().unwrap();
+    let mut sniffing_state = state.info.lock().unwrap();
     let mut sniffers = state.sniffers.lock().unwrap();
     let mut packet_collection = state.packets.lock().unwrap();
 
-    let interface_name = sniffing_state.interface_name.as_ref().ok_or(
+    let interface_name = sniffing_state.interface_name.clone().ok_or(
         SniffingError::StartSniffingWithoutInterfaceSelection(
             "Start sniffing without prior selection of the inteface".to_owned(),
         ),
     )?;
 
-    let interface = sniffing_state.interface.as_ref().ok_or(
+    let interface = sniffing_state.interface.clone().ok_or(
         SniffingError::StartSniffingWithoutInterfaceSelection(
             "Start sniffing without prior selection of the interface".to_owned(),
         ),
     )?;
 
-    if !is_resume {
-        packet_collection.clear();
-    }
-    info!("[{}] Sniffing started", interface_name);
-
-    let _sniffer = sniffers.get_mut(interface_name);
-    // if sniffer.is_none() || sniffer.unwrap().0.send(()).is_err() {
-    // Create a new channel, dealing with layer 2 packets
-    let (_, mut interface_channel) = match datalink::channel(interface, CONFIG) {
-        Ok(Ethernet(tx, rx)) => Ok((tx, rx)),
-        Ok(_) => Err(SniffingError::UnhandledChannelType(
-            "Unhandled channel type".to_owned(),
-        )),
-        Err(e) => {
-            error!("Unexpected channel creation failure: {}", e);
-            Err(SniffingError::FailedChannelCreation(
-                "Unexpected channel creation failure".to_owned(),
-            ))
-        }
-    }?;
-
-    let (send_stop, receive_stop) = channel();
-    let (send_error, receive_error) = channel();
-
-    sniffers.insert(interface_name.to_string(), (send_stop, receive_error));
+    // Sniffed concurrently, each by its own sniffing process
+    let mut interfaces = vec![(interface_name, interface)];
+    interfaces.extend(
+        sniffing_state
+            .additional_interfaces
+            .iter()
+            .map(|interface| (get_interface_display_name(interface), interface.clone())),
+    );
 
-    let exchanged_packets = Arc::clone(&state.exchanged_packets);
-    let packets = Arc::clone(&state.packets);
-    let info = Arc::clone(&state.info);
+    // Fail before clearing the collected packets
+    let permissions = get_capture_permissions();
+    if !permissions.can_capture {
+        return Err(SniffingError::InsufficientPrivileges(
+            permissions.reason.unwrap_or_default(),
+        ));
+    }
 
-    std::thread::spawn(move || {
-        // let mut counter_id = 0;
-        loop {
-            match interface_channel.next() {
-                Ok(packet) if receive_stop.try_recv().is_err() => {
-                    let ethernet_packet = EthernetPacket::new(packet).unwrap();
+    let filter = get_capture_filter(state, is_resume, filter);
+    if let Some(filter) = &filter {
+        for (_, interface) in &interfaces {
+            validate_bpf_filter(filter, get_interface_link_type(interface))?;
+        }
+    }
 
-                    let mut info = info.lock().unwrap();
-                    let new_packet = parse_ethernet_frame(&ethernet_packet, info.counter);
-                    info.counter += 1;
-
-                    /* Save packet in HashMap */
-                    let now = Local::now();
-                    let sender_receiver = get_sender_receiver(&new_packet);
-                    let mut transmitted_bytes = 0;
-                    let protocols: Vec<String> = sender_receiver.1;
-                    if let SerializablePacket::EthernetPacket(link_packet) =
-                        new_packet.get_link_layer_packet().unwrap()
-                    {
-                        transmitted_bytes = link_packet.payload.len() + HeaderLength::ETHERNET;
-                    }
+    // Create a new channel for each interface, dealing with layer 2 packets
+    // Only libpcap applies the filter
+    let backend = match filter {
+        Some(_) => CaptureBackends::PCAP.to_owned(),
+        None => state.capture_backend.lock().unwrap().clone(),
+    };
+    let config = state.capture_config.lock().unwrap().get_channel_config();
+    // All opened before starting, so that no sniffing process is left running if one fails
+    let mut interface_channels = vec![];
+    for (interface_name, interface) in interfaces {
+        let interface_channel =
+            open_capture_channel(&interface, &backend, config, filter.as_deref())?;
+        interface_channels.push((interface_name, interface, interface_channel));
+    }
+    *state.capture_filter.lock().unwrap() = filter.clone();
+
+    // Only once all the channels are opened, so that a failing start keeps the collected packets
+    if append {
+        // A full stop resets the counter, so continue after the last collected packet instead
+        sniffing_state.counter = packet_collection
+            .packets
+            .last()
+            .map_or(0, |packet| packet.get_id() + 1);
+    } else if !is_resume {
+        packet_collection.clear();
+        state.bookmarks.lock().unwrap().clear();
+    }
 
-                    let mut packets_collection = packets.lock().unwrap();
-                    let parsed_packet = Arc::new(new_packet);
-
-                    // Index by Source IP
-                    if let Some(ip_address) = get_source_ip(&parsed_packet) {
-                        packets_collection
-                            .source_ip_index
-                            .entry(ip_address)
-                            .and_modify(|packets| packets.push(Arc::clone(&parsed_packet)))
-                            .or_insert(vec![Arc::clone(&parsed_packet)]);
-                    }
+    packet_collection
+        .capture_intervals
+        .push((Local::now().timestamp_micros(), None));
+
+    // Each sniffing process holds a sender, so that the timer doesn't outlive a stop or a pause of the last one
+    let (send_bitrate_stop, receive_bitrate_stop) = channel::<()>();
+    // Since the previous `bitrate_update` event, on all the interfaces
+    let bitrate_bytes = Arc::new(AtomicUsize::new(0));
+    let bitrate_packets = Arc::new(AtomicUsize::new(0));
+    // Packets in the collection as stored by the parsers, so that heartbeats don't wait for its lock
+    let stored_packets = Arc::new(AtomicUsize::new(packet_collection.packets.len()));
+    let bitrate_interval = bitrate_interval.map_or(DEFAULT_BITRATE_INTERVAL, |milliseconds| {
+        Duration::from_millis(milliseconds.max(1))
+    });
+    {
+        let bitrate_bytes = Arc::clone(&bitrate_bytes);
+        let bitrate_packets = Arc::clone(&bitrate_packets);
+        let window = window.clone();
+
+        std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) =
+                receive_bitrate_stop.recv_timeout(bitrate_interval)
+            {
+                let _result = window.emit(
+                    "bitrate_update",
+                    BitrateUpdate {
+                        timestamp: Local::now().timestamp_micros(),
+                        bytes: bitrate_bytes.swap(0, Ordering::Relaxed),
+                        packets: bitrate_packets.swap(0, Ordering::Relaxed),
+                    },
+                );
+            }
+        });
+    }
 
-                    // Index by Dest IP
-                    if let Some(ip_address) = get_dest_ip(&parsed_packet) {
-                        packets_collection
-                            .dest_ip_index
-                            .entry(ip_address)
-                            .and_modify(|packets| packets.push(Arc::clone(&parsed_packet)))
-                            .or_insert(vec![Arc::clone(&parsed_packet)]);
+    for (interface_name, interface, mut interface_channel) in interface_channels {
+        info!("[{}] Sniffing started", interface_name);
+
+        let _sniffer = sniffers.get_mut(&interface_name);
+        // if sniffer.is_none() || sniffer.unwrap().0.send(()).is_err() {
+        let link_type = get_interface_link_type(&interface);
+        let send_bitrate_stop = send_bitrate_stop.clone();
+        let window = window.clone();
+
+        let (send_stop, receive_stop) = channel();
+        let (send_error, receive_error) = channel();
+
+        sniffers.insert(interface_name.clone(), (send_stop, receive_error));
+
+        let exchanged_packets = Arc::clone(&state.exchanged_packets);
+        let packets = Arc::clone(&state.packets);
+        let stored_packets = Arc::clone(&stored_packets);
+        let info = Arc::clone(&state.info);
+        let dissectors = Arc::clone(&state.dissectors);
+        let heartbeat_interval = Arc::clone(&state.heartbeat_interval);
+        let audit_mode = Arc::clone(&state.audit_mode);
+        let exclude_own_traffic = Arc::clone(&state.exclude_own_traffic);
+        let ethertypes = Arc::clone(&state.ethertypes);
+        let fcs_mode = Arc::clone(&state.fcs_mode);
+        let flow_sampling = Arc::clone(&state.flow_sampling);
+        let packet_push = Arc::clone(&state.packet_push);
+        let packet_batch = Arc::new(Mutex::new(PacketBatch::new()));
+        // libpcap keeps the loopback header of the frames
+        let restore_loopback = link_type == LinkTypes::NULL
+            && LOOPBACK_HEADER_REPLACED
+            && backend != CaptureBackends::PCAP;
+
+        let mut enabled_dissectors = dissectors
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        enabled_dissectors.sort();
+        let _result = window.emit(
+            "capture_started",
+            CaptureStarted {
+                interface_name: interface_name.clone(),
+                is_resume,
+                append,
+                backend: backend.clone(),
+                link_type,
+                promiscuous: config.promiscuous,
+                snaplen: interface_channel.get_snaplen(&config),
+                filter: filter.clone(),
+                dissectors: enabled_dissectors,
+                fcs_mode: fcs_mode.lock().unwrap().clone(),
+                flow_sampling: flow_sampling.lock().unwrap().clone(),
+                packet_push: packet_push.lock().unwrap().mode,
+                backpressure: state.backpressure.lock().unwrap().policy,
+            },
+        );
+
+        // Frames are parsed and stored in a separate thread, so that the capture never waits for the locks
+        let (mut frame_queue, frames) = FrameQueue::new(&state.backpressure.lock().unwrap());
+        let captured_packets = Arc::new(AtomicUsize::new(0));
+        let dropped_packets = Arc::new(AtomicUsize::new(0));
+
+        let parser = {
+            let packets = Arc::clone(&packets);
+            let packet_push = Arc::clone(&packet_push);
+            let packet_batch = Arc::clone(&packet_batch);
+            let captured_packets = Arc::clone(&captured_packets);
+            let dropped_packets = Arc::clone(&dropped_packets);
+            let stored_packets = Arc::clone(&stored_packets);
+            let bitrate_bytes = Arc::clone(&bitrate_bytes);
+            let bitrate_packets = Arc::clone(&bitrate_packets);
+            let interface_name = interface_name.clone();
+            let window = window.clone();
+
+            std::thread::spawn(move || {
+                // Applied even if empty, the parser thread enabling every dissector by default
+                let mut enabled_dissectors = dissectors.lock().unwrap().clone();
+                set_thread_enabled_dissectors(&enabled_dissectors);
+                let mut audit_enabled = false;
+                let mut registered_ethertypes = HashMap::new();
+
+                for frame in frames {
+                    // Apply dissectors changes made while sniffing
+                    let current_dissectors = dissectors.lock().unwrap();
+                    if *current_dissectors != enabled_dissectors {
+                        enabled_dissectors = current_dissectors.clone();
+                        set_thread_enabled_dissectors(&enabled_dissectors);
                     }
+                    drop(current_dissectors);
 
-                    // Index by Source MAC
-                    if let Some(mac_address) = get_source_mac(&parsed_packet) {
-                        packets_collection
-                            .source_mac_index
-                            .entry(mac_address)
-                            .and_modify(|packets| packets.push(Arc::clone(&parsed_packet)))
-                            .or_insert(vec![Arc::clone(&parsed_packet)]);
+                    let current_audit_mode = *audit_mode.lock().unwrap();
+                    if current_audit_mode != audit_enabled {
+                        audit_enabled = current_audit_mode;
+                        set_thread_audit_mode(audit_enabled);
                     }
 
-                    // Index by Dest MAC
-                    if let Some(mac_address) = get_dest_mac(&parsed_packet) {
-                        packets_collection
-                            .dest_mac_index
-                            .entry(mac_address)
-                            .and_modify(|packets| packets.push(Arc::clone(&parsed_packet)))
-                            .or_insert(vec![Arc::clone(&parsed_packet)]);
+                    let current_ethertypes = ethertypes.lock().unwrap();
+                    if *current_ethertypes != registered_ethertypes {
+                        registered_ethertypes = current_ethertypes.clone();
+                        set_thread_registered_ethertypes(&registered_ethertypes);
                     }
+                    drop(current_ethertypes);
 
-                    // Index by Source Port
-                    if let Some(port) = get_source_port(&parsed_packet) {
-                        packets_collection
-                            .source_port_index
-                            .entry(port)
-                            .and_modify(|packets| packets.push(Arc::clone(&parsed_packet)))
-                            .or_insert(vec![Arc::clone(&parsed_packet)]);
-                    }
+                    let data = strip_fcs(&fcs_mode.lock().unwrap(), link_type, &frame.data);
 
-                    // Index by Dest Port
-                    if let Some(port) = get_dest_port(&parsed_packet) {
-                        packets_collection
-                            .dest_port_index
-                            .entry(port)
-                            .and_modify(|packets| packets.push(Arc::clone(&parsed_packet)))
-                            .or_insert(vec![Arc::clone(&parsed_packet)]);
+                    // Held until the packet is stored, see `collect_sniffed_packet`
+                    let mut info = info.lock().unwrap();
+                    let mut packets_collection = packets.lock().unwrap();
+                    let mut exchanged_packets = exchanged_packets.lock().unwrap();
+                    let mut new_packet = match parse_frame(link_type, data, info.counter) {
+                        Some(new_packet) => new_packet,
+                        None => {
+                            dropped_packets.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                    };
+                    if *exclude_own_traffic.lock().unwrap() && is_own_traffic(&new_packet) {
+                        continue;
                     }
-
-                    if contains_ethernet(&parsed_packet) {
-                        packets_collection
-                            .ethernet_packets
-                            .push(parsed_packet.clone());
+                    captured_packets.fetch_add(1, Ordering::Relaxed);
+                    bitrate_bytes.fetch_add(data.len(), Ordering::Relaxed);
+                    bitrate_packets.fetch_add(1, Ordering::Relaxed);
+                    new_packet.set_timestamp(frame.timestamp.timestamp_micros());
+                    new_packet.set_interface_name(Some(interface_name.clone()));
+                    let credential = get_detected_credential(&new_packet);
+
+                    let is_sampled = collect_sniffed_packet(
+                        &mut info,
+                        &mut packets_collection,
+                        &mut exchanged_packets,
+                        &flow_sampling.lock().unwrap(),
+                        new_packet,
+                        link_type,
+                        data,
+                        frame.timestamp,
+                    );
+                    drop(info);
+                    stored_packets.store(packets_collection.packets.len(), Ordering::Relaxed);
+                    let conflict = packets_collection.addresses.take_conflict();
+                    let new_flow = packets_collection.baseline.take_new_flow();
+                    // Nothing new collected to push for the packets left out by the flow sampling
+                    let push_mode = if is_sampled {
+                        packet_push.lock().unwrap().mode
+                    } else {
+                        PacketPushModes::OFF
+                    };
+                    if let Some(pushed) =
+                        get_pushed_packet(push_mode, &packets_collection, data.len())
+                    {
+                        packet_batch.lock().unwrap().push(pushed);
                     }
+                    drop(packets_collection);
+                    drop(exchanged_packets);
 
-                    if contains_malformed(&parsed_packet) {
-                        packets_collection
-                            .malformed_packets
-                            .push(parsed_packet.clone());
+                    if let Some(credential) = credential {
+                        let _result = window.emit("credential_detected", credential);
                     }
-
-                    if contains_unknokn(&parsed_packet) {
-                        packets_collection
-                            .unknown_packets
-                            .push(parsed_packet.clone());
+                    if let Some(conflict) = conflict {
+                        let _result = window.emit("ip_conflict_detected", conflict);
                     }
-
-                    if contains_tcp(&parsed_packet) {
-                        packets_collection.tcp_packets.push(parsed_packet.clone());
+                    if let Some(new_flow) = new_flow {
+                        let _result = window.emit("new_flow", new_flow);
                     }
 
-                    if contains_udp(&parsed_packet) {
-                        packets_collection.udp_packets.push(parsed_packet.clone());
-                    }
+                    let _result = window.emit("packet_received", ());
+                }
+            })
+        };
+
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let mut last_heartbeat = started;
+            let mut drain_deadline: Option<Instant> = None;
+            loop {
+                if last_heartbeat.elapsed() >= *heartbeat_interval.lock().unwrap() {
+                    last_heartbeat = Instant::now();
+                    let _result = window.emit(
+                        "capture_heartbeat",
+                        CaptureHeartbeat {
+                            interface_name: interface_name.clone(),
+                            packets: stored_packets.load(Ordering::Relaxed),
+                            captured_packets: captured_packets.load(Ordering::Relaxed),
+                            dropped_packets: dropped_packets.load(Ordering::Relaxed),
+                            overflow_packets: frame_queue.overflows,
+                            active_time: started.elapsed().as_millis(),
+                        },
+                    );
+                }
 
-                    if contains_icmp(&parsed_packet) {
-                        packets_collection.icmp_packets.push(parsed_packet.clone());
-                    }
+                let push_interval = packet_push.lock().unwrap().interval;
+                let batch = packet_batch
+                    .lock()
+                    .unwrap()
+                    .take_due(push_interval, Instant::now());
+                if let Some(batch) = batch {
+                    let _result = window.emit("packet", batch);
+                }
 
-                    if contains_icmp6(&parsed_packet) {
-                        packets_collection
-                            .icmpv6_packets
-                            .push(parsed_packet.clone());
+                match drain_deadline {
+                    Some(deadline) if Instant::now() >= deadline => break,
+                    Some(_) => (),
+                    None => {
+                        if let Ok(drain) = receive_stop.try_recv() {
+                            // Clean the channel
+                            while receive_stop.try_recv().is_ok() {}
+                            if !drain {
+                                break;
+                            }
+                            drain_deadline = Some(Instant::now() + DRAIN_TIMEOUT);
+                        }
                     }
+                }
 
-                    if contains_http(&parsed_packet) {
-                        packets_collection.http_packets.push(parsed_packet.clone());
+                match interface_channel.next() {
+                    Ok((packet, captured_at)) => {
+                        let data = if restore_loopback {
+                            restore_loopback_header(packet)
+                        } else {
+                            packet.to_vec()
+                        };
+
+                        frame_queue.push(CapturedFrame {
+                            data,
+                            timestamp: captured_at.unwrap_or_else(Local::now),
+                        });
                     }
-
-                    if contains_tls(&parsed_packet) {
-                        packets_collection.tls_packets.push(parsed_packet.clone());
+                    Err(e) if e.kind() == ErrorKind::TimedOut => {
+                        // Nothing left to drain
+                        if drain_deadline.is_some() {
+                            break;
+                        }
                     }
-
-                    if contains_ipv4(&parsed_packet) {
-                        packets_collection.ipv4_packets.push(parsed_packet.clone());
+                    Err(e) => {
+                        match send_error.send(SniffingError::ReadingChannelFailed(format!(
+                            "Reading from channel failed: {}",
+                            e
+                        ))) {
+                            _ => (),
+                        }
+
+                        // Clean the channel
+                        while !receive_stop.try_recv().is_err() {}
+                        break;
                     }
+                }
+            }
 
-                    if contains_ipv6(&parsed_packet) {
-                        packets_collection.ipv6_packets.push(parsed_packet.clone());
-                    }
+            // Let the parser store the frames still queued
+            drop(frame_queue);
+            let _result = parser.join();
 
-                    if contains_arp(&parsed_packet) {
-                        packets_collection.arp_packets.push(parsed_packet.clone());
-                    }
+            drop(send_bitrate_stop);
 
-                    if contains_dns(&parsed_packet) {
-                        packets_collection.dns_packets.push(parsed_packet.clone());
-                    }
+            let batch = packet_batch
+                .lock()
+                .unwrap()
+                .take_due(Duration::ZERO, Instant::now());
+            if let Some(batch) = batch {
+                let _result = window.emit("packet", batch);
+            }
+        });
+        // }
+    }
 
-                    // Insert packet
-                    packets_collection.packets.push(parsed_packet);
+    Ok(())
+}
 
-                    let mut exchanged_packets = exchanged_packets.lock().unwrap();
-                    exchanged_packets
-                        .entry(sender_receiver.0)
-                        .and_modify(|exchange| {
-                            exchange.add_packet(protocols.clone(), transmitted_bytes, now)
-                        })
-                        .or_insert(PacketExchange::new(protocols, transmitted_bytes, now));
+/// Collects a packet of a sniffing process, parsed with the next packet ID, returning whether the flow
+/// sampling kept it
+///
+/// `info` is held since the parsing along with the collection, so that the packets of the interfaces
+/// sniffed concurrently are inserted in the order of their IDs, which the filters and `get_packets` rely on
+#[allow(clippy::too_many_arguments)]
+fn collect_sniffed_packet(
+    info: &mut SniffingInfo,
+    packets_collection: &mut PacketsCollection,
+    exchanged_packets: &mut HashMap<SourceDestination, PacketExchange>,
+    flow_sampling: &str,
+    new_packet: ParsedPacket,
+    link_type: u32,
+    data: &[u8],
+    timestamp: DateTime<Local>,
+) -> bool {
+    let is_sampled = packets_collection
+        .flow_sampler
+        .is_sampled(flow_sampling, &new_packet);
+    if is_sampled {
+        store_packet(
+            packets_collection,
+            exchanged_packets,
+            new_packet,
+            link_type,
+            data,
+            timestamp,
+        );
+    } else {
+        count_exchanged_packet(exchanged_packets, &new_packet, data.len(), timestamp);
+    }
+    info.counter += 1;
 
-                    let _result = window.emit("packet_received", ());
-                }
-                Ok(_) => {
-                    // Clean the channel
-                    while !receive_stop.try_recv().is_err() {}
-                    break;
-                }
-                Err(e) => {
-                    match send_error.send(SniffingError::ReadingChannelFailed(format!(
-                        "Reading from channel failed: {}",
-                        e
-                    ))) {
-                        _ => (),
-                    }
+    is_sampled
+}
 
-                    // Clean the channel
-                    while !receive_stop.try_recv().is_err() {}
-                    break;
-                }
-            }
-        }
-    });
-    // }
+/// Saves a parsed packet among the collected ones and in the exchanged packets used by the report
+pub(crate) fn store_packet(
+    packets_collection: &mut PacketsCollection,
+    exchanged_packets: &mut HashMap<SourceDestination, PacketExchange>,
+    mut new_packet: ParsedPacket,
+    link_type: u32,
+    raw_packet: &[u8],
+    timestamp: DateTime<Local>,
+) {
+    packets_collection.expert.analyze(&mut new_packet);
+
+    // Whole captured frame, whatever its link layer and size (e.g. jumbo frames)
+    count_exchanged_packet(exchanged_packets, &new_packet, raw_packet.len(), timestamp);
+
+    packets_collection.add_raw_packet(new_packet.get_id(), link_type, raw_packet);
+    packets_collection.share_raw_packet(&mut new_packet);
+    packets_collection.insert(Arc::new(new_packet));
+}
 
-    Ok(())
+/// Adds a parsed packet to the exchanged packets used by the report, even if it isn't collected
+fn count_exchanged_packet(
+    exchanged_packets: &mut HashMap<SourceDestination, PacketExchange>,
+    packet: &ParsedPacket,
+    transmitted_bytes: usize,
+    timestamp: DateTime<Local>,
+) {
+    /* Save packet in HashMap */
+    let sender_receiver = get_sender_receiver(packet);
+    let protocols: Vec<String> = sender_receiver.1;
+
+    exchanged_packets
+        .entry(sender_receiver.0)
+        .and_modify(|exchange| exchange.add_packet(protocols.clone(), transmitted_bytes, timestamp))
+        .or_insert(PacketExchange::new(protocols, transmitted_bytes, timestamp));
 }
 
 #[tauri::command]
 /// Terminates (stop: true) or Pauses (stop: false) the sniffing process
-fn stop_sniffing(state: tauri::State<SniffingState>, stop: bool) -> Result<(), SniffingError> {
+///
+/// The sniffing process notices the request within the read timeout of the channel, even if the interface
+/// is idle. By default it stops immediately, discarding the frames still buffered by the interface: with
+/// `drain` it keeps reading them until the interface has nothing left or for at most `DRAIN_TIMEOUT`.
+/// Stopping a sniffing process already stopped has no effect
+fn stop_sniffing(
+    state: tauri::State<SniffingState>,
+    stop: bool,
+    drain: Option<bool>,
+) -> Result<(), ContextualError> {
+    stop_sniffing_internal(&state, stop, drain.unwrap_or(false)).map_err(|e| {
+        let interface_name = state.info.lock().unwrap().interface_name.clone();
+        e.with_context(Actions::STOP_SNIFFING, interface_name)
+    })
+}
+
+pub(crate) fn stop_sniffing_internal(
+    state: &SniffingState,
+    stop: bool,
+    drain: bool,
+) -> Result<(), SniffingError> {
     let mut sniffing_state = state.info.lock().unwrap();
-    let mut sniffers = state.sniffers.lock().unwrap();
+    let sniffers = state.sniffers.lock().unwrap();
 
-    if stop {
-        let mut exchanged_packets = state.exchanged_packets.lock().unwrap();
-        std::mem::take(&mut *exchanged_packets);
-        sniffing_state.counter = 0;
-    }
-    
-    let interface_name = sniffing_state.interface_name.as_ref().ok_or(
+    let interface_name = sniffing_state.interface_name.clone().ok_or(
         SniffingError::StopSniffingWithoutPriorStart(
             "Stop sniffing without prior starting of the process".to_owned(),
         ),
     )?;
 
-    let (send_stop, receive_error) = sniffers
-        .get_mut(&sniffing_state.interface_name.as_ref().unwrap().to_string())
-        .unwrap();
+    // Along with the sniffing processes of the interfaces selected with it
+    let selected_sniffers = std::iter::once(interface_name.clone())
+        .chain(
+            sniffing_state
+                .additional_interfaces
+                .iter()
+                .map(get_interface_display_name),
+        )
+        .filter_map(|name| sniffers.get(&name))
+        .collect::<Vec<_>>();
+    if selected_sniffers.is_empty() {
+        return Err(SniffingError::StopSniffingWithoutPriorStart(
+            "Stop sniffing without prior starting of the process".to_owned(),
+        ));
+    }
 
-    match send_stop.send(()) {
-        Ok(_) => {
-            if let Ok(e) = receive_error.try_recv() {
-                return Err(e);
-            }
-        }
-        Err(_) => {
-            // When Stop Sniffing provided before the thread sniffer is created
-            sniffers.remove(&sniffing_state.interface_name.as_ref().unwrap().to_string());
+    if stop {
+        let mut exchanged_packets = state.exchanged_packets.lock().unwrap();
+        std::mem::take(&mut *exchanged_packets);
+        sniffing_state.counter = 0;
+        state.capture_filter.lock().unwrap().take();
+    }
+
+    // A sniffing process already stopped has dropped its end of the channel
+    let mut is_running = false;
+    let mut error = None;
+    for (send_stop, receive_error) in selected_sniffers {
+        is_running |= send_stop.send(drain).is_ok();
+        if let Ok(e) = receive_error.try_recv() {
+            error.get_or_insert(e);
         }
     }
 
+    // Even if the sniffing process already ended on a failure, so that the capture isn't left running
     cleanup_sniffing_state();
 
+    let mut packets_collection = state.packets.lock().unwrap();
+    if let Some((_, end @ None)) = packets_collection.capture_intervals.last_mut() {
+        *end = Some(Local::now().timestamp_micros());
+    }
+    drop(packets_collection);
+
+    if let Some(e) = error {
+        return Err(e);
+    }
+
+    if !is_running {
+        info!("[{}] Sniffing already stopped", interface_name);
+        return Ok(());
+    }
+
     info!("[{}] Sniffing stopped", interface_name);
 
     Ok(())
 }
 
+/// Sets the interval between two `capture_heartbeat` events emitted by the sniffing process
+#[tauri::command]
+fn set_heartbeat_interval(state: tauri::State<SniffingState>, milliseconds: u64) {
+    info!("Heartbeat interval: {}ms", milliseconds);
+    *state.heartbeat_interval.lock().unwrap() = Duration::from_millis(milliseconds.max(1));
+}
+
+/// Enables or disables the detection of cleartext credentials (HTTP Basic, FTP, Telnet, POP3, IMAP),
+/// both for the current and the following sniffing processes
+///
+/// Each detection is emitted as a `credential_detected` event, carrying the captured credential
+#[tauri::command]
+fn set_audit_mode(state: tauri::State<SniffingState>, enabled: bool) {
+    if enabled {
+        warn!("Audit mode enabled: cleartext credentials will be reported to the UI");
+    } else {
+        info!("Audit mode disabled");
+    }
+    *state.audit_mode.lock().unwrap() = enabled;
+}
+
+/// Event reporting the credential carried by the packet, if any
+fn get_detected_credential(packet: &ParsedPacket) -> Option<CredentialDetected> {
+    let credential = packet.get_credential()?;
+    let endpoint = |ip: Option<String>, port: Option<String>| {
+        format!("{}:{}", ip.unwrap_or_default(), port.unwrap_or_default())
+    };
+
+    // Credentials are always completed by a packet of the client
+    let client = endpoint(get_source_ip(packet), get_source_port(packet));
+    let server = endpoint(get_dest_ip(packet), get_dest_port(packet));
+
+    warn!(
+        "[{}] Cleartext {} credential sent by {} to {}",
+        packet.get_id(),
+        credential.protocol,
+        client,
+        server
+    );
+
+    Some(CredentialDetected {
+        packet_id: packet.get_id(),
+        protocol: credential.protocol.clone(),
+        client,
+        server,
+        username: credential.username.clone(),
+        password: credential.password.clone(),
+    })
+}
+
+/// Sets the description of the capture session, an empty text removes it
+///
+/// Saved beside the capture files exported afterwards, and restored by loading them
+#[tauri::command]
+fn set_session_description(state: tauri::State<SniffingState>, text: String) {
+    let text = text.trim();
+    info!("Session description: {:?}", text);
+    *state.description.lock().unwrap() = if text.is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    };
+}
+
+/// Selects the application layer protocols to parse, both for the current and the following sniffing processes
+///
+/// Packets of disabled protocols are still classified up to the transport layer
+#[tauri::command]
+fn set_enabled_dissectors(
+    state: tauri::State<SniffingState>,
+    protocols: Vec<String>,
+) -> Result<(), SniffingError> {
+    let protocols = get_dissectors(protocols)?;
+
+    info!("Enabled dissectors: {:?}", protocols);
+    *state.dissectors.lock().unwrap() = protocols;
+
+    Ok(())
+}
+
+/// EtherType labelled as a raw packet of the named protocol
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct RegisteredEtherType {
+    ethertype: u16,
+    name: String,
+}
+
+/// Labels the payload of the EtherType with the protocol `name`, both for the current and the following
+/// sniffing processes. An empty name removes the registration
+///
+/// Frames of unregistered EtherTypes without a native parser are stored as unknown packets
+#[tauri::command]
+fn register_ethertype(
+    state: tauri::State<SniffingState>,
+    ethertype: u16,
+    name: String,
+) -> Result<(), SniffingError> {
+    let native = [EtherTypes::Ipv4, EtherTypes::Ipv6, EtherTypes::Arp];
+    if ethertype < 0x0600 || native.iter().any(|native| native.0 == ethertype) {
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "EtherType not registrable: 0x{:04x}",
+            ethertype
+        )));
+    }
+
+    let mut ethertypes = state.ethertypes.lock().unwrap();
+    let name = name.trim();
+    if name.is_empty() {
+        info!("Unregistered EtherType 0x{:04x}", ethertype);
+        ethertypes.remove(&ethertype);
+    } else {
+        info!("Registered EtherType 0x{:04x}: {}", ethertype, name);
+        ethertypes.insert(ethertype, name.to_owned());
+    }
+
+    Ok(())
+}
+
+/// Returns the registered EtherTypes, sorted by value
+#[tauri::command]
+fn get_registered_ethertypes(state: tauri::State<SniffingState>) -> Vec<RegisteredEtherType> {
+    let mut ethertypes = state
+        .ethertypes
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(ethertype, name)| RegisteredEtherType {
+            ethertype: *ethertype,
+            name: name.clone(),
+        })
+        .collect::<Vec<RegisteredEtherType>>();
+    ethertypes.sort_by_key(|registered| registered.ethertype);
+
+    ethertypes
+}
+
+/// Validates the names of the dissectors, case insensitive
+pub(crate) fn get_dissectors(protocols: Vec<String>) -> Result<HashSet<String>, SniffingError> {
+    let protocols = protocols
+        .into_iter()
+        .map(|p| p.to_lowercase())
+        .collect::<HashSet<String>>();
+
+    if let Some(unknown) = protocols
+        .iter()
+        .find(|p| !Dissectors::ALL.contains(&p.as_str()))
+    {
+        return Err(SniffingError::UnknownDissector(format!(
+            "Unknown dissector: {}",
+            unknown
+        )));
+    }
+
+    Ok(protocols)
+}
+
 /// Produces or updates a .csv report with the data collected since the last report generation
+///
+/// When `anonymize` is set the IP addresses are replaced with pseudonyms, kept the same across the
+/// updates of the report and renewed at its first generation
+///
+/// Timestamps are written in local time with second precision, unless a `timestamp_format` (`default`
+/// or `iso8601`, with nanosecond precision) and `utc` are provided
+///
+/// The report is replaced only once completely written: if the generation fails it's left as it was, and
+/// the data is kept for the next generation
 #[tauri::command]
 fn generate_report(
     state: tauri::State<SniffingState>,
     report_path: String,
     first_generation: bool,
+    anonymize: bool,
+    timestamp_format: Option<String>,
+    utc: Option<bool>,
 ) -> Result<bool, SniffingError> {
+    let timestamp_format = timestamp_format
+        .as_deref()
+        .unwrap_or(TimestampFormats::DEFAULT);
+    let timestamp_format = TimestampFormat::new(timestamp_format, utc.unwrap_or(false))
+        .ok_or_else(|| {
+            SniffingError::InvalidConfiguration(format!(
+                "Unknown timestamp format: {}",
+                timestamp_format
+            ))
+        })?;
+
     let mut exchanged_packets = state.exchanged_packets.lock().unwrap();
     let mut packets = std::mem::take(&mut *exchanged_packets);
 
-    write_report(&report_path, &mut packets, first_generation).map_err(|e| {
+    let mut anonymizer = state.report_anonymizer.lock().unwrap();
+    if first_generation {
+        *anonymizer = Anonymizer::new();
+    }
+
+    let anonymizer = if anonymize {
+        Some(&mut *anonymizer)
+    } else {
+        None
+    };
+
+    write_report(
+        &report_path,
+        &mut packets,
+        first_generation,
+        anonymizer,
+        timestamp_format,
+    )
+    .map_err(|e| {
+        // Keep the exchanges for the next generation, the report being left as it was
+        *exchanged_packets = packets;
         SniffingError::ReportGenerationFailed(format!("Report generation failed: {}", e))
     })
 }
 
 fn main() {
     dotenv::dotenv().ok();
-    if !cfg!(target_os = "windows") {
-        // sudo::escalate_if_needed();
-    }
+    escalate_if_configured();
 
     tauri::Builder::default()
         .plugin(
@@ -520,8 +1511,226 @@ fn main() {
             get_interfaces_list,
             generate_report,
             select_interface,
+            select_interfaces,
             get_packets,
+            get_packets_since,
+            get_capture_summary,
+            set_enabled_dissectors,
+            set_heartbeat_interval,
+            load_pcap,
+            load_pcap_dir,
+            import_pcap,
+            extract_objects,
+            get_rtt_samples,
+            export_conversations,
+            get_connections,
+            set_index_limit,
+            resolve_hostnames,
+            set_session_description,
+            check_capture_permissions,
+            set_service_names,
+            set_audit_mode,
+            set_backpressure,
+            list_sessions,
+            sample_packets,
+            get_udp_stream,
+            get_host_os_guesses,
+            get_conversation_timeline,
+            get_connection_entropy_timeline,
+            export_arp_table,
+            export_dns_history,
+            get_distinct_values,
+            save_profile,
+            load_profile,
+            list_profiles,
+            get_idle_connections,
+            register_ethertype,
+            get_registered_ethertypes,
+            get_traceroute_paths,
+            schedule_capture,
+            cancel_schedule,
+            get_ping_stats,
+            export_pcap,
+            get_conflicts,
+            get_packet_size_stats,
+            capture_n_packets,
+            detect_beaconing,
+            load_oui_file,
+            get_connection_efficiency,
+            get_supported_protocols,
+            get_expert_info,
+            load_baseline,
+            get_new_flows,
+            set_exclude_own_traffic,
+            get_activity_periods,
+            get_dns_timings,
+            toggle_bookmark,
+            list_bookmarks,
+            next_bookmark,
+            set_capture_backend,
+            set_capture_config,
+            set_promiscuous,
+            reparse,
+            set_packet_storage,
+            get_conversation_palette,
+            get_tcp_sequence_graph,
+            set_fcs_mode,
+            set_flow_sampling,
+            set_packet_push,
+            validate_filter,
+            set_tls_keylog,
+            get_decrypted_tls,
+            get_http2_streams,
+            get_resets,
+            get_link_utilization,
+            get_failed_handshakes,
+            search_payload,
         ])
         .run(tauri::generate_context!())
         .expect("Error while running tauri application");
 }
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+
+    use chrono::Local;
+
+    use crate::capture_backend::is_capturing;
+    use crate::capture_file::{parse_frame, LinkTypes};
+    use crate::flow_sampling::FlowSamplingModes;
+    use crate::{collect_sniffed_packet, stop_sniffing_internal, SniffingError, SniffingState};
+
+    /// Ethernet frame of an ARP request
+    const ARP_FRAME: [u8; 42] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0x11, 0x22, 0x33, 0x44, 0x55, 0x08, 0x06, 0, 1,
+        0x08, 0, 6, 4, 0, 1, 0, 0x11, 0x22, 0x33, 0x44, 0x55, 10, 0, 0, 1, 0, 0, 0, 0, 0, 0, 10, 0,
+        0, 2,
+    ];
+
+    #[test]
+    fn stop_before_start_and_double_stop() {
+        let state = SniffingState::new();
+        assert!(matches!(
+            stop_sniffing_internal(&state, true, false),
+            Err(SniffingError::StopSniffingWithoutPriorStart(_))
+        ));
+
+        // Selected but never started
+        state.info.lock().unwrap().interface_name = Some("eth0".to_owned());
+        assert!(matches!(
+            stop_sniffing_internal(&state, true, false),
+            Err(SniffingError::StopSniffingWithoutPriorStart(_))
+        ));
+
+        let (send_stop, receive_stop) = channel();
+        let (_send_error, receive_error) = channel();
+        state
+            .sniffers
+            .lock()
+            .unwrap()
+            .insert("eth0".to_owned(), (send_stop, receive_error));
+        state
+            .packets
+            .lock()
+            .unwrap()
+            .capture_intervals
+            .push((100, None));
+
+        stop_sniffing_internal(&state, true, true).unwrap();
+        assert_eq!(receive_stop.try_recv(), Ok(true));
+        let end = state.packets.lock().unwrap().capture_intervals[0].1;
+        assert!(end.is_some());
+
+        // The sniffing thread is gone
+        drop(receive_stop);
+        stop_sniffing_internal(&state, true, false).unwrap();
+        stop_sniffing_internal(&state, false, false).unwrap();
+        assert_eq!(state.packets.lock().unwrap().capture_intervals[0].1, end);
+    }
+
+    #[test]
+    fn stop_after_failure() {
+        let state = SniffingState::new();
+        state.info.lock().unwrap().interface_name = Some("eth0".to_owned());
+
+        // The sniffing process ended on a failure of its channel
+        let (send_stop, receive_stop) = channel();
+        let (send_error, receive_error) = channel();
+        send_error
+            .send(SniffingError::ReadingChannelFailed("eth0".to_owned()))
+            .unwrap();
+        drop(receive_stop);
+        state
+            .sniffers
+            .lock()
+            .unwrap()
+            .insert("eth0".to_owned(), (send_stop, receive_error));
+        state
+            .packets
+            .lock()
+            .unwrap()
+            .capture_intervals
+            .push((100, None));
+
+        assert!(matches!(
+            stop_sniffing_internal(&state, true, false),
+            Err(SniffingError::ReadingChannelFailed(_))
+        ));
+        assert!(!is_capturing(&state));
+
+        stop_sniffing_internal(&state, true, false).unwrap();
+        assert!(!is_capturing(&state));
+    }
+
+    #[test]
+    fn concurrent_interfaces_ids_ordered() {
+        let state = Arc::new(SniffingState::new());
+        let sniffers = ["eth0", "wlan0"].map(|interface_name| {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                for _ in 0..200 {
+                    let mut info = state.info.lock().unwrap();
+                    let mut packets_collection = state.packets.lock().unwrap();
+                    let mut exchanged_packets = state.exchanged_packets.lock().unwrap();
+                    let mut new_packet =
+                        parse_frame(LinkTypes::ETHERNET, &ARP_FRAME, info.counter).unwrap();
+                    new_packet.set_interface_name(Some(interface_name.to_owned()));
+                    collect_sniffed_packet(
+                        &mut info,
+                        &mut packets_collection,
+                        &mut exchanged_packets,
+                        FlowSamplingModes::OFF,
+                        new_packet,
+                        LinkTypes::ETHERNET,
+                        &ARP_FRAME,
+                        Local::now(),
+                    );
+                }
+            })
+        });
+        for sniffer in sniffers {
+            sniffer.join().unwrap();
+        }
+
+        let packets_collection = state.packets.lock().unwrap();
+        let ids = packets_collection
+            .packets
+            .iter()
+            .map(|packet| packet.get_id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, (0..400).collect::<Vec<_>>());
+        assert_eq!(state.info.lock().unwrap().counter, 400);
+        for interface_name in ["eth0", "wlan0"] {
+            let interface_packets = packets_collection
+                .packets
+                .iter()
+                .filter(|packet| {
+                    packet.get_interface_name().map(String::as_str) == Some(interface_name)
+                })
+                .count();
+            assert_eq!(interface_packets, 200);
+        }
+    }
+}
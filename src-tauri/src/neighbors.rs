@@ -0,0 +1,606 @@
+//! Exports of the address mappings observed in the collected packets
+//!
+//! - ARP table: the IP to MAC address mappings announced by the senders of ARP packets, flagged as
+//!   conflicting when the same IP address is announced by more than one MAC address
+//! - DNS history: the records answered by DNS responses, in capture order
+//!
+//! Both are written as .csv files, with timestamps in the format of the report.
+//!
+//! IP address conflicts are instead tracked while the packets are collected, from the senders of ARP
+//! packets and from the source addresses of IP packets: each IP address seen with a new MAC address is
+//! reported with an `ip_conflict_detected` event. The source MAC address of a packet coming from another
+//! network is the one of the last router, so IP packets are only tracked for private and link-local
+//! source addresses
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use chrono::{Local, TimeZone};
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::application::CustomResourceData;
+use sniffer_parser::serializable_packet::util::{get_dest_ip, get_source_ip};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::filtering::PacketsCollection;
+use crate::{SniffingError, SniffingState};
+
+/// IP to MAC address mapping announced by ARP packets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArpEntry {
+    pub ip: String,
+    pub mac: String,
+    /// Timestamps of the first and last announcement, in microseconds since the Unix epoch
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub packets: usize,
+    /// Whether the IP address is announced by other MAC addresses too
+    pub conflict: bool,
+}
+
+/// Record answered by a DNS response
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsResolution {
+    pub timestamp: i64,
+    pub client: String,
+    pub server: String,
+    pub name: String,
+    pub record_type: String,
+    pub answer: String,
+    pub ttl: u32,
+}
+
+/// MAC address seen sending packets with an IP address
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MacAssociation {
+    pub mac: String,
+    /// Timestamps of the first and last packet, in microseconds since the Unix epoch
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub packets: usize,
+}
+
+/// IP address used by more than one MAC address
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IpConflict {
+    pub ip: String,
+    /// Competing MAC addresses, in order of appearance
+    pub macs: Vec<MacAssociation>,
+}
+
+/// Event reporting an IP address seen with a new MAC address
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IpConflictDetected {
+    pub packet_id: usize,
+    pub timestamp: i64,
+    pub ip: String,
+    pub mac: String,
+    /// MAC addresses already seen with the IP address, in order of appearance
+    pub previous_macs: Vec<String>,
+}
+
+/// IP to MAC address associations of the collected packets
+#[derive(Debug, Default)]
+pub struct AddressTracker {
+    associations: HashMap<IpAddr, Vec<MacAssociation>>,
+    /// Conflict caused by the last tracked packet, if any
+    last_conflict: Option<IpConflictDetected>,
+}
+
+impl AddressTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, packet: &ParsedPacket) {
+        self.last_conflict = None;
+
+        let (ip, mac) = match get_address_association(packet) {
+            Some(association) => association,
+            None => return,
+        };
+        let timestamp = packet.get_timestamp();
+
+        let macs = self.associations.entry(ip).or_default();
+        if let Some(association) = macs.iter_mut().find(|association| association.mac == mac) {
+            association.first_seen = association.first_seen.min(timestamp);
+            association.last_seen = association.last_seen.max(timestamp);
+            association.packets += 1;
+            return;
+        }
+
+        if !macs.is_empty() {
+            warn!(
+                "[{}] IP address {} used by {} too",
+                packet.get_id(),
+                ip,
+                mac
+            );
+            self.last_conflict = Some(IpConflictDetected {
+                packet_id: packet.get_id(),
+                timestamp,
+                ip: ip.to_string(),
+                mac: mac.clone(),
+                previous_macs: macs
+                    .iter()
+                    .map(|association| association.mac.clone())
+                    .collect(),
+            });
+        }
+
+        macs.push(MacAssociation {
+            mac,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            packets: 1,
+        });
+    }
+
+    /// Takes the conflict caused by the last tracked packet, if any
+    pub fn take_conflict(&mut self) -> Option<IpConflictDetected> {
+        self.last_conflict.take()
+    }
+
+    /// IP addresses used by more than one MAC address, sorted
+    pub fn get_conflicts(&self) -> Vec<IpConflict> {
+        let mut conflicts = self
+            .associations
+            .iter()
+            .filter(|(_, macs)| macs.len() > 1)
+            .collect::<Vec<(&IpAddr, &Vec<MacAssociation>)>>();
+        conflicts.sort_by_key(|(ip, _)| **ip);
+
+        conflicts
+            .into_iter()
+            .map(|(ip, macs)| IpConflict {
+                ip: ip.to_string(),
+                macs: macs.clone(),
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.associations.clear();
+        self.last_conflict = None;
+    }
+}
+
+/// IP address claimed by the sender of the packet, with its MAC address
+fn get_address_association(packet: &ParsedPacket) -> Option<(IpAddr, String)> {
+    let source_mac = match packet.get_link_layer_packet() {
+        Some(SerializablePacket::EthernetPacket(ethernet_packet)) => Some(ethernet_packet.source),
+        _ => None,
+    };
+
+    let (ip, mac) = match packet.get_network_layer_packet()? {
+        // ARP probes don't announce any address
+        SerializablePacket::ArpPacket(arp_packet)
+            if !arp_packet.sender_proto_addr.is_unspecified() =>
+        {
+            (
+                IpAddr::V4(arp_packet.sender_proto_addr),
+                arp_packet.sender_hw_addr,
+            )
+        }
+        SerializablePacket::Ipv4Packet(ipv4_packet)
+            if ipv4_packet.source.is_private() || ipv4_packet.source.is_link_local() =>
+        {
+            (IpAddr::V4(ipv4_packet.source), source_mac?)
+        }
+        SerializablePacket::Ipv6Packet(ipv6_packet)
+            if is_ipv6_link_local(&ipv6_packet.source)
+                || is_ipv6_unique_local(&ipv6_packet.source) =>
+        {
+            (IpAddr::V6(ipv6_packet.source), source_mac?)
+        }
+        _ => return None,
+    };
+
+    if mac.is_zero() || mac.is_broadcast() || mac.is_multicast() {
+        return None;
+    }
+
+    Some((ip, mac.to_string()))
+}
+
+/// Whether the address is in fe80::/10
+fn is_ipv6_link_local(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xffc0 == 0xfe80
+}
+
+/// Whether the address is in fc00::/7
+fn is_ipv6_unique_local(ip: &Ipv6Addr) -> bool {
+    ip.segments()[0] & 0xfe00 == 0xfc00
+}
+
+/// Returns the IP addresses used by more than one MAC address, with the competing MAC addresses
+#[tauri::command]
+pub fn get_conflicts(state: tauri::State<SniffingState>) -> Vec<IpConflict> {
+    state.packets.lock().unwrap().addresses.get_conflicts()
+}
+
+/// Writes the observed ARP table in a .csv file, returning the number of written entries
+#[tauri::command]
+pub fn export_arp_table(
+    state: tauri::State<SniffingState>,
+    path: String,
+) -> Result<usize, SniffingError> {
+    let entries = get_arp_table(&state.packets.lock().unwrap());
+
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.ip.clone(),
+                entry.mac.clone(),
+                format_timestamp(entry.first_seen),
+                format_timestamp(entry.last_seen),
+                entry.packets.to_string(),
+                entry.conflict.to_string(),
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+
+    let headers = [
+        "IP",
+        "MAC",
+        "First Seen",
+        "Last Seen",
+        "Packets",
+        "Conflict",
+    ];
+    write_csv(&path, &headers, &rows).map_err(|e| {
+        SniffingError::ReportGenerationFailed(format!("ARP table export failed: {}", e))
+    })?;
+
+    info!("Exported {} ARP entries in {}", rows.len(), path);
+
+    Ok(rows.len())
+}
+
+/// Writes the DNS resolution history in a .csv file, returning the number of written records
+#[tauri::command]
+pub fn export_dns_history(
+    state: tauri::State<SniffingState>,
+    path: String,
+) -> Result<usize, SniffingError> {
+    let resolutions = get_dns_history(&state.packets.lock().unwrap());
+
+    let rows = resolutions
+        .iter()
+        .map(|resolution| {
+            vec![
+                format_timestamp(resolution.timestamp),
+                resolution.client.clone(),
+                resolution.server.clone(),
+                resolution.name.clone(),
+                resolution.record_type.clone(),
+                resolution.answer.clone(),
+                resolution.ttl.to_string(),
+            ]
+        })
+        .collect::<Vec<Vec<String>>>();
+
+    let headers = ["Time", "Client", "Server", "Name", "Type", "Answer", "TTL"];
+    write_csv(&path, &headers, &rows).map_err(|e| {
+        SniffingError::ReportGenerationFailed(format!("DNS history export failed: {}", e))
+    })?;
+
+    info!("Exported {} DNS records in {}", rows.len(), path);
+
+    Ok(rows.len())
+}
+
+/// ARP table sorted by IP address, then by first announcement
+fn get_arp_table(packets_collection: &PacketsCollection) -> Vec<ArpEntry> {
+    let mut entries: BTreeMap<(Ipv4Addr, String), ArpEntry> = BTreeMap::new();
+
+    for packet in &packets_collection.arp_packets {
+        let arp_packet = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::ArpPacket(arp_packet)) => arp_packet,
+            _ => continue,
+        };
+
+        // ARP probes don't announce any address
+        if arp_packet.sender_proto_addr.is_unspecified() {
+            continue;
+        }
+
+        let ip = arp_packet.sender_proto_addr;
+        let mac = arp_packet.sender_hw_addr.to_string();
+        let timestamp = packet.get_timestamp();
+
+        let entry = entries
+            .entry((ip, mac.clone()))
+            .or_insert_with(|| ArpEntry {
+                ip: ip.to_string(),
+                mac,
+                first_seen: timestamp,
+                last_seen: timestamp,
+                packets: 0,
+                conflict: false,
+            });
+        entry.first_seen = entry.first_seen.min(timestamp);
+        entry.last_seen = entry.last_seen.max(timestamp);
+        entry.packets += 1;
+    }
+
+    let mut macs_per_ip: BTreeMap<Ipv4Addr, usize> = BTreeMap::new();
+    for (ip, _) in entries.keys() {
+        *macs_per_ip.entry(*ip).or_insert(0) += 1;
+    }
+
+    let mut entries = entries
+        .into_iter()
+        .map(|((ip, _), mut entry)| {
+            entry.conflict = macs_per_ip[&ip] > 1;
+            (ip, entry)
+        })
+        .collect::<Vec<(Ipv4Addr, ArpEntry)>>();
+    entries.sort_by_key(|(ip, entry)| (*ip, entry.first_seen));
+
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Answers of the DNS responses, in capture order
+fn get_dns_history(packets_collection: &PacketsCollection) -> Vec<DnsResolution> {
+    let mut resolutions = vec![];
+
+    for packet in &packets_collection.dns_packets {
+        let dns_packet = match packet.get_application_layer_packet() {
+            Some(SerializablePacket::DnsPacket(dns_packet)) if !dns_packet.header.query => {
+                dns_packet
+            }
+            _ => continue,
+        };
+
+        for answer in &dns_packet.answers {
+            let (record_type, value) = match get_answer_value(&answer.data) {
+                Some(answer_value) => answer_value,
+                None => continue,
+            };
+
+            resolutions.push(DnsResolution {
+                timestamp: packet.get_timestamp(),
+                client: get_dest_ip(packet).unwrap_or_default(),
+                server: get_source_ip(packet).unwrap_or_default(),
+                name: answer.name.clone(),
+                record_type: record_type.to_owned(),
+                answer: value,
+                ttl: answer.ttl,
+            });
+        }
+    }
+
+    resolutions
+}
+
+/// Record type and value of a DNS answer, `None` for the record types without a resolved name or address
+pub(crate) fn get_answer_value(data: &CustomResourceData) -> Option<(&'static str, String)> {
+    match data {
+        CustomResourceData::A(a) => Some(("A", a.address.to_string())),
+        CustomResourceData::AAAA(aaaa) => Some(("AAAA", aaaa.address.to_string())),
+        CustomResourceData::CNAME(cname) => Some(("CNAME", cname.name.clone())),
+        CustomResourceData::PTR(ptr) => Some(("PTR", ptr.name.clone())),
+        CustomResourceData::MX(mx) => Some(("MX", mx.exchange.clone())),
+        CustomResourceData::NS(ns) => Some(("NS", ns.name.clone())),
+        CustomResourceData::SRV(srv) => Some(("SRV", format!("{}:{}", srv.target, srv.port))),
+        _ => None,
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    Local
+        .timestamp_nanos(timestamp * 1000)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+fn write_csv(path: &str, headers: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    let path = Path::new(path);
+    if path.extension().and_then(|extension| extension.to_str()) != Some("csv") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Provide a .csv file",
+        ));
+    }
+
+    if let Some(parent_directory) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent_directory)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all((headers.join(",") + "\n").as_bytes())?;
+    for row in rows {
+        let fields = row
+            .iter()
+            .map(|field| field.replace(',', ";"))
+            .collect::<Vec<String>>();
+        writer.write_all((fields.join(",") + "\n").as_bytes())?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::network::SerializableArpPacket;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::{get_arp_table, AddressTracker};
+
+    #[test]
+    fn arp_table_with_conflicts() {
+        let mut packets_collection = PacketsCollection::new();
+
+        for (id, (mac, ip, timestamp)) in [
+            (
+                MacAddr::new(2, 0, 0, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 1),
+                10,
+            ),
+            (
+                MacAddr::new(2, 0, 0, 0, 0, 2),
+                Ipv4Addr::new(10, 0, 0, 2),
+                20,
+            ),
+            (
+                MacAddr::new(2, 0, 0, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 1),
+                30,
+            ),
+            (
+                MacAddr::new(2, 0, 0, 0, 0, 3),
+                Ipv4Addr::new(0, 0, 0, 0),
+                40,
+            ),
+            (
+                MacAddr::new(2, 0, 0, 0, 0, 3),
+                Ipv4Addr::new(10, 0, 0, 2),
+                50,
+            ),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_network_layer_packet(Some(SerializablePacket::ArpPacket(
+                SerializableArpPacket {
+                    hardware_type: "Ethernet".to_owned(),
+                    protocol_type: 0x0800,
+                    hw_addr_len: 6,
+                    proto_addr_len: 4,
+                    operation: "ARP Reply (2)".to_owned(),
+                    sender_hw_addr: mac,
+                    sender_proto_addr: ip,
+                    target_hw_addr: MacAddr::zero(),
+                    target_proto_addr: Ipv4Addr::new(10, 0, 0, 254),
+                    length: 28,
+                },
+            )));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let entries: Vec<(String, String, i64, i64, usize, bool)> =
+            get_arp_table(&packets_collection)
+                .into_iter()
+                .map(|entry| {
+                    (
+                        entry.ip,
+                        entry.mac,
+                        entry.first_seen,
+                        entry.last_seen,
+                        entry.packets,
+                        entry.conflict,
+                    )
+                })
+                .collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "10.0.0.1".to_owned(),
+                    "02:00:00:00:00:01".to_owned(),
+                    10,
+                    30,
+                    2,
+                    false
+                ),
+                (
+                    "10.0.0.2".to_owned(),
+                    "02:00:00:00:00:02".to_owned(),
+                    20,
+                    20,
+                    1,
+                    true
+                ),
+                (
+                    "10.0.0.2".to_owned(),
+                    "02:00:00:00:00:03".to_owned(),
+                    50,
+                    50,
+                    1,
+                    true
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ip_conflicts_from_ip_traffic() {
+        let (host, spoofer, router) = (
+            MacAddr::new(2, 0, 0, 0, 0, 1),
+            MacAddr::new(2, 0, 0, 0, 0, 2),
+            MacAddr::new(2, 0, 0, 0, 0, 254),
+        );
+        let local = Ipv4Addr::new(192, 168, 1, 10);
+        let packet = |id: usize, mac: MacAddr, ip: Ipv4Addr, timestamp: i64| {
+            let template =
+                build_test_parsed_packet(mac, router, ip, Ipv4Addr::new(1, 1, 1, 1), 5000, 443);
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(timestamp);
+            parsed_packet.set_link_layer_packet(template.get_link_layer_packet().cloned());
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+        };
+
+        let mut tracker = AddressTracker::new();
+        tracker.update(&packet(0, host, local, 10));
+        tracker.update(&packet(1, host, local, 20));
+        assert_eq!(tracker.take_conflict(), None);
+
+        // Remote addresses carry the MAC address of the router
+        tracker.update(&packet(2, router, Ipv4Addr::new(8, 8, 8, 8), 30));
+        tracker.update(&packet(3, spoofer, Ipv4Addr::new(8, 8, 8, 8), 40));
+        assert_eq!(tracker.take_conflict(), None);
+
+        tracker.update(&packet(4, spoofer, local, 50));
+        let conflict = tracker.take_conflict().unwrap();
+        assert_eq!((conflict.packet_id, conflict.timestamp), (4, 50));
+        assert_eq!(conflict.ip, "192.168.1.10");
+        assert_eq!(conflict.mac, "02:00:00:00:00:02");
+        assert_eq!(conflict.previous_macs, vec!["02:00:00:00:00:01"]);
+        assert_eq!(tracker.take_conflict(), None);
+
+        // Already known
+        tracker.update(&packet(5, spoofer, local, 60));
+        assert_eq!(tracker.take_conflict(), None);
+
+        let conflicts = tracker.get_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        let macs: Vec<(String, i64, i64, usize)> = conflicts[0]
+            .macs
+            .iter()
+            .map(|association| {
+                (
+                    association.mac.clone(),
+                    association.first_seen,
+                    association.last_seen,
+                    association.packets,
+                )
+            })
+            .collect();
+        assert_eq!(
+            macs,
+            vec![
+                ("02:00:00:00:00:01".to_owned(), 10, 20, 2),
+                ("02:00:00:00:00:02".to_owned(), 50, 60, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Import of packets from capture files in Microsoft Network Monitor 2.x format (`.cap`)
+//!
+//! A NetMon file starts with the `GMBU` signature, followed by the version, the media type of the
+//! capture and its start time (a Windows `SYSTEMTIME`, in UTC). The header points to the frame table, an
+//! array with the offset of each frame in the file, usually written at its end: frames are read in the
+//! order of the table. Each frame record starts with the time elapsed since the start of the capture (in
+//! microseconds), the length of the frame on the wire and the captured length, followed by the data.
+//!
+//! The whole file is read in memory, as the frame table is found only after the frames. Only Ethernet
+//! captures are supported, NetMon 1.x files (`RTSS` signature) and ETL traces are not.
+
+use std::io::Read;
+
+use chrono::NaiveDate;
+
+use crate::capture_file::{LinkTypes, PcapRecord, MAX_RECORD_LENGTH};
+use crate::SniffingError;
+
+/// First bytes of a NetMon 2.x file
+pub(crate) const NETMON_SIGNATURE: [u8; 4] = *b"GMBU";
+/// First bytes of a NetMon 1.x file
+pub(crate) const NETMON_V1_SIGNATURE: [u8; 4] = *b"RTSS";
+
+/// Media types of the captures
+const MEDIA_ETHERNET: u16 = 1;
+
+const HEADER_LENGTH: usize = 32;
+const RECORD_HEADER_LENGTH: usize = 16;
+
+/// Sequential reader of the packets stored in a NetMon 2.x file
+pub struct NetmonReader {
+    data: Vec<u8>,
+    pub link_type: u32,
+    /// Start of the capture, in microseconds since the Unix epoch
+    start: i64,
+    /// Offsets of the frames, in order
+    frames: Vec<u32>,
+    next_frame: usize,
+}
+
+impl NetmonReader {
+    /// Read the whole file, checking its header and frame table
+    pub fn new<R: Read>(mut reader: R) -> Result<Self, SniffingError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data).map_err(|e| {
+            SniffingError::InvalidCaptureFile(format!("Unable to read NetMon file: {}", e))
+        })?;
+
+        if data.starts_with(&NETMON_V1_SIGNATURE) {
+            return Err(SniffingError::InvalidCaptureFile(
+                "Unsupported NetMon 1.x capture".to_owned(),
+            ));
+        }
+        if data.len() < HEADER_LENGTH || !data.starts_with(&NETMON_SIGNATURE) {
+            return Err(SniffingError::InvalidCaptureFile(
+                "Missing NetMon header".to_owned(),
+            ));
+        }
+
+        let version = (data[5], data[4]);
+        if version.0 != 2 {
+            return Err(SniffingError::InvalidCaptureFile(format!(
+                "Unsupported NetMon version: {}.{}",
+                version.0, version.1
+            )));
+        }
+
+        let media_type = read_u16(&data, 6);
+        let link_type = match media_type {
+            MEDIA_ETHERNET => LinkTypes::ETHERNET,
+            _ => {
+                return Err(SniffingError::InvalidCaptureFile(format!(
+                    "Unsupported NetMon media type: {}",
+                    media_type
+                )))
+            }
+        };
+
+        let start = read_start_time(&data[8..24]).ok_or_else(|| {
+            SniffingError::InvalidCaptureFile("Invalid NetMon capture start time".to_owned())
+        })?;
+
+        let table_offset = read_u32(&data, 24) as usize;
+        let table_length = read_u32(&data, 28) as usize;
+        let table = data
+            .get(table_offset..table_offset.saturating_add(table_length))
+            .filter(|table| table.len() % 4 == 0)
+            .ok_or_else(|| {
+                SniffingError::InvalidCaptureFile(format!(
+                    "Invalid NetMon frame table: {} bytes at offset {}",
+                    table_length, table_offset
+                ))
+            })?;
+        let frames = table
+            .chunks_exact(4)
+            .map(|offset| u32::from_le_bytes(offset.try_into().unwrap()))
+            .collect();
+
+        Ok(NetmonReader {
+            data,
+            link_type,
+            start,
+            frames,
+            next_frame: 0,
+        })
+    }
+
+    /// Read the next packet, `None` after the last frame of the table
+    pub fn next_record(&mut self) -> Result<Option<PcapRecord>, SniffingError> {
+        let offset = match self.frames.get(self.next_frame) {
+            Some(offset) => *offset as usize,
+            None => return Ok(None),
+        };
+        let frame = self.next_frame;
+        self.next_frame += 1;
+
+        let header = self
+            .data
+            .get(offset..offset.saturating_add(RECORD_HEADER_LENGTH))
+            .ok_or_else(|| {
+                SniffingError::InvalidCaptureFile(format!(
+                    "Truncated NetMon frame {} header at offset {}",
+                    frame, offset
+                ))
+            })?;
+
+        let elapsed = u64::from_le_bytes(header[0..8].try_into().unwrap());
+        let original_length = read_u32(header, 8);
+        let captured_length = read_u32(header, 12);
+        if captured_length > MAX_RECORD_LENGTH {
+            return Err(SniffingError::InvalidCaptureFile(format!(
+                "Invalid NetMon frame {} length: {}",
+                frame, captured_length
+            )));
+        }
+
+        let start = offset + RECORD_HEADER_LENGTH;
+        let data = self
+            .data
+            .get(start..start + captured_length as usize)
+            .ok_or_else(|| {
+                SniffingError::InvalidCaptureFile(format!(
+                    "Truncated NetMon frame {}: {} bytes expected",
+                    frame, captured_length
+                ))
+            })?;
+
+        Ok(Some(PcapRecord {
+            timestamp: self.start + elapsed as i64,
+            original_length,
+            data: data.to_vec(),
+        }))
+    }
+}
+
+/// Start of the capture from a `SYSTEMTIME`: year, month, day of week, day, hour, minute, second and
+/// milliseconds, 16 bits each
+fn read_start_time(system_time: &[u8]) -> Option<i64> {
+    let field = |index: usize| read_u16(system_time, index * 2) as u32;
+
+    let date = NaiveDate::from_ymd_opt(field(0) as i32, field(1), field(3))?;
+    let time = date.and_hms_milli_opt(field(4), field(5), field(6), field(7))?;
+    Some(time.and_utc().timestamp_micros())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::capture_file::LinkTypes;
+    use crate::SniffingError;
+
+    use super::NetmonReader;
+
+    /// NetMon 2.0 Ethernet capture started on 2022-09-01 10:20:30.500 UTC, with the frame table at the end
+    fn netmon_file(frames: &[(u64, &[u8])], media_type: u16) -> Vec<u8> {
+        let mut file = b"GMBU".to_vec();
+        file.extend_from_slice(&[0, 2]);
+        file.extend_from_slice(&media_type.to_le_bytes());
+        for field in [2022u16, 9, 4, 1, 10, 20, 30, 500] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        // Frame table offset and length, written below
+        file.extend_from_slice(&[0; 8]);
+        // Other tables, empty
+        file.extend_from_slice(&[0; 40]);
+
+        let mut offsets = vec![];
+        for (elapsed, data) in frames {
+            offsets.push(file.len() as u32);
+            file.extend_from_slice(&elapsed.to_le_bytes());
+            file.extend_from_slice(&(data.len() as u32 + 4).to_le_bytes());
+            file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            file.extend_from_slice(data);
+        }
+
+        let table_offset = file.len() as u32;
+        file[24..28].copy_from_slice(&table_offset.to_le_bytes());
+        file[28..32].copy_from_slice(&(offsets.len() as u32 * 4).to_le_bytes());
+        for offset in offsets {
+            file.extend_from_slice(&offset.to_le_bytes());
+        }
+        file
+    }
+
+    #[test]
+    fn netmon_frames_read_in_order() {
+        let file = netmon_file(&[(0, &[0xde, 0xad]), (1_250_000, &[0xbe, 0xef, 0x00])], 1);
+
+        let mut netmon_reader = NetmonReader::new(file.as_slice()).unwrap();
+        assert_eq!(netmon_reader.link_type, LinkTypes::ETHERNET);
+
+        let record = netmon_reader.next_record().unwrap().unwrap();
+        assert_eq!(record.timestamp, 1_662_027_630_500_000);
+        assert_eq!(record.data, vec![0xde, 0xad]);
+        assert_eq!(record.original_length, 6);
+
+        let record = netmon_reader.next_record().unwrap().unwrap();
+        assert_eq!(record.timestamp, 1_662_027_631_750_000);
+        assert_eq!(record.data, vec![0xbe, 0xef, 0x00]);
+
+        assert!(netmon_reader.next_record().unwrap().is_none());
+
+        // Token Ring
+        assert!(matches!(
+            NetmonReader::new(netmon_file(&[], 2).as_slice()),
+            Err(SniffingError::InvalidCaptureFile(_))
+        ));
+
+        // Frame beyond the end of the file
+        let mut file = netmon_file(&[(0, &[0xde, 0xad])], 1);
+        let table_offset = file.len() - 4;
+        file[table_offset..].copy_from_slice(&1000u32.to_le_bytes());
+        let mut netmon_reader = NetmonReader::new(file.as_slice()).unwrap();
+        assert!(netmon_reader.next_record().is_err());
+    }
+}
@@ -0,0 +1,664 @@
+//! Extraction of the files transferred over HTTP and FTP
+//!
+//! HTTP bodies are taken from the reassembled HTTP requests and responses, already merged when
+//! chunked and decompressed when encoded (e.g. gzip), and text bodies are written as transferred
+//! rather than as decoded with their charset. Responses are named after the path of the request
+//! they answer, matched in order on the same TCP connection.
+//!
+//! FTP files are reassembled from the data connections announced on a control connection (port 21)
+//! by the PASV and EPSV replies or the PORT and EPRT commands, and named after the RETR, STOR or APPE
+//! command following the announcement. The segments of a data connection are placed by sequence
+//! number, so retransmitted ones are written once, and the file stops at the first missing segment.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::application::HttpContentType;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::filtering::PacketsCollection;
+use crate::payload_search::get_tcp_payload;
+use crate::{SniffingError, SniffingState};
+
+const FTP_CONTROL_PORT: &str = "21";
+/// SYN flag of the TCP header
+const TCP_SYN: u16 = 0x02;
+
+/// Object carved out of the collected packets
+#[derive(Serialize, Debug, Clone)]
+pub struct ExtractedObject {
+    pub filename: String,
+    pub protocol: String,
+    /// Host and path of the request that transferred the object, or FTP server and file name
+    pub source: String,
+    pub content_type: Option<String>,
+    pub size: usize,
+    /// IDs of the first and last packet carrying the object
+    pub first_packet_id: usize,
+    pub last_packet_id: usize,
+}
+
+/// Writes all the objects transferred over HTTP and FTP in `out_dir`, returning their manifest
+#[tauri::command]
+pub fn extract_objects(
+    state: tauri::State<SniffingState>,
+    out_dir: String,
+) -> Result<Vec<ExtractedObject>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+    let objects = extract_objects_internal(&packets_collection);
+    drop(packets_collection);
+
+    let out_dir = Path::new(&out_dir);
+    fs::create_dir_all(out_dir).map_err(|e| {
+        SniffingError::ObjectExtractionFailed(format!("Unable to create output directory: {}", e))
+    })?;
+
+    for (object, content) in &objects {
+        fs::write(out_dir.join(&object.filename), content).map_err(|e| {
+            SniffingError::ObjectExtractionFailed(format!(
+                "Unable to write {}: {}",
+                object.filename, e
+            ))
+        })?;
+    }
+
+    info!("Extracted {} objects in {:?}", objects.len(), out_dir);
+
+    Ok(objects.into_iter().map(|(object, _)| object).collect())
+}
+
+type Flow = (String, String, String, String);
+/// IP address and port
+type Endpoint = (String, String);
+
+/// Objects of all the protocols, ordered by the packet completing them
+fn extract_objects_internal(
+    packets_collection: &PacketsCollection,
+) -> Vec<(ExtractedObject, Vec<u8>)> {
+    let mut objects = extract_http_objects(&packets_collection.packets);
+    objects.extend(extract_ftp_objects(packets_collection));
+    objects.sort_by_key(|(object, _)| object.last_packet_id);
+
+    objects
+}
+
+fn extract_http_objects(packets: &[Arc<ParsedPacket>]) -> Vec<(ExtractedObject, Vec<u8>)> {
+    let mut objects = vec![];
+    // First packet carrying data of the message currently being transferred on each flow
+    let mut message_starts: HashMap<Flow, usize> = HashMap::new();
+    // Requests waiting for their response on each flow (as seen from the server)
+    let mut pending_requests: HashMap<Flow, VecDeque<(String, String)>> = HashMap::new();
+
+    for packet in packets {
+        let flow = match get_flow(packet) {
+            Some(flow) => flow,
+            None => continue,
+        };
+
+        if let Some(SerializablePacket::TcpPacket(tcp_packet)) = packet.get_transport_layer_packet()
+        {
+            if tcp_packet.length > 0 {
+                message_starts
+                    .entry(flow.clone())
+                    .or_insert(packet.get_id());
+            }
+        }
+
+        let (headers, payload, text_body, host, path) = match packet.get_application_layer_packet()
+        {
+            Some(SerializablePacket::HttpRequestPacket(request)) => {
+                let host = get_header(&request.headers, "Host")
+                    .unwrap_or(&flow.2)
+                    .to_owned();
+                let reverse_flow = (
+                    flow.2.clone(),
+                    flow.3.clone(),
+                    flow.0.clone(),
+                    flow.1.clone(),
+                );
+                pending_requests
+                    .entry(reverse_flow)
+                    .or_default()
+                    .push_back((host.clone(), request.path.clone()));
+
+                (
+                    &request.headers,
+                    &request.payload,
+                    &request.text_body,
+                    host,
+                    request.path.clone(),
+                )
+            }
+            Some(SerializablePacket::HttpResponsePacket(response)) => {
+                let (host, path) = pending_requests
+                    .get_mut(&flow)
+                    .and_then(|requests| requests.pop_front())
+                    .unwrap_or((flow.0.clone(), "/".to_owned()));
+
+                (
+                    &response.headers,
+                    &response.payload,
+                    &response.text_body,
+                    host,
+                    path,
+                )
+            }
+            _ => continue,
+        };
+
+        let first_packet_id = message_starts.remove(&flow).unwrap_or(packet.get_id());
+
+        let content = match get_content(payload, text_body.as_ref()) {
+            Some(content) if !content.is_empty() => content,
+            _ => continue,
+        };
+
+        let content_type = get_header(headers, "Content-Type").map(|c| c.to_owned());
+
+        objects.push((
+            ExtractedObject {
+                filename: get_filename(packet.get_id(), &path, content_type.as_deref()),
+                protocol: "HTTP".to_owned(),
+                source: format!("{}{}", host, path),
+                content_type,
+                size: content.len(),
+                first_packet_id,
+                last_packet_id: packet.get_id(),
+            },
+            content,
+        ));
+    }
+
+    objects
+}
+
+fn get_flow(packet: &ParsedPacket) -> Option<Flow> {
+    Some((
+        get_source_ip(packet)?,
+        get_source_port(packet)?,
+        get_dest_ip(packet)?,
+        get_dest_port(packet)?,
+    ))
+}
+
+fn get_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Bytes of the body, the ones a text was decoded from if known
+fn get_content(payload: &HttpContentType, text_body: Option<&Vec<u8>>) -> Option<Vec<u8>> {
+    match payload {
+        HttpContentType::TextCorrectlyDecoded(text)
+        | HttpContentType::TextMalformedDecoded(text)
+        | HttpContentType::TextDefaultDecoded(text) => {
+            Some(text_body.map_or_else(|| text.as_bytes().to_vec(), |text_body| text_body.clone()))
+        }
+        HttpContentType::Image(content)
+        | HttpContentType::Unknown(content)
+        | HttpContentType::Encoded(_, content)
+        | HttpContentType::Multipart(content) => Some(content.clone()),
+        HttpContentType::None => None,
+    }
+}
+
+/// Data transfer announced on an FTP control connection
+struct FtpTransfer {
+    /// Server of the control connection
+    server: String,
+    /// Argument of the command starting the transfer
+    filename: Option<String>,
+}
+
+/// Data sent on one direction of an FTP data connection
+struct FtpData {
+    transfer: usize,
+    /// Sequence number of the first byte of data
+    initial_sequence: Option<u32>,
+    /// Segments by offset from the initial sequence number
+    segments: BTreeMap<u32, Vec<u8>>,
+    first_packet_id: Option<usize>,
+    last_packet_id: usize,
+}
+
+fn extract_ftp_objects(packets_collection: &PacketsCollection) -> Vec<(ExtractedObject, Vec<u8>)> {
+    let mut transfers: Vec<FtpTransfer> = vec![];
+    // Latest transfer announced on each data endpoint
+    let mut announced: HashMap<Endpoint, usize> = HashMap::new();
+    // Transfer waiting for its command on each control connection (as seen from the client)
+    let mut pending: HashMap<Flow, usize> = HashMap::new();
+    // Control data not yet ended by a line terminator on each flow
+    let mut control_lines: HashMap<Flow, Vec<u8>> = HashMap::new();
+    let mut data_flows: Vec<FtpData> = vec![];
+    let mut data_flow_indexes: HashMap<Flow, usize> = HashMap::new();
+
+    for packet in &packets_collection.packets {
+        let tcp_packet = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => continue,
+        };
+        let flow = match get_flow(packet) {
+            Some(flow) => flow,
+            None => continue,
+        };
+        let payload = packets_collection
+            .raw_packets
+            .get(&packet.get_id())
+            .and_then(|raw_packet| get_tcp_payload(packet, &raw_packet.data, tcp_packet))
+            .unwrap_or_default();
+
+        if flow.1 == FTP_CONTROL_PORT || flow.3 == FTP_CONTROL_PORT {
+            let buffer = control_lines.entry(flow.clone()).or_default();
+            buffer.extend_from_slice(payload);
+
+            while let Some(end) = buffer.windows(2).position(|w| w == b"\r\n") {
+                let line = String::from_utf8_lossy(&buffer[..end]).into_owned();
+                buffer.drain(..end + 2);
+
+                handle_ftp_line(&flow, &line, &mut transfers, &mut announced, &mut pending);
+            }
+
+            continue;
+        }
+
+        let index = match data_flow_indexes.get(&flow) {
+            Some(index) => *index,
+            None => {
+                let transfer = announced
+                    .get(&(flow.0.clone(), flow.1.clone()))
+                    .or_else(|| announced.get(&(flow.2.clone(), flow.3.clone())));
+                let transfer = match transfer {
+                    Some(transfer) => *transfer,
+                    None => continue,
+                };
+
+                data_flows.push(FtpData {
+                    transfer,
+                    initial_sequence: None,
+                    segments: BTreeMap::new(),
+                    first_packet_id: None,
+                    last_packet_id: packet.get_id(),
+                });
+                data_flow_indexes.insert(flow, data_flows.len() - 1);
+                data_flows.len() - 1
+            }
+        };
+
+        let data = &mut data_flows[index];
+        if tcp_packet.flags & TCP_SYN != 0 {
+            data.initial_sequence = Some(tcp_packet.sequence.wrapping_add(1));
+        }
+        if payload.is_empty() {
+            continue;
+        }
+
+        let initial_sequence = *data.initial_sequence.get_or_insert(tcp_packet.sequence);
+        let segment = data
+            .segments
+            .entry(tcp_packet.sequence.wrapping_sub(initial_sequence))
+            .or_default();
+        if payload.len() > segment.len() {
+            *segment = payload.to_vec();
+        }
+        data.first_packet_id.get_or_insert(packet.get_id());
+        data.last_packet_id = packet.get_id();
+    }
+
+    data_flows
+        .into_iter()
+        .filter_map(|data| {
+            let first_packet_id = data.first_packet_id?;
+            let content = reassemble(data.segments);
+            let transfer = &transfers[data.transfer];
+            let filename = transfer.filename.as_deref().unwrap_or_default();
+
+            Some((
+                ExtractedObject {
+                    filename: get_filename(data.last_packet_id, filename, None),
+                    protocol: "FTP".to_owned(),
+                    source: format!("{}/{}", transfer.server, filename),
+                    content_type: None,
+                    size: content.len(),
+                    first_packet_id,
+                    last_packet_id: data.last_packet_id,
+                },
+                content,
+            ))
+        })
+        .collect()
+}
+
+/// Follows the data transfers announced and started by a line of an FTP control connection
+fn handle_ftp_line(
+    flow: &Flow,
+    line: &str,
+    transfers: &mut Vec<FtpTransfer>,
+    announced: &mut HashMap<Endpoint, usize>,
+    pending: &mut HashMap<Flow, usize>,
+) {
+    let from_server = flow.1 == FTP_CONTROL_PORT;
+    let (control, server, client) = if from_server {
+        (
+            (
+                flow.2.clone(),
+                flow.3.clone(),
+                flow.0.clone(),
+                flow.1.clone(),
+            ),
+            &flow.0,
+            &flow.2,
+        )
+    } else {
+        (flow.clone(), &flow.2, &flow.0)
+    };
+
+    let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+    let argument = argument.trim();
+
+    let endpoint = match (from_server, command.to_ascii_uppercase().as_str()) {
+        (true, "227") => parse_host_port(argument),
+        (true, "229") => argument
+            .find('(')
+            .and_then(|start| parse_extended_address(&argument[start + 1..], server)),
+        (false, "PORT") => parse_host_port(argument),
+        (false, "EPRT") => parse_extended_address(argument, client),
+        (false, "RETR") | (false, "STOR") | (false, "APPE") => {
+            if let Some(transfer) = pending.remove(&control) {
+                transfers[transfer].filename = Some(argument.to_owned());
+            }
+            None
+        }
+        _ => None,
+    };
+
+    if let Some(endpoint) = endpoint {
+        transfers.push(FtpTransfer {
+            server: server.clone(),
+            filename: None,
+        });
+        announced.insert(endpoint, transfers.len() - 1);
+        pending.insert(control, transfers.len() - 1);
+    }
+}
+
+/// Endpoint written as `h1,h2,h3,h4,p1,p2` by PORT and in the PASV reply
+fn parse_host_port(text: &str) -> Option<Endpoint> {
+    let numbers = text
+        .split(|c: char| !(c.is_ascii_digit() || c == ','))
+        .find(|numbers| numbers.matches(',').count() == 5)?
+        .split(',')
+        .map(|number| number.parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some((
+        Ipv4Addr::new(numbers[0], numbers[1], numbers[2], numbers[3]).to_string(),
+        (u16::from(numbers[4]) << 8 | u16::from(numbers[5])).to_string(),
+    ))
+}
+
+/// Endpoint written as `|protocol|address|port|` by EPRT and in the EPSV reply, where the address can
+/// be left out for the one of `host`
+fn parse_extended_address(text: &str, host: &str) -> Option<Endpoint> {
+    let delimiter = text.chars().next()?;
+    let fields = text.split(delimiter).collect::<Vec<&str>>();
+    let address = match fields.get(2)? {
+        &"" => host.to_owned(),
+        address => address.parse::<IpAddr>().ok()?.to_string(),
+    };
+    let port = fields.get(3)?.parse::<u16>().ok()?;
+
+    Some((address, port.to_string()))
+}
+
+/// Data of the segments in sequence, up to the first missing one
+fn reassemble(segments: BTreeMap<u32, Vec<u8>>) -> Vec<u8> {
+    let mut data = vec![];
+
+    for (offset, segment) in segments {
+        let offset = offset as usize;
+        if offset > data.len() {
+            warn!("FTP data missing after {} bytes", data.len());
+            break;
+        }
+        if offset + segment.len() > data.len() {
+            data.extend_from_slice(&segment[data.len() - offset..]);
+        }
+    }
+
+    data
+}
+
+/// Name the object after the last segment of the URL path, prefixed by the packet ID to avoid collisions
+fn get_filename(id: usize, path: &str, content_type: Option<&str>) -> String {
+    let path = path
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default();
+    let name = path
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let name = name.trim_start_matches('.');
+
+    match name {
+        "" => format!("{}_index.{}", id, get_extension(content_type)),
+        name if !name.contains('.') => format!("{}_{}.{}", id, name, get_extension(content_type)),
+        name => format!("{}_{}", id, name),
+    }
+}
+
+fn get_extension(content_type: Option<&str>) -> &'static str {
+    let mime = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_ascii_lowercase());
+
+    match mime.as_deref() {
+        Some("text/html") => "html",
+        Some("text/plain") => "txt",
+        Some("text/css") => "css",
+        Some("text/javascript") | Some("application/javascript") => "js",
+        Some("application/json") => "json",
+        Some("application/xml") | Some("text/xml") => "xml",
+        Some("application/pdf") => "pdf",
+        Some("image/png") => "png",
+        Some("image/jpeg") => "jpg",
+        Some("image/gif") => "gif",
+        Some("image/svg+xml") => "svg",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::parse_ethernet_bytes;
+    use sniffer_parser::serializable_packet::application::{
+        HttpContentType, SerializableHttpRequestPacket, SerializableHttpResponsePacket,
+    };
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::{extract_objects_internal, get_filename};
+
+    fn build_http_packet(
+        id: usize,
+        to_server: bool,
+        http: Option<SerializablePacket>,
+    ) -> ParsedPacket {
+        let (client, server) = (Ipv4Addr::new(10, 10, 10, 10), Ipv4Addr::new(11, 11, 11, 11));
+        let template = if to_server {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), client, server, 4444, 80)
+        } else {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), server, client, 80, 4444)
+        };
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_link_layer_packet(template.get_link_layer_packet().cloned());
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+        parsed_packet.set_application_layer_packet(http);
+
+        parsed_packet
+    }
+
+    #[test]
+    fn http_response_named_after_request() {
+        let request = SerializablePacket::HttpRequestPacket(SerializableHttpRequestPacket {
+            method: "GET".to_owned(),
+            path: "/files/report.pdf?download=1".to_owned(),
+            version: 1,
+            headers: vec![("Host".to_owned(), "example.com".to_owned())],
+            payload: HttpContentType::None,
+            text_body: None,
+        });
+        let response = SerializablePacket::HttpResponsePacket(SerializableHttpResponsePacket {
+            version: 1,
+            code: 200,
+            reason: "OK".to_owned(),
+            headers: vec![("Content-Type".to_owned(), "application/pdf".to_owned())],
+            payload: HttpContentType::Unknown(b"%PDF-1.4".to_vec()),
+            text_body: None,
+        });
+
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection.insert(Arc::new(build_http_packet(0, true, Some(request))));
+        packets_collection.insert(Arc::new(build_http_packet(1, false, None)));
+        packets_collection.insert(Arc::new(build_http_packet(2, false, None)));
+        packets_collection.insert(Arc::new(build_http_packet(3, false, Some(response))));
+
+        let objects = extract_objects_internal(&packets_collection);
+        assert_eq!(objects.len(), 1);
+
+        let (object, content) = &objects[0];
+        assert_eq!(object.filename, "3_report.pdf");
+        assert_eq!(object.source, "example.com/files/report.pdf?download=1");
+        assert_eq!(object.content_type.as_deref(), Some("application/pdf"));
+        assert_eq!(object.size, 8);
+        assert_eq!((object.first_packet_id, object.last_packet_id), (1, 3));
+        assert_eq!(content, b"%PDF-1.4");
+    }
+
+    #[test]
+    fn http_text_written_as_transferred() {
+        let response = SerializablePacket::HttpResponsePacket(SerializableHttpResponsePacket {
+            version: 1,
+            code: 200,
+            reason: "OK".to_owned(),
+            headers: vec![(
+                "Content-Type".to_owned(),
+                "text/plain; charset=iso-8859-1".to_owned(),
+            )],
+            payload: HttpContentType::TextCorrectlyDecoded("caf\u{e9}".to_owned()),
+            text_body: Some(b"caf\xe9".to_vec()),
+        });
+
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection.insert(Arc::new(build_http_packet(0, false, Some(response))));
+
+        let objects = extract_objects_internal(&packets_collection);
+        assert_eq!(objects[0].1, b"caf\xe9");
+    }
+
+    /// Ethernet frame of an IPv4 TCP segment, without options
+    fn build_tcp_frame(
+        source: ([u8; 4], u16),
+        dest: ([u8; 4], u16),
+        sequence: u32,
+        flags: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&(40 + payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 64, 6, 0, 0]);
+        frame.extend_from_slice(&source.0);
+        frame.extend_from_slice(&dest.0);
+        frame.extend_from_slice(&source.1.to_be_bytes());
+        frame.extend_from_slice(&dest.1.to_be_bytes());
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+
+        frame
+    }
+
+    #[test]
+    fn ftp_passive_transfer_reassembled() {
+        let control = ([10, 0, 0, 1], 40000);
+        let server = ([10, 0, 0, 2], 21);
+        let data_client = ([10, 0, 0, 1], 40001);
+        // 195 * 256 + 80
+        let data_server = ([10, 0, 0, 2], 50000);
+
+        let frames = [
+            build_tcp_frame(control, server, 1, 0x18, b"PASV\r\n"),
+            build_tcp_frame(
+                server,
+                control,
+                1,
+                0x18,
+                b"227 Entering Passive Mode (10,0,0,2,195,80).\r\n",
+            ),
+            build_tcp_frame(data_client, data_server, 99, 0x02, b""),
+            build_tcp_frame(control, server, 7, 0x18, b"RETR pub/notes.txt\r\n"),
+            build_tcp_frame(data_server, data_client, 999, 0x12, b""),
+            // Out of order, then retransmitted
+            build_tcp_frame(data_server, data_client, 1004, 0x18, b"fish\n"),
+            build_tcp_frame(data_server, data_client, 1000, 0x18, b"blue"),
+            build_tcp_frame(data_server, data_client, 1000, 0x18, b"blue"),
+        ];
+
+        let mut packets_collection = PacketsCollection::new();
+        for (id, frame) in frames.iter().enumerate() {
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, frame);
+            packets_collection.insert(Arc::new(parse_ethernet_bytes(frame, id)));
+        }
+
+        let objects = extract_objects_internal(&packets_collection);
+        assert_eq!(objects.len(), 1);
+
+        let (object, content) = &objects[0];
+        assert_eq!(object.filename, "7_notes.txt");
+        assert_eq!(object.protocol, "FTP");
+        assert_eq!(object.source, "10.0.0.2/pub/notes.txt");
+        assert_eq!((object.first_packet_id, object.last_packet_id), (5, 7));
+        assert_eq!(content, b"bluefish\n");
+    }
+
+    #[test]
+    fn sanitized_filenames() {
+        assert_eq!(
+            get_filename(1, "/", Some("text/html; charset=utf-8")),
+            "1_index.html"
+        );
+        assert_eq!(
+            get_filename(2, "/api/users", Some("application/json")),
+            "2_users.json"
+        );
+        assert_eq!(get_filename(3, "/../..%2Fetc/passwd", None), "3_passwd.bin");
+        assert_eq!(get_filename(4, "/a/..", None), "4_index.bin");
+    }
+}
@@ -0,0 +1,188 @@
+//! One-shot captures, for scripted grabs and tests
+//!
+//! `capture_n_packets` selects the interface, starts a sniffing process appending to the collected
+//! packets, waits until the requested number of packets is captured (or the timeout expires), stops the
+//! sniffing process and returns the captured packets. Packets stored while stopping, past the requested
+//! number, are kept in the collection but not returned
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::info;
+use pnet::datalink;
+use sniffer_parser::serializable_packet::ParsedPacket;
+use tauri::{Window, Wry};
+
+use crate::bookmarks::tag_bookmarks;
+use crate::conversation_colors::tag_conversation_colors;
+use crate::filtering::PacketsCollection;
+use crate::flow_time::tag_flow_times;
+use crate::interfaces::{find_interface, get_interface_display_name};
+use crate::services::tag_services;
+use crate::vendors::tag_vendors;
+use crate::{
+    start_sniffing_internal, stop_sniffing_internal, Actions, ContextualError, SniffingError,
+    SniffingState,
+};
+
+/// Interval between two checks of the number of captured packets
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Captures `n` packets on `interface` and returns them, or the ones captured before `timeout_secs`
+/// seconds elapse
+#[tauri::command(async)]
+pub fn capture_n_packets(
+    state: tauri::State<SniffingState>,
+    window: Window<Wry>,
+    interface: String,
+    n: usize,
+    timeout_secs: u64,
+) -> Result<Vec<ParsedPacket>, ContextualError> {
+    capture_n_packets_internal(&state, window, &interface, n, timeout_secs)
+        .map_err(|e| e.with_context(Actions::CAPTURE_PACKETS, Some(interface)))
+}
+
+fn capture_n_packets_internal(
+    state: &SniffingState,
+    window: Window<Wry>,
+    interface: &str,
+    n: usize,
+    timeout_secs: u64,
+) -> Result<Vec<ParsedPacket>, SniffingError> {
+    if n == 0 || timeout_secs == 0 {
+        return Err(SniffingError::InvalidConfiguration(
+            "Provide a positive number of packets and timeout".to_owned(),
+        ));
+    }
+
+    let interface = find_interface(datalink::interfaces(), interface)?;
+    let interface_name = get_interface_display_name(&interface);
+
+    let mut sniffing_info = state.info.lock().unwrap();
+    sniffing_info.interface_name = Some(interface_name.clone());
+    sniffing_info.interface = Some(interface);
+    sniffing_info.additional_interfaces.clear();
+    drop(sniffing_info);
+
+    let start = state.packets.lock().unwrap().packets.len();
+    start_sniffing_internal(false, true, None, None, state, window)?;
+
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    let captured = wait_for_packets(&state.packets, start, n, deadline);
+    stop_sniffing_internal(state, true, false)?;
+
+    info!(
+        "[{}] One-shot capture of {} packets{}",
+        interface_name,
+        captured,
+        if captured < n { " (timed out)" } else { "" }
+    );
+
+    let packets_collection = state.packets.lock().unwrap();
+    let mut packets = get_captured_packets(&packets_collection, start, n);
+    tag_services(&mut packets, &packets_collection.services);
+    tag_vendors(&mut packets, &packets_collection.vendors);
+    tag_bookmarks(&mut packets, &state.bookmarks.lock().unwrap());
+    tag_conversation_colors(&mut packets);
+    tag_flow_times(&mut packets, &packets_collection.flow_index);
+
+    Ok(packets)
+}
+
+/// Waits until `n` packets are collected after the first `start` ones, or until `deadline`, returning
+/// the number of packets collected meanwhile
+fn wait_for_packets(
+    packets: &Arc<Mutex<PacketsCollection>>,
+    start: usize,
+    n: usize,
+    deadline: Instant,
+) -> usize {
+    loop {
+        let captured = packets.lock().unwrap().packets.len().saturating_sub(start);
+        let now = Instant::now();
+        if captured >= n || now >= deadline {
+            return captured.min(n);
+        }
+
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+fn get_captured_packets(
+    packets_collection: &PacketsCollection,
+    start: usize,
+    n: usize,
+) -> Vec<ParsedPacket> {
+    packets_collection
+        .packets
+        .iter()
+        .skip(start)
+        .take(n)
+        .map(|packet| (**packet).clone())
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::{get_captured_packets, wait_for_packets};
+
+    fn build_packet(id: usize) -> Arc<ParsedPacket> {
+        let template = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+            4444,
+            443,
+        );
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn wait_for_captured_packets() {
+        let packets = Arc::new(Mutex::new(PacketsCollection::new()));
+        // Collected before the one-shot capture
+        packets.lock().unwrap().insert(build_packet(0));
+
+        let sniffer = {
+            let packets = Arc::clone(&packets);
+            std::thread::spawn(move || {
+                for id in 1..=3 {
+                    std::thread::sleep(Duration::from_millis(5));
+                    packets.lock().unwrap().insert(build_packet(id));
+                }
+            })
+        };
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert_eq!(wait_for_packets(&packets, 1, 2, deadline), 2);
+        assert!(Instant::now() < deadline);
+        sniffer.join().unwrap();
+
+        let captured = get_captured_packets(&packets.lock().unwrap(), 1, 2);
+        assert_eq!(
+            captured
+                .iter()
+                .map(|packet| packet.get_id())
+                .collect::<Vec<usize>>(),
+            vec![1, 2]
+        );
+
+        // Timed out, with the packets captured so far
+        let deadline = Instant::now() + Duration::from_millis(30);
+        assert_eq!(wait_for_packets(&packets, 1, 5, deadline), 3);
+        assert!(Instant::now() >= deadline);
+    }
+}
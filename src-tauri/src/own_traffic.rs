@@ -0,0 +1,249 @@
+//! Exclusion of the traffic generated by the app itself (e.g. the reverse DNS lookups of the hostnames)
+//!
+//! Each socket opened by the optional features is registered with its local port and remote endpoint
+//! for as long as it is open, and for `LINGER` after it's closed, since its packets can be parsed after
+//! the lookup completed. When the exclusion is enabled, the packets exchanged through a registered
+//! socket are dropped by the parser before reaching the capture collections.
+
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use log::info;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port,
+};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::SniffingState;
+
+/// Time a closed socket is still matched, covering the frames still queued for parsing
+const LINGER: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref OWN_SOCKETS: Mutex<OwnSockets> = Mutex::new(OwnSockets::new());
+}
+
+/// Transport protocols of the registered sockets
+#[allow(non_snake_case)]
+pub mod OwnProtocols {
+    pub const TCP: &str = "TCP";
+    pub const UDP: &str = "UDP";
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct OwnSocket {
+    id: u64,
+    protocol: &'static str,
+    local_port: u16,
+    remote: SocketAddr,
+    closed: Option<Instant>,
+}
+
+/// Sockets opened by the app, still open or closed recently
+#[derive(Debug)]
+struct OwnSockets {
+    sockets: Vec<OwnSocket>,
+    next_id: u64,
+}
+
+impl OwnSockets {
+    fn new() -> Self {
+        OwnSockets {
+            sockets: vec![],
+            next_id: 0,
+        }
+    }
+
+    fn register(
+        &mut self,
+        protocol: &'static str,
+        local_port: u16,
+        remote: SocketAddr,
+        now: Instant,
+    ) -> u64 {
+        self.sockets.retain(|socket| {
+            socket
+                .closed
+                .map_or(true, |closed| now.duration_since(closed) < LINGER)
+        });
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.sockets.push(OwnSocket {
+            id,
+            protocol,
+            local_port,
+            remote,
+            closed: None,
+        });
+
+        id
+    }
+
+    fn close(&mut self, id: u64, now: Instant) {
+        if let Some(socket) = self.sockets.iter_mut().find(|socket| socket.id == id) {
+            socket.closed = Some(now);
+        }
+    }
+
+    /// Whether the packet was sent or received through one of the sockets
+    fn matches(&self, packet: &ParsedPacket, now: Instant) -> bool {
+        let protocol = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(_)) => OwnProtocols::TCP,
+            Some(SerializablePacket::UdpPacket(_)) => OwnProtocols::UDP,
+            _ => return false,
+        };
+
+        let endpoints = (
+            get_source_ip(packet),
+            get_source_port(packet),
+            get_dest_ip(packet),
+            get_dest_port(packet),
+        );
+        let (source_ip, source_port, dest_ip, dest_port) = match endpoints {
+            (Some(source_ip), Some(source_port), Some(dest_ip), Some(dest_port)) => {
+                (source_ip, source_port, dest_ip, dest_port)
+            }
+            _ => return false,
+        };
+
+        self.sockets.iter().any(|socket| {
+            let local_port = socket.local_port.to_string();
+            let remote_ip = socket.remote.ip().to_string();
+            let remote_port = socket.remote.port().to_string();
+
+            let active = socket
+                .closed
+                .map_or(true, |closed| now.duration_since(closed) < LINGER);
+            let sent =
+                source_port == local_port && dest_ip == remote_ip && dest_port == remote_port;
+            let received =
+                dest_port == local_port && source_ip == remote_ip && source_port == remote_port;
+
+            socket.protocol == protocol && active && (sent || received)
+        })
+    }
+}
+
+/// Registration of a socket opened by the app, closed when dropped
+pub struct OwnSocketGuard {
+    id: u64,
+}
+
+impl Drop for OwnSocketGuard {
+    fn drop(&mut self) {
+        OWN_SOCKETS.lock().unwrap().close(self.id, Instant::now());
+    }
+}
+
+/// Registers a socket of the app, bound to `local` and connected to `remote`, until the returned guard
+/// is dropped
+pub fn register_own_socket(
+    protocol: &'static str,
+    local: SocketAddr,
+    remote: SocketAddr,
+) -> OwnSocketGuard {
+    let id = OWN_SOCKETS
+        .lock()
+        .unwrap()
+        .register(protocol, local.port(), remote, Instant::now());
+
+    OwnSocketGuard { id }
+}
+
+/// Whether the packet belongs to the traffic of the app
+pub fn is_own_traffic(packet: &ParsedPacket) -> bool {
+    OWN_SOCKETS.lock().unwrap().matches(packet, Instant::now())
+}
+
+/// Excludes (or includes again) the traffic of the app from the packets collected, both for the current
+/// and the following sniffing processes
+#[tauri::command]
+pub fn set_exclude_own_traffic(state: tauri::State<SniffingState>, enabled: bool) {
+    info!(
+        "Traffic of the app {}",
+        if enabled { "excluded" } else { "included" }
+    );
+    *state.exclude_own_traffic.lock().unwrap() = enabled;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::time::{Duration, Instant};
+
+    use pnet::util::MacAddr;
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{OwnProtocols, OwnSockets, LINGER};
+
+    #[test]
+    fn packets_of_own_sockets() {
+        let local = Ipv4Addr::new(10, 0, 0, 1);
+        let nameserver = Ipv4Addr::new(10, 0, 0, 53);
+        let now = Instant::now();
+
+        let mut own_sockets = OwnSockets::new();
+        let id = own_sockets.register(
+            OwnProtocols::TCP,
+            40000,
+            SocketAddr::from((nameserver, 53)),
+            now,
+        );
+
+        let query = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            local,
+            nameserver,
+            40000,
+            53,
+        );
+        let response = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            nameserver,
+            local,
+            53,
+            40000,
+        );
+        let other_port = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            local,
+            nameserver,
+            40001,
+            53,
+        );
+        let other_server = build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            local,
+            Ipv4Addr::new(10, 0, 0, 54),
+            40000,
+            53,
+        );
+
+        assert!(own_sockets.matches(&query, now));
+        assert!(own_sockets.matches(&response, now));
+        assert!(!own_sockets.matches(&other_port, now));
+        assert!(!own_sockets.matches(&other_server, now));
+
+        // Still matched for a while after closing
+        own_sockets.close(id, now);
+        assert!(own_sockets.matches(&response, now + LINGER / 2));
+        assert!(!own_sockets.matches(&response, now + LINGER));
+
+        // Expired sockets are removed on the next registration
+        own_sockets.register(
+            OwnProtocols::UDP,
+            40002,
+            SocketAddr::from((nameserver, 53)),
+            now + LINGER + Duration::from_secs(1),
+        );
+        assert_eq!(own_sockets.sockets.len(), 1);
+    }
+}
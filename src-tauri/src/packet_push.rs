@@ -0,0 +1,252 @@
+//! Push of the parsed packets to the frontend, complementing the polling of `get_packets`
+//!
+//! - `off`: nothing is pushed (default)
+//! - `summary`: the fields shown by the packets table, see [`PacketSummary`]
+//! - `full`: the whole parsed packets, as returned by `get_packets`
+//!
+//! Serializing every packet over IPC is expensive, so the packets parsed by a sniffing process are
+//! gathered in batches and emitted with a `packet` event at most once every interval, carrying all the
+//! packets parsed in the meantime. The batch still pending is emitted when the sniffing process ends.
+
+use std::time::{Duration, Instant};
+
+use log::info;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    get_dest_ip, get_dest_mac, get_dest_port, get_source_ip, get_source_mac, get_source_port,
+};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::conversation_colors::tag_conversation_colors;
+use crate::filtering::PacketsCollection;
+use crate::flow_time::tag_flow_times;
+use crate::report::get_sender_receiver;
+use crate::services::tag_services;
+use crate::vendors::tag_vendors;
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod PacketPushModes {
+    pub const OFF: &str = "off";
+    pub const SUMMARY: &str = "summary";
+    pub const FULL: &str = "full";
+
+    pub const ALL: [&str; 3] = [OFF, SUMMARY, FULL];
+}
+
+const DEFAULT_PUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PacketPushConfig {
+    pub mode: &'static str,
+    /// Minimum time between two `packet` events
+    pub interval: Duration,
+}
+
+impl PacketPushConfig {
+    pub fn new() -> Self {
+        PacketPushConfig {
+            mode: PacketPushModes::OFF,
+            interval: DEFAULT_PUSH_INTERVAL,
+        }
+    }
+}
+
+/// Fields of a packet shown by the packets table
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PacketSummary {
+    pub id: usize,
+    pub timestamp: i64,
+    /// Topmost protocol recognized
+    pub protocol: Option<String>,
+    pub source_mac: Option<String>,
+    pub dest_mac: Option<String>,
+    pub source_ip: Option<String>,
+    pub dest_ip: Option<String>,
+    pub source_port: Option<String>,
+    pub dest_port: Option<String>,
+    /// Length of the captured frame
+    pub length: usize,
+    pub service: Option<String>,
+    pub conversation_color: Option<usize>,
+}
+
+impl PacketSummary {
+    fn new(packet: &ParsedPacket, length: usize) -> Self {
+        PacketSummary {
+            id: packet.get_id(),
+            timestamp: packet.get_timestamp(),
+            protocol: get_sender_receiver(packet).1.pop(),
+            source_mac: get_source_mac(packet),
+            dest_mac: get_dest_mac(packet),
+            source_ip: get_source_ip(packet),
+            dest_ip: get_dest_ip(packet),
+            source_port: get_source_port(packet),
+            dest_port: get_dest_port(packet),
+            length,
+            service: packet.get_service().cloned(),
+            conversation_color: packet.get_conversation_color(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum PushedPacket {
+    Summary(Box<PacketSummary>),
+    Full(Box<ParsedPacket>),
+}
+
+/// Packets parsed since the last `packet` event
+#[derive(Debug, Default)]
+pub struct PacketBatch {
+    packets: Vec<PushedPacket>,
+    last_emitted: Option<Instant>,
+}
+
+impl PacketBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, packet: PushedPacket) {
+        self.packets.push(packet);
+    }
+
+    /// Takes the pending packets once `interval` elapsed since the last batch, `None` if there are none
+    pub fn take_due(&mut self, interval: Duration, now: Instant) -> Option<Vec<PushedPacket>> {
+        if self.packets.is_empty()
+            || self.last_emitted.map_or(false, |last_emitted| {
+                now.duration_since(last_emitted) < interval
+            })
+        {
+            return None;
+        }
+
+        self.last_emitted = Some(now);
+        Some(std::mem::take(&mut self.packets))
+    }
+}
+
+/// Selects what is pushed of each parsed packet, and the minimum time between two `packet` events
+#[tauri::command]
+pub fn set_packet_push(
+    state: tauri::State<SniffingState>,
+    mode: String,
+    interval_ms: Option<u64>,
+) -> Result<(), SniffingError> {
+    let mode = mode.to_lowercase();
+    let mode = match PacketPushModes::ALL.iter().find(|known| **known == mode) {
+        Some(mode) => *mode,
+        None => {
+            return Err(SniffingError::InvalidConfiguration(format!(
+                "Unknown packet push mode: {} (available: {})",
+                mode,
+                PacketPushModes::ALL.join(", ")
+            )))
+        }
+    };
+
+    let mut packet_push = state.packet_push.lock().unwrap();
+    packet_push.mode = mode;
+    if let Some(interval_ms) = interval_ms {
+        packet_push.interval = Duration::from_millis(interval_ms);
+    }
+
+    info!(
+        "Packet push: {}, every {:?}",
+        packet_push.mode, packet_push.interval
+    );
+    Ok(())
+}
+
+/// Form of the last collected packet to push, tagged as by `get_packets`
+pub(crate) fn get_pushed_packet(
+    mode: &str,
+    packets_collection: &PacketsCollection,
+    length: usize,
+) -> Option<PushedPacket> {
+    if mode == PacketPushModes::OFF {
+        return None;
+    }
+
+    let mut packets = vec![ParsedPacket::clone(packets_collection.packets.last()?)];
+    tag_services(&mut packets, &packets_collection.services);
+    tag_vendors(&mut packets, &packets_collection.vendors);
+    tag_conversation_colors(&mut packets);
+    tag_flow_times(&mut packets, &packets_collection.flow_index);
+    let packet = packets.pop()?;
+
+    if mode == PacketPushModes::FULL {
+        Some(PushedPacket::Full(Box::new(packet)))
+    } else {
+        Some(PushedPacket::Summary(Box::new(PacketSummary::new(
+            &packet, length,
+        ))))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use pnet::util::MacAddr;
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::{get_pushed_packet, PacketBatch, PacketPushModes, PushedPacket};
+
+    #[test]
+    fn packets_pushed_in_batches() {
+        let mut packets_collection = PacketsCollection::new();
+        assert!(get_pushed_packet(PacketPushModes::SUMMARY, &packets_collection, 60).is_none());
+
+        packets_collection.insert(Arc::new(build_test_parsed_packet(
+            MacAddr::new(0x02, 0, 0, 0, 0, 1),
+            MacAddr::new(0x02, 0, 0, 0, 0, 2),
+            "10.0.0.1".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+            4444,
+            80,
+        )));
+
+        assert!(get_pushed_packet(PacketPushModes::OFF, &packets_collection, 60).is_none());
+        let summary = match get_pushed_packet(PacketPushModes::SUMMARY, &packets_collection, 60) {
+            Some(PushedPacket::Summary(summary)) => summary,
+            _ => panic!("Summary not pushed"),
+        };
+        assert_eq!(summary.protocol.as_deref(), Some("TCP"));
+        assert_eq!(summary.source_ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(summary.dest_port.as_deref(), Some("80"));
+        assert_eq!(summary.length, 60);
+        assert!(matches!(
+            get_pushed_packet(PacketPushModes::FULL, &packets_collection, 60),
+            Some(PushedPacket::Full(_))
+        ));
+
+        let mut batch = PacketBatch::new();
+        let interval = Duration::from_millis(100);
+        let start = Instant::now();
+        assert!(batch.take_due(interval, start).is_none());
+
+        let pushed = get_pushed_packet(PacketPushModes::SUMMARY, &packets_collection, 60).unwrap();
+        batch.push(pushed.clone());
+        batch.push(pushed.clone());
+        assert_eq!(batch.take_due(interval, start).unwrap().len(), 2);
+
+        // Held until the interval elapsed
+        batch.push(pushed);
+        assert!(batch
+            .take_due(interval, start + Duration::from_millis(50))
+            .is_none());
+        assert_eq!(
+            batch
+                .take_due(interval, start + Duration::from_millis(100))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
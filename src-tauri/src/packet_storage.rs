@@ -0,0 +1,406 @@
+//! Storage of the raw bytes of the collected packets
+//!
+//! - `memory`: in the heap of the process (default)
+//! - `mapped`: in files mapped in memory, on Linux only. The OS pages the frames in and out of the files as
+//!   needed, so that captures larger than the RAM can be kept whole, instead of trimmed by a ring buffer
+//!
+//! The link layer payload of a collected packet is a view of its stored frame rather than a copy, so that
+//! the frames are only held once. The rest of the parsed packets stays in memory, along with the indexes,
+//! the bodies of the reassembled application messages (e.g. HTTP, TLS) and the decoded fields, which can
+//! add up to a good share of the frames when most of the traffic is parsed above the transport layer.
+//! Mapped frames are written in chunks of `CHUNK_SIZE` bytes, each one in its own file of the chosen
+//! directory (the temporary one by default). The disk space of a chunk is reserved when it's created and
+//! its file is removed right after being mapped, so that the space is given back as soon as no frame of
+//! the chunk is collected anymore, even if the app crashes.
+
+use std::fmt;
+use std::ops::Deref;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log::info;
+#[cfg(target_os = "linux")]
+use log::warn;
+use sniffer_parser::serializable_packet::{PacketBytes, ParsedPacket, SerializablePacket};
+
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod PacketStorages {
+    pub const MEMORY: &str = "memory";
+    pub const MAPPED: &str = "mapped";
+
+    #[cfg(target_os = "linux")]
+    pub const ALL: [&str; 2] = [MEMORY, MAPPED];
+    #[cfg(not(target_os = "linux"))]
+    pub const ALL: [&str; 1] = [MEMORY];
+}
+
+/// Selects where the raw bytes of the packets are stored, moving the ones already collected
+///
+/// `directory` holds the files of the `mapped` storage, the temporary directory if not provided
+#[tauri::command]
+pub fn set_packet_storage(
+    state: tauri::State<SniffingState>,
+    storage: String,
+    directory: Option<String>,
+) -> Result<(), SniffingError> {
+    let storage = storage.to_lowercase();
+    let frame_store = get_frame_store(&storage, directory)?;
+
+    info!("Packet storage: {}", storage);
+    state.packets.lock().unwrap().set_frame_store(frame_store);
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn get_frame_store(storage: &str, directory: Option<String>) -> Result<FrameStore, SniffingError> {
+    match storage {
+        PacketStorages::MEMORY => Ok(FrameStore::default()),
+        PacketStorages::MAPPED => {
+            let directory = directory.map_or_else(std::env::temp_dir, PathBuf::from);
+            if !directory.is_dir() {
+                return Err(SniffingError::InvalidConfiguration(format!(
+                    "Not a directory: {}",
+                    directory.display()
+                )));
+            }
+
+            Ok(FrameStore {
+                mapped: Some(MappedFrames::new(directory, CHUNK_SIZE)),
+            })
+        }
+        _ => Err(get_unknown_storage_error(storage)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_frame_store(storage: &str, _directory: Option<String>) -> Result<FrameStore, SniffingError> {
+    match storage {
+        PacketStorages::MEMORY => Ok(FrameStore::default()),
+        _ => Err(get_unknown_storage_error(storage)),
+    }
+}
+
+fn get_unknown_storage_error(storage: &str) -> SniffingError {
+    SniffingError::InvalidConfiguration(format!(
+        "Unknown or unsupported packet storage: {} (available: {})",
+        storage,
+        PacketStorages::ALL.join(", ")
+    ))
+}
+
+/// Raw bytes of a packet
+pub enum FrameData {
+    Owned(Vec<u8>),
+    #[cfg(target_os = "linux")]
+    Mapped {
+        chunk: Arc<MappedChunk>,
+        offset: usize,
+        length: usize,
+    },
+}
+
+impl Deref for FrameData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FrameData::Owned(data) => data,
+            #[cfg(target_os = "linux")]
+            FrameData::Mapped {
+                chunk,
+                offset,
+                length,
+            } => unsafe { std::slice::from_raw_parts(chunk.map.add(*offset), *length) },
+        }
+    }
+}
+
+impl AsRef<[u8]> for FrameData {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for FrameData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Points the link layer payload of the packet to the stored frame it was parsed from, dropping its copy
+pub fn share_link_payload(packet: &mut ParsedPacket, frame: &Arc<FrameData>) {
+    let mut link_layer_packet = packet.get_link_layer_packet().cloned();
+    let payload = match &mut link_layer_packet {
+        Some(SerializablePacket::EthernetPacket(ethernet)) => &mut ethernet.payload,
+        Some(SerializablePacket::LoopbackPacket(loopback)) => &mut loopback.payload,
+        _ => return,
+    };
+
+    // The payload ends the frame, trailer included
+    if !frame.ends_with(payload) {
+        return;
+    }
+    *payload = PacketBytes::shared(
+        Arc::clone(frame) as Arc<dyn AsRef<[u8]> + Send + Sync>,
+        frame.len() - payload.len()..frame.len(),
+    );
+
+    packet.set_link_layer_packet(link_layer_packet);
+}
+
+/// Destination of the raw bytes of the packets being collected
+#[derive(Default, Debug)]
+pub struct FrameStore {
+    #[cfg(target_os = "linux")]
+    mapped: Option<MappedFrames>,
+}
+
+impl FrameStore {
+    pub fn get_storage(&self) -> &'static str {
+        #[cfg(target_os = "linux")]
+        if self.mapped.is_some() {
+            return PacketStorages::MAPPED;
+        }
+
+        PacketStorages::MEMORY
+    }
+
+    /// Copies the frame in the store
+    ///
+    /// Frames are kept in memory when they can't be mapped (e.g. the disk is full)
+    pub fn store(&mut self, data: &[u8]) -> FrameData {
+        #[cfg(target_os = "linux")]
+        if let Some(mapped) = &mut self.mapped {
+            match mapped.store(data) {
+                Ok(Some(frame_data)) => return frame_data,
+                Ok(None) => {}
+                Err(e) => warn!("Unable to map frame, kept in memory: {}", e),
+            }
+        }
+
+        FrameData::Owned(data.to_vec())
+    }
+
+    /// Starts a new chunk for the following frames, so that the current one is released along with its frames
+    pub fn clear(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(mapped) = &mut self.mapped {
+            mapped.current = None;
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+const CHUNK_SIZE: usize = 64 << 20;
+
+/// Number of chunks created, to name their files
+#[cfg(target_os = "linux")]
+static CREATED_CHUNKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Mapping of a chunk file, unmapped when its last frame is dropped
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct MappedChunk {
+    map: *mut u8,
+    size: usize,
+}
+
+// Frames are only written in the part of the chunk not handed out yet
+#[cfg(target_os = "linux")]
+unsafe impl Send for MappedChunk {}
+#[cfg(target_os = "linux")]
+unsafe impl Sync for MappedChunk {}
+
+#[cfg(target_os = "linux")]
+impl MappedChunk {
+    fn create(path: &std::path::Path, size: usize) -> std::io::Result<Self> {
+        use std::fs::{self, OpenOptions};
+        use std::io;
+        use std::os::unix::io::AsRawFd;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        // Its space is given back once closed and unmapped
+        let _result = fs::remove_file(path);
+
+        // Writing in a sparse file beyond the free space would crash the process
+        let result = unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, size as libc::off_t) };
+        if result != 0 {
+            return Err(io::Error::from_raw_os_error(result));
+        }
+
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(MappedChunk {
+            map: map as *mut u8,
+            size,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for MappedChunk {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.map as *mut libc::c_void, self.size);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct MappedFrames {
+    directory: PathBuf,
+    chunk_size: usize,
+    /// Chunk being filled, with the number of bytes written
+    current: Option<(Arc<MappedChunk>, usize)>,
+}
+
+#[cfg(target_os = "linux")]
+impl MappedFrames {
+    fn new(directory: PathBuf, chunk_size: usize) -> Self {
+        MappedFrames {
+            directory,
+            chunk_size,
+            current: None,
+        }
+    }
+
+    /// Copies the frame in the current chunk, `None` if larger than a chunk
+    fn store(&mut self, data: &[u8]) -> std::io::Result<Option<FrameData>> {
+        if data.len() > self.chunk_size {
+            return Ok(None);
+        }
+
+        if !matches!(&self.current, Some((_, used)) if used + data.len() <= self.chunk_size) {
+            let path = self.directory.join(format!(
+                "wirefish-{}-{}.frames",
+                std::process::id(),
+                CREATED_CHUNKS.fetch_add(1, Ordering::Relaxed)
+            ));
+            self.current = Some((Arc::new(MappedChunk::create(&path, self.chunk_size)?), 0));
+        }
+
+        let (chunk, used) = self.current.as_mut().unwrap();
+        let offset = *used;
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), chunk.map.add(offset), data.len());
+        }
+        *used += data.len();
+
+        Ok(Some(FrameData::Mapped {
+            chunk: Arc::clone(chunk),
+            offset,
+            length: data.len(),
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use sniffer_parser::parse_ethernet_bytes;
+    use sniffer_parser::serializable_packet::SerializablePacket;
+
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::PacketsCollection;
+
+    #[test]
+    fn link_payload_shared_with_frame() {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00, 1, 2, 3]);
+
+        let mut packets_collection = PacketsCollection::new();
+        let mut parsed_packet = parse_ethernet_bytes(&frame, 0);
+        let serialized = serde_json::to_string(&parsed_packet).unwrap();
+
+        packets_collection.add_raw_packet(0, LinkTypes::ETHERNET, &frame);
+        packets_collection.share_raw_packet(&mut parsed_packet);
+
+        let stored_frame = &packets_collection.raw_packets[&0].data;
+        match parsed_packet.get_link_layer_packet() {
+            Some(SerializablePacket::EthernetPacket(ethernet)) => {
+                assert_eq!(ethernet.payload, vec![1, 2, 3]);
+                assert_eq!(ethernet.payload.as_ptr(), stored_frame[14..].as_ptr());
+            }
+            _ => panic!("Not an Ethernet packet"),
+        }
+        assert_eq!(Arc::strong_count(stored_frame), 2);
+        assert_eq!(serde_json::to_string(&parsed_packet).unwrap(), serialized);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn frames_stored_in_mapped_chunks() {
+        use std::env::temp_dir;
+
+        use super::{FrameData, FrameStore, MappedFrames, PacketStorages};
+
+        let mut frame_store = FrameStore {
+            mapped: Some(MappedFrames::new(temp_dir(), 16)),
+        };
+        assert_eq!(frame_store.get_storage(), PacketStorages::MAPPED);
+
+        let frames = [vec![1; 10], vec![2; 6], vec![3; 4], vec![4; 20]];
+        let stored = frames
+            .iter()
+            .map(|frame| frame_store.store(frame))
+            .collect::<Vec<FrameData>>();
+
+        for (frame, frame_data) in frames.iter().zip(&stored) {
+            assert_eq!(&frame_data[..], &frame[..]);
+        }
+        match (&stored[0], &stored[1], &stored[2]) {
+            (
+                FrameData::Mapped { chunk: first, .. },
+                FrameData::Mapped {
+                    chunk: second,
+                    offset: 10,
+                    ..
+                },
+                FrameData::Mapped {
+                    chunk: third,
+                    offset: 0,
+                    ..
+                },
+            ) => {
+                // The third frame doesn't fit in the first chunk
+                assert!(std::sync::Arc::ptr_eq(first, second));
+                assert!(!std::sync::Arc::ptr_eq(first, third));
+            }
+            _ => panic!("Frames not mapped"),
+        }
+        // Larger than a chunk
+        assert!(matches!(stored[3], FrameData::Owned(_)));
+
+        // Removed once mapped
+        let prefix = format!("wirefish-{}-", std::process::id());
+        assert!(!std::fs::read_dir(temp_dir()).unwrap().any(|entry| entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with(&prefix)));
+    }
+}
@@ -0,0 +1,275 @@
+//! Search of a substring in the payloads of the collected packets
+//!
+//! The payload of a packet is the one of its TCP segment or UDP datagram, taken from the stored frame
+//! (so the TLS records and the HTTP messages as sent), plus the decoded body of its HTTP message and
+//! the DATA frames of its HTTP/2 packet, so that a decompressed body is searched too.
+//!
+//! The packets are searched in parallel, and the matches are reported in collection order whatever the
+//! thread that found them.
+
+use std::borrow::Cow;
+
+use log::{info, warn};
+use rayon::prelude::*;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::application::HttpContentType;
+use sniffer_parser::serializable_packet::transport::SerializableTcpPacket;
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+use sniffer_parser::TunnelTypes;
+
+use crate::filtering::{PacketsCollection, RawPacket};
+use crate::streams::get_udp_payload;
+use crate::{SniffingError, SniffingState};
+
+/// IDs of the matching packets returned along with their count
+const SEARCH_PAGE_SIZE: usize = 100;
+
+const IPV6_HEADER_LENGTH: usize = 40;
+/// LLC/SNAP header preceding the network layer packet in 802.11 data frames
+const LLC_SNAP_HEADER_LENGTH: usize = 8;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct PayloadSearch {
+    /// Number of matching packets
+    pub count: usize,
+    /// IDs of the first matching packets, in collection order
+    pub packet_ids: Vec<usize>,
+}
+
+/// Returns the packets whose payload contains `query`, case insensitive for ASCII letters unless
+/// `case_sensitive`
+#[tauri::command]
+pub fn search_payload(
+    state: tauri::State<SniffingState>,
+    query: String,
+    case_sensitive: bool,
+) -> Result<PayloadSearch, SniffingError> {
+    if query.is_empty() {
+        warn!("Empty payload search");
+        return Err(SniffingError::InvalidFilterValue(
+            "The search query can't be empty".to_owned(),
+        ));
+    }
+
+    let packets_collection = state.packets.lock().unwrap();
+    let search = search_payload_internal(query.as_bytes(), case_sensitive, &packets_collection);
+
+    info!("Payload search of {:?}: {} packets", query, search.count);
+
+    Ok(search)
+}
+
+fn search_payload_internal(
+    query: &[u8],
+    case_sensitive: bool,
+    packets_collection: &PacketsCollection,
+) -> PayloadSearch {
+    let matches = packets_collection
+        .packets
+        .par_iter()
+        .filter(|packet| {
+            let raw_packet = packets_collection.raw_packets.get(&packet.get_id());
+            get_payloads(packet, raw_packet)
+                .iter()
+                .any(|payload| contains(payload, query, case_sensitive))
+        })
+        .map(|packet| packet.get_id())
+        .collect::<Vec<usize>>();
+
+    PayloadSearch {
+        count: matches.len(),
+        packet_ids: matches.into_iter().take(SEARCH_PAGE_SIZE).collect(),
+    }
+}
+
+/// Payloads of the packet: the transport one and the decoded application ones
+fn get_payloads<'a>(
+    packet: &'a ParsedPacket,
+    raw_packet: Option<&'a RawPacket>,
+) -> Vec<Cow<'a, [u8]>> {
+    let mut payloads = vec![];
+
+    match (packet.get_transport_layer_packet(), raw_packet) {
+        (Some(SerializablePacket::TcpPacket(tcp)), Some(raw_packet)) => {
+            payloads.extend(get_tcp_payload(packet, &raw_packet.data, tcp).map(Cow::Borrowed))
+        }
+        (Some(SerializablePacket::UdpPacket(udp)), Some(raw_packet)) => {
+            payloads.push(Cow::Owned(get_udp_payload(&raw_packet.data, udp)))
+        }
+        _ => (),
+    }
+
+    let body = match packet.get_application_layer_packet() {
+        Some(SerializablePacket::HttpRequestPacket(request)) => Some(&request.payload),
+        Some(SerializablePacket::HttpResponsePacket(response)) => Some(&response.payload),
+        Some(SerializablePacket::Http2Packet(http2_packet)) => {
+            payloads.extend(
+                http2_packet
+                    .frames
+                    .iter()
+                    .filter_map(|frame| frame.data.as_deref().map(Cow::Borrowed)),
+            );
+            None
+        }
+        _ => None,
+    };
+
+    match body {
+        Some(HttpContentType::TextCorrectlyDecoded(text))
+        | Some(HttpContentType::TextMalformedDecoded(text))
+        | Some(HttpContentType::TextDefaultDecoded(text)) => {
+            payloads.push(Cow::Borrowed(text.as_bytes()))
+        }
+        Some(HttpContentType::Image(data))
+        | Some(HttpContentType::Unknown(data))
+        | Some(HttpContentType::Encoded(_, data))
+        | Some(HttpContentType::Multipart(data)) => payloads.push(Cow::Borrowed(data)),
+        _ => (),
+    }
+
+    payloads
+}
+
+/// Captured part of the data carried by the TCP segment of the packet, `None` when the frame doesn't
+/// reach it
+///
+/// The data starts after the TCP header, whose options are included by `data_offset`, and is
+/// `tcp.length` bytes long, so the link layer trailer (e.g. Ethernet padding) is left out
+pub(crate) fn get_tcp_payload<'a>(
+    packet: &ParsedPacket,
+    frame: &'a [u8],
+    tcp: &SerializableTcpPacket,
+) -> Option<&'a [u8]> {
+    let start = get_transport_offset(packet, frame)? + tcp.data_offset as usize * 4;
+    let end = frame.len().min(start + tcp.length);
+
+    frame.get(start..end)
+}
+
+/// Offset of the transport layer header in the frame, from the lengths of the link layer and IP headers
+/// parsed before it, the ones of the IPv6 packet tunneled by 6to4 included
+fn get_transport_offset(packet: &ParsedPacket, frame: &[u8]) -> Option<usize> {
+    let network_offset = match packet.get_link_layer_packet()? {
+        SerializablePacket::EthernetPacket(ethernet) => {
+            frame.len().checked_sub(ethernet.payload.len())?
+        }
+        SerializablePacket::LoopbackPacket(loopback) => {
+            frame.len().checked_sub(loopback.payload.len())?
+        }
+        SerializablePacket::Dot11Packet(dot11) => {
+            dot11
+                .radiotap
+                .as_ref()
+                .map_or(0, |radiotap| radiotap.length as usize)
+                + dot11.header_length
+                + LLC_SNAP_HEADER_LENGTH
+        }
+        _ => return None,
+    };
+
+    let network_length = match packet.get_network_layer_packet()? {
+        SerializablePacket::Ipv4Packet(ipv4) => ipv4.header_length as usize * 4,
+        SerializablePacket::Ipv6Packet(_) => IPV6_HEADER_LENGTH,
+        _ => return None,
+    };
+
+    // Teredo tunnels are left at their UDP datagram
+    let tunnel_length = match packet.get_tunnel_layer_packet() {
+        Some(SerializablePacket::TunnelPacket(tunnel))
+            if tunnel.tunnel_type == TunnelTypes::SIX_TO_FOUR =>
+        {
+            IPV6_HEADER_LENGTH
+        }
+        _ => 0,
+    };
+
+    Some(network_offset + network_length + tunnel_length)
+}
+
+fn contains(payload: &[u8], query: &[u8], case_sensitive: bool) -> bool {
+    if case_sensitive {
+        payload.windows(query.len()).any(|window| window == query)
+    } else {
+        payload
+            .windows(query.len())
+            .any(|window| window.eq_ignore_ascii_case(query))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use sniffer_parser::parse_ethernet_bytes;
+
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::PacketsCollection;
+
+    use super::{search_payload_internal, PayloadSearch};
+
+    /// Ethernet frame of an IPv4 TCP segment, with options, or UDP datagram followed by Ethernet padding
+    fn build_frame(id: usize, is_tcp: bool, payload: &[u8]) -> Vec<u8> {
+        let mut transport = vec![];
+        if is_tcp {
+            transport.extend_from_slice(&(4000 + id as u16).to_be_bytes());
+            transport.extend_from_slice(&21u16.to_be_bytes());
+            transport.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]);
+            // Data offset of 6 words, the last one of NOP options
+            transport.extend_from_slice(&[0x60, 0x18, 0xff, 0xff, 0, 0, 0, 0, 1, 1, 1, 1]);
+        } else {
+            transport.extend_from_slice(&5353u16.to_be_bytes());
+            transport.extend_from_slice(&5353u16.to_be_bytes());
+            transport.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+            transport.extend_from_slice(&[0x12, 0x34]);
+        }
+        transport.extend_from_slice(payload);
+
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&(20 + transport.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 64, if is_tcp { 6 } else { 17 }, 0, 0]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(&transport);
+        frame.extend_from_slice(b"fish");
+
+        frame
+    }
+
+    #[test]
+    fn payloads_searched() {
+        let mut packets_collection = PacketsCollection::new();
+        let payloads: [(bool, &[u8]); 3] = [
+            (true, b"USER Wirefish"),
+            (false, b"token=wirefish"),
+            (true, b"nothing here"),
+        ];
+
+        for (id, (is_tcp, payload)) in payloads.into_iter().enumerate() {
+            let frame = build_frame(id, is_tcp, payload);
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &frame);
+            packets_collection.insert(Arc::new(parse_ethernet_bytes(&frame, id)));
+        }
+
+        assert_eq!(
+            search_payload_internal(b"wirefish", false, &packets_collection),
+            PayloadSearch {
+                count: 2,
+                packet_ids: vec![0, 1]
+            }
+        );
+        assert_eq!(
+            search_payload_internal(b"Wirefish", true, &packets_collection).packet_ids,
+            vec![0]
+        );
+        // The TCP options and the padding aren't part of the payload
+        assert_eq!(
+            search_payload_internal(&[1, 1, 1, 1, b'U'], false, &packets_collection).count,
+            0
+        );
+        assert_eq!(
+            search_payload_internal(b"herefish", false, &packets_collection).count,
+            0
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! Import of packets from capture files in pcapng format
+//!
+//! A pcapng file is a sequence of blocks, each one starting with its type and total length. The Section Header
+//! Block opens each section and sets its byte order through the `0x1a2b3c4d` magic number, the Interface
+//! Description Blocks list the capture interfaces with their link type and timestamps resolution, and the
+//! Enhanced (or Simple) Packet Blocks carry the packets. Any other block is skipped.
+//!
+//! Enhanced Packet Blocks can carry the `epb_flags` option, recording the direction of the packet and the
+//! errors detected on the link layer by the capture tool
+//!
+//! Name Resolution Blocks record the hostnames of IP addresses known by the capture tool, collected along the
+//! packets. Only IPv4 and IPv6 records are read, each one naming an address with the first of its names
+
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::capture_file::{PcapRecord, MAX_RECORD_LENGTH};
+use crate::filtering::PacketDirections;
+use crate::SniffingError;
+
+/// First bytes of a pcapng file, the type of the Section Header Block
+pub(crate) const PCAPNG_MAGIC_NUMBER: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+#[allow(non_snake_case)]
+mod BlockTypes {
+    pub const INTERFACE_DESCRIPTION: u32 = 0x00000001;
+    pub const SIMPLE_PACKET: u32 = 0x00000003;
+    pub const NAME_RESOLUTION: u32 = 0x00000004;
+    pub const ENHANCED_PACKET: u32 = 0x00000006;
+    pub const SECTION_HEADER: u32 = 0x0a0d0d0a;
+}
+
+const BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+
+const BLOCK_HEADER_LENGTH: usize = 8;
+
+const OPTION_END: u16 = 0;
+const OPTION_IF_TSRESOL: u16 = 9;
+const OPTION_EPB_FLAGS: u16 = 2;
+
+const NRB_RECORD_IPV4: u16 = 1;
+const NRB_RECORD_IPV6: u16 = 2;
+
+/// Errors of the link layer, as the high 16 bits of the `epb_flags` option
+const LINK_LAYER_ERRORS: [(u32, &str); 8] = [
+    (1 << 31, "Symbol error"),
+    (1 << 30, "Preamble error"),
+    (1 << 29, "Start frame delimiter error"),
+    (1 << 28, "Unaligned frame error"),
+    (1 << 27, "Wrong inter-frame gap error"),
+    (1 << 26, "Packet too short error"),
+    (1 << 25, "Packet too long error"),
+    (1 << 24, "CRC error"),
+];
+
+/// Packet read from a pcapng file
+#[derive(Debug)]
+pub struct PcapngRecord {
+    /// Link-layer header type of the interface that captured the packet
+    pub link_type: u32,
+    /// Value of the `epb_flags` option, if present
+    pub flags: Option<u32>,
+    pub record: PcapRecord,
+}
+
+/// Interface described in the current section
+struct Interface {
+    link_type: u32,
+    snapshot_length: u32,
+    /// Timestamps unit, as (base, exponent): 10^-6 seconds unless set by `if_tsresol`
+    resolution: (u32, u32),
+}
+
+/// Sequential reader of the packets stored in a pcapng stream
+pub struct PcapngReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+    /// Hostnames recorded by the Name Resolution Blocks read so far
+    names: Vec<(IpAddr, String)>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Read the first Section Header Block, detecting the byte order
+    pub fn new(reader: R) -> Result<Self, SniffingError> {
+        let mut pcapng_reader = PcapngReader {
+            reader,
+            big_endian: false,
+            interfaces: vec![],
+            names: vec![],
+        };
+
+        match pcapng_reader.read_block()? {
+            Some((BlockTypes::SECTION_HEADER, _)) => Ok(pcapng_reader),
+            _ => Err(SniffingError::InvalidCaptureFile(
+                "Missing pcapng section header".to_owned(),
+            )),
+        }
+    }
+
+    /// Read the next packet, `None` at the end of the stream
+    pub fn next_record(&mut self) -> Result<Option<PcapngRecord>, SniffingError> {
+        while let Some((block_type, body)) = self.read_block()? {
+            match block_type {
+                BlockTypes::INTERFACE_DESCRIPTION => self.read_interface(&body)?,
+                BlockTypes::ENHANCED_PACKET => return self.read_enhanced_packet(&body).map(Some),
+                BlockTypes::SIMPLE_PACKET => return self.read_simple_packet(&body).map(Some),
+                BlockTypes::NAME_RESOLUTION => self.read_name_resolution(&body),
+                // Section headers are handled while reading the block
+                _ => (),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Read a whole block, returning its type and body, `None` at the end of the stream
+    ///
+    /// A Section Header Block starts a new section, with its own byte order and interfaces
+    fn read_block(&mut self) -> Result<Option<(u32, Vec<u8>)>, SniffingError> {
+        let mut header = [0u8; BLOCK_HEADER_LENGTH];
+        let mut read = 0;
+        while read < BLOCK_HEADER_LENGTH {
+            match self.reader.read(&mut header[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(SniffingError::InvalidCaptureFile(format!(
+                        "Truncated pcapng block header: {} of {} bytes",
+                        read, BLOCK_HEADER_LENGTH
+                    )))
+                }
+                Ok(n) => read += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(e) => {
+                    return Err(SniffingError::InvalidCaptureFile(format!(
+                        "Unable to read pcapng block header: {}",
+                        e
+                    )))
+                }
+            }
+        }
+
+        // The section header type is the same in both byte orders, which are told by the following magic number
+        let mut body = vec![];
+        if u32::from_le_bytes(header[0..4].try_into().unwrap()) == BlockTypes::SECTION_HEADER {
+            let mut byte_order_magic = [0u8; 4];
+            self.reader.read_exact(&mut byte_order_magic).map_err(|e| {
+                SniffingError::InvalidCaptureFile(format!("Truncated pcapng section header: {}", e))
+            })?;
+
+            self.big_endian = match u32::from_le_bytes(byte_order_magic) {
+                BYTE_ORDER_MAGIC => false,
+                magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => true,
+                magic => {
+                    return Err(SniffingError::InvalidCaptureFile(format!(
+                        "Unknown pcapng byte order magic: {:#010x}",
+                        magic
+                    )))
+                }
+            };
+            self.interfaces.clear();
+            body.extend_from_slice(&byte_order_magic);
+        }
+
+        let block_type = self.read_u32(&header[0..4]);
+        let total_length = self.read_u32(&header[4..8]);
+        if total_length < 12 || total_length % 4 != 0 || total_length > MAX_RECORD_LENGTH {
+            return Err(SniffingError::InvalidCaptureFile(format!(
+                "Invalid pcapng block length: {}",
+                total_length
+            )));
+        }
+
+        // Body and trailing total length
+        let read = body.len();
+        body.resize(total_length as usize - BLOCK_HEADER_LENGTH, 0);
+        self.reader.read_exact(&mut body[read..]).map_err(|e| {
+            SniffingError::InvalidCaptureFile(format!("Truncated pcapng block: {}", e))
+        })?;
+
+        body.truncate(body.len() - 4);
+        Ok(Some((block_type, body)))
+    }
+
+    fn read_interface(&mut self, body: &[u8]) -> Result<(), SniffingError> {
+        if body.len() < 8 {
+            return Err(SniffingError::InvalidCaptureFile(
+                "Truncated pcapng interface description".to_owned(),
+            ));
+        }
+
+        let mut interface = Interface {
+            link_type: self.read_u16(&body[0..2]) as u32,
+            snapshot_length: self.read_u32(&body[4..8]),
+            resolution: (10, 6),
+        };
+
+        for (code, value) in self.read_options(&body[8..]) {
+            if let (OPTION_IF_TSRESOL, [resolution]) = (code, value) {
+                // Negative power of 2 when the highest bit is set, of 10 otherwise
+                interface.resolution = if resolution & 0x80 != 0 {
+                    (2, (resolution & 0x7f) as u32)
+                } else {
+                    (10, *resolution as u32)
+                };
+            }
+        }
+
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    fn read_enhanced_packet(&self, body: &[u8]) -> Result<PcapngRecord, SniffingError> {
+        if body.len() < 20 {
+            return Err(SniffingError::InvalidCaptureFile(
+                "Truncated pcapng enhanced packet".to_owned(),
+            ));
+        }
+
+        let interface = self.get_interface(self.read_u32(&body[0..4]))?;
+        let timestamp =
+            ((self.read_u32(&body[4..8]) as u64) << 32) | self.read_u32(&body[8..12]) as u64;
+        let captured_length = self.read_u32(&body[12..16]) as usize;
+        let original_length = self.read_u32(&body[16..20]);
+
+        let data = body.get(20..20 + captured_length).ok_or_else(|| {
+            SniffingError::InvalidCaptureFile(format!(
+                "Invalid pcapng packet length: {}",
+                captured_length
+            ))
+        })?;
+
+        let options = body
+            .get(20 + (captured_length + 3) / 4 * 4..)
+            .unwrap_or(&[]);
+        let flags = self
+            .read_options(options)
+            .into_iter()
+            .find(|(code, value)| *code == OPTION_EPB_FLAGS && value.len() == 4)
+            .map(|(_, value)| self.read_u32(value));
+
+        Ok(PcapngRecord {
+            link_type: interface.link_type,
+            flags,
+            record: PcapRecord {
+                timestamp: get_microseconds(timestamp, interface.resolution),
+                original_length,
+                data: data.to_vec(),
+            },
+        })
+    }
+
+    /// Simple Packet Blocks have no timestamp and belong to the first interface
+    fn read_simple_packet(&self, body: &[u8]) -> Result<PcapngRecord, SniffingError> {
+        if body.len() < 4 {
+            return Err(SniffingError::InvalidCaptureFile(
+                "Truncated pcapng simple packet".to_owned(),
+            ));
+        }
+
+        let interface = self.get_interface(0)?;
+        let original_length = self.read_u32(&body[0..4]);
+        let captured_length =
+            (original_length as usize)
+                .min(body.len() - 4)
+                .min(match interface.snapshot_length {
+                    0 => usize::MAX,
+                    snapshot_length => snapshot_length as usize,
+                });
+
+        Ok(PcapngRecord {
+            link_type: interface.link_type,
+            flags: None,
+            record: PcapRecord {
+                timestamp: 0,
+                original_length,
+                data: body[4..4 + captured_length].to_vec(),
+            },
+        })
+    }
+
+    /// Takes the hostnames recorded by the Name Resolution Blocks read so far, in order of appearance
+    pub fn take_names(&mut self) -> Vec<(IpAddr, String)> {
+        std::mem::take(&mut self.names)
+    }
+
+    /// Records share the layout of the options, unknown and malformed ones are skipped
+    fn read_name_resolution(&mut self, body: &[u8]) {
+        for (record_type, value) in self.read_options(body) {
+            let (address, names) = match record_type {
+                NRB_RECORD_IPV4 if value.len() > 4 => {
+                    let octets: [u8; 4] = value[..4].try_into().unwrap();
+                    (IpAddr::V4(Ipv4Addr::from(octets)), &value[4..])
+                }
+                NRB_RECORD_IPV6 if value.len() > 16 => {
+                    let octets: [u8; 16] = value[..16].try_into().unwrap();
+                    (IpAddr::V6(Ipv6Addr::from(octets)), &value[16..])
+                }
+                _ => continue,
+            };
+
+            // Zero-terminated names
+            if let Some(name) = names
+                .split(|byte| *byte == 0)
+                .find(|name| !name.is_empty())
+                .and_then(|name| std::str::from_utf8(name).ok())
+            {
+                self.names.push((address, name.to_owned()));
+            }
+        }
+    }
+
+    fn get_interface(&self, interface_id: u32) -> Result<&Interface, SniffingError> {
+        self.interfaces.get(interface_id as usize).ok_or_else(|| {
+            SniffingError::InvalidCaptureFile(format!("Unknown pcapng interface: {}", interface_id))
+        })
+    }
+
+    /// Split the options of a block in (code, value) pairs, up to the end of options marker
+    fn read_options<'a>(&self, mut options: &'a [u8]) -> Vec<(u16, &'a [u8])> {
+        let mut parsed_options = vec![];
+
+        while options.len() >= 4 {
+            let code = self.read_u16(&options[0..2]);
+            let length = self.read_u16(&options[2..4]) as usize;
+            if code == OPTION_END {
+                break;
+            }
+
+            let value = match options.get(4..4 + length) {
+                Some(value) => value,
+                None => break,
+            };
+            parsed_options.push((code, value));
+
+            // Values are padded to 32 bits
+            options = options.get(4 + (length + 3) / 4 * 4..).unwrap_or(&[]);
+        }
+
+        parsed_options
+    }
+
+    fn read_u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+/// Convert a timestamp in units of base^-exponent seconds to microseconds
+fn get_microseconds(timestamp: u64, (base, exponent): (u32, u32)) -> i64 {
+    let units_per_second = (base as u128).checked_pow(exponent).unwrap_or(u128::MAX);
+    (timestamp as u128 * 1_000_000 / units_per_second.max(1)) as i64
+}
+
+/// Direction recorded by the `epb_flags` option, relative to the capture interface
+pub fn get_flags_direction(flags: u32) -> Option<&'static str> {
+    match flags & 0b11 {
+        0b01 => Some(PacketDirections::IN),
+        0b10 => Some(PacketDirections::OUT),
+        _ => None,
+    }
+}
+
+/// Link layer errors recorded by the `epb_flags` option
+pub fn get_flags_errors(flags: u32) -> Vec<String> {
+    LINK_LAYER_ERRORS
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, error)| error.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::PacketDirections;
+
+    use std::net::IpAddr;
+
+    use super::{get_flags_direction, get_flags_errors, PcapngReader};
+
+    /// Blocks written in little-endian byte order
+    pub fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let total_length = (body.len() + 12) as u32;
+        let mut block = block_type.to_le_bytes().to_vec();
+        block.extend_from_slice(&total_length.to_le_bytes());
+        block.extend_from_slice(body);
+        block.extend_from_slice(&total_length.to_le_bytes());
+        block
+    }
+
+    pub fn enhanced_packet(timestamp: u64, data: &[u8], options: &[u8]) -> Vec<u8> {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(data);
+        body.resize((body.len() + 3) / 4 * 4, 0);
+        body.extend_from_slice(options);
+        block(0x00000006, &body)
+    }
+
+    #[test]
+    fn enhanced_packets_with_flags() {
+        let mut capture = block(
+            0x0a0d0d0a,
+            &[
+                0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+        );
+        // Ethernet interface, nanoseconds resolution
+        capture.extend(block(
+            0x00000001,
+            &[1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0],
+        ));
+
+        // Outbound, with a CRC error
+        let flags = ((1u32 << 24) | 0b10).to_le_bytes();
+        let mut options = vec![2, 0, 4, 0];
+        options.extend_from_slice(&flags);
+        options.extend_from_slice(&[0, 0, 0, 0]);
+        capture.extend(enhanced_packet(1_500_000_000, &[0xaa; 5], &options));
+        capture.extend(enhanced_packet(2_000_000_000, &[0xbb; 8], &[]));
+
+        let mut pcapng_reader = PcapngReader::new(capture.as_slice()).unwrap();
+
+        let first = pcapng_reader.next_record().unwrap().unwrap();
+        assert_eq!(first.link_type, LinkTypes::ETHERNET);
+        assert_eq!(first.record.timestamp, 1_500_000);
+        assert_eq!(first.record.data, vec![0xaa; 5]);
+        let flags = first.flags.unwrap();
+        assert_eq!(get_flags_direction(flags), Some(PacketDirections::OUT));
+        assert_eq!(get_flags_errors(flags), vec!["CRC error"]);
+
+        let second = pcapng_reader.next_record().unwrap().unwrap();
+        assert_eq!(second.record.timestamp, 2_000_000);
+        assert_eq!(second.flags, None);
+
+        assert!(pcapng_reader.next_record().unwrap().is_none());
+        assert!(PcapngReader::new(&capture[28..]).is_err());
+    }
+
+    #[test]
+    fn name_resolution_records() {
+        let mut capture = block(
+            0x0a0d0d0a,
+            &[
+                0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+        );
+        capture.extend(block(0x00000001, &[1, 0, 0, 0, 0, 0, 0, 0]));
+
+        let mut records = vec![];
+        // IPv4, two names
+        records.extend_from_slice(&[1, 0, 19, 0, 10, 0, 0, 1]);
+        records.extend_from_slice(b"router\0gateway\0\0");
+        // Unknown record type
+        records.extend_from_slice(&[9, 0, 2, 0, 0xff, 0xff, 0, 0]);
+        // IPv6
+        records.extend_from_slice(&[2, 0, 27, 0]);
+        records.extend_from_slice(
+            &"2001:db8::1"
+                .parse::<std::net::Ipv6Addr>()
+                .unwrap()
+                .octets(),
+        );
+        records.extend_from_slice(b"server.lan\0\0");
+        // Address without names
+        records.extend_from_slice(&[1, 0, 4, 0, 10, 0, 0, 2]);
+        records.extend_from_slice(&[0, 0, 0, 0]);
+        capture.extend(block(0x00000004, &records));
+        capture.extend(enhanced_packet(1_000_000, &[0xaa; 4], &[]));
+
+        let mut pcapng_reader = PcapngReader::new(capture.as_slice()).unwrap();
+        assert!(pcapng_reader.next_record().unwrap().is_some());
+        assert!(pcapng_reader.next_record().unwrap().is_none());
+
+        assert_eq!(
+            pcapng_reader.take_names(),
+            vec![
+                ("10.0.0.1".parse::<IpAddr>().unwrap(), "router".to_owned()),
+                (
+                    "2001:db8::1".parse::<IpAddr>().unwrap(),
+                    "server.lan".to_owned()
+                ),
+            ]
+        );
+        assert!(pcapng_reader.take_names().is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! Detection of the privileges required to capture packets
+//!
+//! At startup the application either proceeds with the current privileges or, when the
+//! `WIREFISH_ESCALATE` environment variable is set, restarts itself through `sudo` if it
+//! lacks the capture capability. In both cases the UI can query the outcome with
+//! `check_capture_permissions` before the user selects an interface.
+
+use std::env;
+
+use serde::Serialize;
+use sudo::RunningAs;
+
+/// Environment variable enabling the escalation through `sudo` at startup
+const ESCALATE_VARIABLE: &str = "WIREFISH_ESCALATE";
+/// Bit of `CAP_NET_RAW` in the Linux capability sets
+#[cfg(target_os = "linux")]
+const CAP_NET_RAW: u64 = 13;
+
+/// Capture privileges of the running process
+#[derive(Serialize, Debug, Clone)]
+pub struct CapturePermissions {
+    pub can_capture: bool,
+    pub running_as_root: bool,
+    /// Explanation shown when `can_capture` is not set
+    pub reason: Option<String>,
+}
+
+/// Returns whether the application is allowed to open capture channels
+#[tauri::command]
+pub fn check_capture_permissions() -> CapturePermissions {
+    get_capture_permissions()
+}
+
+pub fn get_capture_permissions() -> CapturePermissions {
+    let running_as_root = !cfg!(target_os = "windows") && sudo::check() == RunningAs::Root;
+    let can_capture = running_as_root || has_capture_capability();
+
+    CapturePermissions {
+        can_capture,
+        running_as_root,
+        reason: if can_capture {
+            None
+        } else {
+            Some(get_missing_privileges_reason().to_owned())
+        },
+    }
+}
+
+/// Restarts the application through `sudo` when it cannot capture and `WIREFISH_ESCALATE` is set
+///
+/// On success the current process is replaced and this function does not return. Since it runs before the
+/// logger is set up, the outcome is printed to the standard error
+pub fn escalate_if_configured() {
+    if cfg!(target_os = "windows") || !is_escalation_enabled(env::var(ESCALATE_VARIABLE).ok()) {
+        return;
+    }
+
+    if get_capture_permissions().can_capture {
+        eprintln!("Capture privileges available, no escalation needed");
+        return;
+    }
+
+    eprintln!("Insufficient privileges to capture, escalating through sudo");
+    // Keep the configuration variables across the restart
+    if let Err(e) = sudo::with_env(&["WIREFISH_", "RUST_LOG"]) {
+        eprintln!("Privilege escalation failed: {}", e);
+    }
+}
+
+fn is_escalation_enabled(value: Option<String>) -> bool {
+    matches!(
+        value.as_deref().map(str::to_lowercase).as_deref(),
+        Some("1") | Some("true") | Some("yes")
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn has_capture_capability() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .map(|status| has_net_raw_capability(&status))
+        .unwrap_or(false)
+}
+
+/// The capture devices are usually accessible only by root, unless their permissions were changed
+#[cfg(target_os = "macos")]
+fn has_capture_capability() -> bool {
+    (0..256).any(|n| {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .open(format!("/dev/bpf{}", n))
+            .is_ok()
+    })
+}
+
+/// Npcap does not require administrator privileges, unless installed in restricted mode
+#[cfg(target_os = "windows")]
+fn has_capture_capability() -> bool {
+    true
+}
+
+/// Unknown requirements, left to the opening of the capture channels
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn has_capture_capability() -> bool {
+    true
+}
+
+/// Whether the effective capability set reported in `/proc/<pid>/status` contains `CAP_NET_RAW`
+#[cfg(target_os = "linux")]
+fn has_net_raw_capability(status: &str) -> bool {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|capabilities| u64::from_str_radix(capabilities.trim(), 16).ok())
+        .map_or(false, |capabilities| capabilities & (1 << CAP_NET_RAW) != 0)
+}
+
+fn get_missing_privileges_reason() -> &'static str {
+    if cfg!(target_os = "linux") {
+        "Insufficient privileges to capture: run as root or grant CAP_NET_RAW and CAP_NET_ADMIN to the executable"
+    } else if cfg!(target_os = "macos") {
+        "Insufficient privileges to capture: run as root or make the /dev/bpf* devices readable"
+    } else {
+        "Insufficient privileges to capture"
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::is_escalation_enabled;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn net_raw_capability() {
+        use super::has_net_raw_capability;
+
+        let status = |effective: &str| {
+            format!(
+                "Name:\twirefish\nCapInh:\t0000000000000000\nCapPrm:\t{0}\nCapEff:\t{0}\n",
+                effective
+            )
+        };
+
+        assert!(has_net_raw_capability(&status("000001ffffffffff")));
+        assert!(has_net_raw_capability(&status("0000000000003000")));
+        assert!(!has_net_raw_capability(&status("0000000000001000")));
+        assert!(!has_net_raw_capability("Name:\twirefish\n"));
+    }
+
+    #[test]
+    fn escalation_variable() {
+        assert!(is_escalation_enabled(Some("1".to_owned())));
+        assert!(is_escalation_enabled(Some("True".to_owned())));
+        assert!(!is_escalation_enabled(Some("0".to_owned())));
+        assert!(!is_escalation_enabled(None));
+    }
+}
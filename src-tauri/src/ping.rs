@@ -0,0 +1,268 @@
+//! Round-trip times and losses of pings (ICMP and ICMPv6 echoes)
+//!
+//! Requests and replies are paired by requester, target, identifier and sequence number, so replies
+//! arriving out of order are still matched to their request:
+//! - the first reply of a request produces an RTT sample, the following ones are counted as duplicates
+//! - replies without a previous request (e.g. captured after the request was sent) are only counted
+//! - requests without any reply are lost
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{get_dest_ip, get_source_ip};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::filtering::apply_all_strong_filters;
+use crate::latency::{get_rtt_statistics, RttStatistics};
+use crate::{SniffingError, SniffingState};
+
+/// Echoes exchanged by a requester with a target
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PingStatistics {
+    pub source: String,
+    pub target: String,
+    pub requests: usize,
+    pub replies: usize,
+    pub duplicate_replies: usize,
+    pub unmatched_replies: usize,
+    pub lost: usize,
+    /// Percentage of the requests without reply
+    pub loss: f64,
+    /// Round-trip times, in microseconds
+    pub rtt: Option<RttStatistics>,
+}
+
+/// Returns the ping statistics of each requester and target among the packets matching the filters
+#[tauri::command]
+pub fn get_ping_stats<'a>(
+    state: tauri::State<SniffingState>,
+    filters_value: Vec<(&'a str, &'a str)>,
+) -> Result<Vec<PingStatistics>, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+
+    let packets = if filters_value.is_empty() {
+        let mut packets = packets_collection.icmp_packets.clone();
+        packets.extend(packets_collection.icmpv6_packets.iter().cloned());
+        packets.sort_by_key(|packet| packet.get_id());
+        packets
+    } else {
+        let end = packets_collection.packets.len();
+        apply_all_strong_filters(end, &filters_value, &mut packets_collection)?
+    };
+
+    Ok(get_ping_stats_internal(&packets))
+}
+
+enum Echo {
+    Request(u16, u16),
+    Reply(u16, u16),
+}
+
+struct PendingRequest {
+    timestamp: i64,
+    replied: bool,
+}
+
+struct PingState {
+    statistics: PingStatistics,
+    rtts: Vec<i64>,
+    /// Requests by identifier and sequence number
+    requests: HashMap<(u16, u16), PendingRequest>,
+}
+
+fn get_echo(packet: &ParsedPacket) -> Option<Echo> {
+    match packet.get_transport_layer_packet()? {
+        SerializablePacket::EchoRequestPacket(request) => {
+            Some(Echo::Request(request.identifier, request.sequence_number))
+        }
+        SerializablePacket::EchoReplyPacket(reply) => {
+            Some(Echo::Reply(reply.identifier, reply.sequence_number))
+        }
+        SerializablePacket::Icmpv6Packet(icmpv6_packet) => {
+            let echo = icmpv6_packet
+                .identifier
+                .zip(icmpv6_packet.sequence_number)?;
+            if icmpv6_packet.icmpv6_type.starts_with("EchoRequest") {
+                Some(Echo::Request(echo.0, echo.1))
+            } else if icmpv6_packet.icmpv6_type.starts_with("EchoReply") {
+                Some(Echo::Reply(echo.0, echo.1))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn get_ping_stats_internal(packets: &[Arc<ParsedPacket>]) -> Vec<PingStatistics> {
+    let mut pings: BTreeMap<(String, String), PingState> = BTreeMap::new();
+
+    for packet in packets {
+        let (echo, source, destination) =
+            match (get_echo(packet), get_source_ip(packet), get_dest_ip(packet)) {
+                (Some(echo), Some(source), Some(destination)) => (echo, source, destination),
+                _ => continue,
+            };
+
+        // Replies are accounted to the requester
+        let key = match echo {
+            Echo::Request(..) => (source, destination),
+            Echo::Reply(..) => (destination, source),
+        };
+        let ping = pings.entry(key.clone()).or_insert_with(|| PingState {
+            statistics: PingStatistics {
+                source: key.0,
+                target: key.1,
+                requests: 0,
+                replies: 0,
+                duplicate_replies: 0,
+                unmatched_replies: 0,
+                lost: 0,
+                loss: 0.0,
+                rtt: None,
+            },
+            rtts: vec![],
+            requests: HashMap::new(),
+        });
+
+        match echo {
+            Echo::Request(identifier, sequence_number) => {
+                ping.statistics.requests += 1;
+                // A reused sequence number (e.g. after wrapping around) starts a new request
+                if let Some(previous) = ping.requests.insert(
+                    (identifier, sequence_number),
+                    PendingRequest {
+                        timestamp: packet.get_timestamp(),
+                        replied: false,
+                    },
+                ) {
+                    if !previous.replied {
+                        ping.statistics.lost += 1;
+                    }
+                }
+            }
+            Echo::Reply(identifier, sequence_number) => {
+                ping.statistics.replies += 1;
+                match ping.requests.get_mut(&(identifier, sequence_number)) {
+                    Some(request) if request.replied => ping.statistics.duplicate_replies += 1,
+                    Some(request) => {
+                        request.replied = true;
+                        ping.rtts.push(packet.get_timestamp() - request.timestamp);
+                    }
+                    None => ping.statistics.unmatched_replies += 1,
+                }
+            }
+        }
+    }
+
+    pings
+        .into_values()
+        .map(|ping| {
+            let mut statistics = ping.statistics;
+            statistics.lost += ping
+                .requests
+                .values()
+                .filter(|request| !request.replied)
+                .count();
+            if statistics.requests > 0 {
+                statistics.loss = statistics.lost as f64 * 100.0 / statistics.requests as f64;
+            }
+            statistics.rtt = get_rtt_statistics(&ping.rtts);
+            statistics
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::transport::{
+        SerializableEchoReplyPacket, SerializableEchoRequestPacket,
+    };
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::get_ping_stats_internal;
+
+    fn build_echo(
+        id: usize,
+        timestamp: i64,
+        request: bool,
+        sequence_number: u16,
+    ) -> Arc<ParsedPacket> {
+        let (client, target) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(1, 1, 1, 1));
+        let template = if request {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), client, target, 0, 0)
+        } else {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), target, client, 0, 0)
+        };
+
+        let echo = if request {
+            SerializablePacket::EchoRequestPacket(SerializableEchoRequestPacket {
+                icmp_type: 8,
+                icmp_code: 0,
+                checksum: 0,
+                checksum_valid: None,
+                identifier: 42,
+                sequence_number,
+                length: 56,
+            })
+        } else {
+            SerializablePacket::EchoReplyPacket(SerializableEchoReplyPacket {
+                icmp_type: 0,
+                icmp_code: 0,
+                checksum: 0,
+                checksum_valid: None,
+                identifier: 42,
+                sequence_number,
+                length: 56,
+            })
+        };
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_timestamp(timestamp);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(echo));
+
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn ping_statistics() {
+        let packets = vec![
+            build_echo(0, 0, true, 1),
+            build_echo(1, 1000, true, 2),
+            // Out of order replies
+            build_echo(2, 4000, false, 2),
+            build_echo(3, 5000, false, 1),
+            // Duplicate
+            build_echo(4, 5500, false, 1),
+            // Lost
+            build_echo(5, 6000, true, 3),
+            // Request not captured
+            build_echo(6, 7000, false, 9),
+        ];
+
+        let statistics = get_ping_stats_internal(&packets);
+        assert_eq!(statistics.len(), 1);
+
+        let ping = &statistics[0];
+        assert_eq!(ping.source, "10.0.0.1");
+        assert_eq!(ping.target, "1.1.1.1");
+        assert_eq!(
+            (ping.requests, ping.replies, ping.duplicate_replies),
+            (3, 4, 1)
+        );
+        assert_eq!((ping.unmatched_replies, ping.lost), (1, 1));
+        assert!((ping.loss - 100.0 / 3.0).abs() < 1e-9);
+
+        let rtt = ping.rtt.as_ref().unwrap();
+        assert_eq!((rtt.samples, rtt.min, rtt.max), (2, 3000, 5000));
+        assert_eq!(rtt.avg, 4000.0);
+    }
+}
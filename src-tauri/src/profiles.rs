@@ -0,0 +1,295 @@
+//! Named capture profiles, to switch between capture setups
+//!
+//! A profile collects the settings applied by the configuration commands: selected interface, enabled
+//! dissectors, heartbeat interval, backpressure policy, index limits and audit mode. Profiles are stored as
+//! `<name>.json` files in the `profiles` directory of the application data directory.
+//!
+//! Loading a profile only applies its settings: a running sniffing process keeps going, and uses the ones
+//! read at start (interface, backpressure) only from the next start
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{info, warn};
+use pnet::datalink;
+use serde::{Deserialize, Serialize};
+
+use crate::backpressure::get_backpressure_config;
+use crate::interfaces::{find_interface, get_interface_display_name};
+use crate::{get_dissectors, SniffingError, SniffingState};
+
+const PROFILES_DIRECTORY: &str = "profiles";
+const PROFILE_EXTENSION: &str = ".json";
+
+/// Settings of a capture setup
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CaptureProfile {
+    /// Name of the selected interface, if any
+    pub interface: Option<String>,
+    pub dissectors: Vec<String>,
+    pub heartbeat_interval_ms: u64,
+    pub backpressure_policy: String,
+    pub queue_capacity: usize,
+    /// Limit of the number of keys of the filtering indexes, by index name
+    pub index_limits: BTreeMap<String, Option<usize>>,
+    pub audit_mode: bool,
+}
+
+/// Saves the current settings as the profile `name`, replacing it if it already exists
+#[tauri::command]
+pub fn save_profile(
+    state: tauri::State<SniffingState>,
+    app_handle: tauri::AppHandle,
+    name: String,
+) -> Result<CaptureProfile, SniffingError> {
+    let profile = get_current_profile(&state);
+    write_profile(&get_profiles_directory(&app_handle)?, &name, &profile)?;
+
+    info!("Saved profile {}", name);
+
+    Ok(profile)
+}
+
+/// Applies the settings of the profile `name`, without starting the sniffing process
+#[tauri::command]
+pub fn load_profile(
+    state: tauri::State<SniffingState>,
+    app_handle: tauri::AppHandle,
+    name: String,
+) -> Result<CaptureProfile, SniffingError> {
+    let profile = read_profile(&get_profiles_directory(&app_handle)?, &name)?;
+    apply_profile(&state, &profile)?;
+
+    info!("Loaded profile {}", name);
+
+    Ok(profile)
+}
+
+/// Returns the names of the saved profiles, sorted
+#[tauri::command]
+pub fn list_profiles(app_handle: tauri::AppHandle) -> Result<Vec<String>, SniffingError> {
+    list_profiles_internal(&get_profiles_directory(&app_handle)?)
+}
+
+fn get_profiles_directory(app_handle: &tauri::AppHandle) -> Result<PathBuf, SniffingError> {
+    app_handle
+        .path_resolver()
+        .app_dir()
+        .map(|directory| directory.join(PROFILES_DIRECTORY))
+        .ok_or_else(|| {
+            SniffingError::ProfileAccessFailed(
+                "Unable to locate the application directory".to_owned(),
+            )
+        })
+}
+
+fn get_current_profile(state: &SniffingState) -> CaptureProfile {
+    let interface = state
+        .info
+        .lock()
+        .unwrap()
+        .interface
+        .as_ref()
+        .map(|interface| interface.name.clone());
+
+    let mut dissectors = state
+        .dissectors
+        .lock()
+        .unwrap()
+        .iter()
+        .cloned()
+        .collect::<Vec<String>>();
+    dissectors.sort();
+
+    let backpressure = state.backpressure.lock().unwrap().clone();
+
+    CaptureProfile {
+        interface,
+        dissectors,
+        heartbeat_interval_ms: state.heartbeat_interval.lock().unwrap().as_millis() as u64,
+        backpressure_policy: backpressure.policy.to_owned(),
+        queue_capacity: backpressure.queue_capacity,
+        index_limits: state.packets.lock().unwrap().get_index_limits(),
+        audit_mode: *state.audit_mode.lock().unwrap(),
+    }
+}
+
+/// Validates the whole profile before applying any of its settings
+fn apply_profile(state: &SniffingState, profile: &CaptureProfile) -> Result<(), SniffingError> {
+    let interface = profile
+        .interface
+        .as_ref()
+        .map(|interface| find_interface(datalink::interfaces(), interface))
+        .transpose()?;
+    let dissectors = get_dissectors(profile.dissectors.clone())?;
+    let backpressure =
+        get_backpressure_config(&profile.backpressure_policy, Some(profile.queue_capacity))?;
+
+    let mut packets_collection = state.packets.lock().unwrap();
+    if let Some(unknown) = profile
+        .index_limits
+        .keys()
+        .find(|index| !packets_collection.get_index_limits().contains_key(*index))
+    {
+        return Err(SniffingError::UnknownFilterType(format!(
+            "Unknown index: {}",
+            unknown
+        )));
+    }
+
+    for (index, limit) in &profile.index_limits {
+        packets_collection.set_index_limit(index, *limit)?;
+    }
+    drop(packets_collection);
+
+    if let Some(interface) = interface {
+        let mut sniffing_info = state.info.lock().unwrap();
+        sniffing_info.interface_name = Some(get_interface_display_name(&interface));
+        sniffing_info.interface = Some(interface);
+        sniffing_info.additional_interfaces.clear();
+    }
+
+    *state.dissectors.lock().unwrap() = dissectors;
+    *state.heartbeat_interval.lock().unwrap() =
+        Duration::from_millis(profile.heartbeat_interval_ms.max(1));
+    *state.backpressure.lock().unwrap() = backpressure;
+    *state.audit_mode.lock().unwrap() = profile.audit_mode;
+
+    Ok(())
+}
+
+/// Path of the profile file, rejecting names that would escape the profiles directory
+fn get_profile_path(directory: &Path, name: &str) -> Result<PathBuf, SniffingError> {
+    let is_valid = !name.trim().is_empty()
+        && !name.starts_with('.')
+        && !name.contains(|c: char| c == '/' || c == '\\' || c.is_control());
+
+    if !is_valid {
+        warn!("Invalid profile name: {}", name);
+        return Err(SniffingError::InvalidConfiguration(format!(
+            "Invalid profile name: {}",
+            name
+        )));
+    }
+
+    Ok(directory.join(format!("{}{}", name, PROFILE_EXTENSION)))
+}
+
+fn write_profile(
+    directory: &Path,
+    name: &str,
+    profile: &CaptureProfile,
+) -> Result<(), SniffingError> {
+    let path = get_profile_path(directory, name)?;
+    let access_failed = |e: &dyn std::fmt::Display| {
+        SniffingError::ProfileAccessFailed(format!("Unable to write {}: {}", path.display(), e))
+    };
+
+    let content = serde_json::to_string_pretty(profile).map_err(|e| access_failed(&e))?;
+    fs::create_dir_all(directory).map_err(|e| access_failed(&e))?;
+    fs::write(&path, content).map_err(|e| access_failed(&e))
+}
+
+fn read_profile(directory: &Path, name: &str) -> Result<CaptureProfile, SniffingError> {
+    let path = get_profile_path(directory, name)?;
+
+    let content = fs::read_to_string(&path).map_err(|e| {
+        SniffingError::ProfileAccessFailed(format!("Unable to read {}: {}", path.display(), e))
+    })?;
+
+    serde_json::from_str(&content).map_err(|e| {
+        SniffingError::ProfileAccessFailed(format!("Invalid profile {}: {}", path.display(), e))
+    })
+}
+
+fn list_profiles_internal(directory: &Path) -> Result<Vec<String>, SniffingError> {
+    // No profile saved yet
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let entries = fs::read_dir(directory).map_err(|e| {
+        SniffingError::ProfileAccessFailed(format!("Unable to read {}: {}", directory.display(), e))
+    })?;
+
+    let mut names = entries
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            file_name.strip_suffix(PROFILE_EXTENSION).map(str::to_owned)
+        })
+        .collect::<Vec<String>>();
+    names.sort();
+
+    Ok(names)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use crate::backpressure::BackpressurePolicies;
+    use crate::filtering::FilterNamesValues;
+    use crate::SniffingState;
+
+    use super::{
+        apply_profile, get_current_profile, list_profiles_internal, read_profile, write_profile,
+        CaptureProfile,
+    };
+
+    #[test]
+    fn saved_profile_is_loaded_back() {
+        let directory =
+            std::env::temp_dir().join(format!("wirefish-profiles-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        let profile = CaptureProfile {
+            interface: None,
+            dissectors: vec!["dns".to_owned(), "http".to_owned()],
+            heartbeat_interval_ms: 500,
+            backpressure_policy: BackpressurePolicies::BLOCK.to_owned(),
+            queue_capacity: 128,
+            index_limits: BTreeMap::from([(FilterNamesValues::SRC_IP.to_owned(), Some(1000))]),
+            audit_mode: true,
+        };
+
+        assert_eq!(
+            list_profiles_internal(&directory).unwrap(),
+            Vec::<String>::new()
+        );
+        write_profile(&directory, "office", &profile).unwrap();
+        write_profile(&directory, "home", &profile).unwrap();
+        assert_eq!(
+            list_profiles_internal(&directory).unwrap(),
+            vec!["home", "office"]
+        );
+        assert!(write_profile(&directory, "../escape", &profile).is_err());
+        assert!(read_profile(&directory, "missing").is_err());
+
+        let state = SniffingState::new();
+        apply_profile(&state, &read_profile(&directory, "office").unwrap()).unwrap();
+
+        let current = get_current_profile(&state);
+        assert_eq!(current.dissectors, profile.dissectors);
+        assert_eq!(current.heartbeat_interval_ms, 500);
+        assert_eq!(current.backpressure_policy, BackpressurePolicies::BLOCK);
+        assert_eq!(current.queue_capacity, 128);
+        assert_eq!(current.index_limits[FilterNamesValues::SRC_IP], Some(1000));
+        assert!(current.audit_mode);
+
+        let invalid = CaptureProfile {
+            dissectors: vec!["gopher".to_owned()],
+            audit_mode: false,
+            ..profile
+        };
+        assert!(apply_profile(&state, &invalid).is_err());
+        // Nothing applied
+        assert!(get_current_profile(&state).audit_mode);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
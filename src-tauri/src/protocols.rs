@@ -0,0 +1,433 @@
+//! Protocols dissected by Wirefish, with the coverage of their parsing and the fields they expose
+//!
+//! Coverage levels:
+//! - detected-only: the protocol is recognized and labeled, its content isn't decoded
+//! - partial: a subset of the fields (or of the messages) is decoded
+//! - full: all the header fields are decoded
+//!
+//! Fields are named as in the serialized packets. The table must follow the serializable packets and
+//! the layer type filters of `sniffer_parser` when a protocol is added
+
+use serde::Serialize;
+use sniffer_parser::Dissectors;
+
+use crate::filtering::FilterNamesValues;
+
+#[allow(non_snake_case)]
+pub mod CoverageLevels {
+    pub const DETECTED_ONLY: &str = "detected-only";
+    pub const PARTIAL: &str = "partial";
+    pub const FULL: &str = "full";
+}
+
+#[allow(non_snake_case)]
+pub mod ProtocolLayers {
+    pub const LINK: &str = "link";
+    pub const NETWORK: &str = "network";
+    pub const TRANSPORT: &str = "transport";
+    pub const APPLICATION: &str = "application";
+}
+
+/// Protocol parsed by Wirefish
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SupportedProtocol {
+    pub name: &'static str,
+    pub layer: &'static str,
+    pub coverage: &'static str,
+    /// Layer type filter of its packets, if any
+    pub filter: Option<&'static str>,
+    /// Dissector enabling its parsing, if it can be disabled
+    pub dissector: Option<&'static str>,
+    pub fields: &'static [&'static str],
+}
+
+const SUPPORTED_PROTOCOLS: [SupportedProtocol; 22] = [
+    SupportedProtocol {
+        name: "Ethernet",
+        layer: ProtocolLayers::LINK,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::ETHERNET),
+        dissector: None,
+        fields: &["destination", "source", "ethertype", "payload"],
+    },
+    SupportedProtocol {
+        name: "IEEE 802.11",
+        layer: ProtocolLayers::LINK,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::DOT11),
+        dissector: None,
+        fields: &[
+            "radiotap",
+            "frame_type",
+            "subtype",
+            "type_name",
+            "to_ds",
+            "from_ds",
+            "protected",
+            "duration",
+            "addresses",
+            "destination",
+            "source",
+            "bssid",
+            "sequence_number",
+            "ssid",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "Loopback",
+        layer: ProtocolLayers::LINK,
+        coverage: CoverageLevels::FULL,
+        filter: None,
+        dissector: None,
+        fields: &["family", "protocol", "payload"],
+    },
+    SupportedProtocol {
+        name: "Registered EtherType",
+        layer: ProtocolLayers::NETWORK,
+        coverage: CoverageLevels::DETECTED_ONLY,
+        filter: None,
+        dissector: None,
+        fields: &["protocol", "ethertype", "payload", "length"],
+    },
+    SupportedProtocol {
+        name: "Unknown",
+        layer: ProtocolLayers::NETWORK,
+        coverage: CoverageLevels::DETECTED_ONLY,
+        filter: Some(FilterNamesValues::UNKNOWN),
+        dissector: None,
+        fields: &["destination", "source", "ethertype", "length"],
+    },
+    SupportedProtocol {
+        name: "ARP",
+        layer: ProtocolLayers::NETWORK,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::ARP),
+        dissector: None,
+        fields: &[
+            "hardware_type",
+            "protocol_type",
+            "hw_addr_len",
+            "proto_addr_len",
+            "operation",
+            "sender_hw_addr",
+            "sender_proto_addr",
+            "target_hw_addr",
+            "target_proto_addr",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "IPv4",
+        layer: ProtocolLayers::NETWORK,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::IPV4),
+        dissector: None,
+        fields: &[
+            "version",
+            "header_length",
+            "dscp",
+            "ecn",
+            "total_length",
+            "identification",
+            "flags",
+            "fragment_offset",
+            "ttl",
+            "next_level_protocol",
+            "checksum",
+            "checksum_valid",
+            "source",
+            "destination",
+            "parsed_options",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "IPv6",
+        layer: ProtocolLayers::NETWORK,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::IPV6),
+        dissector: None,
+        fields: &[
+            "version",
+            "traffic_class",
+            "flow_label",
+            "payload_length",
+            "next_header",
+            "hop_limit",
+            "source",
+            "destination",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "IPv6 tunnel",
+        layer: ProtocolLayers::NETWORK,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::TUNNEL),
+        dissector: None,
+        fields: &["tunnel_type", "inner"],
+    },
+    SupportedProtocol {
+        name: "ICMP",
+        layer: ProtocolLayers::TRANSPORT,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::ICMP),
+        dissector: None,
+        fields: &[
+            "icmp_type",
+            "icmp_code",
+            "checksum",
+            "checksum_valid",
+            "identifier",
+            "sequence_number",
+            "original",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "ICMPv6",
+        layer: ProtocolLayers::TRANSPORT,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::ICMPV6),
+        dissector: None,
+        fields: &[
+            "icmpv6_type",
+            "icmpv6_code",
+            "checksum",
+            "identifier",
+            "sequence_number",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "TCP",
+        layer: ProtocolLayers::TRANSPORT,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::TCP),
+        dissector: None,
+        fields: &[
+            "source",
+            "destination",
+            "sequence",
+            "acknowledgement",
+            "data_offset",
+            "reserved",
+            "flags",
+            "window",
+            "checksum",
+            "checksum_valid",
+            "urgent_ptr",
+            "options",
+            "parsed_options",
+            "length",
+        ],
+    },
+    SupportedProtocol {
+        name: "UDP",
+        layer: ProtocolLayers::TRANSPORT,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::UDP),
+        dissector: None,
+        fields: &[
+            "source",
+            "destination",
+            "length",
+            "checksum",
+            "checksum_valid",
+        ],
+    },
+    SupportedProtocol {
+        name: "HTTP",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::HTTP),
+        dissector: Some(Dissectors::HTTP),
+        fields: &[
+            "method", "path", "version", "code", "reason", "headers", "payload",
+        ],
+    },
+    SupportedProtocol {
+        name: "HTTP/2",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::HTTP2),
+        dissector: Some(Dissectors::HTTP),
+        fields: &["preface", "frames"],
+    },
+    SupportedProtocol {
+        name: "TLS",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::TLS),
+        dissector: Some(Dissectors::TLS),
+        fields: &["version", "messages", "length"],
+    },
+    SupportedProtocol {
+        name: "DNS",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::FULL,
+        filter: Some(FilterNamesValues::DNS),
+        dissector: Some(Dissectors::DNS),
+        fields: &[
+            "header",
+            "questions",
+            "answers",
+            "nameservers",
+            "additional",
+        ],
+    },
+    SupportedProtocol {
+        name: "SIP",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::SIP),
+        dissector: Some(Dissectors::SIP),
+        fields: &[
+            "method",
+            "request_uri",
+            "status_code",
+            "reason",
+            "from",
+            "to",
+            "call_id",
+            "cseq",
+            "via",
+            "headers",
+            "sdp",
+        ],
+    },
+    SupportedProtocol {
+        name: "DHCPv6",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::DHCPV6),
+        dissector: Some(Dissectors::DHCPV6),
+        fields: &[
+            "message_type",
+            "message_type_name",
+            "transaction_id",
+            "hop_count",
+            "link_address",
+            "peer_address",
+            "client_duid",
+            "server_duid",
+            "ia_na",
+            "status_code",
+            "dns_servers",
+            "domain_search_list",
+            "relayed_message",
+            "options",
+        ],
+    },
+    SupportedProtocol {
+        name: "Kerberos",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::KERBEROS),
+        dissector: Some(Dissectors::KERBEROS),
+        fields: &[
+            "message_type",
+            "message_type_name",
+            "realm",
+            "client_name",
+            "server_name",
+            "encryption_types",
+            "padata_types",
+            "error_code",
+            "error_name",
+            "error_text",
+        ],
+    },
+    SupportedProtocol {
+        name: "RADIUS",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::PARTIAL,
+        filter: Some(FilterNamesValues::RADIUS),
+        dissector: Some(Dissectors::RADIUS),
+        fields: &[
+            "code",
+            "code_name",
+            "identifier",
+            "length",
+            "authenticator",
+            "user_name",
+            "nas_ip_address",
+            "nas_identifier",
+            "framed_ip_address",
+            "called_station_id",
+            "calling_station_id",
+            "acct_status_type",
+            "acct_session_id",
+            "reply_messages",
+            "attributes",
+        ],
+    },
+    SupportedProtocol {
+        name: "BitTorrent",
+        layer: ProtocolLayers::APPLICATION,
+        coverage: CoverageLevels::DETECTED_ONLY,
+        filter: Some(FilterNamesValues::BITTORRENT),
+        dissector: Some(Dissectors::BITTORRENT),
+        fields: &[
+            "message_type",
+            "info_hash",
+            "peer_id",
+            "transaction_id",
+            "query",
+            "error_code",
+        ],
+    },
+];
+
+/// Returns the protocols Wirefish can dissect, from the link layer to the application one
+#[tauri::command]
+pub fn get_supported_protocols() -> Vec<SupportedProtocol> {
+    SUPPORTED_PROTOCOLS.to_vec()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::Dissectors;
+
+    use crate::filtering::apply_layer_type_filter;
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{CoverageLevels, SUPPORTED_PROTOCOLS};
+
+    #[test]
+    fn protocols_follow_filters_and_dissectors() {
+        let packet = Arc::new(build_test_parsed_packet(
+            MacAddr::zero(),
+            MacAddr::zero(),
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+            4444,
+            80,
+        ));
+
+        for protocol in SUPPORTED_PROTOCOLS.iter() {
+            if let Some(filter) = protocol.filter {
+                assert!(apply_layer_type_filter(filter, &packet).is_ok());
+            }
+            assert!([
+                CoverageLevels::DETECTED_ONLY,
+                CoverageLevels::PARTIAL,
+                CoverageLevels::FULL
+            ]
+            .contains(&protocol.coverage));
+            assert!(!protocol.fields.is_empty());
+        }
+
+        // Each dissector has its protocol
+        for dissector in Dissectors::ALL {
+            assert!(SUPPORTED_PROTOCOLS
+                .iter()
+                .any(|protocol| protocol.dissector == Some(dissector)));
+        }
+    }
+}
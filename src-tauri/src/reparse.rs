@@ -0,0 +1,218 @@
+//! Classification of the collected packets again, parsing their raw bytes with other dissectors
+//!
+//! Packets captured while a dissector was disabled are left unparsed above the transport layer: enabling
+//! the dissector only applies to the following packets, unless the collected ones are parsed again. The
+//! timestamp, direction and link layer errors of each packet are kept, everything derived from the
+//! parsing (protocol lists, indexes, connections, expert info) is rebuilt.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::util::{
+    contains_bittorrent, contains_dhcpv6, contains_dns, contains_http, contains_http2,
+    contains_kerberos, contains_radius, contains_sip, contains_tls,
+};
+use sniffer_parser::serializable_packet::ParsedPacket;
+use sniffer_parser::{
+    cleanup_sniffing_state, set_enabled_dissectors, set_registered_ethertypes, Dissectors,
+};
+
+use crate::capture_file::parse_frame;
+use crate::filtering::PacketsCollection;
+use crate::{get_dissectors, SniffingError, SniffingState};
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReparseSummary {
+    /// Number of packets parsed again
+    reparsed: usize,
+    /// For each requested protocol, number of packets classified as such only after parsing them again
+    reclassified: BTreeMap<String, usize>,
+}
+
+/// Parses again the collected packets with the `protocols` dissectors, along with the enabled ones
+///
+/// The dissectors are only used for this parsing, the ones of the sniffing processes don't change
+#[tauri::command]
+pub fn reparse(
+    state: tauri::State<SniffingState>,
+    protocols: Vec<String>,
+) -> Result<ReparseSummary, SniffingError> {
+    let protocols = get_dissectors(protocols)?;
+    let mut dissectors = state.dissectors.lock().unwrap().clone();
+    dissectors.extend(protocols.iter().cloned());
+    let ethertypes = state.ethertypes.lock().unwrap().clone();
+
+    let mut packets_collection = state.packets.lock().unwrap();
+    let summary = reparse_internal(
+        &mut packets_collection,
+        &protocols,
+        &dissectors,
+        &ethertypes,
+    );
+
+    info!(
+        "Parsed again {} packets, reclassified: {:?}",
+        summary.reparsed, summary.reclassified
+    );
+    Ok(summary)
+}
+
+fn reparse_internal(
+    packets_collection: &mut PacketsCollection,
+    protocols: &HashSet<String>,
+    dissectors: &HashSet<String>,
+    ethertypes: &HashMap<u16, String>,
+) -> ReparseSummary {
+    let mut reclassified = protocols
+        .iter()
+        .map(|protocol| (protocol.clone(), 0))
+        .collect::<BTreeMap<String, usize>>();
+
+    // Streams are reassembled from the first packet
+    cleanup_sniffing_state();
+    set_enabled_dissectors(dissectors);
+    set_registered_ethertypes(ethertypes);
+    packets_collection.expert.clear();
+
+    let mut reparsed = 0;
+    let mut packets = Vec::with_capacity(packets_collection.packets.len());
+    for old_packet in std::mem::take(&mut packets_collection.packets) {
+        let id = old_packet.get_id();
+        let new_packet = packets_collection
+            .raw_packets
+            .get(&id)
+            .and_then(|raw_packet| parse_frame(raw_packet.link_type, &raw_packet.data, id));
+
+        let mut new_packet = match new_packet {
+            Some(new_packet) => new_packet,
+            None => {
+                warn!("Kept packet {} without its raw bytes", id);
+                packets.push(old_packet);
+                continue;
+            }
+        };
+        new_packet.set_timestamp(old_packet.get_timestamp());
+        new_packet.set_direction(old_packet.get_direction().cloned());
+        new_packet.set_link_errors(old_packet.get_link_errors().clone());
+        packets_collection.expert.analyze(&mut new_packet);
+        packets_collection.share_raw_packet(&mut new_packet);
+
+        for (protocol, count) in reclassified.iter_mut() {
+            if !is_protocol(protocol, &old_packet) && is_protocol(protocol, &new_packet) {
+                *count += 1;
+            }
+        }
+
+        reparsed += 1;
+        packets.push(Arc::new(new_packet));
+    }
+    cleanup_sniffing_state();
+
+    packets_collection.reindex(packets);
+
+    ReparseSummary {
+        reparsed,
+        reclassified,
+    }
+}
+
+fn is_protocol(protocol: &str, packet: &ParsedPacket) -> bool {
+    match protocol {
+        Dissectors::HTTP => contains_http(packet) || contains_http2(packet),
+        Dissectors::TLS => contains_tls(packet),
+        Dissectors::DNS => contains_dns(packet),
+        Dissectors::SIP => contains_sip(packet),
+        Dissectors::DHCPV6 => contains_dhcpv6(packet),
+        Dissectors::KERBEROS => contains_kerberos(packet),
+        Dissectors::RADIUS => contains_radius(packet),
+        Dissectors::BITTORRENT => contains_bittorrent(packet),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::{BTreeMap, HashMap, HashSet};
+
+    use dns_parser::{Builder, QueryClass, QueryType};
+    use sniffer_parser::serializable_packet::util::contains_dns;
+    use sniffer_parser::{
+        cleanup_sniffing_state, parse_ethernet_bytes, set_enabled_dissectors, Dissectors,
+    };
+
+    use super::reparse_internal;
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::PacketsCollection;
+
+    fn build_udp_frame(dest_port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![];
+        // Ethernet
+        frame.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x01, 0x02, 0, 0, 0, 0, 0x02, 0x08, 0x00]);
+        // IPv4
+        let total_length = (20 + 8 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0x00, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        // UDP
+        frame.extend_from_slice(&50000u16.to_be_bytes());
+        frame.extend_from_slice(&dest_port.to_be_bytes());
+        frame.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn packets_reclassified_with_enabled_dissector() {
+        let mut query = Builder::new_query(1, true);
+        query.add_question("example.com", false, QueryType::A, QueryClass::IN);
+        let query = query.build().unwrap();
+
+        let frames = [
+            build_udp_frame(53, &query),
+            build_udp_frame(9999, &[1, 2, 3]),
+        ];
+
+        // Captured with the DNS dissector disabled
+        let mut packets_collection = PacketsCollection::new();
+        cleanup_sniffing_state();
+        set_enabled_dissectors(&HashSet::new());
+        for (id, frame) in frames.iter().enumerate() {
+            let mut packet = parse_ethernet_bytes(frame, id);
+            packet.set_timestamp(1000 + id as i64);
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, frame);
+            packets_collection.insert(std::sync::Arc::new(packet));
+        }
+        assert!(packets_collection.dns_packets.is_empty());
+
+        let protocols = HashSet::from([Dissectors::DNS.to_owned()]);
+        let summary = reparse_internal(
+            &mut packets_collection,
+            &protocols,
+            &protocols,
+            &HashMap::new(),
+        );
+        assert_eq!(summary.reparsed, 2);
+        assert_eq!(
+            summary.reclassified,
+            BTreeMap::from([(Dissectors::DNS.to_owned(), 1)])
+        );
+
+        assert_eq!(packets_collection.packets.len(), 2);
+        assert_eq!(packets_collection.dns_packets.len(), 1);
+        assert!(contains_dns(&packets_collection.packets[0]));
+        assert_eq!(packets_collection.packets[1].get_timestamp(), 1001);
+
+        // Nothing left to reclassify
+        let summary = reparse_internal(
+            &mut packets_collection,
+            &protocols,
+            &protocols,
+            &HashMap::new(),
+        );
+        assert_eq!(summary.reclassified[Dissectors::DNS], 0);
+        assert_eq!(packets_collection.dns_packets.len(), 1);
+    }
+}
@@ -7,9 +7,10 @@
 //!
 //! ```
 //! use report::{
-//!    data::{PacketExchange, SourceDestination},
+//!    data::{PacketExchange, SourceDestination, TimestampFormat, TimestampFormats},
 //!    write_report
 //! };
+//! use anonymize::Anonymizer;
 //! use chrono::Local;
 //!
 //! fn main() {
@@ -41,45 +42,71 @@
 //!     // Generate report
 //!     let report_path = "./path/to/report.csv";
 //!     let mut first_generation = true; // Only the first time, this adds the csv header
-//!     write_report(report_path, exchanged_packets, first_generation);
+//!     write_report(report_path, exchanged_packets, first_generation, None, TimestampFormat::default());
 //!
 //!     // From the second time onwards
 //!     first_generation = false;
 //!
 //!     // .. Add packets exchange ..
-//!     write_report(report_path, exchanged_packets, first_generation);
+//!     write_report(report_path, exchanged_packets, first_generation, None, TimestampFormat::default());
 //!
-//!     // .. Add packets exchange ..
-//!     write_report(report_path, exchanged_packets, first_generation);
+//!     // Addresses can be replaced with pseudonyms, consistently among the generations
+//!     let mut anonymizer = Anonymizer::new();
+//!     write_report(
+//!         report_path,
+//!         exchanged_packets,
+//!         first_generation,
+//!         Some(&mut anonymizer),
+//!         TimestampFormat::default(),
+//!     );
+//!
+//!     // Timestamps can be written in ISO-8601 with sub-second precision, optionally in UTC
+//!     let timestamp_format = TimestampFormat::new(TimestampFormats::ISO8601, true).unwrap();
+//!     write_report(report_path, exchanged_packets, first_generation, None, timestamp_format);
 //! }
 //! ```
 
-use self::data::{PacketExchange, SourceDestination};
+use self::data::{PacketExchange, SourceDestination, TimestampFormat};
+use crate::anonymize::Anonymizer;
 use sniffer_parser::serializable_packet::util::{
-    contains_arp, contains_dns, contains_http, contains_icmp, contains_icmp6, contains_ipv4,
-    contains_ipv6, contains_tcp, contains_tls, contains_udp, get_dest_ip, get_dest_port,
-    get_source_ip, get_source_port,
+    contains_arp, contains_bittorrent, contains_dhcpv6, contains_dns, contains_dot11,
+    contains_http, contains_http2, contains_icmp, contains_icmp6, contains_ipv4, contains_ipv6,
+    contains_kerberos, contains_radius, contains_sip, contains_tcp, contains_tls, contains_udp,
+    get_dest_ip, get_dest_port, get_source_ip, get_source_port, get_tunnel_type,
 };
 use sniffer_parser::serializable_packet::ParsedPacket;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fs::{self, OpenOptions};
 use std::io::{self, BufWriter, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Appends data to a report file, creates the file if it doesn't exist
 ///
 /// The file and the directory path to it are created if they do not exist.
-/// The hashmap is consumed and its content is written in the csv file indicated by the path.
+/// The content of the hashmap is written in the csv file indicated by the path, and the hashmap is
+/// emptied once the report is written.
 /// If the first_generation attribute it's true any file corresponding to the provided path will
-/// be deleted and a new file will be generated with a header containing the name of the fields.pub fn write_report(output_path: &str, data: &mut HashMap<SourceDestination, PacketExchange>, first_generation: bool) -> Result<bool, io::Error> {
+/// be replaced by a new file with a header containing the name of the fields.
+/// If an anonymizer is provided, the IP addresses are replaced with their pseudonyms.
+/// Timestamps are written in the provided format, `TimestampFormat::default()` being local time with
+/// second precision.
+///
+/// The report is written atomically: the new content (the previous report followed by the new lines,
+/// when updating) goes to a temporary file in the same directory, renamed over the report once
+/// complete. If the writing fails (e.g. disk full) the report is left as it was, and the hashmap
+/// untouched.
+///
+/// Without any packet exchange, a first generation writes only the header while an update leaves the
+/// file untouched and returns false. An update of a missing file writes the header too.
 pub fn write_report(
     output_path: &str,
     data: &mut HashMap<SourceDestination, PacketExchange>,
     first_generation: bool,
+    anonymizer: Option<&mut Anonymizer>,
+    timestamp_format: TimestampFormat,
 ) -> Result<bool, io::Error> {
     let path = Path::new(&output_path);
-    let mut file_exists = path.is_file();
     let file_extension = path.extension();
 
     // Check file extension is .csv
@@ -90,27 +117,87 @@ pub fn write_report(
         ));
     }
 
-    if !file_exists {
-        // Create parent directories if they don't exist
-        let parent_directory = path.parent().unwrap();
-        if !parent_directory.is_dir() {
-            fs::create_dir_all(parent_directory)?;
-        }
-    } else if first_generation {
-        // Remove old report file
-        fs::remove_file(&output_path)?;
-        file_exists = false;
+    if data.is_empty() && !first_generation {
+        return Ok(false);
+    }
+
+    // Create parent directories if they don't exist
+    let parent_directory = path.parent().unwrap();
+    if !parent_directory.as_os_str().is_empty() && !parent_directory.is_dir() {
+        fs::create_dir_all(parent_directory)?;
+    }
+
+    // Start from the previous report, unless replaced
+    let is_update = !first_generation && path.is_file();
+    let temporary_path = get_temporary_path(path);
+    let result = write_temporary_report(
+        path,
+        &temporary_path,
+        data,
+        is_update,
+        anonymizer,
+        timestamp_format,
+    )
+    .map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "Writing the temporary report {} failed: {}",
+                temporary_path.display(),
+                e
+            ),
+        )
+    })
+    .and_then(|_| {
+        fs::rename(&temporary_path, path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "Replacing {} with the temporary report failed: {}",
+                    path.display(),
+                    e
+                ),
+            )
+        })
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    result?;
+
+    data.clear();
+    Ok(true)
+}
+
+/// Hidden file next to the report, e.g. `.report.csv.tmp`
+fn get_temporary_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp", file_name))
+}
+
+fn write_temporary_report(
+    path: &Path,
+    temporary_path: &Path,
+    data: &HashMap<SourceDestination, PacketExchange>,
+    is_update: bool,
+    mut anonymizer: Option<&mut Anonymizer>,
+    timestamp_format: TimestampFormat,
+) -> Result<(), io::Error> {
+    if is_update {
+        fs::copy(path, temporary_path)?;
     }
 
-    // Open file in append mode, create it if it doesn't exist
     let file = OpenOptions::new()
-        .append(true)
-        .create(!file_exists)
-        .open(path)?;
+        .write(true)
+        .append(is_update)
+        .truncate(!is_update)
+        .create(true)
+        .open(temporary_path)?;
     let mut writer = BufWriter::new(file);
 
-    // Write report headers
-    if first_generation {
+    // Write report headers, also when the file was removed since the first generation
+    if !is_update {
         let headers = [
             "Source IP",
             "Destination IP",
@@ -124,18 +211,24 @@ pub fn write_report(
         writer.write_all((headers.join(",") + "\n").as_bytes())?;
     }
 
-    let mut data_pairs = data.drain().peekable();
-
-    if data_pairs.peek().is_some() {
-        // Write packets exchange data
-        for (source_destination, exchange) in data_pairs {
-            writer.write_all(
-                (source_destination.to_string() + "," + &exchange.to_string() + "\n").as_bytes(),
-            )?
+    // Write packets exchange data
+    for (source_destination, exchange) in data {
+        let mut source_destination = source_destination.clone();
+        if let Some(anonymizer) = anonymizer.as_deref_mut() {
+            source_destination.ip_source =
+                anonymizer.anonymize_address(&source_destination.ip_source);
+            source_destination.ip_destination =
+                anonymizer.anonymize_address(&source_destination.ip_destination);
         }
+
+        writer.write_all(
+            (source_destination.to_string() + "," + &exchange.format(timestamp_format) + "\n")
+                .as_bytes(),
+        )?
     }
 
-    Ok(true)
+    // Make sure the content reached the disk before replacing the report
+    writer.into_inner()?.sync_all()
 }
 
 /// Returns (Source IP, Destination IP, Source Port, Destination Port, and Protocols) contained in a packet
@@ -146,6 +239,10 @@ pub fn get_sender_receiver(packet: &ParsedPacket) -> (SourceDestination, Vec<Str
     let transport_destination = get_dest_port(packet).unwrap_or(String::from("-"));
     let mut protocols = Vec::new();
 
+    if contains_dot11(packet) {
+        protocols.push(String::from("802.11"));
+    }
+
     if contains_ipv4(packet) {
         protocols.push(String::from("IPv4"));
     } else if contains_ipv6(packet) {
@@ -154,6 +251,10 @@ pub fn get_sender_receiver(packet: &ParsedPacket) -> (SourceDestination, Vec<Str
         protocols.push(String::from("ARP"));
     }
 
+    if let Some(tunnel_type) = get_tunnel_type(packet) {
+        protocols.push(tunnel_type);
+    }
+
     if contains_icmp(packet) {
         protocols.push(String::from("ICMP"));
     } else if contains_icmp6(packet) {
@@ -170,8 +271,20 @@ pub fn get_sender_receiver(packet: &ParsedPacket) -> (SourceDestination, Vec<Str
         protocols.push(String::from("DNS"));
     } else if contains_http(packet) {
         protocols.push(String::from("HTTP"));
+    } else if contains_http2(packet) {
+        protocols.push(String::from("HTTP/2"));
     } else if contains_tls(packet) {
         protocols.push(String::from("TLS"));
+    } else if contains_sip(packet) {
+        protocols.push(String::from("SIP"));
+    } else if contains_dhcpv6(packet) {
+        protocols.push(String::from("DHCPv6"));
+    } else if contains_kerberos(packet) {
+        protocols.push(String::from("Kerberos"));
+    } else if contains_radius(packet) {
+        protocols.push(String::from("RADIUS"));
+    } else if contains_bittorrent(packet) {
+        protocols.push(String::from("BitTorrent"));
     }
 
     (
@@ -187,12 +300,55 @@ pub fn get_sender_receiver(packet: &ParsedPacket) -> (SourceDestination, Vec<Str
 
 /// Data structures used to write a report
 pub mod data {
-    use chrono::{DateTime, Local};
+    use chrono::{DateTime, Local, SecondsFormat, Utc};
     use std::cmp;
     use std::collections::HashSet;
 
+    /// Formats of the first and last data exchange timestamps
+    #[allow(non_snake_case)]
+    pub mod TimestampFormats {
+        /// Second precision, e.g. `2022-09-01 10:30:00`
+        pub const DEFAULT: &str = "default";
+        /// ISO-8601 (RFC 3339) with nanosecond precision, e.g. `2022-09-01T10:30:00.123456000+02:00`
+        pub const ISO8601: &str = "iso8601";
+    }
+
+    /// Format and timezone of the timestamps, local time with second precision by default
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct TimestampFormat {
+        iso8601: bool,
+        utc: bool,
+    }
+
+    impl TimestampFormat {
+        /// Timestamp format by name (see `TimestampFormats`), `None` if unknown
+        pub fn new(format: &str, utc: bool) -> Option<Self> {
+            let iso8601 = match format {
+                TimestampFormats::DEFAULT => false,
+                TimestampFormats::ISO8601 => true,
+                _ => return None,
+            };
+
+            Some(TimestampFormat { iso8601, utc })
+        }
+
+        pub fn format(&self, timestamp: &DateTime<Local>) -> String {
+            match (self.iso8601, self.utc) {
+                (false, false) => timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+                (false, true) => timestamp
+                    .with_timezone(&Utc)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                (true, false) => timestamp.to_rfc3339_opts(SecondsFormat::Nanos, false),
+                (true, true) => timestamp
+                    .with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::Nanos, true),
+            }
+        }
+    }
+
     /// Ip addresses and port numbers of source and destination of a packet exchange
-    #[derive(PartialEq, Eq, Hash, Debug)]
+    #[derive(PartialEq, Eq, Hash, Debug, Clone)]
     pub struct SourceDestination {
         pub ip_source: String,
         pub ip_destination: String,
@@ -270,10 +426,11 @@ pub mod data {
         }
     }
 
-    impl ToString for PacketExchange {
-        fn to_string(&self) -> String {
-            let first_exchange = self.first_exchange.format("%Y-%m-%d %H:%M:%S").to_string();
-            let last_exchange = self.last_exchange.format("%Y-%m-%d %H:%M:%S").to_string();
+    impl PacketExchange {
+        /// Timestamps, transmitted bytes and protocols, comma separated
+        pub fn format(&self, timestamp_format: TimestampFormat) -> String {
+            let first_exchange = timestamp_format.format(&self.first_exchange);
+            let last_exchange = timestamp_format.format(&self.last_exchange);
             let protocols_set = self.protocols.clone().into_iter().collect::<Vec<String>>();
             let protocols = if protocols_set.len() == 0 {
                 "-".to_owned()
@@ -293,10 +450,38 @@ pub mod data {
 
     #[cfg(test)]
     mod tests {
-        use super::{PacketExchange, SourceDestination};
-        use chrono::{Duration, Local};
+        use super::{PacketExchange, SourceDestination, TimestampFormat, TimestampFormats};
+        use chrono::{Duration, FixedOffset, Local, TimeZone};
         use std::collections::HashSet;
 
+        #[test]
+        fn timestamp_formats() {
+            let timestamp = FixedOffset::east_opt(2 * 3600)
+                .unwrap()
+                .with_ymd_and_hms(2022, 9, 1, 10, 30, 0)
+                .unwrap()
+                + Duration::microseconds(123_456);
+            let timestamp = timestamp.with_timezone(&Local);
+
+            let utc = TimestampFormat::new(TimestampFormats::DEFAULT, true).unwrap();
+            assert_eq!(utc.format(&timestamp), "2022-09-01 08:30:00");
+
+            let iso8601 = TimestampFormat::new(TimestampFormats::ISO8601, true).unwrap();
+            assert_eq!(iso8601.format(&timestamp), "2022-09-01T08:30:00.123456000Z");
+
+            let local = TimestampFormat::new(TimestampFormats::ISO8601, false).unwrap();
+            assert_eq!(
+                chrono::DateTime::parse_from_rfc3339(&local.format(&timestamp)).unwrap(),
+                timestamp
+            );
+
+            assert_eq!(
+                TimestampFormat::default(),
+                TimestampFormat::new(TimestampFormats::DEFAULT, false).unwrap()
+            );
+            assert!(TimestampFormat::new("rfc2822", false).is_none());
+        }
+
         #[test]
         fn empty_packet_exchange() {
             let now = Local::now();
@@ -455,3 +640,138 @@ pub mod data {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use chrono::Local;
+
+    use super::data::{PacketExchange, SourceDestination, TimestampFormat};
+    use super::write_report;
+
+    const HEADER: &str =
+        "Source IP,Destination IP,Source Port,Destination Port,First Data Exchange,\
+        Last Data Exchange,Bytes Exchanged,Protocols\n";
+
+    fn single_exchange() -> HashMap<SourceDestination, PacketExchange> {
+        let source_destination = SourceDestination::new(
+            "10.0.0.1".to_owned(),
+            "10.0.0.2".to_owned(),
+            "4444".to_owned(),
+            "80".to_owned(),
+        );
+        let exchange = PacketExchange::new(vec!["TCP".to_owned()], 60, Local::now());
+
+        HashMap::from([(source_destination, exchange)])
+    }
+
+    #[test]
+    fn reports_without_packets() {
+        let directory =
+            std::env::temp_dir().join(format!("wirefish-reports-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let path = directory.join("report.csv");
+        let path = path.to_str().unwrap();
+
+        // Empty first generation, only the header
+        assert!(write_report(
+            path,
+            &mut HashMap::new(),
+            true,
+            None,
+            TimestampFormat::default()
+        )
+        .unwrap());
+        assert_eq!(fs::read_to_string(path).unwrap(), HEADER);
+
+        // Empty update, untouched
+        assert!(!write_report(
+            path,
+            &mut HashMap::new(),
+            false,
+            None,
+            TimestampFormat::default()
+        )
+        .unwrap());
+        assert_eq!(fs::read_to_string(path).unwrap(), HEADER);
+
+        // Update after the empty generation
+        let mut data = single_exchange();
+        assert!(write_report(path, &mut data, false, None, TimestampFormat::default()).unwrap());
+        assert!(data.is_empty());
+        let content = fs::read_to_string(path).unwrap();
+        let lines = content.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("10.0.0.1,10.0.0.2,4444,80,"));
+        assert!(lines[1].ends_with(",60,TCP"));
+
+        // Empty update of a missing file, not created
+        fs::remove_file(path).unwrap();
+        assert!(!write_report(
+            path,
+            &mut HashMap::new(),
+            false,
+            None,
+            TimestampFormat::default()
+        )
+        .unwrap());
+        assert!(fs::metadata(path).is_err());
+
+        // Single packet update of a missing file, with the header
+        assert!(write_report(
+            path,
+            &mut single_exchange(),
+            false,
+            None,
+            TimestampFormat::default()
+        )
+        .unwrap());
+        let content = fs::read_to_string(path).unwrap();
+        assert!(content.starts_with(HEADER));
+        assert_eq!(content.lines().count(), 2);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn reports_written_atomically() {
+        let directory =
+            std::env::temp_dir().join(format!("wirefish-atomic-reports-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        let path = directory.join("report.csv");
+        let temporary_path = directory.join(".report.csv.tmp");
+        let path = path.to_str().unwrap();
+
+        assert!(write_report(
+            path,
+            &mut single_exchange(),
+            true,
+            None,
+            TimestampFormat::default()
+        )
+        .unwrap());
+        assert!(fs::metadata(&temporary_path).is_err());
+        let report = fs::read_to_string(path).unwrap();
+
+        // The temporary report can't be written
+        fs::create_dir(&temporary_path).unwrap();
+        let mut data = single_exchange();
+        let error = write_report(path, &mut data, false, None, TimestampFormat::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Writing the temporary report"));
+        assert_eq!(fs::read_to_string(path).unwrap(), report);
+        assert_eq!(data.len(), 1);
+
+        // Appended through the temporary copy
+        fs::remove_dir(&temporary_path).unwrap();
+        assert!(write_report(path, &mut data, false, None, TimestampFormat::default()).unwrap());
+        assert!(data.is_empty());
+        assert_eq!(fs::read_to_string(path).unwrap().lines().count(), 3);
+        assert!(fs::metadata(&temporary_path).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+//! Captures started and stopped automatically in a time window, once or every day
+//!
+//! A scheduler thread waits for the start of the window, selects the interface and starts a sniffing
+//! process appending to the collected packets, then stops it (draining the buffered frames) at the end of
+//! the window. Each transition is notified with a `capture_schedule` event.
+//!
+//! Times are local wall-clock times, a window ending before its start ends the day after (e.g. 22:00 to
+//! 06:00). Only one schedule is armed at a time: a new one, or `cancel_schedule`, disarms the previous
+//! one, stopping its capture if running
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+
+use chrono::{Duration, Local, NaiveDateTime, NaiveTime};
+use log::{info, warn};
+use pnet::datalink;
+use serde::Serialize;
+use tauri::{Manager, Window, Wry};
+
+use crate::interfaces::{find_interface, get_interface_display_name};
+use crate::{
+    start_sniffing_internal, stop_sniffing_internal, Actions, ContextualError, SniffingError,
+    SniffingState,
+};
+
+/// Armed schedule
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CaptureSchedule {
+    pub interface: String,
+    pub start_time: String,
+    pub stop_time: String,
+    pub daily: bool,
+    /// Start of the first window, in microseconds since the Unix epoch
+    pub next_start: i64,
+}
+
+/// Start or stop of a scheduled capture
+#[derive(Serialize, Debug, Clone)]
+struct ScheduleTransition {
+    interface: String,
+    started: bool,
+    timestamp: i64,
+    /// Reason why the capture couldn't be started or stopped
+    error: Option<String>,
+}
+
+/// Arms a capture on `interface` between `start_time` and `stop_time` ("HH:MM" or "HH:MM:SS"), repeated
+/// every day with `daily`. If the window is already open the capture starts immediately
+#[tauri::command]
+pub fn schedule_capture(
+    state: tauri::State<SniffingState>,
+    window: Window<Wry>,
+    interface: String,
+    start_time: String,
+    stop_time: String,
+    daily: Option<bool>,
+) -> Result<CaptureSchedule, ContextualError> {
+    let context =
+        |e: SniffingError| e.with_context(Actions::SCHEDULE_CAPTURE, Some(interface.clone()));
+
+    let start = parse_time(&start_time).map_err(context)?;
+    let stop = parse_time(&stop_time).map_err(context)?;
+    if start == stop {
+        return Err(context(SniffingError::InvalidConfiguration(
+            "Empty capture window".to_owned(),
+        )));
+    }
+    // Fail now rather than at the start of the window
+    find_interface(datalink::interfaces(), &interface).map_err(context)?;
+
+    let daily = daily.unwrap_or(false);
+    let (next_start, _) = get_next_window(Local::now().naive_local(), start, stop);
+
+    let (send_cancel, receive_cancel) = channel();
+    if let Some(previous) = state.schedule.lock().unwrap().replace(send_cancel) {
+        let _result = previous.send(());
+    }
+
+    info!(
+        "Capture scheduled on {} from {} to {}{}",
+        interface,
+        start,
+        stop,
+        if daily { ", every day" } else { "" }
+    );
+
+    let schedule = CaptureSchedule {
+        interface: interface.clone(),
+        start_time: start.to_string(),
+        stop_time: stop.to_string(),
+        daily,
+        next_start: next_start
+            .and_local_timezone(Local)
+            .earliest()
+            .map_or(0, |next_start| next_start.timestamp_micros()),
+    };
+
+    std::thread::spawn(move || run_schedule(window, interface, start, stop, daily, receive_cancel));
+
+    Ok(schedule)
+}
+
+/// Disarms the current schedule, stopping its capture if running
+#[tauri::command]
+pub fn cancel_schedule(state: tauri::State<SniffingState>) {
+    if let Some(schedule) = state.schedule.lock().unwrap().take() {
+        info!("Capture schedule cancelled");
+        let _result = schedule.send(());
+    }
+}
+
+fn run_schedule(
+    window: Window<Wry>,
+    interface: String,
+    start: NaiveTime,
+    stop: NaiveTime,
+    daily: bool,
+    cancel: Receiver<()>,
+) {
+    loop {
+        let (window_start, window_stop) = get_next_window(Local::now().naive_local(), start, stop);
+        if wait_until(&cancel, window_start) {
+            return;
+        }
+
+        let state = window.state::<SniffingState>();
+        let started = start_scheduled_capture(&state, &window, &interface);
+        emit_transition(&window, &interface, true, started.as_ref().err());
+
+        let cancelled = wait_until(&cancel, window_stop);
+
+        if started.is_ok() {
+            let stopped = stop_sniffing_internal(&state, true, true);
+            emit_transition(&window, &interface, false, stopped.as_ref().err());
+        }
+
+        if cancelled || !daily {
+            return;
+        }
+    }
+}
+
+fn start_scheduled_capture(
+    state: &SniffingState,
+    window: &Window<Wry>,
+    interface: &str,
+) -> Result<(), SniffingError> {
+    let interface = find_interface(datalink::interfaces(), interface)?;
+
+    let mut sniffing_info = state.info.lock().unwrap();
+    sniffing_info.interface_name = Some(get_interface_display_name(&interface));
+    sniffing_info.interface = Some(interface);
+    sniffing_info.additional_interfaces.clear();
+    drop(sniffing_info);
+
+    start_sniffing_internal(false, true, None, None, state, window.clone())
+}
+
+fn emit_transition(
+    window: &Window<Wry>,
+    interface: &str,
+    started: bool,
+    error: Option<&SniffingError>,
+) {
+    match error {
+        Some(e) => warn!(
+            "Scheduled capture on {} not {}: {:?}",
+            interface,
+            if started { "started" } else { "stopped" },
+            e
+        ),
+        None => info!(
+            "Scheduled capture on {} {}",
+            interface,
+            if started { "started" } else { "stopped" }
+        ),
+    }
+
+    let _result = window.emit(
+        "capture_schedule",
+        ScheduleTransition {
+            interface: interface.to_owned(),
+            started,
+            timestamp: Local::now().timestamp_micros(),
+            error: error.map(|e| format!("{:?}", e)),
+        },
+    );
+}
+
+/// Waits until the local time `until`, returning whether the schedule was cancelled meanwhile
+fn wait_until(cancel: &Receiver<()>, until: NaiveDateTime) -> bool {
+    let timeout = (until - Local::now().naive_local())
+        .to_std()
+        .unwrap_or_default();
+
+    match cancel.recv_timeout(timeout) {
+        Err(RecvTimeoutError::Timeout) => false,
+        Ok(()) | Err(RecvTimeoutError::Disconnected) => true,
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, SniffingError> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time.trim(), "%H:%M"))
+        .map_err(|_| SniffingError::InvalidConfiguration(format!("Invalid time: {}", time)))
+}
+
+/// Start and stop of the window open at `now`, or of the next one
+fn get_next_window(
+    now: NaiveDateTime,
+    start: NaiveTime,
+    stop: NaiveTime,
+) -> (NaiveDateTime, NaiveDateTime) {
+    let window = |day: NaiveDateTime| {
+        let window_start = day.date().and_time(start);
+        let mut window_stop = day.date().and_time(stop);
+        if window_stop <= window_start {
+            window_stop += Duration::days(1);
+        }
+        (window_start, window_stop)
+    };
+
+    // The window started yesterday may still be open
+    [now - Duration::days(1), now, now + Duration::days(1)]
+        .into_iter()
+        .map(window)
+        .find(|(_, window_stop)| *window_stop > now)
+        .unwrap()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    use super::{get_next_window, parse_time};
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2022, 9, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn next_capture_window() {
+        let (start, stop) = (parse_time("09:00").unwrap(), parse_time("17:30").unwrap());
+        assert_eq!(
+            get_next_window(at(10, 8, 0), start, stop),
+            (at(10, 9, 0), at(10, 17, 30))
+        );
+        // Already open
+        assert_eq!(
+            get_next_window(at(10, 12, 0), start, stop),
+            (at(10, 9, 0), at(10, 17, 30))
+        );
+        assert_eq!(
+            get_next_window(at(10, 18, 0), start, stop),
+            (at(11, 9, 0), at(11, 17, 30))
+        );
+
+        // Overnight
+        let (start, stop) = (
+            parse_time("22:00:00").unwrap(),
+            parse_time("06:00").unwrap(),
+        );
+        assert_eq!(
+            get_next_window(at(10, 3, 0), start, stop),
+            (at(9, 22, 0), at(10, 6, 0))
+        );
+        assert_eq!(
+            get_next_window(at(10, 7, 0), start, stop),
+            (at(10, 22, 0), at(11, 6, 0))
+        );
+
+        assert_eq!(
+            parse_time(" 6:05 ").unwrap(),
+            NaiveTime::from_hms_opt(6, 5, 0).unwrap()
+        );
+        assert!(parse_time("25:00").is_err());
+    }
+}
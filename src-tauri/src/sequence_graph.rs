@@ -0,0 +1,304 @@
+//! Data of the time-sequence graph (Stevens graph) of a TCP connection, in each direction
+//!
+//! Each segment is a point with its sequence number over time, along with the acknowledgement number
+//! and the receive window it carries: a steady slope is a steady transfer, flat steps are stalls and
+//! points going back are retransmissions. The gap between the acknowledgement plus the window and the
+//! sequence numbers sent in the other direction is the room left to the sender.
+//!
+//! Sequence numbers are relative to the first one seen in the direction, acknowledgement numbers to the
+//! first one of the other direction, wrapping around as the original ones. Windows are scaled only when
+//! the option was sent in both SYNs (RFC 7323), and never in the SYNs themselves.
+//!
+//! The direction is relative to the endpoint given as source: `forward` for the segments it sent,
+//! `reverse` for the ones it received.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use log::{info, warn};
+use pnet::packet::tcp::TcpFlags;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::transport::SerializableTcpPacket;
+use sniffer_parser::serializable_packet::util::get_tcp_window_scale;
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::efficiency::get_endpoints;
+use crate::{SniffingError, SniffingState};
+
+/// Segment of a connection, in the graph of its direction
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SequenceGraphPoint {
+    pub packet_id: usize,
+    pub timestamp: i64,
+    /// Relative sequence number of the first byte (or of the SYN or FIN)
+    pub sequence: u32,
+    /// Payload bytes
+    pub length: usize,
+    /// Relative acknowledgement number, if the ACK flag is set
+    pub acknowledgement: Option<u32>,
+    /// Receive window advertised, in bytes
+    pub window: u32,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SequenceGraphDirection {
+    /// First sequence number seen, the origin of the relative ones
+    pub initial_sequence: Option<u32>,
+    pub points: Vec<SequenceGraphPoint>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TcpSequenceGraph {
+    pub forward: SequenceGraphDirection,
+    pub reverse: SequenceGraphDirection,
+    /// Whether both SYNs carried the window scale option
+    pub window_scaling: bool,
+}
+
+type Endpoint = (IpAddr, u16);
+
+/// Returns the sequence numbers, acknowledgement numbers and windows over time of the TCP connection
+/// between the two endpoints, per direction
+#[tauri::command]
+pub fn get_tcp_sequence_graph(
+    state: tauri::State<SniffingState>,
+    source_ip: String,
+    source_port: u16,
+    dest_ip: String,
+    dest_port: u16,
+) -> Result<TcpSequenceGraph, SniffingError> {
+    let parse_ip = |ip: &str| {
+        ip.parse::<IpAddr>().map_err(|e| {
+            warn!("Invalid connection IP address {}: {}", ip, e);
+            SniffingError::InvalidIpAddress(format!("Invalid connection IP address {}: {}", ip, e))
+        })
+    };
+
+    let source = (parse_ip(&source_ip)?, source_port);
+    let destination = (parse_ip(&dest_ip)?, dest_port);
+
+    let packets_collection = state.packets.lock().unwrap();
+    let graph =
+        get_tcp_sequence_graph_internal(source, destination, &packets_collection.tcp_packets);
+
+    info!(
+        "TCP sequence graph {:?} - {:?}: {} and {} segments",
+        source,
+        destination,
+        graph.forward.points.len(),
+        graph.reverse.points.len()
+    );
+
+    Ok(graph)
+}
+
+fn get_tcp_sequence_graph_internal(
+    source: Endpoint,
+    destination: Endpoint,
+    packets: &[Arc<ParsedPacket>],
+) -> TcpSequenceGraph {
+    // Segments of the connection, forward ones flagged
+    let mut segments: Vec<(bool, &ParsedPacket, &SerializableTcpPacket)> = vec![];
+    for packet in packets {
+        let tcp_packet = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet,
+            _ => continue,
+        };
+
+        let forward = match get_endpoints(packet) {
+            Some(endpoints) if endpoints == (source, destination) => true,
+            Some(endpoints) if endpoints == (destination, source) => false,
+            _ => continue,
+        };
+
+        segments.push((forward, packet, tcp_packet));
+    }
+
+    let mut graph = TcpSequenceGraph::default();
+    // Window scale of the SYN of each direction, forward first
+    let (mut forward_scale, mut reverse_scale) = (None, None);
+    for (forward, packet, tcp_packet) in &segments {
+        let (direction, scale) = if *forward {
+            (&mut graph.forward, &mut forward_scale)
+        } else {
+            (&mut graph.reverse, &mut reverse_scale)
+        };
+
+        direction
+            .initial_sequence
+            .get_or_insert(tcp_packet.sequence);
+        if tcp_packet.flags & TcpFlags::SYN != 0 {
+            *scale = get_tcp_window_scale(packet);
+        }
+    }
+
+    graph.window_scaling = forward_scale.is_some() && reverse_scale.is_some();
+    let (forward_initial, reverse_initial) = (
+        graph.forward.initial_sequence,
+        graph.reverse.initial_sequence,
+    );
+
+    for (forward, packet, tcp_packet) in segments {
+        let (direction, initial, other_initial, scale) = if forward {
+            (
+                &mut graph.forward,
+                forward_initial,
+                reverse_initial,
+                forward_scale,
+            )
+        } else {
+            (
+                &mut graph.reverse,
+                reverse_initial,
+                forward_initial,
+                reverse_scale,
+            )
+        };
+
+        let acknowledgement = if tcp_packet.flags & TcpFlags::ACK != 0 {
+            other_initial
+                .map(|other_initial| tcp_packet.acknowledgement.wrapping_sub(other_initial))
+        } else {
+            None
+        };
+
+        let shift = match scale {
+            Some(shift) if graph.window_scaling && tcp_packet.flags & TcpFlags::SYN == 0 => {
+                // Larger shifts are treated as the maximum one (RFC 7323, 2.3)
+                shift.min(14)
+            }
+            _ => 0,
+        };
+
+        direction.points.push(SequenceGraphPoint {
+            packet_id: packet.get_id(),
+            timestamp: packet.get_timestamp(),
+            sequence: tcp_packet
+                .sequence
+                .wrapping_sub(initial.unwrap_or(tcp_packet.sequence)),
+            length: tcp_packet.length,
+            acknowledgement,
+            window: (tcp_packet.window as u32) << shift,
+        });
+    }
+
+    graph
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+
+    use pnet::packet::tcp::TcpFlags;
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::transport::SerializableTcpOption;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+
+    use super::{get_tcp_sequence_graph_internal, SequenceGraphPoint};
+
+    fn build_tcp_packet(
+        id: usize,
+        to_server: bool,
+        flags: u16,
+        (sequence, acknowledgement): (u32, u32),
+        length: usize,
+        window: u16,
+        window_scale: Option<u8>,
+    ) -> Arc<ParsedPacket> {
+        let (client, server) = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 80));
+        let template = if to_server {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), client, server, 4444, 80)
+        } else {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), server, client, 80, 4444)
+        };
+
+        let mut tcp_packet = match template.get_transport_layer_packet() {
+            Some(SerializablePacket::TcpPacket(tcp_packet)) => tcp_packet.clone(),
+            _ => unreachable!(),
+        };
+        tcp_packet.flags = flags;
+        tcp_packet.sequence = sequence;
+        tcp_packet.acknowledgement = acknowledgement;
+        tcp_packet.length = length;
+        tcp_packet.window = window;
+        tcp_packet.parsed_options = window_scale
+            .map(SerializableTcpOption::WindowScale)
+            .into_iter()
+            .collect();
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_timestamp(1000 * id as i64);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(Some(SerializablePacket::TcpPacket(tcp_packet)));
+
+        Arc::new(parsed_packet)
+    }
+
+    #[test]
+    fn sequence_graph_per_direction() {
+        let data = TcpFlags::ACK | TcpFlags::PSH;
+        // Close to the wrap around of the sequence numbers
+        let isn = u32::MAX - 50;
+        let packets = vec![
+            build_tcp_packet(0, true, TcpFlags::SYN, (isn, 0), 0, 64240, Some(7)),
+            build_tcp_packet(
+                1,
+                false,
+                TcpFlags::SYN | TcpFlags::ACK,
+                (9000, isn.wrapping_add(1)),
+                0,
+                65160,
+                Some(2),
+            ),
+            build_tcp_packet(2, true, data, (isn.wrapping_add(1), 9001), 100, 502, None),
+            build_tcp_packet(
+                3,
+                false,
+                TcpFlags::ACK,
+                (9001, isn.wrapping_add(101)),
+                0,
+                1000,
+                None,
+            ),
+            // Retransmission
+            build_tcp_packet(4, true, data, (isn.wrapping_add(1), 9001), 100, 502, None),
+        ];
+
+        let graph = get_tcp_sequence_graph_internal(
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 4444),
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 80)), 80),
+            &packets,
+        );
+
+        assert!(graph.window_scaling);
+        assert_eq!(graph.forward.initial_sequence, Some(isn));
+        assert_eq!(
+            graph.forward.points[1],
+            SequenceGraphPoint {
+                packet_id: 2,
+                timestamp: 2000,
+                sequence: 1,
+                length: 100,
+                acknowledgement: Some(1),
+                window: 502 << 7,
+            }
+        );
+        assert_eq!(
+            graph
+                .forward
+                .points
+                .iter()
+                .map(|point| (point.sequence, point.acknowledgement))
+                .collect::<Vec<(u32, Option<u32>)>>(),
+            vec![(0, None), (1, Some(1)), (1, Some(1))]
+        );
+
+        // The SYN window is never scaled
+        assert_eq!(graph.reverse.points[0].window, 65160);
+        assert_eq!(graph.reverse.points[1].acknowledgement, Some(101));
+        assert_eq!(graph.reverse.points[1].window, 4000);
+    }
+}
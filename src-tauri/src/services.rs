@@ -0,0 +1,172 @@
+//! Names of the services associated to the transport ports
+//!
+//! A built-in table of IANA well-known ports can be overridden or extended with custom names, e.g. for
+//! services listening on non-standard ports. When both ports of a packet are known, the lowest one is
+//! assumed to be the server port and names the service.
+
+use std::collections::HashMap;
+
+use log::info;
+use sniffer_parser::serializable_packet::util::{get_dest_port, get_source_port};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::{SniffingError, SniffingState};
+
+/// IANA service names of the most common well-known ports
+const WELL_KNOWN_SERVICES: [(u16, &str); 46] = [
+    (20, "FTP-DATA"),
+    (21, "FTP"),
+    (22, "SSH"),
+    (23, "TELNET"),
+    (25, "SMTP"),
+    (53, "DNS"),
+    (67, "DHCP"),
+    (68, "DHCP"),
+    (69, "TFTP"),
+    (80, "HTTP"),
+    (88, "KERBEROS"),
+    (110, "POP3"),
+    (111, "SUNRPC"),
+    (123, "NTP"),
+    (135, "MSRPC"),
+    (137, "NETBIOS-NS"),
+    (138, "NETBIOS-DGM"),
+    (139, "NETBIOS-SSN"),
+    (143, "IMAP"),
+    (161, "SNMP"),
+    (162, "SNMPTRAP"),
+    (179, "BGP"),
+    (389, "LDAP"),
+    (443, "HTTPS"),
+    (445, "SMB"),
+    (465, "SMTPS"),
+    (500, "ISAKMP"),
+    (514, "SYSLOG"),
+    (546, "DHCPV6"),
+    (547, "DHCPV6"),
+    (587, "SUBMISSION"),
+    (636, "LDAPS"),
+    (853, "DNS-OVER-TLS"),
+    (993, "IMAPS"),
+    (995, "POP3S"),
+    (1194, "OPENVPN"),
+    (1433, "MSSQL"),
+    (1812, "RADIUS"),
+    (1813, "RADIUS-ACCT"),
+    (3306, "MYSQL"),
+    (3389, "RDP"),
+    (5060, "SIP"),
+    (5353, "MDNS"),
+    (5432, "POSTGRESQL"),
+    (6881, "BITTORRENT"),
+    (8080, "HTTP-ALT"),
+];
+
+/// Port to service mapping, built-in names first overridden by the custom ones
+#[derive(Debug)]
+pub struct ServiceNames {
+    names: HashMap<u16, String>,
+}
+
+impl ServiceNames {
+    pub fn new() -> Self {
+        ServiceNames {
+            names: get_well_known_services(),
+        }
+    }
+
+    /// Replace the custom names, an empty name removes the service of a well-known port
+    pub fn set_custom_names(&mut self, custom_names: HashMap<u16, String>) {
+        let mut names = get_well_known_services();
+
+        for (port, name) in custom_names {
+            let name = name.trim();
+            if name.is_empty() {
+                names.remove(&port);
+            } else {
+                names.insert(port, name.to_owned());
+            }
+        }
+
+        self.names = names;
+    }
+
+    /// Name of the service listening on the port, if known
+    pub fn get(&self, port: u16) -> Option<&str> {
+        self.names.get(&port).map(String::as_str)
+    }
+
+    /// Name of the service of the packet, from its lowest known transport port
+    pub fn get_packet_service(&self, packet: &ParsedPacket) -> Option<&str> {
+        let mut ports = [get_source_port(packet), get_dest_port(packet)]
+            .into_iter()
+            .flatten()
+            .filter_map(|port| port.parse::<u16>().ok())
+            .collect::<Vec<u16>>();
+        ports.sort_unstable();
+
+        ports.into_iter().find_map(|port| self.get(port))
+    }
+}
+
+fn get_well_known_services() -> HashMap<u16, String> {
+    WELL_KNOWN_SERVICES
+        .iter()
+        .map(|(port, name)| (*port, name.to_string()))
+        .collect()
+}
+
+/// Overrides or extends the built-in port to service mapping
+///
+/// Each call replaces the custom names set previously
+#[tauri::command]
+pub fn set_service_names(
+    state: tauri::State<SniffingState>,
+    names: HashMap<u16, String>,
+) -> Result<(), SniffingError> {
+    info!("Custom service names: {:?}", names);
+    state
+        .packets
+        .lock()
+        .unwrap()
+        .services
+        .set_custom_names(names);
+
+    Ok(())
+}
+
+/// Tag each packet with the name of its service
+pub fn tag_services(packets: &mut Vec<ParsedPacket>, services: &ServiceNames) {
+    for packet in packets {
+        let service = services.get_packet_service(packet).map(str::to_owned);
+        packet.set_service(service);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+
+    use super::ServiceNames;
+
+    #[test]
+    fn custom_service_names() {
+        let mut services = ServiceNames::new();
+        assert_eq!(services.get(443), Some("HTTPS"));
+        assert_eq!(services.get(8443), None);
+
+        services.set_custom_names(HashMap::from([
+            (8443, "HTTPS-ALT".to_owned()),
+            (80, "PROXY".to_owned()),
+            (22, " ".to_owned()),
+        ]));
+        assert_eq!(services.get(8443), Some("HTTPS-ALT"));
+        assert_eq!(services.get(80), Some("PROXY"));
+        assert_eq!(services.get(22), None);
+
+        // Previous custom names are replaced
+        services.set_custom_names(HashMap::new());
+        assert_eq!(services.get(80), Some("HTTP"));
+        assert_eq!(services.get(8443), None);
+    }
+}
@@ -0,0 +1,339 @@
+//! Enumeration of the capture sessions saved in a directory
+//!
+//! Sessions are the capture files (`.pcap`, `.pcapng`, `.cap` as either pcap or NetMon, optionally
+//! gzip-compressed) found in the directory. Only the record headers of pcap files are read, the packet
+//! data being seeked through unless compressed, and each session is paired with the .csv report of the
+//! same name, if any. The capture interface is not read, so it's not available.
+//!
+//! The description of the session has no room in the pcap format, so the one of an exported capture is saved
+//! beside it, in `<capture file>.description`, and restored by loading the capture file.
+//!
+//! Files that fail to parse are listed with the error, instead of failing the whole request
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, ErrorKind};
+use std::path::Path;
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::capture_file::{get_capture_reader, get_capture_stream, is_gzip_stream, SkipRead};
+use crate::SniffingError;
+
+const SESSION_EXTENSIONS: [&str; 6] = [
+    ".pcap",
+    ".pcapng",
+    ".cap",
+    ".pcap.gz",
+    ".pcapng.gz",
+    ".cap.gz",
+];
+const REPORT_EXTENSION: &str = ".csv";
+const DESCRIPTION_EXTENSION: &str = ".description";
+
+/// Metadata of a saved capture session
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct SessionInfo {
+    pub path: String,
+    pub name: String,
+    pub compressed: bool,
+    /// Size of the file on disk, in bytes
+    pub file_size: u64,
+    pub link_type: Option<u32>,
+    /// Timestamps of the first and last packet, in microseconds since the Unix epoch
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub packet_count: usize,
+    pub captured_bytes: u64,
+    /// Path of the report generated for the session
+    pub report_path: Option<String>,
+    /// Free-text description of the session, saved along with the capture file
+    pub description: Option<String>,
+    /// Reason why the file could not be read, the other metadata being partial
+    pub error: Option<String>,
+}
+
+/// Returns the sessions saved in `dir`, sorted by start time (unreadable ones last)
+#[tauri::command(async)]
+pub fn list_sessions(dir: String) -> Result<Vec<SessionInfo>, SniffingError> {
+    let entries = fs::read_dir(&dir).map_err(|e| {
+        SniffingError::CaptureFileAccessFailed(format!("Unable to read {}: {}", dir, e))
+    })?;
+
+    let mut sessions = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        let stem = match SESSION_EXTENSIONS
+            .iter()
+            .find_map(|extension| name.strip_suffix(extension))
+        {
+            Some(stem) if path.is_file() => stem.to_owned(),
+            _ => continue,
+        };
+
+        let report_path = Path::new(&dir).join(format!("{}{}", stem, REPORT_EXTENSION));
+        let mut session = read_session_file(&path);
+        session.name = name;
+        session.path = path.to_string_lossy().into_owned();
+        session.description = read_description_file(&session.path);
+        session.report_path = report_path
+            .is_file()
+            .then(|| report_path.to_string_lossy().into_owned());
+
+        sessions.push(session);
+    }
+
+    sort_sessions(&mut sessions);
+    info!("Listed {} sessions in {}", sessions.len(), dir);
+
+    Ok(sessions)
+}
+
+fn read_session_file(path: &Path) -> SessionInfo {
+    let mut session = SessionInfo {
+        compressed: path.to_string_lossy().ends_with(".gz"),
+        file_size: fs::metadata(path).map_or(0, |metadata| metadata.len()),
+        ..Default::default()
+    };
+
+    let result = File::open(path)
+        .map_err(|e| SniffingError::CaptureFileAccessFailed(format!("Unable to open: {}", e)))
+        .and_then(|file| {
+            let mut file = BufReader::new(file);
+            // Only uncompressed files can be seeked through
+            if session.compressed || is_gzip_stream(&mut file)? {
+                let stream = get_capture_stream(file, true)?;
+                read_session_info(BufReader::new(stream), &mut session)
+            } else {
+                read_session_info(file, &mut session)
+            }
+        });
+
+    if let Err(e) = result {
+        warn!("Invalid session file {:?}: {:?}", path, e);
+        session.error = Some(get_error_description(e));
+    }
+
+    session
+}
+
+/// Fill the session metadata from the record headers of the capture stream
+fn read_session_info<R: BufRead + SkipRead>(
+    stream: R,
+    session: &mut SessionInfo,
+) -> Result<(), SniffingError> {
+    let mut capture_reader = get_capture_reader(stream)?;
+    session.link_type = capture_reader.get_link_type();
+
+    while let Some((link_type, timestamp, captured_length)) = capture_reader.skip_record()? {
+        session.link_type.get_or_insert(link_type);
+        session.start_time.get_or_insert(timestamp);
+        session.end_time = Some(timestamp);
+        session.packet_count += 1;
+        session.captured_bytes += captured_length as u64;
+    }
+
+    Ok(())
+}
+
+fn get_description_path(capture_path: &str) -> String {
+    format!("{}{}", capture_path, DESCRIPTION_EXTENSION)
+}
+
+/// Saves the description of the session beside the capture file, removing the file of a previous export
+/// when there is none
+pub fn write_description_file(capture_path: &str, description: Option<&str>) -> io::Result<()> {
+    let path = get_description_path(capture_path);
+    let description = match description {
+        Some(description) => description,
+        None => {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        }
+    };
+
+    fs::write(&path, description)?;
+    info!("Saved the session description in {}", path);
+
+    Ok(())
+}
+
+/// Description of the session saved beside the capture file, if any
+pub fn read_description_file(capture_path: &str) -> Option<String> {
+    let path = get_description_path(capture_path);
+    match fs::read_to_string(&path) {
+        Ok(description) if !description.trim().is_empty() => Some(description.trim().to_owned()),
+        Ok(_) => None,
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("Unable to read {}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn sort_sessions(sessions: &mut [SessionInfo]) {
+    sessions.sort_by(|a, b| {
+        (
+            a.error.is_some(),
+            a.start_time.is_none(),
+            a.start_time,
+            &a.name,
+        )
+            .cmp(&(
+                b.error.is_some(),
+                b.start_time.is_none(),
+                b.start_time,
+                &b.name,
+            ))
+    });
+}
+
+fn get_error_description(error: SniffingError) -> String {
+    match error {
+        SniffingError::InvalidCaptureFile(description)
+        | SniffingError::CaptureFileAccessFailed(description) => description,
+        e => format!("{:?}", e),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::env::temp_dir;
+    use std::fs;
+    use std::io::{BufReader, Read};
+
+    use crate::capture_file::{LinkTypes, PcapWriter};
+    use crate::pcapng::tests::{block, enhanced_packet};
+
+    use super::{
+        get_description_path, read_description_file, read_session_file, read_session_info,
+        sort_sessions, write_description_file, SessionInfo,
+    };
+
+    /// Stream of a capture in memory, read through as a decompressed one
+    fn stream(capture: &[u8]) -> BufReader<Box<dyn Read + '_>> {
+        BufReader::new(Box::new(capture))
+    }
+
+    #[test]
+    fn session_metadata() {
+        let mut pcap_writer = PcapWriter::new(vec![], LinkTypes::ETHERNET).unwrap();
+        pcap_writer
+            .write_record(1_600_000_000_000_000, &[0; 60])
+            .unwrap();
+        pcap_writer
+            .write_record(1_600_000_005_000_000, &[0; 40])
+            .unwrap();
+        let capture = pcap_writer.into_inner();
+
+        let mut session = SessionInfo::default();
+        read_session_info(stream(&capture), &mut session).unwrap();
+        assert_eq!(session.link_type, Some(LinkTypes::ETHERNET));
+        assert_eq!(session.start_time, Some(1_600_000_000_000_000));
+        assert_eq!(session.end_time, Some(1_600_000_005_000_000));
+        assert_eq!(session.packet_count, 2);
+        assert_eq!(session.captured_bytes, 100);
+
+        // Truncated in the middle of the last record
+        let mut session = SessionInfo::default();
+        assert!(read_session_info(stream(&capture[..capture.len() - 10]), &mut session).is_err());
+        assert_eq!(session.packet_count, 1);
+    }
+
+    #[test]
+    fn session_file_seeked() {
+        let path = temp_dir().join("wirefish_session_test.pcap");
+        let mut pcap_writer = PcapWriter::new(vec![], LinkTypes::ETHERNET).unwrap();
+        pcap_writer.write_record(100, &[0; 60]).unwrap();
+        pcap_writer.write_record(200, &[0; 10_000]).unwrap();
+        pcap_writer.write_record(300, &[]).unwrap();
+        let capture = pcap_writer.into_inner();
+
+        fs::write(&path, &capture).unwrap();
+        let session = read_session_file(&path);
+        assert_eq!(session.error, None);
+        assert_eq!(session.link_type, Some(LinkTypes::ETHERNET));
+        assert_eq!(session.end_time, Some(300));
+        assert_eq!(session.packet_count, 3);
+        assert_eq!(session.captured_bytes, 10_060);
+
+        // Truncated in the middle of the second record, once its header is read
+        fs::write(&path, &capture[..capture.len() - 100]).unwrap();
+        let session = read_session_file(&path);
+        assert!(session.error.is_some());
+        assert_eq!(session.packet_count, 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn pcapng_session_metadata() {
+        let mut capture = block(
+            0x0a0d0d0a,
+            &[
+                0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            ],
+        );
+        // Ethernet interface, microseconds resolution
+        capture.extend(block(0x00000001, &[1, 0, 0, 0, 0, 0, 0, 0]));
+        capture.extend(enhanced_packet(1_000, &[0xaa; 5], &[]));
+        capture.extend(enhanced_packet(2_000, &[0xbb; 8], &[]));
+
+        let mut session = SessionInfo::default();
+        read_session_info(stream(&capture), &mut session).unwrap();
+        assert_eq!(session.link_type, Some(LinkTypes::ETHERNET));
+        assert_eq!(session.start_time, Some(1_000));
+        assert_eq!(session.end_time, Some(2_000));
+        assert_eq!(session.packet_count, 2);
+        assert_eq!(session.captured_bytes, 13);
+    }
+
+    #[test]
+    fn description_saved_beside_capture() {
+        let capture_path = temp_dir()
+            .join("wirefish_description_test.pcap")
+            .to_string_lossy()
+            .into_owned();
+
+        write_description_file(&capture_path, Some("TICKET-42: slow DNS")).unwrap();
+        assert_eq!(
+            read_description_file(&capture_path),
+            Some("TICKET-42: slow DNS".to_owned())
+        );
+
+        // Exported again without a description
+        write_description_file(&capture_path, None).unwrap();
+        assert!(fs::metadata(get_description_path(&capture_path)).is_err());
+        assert_eq!(read_description_file(&capture_path), None);
+        write_description_file(&capture_path, None).unwrap();
+    }
+
+    #[test]
+    fn sessions_order() {
+        let session = |name: &str, start_time, error: Option<&str>| SessionInfo {
+            name: name.to_owned(),
+            start_time,
+            error: error.map(str::to_owned),
+            ..Default::default()
+        };
+
+        let mut sessions = vec![
+            session("broken.pcap", Some(1), Some("Truncated pcap record")),
+            session("empty.pcap", None, None),
+            session("late.pcap", Some(20), None),
+            session("early.pcap", Some(10), None),
+        ];
+        sort_sessions(&mut sessions);
+
+        let names: Vec<&str> = sessions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["early.pcap", "late.pcap", "empty.pcap", "broken.pcap"]
+        );
+    }
+}
@@ -0,0 +1,819 @@
+//! Aggregated information about the collected packets
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+use serde::Serialize;
+use sniffer_parser::serializable_packet::application::{CustomHandshakeMessage, CustomTlsMessage};
+use sniffer_parser::serializable_packet::util::{
+    get_cast_type, get_dest_ip, get_dest_mac, get_dest_port, get_source_ip, get_source_mac,
+    get_source_port,
+};
+use sniffer_parser::serializable_packet::{ExpertSeverities, ParsedPacket, SerializablePacket};
+
+use crate::filtering::{apply_all_strong_filters, FilterNamesValues, PacketsCollection};
+use crate::{SniffingError, SniffingState};
+
+/// Fields without a filter of the same name
+#[allow(non_snake_case)]
+pub mod DistinctFields {
+    /// Name of the protocol filters matching the packet
+    pub const PROTOCOL: &str = "protocol";
+    /// Server name requested by TLS Client Hello messages
+    pub const SNI: &str = "sni";
+}
+
+/// Overview of the collected packets, useful to characterize a capture before sharing it
+#[derive(Serialize, Debug)]
+pub struct CaptureSummary {
+    pub packets: usize,
+    pub bytes: usize,
+    /// Timestamp of the first and last packet, in microseconds since the Unix epoch
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+    /// Time span between the first and last packet, in microseconds
+    pub duration: i64,
+    /// Distinct network addresses, either as source or destination
+    pub endpoints: Vec<String>,
+    /// Protocols contained in at least one packet
+    pub protocols: Vec<String>,
+    /// SHA-256 of the capture as exported in a pcap file, see [`crate::filtering::ContentSnapshot::get_hash`]
+    pub content_hash: String,
+    /// Free-text description of the session, as set by the user
+    pub description: Option<String>,
+}
+
+/// Value of a field, with the number of packets carrying it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DistinctValue {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Upper bounds of the packet size histogram buckets, in bytes, the last bucket having no upper bound
+const SIZE_BUCKETS: [usize; 6] = [64, 128, 256, 512, 1024, 1518];
+
+/// Distribution of the sizes of a set of packets, in bytes
+///
+/// Percentiles (and median) use the nearest-rank method, so they are always sizes of actual packets
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SizeStatistics {
+    pub packets: usize,
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+    pub p95: usize,
+    pub p99: usize,
+}
+
+/// Packets whose size is between `min` and `max` bytes, inclusive
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SizeBucket {
+    pub min: usize,
+    pub max: Option<usize>,
+    pub packets: usize,
+}
+
+/// Packet sizes, overall and by protocol
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PacketSizeStats {
+    pub overall: Option<SizeStatistics>,
+    /// By protocol filter name, for the protocols contained in at least one packet
+    pub protocols: BTreeMap<String, SizeStatistics>,
+    pub histogram: Vec<SizeBucket>,
+}
+
+/// Returns a summary of the collected packets
+#[tauri::command]
+pub fn get_capture_summary(
+    state: tauri::State<SniffingState>,
+) -> Result<CaptureSummary, SniffingError> {
+    let content_hash = get_content_hash(&state.packets);
+    let packets_collection = state.packets.lock().unwrap();
+    let description = state.description.lock().unwrap().clone();
+
+    Ok(get_capture_summary_internal(
+        &packets_collection,
+        content_hash,
+        description,
+    ))
+}
+
+/// Content hash of the collection, computed once released so that the parsing of the captured packets
+/// isn't held up by the hashing of the whole capture, and cached until packets are collected
+fn get_content_hash(packets: &Mutex<PacketsCollection>) -> String {
+    let snapshot = {
+        let packets_collection = packets.lock().unwrap();
+        if let Some(content_hash) = packets_collection.get_cached_content_hash() {
+            return content_hash;
+        }
+        packets_collection.get_content_snapshot()
+    };
+
+    let content_hash = snapshot.get_hash();
+    packets
+        .lock()
+        .unwrap()
+        .cache_content_hash(&snapshot, content_hash.clone());
+
+    content_hash
+}
+
+fn get_capture_summary_internal(
+    packets_collection: &PacketsCollection,
+    content_hash: String,
+    description: Option<String>,
+) -> CaptureSummary {
+    let timestamps = packets_collection
+        .packets
+        .iter()
+        .map(|packet| packet.get_timestamp());
+    let first_timestamp = timestamps.clone().min();
+    let last_timestamp = timestamps.max();
+
+    // The keys evicted from the indexes are only found by scanning the packets
+    let endpoints = if packets_collection.is_index_complete(FilterNamesValues::SRC_IP)
+        && packets_collection.is_index_complete(FilterNamesValues::DST_IP)
+    {
+        packets_collection
+            .source_ip_index
+            .keys()
+            .chain(packets_collection.dest_ip_index.keys())
+            .cloned()
+            .collect::<BTreeSet<String>>()
+    } else {
+        packets_collection
+            .packets
+            .iter()
+            .flat_map(|packet| get_source_ip(packet).into_iter().chain(get_dest_ip(packet)))
+            .collect::<BTreeSet<String>>()
+    };
+
+    let protocols = get_protocol_packets(packets_collection)
+        .iter()
+        .filter(|(_, packets)| !packets.is_empty())
+        .map(|(name, _)| name.to_string())
+        .collect();
+
+    CaptureSummary {
+        packets: packets_collection.packets.len(),
+        bytes: packets_collection.captured_bytes,
+        first_timestamp,
+        last_timestamp,
+        duration: last_timestamp.unwrap_or(0) - first_timestamp.unwrap_or(0),
+        endpoints: endpoints.into_iter().collect(),
+        protocols,
+        content_hash,
+        description,
+    }
+}
+
+/// Returns the distinct values of a field among the collected packets, most frequent first
+///
+/// Fields are named as the filters on the same attribute (`src_ip`, `dst_port`, `service`, `cast`, ...),
+/// plus `protocol` and `sni`. Addresses and ports are read from the filtering indexes, unless keys were
+/// evicted from them by their limit
+#[tauri::command]
+pub fn get_distinct_values(
+    state: tauri::State<SniffingState>,
+    field: String,
+) -> Result<Vec<DistinctValue>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+    get_distinct_values_internal(&field, &packets_collection)
+}
+
+fn get_distinct_values_internal(
+    field: &str,
+    packets_collection: &PacketsCollection,
+) -> Result<Vec<DistinctValue>, SniffingError> {
+    let index_counts = |index: &BTreeMap<String, Vec<Arc<ParsedPacket>>>| {
+        index
+            .iter()
+            .map(|(value, packets)| (value.clone(), packets.len()))
+            .collect::<HashMap<String, usize>>()
+    };
+
+    let scan_counts = |get_values: &dyn Fn(&ParsedPacket) -> Vec<String>| {
+        let mut counts = HashMap::new();
+        for packet in &packets_collection.packets {
+            for value in get_values(packet) {
+                *counts.entry(value).or_insert(0) += 1;
+            }
+        }
+        counts
+    };
+
+    let index_complete = packets_collection.is_index_complete(field);
+    let counts = match field {
+        FilterNamesValues::SRC_IP if !index_complete => {
+            scan_counts(&|packet| get_source_ip(packet).into_iter().collect())
+        }
+        FilterNamesValues::DST_IP if !index_complete => {
+            scan_counts(&|packet| get_dest_ip(packet).into_iter().collect())
+        }
+        FilterNamesValues::SRC_MAC if !index_complete => {
+            scan_counts(&|packet| get_source_mac(packet).into_iter().collect())
+        }
+        FilterNamesValues::DST_MAC if !index_complete => {
+            scan_counts(&|packet| get_dest_mac(packet).into_iter().collect())
+        }
+        FilterNamesValues::SRC_PORT if !index_complete => {
+            scan_counts(&|packet| get_source_port(packet).into_iter().collect())
+        }
+        FilterNamesValues::DST_PORT if !index_complete => {
+            scan_counts(&|packet| get_dest_port(packet).into_iter().collect())
+        }
+        FilterNamesValues::SRC_IP => index_counts(&packets_collection.source_ip_index),
+        FilterNamesValues::DST_IP => index_counts(&packets_collection.dest_ip_index),
+        FilterNamesValues::SRC_MAC => index_counts(&packets_collection.source_mac_index),
+        FilterNamesValues::DST_MAC => index_counts(&packets_collection.dest_mac_index),
+        FilterNamesValues::SRC_PORT => index_counts(&packets_collection.source_port_index),
+        FilterNamesValues::DST_PORT => index_counts(&packets_collection.dest_port_index),
+        FilterNamesValues::SERVICE => scan_counts(&|packet| {
+            packets_collection
+                .services
+                .get_packet_service(packet)
+                .map(str::to_owned)
+                .into_iter()
+                .collect()
+        }),
+        FilterNamesValues::VENDOR => scan_counts(&|packet| {
+            let (source_vendor, dest_vendor) =
+                packets_collection.vendors.get_packet_vendors(packet);
+            let mut vendors = source_vendor.into_iter().collect::<Vec<&str>>();
+            // Counted once per packet
+            vendors.extend(dest_vendor.filter(|vendor| Some(*vendor) != source_vendor));
+            vendors.into_iter().map(str::to_owned).collect()
+        }),
+        FilterNamesValues::CAST => scan_counts(&|packet| {
+            get_cast_type(packet)
+                .map(str::to_owned)
+                .into_iter()
+                .collect()
+        }),
+        FilterNamesValues::FLOW => {
+            scan_counts(&|packet| packet.get_flow_hash().cloned().into_iter().collect())
+        }
+        FilterNamesValues::INTERFACE => {
+            scan_counts(&|packet| packet.get_interface_name().cloned().into_iter().collect())
+        }
+        FilterNamesValues::EXPERT => scan_counts(&|packet| {
+            // Highest severity of the packet, as matched by the filter
+            packet
+                .get_expert_info()
+                .iter()
+                .max_by_key(|info| ExpertSeverities::get_level(&info.severity))
+                .map(|info| info.severity.clone())
+                .into_iter()
+                .collect()
+        }),
+        DistinctFields::SNI => scan_counts(&get_server_names),
+        DistinctFields::PROTOCOL => get_protocol_packets(packets_collection)
+            .iter()
+            .filter(|(_, packets)| !packets.is_empty())
+            .map(|(name, packets)| (name.to_string(), packets.len()))
+            .collect(),
+        _ => {
+            warn!("Unknown field: {}", field);
+            return Err(SniffingError::UnknownFilterType(format!(
+                "Unknown field: {}",
+                field
+            )));
+        }
+    };
+
+    let mut values = counts
+        .into_iter()
+        .map(|(value, count)| DistinctValue { value, count })
+        .collect::<Vec<DistinctValue>>();
+    values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+
+    Ok(values)
+}
+
+/// Returns the distribution of the sizes of the packets matching the filters, the size of a packet being
+/// the one of the whole captured frame
+#[tauri::command]
+pub fn get_packet_size_stats<'a>(
+    state: tauri::State<SniffingState>,
+    filters_value: Vec<(&'a str, &'a str)>,
+) -> Result<PacketSizeStats, SniffingError> {
+    let mut packets_collection = state.packets.lock().unwrap();
+
+    let packets = if filters_value.is_empty() {
+        packets_collection.packets.clone()
+    } else {
+        let end = packets_collection.packets.len();
+        apply_all_strong_filters(end, &filters_value, &mut packets_collection)?
+    };
+
+    Ok(get_packet_size_stats_internal(
+        &packets,
+        &packets_collection,
+    ))
+}
+
+fn get_packet_size_stats_internal(
+    packets: &[Arc<ParsedPacket>],
+    packets_collection: &PacketsCollection,
+) -> PacketSizeStats {
+    let get_size = |packet: &Arc<ParsedPacket>| {
+        packets_collection
+            .raw_packets
+            .get(&packet.get_id())
+            .map(|raw_packet| raw_packet.data.len())
+    };
+
+    let sizes = packets.iter().filter_map(get_size).collect::<Vec<usize>>();
+
+    let selected = packets
+        .iter()
+        .map(|packet| packet.get_id())
+        .collect::<HashSet<usize>>();
+    let protocols = get_protocol_packets(packets_collection)
+        .iter()
+        .filter_map(|(name, protocol_packets)| {
+            let sizes = protocol_packets
+                .iter()
+                .filter(|packet| selected.contains(&packet.get_id()))
+                .filter_map(get_size)
+                .collect::<Vec<usize>>();
+            get_size_statistics(sizes).map(|statistics| (name.to_string(), statistics))
+        })
+        .collect();
+
+    let mut histogram = vec![];
+    let mut min = 0;
+    for max in SIZE_BUCKETS.into_iter().map(Some).chain([None]) {
+        histogram.push(SizeBucket {
+            min,
+            max,
+            packets: sizes
+                .iter()
+                .filter(|size| **size >= min && max.map_or(true, |max| **size <= max))
+                .count(),
+        });
+        min = max.map_or(0, |max| max + 1);
+    }
+
+    PacketSizeStats {
+        overall: get_size_statistics(sizes),
+        protocols,
+        histogram,
+    }
+}
+
+fn get_size_statistics(mut sizes: Vec<usize>) -> Option<SizeStatistics> {
+    if sizes.is_empty() {
+        return None;
+    }
+    sizes.sort_unstable();
+
+    let percentile = |percentile: usize| {
+        let rank = (percentile * sizes.len()).saturating_sub(1) / 100;
+        sizes[rank]
+    };
+
+    Some(SizeStatistics {
+        packets: sizes.len(),
+        min: sizes[0],
+        max: sizes[sizes.len() - 1],
+        mean: sizes.iter().sum::<usize>() as f64 / sizes.len() as f64,
+        median: percentile(50),
+        p95: percentile(95),
+        p99: percentile(99),
+    })
+}
+
+/// Packets of each protocol, by protocol filter name
+fn get_protocol_packets(
+    packets_collection: &PacketsCollection,
+) -> [(&'static str, &Vec<Arc<ParsedPacket>>); 19] {
+    [
+        (
+            FilterNamesValues::ETHERNET,
+            &packets_collection.ethernet_packets,
+        ),
+        (FilterNamesValues::DOT11, &packets_collection.dot11_packets),
+        (FilterNamesValues::ARP, &packets_collection.arp_packets),
+        (FilterNamesValues::IPV4, &packets_collection.ipv4_packets),
+        (FilterNamesValues::IPV6, &packets_collection.ipv6_packets),
+        (FilterNamesValues::ICMP, &packets_collection.icmp_packets),
+        (
+            FilterNamesValues::ICMPV6,
+            &packets_collection.icmpv6_packets,
+        ),
+        (FilterNamesValues::TCP, &packets_collection.tcp_packets),
+        (FilterNamesValues::UDP, &packets_collection.udp_packets),
+        (FilterNamesValues::HTTP, &packets_collection.http_packets),
+        (FilterNamesValues::HTTP2, &packets_collection.http2_packets),
+        (FilterNamesValues::TLS, &packets_collection.tls_packets),
+        (FilterNamesValues::DNS, &packets_collection.dns_packets),
+        (FilterNamesValues::SIP, &packets_collection.sip_packets),
+        (
+            FilterNamesValues::DHCPV6,
+            &packets_collection.dhcpv6_packets,
+        ),
+        (
+            FilterNamesValues::KERBEROS,
+            &packets_collection.kerberos_packets,
+        ),
+        (
+            FilterNamesValues::RADIUS,
+            &packets_collection.radius_packets,
+        ),
+        (
+            FilterNamesValues::BITTORRENT,
+            &packets_collection.bittorrent_packets,
+        ),
+        (
+            FilterNamesValues::TUNNEL,
+            &packets_collection.tunnel_packets,
+        ),
+    ]
+}
+
+/// Server names of the TLS Client Hello messages carried by the packet
+fn get_server_names(packet: &ParsedPacket) -> Vec<String> {
+    let tls_packet = match packet.get_application_layer_packet() {
+        Some(SerializablePacket::TlsPacket(tls_packet)) => tls_packet,
+        _ => return vec![],
+    };
+
+    tls_packet
+        .messages
+        .iter()
+        .filter_map(|message| match message {
+            CustomTlsMessage::Handshake(CustomHandshakeMessage::ClientHello(client_hello)) => {
+                Some(client_hello)
+            }
+            _ => None,
+        })
+        .flat_map(|client_hello| client_hello.extensions.iter())
+        // Formatted as `SNI: HostName = example.com, HostName = ...`
+        .filter_map(|extension| extension.strip_prefix("SNI: "))
+        .flat_map(|names| names.split(", "))
+        .filter_map(|name| name.split_once(" = ").map(|(_, name)| name.to_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::sync::{Arc, Mutex};
+
+    use pnet::util::MacAddr;
+    use sha2::{Digest, Sha256};
+    use sniffer_parser::serializable_packet::ParsedPacket;
+
+    use crate::capture_file::{write_pcap, LinkTypes};
+    use crate::filtering::tests::{
+        build_second_test_parsed_packet, build_test_packets_collection, build_test_parsed_packet,
+    };
+    use crate::filtering::{FilterNamesValues, PacketsCollection};
+
+    use super::{
+        get_capture_summary_internal, get_content_hash, get_distinct_values_internal,
+        get_packet_size_stats_internal, DistinctFields, DistinctValue,
+    };
+
+    #[test]
+    fn empty_capture_summary() {
+        let packets_collection = PacketsCollection::new();
+        let summary = get_capture_summary_internal(
+            &packets_collection,
+            packets_collection.get_content_hash(),
+            None,
+        );
+
+        assert_eq!(summary.packets, 0);
+        assert_eq!(summary.bytes, 0);
+        assert_eq!(summary.first_timestamp, None);
+        assert_eq!(summary.duration, 0);
+        assert!(summary.endpoints.is_empty());
+        assert!(summary.protocols.is_empty());
+        assert_eq!(summary.description, None);
+        // Hash of the pcap global header alone
+        assert_eq!(
+            summary.content_hash,
+            "a7829d7181a8851c0f2ced720cd0b0274d96bd37495cd690809e3797bcdb76c9"
+        );
+    }
+
+    #[test]
+    fn capture_summary_with_packets() {
+        let mut first_packet = build_test_parsed_packet(
+            MacAddr::new(10, 10, 10, 10, 10, 10),
+            MacAddr::new(11, 11, 11, 11, 11, 11),
+            Ipv4Addr::new(10, 10, 10, 10),
+            Ipv4Addr::new(11, 11, 11, 11),
+            4444,
+            443,
+        );
+        first_packet.set_timestamp(1_000_000);
+
+        let mut second_packet = build_second_test_parsed_packet(
+            MacAddr::new(10, 10, 10, 10, 10, 10),
+            MacAddr::new(11, 11, 11, 11, 11, 11),
+            Ipv6Addr::new(10, 10, 10, 10, 0, 0, 0, 0),
+            Ipv6Addr::new(11, 11, 11, 11, 0, 0, 0, 0),
+            4444,
+            443,
+        );
+        second_packet.set_timestamp(3_500_000);
+
+        let mut packets_collection =
+            build_test_packets_collection(vec![first_packet, second_packet]);
+        packets_collection.add_raw_packet(0, LinkTypes::ETHERNET, &[0; 64]);
+        packets_collection.add_raw_packet(1, LinkTypes::ETHERNET, &[0; 128]);
+
+        let summary = get_capture_summary_internal(
+            &packets_collection,
+            packets_collection.get_content_hash(),
+            Some("TICKET-42".to_owned()),
+        );
+
+        assert_eq!(summary.packets, 2);
+        assert_eq!(summary.bytes, 192);
+        assert_eq!(summary.first_timestamp, Some(1_000_000));
+        assert_eq!(summary.last_timestamp, Some(3_500_000));
+        assert_eq!(summary.duration, 2_500_000);
+        assert_eq!(
+            summary.endpoints,
+            vec!["10.10.10.10", "11.11.11.11", "a:a:a:a::", "b:b:b:b::"]
+        );
+        assert_eq!(summary.protocols, vec!["ipv4", "ipv6", "tcp", "udp"]);
+        assert_eq!(summary.content_hash.len(), 64);
+        assert_eq!(summary.description, Some("TICKET-42".to_owned()));
+    }
+
+    #[test]
+    fn capture_summary_with_index_limit() {
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection
+            .set_index_limit(FilterNamesValues::SRC_IP, Some(1))
+            .unwrap();
+
+        for source in [Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)] {
+            let packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                source,
+                Ipv4Addr::new(10, 0, 0, 254),
+                4444,
+                443,
+            );
+            packets_collection.insert(Arc::new(packet));
+        }
+
+        let summary = get_capture_summary_internal(
+            &packets_collection,
+            packets_collection.get_content_hash(),
+            None,
+        );
+        assert_eq!(
+            summary.endpoints,
+            vec!["10.0.0.1", "10.0.0.2", "10.0.0.254"]
+        );
+    }
+
+    #[test]
+    fn content_hash_depends_on_packet_boundaries() {
+        let mut single_packet = PacketsCollection::new();
+        single_packet.insert(Arc::new(ParsedPacket::new(0)));
+        single_packet.add_raw_packet(0, LinkTypes::ETHERNET, &[1, 2, 3, 4]);
+
+        let mut two_packets = PacketsCollection::new();
+        for (id, data) in [[1, 2], [3, 4]].iter().enumerate() {
+            two_packets.insert(Arc::new(ParsedPacket::new(id)));
+            two_packets.add_raw_packet(id, LinkTypes::ETHERNET, data);
+        }
+
+        assert_ne!(
+            single_packet.get_content_hash(),
+            two_packets.get_content_hash()
+        );
+        assert_eq!(single_packet.captured_bytes, two_packets.captured_bytes);
+    }
+
+    #[test]
+    fn content_hash_of_exported_pcap() {
+        let mut packets_collection = PacketsCollection::new();
+        for (id, timestamp) in [1_500_000, 2_250_000].iter().enumerate() {
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_timestamp(*timestamp);
+            packets_collection.insert(Arc::new(parsed_packet));
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &[id as u8; 60]);
+        }
+
+        let mut exported = vec![];
+        write_pcap(
+            &mut exported,
+            &packets_collection.packets,
+            &packets_collection.raw_packets,
+        )
+        .unwrap();
+        let exported_hash = Sha256::digest(&exported)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        assert_eq!(packets_collection.get_content_hash(), exported_hash);
+    }
+
+    #[test]
+    fn content_hash_cached_until_collected() {
+        let packets = Mutex::new(PacketsCollection::new());
+        packets
+            .lock()
+            .unwrap()
+            .insert(Arc::new(ParsedPacket::new(0)));
+        packets
+            .lock()
+            .unwrap()
+            .add_raw_packet(0, LinkTypes::ETHERNET, &[0; 60]);
+
+        let content_hash = get_content_hash(&packets);
+        assert_eq!(
+            packets.lock().unwrap().get_cached_content_hash(),
+            Some(content_hash.clone())
+        );
+
+        let mut packets_collection = packets.lock().unwrap();
+        packets_collection.insert(Arc::new(ParsedPacket::new(1)));
+        packets_collection.add_raw_packet(1, LinkTypes::ETHERNET, &[1; 60]);
+        assert_eq!(packets_collection.get_cached_content_hash(), None);
+        let new_content_hash = packets_collection.get_content_hash();
+        drop(packets_collection);
+
+        assert_ne!(new_content_hash, content_hash);
+        assert_eq!(get_content_hash(&packets), new_content_hash);
+
+        packets.lock().unwrap().clear();
+        assert_eq!(packets.lock().unwrap().get_cached_content_hash(), None);
+    }
+
+    #[test]
+    fn distinct_values_by_frequency() {
+        let mut packets_collection = PacketsCollection::new();
+        for source in [
+            Ipv4Addr::new(10, 0, 0, 2),
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+        ] {
+            let packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                source,
+                Ipv4Addr::new(10, 0, 0, 254),
+                4444,
+                443,
+            );
+            packets_collection.insert(Arc::new(packet));
+        }
+
+        let distinct_value = |value: &str, count| DistinctValue {
+            value: value.to_owned(),
+            count,
+        };
+
+        assert_eq!(
+            get_distinct_values_internal(FilterNamesValues::SRC_IP, &packets_collection).unwrap(),
+            vec![distinct_value("10.0.0.2", 2), distinct_value("10.0.0.1", 1)]
+        );
+        assert_eq!(
+            get_distinct_values_internal(DistinctFields::PROTOCOL, &packets_collection).unwrap(),
+            vec![
+                distinct_value("ethernet", 3),
+                distinct_value("ipv4", 3),
+                distinct_value("tcp", 3)
+            ]
+        );
+        assert!(get_distinct_values_internal("ttl", &packets_collection).is_err());
+    }
+
+    #[test]
+    fn distinct_values_with_index_limit() {
+        let mut packets_collection = PacketsCollection::new();
+        packets_collection
+            .set_index_limit(FilterNamesValues::SRC_IP, Some(1))
+            .unwrap();
+
+        for source in [
+            Ipv4Addr::new(10, 0, 0, 2),
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+        ] {
+            let packet = build_test_parsed_packet(
+                MacAddr::new(10, 10, 10, 10, 10, 10),
+                MacAddr::new(12, 12, 12, 12, 12, 12),
+                source,
+                Ipv4Addr::new(10, 0, 0, 254),
+                4444,
+                443,
+            );
+            packets_collection.insert(Arc::new(packet));
+        }
+
+        let values =
+            get_distinct_values_internal(FilterNamesValues::SRC_IP, &packets_collection).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                DistinctValue {
+                    value: "10.0.0.2".to_owned(),
+                    count: 2
+                },
+                DistinctValue {
+                    value: "10.0.0.1".to_owned(),
+                    count: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn packet_size_distribution() {
+        let (source_mac, dest_mac) = (
+            MacAddr::new(10, 10, 10, 10, 10, 10),
+            MacAddr::new(11, 11, 11, 11, 11, 11),
+        );
+        let tcp_packet = build_test_parsed_packet(
+            source_mac,
+            dest_mac,
+            Ipv4Addr::new(10, 10, 10, 10),
+            Ipv4Addr::new(11, 11, 11, 11),
+            4444,
+            443,
+        );
+        let udp_packet = build_second_test_parsed_packet(
+            source_mac,
+            dest_mac,
+            Ipv6Addr::new(10, 10, 10, 10, 0, 0, 0, 0),
+            Ipv6Addr::new(11, 11, 11, 11, 0, 0, 0, 0),
+            4444,
+            53,
+        );
+
+        let mut packets_collection = PacketsCollection::new();
+        for (id, size) in [60, 100, 1500, 9000, 64].into_iter().enumerate() {
+            let template = if id % 2 == 0 {
+                &tcp_packet
+            } else {
+                &udp_packet
+            };
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_link_layer_packet(template.get_link_layer_packet().cloned());
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet
+                .set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &vec![0; size]);
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let packets = packets_collection.packets.clone();
+        let stats = get_packet_size_stats_internal(&packets, &packets_collection);
+
+        let overall = stats.overall.unwrap();
+        assert_eq!((overall.packets, overall.min, overall.max), (5, 60, 9000));
+        assert_eq!(overall.mean, 2144.8);
+        assert_eq!(
+            (overall.median, overall.p95, overall.p99),
+            (100, 9000, 9000)
+        );
+
+        let tcp = &stats.protocols["tcp"];
+        assert_eq!(
+            (tcp.packets, tcp.min, tcp.max, tcp.median),
+            (3, 60, 1500, 64)
+        );
+        assert_eq!(stats.protocols["udp"].packets, 2);
+        assert!(!stats.protocols.contains_key("arp"));
+
+        let histogram: Vec<(usize, Option<usize>, usize)> = stats
+            .histogram
+            .iter()
+            .map(|bucket| (bucket.min, bucket.max, bucket.packets))
+            .collect();
+        assert_eq!(
+            histogram,
+            vec![
+                (0, Some(64), 2),
+                (65, Some(128), 1),
+                (129, Some(256), 0),
+                (257, Some(512), 0),
+                (513, Some(1024), 0),
+                (1025, Some(1518), 1),
+                (1519, None, 1),
+            ]
+        );
+
+        // Only the selected packets
+        let stats = get_packet_size_stats_internal(&packets[..2], &packets_collection);
+        assert_eq!(stats.overall.unwrap().max, 100);
+        assert_eq!(stats.protocols["tcp"].packets, 1);
+    }
+}
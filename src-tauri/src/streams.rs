@@ -0,0 +1,262 @@
+//! Following of UDP streams, the datagrams exchanged between two endpoints
+//!
+//! UDP has no connection, so a stream gathers the datagrams matching the 5-tuple in both directions,
+//! in capture order and without any reassembly. The direction is relative to the endpoint given as
+//! source: `forward` for the datagrams it sent, `reverse` for the ones it received.
+//!
+//! Payloads are taken from the stored frames, so they are truncated when the frame was captured partially (snap length)
+
+use std::net::IpAddr;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::serializable_packet::transport::SerializableUdpPacket;
+use sniffer_parser::serializable_packet::util::{get_dest_ip, get_source_ip};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::filtering::PacketsCollection;
+use crate::{SniffingError, SniffingState};
+
+#[allow(non_snake_case)]
+pub mod StreamDirections {
+    pub const FORWARD: &str = "forward";
+    pub const REVERSE: &str = "reverse";
+}
+
+const UDP_HEADER_LENGTH: usize = 8;
+
+/// Datagram of a UDP stream
+#[derive(Serialize, Debug, Clone)]
+pub struct UdpDatagram {
+    pub packet_id: usize,
+    pub timestamp: i64,
+    pub direction: String,
+    /// Payload length declared in the UDP header
+    pub length: usize,
+    pub payload: Vec<u8>,
+}
+
+/// Datagrams and payload bytes sent in one direction
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct StreamDirectionSummary {
+    pub datagrams: usize,
+    pub bytes: usize,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct UdpStream {
+    pub datagrams: Vec<UdpDatagram>,
+    pub forward: StreamDirectionSummary,
+    pub reverse: StreamDirectionSummary,
+}
+
+type Endpoint = (IpAddr, u16);
+
+/// Returns the UDP datagrams exchanged between the two endpoints, in both directions
+#[tauri::command]
+pub fn get_udp_stream(
+    state: tauri::State<SniffingState>,
+    source_ip: String,
+    source_port: u16,
+    dest_ip: String,
+    dest_port: u16,
+) -> Result<UdpStream, SniffingError> {
+    let parse_ip = |ip: &str| {
+        ip.parse::<IpAddr>().map_err(|e| {
+            warn!("Invalid stream IP address {}: {}", ip, e);
+            SniffingError::InvalidIpAddress(format!("Invalid stream IP address {}: {}", ip, e))
+        })
+    };
+
+    let source = (parse_ip(&source_ip)?, source_port);
+    let destination = (parse_ip(&dest_ip)?, dest_port);
+
+    let packets_collection = state.packets.lock().unwrap();
+    let stream = get_udp_stream_internal(source, destination, &packets_collection);
+
+    info!(
+        "UDP stream {:?} - {:?}: {} datagrams",
+        source,
+        destination,
+        stream.datagrams.len()
+    );
+
+    Ok(stream)
+}
+
+fn get_udp_stream_internal(
+    source: Endpoint,
+    destination: Endpoint,
+    packets_collection: &PacketsCollection,
+) -> UdpStream {
+    let mut stream = UdpStream::default();
+
+    for packet in &packets_collection.udp_packets {
+        let udp = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::UdpPacket(udp)) => udp,
+            _ => continue,
+        };
+
+        let endpoints = match get_endpoints(packet, udp) {
+            Some(endpoints) => endpoints,
+            None => continue,
+        };
+
+        let (direction, summary) = if endpoints == (source, destination) {
+            (StreamDirections::FORWARD, &mut stream.forward)
+        } else if endpoints == (destination, source) {
+            (StreamDirections::REVERSE, &mut stream.reverse)
+        } else {
+            continue;
+        };
+
+        let length = (udp.length as usize).saturating_sub(UDP_HEADER_LENGTH);
+        let payload = packets_collection
+            .raw_packets
+            .get(&packet.get_id())
+            .map_or(vec![], |raw_packet| get_udp_payload(&raw_packet.data, udp));
+
+        summary.datagrams += 1;
+        summary.bytes += length;
+
+        stream.datagrams.push(UdpDatagram {
+            packet_id: packet.get_id(),
+            timestamp: packet.get_timestamp(),
+            direction: direction.to_owned(),
+            length,
+            payload,
+        });
+    }
+
+    stream
+}
+
+fn get_endpoints(
+    packet: &ParsedPacket,
+    udp: &SerializableUdpPacket,
+) -> Option<(Endpoint, Endpoint)> {
+    let source_ip = get_source_ip(packet)?.parse::<IpAddr>().ok()?;
+    let dest_ip = get_dest_ip(packet)?.parse::<IpAddr>().ok()?;
+
+    Some(((source_ip, udp.source), (dest_ip, udp.destination)))
+}
+
+/// Locates the UDP header in the frame, whatever the link and network layers are, and returns the
+/// captured part of the payload following it
+pub(crate) fn get_udp_payload(frame: &[u8], udp: &SerializableUdpPacket) -> Vec<u8> {
+    let mut header = [0; UDP_HEADER_LENGTH];
+    header[0..2].copy_from_slice(&udp.source.to_be_bytes());
+    header[2..4].copy_from_slice(&udp.destination.to_be_bytes());
+    header[4..6].copy_from_slice(&udp.length.to_be_bytes());
+    header[6..8].copy_from_slice(&udp.checksum.to_be_bytes());
+
+    let start = match frame
+        .windows(UDP_HEADER_LENGTH)
+        .position(|window| window == header)
+    {
+        Some(position) => position + UDP_HEADER_LENGTH,
+        None => return vec![],
+    };
+
+    // Ignore the link layer trailer (e.g. Ethernet padding)
+    let length = (udp.length as usize).saturating_sub(UDP_HEADER_LENGTH);
+    let end = frame.len().min(start + length);
+
+    frame[start..end].to_vec()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::transport::SerializableUdpPacket;
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::capture_file::LinkTypes;
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::filtering::PacketsCollection;
+
+    use super::{get_udp_stream_internal, StreamDirectionSummary, StreamDirections};
+
+    #[test]
+    fn datagrams_of_both_directions() {
+        let (client, server, other) = (
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 53),
+            Ipv4Addr::new(10, 0, 0, 2),
+        );
+
+        let mut packets_collection = PacketsCollection::new();
+        let datagrams: [(Ipv4Addr, Ipv4Addr, u16, u16, &[u8]); 4] = [
+            (client, server, 5000, 53, b"query"),
+            (other, server, 5000, 53, b"other"),
+            (server, client, 53, 5000, b"answer"),
+            (client, server, 5001, 53, b"retry"),
+        ];
+
+        for (id, (source, destination, source_port, dest_port, payload)) in
+            datagrams.into_iter().enumerate()
+        {
+            let udp = SerializableUdpPacket {
+                source: source_port,
+                destination: dest_port,
+                length: 8 + payload.len() as u16,
+                checksum: 0x1234,
+                checksum_valid: Some(true),
+            };
+
+            // Ethernet and IPv4 headers, UDP header, payload and Ethernet padding
+            let mut frame = vec![0; 34];
+            frame.extend_from_slice(&source_port.to_be_bytes());
+            frame.extend_from_slice(&dest_port.to_be_bytes());
+            frame.extend_from_slice(&udp.length.to_be_bytes());
+            frame.extend_from_slice(&udp.checksum.to_be_bytes());
+            frame.extend_from_slice(payload);
+            frame.extend_from_slice(&[0; 4]);
+            packets_collection.add_raw_packet(id, LinkTypes::ETHERNET, &frame);
+
+            let template = build_test_parsed_packet(
+                MacAddr::zero(),
+                MacAddr::zero(),
+                source,
+                destination,
+                source_port,
+                dest_port,
+            );
+
+            let mut parsed_packet = ParsedPacket::new(id);
+            parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+            parsed_packet.set_transport_layer_packet(Some(SerializablePacket::UdpPacket(udp)));
+            packets_collection.insert(Arc::new(parsed_packet));
+        }
+
+        let stream = get_udp_stream_internal(
+            (IpAddr::V4(client), 5000),
+            (IpAddr::V4(server), 53),
+            &packets_collection,
+        );
+
+        let datagrams: Vec<(usize, &str, &[u8])> = stream
+            .datagrams
+            .iter()
+            .map(|d| (d.packet_id, d.direction.as_str(), d.payload.as_slice()))
+            .collect();
+        assert_eq!(
+            datagrams,
+            vec![
+                (0, StreamDirections::FORWARD, &b"query"[..]),
+                (2, StreamDirections::REVERSE, &b"answer"[..]),
+            ]
+        );
+        assert_eq!(
+            stream.forward,
+            StreamDirectionSummary {
+                datagrams: 1,
+                bytes: 5
+            }
+        );
+        assert_eq!(stream.reverse.bytes, 6);
+    }
+}
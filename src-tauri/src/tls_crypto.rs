@@ -0,0 +1,206 @@
+//! Cryptographic primitives of the TLS decryption, on top of the RustCrypto crates
+//!
+//! - AES-GCM authenticated decryption, with 128-bit or 256-bit keys and 96-bit nonces
+//! - TLS 1.2 pseudorandom function (RFC 5246, 5), built on HMAC
+//! - TLS 1.3 HKDF-Expand-Label (RFC 8446, 7.1), built on HKDF-Expand
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384};
+
+pub const GCM_TAG_LENGTH: usize = 16;
+const GCM_NONCE_LENGTH: usize = 12;
+
+/// AES-GCM cipher with its expanded key
+#[derive(Clone)]
+pub enum Aes {
+    Aes128(Box<Aes128Gcm>),
+    Aes256(Box<Aes256Gcm>),
+}
+
+impl Aes {
+    /// Cipher of a 128-bit or 256-bit key, `None` for other lengths
+    pub fn new(key: &[u8]) -> Option<Self> {
+        match key.len() {
+            16 => Aes128Gcm::new_from_slice(key)
+                .ok()
+                .map(|cipher| Aes::Aes128(Box::new(cipher))),
+            32 => Aes256Gcm::new_from_slice(key)
+                .ok()
+                .map(|cipher| Aes::Aes256(Box::new(cipher))),
+            _ => None,
+        }
+    }
+}
+
+/// Decrypts the ciphertext followed by its tag, `None` if the tag doesn't authenticate it
+pub fn aes_gcm_open(cipher: &Aes, nonce: &[u8], aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    if nonce.len() != GCM_NONCE_LENGTH {
+        return None;
+    }
+
+    let nonce = Nonce::from_slice(nonce);
+    let payload = Payload { msg: data, aad };
+    match cipher {
+        Aes::Aes128(cipher) => cipher.decrypt(nonce, payload),
+        Aes::Aes256(cipher) => cipher.decrypt(nonce, payload),
+    }
+    .ok()
+}
+
+/// Encrypts the plaintext, followed by its tag
+#[cfg(test)]
+pub fn aes_gcm_seal(cipher: &Aes, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let nonce = Nonce::from_slice(nonce);
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    match cipher {
+        Aes::Aes128(cipher) => cipher.encrypt(nonce, payload),
+        Aes::Aes256(cipher) => cipher.encrypt(nonce, payload),
+    }
+    .unwrap()
+}
+
+/// Hash function of a cipher suite, used by the key derivation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+}
+
+/// TLS 1.2 PRF, the P_hash expansion of the secret with the label and the seed
+pub fn tls12_prf(
+    hash: HashAlgorithm,
+    secret: &[u8],
+    label: &str,
+    seed: &[u8],
+    length: usize,
+) -> Vec<u8> {
+    match hash {
+        HashAlgorithm::Sha256 => p_hash::<Hmac<Sha256>>(secret, &[label.as_bytes(), seed], length),
+        HashAlgorithm::Sha384 => p_hash::<Hmac<Sha384>>(secret, &[label.as_bytes(), seed], length),
+    }
+}
+
+fn p_hash<M: Mac + KeyInit + Clone>(secret: &[u8], seed: &[&[u8]], length: usize) -> Vec<u8> {
+    let hmac = <M as Mac>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    let mut output = Vec::with_capacity(length);
+
+    // A(1), then A(i) = HMAC(secret, A(i - 1))
+    let mut a = seed
+        .iter()
+        .fold(hmac.clone(), |hmac, data| hmac.chain_update(data))
+        .finalize()
+        .into_bytes();
+    while output.len() < length {
+        let block = seed
+            .iter()
+            .fold(hmac.clone().chain_update(&a), |hmac, data| {
+                hmac.chain_update(data)
+            });
+        output.extend_from_slice(&block.finalize().into_bytes());
+        a = hmac.clone().chain_update(&a).finalize().into_bytes();
+    }
+    output.truncate(length);
+
+    output
+}
+
+/// TLS 1.3 HKDF-Expand-Label, with an empty context, `None` if the secret is shorter than a hash
+pub fn hkdf_expand_label(
+    hash: HashAlgorithm,
+    secret: &[u8],
+    label: &str,
+    length: usize,
+) -> Option<Vec<u8>> {
+    let label = format!("tls13 {}", label);
+    let mut info = (length as u16).to_be_bytes().to_vec();
+    info.push(label.len() as u8);
+    info.extend_from_slice(label.as_bytes());
+    info.push(0);
+
+    let mut output = vec![0; length];
+    match hash {
+        HashAlgorithm::Sha256 => Hkdf::<Sha256>::from_prk(secret)
+            .ok()?
+            .expand(&info, &mut output),
+        HashAlgorithm::Sha384 => Hkdf::<Sha384>::from_prk(secret)
+            .ok()?
+            .expand(&info, &mut output),
+    }
+    .ok()?;
+
+    Some(output)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{aes_gcm_open, aes_gcm_seal, hkdf_expand_label, tls12_prf, Aes, HashAlgorithm};
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn crypto_test_vectors() {
+        // GCM specification, test case 4
+        let cipher = Aes::new(&from_hex("feffe9928665731c6d6a8f9467308308")).unwrap();
+        let nonce = from_hex("cafebabefacedbaddecaf888");
+        let aad = from_hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = from_hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+            1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        );
+        let sealed = from_hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+            21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+            5bc94fbc3221a5db94fae95ae7121a47",
+        );
+        assert_eq!(aes_gcm_seal(&cipher, &nonce, &aad, &plaintext), sealed);
+        assert_eq!(
+            aes_gcm_open(&cipher, &nonce, &aad, &sealed),
+            Some(plaintext)
+        );
+
+        // Tampered
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(aes_gcm_open(&cipher, &nonce, &aad, &tampered).is_none());
+        assert!(aes_gcm_open(&cipher, &nonce, &aad[1..], &sealed).is_none());
+
+        // P_SHA256 test vector of the TLS working group
+        assert_eq!(
+            tls12_prf(
+                HashAlgorithm::Sha256,
+                &from_hex("9bbe436ba940f017b17652849a71db35"),
+                "test label",
+                &from_hex("a0ba9f936cda311827a6f796ffd5198c"),
+                100
+            ),
+            from_hex(
+                "e3f229ba727be17b8d122620557cd453c2aab21d07c3d495329b52d4e61edb5a\
+                6b301791e90d35c9c9a46b4e14baf9af0fa022f7077def17abfd3797c0564bab\
+                4fbc91666e9def9b97fce34f796789baa48082d122ee42c5a72e5a5110fff701\
+                87347b66"
+            )
+        );
+
+        // RFC 8448, keys of the server handshake traffic
+        let secret = from_hex("b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38");
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &secret, "key", 16).unwrap(),
+            from_hex("3fce516009c21727d0f2e4e86ee403bc")
+        );
+        assert_eq!(
+            hkdf_expand_label(HashAlgorithm::Sha256, &secret, "iv", 12).unwrap(),
+            from_hex("5d313eb2671276ee13000b30")
+        );
+    }
+}
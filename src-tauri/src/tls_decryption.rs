@@ -0,0 +1,877 @@
+//! Decryption of the captured TLS connections, with the secrets logged by the client in an NSS key log file
+//! (the `SSLKEYLOGFILE` of browsers and TLS libraries)
+//!
+//! Each line of the file is a label, the client random of the connection and a secret, in hexadecimal:
+//! - `CLIENT_RANDOM`: TLS 1.2 master secret
+//! - `CLIENT_HANDSHAKE_TRAFFIC_SECRET`, `SERVER_HANDSHAKE_TRAFFIC_SECRET`, `CLIENT_TRAFFIC_SECRET_0`,
+//!   `SERVER_TRAFFIC_SECRET_0`: TLS 1.3 secrets of the handshake and of the application data
+//!
+//! Lines starting with `#` and the other labels are ignored.
+//!
+//! Decryption is opt-in: nothing is decrypted until a key log is loaded, and only the connection asked
+//! with `get_decrypted_tls` is, leaving the collected packets untouched. A connection is decrypted when
+//! the client random of its ClientHello is in the key log and its cipher suite uses AES-GCM; the others
+//! stay opaque. Key updates and 0-RTT data aren't supported, the records following them are reported as
+//! undecrypted. The decrypted application data is parsed again as HTTP/1 or, after the connection preface,
+//! as HTTP/2.
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::Serialize;
+use sniffer_parser::cleanup_sniffing_state;
+use sniffer_parser::http::handle_http_packet;
+use sniffer_parser::http2::{handle_http2_packet, is_http2_connection};
+use sniffer_parser::serializable_packet::application::{CustomHandshakeMessage, CustomTlsMessage};
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+use sniffer_parser::HttpPacketType;
+
+use crate::efficiency::get_endpoints;
+use crate::http2_streams::{build_http2_streams, Http2Stream};
+use crate::tls_crypto::{
+    aes_gcm_open, hkdf_expand_label, tls12_prf, Aes, HashAlgorithm, GCM_TAG_LENGTH,
+};
+use crate::{SniffingError, SniffingState};
+
+const CLIENT_RANDOM_LENGTH: usize = 32;
+const MASTER_SECRET_LENGTH: usize = 48;
+const TLS12_EXPLICIT_NONCE_LENGTH: usize = 8;
+const TLS12_SALT_LENGTH: usize = 4;
+const TLS13_IV_LENGTH: usize = 12;
+/// Records of a direction that may be missing from the capture between two decrypted ones
+const MAX_SKIPPED_RECORDS: u64 = 2;
+
+#[allow(non_snake_case)]
+pub mod KeyLogLabels {
+    pub const CLIENT_RANDOM: &str = "CLIENT_RANDOM";
+    pub const CLIENT_HANDSHAKE_TRAFFIC_SECRET: &str = "CLIENT_HANDSHAKE_TRAFFIC_SECRET";
+    pub const SERVER_HANDSHAKE_TRAFFIC_SECRET: &str = "SERVER_HANDSHAKE_TRAFFIC_SECRET";
+    pub const CLIENT_TRAFFIC_SECRET_0: &str = "CLIENT_TRAFFIC_SECRET_0";
+    pub const SERVER_TRAFFIC_SECRET_0: &str = "SERVER_TRAFFIC_SECRET_0";
+
+    pub const TLS13: [&str; 4] = [
+        CLIENT_HANDSHAKE_TRAFFIC_SECRET,
+        SERVER_HANDSHAKE_TRAFFIC_SECRET,
+        CLIENT_TRAFFIC_SECRET_0,
+        SERVER_TRAFFIC_SECRET_0,
+    ];
+}
+
+#[allow(non_snake_case)]
+mod ContentTypes {
+    pub const CHANGE_CIPHER_SPEC: u8 = 20;
+    pub const ALERT: u8 = 21;
+    pub const HANDSHAKE: u8 = 22;
+    pub const APPLICATION_DATA: u8 = 23;
+}
+
+/// Secrets of a key log file, by client random
+#[derive(Debug, Default)]
+pub struct TlsKeyLog {
+    master_secrets: HashMap<Vec<u8>, Vec<u8>>,
+    traffic_secrets: HashMap<Vec<u8>, HashMap<String, Vec<u8>>>,
+}
+
+impl TlsKeyLog {
+    /// Secrets of the content of a key log file, skipping the lines not valid
+    pub fn parse(content: &str) -> Self {
+        let mut keylog = TlsKeyLog::default();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let (label, client_random, secret) = match fields[..] {
+                [label, client_random, secret] => {
+                    match (decode_hex(client_random), decode_hex(secret)) {
+                        (Some(client_random), Some(secret))
+                            if client_random.len() == CLIENT_RANDOM_LENGTH
+                                && !secret.is_empty() =>
+                        {
+                            (label, client_random, secret)
+                        }
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            if label == KeyLogLabels::CLIENT_RANDOM {
+                if secret.len() == MASTER_SECRET_LENGTH {
+                    keylog.master_secrets.insert(client_random, secret);
+                }
+            } else if KeyLogLabels::TLS13.contains(&label) {
+                keylog
+                    .traffic_secrets
+                    .entry(client_random)
+                    .or_default()
+                    .insert(label.to_owned(), secret);
+            }
+        }
+
+        keylog
+    }
+
+    /// Number of secrets
+    pub fn len(&self) -> usize {
+        self.master_secrets.len()
+            + self
+                .traffic_secrets
+                .values()
+                .map(HashMap::len)
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains(&self, client_random: &[u8]) -> bool {
+        self.master_secrets.contains_key(client_random)
+            || self.traffic_secrets.contains_key(client_random)
+    }
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() || text.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Loads the secrets of an NSS key log file, returning their number, or unloads them with no path
+///
+/// Each call replaces the secrets loaded previously
+#[tauri::command]
+pub fn set_tls_keylog(
+    state: tauri::State<SniffingState>,
+    path: Option<String>,
+) -> Result<usize, SniffingError> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            *state.tls_keylog.lock().unwrap() = None;
+            info!("TLS key log unloaded");
+            return Ok(0);
+        }
+    };
+
+    let content = fs::read_to_string(&path).map_err(|e| {
+        SniffingError::KeyLogAccessFailed(format!("Unable to read {}: {}", path, e))
+    })?;
+
+    let keylog = TlsKeyLog::parse(&content);
+    if keylog.is_empty() {
+        return Err(SniffingError::KeyLogAccessFailed(format!(
+            "No TLS secret in {}",
+            path
+        )));
+    }
+
+    let loaded = keylog.len();
+    *state.tls_keylog.lock().unwrap() = Some(keylog);
+
+    info!("Loaded {} TLS secrets from {}", loaded, path);
+
+    Ok(loaded)
+}
+
+/// Decrypted record of a TLS connection
+#[derive(Serialize, Debug, Clone)]
+pub struct DecryptedTlsRecord {
+    /// ID of the packet completing the record
+    pub packet_id: usize,
+    pub from_client: bool,
+    /// Type of the decrypted content, e.g. `Handshake` or `ApplicationData`
+    pub content_type: String,
+    pub data: Vec<u8>,
+    /// Application data parsed as HTTP, once a whole request, response or HTTP/2 frame is decrypted
+    pub application: Option<SerializablePacket>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DecryptedTlsConnection {
+    pub version: Option<String>,
+    pub cipher_suite: Option<String>,
+    /// Whether the key log has secrets for the client random of the connection
+    pub keys_found: bool,
+    pub records: Vec<DecryptedTlsRecord>,
+    /// Encrypted records that couldn't be decrypted, e.g. without secrets or with an unsupported cipher
+    pub undecrypted: usize,
+    /// Streams rebuilt from the decrypted HTTP/2 frames, if the connection carries HTTP/2
+    pub http2_streams: Vec<Http2Stream>,
+}
+
+type Endpoint = (IpAddr, u16);
+
+/// Decrypts the TLS records exchanged by the two endpoints with the secrets of the loaded key log
+#[tauri::command]
+pub fn get_decrypted_tls(
+    state: tauri::State<SniffingState>,
+    source_ip: String,
+    source_port: u16,
+    dest_ip: String,
+    dest_port: u16,
+) -> Result<DecryptedTlsConnection, SniffingError> {
+    let parse_ip = |ip: &str| {
+        ip.parse::<IpAddr>().map_err(|e| {
+            warn!("Invalid connection IP address {}: {}", ip, e);
+            SniffingError::InvalidIpAddress(format!("Invalid connection IP address {}: {}", ip, e))
+        })
+    };
+
+    let source = (parse_ip(&source_ip)?, source_port);
+    let destination = (parse_ip(&dest_ip)?, dest_port);
+
+    let keylog = state.tls_keylog.lock().unwrap();
+    let keylog = keylog
+        .as_ref()
+        .ok_or_else(|| SniffingError::InvalidConfiguration("No TLS key log loaded".to_owned()))?;
+
+    let packets_collection = state.packets.lock().unwrap();
+    let connection =
+        get_decrypted_tls_internal(keylog, source, destination, &packets_collection.tls_packets);
+
+    info!(
+        "TLS connection {:?} - {:?}: {} records decrypted, {} undecrypted",
+        source,
+        destination,
+        connection.records.len(),
+        connection.undecrypted
+    );
+
+    Ok(connection)
+}
+
+/// AES-GCM suites, with their TLS version, key length and hash
+struct CipherSuite {
+    tls13: bool,
+    key_length: usize,
+    hash: HashAlgorithm,
+}
+
+fn get_cipher_suite(id: u16) -> Option<CipherSuite> {
+    let (tls13, key_length, hash) = match id {
+        // TLS_AES_128_GCM_SHA256
+        0x1301 => (true, 16, HashAlgorithm::Sha256),
+        // TLS_AES_256_GCM_SHA384
+        0x1302 => (true, 32, HashAlgorithm::Sha384),
+        // TLS_{RSA,DHE_RSA,ECDHE_ECDSA,ECDHE_RSA}_WITH_AES_128_GCM_SHA256
+        0x009C | 0x009E | 0xC02B | 0xC02F => (false, 16, HashAlgorithm::Sha256),
+        // TLS_{RSA,DHE_RSA,ECDHE_ECDSA,ECDHE_RSA}_WITH_AES_256_GCM_SHA384
+        0x009D | 0x009F | 0xC02C | 0xC030 => (false, 32, HashAlgorithm::Sha384),
+        _ => return None,
+    };
+
+    Some(CipherSuite {
+        tls13,
+        key_length,
+        hash,
+    })
+}
+
+/// ID of a cipher suite as shown by a ServerHello, e.g. `0x1301(TLS_AES_128_GCM_SHA256)`
+fn parse_cipher_id(cipher: &str) -> Option<u16> {
+    let hex = cipher.strip_prefix("0x")?;
+    let hex = hex.split('(').next()?;
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// Key and IV of the records sent in a direction, for a phase of the connection
+struct RecordKeys {
+    cipher: Aes,
+    /// Implicit part of the nonce in TLS 1.2, IV XORed with the sequence number in TLS 1.3
+    iv: Vec<u8>,
+}
+
+/// Decryption of the records sent in a direction, following their sequence number
+struct DirectionDecrypter {
+    tls13: bool,
+    /// Keys of the handshake and of the application data in TLS 1.3, only the latter in TLS 1.2
+    phases: Vec<RecordKeys>,
+    phase: usize,
+    sequence: u64,
+}
+
+impl DirectionDecrypter {
+    /// Decrypts a record, returning the type and the content of its plaintext
+    fn decrypt(&mut self, content_type: u8, data: &[u8]) -> Option<(u8, Vec<u8>)> {
+        if self.phases.is_empty() {
+            return None;
+        }
+
+        // The next phase starts its sequence numbers again
+        let candidates = [(self.phase, self.sequence), (self.phase + 1, 0)];
+        for (phase, first_sequence) in candidates {
+            if phase >= self.phases.len() {
+                break;
+            }
+
+            for sequence in first_sequence..=first_sequence + MAX_SKIPPED_RECORDS {
+                if let Some(plaintext) = self.open(phase, sequence, content_type, data) {
+                    self.phase = phase;
+                    self.sequence = sequence + 1;
+                    return Some(plaintext);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn open(
+        &self,
+        phase: usize,
+        sequence: u64,
+        content_type: u8,
+        data: &[u8],
+    ) -> Option<(u8, Vec<u8>)> {
+        let keys = &self.phases[phase];
+
+        if self.tls13 {
+            let mut nonce = keys.iv.clone();
+            for (nonce_byte, sequence_byte) in nonce[4..].iter_mut().zip(sequence.to_be_bytes()) {
+                *nonce_byte ^= sequence_byte;
+            }
+
+            let mut aad = vec![ContentTypes::APPLICATION_DATA, 0x03, 0x03];
+            aad.extend_from_slice(&(data.len() as u16).to_be_bytes());
+
+            // Content followed by its type, then by the padding
+            let mut plaintext = aes_gcm_open(&keys.cipher, &nonce, &aad, data)?;
+            let content_end = plaintext.iter().rposition(|byte| *byte != 0)?;
+            let content_type = plaintext[content_end];
+            plaintext.truncate(content_end);
+
+            Some((content_type, plaintext))
+        } else {
+            if data.len() < TLS12_EXPLICIT_NONCE_LENGTH + GCM_TAG_LENGTH {
+                return None;
+            }
+            let (explicit_nonce, data) = data.split_at(TLS12_EXPLICIT_NONCE_LENGTH);
+
+            let mut nonce = keys.iv.clone();
+            nonce.extend_from_slice(explicit_nonce);
+
+            let mut aad = sequence.to_be_bytes().to_vec();
+            aad.extend_from_slice(&[content_type, 0x03, 0x03]);
+            aad.extend_from_slice(&((data.len() - GCM_TAG_LENGTH) as u16).to_be_bytes());
+
+            let plaintext = aes_gcm_open(&keys.cipher, &nonce, &aad, data)?;
+            Some((content_type, plaintext))
+        }
+    }
+}
+
+fn record_keys(key: &[u8], iv: Vec<u8>) -> Option<RecordKeys> {
+    Some(RecordKeys {
+        cipher: Aes::new(key)?,
+        iv,
+    })
+}
+
+/// Decrypters of the client and of the server, from the secrets of the connection
+fn get_decrypters(
+    keylog: &TlsKeyLog,
+    suite: &CipherSuite,
+    client_random: &[u8],
+    server_random: &[u8],
+) -> Option<(DirectionDecrypter, DirectionDecrypter)> {
+    let decrypter = |phases| DirectionDecrypter {
+        tls13: suite.tls13,
+        phases,
+        phase: 0,
+        sequence: 0,
+    };
+
+    if suite.tls13 {
+        let secrets = keylog.traffic_secrets.get(client_random)?;
+        let phases = |labels: [&str; 2]| {
+            labels
+                .iter()
+                .filter_map(|label| secrets.get(*label))
+                .filter_map(|secret| {
+                    record_keys(
+                        &hkdf_expand_label(suite.hash, secret, "key", suite.key_length)?,
+                        hkdf_expand_label(suite.hash, secret, "iv", TLS13_IV_LENGTH)?,
+                    )
+                })
+                .collect::<Vec<RecordKeys>>()
+        };
+
+        Some((
+            decrypter(phases([
+                KeyLogLabels::CLIENT_HANDSHAKE_TRAFFIC_SECRET,
+                KeyLogLabels::CLIENT_TRAFFIC_SECRET_0,
+            ])),
+            decrypter(phases([
+                KeyLogLabels::SERVER_HANDSHAKE_TRAFFIC_SECRET,
+                KeyLogLabels::SERVER_TRAFFIC_SECRET_0,
+            ])),
+        ))
+    } else {
+        let master_secret = keylog.master_secrets.get(client_random)?;
+        let key_length = suite.key_length;
+
+        let seed = [server_random, client_random].concat();
+        let key_block = tls12_prf(
+            suite.hash,
+            master_secret,
+            "key expansion",
+            &seed,
+            2 * key_length + 2 * TLS12_SALT_LENGTH,
+        );
+        let (keys, salts) = key_block.split_at(2 * key_length);
+        let (client_key, server_key) = keys.split_at(key_length);
+        let (client_salt, server_salt) = salts.split_at(TLS12_SALT_LENGTH);
+
+        Some((
+            decrypter(
+                record_keys(client_key, client_salt.to_vec())
+                    .into_iter()
+                    .collect(),
+            ),
+            decrypter(
+                record_keys(server_key, server_salt.to_vec())
+                    .into_iter()
+                    .collect(),
+            ),
+        ))
+    }
+}
+
+fn get_content_type_name(content_type: u8) -> String {
+    match content_type {
+        ContentTypes::CHANGE_CIPHER_SPEC => "ChangeCipherSpec".to_owned(),
+        ContentTypes::ALERT => "Alert".to_owned(),
+        ContentTypes::HANDSHAKE => "Handshake".to_owned(),
+        ContentTypes::APPLICATION_DATA => "ApplicationData".to_owned(),
+        other => format!("Unknown({})", other),
+    }
+}
+
+fn get_content_type(name: &str) -> Option<u8> {
+    match name {
+        "ChangeCipherSpec" => Some(ContentTypes::CHANGE_CIPHER_SPEC),
+        "Alert" => Some(ContentTypes::ALERT),
+        "Handshake" => Some(ContentTypes::HANDSHAKE),
+        "ApplicationData" => Some(ContentTypes::APPLICATION_DATA),
+        _ => None,
+    }
+}
+
+/// Encrypted record of a connection, as sent by the endpoint
+struct EncryptedRecord<'a> {
+    packet_id: usize,
+    endpoints: (Endpoint, Endpoint),
+    content_type: u8,
+    data: &'a [u8],
+}
+
+pub(crate) fn get_decrypted_tls_internal(
+    keylog: &TlsKeyLog,
+    source: Endpoint,
+    destination: Endpoint,
+    tls_packets: &[Arc<ParsedPacket>],
+) -> DecryptedTlsConnection {
+    let mut connection = DecryptedTlsConnection::default();
+    let mut client = None;
+    let mut client_random = None;
+    let mut server_random = None;
+    let mut encrypted_records = vec![];
+
+    for packet in tls_packets {
+        let endpoints = match get_endpoints(packet) {
+            Some(endpoints)
+                if endpoints == (source, destination) || endpoints == (destination, source) =>
+            {
+                endpoints
+            }
+            _ => continue,
+        };
+
+        let tls_packet = match packet.get_application_layer_packet() {
+            Some(SerializablePacket::TlsPacket(tls_packet)) => tls_packet,
+            _ => continue,
+        };
+
+        for message in &tls_packet.messages {
+            match message {
+                CustomTlsMessage::Handshake(CustomHandshakeMessage::ClientHello(client_hello)) => {
+                    client = Some(endpoints.0);
+                    client_random = Some(
+                        [
+                            client_hello.rand_time.to_be_bytes().as_slice(),
+                            &client_hello.rand_data,
+                        ]
+                        .concat(),
+                    );
+                }
+                CustomTlsMessage::Handshake(CustomHandshakeMessage::ServerHello(server_hello)) => {
+                    server_random = Some(
+                        [
+                            server_hello.rand_time.to_be_bytes().as_slice(),
+                            &server_hello.rand_data,
+                        ]
+                        .concat(),
+                    );
+                    connection.cipher_suite = Some(server_hello.cipher.clone());
+                    connection.version = Some(server_hello.version.clone());
+                }
+                CustomTlsMessage::ApplicationData(application_data) => {
+                    encrypted_records.push(EncryptedRecord {
+                        packet_id: packet.get_id(),
+                        endpoints,
+                        content_type: ContentTypes::APPLICATION_DATA,
+                        data: &application_data.data,
+                    });
+                }
+                CustomTlsMessage::Encrypted(encrypted) => {
+                    if let Some(content_type) = get_content_type(&encrypted.message_type) {
+                        encrypted_records.push(EncryptedRecord {
+                            packet_id: packet.get_id(),
+                            endpoints,
+                            content_type,
+                            data: &encrypted.data,
+                        });
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    let suite = connection
+        .cipher_suite
+        .as_deref()
+        .and_then(parse_cipher_id)
+        .and_then(get_cipher_suite);
+    if let Some(suite) = &suite {
+        // The ServerHello of TLS 1.3 claims TLS 1.2, for compatibility
+        connection.version = Some(if suite.tls13 { "TLS 1.3" } else { "TLS 1.2" }.to_owned());
+    }
+
+    connection.keys_found = client_random
+        .as_ref()
+        .map_or(false, |client_random| keylog.contains(client_random));
+
+    let decrypters = match (&suite, &client_random, &server_random) {
+        (Some(suite), Some(client_random), Some(server_random)) => {
+            get_decrypters(keylog, suite, client_random, server_random)
+        }
+        _ => None,
+    };
+    let (mut client_decrypter, mut server_decrypter) = match decrypters {
+        Some(decrypters) => decrypters,
+        None => {
+            connection.undecrypted = encrypted_records.len();
+            return connection;
+        }
+    };
+
+    cleanup_sniffing_state();
+    for record in encrypted_records {
+        let from_client = Some(record.endpoints.0) == client;
+        let decrypter = if from_client {
+            &mut client_decrypter
+        } else {
+            &mut server_decrypter
+        };
+
+        let (content_type, data) = match decrypter.decrypt(record.content_type, record.data) {
+            Some(plaintext) => plaintext,
+            None => {
+                connection.undecrypted += 1;
+                continue;
+            }
+        };
+
+        let application = if content_type == ContentTypes::APPLICATION_DATA {
+            get_http_packet(record.packet_id, record.endpoints, from_client, &data)
+        } else {
+            None
+        };
+
+        connection.records.push(DecryptedTlsRecord {
+            packet_id: record.packet_id,
+            from_client,
+            content_type: get_content_type_name(content_type),
+            data,
+            application,
+        });
+    }
+    cleanup_sniffing_state();
+
+    connection.http2_streams = build_http2_streams(connection.records.iter().filter_map(
+        |record| match &record.application {
+            Some(SerializablePacket::Http2Packet(http2_packet)) => {
+                Some((record.packet_id, record.from_client, http2_packet))
+            }
+            _ => None,
+        },
+    ));
+
+    connection
+}
+
+/// HTTP request, response or HTTP/2 frames completed by the decrypted application data
+fn get_http_packet(
+    packet_id: usize,
+    ((source_ip, source_port), (dest_ip, dest_port)): (Endpoint, Endpoint),
+    from_client: bool,
+    data: &[u8],
+) -> Option<SerializablePacket> {
+    let mut parsed_packet = ParsedPacket::new(packet_id);
+    if is_http2_connection(source_ip, source_port, dest_ip, dest_port, data) {
+        handle_http2_packet(
+            source_ip,
+            source_port,
+            dest_ip,
+            dest_port,
+            data,
+            &mut parsed_packet,
+        );
+        return parsed_packet.get_application_layer_packet().cloned();
+    }
+
+    let http_type = if from_client {
+        HttpPacketType::Request
+    } else {
+        HttpPacketType::Response
+    };
+
+    handle_http_packet(
+        source_ip,
+        source_port,
+        dest_ip,
+        dest_port,
+        http_type,
+        false,
+        data,
+        &mut parsed_packet,
+    );
+
+    parsed_packet.get_application_layer_packet().cloned()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+
+    use pnet::util::MacAddr;
+    use sniffer_parser::serializable_packet::application::{
+        ClientHelloMessage, CustomApplicationDataMessage, CustomHandshakeMessage, CustomTlsMessage,
+        Ja3Fingerprint, SerializableTlsPacket, ServerHelloMessage,
+    };
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use crate::filtering::tests::build_test_parsed_packet;
+    use crate::tls_crypto::{aes_gcm_seal, hkdf_expand_label, Aes, HashAlgorithm};
+
+    use super::{get_decrypted_tls_internal, TlsKeyLog};
+
+    const CLIENT: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const SERVER: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 80);
+
+    fn build_tls_packet(
+        id: usize,
+        from_client: bool,
+        messages: Vec<CustomTlsMessage>,
+    ) -> ParsedPacket {
+        let template = if from_client {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), CLIENT, SERVER, 4444, 443)
+        } else {
+            build_test_parsed_packet(MacAddr::zero(), MacAddr::zero(), SERVER, CLIENT, 443, 4444)
+        };
+
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_network_layer_packet(template.get_network_layer_packet().cloned());
+        parsed_packet.set_transport_layer_packet(template.get_transport_layer_packet().cloned());
+
+        let mut tls_packet = SerializableTlsPacket::default();
+        tls_packet.set_messages(messages);
+        parsed_packet.set_application_layer_packet(Some(SerializablePacket::TlsPacket(tls_packet)));
+        parsed_packet
+    }
+
+    /// TLS 1.3 record of the content, encrypted with the keys of the secret
+    fn seal_record(
+        secret: &[u8],
+        sequence: u64,
+        content_type: u8,
+        content: &[u8],
+    ) -> CustomTlsMessage {
+        let hash = HashAlgorithm::Sha256;
+        let cipher = Aes::new(&hkdf_expand_label(hash, secret, "key", 16).unwrap()).unwrap();
+        let mut nonce = hkdf_expand_label(hash, secret, "iv", 12).unwrap();
+        for (nonce_byte, sequence_byte) in nonce[4..].iter_mut().zip(sequence.to_be_bytes()) {
+            *nonce_byte ^= sequence_byte;
+        }
+
+        // Content type followed by padding
+        let mut plaintext = content.to_vec();
+        plaintext.extend_from_slice(&[content_type, 0, 0]);
+        let length = (plaintext.len() + 16) as u16;
+        let aad = [&[0x17, 0x03, 0x03], length.to_be_bytes().as_slice()].concat();
+
+        CustomTlsMessage::ApplicationData(CustomApplicationDataMessage {
+            data: aes_gcm_seal(&cipher, &nonce, &aad, &plaintext),
+        })
+    }
+
+    #[test]
+    fn keylog_parsed() {
+        let client_random = "0102030405060708091011121314151617181920212223242526272829303132";
+        let content = format!(
+            "# SSL/TLS secrets log file\n\
+            CLIENT_RANDOM {0} {1}\n\
+            CLIENT_TRAFFIC_SECRET_0 {0} {2}\n\
+            SERVER_TRAFFIC_SECRET_0 {0} {2}\n\
+            EXPORTER_SECRET {0} {2}\n\
+            CLIENT_RANDOM 0102 {1}\n\
+            CLIENT_RANDOM {0} zz\n\
+            CLIENT_RANDOM {0}\n",
+            client_random,
+            "ab".repeat(48),
+            "cd".repeat(32)
+        );
+
+        let keylog = TlsKeyLog::parse(&content);
+        assert_eq!(keylog.len(), 3);
+        assert!(keylog.contains(&super::decode_hex(client_random).unwrap()));
+        assert!(TlsKeyLog::parse("# Empty\n\n").is_empty());
+    }
+
+    #[test]
+    fn tls13_connection_decrypted() {
+        let client_random = [vec![1, 2, 3, 4], vec![5; 28]].concat();
+        let secrets = [[0x11; 32], [0x22; 32], [0x33; 32], [0x44; 32]];
+        let keylog = TlsKeyLog::parse(
+            &[
+                "CLIENT_HANDSHAKE_TRAFFIC_SECRET",
+                "SERVER_HANDSHAKE_TRAFFIC_SECRET",
+                "CLIENT_TRAFFIC_SECRET_0",
+                "SERVER_TRAFFIC_SECRET_0",
+            ]
+            .iter()
+            .zip(&secrets)
+            .map(|(label, secret)| {
+                let hex = |bytes: &[u8]| {
+                    bytes
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>()
+                };
+                format!("{} {} {}\n", label, hex(&client_random), hex(secret))
+            })
+            .collect::<String>(),
+        );
+
+        let client_hello = ClientHelloMessage {
+            version: "Tls12".to_owned(),
+            rand_time: 0x01020304,
+            rand_data: vec![5; 28],
+            session_id: None,
+            ciphers: vec![],
+            compressions: vec![],
+            extensions: vec![],
+            ja3: Ja3Fingerprint::new(String::new()),
+        };
+        let server_hello = ServerHelloMessage {
+            version: "Tls12".to_owned(),
+            rand_time: 0,
+            rand_data: vec![9; 28],
+            session_id: None,
+            cipher: "0x1301(TLS_AES_128_GCM_SHA256)".to_owned(),
+            compression: "Null".to_owned(),
+            extensions: vec![],
+            ja3s: Ja3Fingerprint::new(String::new()),
+        };
+
+        let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let tls_packets = [
+            build_tls_packet(
+                0,
+                true,
+                vec![CustomTlsMessage::Handshake(
+                    CustomHandshakeMessage::ClientHello(client_hello),
+                )],
+            ),
+            build_tls_packet(
+                1,
+                false,
+                vec![
+                    CustomTlsMessage::Handshake(CustomHandshakeMessage::ServerHello(server_hello)),
+                    CustomTlsMessage::ChangeCipherSpec,
+                    seal_record(&secrets[1], 0, 22, b"extensions"),
+                    seal_record(&secrets[1], 1, 22, b"finished"),
+                ],
+            ),
+            build_tls_packet(2, true, vec![seal_record(&secrets[0], 0, 22, b"finished")]),
+            build_tls_packet(3, true, vec![seal_record(&secrets[2], 0, 23, request)]),
+            // The second record of the server is missing
+            build_tls_packet(
+                4,
+                false,
+                vec![
+                    seal_record(&secrets[3], 1, 23, response),
+                    seal_record(&secrets[0], 2, 23, b"unknown keys"),
+                ],
+            ),
+        ]
+        .map(Arc::new);
+
+        let client = (IpAddr::V4(CLIENT), 4444);
+        let server = (IpAddr::V4(SERVER), 443);
+        let connection = get_decrypted_tls_internal(&keylog, server, client, &tls_packets);
+
+        assert_eq!(connection.version.as_deref(), Some("TLS 1.3"));
+        assert!(connection.keys_found);
+        assert_eq!(connection.undecrypted, 1);
+        assert_eq!(
+            connection
+                .records
+                .iter()
+                .map(|record| (
+                    record.packet_id,
+                    record.from_client,
+                    record.content_type.as_str(),
+                    record.data.as_slice()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, false, "Handshake", b"extensions".as_slice()),
+                (1, false, "Handshake", b"finished".as_slice()),
+                (2, true, "Handshake", b"finished".as_slice()),
+                (3, true, "ApplicationData", request.as_slice()),
+                (4, false, "ApplicationData", response.as_slice()),
+            ]
+        );
+        assert!(matches!(
+            connection.records[3].application,
+            Some(SerializablePacket::HttpRequestPacket(_))
+        ));
+        assert!(matches!(
+            connection.records[4].application,
+            Some(SerializablePacket::HttpResponsePacket(_))
+        ));
+
+        // Without secrets of the connection
+        let connection =
+            get_decrypted_tls_internal(&TlsKeyLog::default(), client, server, &tls_packets);
+        assert!(!connection.keys_found);
+        assert!(connection.records.is_empty());
+        assert_eq!(connection.undecrypted, 6);
+    }
+}
@@ -0,0 +1,286 @@
+//! Linux capture through a memory-mapped AF_PACKET ring (TPACKET_V3)
+//!
+//! The kernel writes the frames in a ring of blocks shared with the process: each block holds all the
+//! frames received until it's full or its timeout expires, and is then handed over to the process, which
+//! reads them in place and gives the block back. Compared to the default read loop, there is a system
+//! call (`poll`) only when no block is ready, and no copy but the one into the frame queue.
+
+use std::io::{self, ErrorKind};
+use std::mem;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::ptr::{self, addr_of, addr_of_mut};
+use std::slice;
+use std::sync::atomic::{fence, Ordering};
+use std::time::Duration;
+
+/// Size of a block, a multiple of the page size
+const BLOCK_SIZE: usize = 1 << 20;
+const BLOCK_NR: usize = 64;
+/// Only used by the kernel to validate the ring size, frames are packed in the blocks
+const FRAME_SIZE: usize = 2048;
+/// Time after which a partially filled block is handed over, in milliseconds
+const BLOCK_TIMEOUT: c_uint = 10;
+
+// Definitions of linux/if_packet.h
+const PACKET_ADD_MEMBERSHIP: c_int = 1;
+const PACKET_RX_RING: c_int = 5;
+const PACKET_VERSION: c_int = 10;
+const PACKET_MR_PROMISC: u16 = 1;
+const TPACKET_V3: c_int = 2;
+const TP_STATUS_KERNEL: u32 = 0;
+const TP_STATUS_USER: u32 = 1;
+
+#[repr(C)]
+struct TpacketReq3 {
+    tp_block_size: c_uint,
+    tp_block_nr: c_uint,
+    tp_frame_size: c_uint,
+    tp_frame_nr: c_uint,
+    tp_retire_blk_tov: c_uint,
+    tp_sizeof_priv: c_uint,
+    tp_feature_req_word: c_uint,
+}
+
+/// Leading fields of `tpacket_block_desc`, with the `tpacket_hdr_v1` header
+#[repr(C)]
+#[allow(dead_code)]
+struct TpacketBlockDesc {
+    version: u32,
+    offset_to_priv: u32,
+    block_status: u32,
+    num_pkts: u32,
+    offset_to_first_pkt: u32,
+    blk_len: u32,
+}
+
+/// Leading fields of `tpacket3_hdr`
+#[repr(C)]
+#[allow(dead_code)]
+struct Tpacket3Hdr {
+    tp_next_offset: u32,
+    tp_sec: u32,
+    tp_nsec: u32,
+    tp_snaplen: u32,
+    tp_len: u32,
+    tp_status: u32,
+    tp_mac: u16,
+    tp_net: u16,
+}
+
+#[repr(C)]
+struct PacketMreq {
+    mr_ifindex: c_int,
+    mr_type: u16,
+    mr_alen: u16,
+    mr_address: [u8; 8],
+}
+
+/// Receive ring of an AF_PACKET socket bound to an interface
+pub struct TpacketRing {
+    fd: c_int,
+    map: *mut u8,
+    /// Poll timeout, in milliseconds
+    read_timeout: c_int,
+    /// Block being read, or to be read next
+    block: usize,
+    /// Offset of the next frame in the block being read and number of frames left
+    cursor: Option<(usize, u32)>,
+}
+
+// The ring is only accessed by the thread owning it
+unsafe impl Send for TpacketRing {}
+
+impl TpacketRing {
+    /// Opens the ring on the interface with index `interface_index`, optionally in promiscuous mode
+    ///
+    /// `read_timeout` bounds the time `next` waits for a frame
+    pub fn open(
+        interface_index: u32,
+        promiscuous: bool,
+        read_timeout: Duration,
+    ) -> io::Result<Self> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+        let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, protocol as c_int) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // From now on closed on errors when dropped
+        let mut ring = TpacketRing {
+            fd,
+            map: ptr::null_mut(),
+            read_timeout: read_timeout.as_millis().min(c_int::MAX as u128) as c_int,
+            block: 0,
+            cursor: None,
+        };
+
+        set_option(fd, PACKET_VERSION, &TPACKET_V3)?;
+        let request = TpacketReq3 {
+            tp_block_size: BLOCK_SIZE as c_uint,
+            tp_block_nr: BLOCK_NR as c_uint,
+            tp_frame_size: FRAME_SIZE as c_uint,
+            tp_frame_nr: (BLOCK_SIZE * BLOCK_NR / FRAME_SIZE) as c_uint,
+            tp_retire_blk_tov: BLOCK_TIMEOUT,
+            tp_sizeof_priv: 0,
+            tp_feature_req_word: 0,
+        };
+        set_option(fd, PACKET_RX_RING, &request)?;
+
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                BLOCK_SIZE * BLOCK_NR,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        ring.map = map as *mut u8;
+
+        let mut address: libc::sockaddr_ll = unsafe { mem::zeroed() };
+        address.sll_family = libc::AF_PACKET as u16;
+        address.sll_protocol = protocol;
+        address.sll_ifindex = interface_index as c_int;
+        let result = unsafe {
+            libc::bind(
+                fd,
+                addr_of!(address) as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if promiscuous {
+            let membership = PacketMreq {
+                mr_ifindex: interface_index as c_int,
+                mr_type: PACKET_MR_PROMISC,
+                mr_alen: 0,
+                mr_address: [0; 8],
+            };
+            set_option(fd, PACKET_ADD_MEMBERSHIP, &membership)?;
+        }
+
+        Ok(ring)
+    }
+
+    /// Next frame, with its capture timestamp in microseconds since the Unix epoch
+    ///
+    /// Fails with `TimedOut` when no frame is received within the read timeout. The frame is valid until
+    /// the next call, which can hand its block back to the kernel
+    pub fn next(&mut self) -> io::Result<(&[u8], i64)> {
+        if let Some((_, 0)) = self.cursor {
+            self.release_block();
+        }
+
+        if self.cursor.is_none() {
+            if !self.is_block_ready() {
+                self.wait_block()?;
+                if !self.is_block_ready() {
+                    return Err(io::Error::new(ErrorKind::TimedOut, "No frame received"));
+                }
+            }
+            // Read the frames only after the status written by the kernel
+            fence(Ordering::Acquire);
+
+            let block = self.get_block() as *const TpacketBlockDesc;
+            let (offset, frames) =
+                unsafe { ((*block).offset_to_first_pkt as usize, (*block).num_pkts) };
+            if frames == 0 {
+                self.release_block();
+                return Err(io::Error::new(ErrorKind::TimedOut, "No frame received"));
+            }
+            self.cursor = Some((offset, frames));
+        }
+
+        let (offset, frames) = self.cursor.unwrap();
+        let frame = unsafe { self.get_block().add(offset) };
+        let header = unsafe { &*(frame as *const Tpacket3Hdr) };
+        self.cursor = Some((offset + header.tp_next_offset as usize, frames - 1));
+
+        let data = unsafe {
+            slice::from_raw_parts(
+                frame.add(header.tp_mac as usize),
+                header.tp_snaplen as usize,
+            )
+        };
+        let timestamp = header.tp_sec as i64 * 1_000_000 + header.tp_nsec as i64 / 1000;
+
+        Ok((data, timestamp))
+    }
+
+    fn get_block(&self) -> *mut u8 {
+        unsafe { self.map.add(self.block * BLOCK_SIZE) }
+    }
+
+    fn is_block_ready(&self) -> bool {
+        let block = self.get_block() as *const TpacketBlockDesc;
+        let status = unsafe { ptr::read_volatile(addr_of!((*block).block_status)) };
+        status & TP_STATUS_USER != 0
+    }
+
+    /// Waits until a block is ready, or the read timeout expires
+    fn wait_block(&self) -> io::Result<()> {
+        let mut poll_fd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN | libc::POLLERR,
+            revents: 0,
+        };
+
+        match unsafe { libc::poll(&mut poll_fd, 1, self.read_timeout) } {
+            result if result < 0 => {
+                let error = io::Error::last_os_error();
+                match error.kind() {
+                    ErrorKind::Interrupted => Ok(()),
+                    _ => Err(error),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Hands the current block back to the kernel and moves to the next one
+    fn release_block(&mut self) {
+        // The frames must be read before the kernel can overwrite them
+        fence(Ordering::Release);
+        let block = self.get_block() as *mut TpacketBlockDesc;
+        unsafe { ptr::write_volatile(addr_of_mut!((*block).block_status), TP_STATUS_KERNEL) };
+
+        self.block = (self.block + 1) % BLOCK_NR;
+        self.cursor = None;
+    }
+}
+
+impl Drop for TpacketRing {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.map.is_null() {
+                libc::munmap(self.map as *mut c_void, BLOCK_SIZE * BLOCK_NR);
+            }
+            libc::close(self.fd);
+        }
+    }
+}
+
+fn set_option<T>(fd: c_int, option: c_int, value: &T) -> io::Result<()> {
+    let result = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_PACKET,
+            option,
+            value as *const T as *const c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+//! Paths of the traceroutes run over IPv4 while capturing
+//!
+//! Probes are UDP datagrams and ICMP echo requests, matched to the ICMP errors they cause through the
+//! original packet carried by the errors:
+//! - Time Exceeded: sent by the router where the TTL of the probe expired, i.e. the hop at distance TTL
+//! - Destination Unreachable (e.g. Port Unreachable for UDP probes) and Echo Reply: sent by the
+//!   destination, the path ends there
+//!
+//! Only the sources and destinations with at least one Time Exceeded response are reported, so that
+//! the regular UDP and ping traffic is left out
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use serde::Serialize;
+use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+use crate::{SniffingError, SniffingState};
+
+/// Highest TTL considered a probe, as the default maximum number of hops of traceroute tools
+const MAX_HOPS: u8 = 30;
+
+#[allow(non_snake_case)]
+pub mod ProbeProtocols {
+    pub const UDP: &str = "UDP";
+    pub const ICMP: &str = "ICMP";
+}
+
+/// Probes sent with the same TTL and the routers that answered them
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TracerouteHop {
+    pub ttl: u8,
+    /// Addresses of the responders, in order of first response (more than one when load balanced)
+    pub addresses: Vec<String>,
+    /// Round-trip times of the answered probes, in microseconds
+    pub rtts: Vec<i64>,
+    pub probes: usize,
+}
+
+/// Hops from a source to a destination, ordered by TTL
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TraceroutePath {
+    pub source: String,
+    pub destination: String,
+    pub protocol: String,
+    pub hops: Vec<TracerouteHop>,
+    /// Whether the destination answered
+    pub reached: bool,
+}
+
+/// Returns the paths of the traceroutes found among the collected packets
+#[tauri::command]
+pub fn get_traceroute_paths(
+    state: tauri::State<SniffingState>,
+) -> Result<Vec<TraceroutePath>, SniffingError> {
+    let packets_collection = state.packets.lock().unwrap();
+
+    Ok(get_traceroute_paths_internal(&packets_collection.packets))
+}
+
+/// Fields identifying a probe both in the probe itself and in the original packet of the responses
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ProbeKey {
+    /// Source, destination, ports and IP identification
+    Udp(Ipv4Addr, Ipv4Addr, u16, u16, u16),
+    /// Source, destination, identifier and sequence number
+    Echo(Ipv4Addr, Ipv4Addr, u16, u16),
+}
+
+struct Probe {
+    ttl: u8,
+    timestamp: i64,
+}
+
+#[derive(Default)]
+struct PathState {
+    hops: BTreeMap<u8, TracerouteHop>,
+    time_exceeded: bool,
+    /// Lowest TTL answered by the destination
+    reached_ttl: Option<u8>,
+}
+
+fn get_traceroute_paths_internal(packets: &[Arc<ParsedPacket>]) -> Vec<TraceroutePath> {
+    let mut probes: HashMap<ProbeKey, Probe> = HashMap::new();
+    let mut paths: BTreeMap<(Ipv4Addr, Ipv4Addr, &'static str), PathState> = BTreeMap::new();
+
+    for packet in packets {
+        let ipv4_packet = match packet.get_network_layer_packet() {
+            Some(SerializablePacket::Ipv4Packet(ipv4_packet)) => ipv4_packet,
+            _ => continue,
+        };
+        let (source, destination) = (ipv4_packet.source, ipv4_packet.destination);
+
+        // Probes
+        let probe_key = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::UdpPacket(udp_packet)) => Some((
+                ProbeKey::Udp(
+                    source,
+                    destination,
+                    udp_packet.source,
+                    udp_packet.destination,
+                    ipv4_packet.identification,
+                ),
+                ProbeProtocols::UDP,
+            )),
+            Some(SerializablePacket::EchoRequestPacket(echo_request)) => Some((
+                ProbeKey::Echo(
+                    source,
+                    destination,
+                    echo_request.identifier,
+                    echo_request.sequence_number,
+                ),
+                ProbeProtocols::ICMP,
+            )),
+            _ => None,
+        };
+
+        if let Some((key, protocol)) = probe_key {
+            if ipv4_packet.ttl <= MAX_HOPS {
+                probes.insert(
+                    key,
+                    Probe {
+                        ttl: ipv4_packet.ttl,
+                        timestamp: packet.get_timestamp(),
+                    },
+                );
+                get_hop(&mut paths, (source, destination, protocol), ipv4_packet.ttl).probes += 1;
+            }
+            continue;
+        }
+
+        // Responses
+        let (key, protocol, time_exceeded) = match packet.get_transport_layer_packet() {
+            Some(SerializablePacket::IcmpPacket(icmp_packet)) => {
+                let original = match &icmp_packet.original {
+                    Some(original) => original,
+                    None => continue,
+                };
+                let time_exceeded = icmp_packet.icmp_type.starts_with("TimeExceeded");
+                if !time_exceeded && !icmp_packet.icmp_type.starts_with("DestinationUnreachable") {
+                    continue;
+                }
+
+                match (
+                    original.source_port.zip(original.destination_port),
+                    original.echo_identifier.zip(original.echo_sequence_number),
+                ) {
+                    (Some((source_port, destination_port)), _) => (
+                        ProbeKey::Udp(
+                            original.source,
+                            original.destination,
+                            source_port,
+                            destination_port,
+                            original.identification,
+                        ),
+                        ProbeProtocols::UDP,
+                        time_exceeded,
+                    ),
+                    (_, Some((identifier, sequence_number))) => (
+                        ProbeKey::Echo(
+                            original.source,
+                            original.destination,
+                            identifier,
+                            sequence_number,
+                        ),
+                        ProbeProtocols::ICMP,
+                        time_exceeded,
+                    ),
+                    _ => continue,
+                }
+            }
+            Some(SerializablePacket::EchoReplyPacket(echo_reply)) => (
+                ProbeKey::Echo(
+                    destination,
+                    source,
+                    echo_reply.identifier,
+                    echo_reply.sequence_number,
+                ),
+                ProbeProtocols::ICMP,
+                false,
+            ),
+            _ => continue,
+        };
+
+        let (probe_source, probe_destination) = match &key {
+            ProbeKey::Udp(source, destination, ..) | ProbeKey::Echo(source, destination, ..) => {
+                (*source, *destination)
+            }
+        };
+
+        // Only the first response of each probe counts
+        let probe = match probes.remove(&key) {
+            Some(probe) => probe,
+            None => continue,
+        };
+
+        let path = paths
+            .entry((probe_source, probe_destination, protocol))
+            .or_default();
+        path.time_exceeded |= time_exceeded;
+        if !time_exceeded && source == probe_destination {
+            path.reached_ttl = Some(path.reached_ttl.map_or(probe.ttl, |ttl| ttl.min(probe.ttl)));
+        }
+
+        let hop = get_hop(
+            &mut paths,
+            (probe_source, probe_destination, protocol),
+            probe.ttl,
+        );
+        let responder = source.to_string();
+        if !hop.addresses.contains(&responder) {
+            hop.addresses.push(responder);
+        }
+        hop.rtts.push(packet.get_timestamp() - probe.timestamp);
+    }
+
+    paths
+        .into_iter()
+        .filter(|(_, path)| path.time_exceeded)
+        .map(|((source, destination, protocol), path)| TraceroutePath {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            protocol: protocol.to_owned(),
+            // Probes beyond the destination are answered by the destination too
+            hops: path
+                .hops
+                .into_values()
+                .filter(|hop| path.reached_ttl.map_or(true, |ttl| hop.ttl <= ttl))
+                .collect(),
+            reached: path.reached_ttl.is_some(),
+        })
+        .collect()
+}
+
+fn get_hop<'a>(
+    paths: &'a mut BTreeMap<(Ipv4Addr, Ipv4Addr, &'static str), PathState>,
+    key: (Ipv4Addr, Ipv4Addr, &'static str),
+    ttl: u8,
+) -> &'a mut TracerouteHop {
+    paths
+        .entry(key)
+        .or_default()
+        .hops
+        .entry(ttl)
+        .or_insert_with(|| TracerouteHop {
+            ttl,
+            addresses: vec![],
+            rtts: vec![],
+            probes: 0,
+        })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    use sniffer_parser::serializable_packet::network::SerializableIpv4Packet;
+    use sniffer_parser::serializable_packet::transport::{
+        SerializableIcmpOriginalPacket, SerializableIcmpPacket, SerializableUdpPacket,
+    };
+    use sniffer_parser::serializable_packet::{ParsedPacket, SerializablePacket};
+
+    use super::{get_traceroute_paths_internal, ProbeProtocols};
+
+    const CLIENT: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const TARGET: Ipv4Addr = Ipv4Addr::new(8, 8, 8, 8);
+
+    fn build_ipv4_packet(
+        id: usize,
+        timestamp: i64,
+        source: Ipv4Addr,
+        destination: Ipv4Addr,
+        ttl: u8,
+        identification: u16,
+        transport: SerializablePacket,
+    ) -> Arc<ParsedPacket> {
+        let mut parsed_packet = ParsedPacket::new(id);
+        parsed_packet.set_timestamp(timestamp);
+        parsed_packet.set_network_layer_packet(Some(SerializablePacket::Ipv4Packet(
+            SerializableIpv4Packet {
+                version: 4,
+                header_length: 5,
+                dscp: 0,
+                ecn: 0,
+                total_length: 60,
+                identification,
+                flags: 0,
+                fragment_offset: 0,
+                ttl,
+                next_level_protocol: "Udp".to_owned(),
+                checksum: 0,
+                checksum_valid: None,
+                source,
+                destination,
+                parsed_options: vec![],
+                length: 40,
+            },
+        )));
+        parsed_packet.set_transport_layer_packet(Some(transport));
+
+        Arc::new(parsed_packet)
+    }
+
+    fn build_probe(id: usize, timestamp: i64, ttl: u8) -> Arc<ParsedPacket> {
+        build_ipv4_packet(
+            id,
+            timestamp,
+            CLIENT,
+            TARGET,
+            ttl,
+            id as u16,
+            SerializablePacket::UdpPacket(SerializableUdpPacket {
+                source: 40000,
+                destination: 33434 + id as u16,
+                length: 40,
+                checksum: 0,
+                checksum_valid: None,
+            }),
+        )
+    }
+
+    fn build_response(
+        id: usize,
+        timestamp: i64,
+        responder: Ipv4Addr,
+        icmp_type: &str,
+        probe_id: usize,
+    ) -> Arc<ParsedPacket> {
+        build_ipv4_packet(
+            id,
+            timestamp,
+            responder,
+            CLIENT,
+            64,
+            0,
+            SerializablePacket::IcmpPacket(SerializableIcmpPacket {
+                icmp_type: icmp_type.to_owned(),
+                icmp_code: 0,
+                checksum: 0,
+                checksum_valid: None,
+                original: Some(SerializableIcmpOriginalPacket {
+                    source: CLIENT,
+                    destination: TARGET,
+                    identification: probe_id as u16,
+                    ttl: 1,
+                    protocol: 17,
+                    source_port: Some(40000),
+                    destination_port: Some(33434 + probe_id as u16),
+                    echo_identifier: None,
+                    echo_sequence_number: None,
+                }),
+                length: 36,
+            }),
+        )
+    }
+
+    #[test]
+    fn udp_traceroute_path() {
+        let router = Ipv4Addr::new(10, 0, 0, 254);
+        let packets = vec![
+            build_probe(0, 0, 1),
+            build_probe(1, 10, 1),
+            build_response(2, 1000, router, "TimeExceeded (11)", 0),
+            build_response(3, 1500, router, "TimeExceeded (11)", 1),
+            // Unanswered hop
+            build_probe(4, 2000, 2),
+            build_probe(5, 3000, 3),
+            build_probe(6, 3000, 4),
+            build_response(7, 8000, TARGET, "DestinationUnreachable (3)", 5),
+            build_response(8, 9000, TARGET, "DestinationUnreachable (3)", 6),
+            // Regular UDP traffic
+            build_probe(9, 9000, 64),
+        ];
+
+        let paths = get_traceroute_paths_internal(&packets);
+        assert_eq!(paths.len(), 1);
+
+        let path = &paths[0];
+        assert_eq!(path.destination, TARGET.to_string());
+        assert_eq!(path.protocol, ProbeProtocols::UDP);
+        assert!(path.reached);
+
+        let hops = path
+            .hops
+            .iter()
+            .map(|hop| (hop.ttl, hop.addresses.clone(), hop.rtts.clone(), hop.probes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hops,
+            vec![
+                (1, vec![router.to_string()], vec![1000, 1490], 2),
+                (2, vec![], vec![], 1),
+                (3, vec![TARGET.to_string()], vec![5000], 1),
+            ]
+        );
+    }
+}
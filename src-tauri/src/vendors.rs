@@ -0,0 +1,231 @@
+//! Names of the hardware vendors of the MAC addresses, from their Organizationally Unique Identifier
+//!
+//! A bundled table of common vendors (network equipment, virtual machines, IoT devices) can be extended
+//! or overridden by an OUI file, in the format of either the Wireshark `manuf` file
+//! (`00:00:0C<TAB>Cisco<TAB>Cisco Systems, Inc`) or the IEEE `oui.txt` registry
+//! (`00-00-0C   (hex)<TAB><TAB>Cisco Systems, Inc`). Only the 24 bits assignments (MA-L) are read.
+
+use std::collections::HashMap;
+use std::fs;
+
+use log::info;
+use sniffer_parser::serializable_packet::util::{get_dest_mac, get_source_mac};
+use sniffer_parser::serializable_packet::ParsedPacket;
+
+use crate::{SniffingError, SniffingState};
+
+type Oui = [u8; 3];
+
+/// Vendors of the most common OUIs
+const BUNDLED_VENDORS: [(Oui, &str); 40] = [
+    ([0x00, 0x00, 0x0c], "Cisco Systems"),
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x00, 0x04, 0x4b], "NVIDIA"),
+    ([0x00, 0x05, 0x69], "VMware"),
+    ([0x00, 0x09, 0x0f], "Fortinet"),
+    ([0x00, 0x0c, 0x29], "VMware"),
+    ([0x00, 0x0d, 0x3a], "Microsoft"),
+    ([0x00, 0x0e, 0x58], "Sonos"),
+    ([0x00, 0x10, 0x18], "Broadcom"),
+    ([0x00, 0x11, 0x32], "Synology"),
+    ([0x00, 0x14, 0x22], "Dell"),
+    ([0x00, 0x15, 0x5d], "Microsoft"),
+    ([0x00, 0x16, 0x3e], "Xensource"),
+    ([0x00, 0x17, 0x88], "Philips Lighting"),
+    ([0x00, 0x17, 0xf2], "Apple"),
+    ([0x00, 0x1a, 0x11], "Google"),
+    ([0x00, 0x1a, 0xa0], "Dell"),
+    ([0x00, 0x1b, 0x17], "Palo Alto Networks"),
+    ([0x00, 0x1b, 0x21], "Intel"),
+    ([0x00, 0x1b, 0x63], "Apple"),
+    ([0x00, 0x1c, 0x42], "Parallels"),
+    ([0x00, 0x1e, 0xc2], "Apple"),
+    ([0x00, 0x24, 0xd7], "Intel"),
+    ([0x00, 0x25, 0x00], "Apple"),
+    ([0x00, 0x26, 0xbb], "Apple"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x00, 0x50, 0xf2], "Microsoft"),
+    ([0x00, 0xe0, 0x4c], "Realtek"),
+    ([0x08, 0x00, 0x27], "PCS Systemtechnik (VirtualBox)"),
+    ([0x18, 0xb4, 0x30], "Nest Labs"),
+    ([0x18, 0xfe, 0x34], "Espressif"),
+    ([0x24, 0x0a, 0xc4], "Espressif"),
+    ([0x30, 0xae, 0xa4], "Espressif"),
+    ([0x3c, 0x5a, 0xb4], "Google"),
+    ([0x44, 0x65, 0x0d], "Amazon Technologies"),
+    ([0x52, 0x54, 0x00], "QEMU/KVM"),
+    ([0x5c, 0xcf, 0x7f], "Espressif"),
+    ([0xb8, 0x27, 0xeb], "Raspberry Pi Foundation"),
+    ([0xdc, 0xa6, 0x32], "Raspberry Pi Trading"),
+    ([0xe4, 0x5f, 0x01], "Raspberry Pi Trading"),
+];
+
+/// OUI to vendor mapping, bundled names first overridden by the ones of the loaded OUI file
+#[derive(Debug)]
+pub struct VendorNames {
+    names: HashMap<Oui, String>,
+}
+
+impl VendorNames {
+    pub fn new() -> Self {
+        VendorNames {
+            names: get_bundled_vendors(),
+        }
+    }
+
+    /// Replace the names loaded from a previous OUI file
+    pub fn set_file_names(&mut self, file_names: HashMap<Oui, String>) {
+        let mut names = get_bundled_vendors();
+        names.extend(file_names);
+        self.names = names;
+    }
+
+    /// Vendor of the MAC address (e.g. `00:50:56:c0:00:08`), if known
+    pub fn get(&self, mac: &str) -> Option<&str> {
+        self.names.get(&parse_oui(mac)?).map(String::as_str)
+    }
+
+    /// Vendors of the source and destination MAC addresses of the packet
+    pub fn get_packet_vendors(&self, packet: &ParsedPacket) -> (Option<&str>, Option<&str>) {
+        (
+            get_source_mac(packet).and_then(|mac| self.get(&mac)),
+            get_dest_mac(packet).and_then(|mac| self.get(&mac)),
+        )
+    }
+}
+
+fn get_bundled_vendors() -> HashMap<Oui, String> {
+    BUNDLED_VENDORS
+        .iter()
+        .map(|(oui, name)| (*oui, name.to_string()))
+        .collect()
+}
+
+/// OUI of a MAC address or prefix, with bytes separated by `:` or `-`, or not separated at all
+pub fn parse_oui(value: &str) -> Option<Oui> {
+    let digits = value
+        .chars()
+        .filter(|c| *c != ':' && *c != '-')
+        .take(6)
+        .collect::<String>();
+    if digits.len() != 6 {
+        return None;
+    }
+
+    let oui = u32::from_str_radix(&digits, 16).ok()?.to_be_bytes();
+    Some([oui[1], oui[2], oui[3]])
+}
+
+/// Vendors of an OUI file, skipping comments, assignments smaller than 24 bits and unknown lines
+fn parse_oui_file(content: &str) -> HashMap<Oui, String> {
+    let mut names = HashMap::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (prefix, rest) = match line.split_once(char::is_whitespace) {
+            Some(split) => split,
+            None => continue,
+        };
+        // e.g. `00:1B:C5:00:00:00/36`, and the `(base 16)` lines of oui.txt
+        let is_24_bits = prefix.len() == 8 || prefix.ends_with("/24");
+        let oui = match parse_oui(prefix) {
+            Some(oui) if is_24_bits => oui,
+            _ => continue,
+        };
+
+        let rest = rest.trim();
+        let name = match rest.strip_prefix("(hex)") {
+            Some(name) => Some(name.trim()),
+            // Short name, optionally followed by the full one
+            None => rest.split('\t').map(str::trim).rfind(|n| !n.is_empty()),
+        };
+
+        if let Some(name) = name.filter(|name| !name.is_empty()) {
+            names.insert(oui, name.to_owned());
+        }
+    }
+
+    names
+}
+
+/// Loads the vendors of an OUI file, overriding and extending the bundled ones, returning the number of
+/// loaded vendors
+///
+/// Each call replaces the vendors loaded previously
+#[tauri::command]
+pub fn load_oui_file(
+    state: tauri::State<SniffingState>,
+    path: String,
+) -> Result<usize, SniffingError> {
+    let content = fs::read_to_string(&path).map_err(|e| {
+        SniffingError::OuiFileAccessFailed(format!("Unable to read {}: {}", path, e))
+    })?;
+
+    let names = parse_oui_file(&content);
+    if names.is_empty() {
+        return Err(SniffingError::OuiFileAccessFailed(format!(
+            "No OUI assignment in {}",
+            path
+        )));
+    }
+
+    let loaded = names.len();
+    state.packets.lock().unwrap().vendors.set_file_names(names);
+
+    info!("Loaded {} vendors from {}", loaded, path);
+
+    Ok(loaded)
+}
+
+/// Tag each packet with the vendors of its MAC addresses
+pub fn tag_vendors(packets: &mut Vec<ParsedPacket>, vendors: &VendorNames) {
+    for packet in packets {
+        let (source_vendor, dest_vendor) = vendors.get_packet_vendors(packet);
+        packet.set_vendors(
+            source_vendor.map(str::to_owned),
+            dest_vendor.map(str::to_owned),
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{parse_oui, parse_oui_file, VendorNames};
+
+    #[test]
+    fn oui_file_vendors() {
+        let manuf = "# Wireshark manuf\n\
+            00:00:0C\tCisco\tCisco Systems, Inc\n\
+            00:1B:C5:00:00:00/36\tConverg\tConverging Systems Inc.\n\
+            AC:DE:48\tPrivate\n";
+        let oui_txt = "OUI/MA-L  Organization\n\
+            70-B3-D5   (hex)\t\tIEEE Registration Authority\n\
+            70B3D5     (base 16)\t\tIEEE Registration Authority\n";
+
+        let names = parse_oui_file(&format!("{}{}", manuf, oui_txt));
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[&[0x00, 0x00, 0x0c]], "Cisco Systems, Inc");
+        assert_eq!(names[&[0xac, 0xde, 0x48]], "Private");
+        assert_eq!(names[&[0x70, 0xb3, 0xd5]], "IEEE Registration Authority");
+
+        let mut vendors = VendorNames::new();
+        assert_eq!(vendors.get("00:50:56:c0:00:08"), Some("VMware"));
+        assert_eq!(vendors.get("00:00:0c:07:ac:01"), Some("Cisco Systems"));
+        assert_eq!(vendors.get("ac:de:48:00:11:22"), None);
+
+        vendors.set_file_names(names);
+        assert_eq!(vendors.get("00:00:0c:07:ac:01"), Some("Cisco Systems, Inc"));
+        assert_eq!(vendors.get("ac:de:48:00:11:22"), Some("Private"));
+        // Bundled ones are kept
+        assert_eq!(vendors.get("00:50:56:c0:00:08"), Some("VMware"));
+
+        assert_eq!(parse_oui("00-50-56"), Some([0x00, 0x50, 0x56]));
+        assert_eq!(parse_oui("005056"), Some([0x00, 0x50, 0x56]));
+        assert_eq!(parse_oui("00:50"), None);
+        assert_eq!(parse_oui("zz:50:56"), None);
+    }
+}
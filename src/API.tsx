@@ -1,12 +1,12 @@
 import { invoke } from "@tauri-apps/api";
 import { GeneralPacket } from "./types/sniffing";
 
-async function startSniffing(isResume: boolean) {
-  return invoke("start_sniffing", { isResume });
+async function startSniffing(isResume: boolean, append: boolean = false) {
+  return invoke("start_sniffing", { isResume, append });
 }
 
-async function stopSniffing(stop: boolean) {
-  return invoke("stop_sniffing", { stop });
+async function stopSniffing(stop: boolean, drain: boolean = false) {
+  return invoke("stop_sniffing", { stop, drain });
 }
 
 async function selectInterface(interfaceName: string) {
@@ -19,18 +19,26 @@ async function getInterfacesList(): Promise<string[]> {
 
 async function generateReport(
   reportPath: string,
-  firstGeneration: boolean
+  firstGeneration: boolean,
+  anonymize: boolean = false
 ): Promise<boolean> {
-  return invoke("generate_report", { reportPath, firstGeneration });
+  return invoke("generate_report", { reportPath, firstGeneration, anonymize });
 }
 
 async function getPackets(
   start: number,
   end: number,
   filtersType: any[],
-  filtersValue: any[]
+  filtersValue: any[],
+  referenceIp: string | null = null
 ): Promise<GeneralPacket[]> {
-  return invoke("get_packets", { start, end, filtersType, filtersValue });
+  return invoke("get_packets", {
+    start,
+    end,
+    filtersType,
+    filtersValue,
+    referenceIp,
+  });
 }
 
 const API = {